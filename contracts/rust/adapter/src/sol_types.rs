@@ -278,6 +278,12 @@ impl From<PlonkProofSol> for light_client_v3::IPlonkVerifier::PlonkProof {
     }
 }
 
+impl From<light_client_v3::IPlonkVerifier::VerifyingKey> for VerifyingKeySol {
+    fn from(v: light_client_v3::IPlonkVerifier::VerifyingKey) -> Self {
+        unsafe { std::mem::transmute(v) }
+    }
+}
+
 // Transmute conversion functions for LightClientV3Mock
 impl From<light_client_v3_mock::LightClient::LightClientState> for LightClientStateSol {
     fn from(v: light_client_v3_mock::LightClient::LightClientState) -> Self {
@@ -2,6 +2,7 @@ pub mod deployment_info;
 pub mod multisig;
 pub mod proposal_toml;
 pub mod safe_hash;
+pub mod simulate;
 pub mod timelock;
 pub mod verify;
 pub mod write;
@@ -0,0 +1,890 @@
+//! Dry-run of a timelock upgrade proposal against a forked chain.
+//!
+//! Forks the target network with Anvil, impersonates the schedule and execute
+//! Safes, and pushes both timelock calls through exactly as the Safes would
+//! (schedule, wait out the delay, execute). Reports the events emitted, the
+//! storage slots touched and the post-upgrade invariants (implementation,
+//! owner, version, roles), so a proposal can be exercised before anyone signs.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+};
+
+use alloy::{
+    node_bindings::Anvil,
+    primitives::{Address, B256, Bytes, Log, U256, keccak256, utils::parse_ether},
+    providers::{Provider, ProviderBuilder, ext::AnvilApi},
+    rpc::types::{TransactionReceipt, TransactionRequest},
+    sol_types::SolCall,
+};
+use anyhow::{Context, Result, anyhow, bail};
+use hotshot_contract_adapter::sol_types::{OpsTimelock, StakeTableV3};
+use serde_json::json;
+use url::Url;
+
+use crate::{
+    impersonate_filler::ImpersonateFiller,
+    proposals::{
+        proposal_toml::ProposalToml,
+        verify::{
+            CheckRow, ContractKind, ContractKindArg, DecodedUpgrade, contract_kind,
+            decode_proposal, fail, fetch_proxy_major_version, fetch_proxy_owner, load_proposal_dir,
+            owner_timelock_row, pass, resolve_contract_kind,
+        },
+        write::default_rpc_url,
+    },
+};
+
+/// EIP-1967 implementation slot: `keccak256("eip1967.proxy.implementation") - 1`.
+pub const IMPLEMENTATION_SLOT: B256 =
+    alloy::primitives::b256!("360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc");
+
+// ── CLI args ─────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, clap::Args)]
+pub struct SimulateProposalArgs {
+    /// Proposal directory containing schedule.json, execute.json, and proposal.toml.
+    pub dir: PathBuf,
+
+    /// Override the contract kind; defaults to proposal.toml `contract` field.
+    #[clap(long)]
+    pub contract: Option<ContractKindArg>,
+
+    /// RPC URL of the chain to fork; defaults to the network's public node from proposal.toml.
+    ///
+    /// Note: this flag must follow the `simulate-proposal` subcommand; the top-level
+    /// `deploy --rpc-url` does not apply here.
+    #[clap(long, env = "ESPRESSO_L1_PROVIDER")]
+    pub rpc_url: Option<Url>,
+
+    /// Block to fork from; defaults to the latest block.
+    #[clap(long)]
+    pub fork_block: Option<u64>,
+}
+
+// ── Report ───────────────────────────────────────────────────────────────────
+
+/// A storage slot changed by one of the simulated transactions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageChange {
+    pub address: Address,
+    pub slot: B256,
+    pub before: B256,
+    pub after: B256,
+}
+
+/// An event emitted by one of the simulated transactions.
+#[derive(Debug, Clone)]
+pub struct EventRow {
+    /// Phase that emitted the event ("schedule" or "execute").
+    pub phase: &'static str,
+    pub address: Address,
+    /// Event signature, if it is one we know about.
+    pub name: Option<&'static str>,
+    pub topics: Vec<B256>,
+    pub data: Bytes,
+}
+
+/// Proxy state observed before and after the upgrade.
+#[derive(Debug, Clone, Default)]
+pub struct ProxySnapshot {
+    pub implementation: Address,
+    pub owner: Option<Address>,
+    pub major_version: Option<u8>,
+    /// Whether the timelock holds `DEFAULT_ADMIN_ROLE` on the proxy.
+    /// `None` if the proxy does not implement `AccessControl`.
+    pub timelock_is_admin: Option<bool>,
+}
+
+#[derive(Debug)]
+pub struct SimulationReport {
+    pub contract_name: &'static str,
+    pub network: String,
+    pub proxy: Address,
+    pub new_impl: Address,
+    pub fork_block: u64,
+    pub before: ProxySnapshot,
+    pub after: ProxySnapshot,
+    pub rows: Vec<CheckRow>,
+    pub events: Vec<EventRow>,
+    pub storage: Vec<StorageChange>,
+}
+
+impl SimulationReport {
+    pub fn print(&self) {
+        println!("=== Upgrade Proposal Simulation ===");
+        println!("  contract:    {}", self.contract_name);
+        println!("  network:     {}", self.network);
+        println!("  fork block:  {}", self.fork_block);
+        println!("  proxy:       {}", self.proxy);
+        println!("  new_impl:    {}", self.new_impl);
+        println!();
+        println!("{:<40} {:<6} DETAIL", "CHECK", "RESULT");
+        println!("{}", "-".repeat(80));
+        for row in &self.rows {
+            let status = if row.pass { "PASS" } else { "FAIL" };
+            println!("{:<40} {:<6} {}", row.name, status, row.detail);
+        }
+        println!();
+        println!("--- Events ---");
+        for ev in &self.events {
+            let name = match ev.name {
+                Some(name) => name.to_owned(),
+                None => ev
+                    .topics
+                    .first()
+                    .map(|t| format!("unknown topic0={t}"))
+                    .unwrap_or_else(|| "anonymous".to_owned()),
+            };
+            println!("  [{}] {} {}", ev.phase, ev.address, name);
+            for topic in ev.topics.iter().skip(1) {
+                println!("      topic: {topic}");
+            }
+            if !ev.data.is_empty() {
+                println!("      data:  {}", ev.data);
+            }
+        }
+        println!();
+        println!("--- Storage diff ---");
+        for change in &self.storage {
+            println!("  {} slot {}", change.address, change.slot);
+            println!("      {} -> {}", change.before, change.after);
+        }
+        println!();
+        let all_pass = self.rows.iter().all(|r| r.pass);
+        println!("Result: {}", if all_pass { "ALL PASS" } else { "FAIL" });
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        if self.rows.iter().all(|r| r.pass) {
+            0
+        } else {
+            1
+        }
+    }
+}
+
+// ── Pure helpers ─────────────────────────────────────────────────────────────
+
+/// Events we expect to see from a timelock upgrade, keyed by topic0.
+fn known_events() -> BTreeMap<B256, &'static str> {
+    [
+        "CallScheduled(bytes32,uint256,address,uint256,bytes,bytes32,uint256)",
+        "CallSalt(bytes32,bytes32)",
+        "CallExecuted(bytes32,uint256,address,uint256,bytes)",
+        "Upgraded(address)",
+        "Initialized(uint64)",
+        "OwnershipTransferred(address,address)",
+        "RoleGranted(bytes32,address,address)",
+        "RoleRevoked(bytes32,address,address)",
+    ]
+    .into_iter()
+    .map(|sig| (keccak256(sig), sig))
+    .collect()
+}
+
+fn event_rows(phase: &'static str, logs: &[Log]) -> Vec<EventRow> {
+    let known = known_events();
+    logs.iter()
+        .map(|log| EventRow {
+            phase,
+            address: log.address,
+            name: log.topics().first().and_then(|t| known.get(t).copied()),
+            topics: log.topics().to_vec(),
+            data: log.data.data.clone(),
+        })
+        .collect()
+}
+
+/// Parse the output of `debug_traceTransaction` with `prestateTracer` in diff mode.
+///
+/// In diff mode unchanged slots are omitted from both sides; a slot missing
+/// from `pre` was zero before, one missing from `post` was cleared.
+pub fn parse_prestate_diff(trace: &serde_json::Value) -> Result<Vec<StorageChange>> {
+    fn storage(side: &serde_json::Value) -> Result<BTreeMap<(Address, B256), B256>> {
+        let mut out = BTreeMap::new();
+        let Some(accounts) = side.as_object() else {
+            return Ok(out);
+        };
+        for (addr, account) in accounts {
+            let address: Address = addr
+                .parse()
+                .with_context(|| format!("bad address {addr}"))?;
+            let Some(slots) = account.get("storage").and_then(|s| s.as_object()) else {
+                continue;
+            };
+            for (slot, value) in slots {
+                let slot: B256 = slot.parse().with_context(|| format!("bad slot {slot}"))?;
+                let value: B256 = value
+                    .as_str()
+                    .ok_or_else(|| anyhow!("storage value for {slot} is not a string"))?
+                    .parse()
+                    .with_context(|| format!("bad storage value for {slot}"))?;
+                out.insert((address, slot), value);
+            }
+        }
+        Ok(out)
+    }
+
+    let pre = storage(&trace["pre"])?;
+    let post = storage(&trace["post"])?;
+    let keys: BTreeSet<_> = pre.keys().chain(post.keys()).copied().collect();
+    Ok(keys
+        .into_iter()
+        .filter_map(|key| {
+            let before = pre.get(&key).copied().unwrap_or_default();
+            let after = post.get(&key).copied().unwrap_or_default();
+            (before != after).then_some(StorageChange {
+                address: key.0,
+                slot: key.1,
+                before,
+                after,
+            })
+        })
+        .collect())
+}
+
+/// Compare the proxy state after the upgrade against what the proposal promises.
+pub fn invariant_rows(
+    upgrade: &DecodedUpgrade,
+    expected_major: Option<u8>,
+    before: &ProxySnapshot,
+    after: &ProxySnapshot,
+) -> Vec<CheckRow> {
+    let mut rows = Vec::new();
+
+    rows.push(if after.implementation == upgrade.new_impl {
+        pass(
+            "post.implementation",
+            format!("{} -> {}", before.implementation, after.implementation),
+        )
+    } else {
+        fail(
+            "post.implementation",
+            format!(
+                "proxy points at {}, expected {}",
+                after.implementation, upgrade.new_impl
+            ),
+        )
+    });
+
+    match after.owner {
+        Some(owner) => {
+            let mut row = owner_timelock_row(owner, upgrade.outer_to);
+            row.name = "post.owner".to_owned();
+            rows.push(row);
+        },
+        None => rows.push(fail("post.owner", "owner query failed after upgrade")),
+    }
+    if let (Some(b), Some(a)) = (before.owner, after.owner)
+        && b != a
+    {
+        rows.push(fail(
+            "post.owner-unchanged",
+            format!("owner changed {b} -> {a}"),
+        ));
+    }
+
+    rows.push(
+        match (before.major_version, after.major_version, expected_major) {
+            (_, Some(a), Some(expected)) if a == expected => {
+                pass("post.version", format!("major version {a}"))
+            },
+            (_, Some(a), Some(expected)) => fail(
+                "post.version",
+                format!("major version {a}, expected {expected}"),
+            ),
+            (Some(b), Some(a), None) if a >= b => {
+                pass("post.version", format!("major version {b} -> {a}"))
+            },
+            (Some(b), Some(a), None) => fail(
+                "post.version",
+                format!("major version went backwards {b} -> {a}"),
+            ),
+            (_, None, _) => fail("post.version", "getVersion() failed after upgrade"),
+            (None, Some(a), None) => pass("post.version", format!("major version {a}")),
+        },
+    );
+
+    match (before.timelock_is_admin, after.timelock_is_admin) {
+        (Some(true), Some(false)) | (Some(true), None) => rows.push(fail(
+            "post.admin-role",
+            "timelock lost DEFAULT_ADMIN_ROLE on the proxy",
+        )),
+        (_, Some(true)) => rows.push(pass("post.admin-role", "timelock holds DEFAULT_ADMIN_ROLE")),
+        // Not an AccessControl contract, or the timelock never was admin.
+        _ => {},
+    }
+
+    rows
+}
+
+// ── Orchestrator ─────────────────────────────────────────────────────────────
+
+/// Fork the network named in `<args.dir>/proposal.toml` and simulate the proposal on it.
+pub async fn run_simulate_standalone(args: &SimulateProposalArgs) -> Result<SimulationReport> {
+    let toml = ProposalToml::load(&args.dir)?;
+    let chain_id = toml.chain_id;
+
+    let rpc = args
+        .rpc_url
+        .clone()
+        .or_else(|| default_rpc_url(chain_id))
+        .ok_or_else(|| anyhow!("unknown chain id {chain_id}; pass --rpc-url"))?;
+
+    let mut anvil = Anvil::new()
+        .fork(rpc.to_string())
+        .arg("--retries")
+        .arg("20");
+    if let Some(block) = args.fork_block {
+        anvil = anvil.fork_block_number(block);
+    }
+    let anvil = anvil
+        .try_spawn()
+        .context("failed to start anvil; is foundry installed?")?;
+
+    run_simulate(args, anvil.endpoint_url()).await
+}
+
+/// Simulate the proposal against the Anvil node at `anvil_url`.
+///
+/// The node must support the `anvil_*` RPC methods. Its state is modified.
+pub async fn run_simulate(args: &SimulateProposalArgs, anvil_url: Url) -> Result<SimulationReport> {
+    let toml = ProposalToml::load(&args.dir)?;
+    let kind = contract_kind(resolve_contract_kind(args.contract, &toml, &args.dir)?);
+    let upgrade = decode_proposal(load_proposal_dir(&args.dir)?)?;
+
+    let provider = ProviderBuilder::new().connect_http(anvil_url.clone());
+    let fork_block = provider.get_block_number().await?;
+    let chain_id = provider.get_chain_id().await?;
+    if chain_id != toml.chain_id {
+        bail!(
+            "forked chain id {chain_id} does not match proposal.toml chain_id {}",
+            toml.chain_id
+        );
+    }
+
+    let mut rows = Vec::new();
+    let mut events = Vec::new();
+    let mut storage = Vec::new();
+
+    let before = snapshot(&provider, &upgrade, &kind).await;
+
+    // Impersonate both Safes and give them gas money.
+    for safe in [toml.schedule.safe, toml.execute.safe] {
+        provider.anvil_impersonate_account(safe).await?;
+        provider
+            .anvil_set_balance(safe, parse_ether("100")?)
+            .await?;
+    }
+
+    // Skip scheduling if the operation is already queued on chain, e.g. when the
+    // schedule phase has been signed and executed but the execute phase has not.
+    let scheduled = OpsTimelock::scheduleCall::abi_decode(&upgrade.outer_calldatas.schedule)
+        .context("failed to decode schedule calldata")?;
+    let timelock = OpsTimelock::new(upgrade.outer_to, &provider);
+    let op_id = timelock
+        .hashOperation(
+            scheduled.target,
+            scheduled.value,
+            scheduled.data.clone(),
+            scheduled.predecessor,
+            scheduled.salt,
+        )
+        .call()
+        .await?;
+    if timelock.isOperation(op_id).call().await? {
+        rows.push(pass(
+            "schedule.tx",
+            format!("operation {op_id} already scheduled on chain; skipped"),
+        ));
+    } else {
+        let receipt = send_as(
+            &anvil_url,
+            toml.schedule.safe,
+            upgrade.outer_to,
+            upgrade.outer_calldatas.schedule.clone(),
+        )
+        .await;
+        record_tx(
+            &provider,
+            "schedule",
+            receipt,
+            &mut rows,
+            &mut events,
+            &mut storage,
+        )
+        .await;
+    }
+
+    // Wait out the delay.
+    if timelock.isOperation(op_id).call().await? {
+        let ready_at = timelock.getTimestamp(op_id).call().await?;
+        let now = provider
+            .get_block_by_number(Default::default())
+            .await?
+            .ok_or_else(|| anyhow!("latest block not found"))?
+            .header
+            .timestamp;
+        let wait = ready_at.saturating_sub(U256::from(now));
+        if wait > U256::ZERO {
+            provider.anvil_increase_time(wait.to::<u64>()).await?;
+            provider.anvil_mine(Some(1), None).await?;
+        }
+        rows.push(pass(
+            "delay",
+            format!("advanced {wait}s to operation ready time"),
+        ));
+    } else {
+        rows.push(fail(
+            "delay",
+            format!("operation {op_id} was never scheduled"),
+        ));
+    }
+
+    let receipt = send_as(
+        &anvil_url,
+        toml.execute.safe,
+        upgrade.outer_to,
+        upgrade.outer_calldatas.execute.clone(),
+    )
+    .await;
+    record_tx(
+        &provider,
+        "execute",
+        receipt,
+        &mut rows,
+        &mut events,
+        &mut storage,
+    )
+    .await;
+
+    let after = snapshot(&provider, &upgrade, &kind).await;
+    rows.extend(invariant_rows(
+        &upgrade,
+        kind.expected_init.map(|init| init.target_major),
+        &before,
+        &after,
+    ));
+
+    let upgraded = keccak256("Upgraded(address)");
+    let emitted = events.iter().any(|ev| {
+        ev.address == upgrade.proxy
+            && ev.topics.first() == Some(&upgraded)
+            && ev.topics.get(1) == Some(&upgrade.new_impl.into_word())
+    });
+    rows.push(if emitted {
+        pass("post.upgraded-event", "proxy emitted Upgraded(new_impl)")
+    } else {
+        fail(
+            "post.upgraded-event",
+            "no Upgraded(new_impl) event from proxy",
+        )
+    });
+
+    Ok(SimulationReport {
+        contract_name: kind.name,
+        network: toml.network,
+        proxy: upgrade.proxy,
+        new_impl: upgrade.new_impl,
+        fork_block,
+        before,
+        after,
+        rows,
+        events,
+        storage,
+    })
+}
+
+async fn snapshot(
+    provider: &impl Provider,
+    upgrade: &DecodedUpgrade,
+    kind: &ContractKind,
+) -> ProxySnapshot {
+    let implementation = provider
+        .get_storage_at(upgrade.proxy, U256::from_be_bytes(IMPLEMENTATION_SLOT.0))
+        .await
+        .map(|word| Address::from_word(B256::from(word)))
+        .unwrap_or_default();
+    let owner = fetch_proxy_owner(provider, upgrade.proxy, kind.owner_accessor)
+        .await
+        .ok();
+    let major_version = fetch_proxy_major_version(provider, upgrade.proxy)
+        .await
+        .ok();
+    let timelock_is_admin = StakeTableV3::new(upgrade.proxy, provider)
+        .hasRole(B256::ZERO, upgrade.outer_to)
+        .call()
+        .await
+        .ok();
+    ProxySnapshot {
+        implementation,
+        owner,
+        major_version,
+        timelock_is_admin,
+    }
+}
+
+/// Send `calldata` to `to` from the impersonated account `from`.
+async fn send_as(
+    anvil_url: &Url,
+    from: Address,
+    to: Address,
+    calldata: Bytes,
+) -> Result<TransactionReceipt> {
+    let provider = ProviderBuilder::new()
+        .filler(ImpersonateFiller::new(from))
+        .connect_http(anvil_url.clone());
+    let tx = TransactionRequest::default().to(to).input(calldata.into());
+    Ok(provider.send_transaction(tx).await?.get_receipt().await?)
+}
+
+async fn record_tx(
+    provider: &impl Provider,
+    phase: &'static str,
+    receipt: Result<TransactionReceipt>,
+    rows: &mut Vec<CheckRow>,
+    events: &mut Vec<EventRow>,
+    storage: &mut Vec<StorageChange>,
+) {
+    let name = format!("{phase}.tx");
+    let receipt = match receipt {
+        Ok(receipt) => receipt,
+        Err(e) => {
+            rows.push(fail(name, format!("transaction failed: {e}")));
+            return;
+        },
+    };
+    if !receipt.status() {
+        rows.push(fail(
+            name,
+            format!("reverted in {}", receipt.transaction_hash),
+        ));
+        return;
+    }
+    rows.push(pass(
+        name,
+        format!(
+            "{} (gas used {})",
+            receipt.transaction_hash, receipt.gas_used
+        ),
+    ));
+    let logs: Vec<Log> = receipt
+        .inner
+        .logs()
+        .iter()
+        .map(|l| l.inner.clone())
+        .collect();
+    events.extend(event_rows(phase, &logs));
+
+    let trace = provider
+        .raw_request::<_, serde_json::Value>(
+            "debug_traceTransaction".into(),
+            (
+                receipt.transaction_hash,
+                json!({ "tracer": "prestateTracer", "tracerConfig": { "diffMode": true } }),
+            ),
+        )
+        .await;
+    match trace
+        .map_err(anyhow::Error::from)
+        .and_then(|t| parse_prestate_diff(&t))
+    {
+        Ok(changes) => storage.extend(changes),
+        Err(e) => tracing::warn!(phase, "storage diff unavailable: {e:#}"),
+    }
+}
+
+// ── Tests ────────────────────────────────────────────────────────────────────
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{LogData, address, b256};
+    use hotshot_contract_adapter::sol_types::{LightClientStateSol, StakeTableStateSol};
+
+    use super::*;
+    use crate::{
+        Contract, Contracts, DEFAULT_EXIT_ESCROW_PERIOD_SECONDS,
+        builder::DeployerArgsBuilder,
+        deploy_light_client_proxy, deploy_ops_timelock, deploy_stake_table_proxy,
+        deploy_token_proxy,
+        output::output_safe_tx_builder,
+        proposals::{
+            proposal_toml::PhaseToml,
+            timelock::{
+                StakeTableV3TimelockProposalParams, upgrade_stake_table_v3_timelock_proposal,
+            },
+            verify::OuterCalldatas,
+        },
+    };
+
+    fn upgrade() -> DecodedUpgrade {
+        DecodedUpgrade {
+            outer_to: address!("0x00000000000000000000000000000000000000aa"),
+            proxy: address!("0x00000000000000000000000000000000000000bb"),
+            new_impl: address!("0x00000000000000000000000000000000000000cc"),
+            init_data: Bytes::new(),
+            value: U256::ZERO,
+            predecessor: B256::ZERO,
+            salt: B256::ZERO,
+            delay: U256::from(60),
+            description: String::new(),
+            outer_calldatas: OuterCalldatas {
+                schedule: Bytes::new(),
+                execute: Bytes::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_parse_prestate_diff() {
+        let trace = json!({
+            "pre": {
+                "0x00000000000000000000000000000000000000bb": {
+                    "balance": "0x0",
+                    "storage": {
+                        "0x360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc":
+                            "0x00000000000000000000000000000000000000000000000000000000000000dd",
+                        "0x0000000000000000000000000000000000000000000000000000000000000001":
+                            "0x0000000000000000000000000000000000000000000000000000000000000007"
+                    }
+                }
+            },
+            "post": {
+                "0x00000000000000000000000000000000000000bb": {
+                    "storage": {
+                        "0x360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc":
+                            "0x00000000000000000000000000000000000000000000000000000000000000cc",
+                        "0x0000000000000000000000000000000000000000000000000000000000000002":
+                            "0x0000000000000000000000000000000000000000000000000000000000000001"
+                    }
+                }
+            }
+        });
+        let changes = parse_prestate_diff(&trace).unwrap();
+        assert_eq!(changes.len(), 3);
+        let proxy = address!("0x00000000000000000000000000000000000000bb");
+        assert!(changes.contains(&StorageChange {
+            address: proxy,
+            slot: IMPLEMENTATION_SLOT,
+            before: b256!("0x00000000000000000000000000000000000000000000000000000000000000dd"),
+            after: b256!("0x00000000000000000000000000000000000000000000000000000000000000cc"),
+        }));
+        // Cleared slot.
+        assert!(changes.contains(&StorageChange {
+            address: proxy,
+            slot: B256::with_last_byte(1),
+            before: B256::with_last_byte(7),
+            after: B256::ZERO,
+        }));
+        // Newly written slot.
+        assert!(changes.contains(&StorageChange {
+            address: proxy,
+            slot: B256::with_last_byte(2),
+            before: B256::ZERO,
+            after: B256::with_last_byte(1),
+        }));
+
+        assert!(parse_prestate_diff(&json!({})).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_event_rows_names_known_events() {
+        let upgrade = upgrade();
+        let log = Log {
+            address: upgrade.proxy,
+            data: LogData::new_unchecked(
+                vec![keccak256("Upgraded(address)"), upgrade.new_impl.into_word()],
+                Bytes::new(),
+            ),
+        };
+        let unknown = Log {
+            address: upgrade.proxy,
+            data: LogData::new_unchecked(vec![B256::with_last_byte(1)], Bytes::new()),
+        };
+        let rows = event_rows("execute", &[log, unknown]);
+        assert_eq!(rows[0].name, Some("Upgraded(address)"));
+        assert_eq!(rows[1].name, None);
+    }
+
+    #[test]
+    fn test_invariant_rows() {
+        let upgrade = upgrade();
+        let before = ProxySnapshot {
+            implementation: address!("0x00000000000000000000000000000000000000dd"),
+            owner: Some(upgrade.outer_to),
+            major_version: Some(2),
+            timelock_is_admin: Some(true),
+        };
+        let good = ProxySnapshot {
+            implementation: upgrade.new_impl,
+            major_version: Some(3),
+            ..before.clone()
+        };
+        let rows = invariant_rows(&upgrade, Some(3), &before, &good);
+        assert!(rows.iter().all(|r| r.pass), "{rows:?}");
+
+        // Wrong implementation, lost ownership and admin role, wrong version.
+        let bad = ProxySnapshot {
+            implementation: before.implementation,
+            owner: Some(Address::ZERO),
+            major_version: Some(2),
+            timelock_is_admin: Some(false),
+        };
+        let rows = invariant_rows(&upgrade, Some(3), &before, &bad);
+        let failed: Vec<_> = rows
+            .iter()
+            .filter(|r| !r.pass)
+            .map(|r| r.name.as_str())
+            .collect();
+        assert_eq!(
+            failed,
+            [
+                "post.implementation",
+                "post.owner",
+                "post.owner-unchanged",
+                "post.version",
+                "post.admin-role"
+            ]
+        );
+    }
+
+    fn phase(safe: Address) -> PhaseToml {
+        PhaseToml {
+            safe,
+            nonce: 0,
+            domain: B256::ZERO,
+            message: B256::ZERO,
+            safe_tx: B256::ZERO,
+        }
+    }
+
+    /// Simulate a StakeTable V3 proposal against a local deployment whose proxy is owned by the
+    /// OpsTimelock.
+    #[test_log::test(tokio::test)]
+    async fn test_run_simulate_stake_table_v3() -> Result<()> {
+        let anvil = Anvil::new().spawn();
+        let provider = ProviderBuilder::new()
+            .wallet(anvil.wallet().unwrap())
+            .connect_http(anvil.endpoint_url());
+        let accounts = provider.get_accounts().await?;
+        let (safe, outsider) = (accounts[0], accounts[1]);
+        let mut contracts = Contracts::new();
+
+        // Deploy a StakeTable V2 proxy owned by an OpsTimelock whose proposer and executor is
+        // `safe`.
+        let delay = U256::from(60);
+        deploy_ops_timelock(
+            &provider,
+            &mut contracts,
+            delay,
+            vec![safe],
+            vec![safe],
+            safe,
+        )
+        .await?;
+        let token = deploy_token_proxy(
+            &provider,
+            &mut contracts,
+            safe,
+            safe,
+            U256::from(10_000_000u64),
+            "Espresso",
+            "ESP",
+        )
+        .await?;
+        let light_client = deploy_light_client_proxy(
+            &provider,
+            &mut contracts,
+            false,
+            LightClientStateSol::dummy_genesis(),
+            StakeTableStateSol::dummy_genesis(),
+            safe,
+            Some(safe),
+        )
+        .await?;
+        deploy_stake_table_proxy(
+            &provider,
+            &mut contracts,
+            token,
+            light_client,
+            U256::from(DEFAULT_EXIT_ESCROW_PERIOD_SECONDS),
+            safe,
+        )
+        .await?;
+        let mut args_builder = DeployerArgsBuilder::default();
+        args_builder
+            .deployer(provider.clone())
+            .use_timelock_owner(true)
+            .rpc_url(anvil.endpoint_url());
+        args_builder
+            .build()?
+            .deploy(&mut contracts, Contract::StakeTableV2)
+            .await?;
+
+        let proposal = upgrade_stake_table_v3_timelock_proposal(
+            &provider,
+            &mut contracts,
+            StakeTableV3TimelockProposalParams {
+                salt: B256::repeat_byte(0x42),
+                delay,
+            },
+        )
+        .await?;
+
+        let chain_id = provider.get_chain_id().await?;
+        let tmp = tempfile::tempdir()?;
+        let dir = tmp.path();
+        output_safe_tx_builder(
+            &proposal.schedule,
+            Some(&dir.join("schedule.json")),
+            chain_id,
+        )?;
+        output_safe_tx_builder(&proposal.execute, Some(&dir.join("execute.json")), chain_id)?;
+        let mut toml = ProposalToml {
+            contract: "stake-table-v3".to_owned(),
+            network: "local".to_owned(),
+            chain_id,
+            proxy: proposal.proxy_addr,
+            new_impl: proposal.v3_impl_addr,
+            timelock: proposal.timelock_addr,
+            salt: B256::repeat_byte(0x42),
+            delay: 60,
+            predecessor: B256::ZERO,
+            // Not a proposer: scheduling reverts.
+            schedule: phase(outsider),
+            execute: phase(safe),
+        };
+        toml.write(dir)?;
+        let args = SimulateProposalArgs {
+            dir: dir.to_path_buf(),
+            contract: None,
+            rpc_url: None,
+            fork_block: None,
+        };
+
+        let report = run_simulate(&args, anvil.endpoint_url()).await?;
+        let failed: Vec<_> = report
+            .rows
+            .iter()
+            .filter(|r| !r.pass)
+            .map(|r| r.name.as_str())
+            .collect();
+        assert!(failed.contains(&"schedule.tx"), "{:?}", report.rows);
+        assert!(failed.contains(&"delay"), "{:?}", report.rows);
+        assert!(failed.contains(&"execute.tx"), "{:?}", report.rows);
+        assert_eq!(report.exit_code(), 1);
+
+        // Scheduled by the proposer, the upgrade goes through.
+        toml.schedule = phase(safe);
+        toml.write(dir)?;
+        let report = run_simulate(&args, anvil.endpoint_url()).await?;
+        assert!(report.rows.iter().all(|r| r.pass), "{:?}", report.rows);
+        assert_eq!(report.after.implementation, proposal.v3_impl_addr);
+        assert_eq!(report.after.major_version, Some(3));
+        assert_eq!(report.exit_code(), 0);
+
+        Ok(())
+    }
+}
@@ -730,7 +730,7 @@ fn print_hashes(h: &SafeTxHashes) {
     println!("    safe_tx:  {}", h.safe_tx);
 }
 
-fn pass(name: impl Into<String>, detail: impl Into<String>) -> CheckRow {
+pub(crate) fn pass(name: impl Into<String>, detail: impl Into<String>) -> CheckRow {
     CheckRow {
         name: name.into(),
         pass: true,
@@ -738,7 +738,7 @@ fn pass(name: impl Into<String>, detail: impl Into<String>) -> CheckRow {
     }
 }
 
-fn fail(name: impl Into<String>, detail: impl Into<String>) -> CheckRow {
+pub(crate) fn fail(name: impl Into<String>, detail: impl Into<String>) -> CheckRow {
     CheckRow {
         name: name.into(),
         pass: false,
@@ -877,28 +877,7 @@ pub async fn run_verify(
     chain_id: u64,
 ) -> Result<VerifyReport> {
     let toml = ProposalToml::load(&args.dir)?;
-
-    // Resolve contract kind: flag overrides toml; if both present, assert equal.
-    let kind_arg = match args.contract {
-        Some(flag_kind) => {
-            if flag_kind.as_str() != toml.contract {
-                bail!(
-                    "--contract {:?} conflicts with proposal.toml contract {:?} in {}",
-                    flag_kind.as_str(),
-                    toml.contract,
-                    args.dir.display()
-                );
-            }
-            flag_kind
-        },
-        None => ContractKindArg::from_str(&toml.contract, true).map_err(|e| {
-            anyhow!(
-                "proposal.toml has unknown contract {:?}: {e}",
-                toml.contract
-            )
-        })?,
-    };
-    let kind = contract_kind(kind_arg);
+    let kind = contract_kind(resolve_contract_kind(args.contract, &toml, &args.dir)?);
     let mut rows: Vec<CheckRow> = vec![];
 
     // Network/chain_id consistency: static mapping, never circular (item F).
@@ -1050,6 +1029,33 @@ pub async fn run_verify(
     })
 }
 
+/// Resolve the contract kind: flag overrides toml; if both present, assert equal.
+pub(crate) fn resolve_contract_kind(
+    flag: Option<ContractKindArg>,
+    toml: &ProposalToml,
+    dir: &Path,
+) -> Result<ContractKindArg> {
+    match flag {
+        Some(flag_kind) => {
+            if flag_kind.as_str() != toml.contract {
+                bail!(
+                    "--contract {:?} conflicts with proposal.toml contract {:?} in {}",
+                    flag_kind.as_str(),
+                    toml.contract,
+                    dir.display()
+                );
+            }
+            Ok(flag_kind)
+        },
+        None => ContractKindArg::from_str(&toml.contract, true).map_err(|e| {
+            anyhow!(
+                "proposal.toml has unknown contract {:?}: {e}",
+                toml.contract
+            )
+        }),
+    }
+}
+
 /// Build PhaseHashes directly from toml (used when decoding fails).
 fn build_phase_hashes_from_toml(toml: &ProposalToml) -> PhaseHashes {
     PhaseHashes {
@@ -1521,7 +1527,7 @@ async fn governance_checks(
     rows
 }
 
-async fn fetch_proxy_owner(
+pub(crate) async fn fetch_proxy_owner(
     provider: &impl Provider,
     proxy: Address,
     accessor: OwnerAccessor,
@@ -1542,7 +1548,10 @@ async fn fetch_min_delay(provider: &impl Provider, timelock: Address) -> Result<
         .await?)
 }
 
-async fn fetch_proxy_major_version(provider: &impl Provider, proxy: Address) -> Result<u8> {
+pub(crate) async fn fetch_proxy_major_version(
+    provider: &impl Provider,
+    proxy: Address,
+) -> Result<u8> {
     Ok(StakeTableV3::new(proxy, provider)
         .getVersion()
         .call()
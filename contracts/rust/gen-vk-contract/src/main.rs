@@ -1,6 +1,8 @@
 //! This executable generates the solidity files with hardcoded verifying keys for
 //! LightClient updates by running `cargo run -p gen-vk-contract --release`.
 //! LightClientMock updates by running `cargo run -p gen-vk-contract --release -- --mock`.
+//! Pass `--vk-json <PATH>` to also write the verifying key as JSON, e.g. for auditing proofs with
+//! `state-prover --dry-run --verifying-key <PATH>`.
 //! Adapted from [CAPE project][https://github.com/EspressoSystems/cape/blob/main/contracts/rust/src/bin/gen-vk-libraries.rs]
 
 use std::{fs::OpenOptions, io::Write, path::PathBuf, process::Command};
@@ -16,10 +18,15 @@ struct Cli {
     /// indicate if it's for the mock verification key
     #[arg(long, default_value_t = false)]
     mock: bool,
+
+    /// also write the verifying key as JSON to this path
+    #[arg(long)]
+    vk_json: Option<PathBuf>,
 }
 
 fn main() {
-    let mock = Cli::parse().mock;
+    let cli = Cli::parse();
+    let mock = cli.mock;
 
     let srs = {
         // load SRS from Aztec's ceremony
@@ -47,6 +54,12 @@ fn main() {
     };
     let vk: VerifyingKeySol = vk.into();
 
+    if let Some(path) = &cli.vk_json {
+        let json = serde_json::to_string_pretty(&vk).expect("Failed to serialize verifying key");
+        std::fs::write(path, json).expect("Failed to write verifying key JSON");
+        println!("Verifying key JSON:{:?}", path.to_str());
+    }
+
     // calculate the path to solidity file
     let contract_name = if mock {
         "LightClientStateUpdateVKMock"
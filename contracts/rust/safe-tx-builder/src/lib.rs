@@ -24,6 +24,9 @@ pub struct CalldataInfo {
     pub function_info: Option<FunctionInfo>,
     #[serde(skip)]
     pub description: String,
+    /// Safe the batch is created for, recorded in the batch metadata.
+    #[serde(skip)]
+    pub safe_address: Option<Address>,
 }
 
 impl CalldataInfo {
@@ -34,6 +37,7 @@ impl CalldataInfo {
             value: U256::ZERO,
             function_info: None,
             description: String::new(),
+            safe_address: None,
         }
     }
 
@@ -44,6 +48,7 @@ impl CalldataInfo {
             value,
             function_info: Some(function_info),
             description: String::new(),
+            safe_address: None,
         }
     }
 
@@ -51,6 +56,11 @@ impl CalldataInfo {
         self.description = description;
         self
     }
+
+    pub fn with_safe_address(mut self, safe_address: Address) -> Self {
+        self.safe_address = Some(safe_address);
+        self
+    }
 }
 
 /// Safe Transaction Builder batch format
@@ -70,6 +80,11 @@ struct SafeTransactionBuilderBatch {
 struct SafeBatchMeta {
     name: &'static str,
     description: String,
+    #[serde(
+        rename = "createdFromSafeAddress",
+        skip_serializing_if = "Option::is_none"
+    )]
+    created_from_safe_address: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -181,6 +196,7 @@ pub fn output_safe_tx_builder(
         meta: SafeBatchMeta {
             name: "Espresso Multisig Transactions",
             description: info.description.clone(),
+            created_from_safe_address: info.safe_address.map(|safe| safe.to_checksum(None)),
         },
         transactions: vec![{
             let (data, contract_method, contract_inputs_values) = match &info.function_info {
@@ -243,6 +259,31 @@ mod tests {
         assert!(txs[0]["data"].as_str().is_some());
     }
 
+    #[test]
+    fn test_output_safe_tx_builder_safe_address() {
+        let safe: Address = "0x000000000000000000000000000000000000beef"
+            .parse()
+            .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+
+        let path = dir.path().join("without_safe.json");
+        let info = CalldataInfo::new(test_addr(), Bytes::from(vec![0x01]));
+        output_safe_tx_builder(&info, Some(&path), 1).unwrap();
+        let parsed: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert!(parsed["meta"].get("createdFromSafeAddress").is_none());
+
+        let path = dir.path().join("with_safe.json");
+        let info = info.with_safe_address(safe);
+        output_safe_tx_builder(&info, Some(&path), 1).unwrap();
+        let parsed: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(
+            parsed["meta"]["createdFromSafeAddress"].as_str().unwrap(),
+            safe.to_checksum(None)
+        );
+    }
+
     #[test]
     fn test_output_safe_tx_builder_chain_id() {
         let info = CalldataInfo::new(test_addr(), Bytes::from(vec![0x01]));
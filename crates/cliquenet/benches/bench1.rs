@@ -3,7 +3,7 @@ use std::{
 };
 
 use cliquenet::{
-    Config, Network, RetryPolicy, SendAction, SendCommand, Slot,
+    Config, NetAddr, Network, RetryPolicy, SendAction, SendCommand, Slot,
     noise::Protocol,
     x25519::{Keypair, PublicKey},
 };
@@ -360,6 +360,84 @@ fn bench_bidirectional(c: &mut Criterion) {
     group.finish();
 }
 
+// -- Multi-node broadcast -----------------------------------------------------
+
+const NODES: usize = 4;
+
+async fn setup_clique() -> Vec<Network> {
+    let keys = (0..NODES)
+        .map(|_| Keypair::generate().unwrap())
+        .collect::<Vec<_>>();
+
+    let addrs = (0..NODES)
+        .map(|_| (Ipv4Addr::LOCALHOST, reserve_port()))
+        .collect::<Vec<_>>();
+
+    let parties = keys
+        .iter()
+        .zip(&addrs)
+        .map(|(k, a)| (k.public_key(), NetAddr::from(*a)))
+        .collect::<Vec<_>>();
+
+    let mut nets = Vec::new();
+    for (k, a) in keys.into_iter().zip(addrs) {
+        let conf = Config::builder()
+            .name("bench")
+            .keypair(k)
+            .bind(a.into())
+            .parties(parties.clone())
+            .max_message_size(NonZeroUsize::new(100 * MEBI).unwrap())
+            .receive_timeout(Duration::from_secs(60))
+            .connect_retry_delays(vec![1, 3])
+            .send_retry_delays(vec![1, 3])
+            .noise_protocols([(1.into(), Protocol::IK_25519_AesGcm_Blake2s)])
+            .build();
+        nets.push(Network::create(conf).await.unwrap());
+    }
+
+    sleep(Duration::from_secs(2)).await;
+
+    nets
+}
+
+fn bench_broadcast(c: &mut Criterion) {
+    let rt = Runtime::new().unwrap();
+    let mut nets = rt.block_on(setup_clique());
+    let mut slot = Slot::MIN;
+
+    for (name, coded) in [("broadcast", false), ("broadcast[coded]", true)] {
+        let mut group = c.benchmark_group(name);
+        for &n in SIZES {
+            if n > 10 * MEBI {
+                continue;
+            }
+            group.throughput(Throughput::Bytes(n as u64));
+            group.bench_with_input(BenchmarkId::from_parameter(show(n)), &n, |b, &n| {
+                let data = &DATA[&n];
+                b.iter(|| {
+                    let action = if coded {
+                        SendAction::CodedBroadcast(data.clone())
+                    } else {
+                        SendAction::Broadcast(data.clone())
+                    };
+                    slot = Slot::new(u64::from(slot) + 1);
+                    let cmd = SendCommand::builder().slot(slot).action(action).build();
+                    nets[0].send(cmd).unwrap();
+                    // Done once every party, including the sender, has the message.
+                    rt.block_on(async {
+                        for net in &mut nets {
+                            let (_, recv) = net.receive().await.unwrap();
+                            assert_eq!(recv.len(), n);
+                            net.gc(slot).unwrap();
+                        }
+                    })
+                });
+            });
+        }
+        group.finish();
+    }
+}
+
 fn show(size: usize) -> String {
     match size {
         1 => "1 byte".to_string(),
@@ -370,5 +448,11 @@ fn show(size: usize) -> String {
     }
 }
 
-criterion_group!(benches, bench_tcp, bench_cliquenet, bench_bidirectional);
+criterion_group!(
+    benches,
+    bench_tcp,
+    bench_cliquenet,
+    bench_bidirectional,
+    bench_broadcast
+);
 criterion_main!(benches);
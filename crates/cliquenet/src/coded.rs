@@ -0,0 +1,854 @@
+//! Erasure-coded broadcast.
+//!
+//! A coded broadcast splits a message into `data` original shards and adds
+//! `total - data` recovery shards with Reed-Solomon coding, one shard for
+//! each recipient. The recipients are ordered by key and the i-th recipient
+//! gets the i-th shard. Every recipient echoes the shard it got from the
+//! origin to the other recipients and reconstructs the message from any
+//! `data` shards. The origin thus uploads about `total / data` times the
+//! message size, instead of `total` times.
+//!
+//! Shards are committed to by a Merkle tree, whose root also covers the
+//! message length and coding parameters. Every chunk carries the Merkle
+//! proof of its shard, so relays can not alter shards. After decoding, the
+//! message is encoded again and the root compared, hence all recipients
+//! which decode a message decode the same one.
+//!
+//! Like Bracha's reliable broadcast, recipients agree on a root before they
+//! deliver. With `n` recipients of which at most `f < n / 3` are faulty, a
+//! recipient sends READY for a root once `n - f` recipients have echoed a
+//! shard of it, or once `f + 1` recipients are ready for it. A message is
+//! delivered once `n - f` recipients are ready for its root and enough of
+//! its shards are there. The origin gives every message an id, and every
+//! recipient echoes only one shard per origin, slot and id, so at most one
+//! root of a message gets ready. An origin may thus broadcast several
+//! messages in a slot. If one correct recipient delivers a message, all
+//! correct recipients eventually do, even if the origin only sent shards to
+//! some of them.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    mem,
+};
+
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::{
+    msg::{MsgId, Slot},
+    x25519::PublicKey,
+};
+
+type Digest = [u8; 32];
+
+/// A message is identified by its origin, slot, id and root.
+type Key = (Slot, PublicKey, MsgId, Digest);
+
+/// Shard sizes are multiples of this value.
+const SHARD_ALIGN: usize = 64;
+
+/// Max. Merkle proof length, given at most `u16::MAX` shards.
+const MAX_PROOF_LEN: usize = 16;
+
+/// Size of a chunk without its proof and shard.
+const HEADER_SIZE: usize = 32 + 8 + 8 + 32 + 4 + 2 + 2 + 2 + 1;
+
+/// Size of a ready.
+const READY_SIZE: usize = 32 + 8 + 8 + 32;
+
+/// Max. number of bytes a chunk adds to the message size.
+pub(crate) const MAX_OVERHEAD: usize = 1 + HEADER_SIZE + MAX_PROOF_LEN * 32 + SHARD_ALIGN;
+
+const CHUNK: u8 = 0;
+const READY: u8 = 1;
+
+/// A message of the coded broadcast protocol.
+#[derive(Debug, Clone)]
+pub(crate) enum Message {
+    /// A shard, sent by the origin and echoed by its recipient.
+    Chunk(Chunk),
+    /// A recipient is ready to deliver a message.
+    Ready(Ready),
+}
+
+/// A shard of a coded message.
+#[derive(Debug, Clone)]
+pub(crate) struct Chunk {
+    /// The party which broadcast the message.
+    pub(crate) origin: PublicKey,
+    /// The slot the message corresponds to.
+    pub(crate) slot: Slot,
+    /// The id the origin gave the message.
+    pub(crate) id: MsgId,
+    /// Commitment to all shards and coding parameters.
+    pub(crate) root: Digest,
+    /// Length of the message.
+    pub(crate) len: u32,
+    /// Total number of shards.
+    pub(crate) total: u16,
+    /// Number of shards needed to decode the message.
+    pub(crate) data: u16,
+    /// Index of this shard.
+    pub(crate) index: u16,
+    proof: Vec<Digest>,
+    shard: Bytes,
+}
+
+/// A recipient is ready to deliver the message committed to by `root`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Ready {
+    /// The party which broadcast the message.
+    pub(crate) origin: PublicKey,
+    /// The slot the message corresponds to.
+    pub(crate) slot: Slot,
+    /// The id the origin gave the message.
+    pub(crate) id: MsgId,
+    /// Commitment to all shards and coding parameters.
+    pub(crate) root: Digest,
+}
+
+/// Number of shards needed to decode a message coded into `total` shards.
+pub(crate) fn data_shards(total: usize) -> usize {
+    (total / 3 + 1).min(total)
+}
+
+/// Max. number of faulty parties among `n` recipients.
+fn faults(n: usize) -> usize {
+    n.saturating_sub(1) / 3
+}
+
+/// Number of recipients which need to echo or be ready.
+fn quorum(n: usize) -> usize {
+    n - faults(n)
+}
+
+/// Encode a message into `total` chunks, one per recipient.
+///
+/// Returns `None` if the message or the number of chunks is too large.
+pub(crate) fn encode(
+    origin: PublicKey,
+    slot: Slot,
+    id: MsgId,
+    msg: &[u8],
+    total: usize,
+) -> Option<Vec<Chunk>> {
+    let len = u32::try_from(msg.len()).ok()?;
+    if total == 0 || total > u16::MAX.into() {
+        return None;
+    }
+    let data = data_shards(total);
+    let shards = shards(msg, data, total).ok()?;
+    let levels = tree(shards.iter().map(|s| leaf(s)).collect());
+    let root = commit(&levels[levels.len() - 1][0], len, total as u16, data as u16);
+    let chunks = shards
+        .into_iter()
+        .enumerate()
+        .map(|(i, s)| Chunk {
+            origin,
+            slot,
+            id,
+            root,
+            len,
+            total: total as u16,
+            data: data as u16,
+            index: i as u16,
+            proof: proof(&levels, i),
+            shard: Bytes::from(s),
+        })
+        .collect();
+    Some(chunks)
+}
+
+impl Message {
+    pub(crate) fn to_bytes(&self) -> Bytes {
+        match self {
+            Self::Chunk(c) => {
+                let mut b = BytesMut::with_capacity(1 + c.size());
+                b.put_u8(CHUNK);
+                c.put(&mut b);
+                b.freeze()
+            },
+            Self::Ready(r) => {
+                let mut b = BytesMut::with_capacity(1 + READY_SIZE);
+                b.put_u8(READY);
+                b.put_slice(&r.origin.as_bytes());
+                b.put_u64(r.slot.0);
+                b.put_u64(r.id.0);
+                b.put_slice(&r.root);
+                b.freeze()
+            },
+        }
+    }
+
+    pub(crate) fn from_bytes(mut bytes: Bytes) -> Option<Self> {
+        if bytes.is_empty() {
+            return None;
+        }
+        match bytes.split_to(1)[0] {
+            CHUNK => Chunk::from_bytes(bytes).map(Self::Chunk),
+            READY => {
+                if bytes.len() != READY_SIZE {
+                    return None;
+                }
+                Some(Self::Ready(Ready {
+                    origin: PublicKey::try_from(&bytes[..32]).ok()?,
+                    slot: Slot(u64::from_be_bytes(bytes[32..40].try_into().ok()?)),
+                    id: MsgId(u64::from_be_bytes(bytes[40..48].try_into().ok()?)),
+                    root: bytes[48..80].try_into().ok()?,
+                }))
+            },
+            _ => None,
+        }
+    }
+
+    pub(crate) fn origin(&self) -> PublicKey {
+        match self {
+            Self::Chunk(c) => c.origin,
+            Self::Ready(r) => r.origin,
+        }
+    }
+
+    pub(crate) fn slot(&self) -> Slot {
+        match self {
+            Self::Chunk(c) => c.slot,
+            Self::Ready(r) => r.slot,
+        }
+    }
+}
+
+impl Chunk {
+    fn size(&self) -> usize {
+        HEADER_SIZE + self.proof.len() * 32 + self.shard.len()
+    }
+
+    fn put(&self, b: &mut BytesMut) {
+        b.put_slice(&self.origin.as_bytes());
+        b.put_u64(self.slot.0);
+        b.put_u64(self.id.0);
+        b.put_slice(&self.root);
+        b.put_u32(self.len);
+        b.put_u16(self.total);
+        b.put_u16(self.data);
+        b.put_u16(self.index);
+        b.put_u8(self.proof.len() as u8);
+        for d in &self.proof {
+            b.put_slice(d)
+        }
+        b.put_slice(&self.shard);
+    }
+
+    fn from_bytes(mut bytes: Bytes) -> Option<Self> {
+        if bytes.len() < HEADER_SIZE {
+            return None;
+        }
+        let hdr = bytes.split_to(HEADER_SIZE);
+        let origin = PublicKey::try_from(&hdr[..32]).ok()?;
+        let slot = Slot(u64::from_be_bytes(hdr[32..40].try_into().ok()?));
+        let id = MsgId(u64::from_be_bytes(hdr[40..48].try_into().ok()?));
+        let root = hdr[48..80].try_into().ok()?;
+        let len = u32::from_be_bytes(hdr[80..84].try_into().ok()?);
+        let total = u16::from_be_bytes(hdr[84..86].try_into().ok()?);
+        let data = u16::from_be_bytes(hdr[86..88].try_into().ok()?);
+        let index = u16::from_be_bytes(hdr[88..90].try_into().ok()?);
+        let n = usize::from(hdr[90]);
+        if n > MAX_PROOF_LEN || bytes.len() < n * 32 {
+            return None;
+        }
+        let proof = bytes
+            .split_to(n * 32)
+            .chunks_exact(32)
+            .map(|d| d.try_into().expect("32 byte chunks"))
+            .collect();
+        Some(Self {
+            origin,
+            slot,
+            id,
+            root,
+            len,
+            total,
+            data,
+            index,
+            proof,
+            shard: bytes,
+        })
+    }
+
+    /// Check that the shard is part of the message committed to.
+    pub(crate) fn verify(&self, max_message_size: usize) -> bool {
+        let total = usize::from(self.total);
+        let data = usize::from(self.data);
+        let index = usize::from(self.index);
+        let len = self.len as usize;
+        if data == 0 || data > total || index >= total || len > max_message_size {
+            return false;
+        }
+        if self.shard.len() != shard_size(len, data) {
+            return false;
+        }
+        let Some(r) = tree_root(leaf(&self.shard), index, total, &self.proof) else {
+            return false;
+        };
+        commit(&r, self.len, self.total, self.data) == self.root
+    }
+}
+
+/// The result of adding a chunk or ready to a [`Decoder`].
+#[derive(Debug)]
+pub(crate) enum Status {
+    /// The chunk or ready has been ignored, e.g. because it is a duplicate.
+    Ignored,
+    /// The chunk or ready has been added.
+    Added,
+    /// We are ready to deliver the message and have to tell the others.
+    ///
+    /// Our own ready needs to be added as well.
+    Ready(Ready),
+    /// The message has been decoded.
+    Decoded(Bytes),
+    /// Enough recipients were ready, but the shards were inconsistent.
+    Failed,
+}
+
+/// Collects chunks and readies and decodes messages.
+///
+/// The number of recipients `n` is passed in with every chunk and ready.
+/// Callers need to ensure that every recipient only echoes its own shard.
+#[derive(Debug)]
+pub(crate) struct Decoder {
+    /// Max. number of incomplete messages a party may start per origin.
+    max_pending: usize,
+    msgs: HashMap<Key, Entry>,
+    /// Number of incomplete messages per claimed origin and party which
+    /// started them.
+    ///
+    /// A faulty origin can thus not use up the capacity of correct parties,
+    /// nor can a faulty party use up the capacity of correct origins.
+    pending: HashMap<(PublicKey, PublicKey), usize>,
+    /// The messages, by slot, origin and id, whose chunk we have echoed.
+    echoed: HashSet<(Slot, PublicKey, MsgId)>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    /// The party whose chunk or ready started this entry.
+    creator: PublicKey,
+    /// The parties which echoed a chunk.
+    echoes: HashSet<PublicKey>,
+    /// The parties which are ready.
+    readies: HashSet<PublicKey>,
+    /// The shards received so far.
+    shards: BTreeMap<u16, Bytes>,
+    /// Message length and coding parameters, once a chunk is there.
+    params: Option<(u32, u16, u16)>,
+    /// Are we ready?
+    ready: bool,
+    /// Has the message been decoded (or failed to)?
+    done: bool,
+}
+
+impl Decoder {
+    pub(crate) fn new(max_pending: usize) -> Self {
+        Self {
+            max_pending,
+            msgs: HashMap::new(),
+            pending: HashMap::new(),
+            echoed: HashSet::new(),
+        }
+    }
+
+    /// Note that we echo a chunk we got from its origin.
+    ///
+    /// Returns false if we have echoed a chunk of the message before, even
+    /// if it had a different root.
+    pub(crate) fn echo(&mut self, c: &Chunk) -> bool {
+        self.echoed.insert((c.slot, c.origin, c.id))
+    }
+
+    /// Add a verified chunk the given party echoed.
+    pub(crate) fn add(&mut self, src: PublicKey, c: Chunk, n: usize) -> Status {
+        let key = (c.slot, c.origin, c.id, c.root);
+        let Some(e) = self.entry(src, key) else {
+            return Status::Ignored;
+        };
+        if e.done || !e.echoes.insert(src) {
+            return Status::Ignored;
+        }
+        e.shards.entry(c.index).or_insert(c.shard);
+        e.params = Some((c.len, c.total, c.data));
+        if !e.ready && e.echoes.len() >= quorum(n) {
+            e.ready = true;
+            return Status::Ready(Ready {
+                origin: c.origin,
+                slot: c.slot,
+                id: c.id,
+                root: c.root,
+            });
+        }
+        self.complete(key, n)
+    }
+
+    /// Add a ready of the given party.
+    pub(crate) fn add_ready(&mut self, src: PublicKey, r: Ready, n: usize) -> Status {
+        let key = (r.slot, r.origin, r.id, r.root);
+        let Some(e) = self.entry(src, key) else {
+            return Status::Ignored;
+        };
+        if e.done || !e.readies.insert(src) {
+            return Status::Ignored;
+        }
+        if !e.ready && e.readies.len() > faults(n) {
+            e.ready = true;
+            return Status::Ready(r);
+        }
+        self.complete(key, n)
+    }
+
+    /// Remove all messages with slots less than the given one.
+    pub(crate) fn gc(&mut self, s: Slot) {
+        let pending = &mut self.pending;
+        self.msgs.retain(|(slot, origin, ..), e| {
+            if *slot >= s {
+                return true;
+            }
+            if !e.done
+                && let Some(n) = pending.get_mut(&(*origin, e.creator))
+            {
+                *n = n.saturating_sub(1)
+            }
+            false
+        });
+        self.pending.retain(|_, n| *n > 0);
+        self.echoed.retain(|(slot, ..)| *slot >= s);
+    }
+
+    /// Get or create the entry of a message.
+    ///
+    /// Returns `None` if the party may not start another message of the origin.
+    fn entry(&mut self, src: PublicKey, key: Key) -> Option<&mut Entry> {
+        if !self.msgs.contains_key(&key) {
+            let n = self.pending.entry((key.1, src)).or_default();
+            if *n >= self.max_pending {
+                return None;
+            }
+            *n += 1;
+            let e = Entry {
+                creator: src,
+                echoes: HashSet::new(),
+                readies: HashSet::new(),
+                shards: BTreeMap::new(),
+                params: None,
+                ready: false,
+                done: false,
+            };
+            self.msgs.insert(key, e);
+        }
+        self.msgs.get_mut(&key)
+    }
+
+    /// Decode a message once enough parties are ready and shards are there.
+    fn complete(&mut self, key: Key, n: usize) -> Status {
+        let e = self.msgs.get_mut(&key).expect("entry exists");
+        let Some((len, total, data)) = e.params else {
+            return Status::Added;
+        };
+        if e.readies.len() < quorum(n) || e.shards.len() < usize::from(data) {
+            return Status::Added;
+        }
+        let shards = mem::take(&mut e.shards);
+        e.done = true;
+        if let Some(k) = self.pending.get_mut(&(key.1, e.creator)) {
+            *k = k.saturating_sub(1)
+        }
+        match decode(&shards, len, total, data) {
+            Some(msg) if commit_to(&msg, total, data) == Some(key.3) => Status::Decoded(msg),
+            _ => Status::Failed,
+        }
+    }
+}
+
+/// Reconstruct a message from (at least) `data` of its shards.
+fn decode(shards: &BTreeMap<u16, Bytes>, len: u32, total: u16, data: u16) -> Option<Bytes> {
+    let (total, data) = (usize::from(total), usize::from(data));
+    let mut msg = Vec::with_capacity(shard_size(len as usize, data) * data);
+    if shards.range(..data as u16).count() == data {
+        for s in shards.range(..data as u16).map(|(_, s)| s) {
+            msg.extend_from_slice(s)
+        }
+    } else {
+        let original = shards
+            .range(..data as u16)
+            .map(|(i, s)| (usize::from(*i), s));
+        let recovery = shards
+            .range(data as u16..)
+            .map(|(i, s)| (usize::from(*i) - data, s));
+        let mut restored =
+            reed_solomon_simd::decode(data, total - data, original, recovery).ok()?;
+        for i in 0..data {
+            if let Some(s) = shards.get(&(i as u16)) {
+                msg.extend_from_slice(s)
+            } else {
+                msg.extend_from_slice(&restored.remove(&i)?)
+            }
+        }
+    }
+    msg.truncate(len as usize);
+    Some(Bytes::from(msg))
+}
+
+/// The commitment of encoding `msg` with the given parameters.
+fn commit_to(msg: &[u8], total: u16, data: u16) -> Option<Digest> {
+    let shards = shards(msg, data.into(), total.into()).ok()?;
+    let levels = tree(shards.iter().map(|s| leaf(s)).collect());
+    Some(commit(
+        &levels[levels.len() - 1][0],
+        msg.len() as u32,
+        total,
+        data,
+    ))
+}
+
+/// Split a message into `data` original shards and add recovery shards.
+fn shards(msg: &[u8], data: usize, total: usize) -> Result<Vec<Vec<u8>>, reed_solomon_simd::Error> {
+    let size = shard_size(msg.len(), data);
+    let mut shards = (0..data)
+        .map(|i| {
+            let mut s = msg
+                .get(i * size..msg.len().min((i + 1) * size))
+                .unwrap_or_default()
+                .to_vec();
+            s.resize(size, 0);
+            s
+        })
+        .collect::<Vec<_>>();
+    if total > data {
+        let recovery = reed_solomon_simd::encode(data, total - data, &shards)?;
+        shards.extend(recovery)
+    }
+    Ok(shards)
+}
+
+fn shard_size(len: usize, data: usize) -> usize {
+    len.div_ceil(data)
+        .next_multiple_of(SHARD_ALIGN)
+        .max(SHARD_ALIGN)
+}
+
+fn leaf(shard: &[u8]) -> Digest {
+    blake3::Hasher::new()
+        .update(&[0])
+        .update(shard)
+        .finalize()
+        .into()
+}
+
+fn node(l: &Digest, r: &Digest) -> Digest {
+    blake3::Hasher::new()
+        .update(&[1])
+        .update(l)
+        .update(r)
+        .finalize()
+        .into()
+}
+
+fn commit(tree_root: &Digest, len: u32, total: u16, data: u16) -> Digest {
+    blake3::Hasher::new()
+        .update(&[2])
+        .update(tree_root)
+        .update(&len.to_be_bytes())
+        .update(&total.to_be_bytes())
+        .update(&data.to_be_bytes())
+        .finalize()
+        .into()
+}
+
+/// All levels of the Merkle tree over the given leaves, bottom up.
+///
+/// A node without sibling is carried over to the next level as is.
+fn tree(leaves: Vec<Digest>) -> Vec<Vec<Digest>> {
+    let mut levels = vec![leaves];
+    while levels[levels.len() - 1].len() > 1 {
+        let next = levels[levels.len() - 1]
+            .chunks(2)
+            .map(|p| match p {
+                [l, r] => node(l, r),
+                [x] => *x,
+                _ => unreachable!("chunks of 1 or 2 elements"),
+            })
+            .collect();
+        levels.push(next)
+    }
+    levels
+}
+
+/// The Merkle proof of the i-th leaf.
+fn proof(levels: &[Vec<Digest>], mut i: usize) -> Vec<Digest> {
+    let mut p = Vec::new();
+    for level in &levels[..levels.len() - 1] {
+        if let Some(s) = level.get(i ^ 1) {
+            p.push(*s)
+        }
+        i /= 2
+    }
+    p
+}
+
+/// Compute the Merkle root from the i-th of `width` leaves and its proof.
+fn tree_root(leaf: Digest, mut i: usize, mut width: usize, proof: &[Digest]) -> Option<Digest> {
+    let mut h = leaf;
+    let mut p = proof.iter();
+    while width > 1 {
+        if i ^ 1 < width {
+            let s = p.next()?;
+            h = if i % 2 == 0 { node(&h, s) } else { node(s, &h) }
+        }
+        i /= 2;
+        width = width.div_ceil(2)
+    }
+    p.next().is_none().then_some(h)
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use quickcheck::quickcheck;
+
+    use super::{Chunk, Decoder, Message, Ready, Status, data_shards, encode, quorum};
+    use crate::{
+        Keypair, PublicKey,
+        msg::{MsgId, Slot},
+    };
+
+    const ID: MsgId = MsgId::new(0);
+
+    fn key() -> PublicKey {
+        Keypair::generate().unwrap().public_key()
+    }
+
+    fn to_bytes(c: &Chunk) -> Bytes {
+        Message::Chunk(c.clone()).to_bytes()
+    }
+
+    fn from_bytes(b: Bytes) -> Option<Chunk> {
+        match Message::from_bytes(b)? {
+            Message::Chunk(c) => Some(c),
+            Message::Ready(_) => None,
+        }
+    }
+
+    fn ready(c: &Chunk) -> Ready {
+        Ready {
+            origin: c.origin,
+            slot: c.slot,
+            id: c.id,
+            root: c.root,
+        }
+    }
+
+    /// Feed a quorum of readies and the chunks, each from a different party,
+    /// and return the result.
+    fn decode(chunks: impl IntoIterator<Item = Chunk>) -> Option<Bytes> {
+        let mut chunks = chunks.into_iter().peekable();
+        let first = chunks.peek()?;
+        let (n, r) = (usize::from(first.total), ready(first));
+        let mut d = Decoder::new(10);
+        for _ in 0..quorum(n) {
+            assert!(matches!(
+                d.add_ready(key(), r, n),
+                Status::Added | Status::Ready(_)
+            ));
+        }
+        for c in chunks {
+            assert!(c.verify(usize::MAX));
+            if let Status::Decoded(m) = d.add(key(), c, n) {
+                return Some(m);
+            }
+        }
+        None
+    }
+
+    quickcheck! {
+        fn prop_encode_decode_id(msg: Vec<u8>, total: u8, skip: u8) -> bool {
+            let total = usize::from(total.max(1));
+            let chunks = encode(key(), Slot::MIN, ID, &msg, total).unwrap();
+            let data = data_shards(total);
+            // Any `data` chunks are enough:
+            let skip = usize::from(skip) % (total - data + 1);
+            decode(chunks.into_iter().skip(skip).take(data)).as_deref() == Some(&msg[..])
+        }
+
+        fn prop_from_bytes_to_bytes_id(msg: Vec<u8>, total: u8) -> bool {
+            let total = usize::from(total.max(1));
+            encode(key(), Slot::MIN, ID, &msg, total).unwrap().into_iter().all(|c| {
+                let d = from_bytes(to_bytes(&c)).unwrap();
+                to_bytes(&d) == to_bytes(&c) && d.verify(usize::MAX)
+            })
+        }
+
+        fn prop_from_bytes_arbitrary_does_not_panic(bytes: Vec<u8>) -> bool {
+            let _ = Chunk::from_bytes(Bytes::from(bytes.clone()));
+            let _ = Message::from_bytes(Bytes::from(bytes));
+            true
+        }
+    }
+
+    #[test]
+    fn ready_from_bytes_to_bytes_id() {
+        let c = encode(key(), Slot::new(3), MsgId::new(5), b"hello", 4)
+            .unwrap()
+            .remove(0);
+        let r = ready(&c);
+        let b = Message::Ready(r).to_bytes();
+        assert!(matches!(Message::from_bytes(b), Some(Message::Ready(s)) if s == r));
+    }
+
+    #[test]
+    fn too_few_chunks() {
+        let chunks = encode(key(), Slot::MIN, ID, b"hello", 10).unwrap();
+        assert_eq!(data_shards(10), 4);
+        assert!(decode(chunks.into_iter().take(3)).is_none());
+    }
+
+    #[test]
+    fn altered_chunks_are_invalid() {
+        let mut chunks = encode(key(), Slot::MIN, ID, &[1; 1000], 4).unwrap();
+
+        let mut b = to_bytes(&chunks[0]).to_vec();
+        *b.last_mut().unwrap() ^= 1;
+        assert!(!from_bytes(Bytes::from(b)).unwrap().verify(usize::MAX));
+
+        chunks[1].index = 2;
+        assert!(!chunks[1].verify(usize::MAX));
+
+        chunks[2].data = 1;
+        assert!(!chunks[2].verify(usize::MAX));
+
+        assert!(!chunks[3].verify(999));
+    }
+
+    #[test]
+    fn one_chunk_per_party() {
+        let chunks = encode(key(), Slot::MIN, ID, b"hello", 7).unwrap();
+        let mut d = Decoder::new(10);
+        for _ in 0..quorum(7) {
+            d.add_ready(key(), ready(&chunks[0]), 7);
+        }
+        let k = key();
+        assert!(matches!(d.add(k, chunks[0].clone(), 7), Status::Added));
+        assert!(matches!(d.add(k, chunks[1].clone(), 7), Status::Ignored));
+        assert!(matches!(d.add(key(), chunks[2].clone(), 7), Status::Added));
+        assert!(matches!(
+            d.add(key(), chunks[3].clone(), 7),
+            Status::Decoded(_)
+        ));
+        assert!(matches!(
+            d.add(key(), chunks[4].clone(), 7),
+            Status::Ignored
+        ));
+    }
+
+    #[test]
+    fn delivery_requires_ready_quorum() {
+        // 4 recipients tolerate 1 fault, i.e. 3 have to echo or be ready.
+        let chunks = encode(key(), Slot::MIN, ID, b"hello", 4).unwrap();
+        let r = ready(&chunks[0]);
+        let mut d = Decoder::new(10);
+
+        let mut status = Vec::new();
+        for c in chunks {
+            status.push(d.add(key(), c, 4))
+        }
+        assert!(matches!(
+            status[..],
+            [
+                Status::Added,
+                Status::Added,
+                Status::Ready(_),
+                Status::Added
+            ]
+        ));
+
+        // Our own ready and one other are not enough:
+        assert!(matches!(d.add_ready(key(), r, 4), Status::Added));
+        assert!(matches!(d.add_ready(key(), r, 4), Status::Added));
+        assert!(matches!(d.add_ready(key(), r, 4), Status::Decoded(_)));
+    }
+
+    #[test]
+    fn ready_amplification() {
+        let chunks = encode(key(), Slot::MIN, ID, b"hello", 4).unwrap();
+        let r = ready(&chunks[0]);
+        let mut d = Decoder::new(10);
+
+        // A single, possibly faulty, party does not make us ready:
+        assert!(matches!(d.add_ready(key(), r, 4), Status::Added));
+        assert!(matches!(d.add_ready(key(), r, 4), Status::Ready(_)));
+        assert!(matches!(d.add_ready(key(), r, 4), Status::Added));
+
+        // Without a chunk there is nothing to decode yet:
+        assert!(matches!(d.add_ready(key(), r, 4), Status::Added));
+        assert!(matches!(d.add(key(), chunks[0].clone(), 4), Status::Added));
+        assert!(matches!(
+            d.add(key(), chunks[1].clone(), 4),
+            Status::Decoded(_)
+        ));
+    }
+
+    #[test]
+    fn echo_once_per_message() {
+        let o = key();
+        let a = encode(o, Slot::MIN, ID, b"hello", 4).unwrap().remove(0);
+        let b = encode(o, Slot::MIN, ID, b"world", 4).unwrap().remove(0);
+        let c = encode(o, Slot::MIN, MsgId::new(1), b"world", 4)
+            .unwrap()
+            .remove(0);
+        let e = encode(o, Slot::new(1), ID, b"hello", 4).unwrap().remove(0);
+        let mut d = Decoder::new(10);
+        assert!(d.echo(&a));
+        assert!(!d.echo(&b));
+        assert!(d.echo(&c));
+        assert!(!d.echo(&c));
+        assert!(d.echo(&e));
+        d.gc(Slot::new(1));
+        assert!(d.echo(&b));
+    }
+
+    #[test]
+    fn several_messages_per_origin_and_slot() {
+        let o = key();
+        let a = encode(o, Slot::MIN, ID, b"hello", 4).unwrap();
+        let b = encode(o, Slot::MIN, MsgId::new(1), b"world", 4).unwrap();
+        let mut d = Decoder::new(10);
+        for (chunks, msg) in [(a, b"hello"), (b, b"world")] {
+            let r = ready(&chunks[0]);
+            for c in chunks {
+                d.add(key(), c, 4);
+            }
+            assert!(matches!(d.add_ready(key(), r, 4), Status::Added));
+            assert!(matches!(d.add_ready(key(), r, 4), Status::Added));
+            assert!(matches!(d.add_ready(key(), r, 4), Status::Decoded(m) if m == &msg[..]));
+        }
+    }
+
+    #[test]
+    fn pending_messages_are_limited() {
+        let mut d = Decoder::new(2);
+        let (k, o) = (key(), key());
+        for i in 0..3 {
+            let c = encode(o, Slot::new(i), ID, b"hello", 4).unwrap().remove(0);
+            let s = d.add(k, c, 4);
+            assert_eq!(i < 2, matches!(s, Status::Added));
+        }
+
+        // Neither other parties of the origin, nor other origins of the party
+        // are affected:
+        let c = encode(o, Slot::new(2), ID, b"hello", 4).unwrap().remove(0);
+        assert!(matches!(d.add(key(), c, 4), Status::Added));
+        let c = encode(key(), Slot::new(2), ID, b"hello", 4)
+            .unwrap()
+            .remove(0);
+        assert!(matches!(d.add(k, c, 4), Status::Added));
+
+        d.gc(Slot::new(1));
+        let c = encode(o, Slot::new(3), ID, b"hello", 4).unwrap().remove(0);
+        assert!(matches!(d.add(k, c, 4), Status::Added));
+    }
+}
@@ -3,25 +3,28 @@ use std::{
     io,
     iter::{once, repeat},
     net::SocketAddr,
+    pin::Pin,
     sync::Arc,
+    task::{Context, Poll},
     time::Duration,
 };
 
 use rand::RngExt;
-use snow::{Builder, HandshakeState, TransportState, params::NoiseParams};
+use snow::{Builder, HandshakeState, TransportState};
 use socket2::{SockRef, TcpKeepalive};
 use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt},
-    net::TcpStream,
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf},
+    net::{TcpListener, TcpStream, lookup_host},
     time::sleep,
     try_join,
 };
 use tracing::{debug, warn};
 
 use crate::{
-    Config, NetAddr, Version,
+    Config, NetAddr, Transport, Version,
     error::NetworkError,
     msg::{Header, MAX_NOISE_MESSAGE_SIZE, hello::Hello},
+    quic::{self, Link},
     util::until,
     x25519::PublicKey,
 };
@@ -33,56 +36,128 @@ type Result<T> = std::result::Result<T, NetworkError>;
 pub struct Connection {
     pub key: PublicKey,
     pub addr: SocketAddr,
-    pub stream: TcpStream,
+    pub stream: Stream,
     pub state: TransportState,
 }
 
-type Prologue = Vec<u8>;
+/// The stream a connection was established over.
+///
+/// With QUIC this is the control stream of the session.
+pub enum Stream {
+    Tcp(TcpStream),
+    Quic(Link),
+}
 
-impl Connection {
-    pub async fn accept(conf: Arc<Config>, mut stream: TcpStream) -> Result<Self> {
-        let node = conf.keypair.public_key();
-        let addr = stream.peer_addr()?;
+/// Accepts connections from remote parties.
+pub enum Listener {
+    Tcp(TcpListener),
+    Quic(quinn::Endpoint),
+}
 
-        configure_socket(&conf, &node, &addr, &stream);
+/// An accepted connection, before the handshake.
+pub enum Incoming {
+    Tcp(TcpStream),
+    Quic(quinn::Incoming),
+}
 
-        until(conf.handshake_timeout, async move {
-            let (version, prologue) =
-                select_version(&node, &addr, &conf, &mut stream, false).await?;
+/// Opens connections to remote parties.
+#[derive(Clone)]
+pub enum Dialer {
+    Tcp,
+    /// QUIC sessions are opened from the endpoint we listen on.
+    Quic(quinn::Endpoint),
+}
 
-            debug!(name = %conf.name, %node, %addr, %version, "negotiated version");
+type Prologue = Vec<u8>;
 
-            let noise_proto = conf
-                .noise_protocols
-                .get(&version)
-                .expect("selected version has noise config");
+impl Listener {
+    /// Listen on the configured address with the configured transport.
+    pub async fn bind(conf: &Config) -> Result<Self> {
+        let bind_error = |e| NetworkError::Bind(conf.bind.clone(), e);
+        match conf.transport {
+            Transport::Tcp => {
+                let listener = TcpListener::bind(conf.bind.to_string())
+                    .await
+                    .map_err(bind_error)?;
+                Ok(Self::Tcp(listener))
+            },
+            Transport::Quic => {
+                let Some(addr) = lookup_host(conf.bind.to_string())
+                    .await
+                    .map_err(bind_error)?
+                    .next()
+                else {
+                    return Err(bind_error(io::ErrorKind::AddrNotAvailable.into()));
+                };
+                Ok(Self::Quic(quic::endpoint(conf, addr)?))
+            },
+        }
+    }
 
-            let hs = Builder::new(noise_proto.noise_params())
-                .local_private_key(&conf.keypair.secret_key().as_bytes())
-                .expect("valid private key")
-                .prologue(&prologue)
-                .expect("1st time we set the prologue")
-                .build_responder()
-                .expect("valid noise params yield valid handshake state");
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        match self {
+            Self::Tcp(listener) => listener.local_addr(),
+            Self::Quic(endpoint) => endpoint.local_addr(),
+        }
+    }
 
-            let state = on_handshake(&mut stream, hs).await?;
+    pub fn dialer(&self) -> Dialer {
+        match self {
+            Self::Tcp(_) => Dialer::Tcp,
+            Self::Quic(endpoint) => Dialer::Quic(endpoint.clone()),
+        }
+    }
 
-            if let Some(key) = remote_static_key(&state) {
-                Ok(Self {
-                    key,
-                    addr,
-                    stream,
-                    state,
+    /// Wait for the next incoming connection.
+    ///
+    /// This method is cancel safe.
+    pub async fn accept(&self) -> io::Result<(Incoming, SocketAddr)> {
+        match self {
+            Self::Tcp(listener) => {
+                let (stream, addr) = listener.accept().await?;
+                Ok((Incoming::Tcp(stream), addr))
+            },
+            Self::Quic(endpoint) => {
+                // The endpoint is only closed when dropped, i.e. never
+                // while we are listening.
+                let Some(incoming) = endpoint.accept().await else {
+                    return std::future::pending().await;
+                };
+                let addr = incoming.remote_address();
+                Ok((Incoming::Quic(incoming), addr))
+            },
+        }
+    }
+}
+
+impl Connection {
+    pub async fn accept(conf: Arc<Config>, incoming: Incoming) -> Result<Self> {
+        match incoming {
+            Incoming::Tcp(stream) => {
+                let node = conf.keypair.public_key();
+                let addr = stream.peer_addr()?;
+                configure_socket(&conf, &node, &addr, &stream);
+                let stream = Stream::Tcp(stream);
+                until(conf.handshake_timeout, respond(&conf, addr, stream, &[])).await
+            },
+            Incoming::Quic(incoming) => {
+                let addr = incoming.remote_address();
+                until(conf.handshake_timeout, async {
+                    let link = quic::accept(incoming).await?;
+                    let binding = link.binding(&conf);
+                    respond(&conf, addr, Stream::Quic(link), &binding).await
                 })
-            } else {
-                warn!(name = %conf.name, %node, %addr, "invalid static key");
-                Err(NetworkError::InvalidHandshakeMessage)
-            }
-        })
-        .await
+                .await
+            },
+        }
     }
 
-    pub async fn connect(conf: Arc<Config>, peer: PublicKey, addr: NetAddr) -> Self {
+    pub async fn connect(
+        conf: Arc<Config>,
+        dialer: Dialer,
+        peer: PublicKey,
+        addr: NetAddr,
+    ) -> Self {
         let mut delays = once({
             if conf.random_connect_delay {
                 Duration::from_millis(rand::rng().random_range(0..1000))
@@ -114,7 +189,7 @@ impl Connection {
 
             debug!(name = %conf.name, %node, %peer, %addr, "connecting");
 
-            match try_connect(&conf, &peer, &addr).await {
+            match try_connect(&conf, &dialer, &peer, &addr).await {
                 Ok(mut conn) => {
                     let hello_exchange = until(conf.handshake_timeout, async {
                         conn.send_hello(Hello::Ok).await?;
@@ -175,58 +250,131 @@ impl Connection {
     }
 }
 
-async fn try_connect(conf: &Config, peer: &PublicKey, addr: &str) -> Result<Connection> {
-    let new_handshake_state = |prologue: &Prologue, params: NoiseParams| {
-        Builder::new(params)
-            .local_private_key(conf.keypair.secret_key().as_slice())
-            .expect("valid private key")
-            .remote_public_key(peer.as_slice())
-            .expect("valid remote pub key")
-            .prologue(prologue)
-            .expect("1st time we set the prologue")
-            .build_initiator()
-            .expect("valid noise params yield valid handshake state")
+async fn try_connect(
+    conf: &Config,
+    dialer: &Dialer,
+    peer: &PublicKey,
+    addr: &str,
+) -> Result<Connection> {
+    let node = conf.keypair.public_key();
+
+    let (stream, addr, binding) = match dialer {
+        Dialer::Tcp => {
+            let stream = until(conf.connect_timeout, TcpStream::connect(addr)).await?;
+            let addr = stream.peer_addr()?;
+            debug!(name = %conf.name, %node, %peer, %addr, "tcp connection established");
+            configure_socket(conf, &node, &addr, &stream);
+            (Stream::Tcp(stream), addr, Vec::new())
+        },
+        Dialer::Quic(endpoint) => {
+            let link = until(conf.connect_timeout, quic::connect(endpoint, addr)).await?;
+            let addr = link.session.remote_address();
+            debug!(name = %conf.name, %node, %peer, %addr, "quic session established");
+            let binding = link.binding(conf).to_vec();
+            (Stream::Quic(link), addr, binding)
+        },
     };
 
-    let mut stream = until(conf.connect_timeout, TcpStream::connect(addr)).await?;
+    until(
+        conf.handshake_timeout,
+        initiate(conf, peer, addr, stream, &binding),
+    )
+    .await
+}
 
+/// Run the handshake as initiator, expecting the remote to be `peer`.
+///
+/// The `binding` is added to the prologue.
+async fn initiate(
+    conf: &Config,
+    peer: &PublicKey,
+    addr: SocketAddr,
+    mut stream: Stream,
+    binding: &[u8],
+) -> Result<Connection> {
     let node = conf.keypair.public_key();
-    let addr = stream.peer_addr()?;
-
-    debug!(name = %conf.name, %node, %peer, %addr, "tcp connection established");
-
-    configure_socket(conf, &node, &addr, &stream);
-
-    until(conf.handshake_timeout, async move {
-        let (version, prologue) = select_version(&node, &addr, conf, &mut stream, true).await?;
-
-        debug!(name = %conf.name, %node, %peer, %addr, %version, "negotiated version");
-
-        let noise_proto = conf
-            .noise_protocols
-            .get(&version)
-            .expect("selected version has noise config");
-
-        let hshake = new_handshake_state(&prologue, noise_proto.noise_params());
-        let state = handshake(&mut stream, hshake).await?;
-        match remote_static_key(&state) {
-            Some(key) if key == *peer => Ok(Connection {
-                key,
-                addr,
-                stream,
-                state,
-            }),
-            Some(key) => {
-                warn!(name = %conf.name, %node, %peer, remote = %key, %addr, "static key mismatch");
-                Err(NetworkError::InvalidHandshakeMessage)
-            },
-            None => {
-                warn!(name = %conf.name, %node, %peer, %addr, "invalid static key");
-                Err(NetworkError::InvalidHandshakeMessage)
-            },
-        }
-    })
-    .await
+
+    let (version, mut prologue) = select_version(&node, &addr, conf, &mut stream, true).await?;
+    prologue.extend_from_slice(binding);
+
+    debug!(name = %conf.name, %node, %peer, %addr, %version, "negotiated version");
+
+    let noise_proto = conf
+        .noise_protocols
+        .get(&version)
+        .expect("selected version has noise config");
+
+    let hshake = Builder::new(noise_proto.noise_params())
+        .local_private_key(conf.keypair.secret_key().as_slice())
+        .expect("valid private key")
+        .remote_public_key(peer.as_slice())
+        .expect("valid remote pub key")
+        .prologue(&prologue)
+        .expect("1st time we set the prologue")
+        .build_initiator()
+        .expect("valid noise params yield valid handshake state");
+
+    let state = handshake(&mut stream, hshake).await?;
+    match remote_static_key(&state) {
+        Some(key) if key == *peer => Ok(Connection {
+            key,
+            addr,
+            stream,
+            state,
+        }),
+        Some(key) => {
+            warn!(name = %conf.name, %node, %peer, remote = %key, %addr, "static key mismatch");
+            Err(NetworkError::InvalidHandshakeMessage)
+        },
+        None => {
+            warn!(name = %conf.name, %node, %peer, %addr, "invalid static key");
+            Err(NetworkError::InvalidHandshakeMessage)
+        },
+    }
+}
+
+/// Run the handshake as responder.
+///
+/// The `binding` is added to the prologue.
+async fn respond(
+    conf: &Config,
+    addr: SocketAddr,
+    mut stream: Stream,
+    binding: &[u8],
+) -> Result<Connection> {
+    let node = conf.keypair.public_key();
+
+    let (version, mut prologue) = select_version(&node, &addr, conf, &mut stream, false).await?;
+    prologue.extend_from_slice(binding);
+
+    debug!(name = %conf.name, %node, %addr, %version, "negotiated version");
+
+    let noise_proto = conf
+        .noise_protocols
+        .get(&version)
+        .expect("selected version has noise config");
+
+    let hs = Builder::new(noise_proto.noise_params())
+        .local_private_key(&conf.keypair.secret_key().as_bytes())
+        .expect("valid private key")
+        .prologue(&prologue)
+        .expect("1st time we set the prologue")
+        .build_responder()
+        .expect("valid noise params yield valid handshake state");
+
+    let state = on_handshake(&mut stream, hs).await?;
+
+    if let Some(key) = remote_static_key(&state) {
+        Ok(Connection {
+            key,
+            addr,
+            stream,
+            state,
+        })
+    } else {
+        warn!(name = %conf.name, %node, %addr, "invalid static key");
+        Err(NetworkError::InvalidHandshakeMessage)
+    }
 }
 
 fn configure_socket(conf: &Config, node: &PublicKey, addr: &SocketAddr, stream: &TcpStream) {
@@ -252,13 +400,16 @@ fn remote_static_key(state: &TransportState) -> Option<PublicKey> {
 /// Select a version from the range that both sides support.
 ///
 /// This will be the minimum of the max. supported ones from both sides.
-async fn select_version(
+async fn select_version<S>(
     node: &PublicKey,
     addr: &SocketAddr,
     conf: &Config,
-    stream: &mut TcpStream,
+    stream: &mut S,
     is_initiator: bool,
-) -> Result<(Version, Prologue)> {
+) -> Result<(Version, Prologue)>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
     const INIT_PAYLOAD_LEN: usize = 4;
 
     let our_min = *conf.noise_protocols.first().0;
@@ -270,7 +421,7 @@ async fn select_version(
     send_buf[0..2].copy_from_slice(&u16::from(our_min).to_be_bytes());
     send_buf[2..4].copy_from_slice(&u16::from(our_max).to_be_bytes());
 
-    let (mut r, mut w) = stream.split();
+    let (mut r, mut w) = tokio::io::split(stream);
     try_join!(w.write_all(&send_buf), r.read_exact(&mut recv_buf))?;
 
     let their_min = Version::from(u16::from_be_bytes([recv_buf[0], recv_buf[1]]));
@@ -307,7 +458,10 @@ async fn select_version(
 }
 
 /// Perform a noise handshake as initiator with the remote party.
-async fn handshake(stream: &mut TcpStream, mut hs: HandshakeState) -> Result<TransportState> {
+async fn handshake<S>(stream: &mut S, mut hs: HandshakeState) -> Result<TransportState>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
     let mut a = [0u8; MAX_NOISE_HANDSHAKE_SIZE];
     let n = hs.write_message(&[], &mut a[Header::SIZE..])?;
     let h = Header::data(n as u16);
@@ -322,7 +476,10 @@ async fn handshake(stream: &mut TcpStream, mut hs: HandshakeState) -> Result<Tra
 }
 
 /// Perform a noise handshake as responder with a remote party.
-async fn on_handshake(stream: &mut TcpStream, mut hs: HandshakeState) -> Result<TransportState> {
+async fn on_handshake<S>(stream: &mut S, mut hs: HandshakeState) -> Result<TransportState>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
     let mut a = [0u8; MAX_NOISE_HANDSHAKE_SIZE];
     let h = recv_frame(stream, &mut a).await?;
     if !h.is_data() || h.is_partial() {
@@ -367,6 +524,46 @@ where
     Ok(())
 }
 
+impl AsyncRead for Stream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Tcp(stream) => Pin::new(stream).poll_read(cx, buf),
+            Self::Quic(link) => Pin::new(&mut link.recv).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for Stream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Self::Tcp(stream) => Pin::new(stream).poll_write(cx, buf),
+            Self::Quic(link) => Pin::new(&mut link.send).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Tcp(stream) => Pin::new(stream).poll_flush(cx),
+            Self::Quic(link) => Pin::new(&mut link.send).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Tcp(stream) => Pin::new(stream).poll_shutdown(cx),
+            Self::Quic(link) => Pin::new(&mut link.send).poll_shutdown(cx),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::net::Ipv4Addr;
@@ -48,6 +48,18 @@ pub enum NetworkError {
     #[error("noise error: {0}")]
     Noise(#[from] snow::Error),
 
+    /// A QUIC session failed or was closed.
+    #[error("quic error: {0}")]
+    Quic(#[from] quinn::ConnectionError),
+
+    /// A QUIC session could not be started.
+    #[error("quic connect error: {0}")]
+    QuicConnect(#[from] quinn::ConnectError),
+
+    /// Generic TLS error.
+    #[error("tls error: {0}")]
+    Tls(#[from] rustls::Error),
+
     /// The Noise handshake message is not valid.
     #[error("invalid handshake message")]
     InvalidHandshakeMessage,
@@ -1,9 +1,12 @@
+mod coded;
 mod connection;
 mod delay;
 mod metrics;
 mod msg;
 mod net;
 mod queue;
+mod quic;
+mod reputation;
 mod time;
 mod util;
 
@@ -20,10 +23,13 @@ pub use cliquenet_types::{
 pub use error::NetworkError;
 pub use metrics::Metrics;
 pub use msg::Slot;
+#[cfg(feature = "sim")]
+pub use net::{Detached, Inbound, Outgoing};
 pub use net::{
     Network, NetworkReceiver, NetworkSender, RetryPolicy, SendAction, SendCommand,
     SendCommandBuilder,
 };
+pub use reputation::Reputation;
 
 use crate::{
     util::nonempty::NonEmpty,
@@ -57,6 +63,12 @@ pub struct Config {
     /// Address to bind to.
     bind: NetAddr,
 
+    /// The transport to connect parties over.
+    ///
+    /// All parties must use the same transport.
+    #[builder(default)]
+    transport: Transport,
+
     /// Network members with public key and network address.
     #[builder(with = <_>::from_iter)]
     parties: Vec<(PublicKey, NetAddr)>,
@@ -86,7 +98,7 @@ pub struct Config {
     #[builder(default = true)]
     random_connect_delay: bool,
 
-    /// How long to wait to establish a TCP connection?
+    /// How long to wait to establish a TCP connection or QUIC session?
     #[builder(default = Duration::from_secs(30))]
     connect_timeout: Duration,
 
@@ -96,7 +108,8 @@ pub struct Config {
 
     /// After sending a message we expect to hear back from the peer.
     ///
-    /// If we do not receive anything for this duration we reconnect.
+    /// If we do not receive anything for this duration we reconnect. With
+    /// QUIC this is the idle timeout of a session.
     #[builder(default = Duration::from_secs(30))]
     receive_timeout: Duration,
 
@@ -106,11 +119,30 @@ pub struct Config {
     #[builder(default = Duration::from_secs(30))]
     backoff_duration: Duration,
 
+    /// The penalty score at which a misbehaving party is banned.
+    ///
+    /// Decode errors, oversized messages, failed handshakes and reconnects
+    /// add to the score of a party.
+    #[builder(default = 100)]
+    ban_threshold: u32,
+
+    /// How long a party is banned the first time.
+    ///
+    /// Every subsequent ban lasts twice as long as the previous one, up to
+    /// 64 times this duration.
+    #[builder(default = Duration::from_secs(30))]
+    ban_duration: Duration,
+
+    /// After this amount of time, half of a party's penalty score is forgiven.
+    #[builder(default = Duration::from_secs(60))]
+    penalty_half_life: Duration,
+
     /// When to start sending TCP keep alive probes on an idle connection.
     #[builder(default = Duration::from_secs(30))]
     keep_alive_after: Duration,
 
-    /// Time between sending TCP keep alive probes.
+    /// Time between sending TCP keep alive probes, or QUIC keep alive
+    /// packets on an idle session.
     #[builder(default = Duration::from_secs(5))]
     keep_alive_interval: Duration,
 
@@ -147,6 +179,7 @@ impl fmt::Debug for Config {
             .field("name", &self.name)
             .field("key", &self.keypair.public_key())
             .field("bind", &self.bind)
+            .field("transport", &self.transport)
             .field("parties", &self.parties)
             .field("peer_budget", &self.peer_budget)
             .field("max_message_size", &self.max_message_size)
@@ -157,6 +190,9 @@ impl fmt::Debug for Config {
             .field("handshake_timeout", &self.handshake_timeout)
             .field("receive_timeout", &self.receive_timeout)
             .field("backoff_duration", &self.backoff_duration)
+            .field("ban_threshold", &self.ban_threshold)
+            .field("ban_duration", &self.ban_duration)
+            .field("penalty_half_life", &self.penalty_half_life)
             .field("keepalive_after", &self.keep_alive_after)
             .field("keepalive_interval", &self.keep_alive_interval)
             .field("keepalive_retries", &self.keep_alive_retries)
@@ -176,6 +212,30 @@ impl Config {
     }
 }
 
+/// The transport parties are connected over.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Transport {
+    /// Noise over TCP.
+    ///
+    /// All messages to a party share one ordered byte stream.
+    #[default]
+    Tcp,
+    /// QUIC, authenticated with Noise.
+    ///
+    /// Messages of different slots are sent over independent streams, so
+    /// a lost packet only stalls messages of the slots sharing its stream.
+    Quic,
+}
+
+impl fmt::Display for Transport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp => f.write_str("tcp"),
+            Self::Quic => f.write_str("quic"),
+        }
+    }
+}
+
 /// Network peer role.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Role {
@@ -4,6 +4,12 @@ use crate::msg::{MsgId, Slot};
 
 const STD: u8 = 0;
 const NO_ACK: u8 = 1;
+/// Added with coded broadcasts.
+///
+/// Older versions parse this as an unknown trailer, i.e. they neither ack
+/// the message nor recognise it as a chunk, but deliver it as is. Parties
+/// must thus all support chunks before any sends them.
+const CHUNK: u8 = 2;
 
 /// Meta information appended at the end of a message.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -18,6 +24,13 @@ pub enum Trailer {
         /// The slot the message corresponds to.
         slot: Slot,
     },
+    /// Like `Std`, but the message is a chunk of a coded broadcast.
+    Chunk {
+        /// The slot the message corresponds to.
+        slot: Slot,
+        /// The message ID.
+        id: MsgId,
+    },
     Unknown,
 }
 
@@ -32,17 +45,19 @@ impl Trailer {
         let trailer_len = bytes[len - 1];
         let trailer_typ = bytes[len - 2];
         match trailer_typ {
-            STD => {
+            STD | CHUNK => {
                 if trailer_len != 16 || len < 18 {
                     return None;
                 }
                 let id = u64::from_be_bytes(bytes[len - 10..len - 2].try_into().ok()?);
                 let slot = u64::from_be_bytes(bytes[len - 18..len - 10].try_into().ok()?);
                 bytes.truncate(len - 18);
-                Some(Self::Std {
-                    slot: Slot(slot),
-                    id: MsgId(id),
-                })
+                let (slot, id) = (Slot(slot), MsgId(id));
+                if trailer_typ == STD {
+                    Some(Self::Std { slot, id })
+                } else {
+                    Some(Self::Chunk { slot, id })
+                }
             },
             NO_ACK => {
                 if trailer_len != 8 || len < 10 {
@@ -65,11 +80,15 @@ impl Trailer {
 
     pub(crate) fn to_bytes(self) -> TrailerBytes {
         match self {
-            Self::Std { slot, id } => {
+            Self::Std { slot, id } | Self::Chunk { slot, id } => {
                 let mut buf = [0; 18];
                 buf[..8].copy_from_slice(&slot.0.to_be_bytes()[..]);
                 buf[8..16].copy_from_slice(&id.0.to_be_bytes()[..]);
-                buf[16] = STD;
+                buf[16] = if matches!(self, Self::Std { .. }) {
+                    STD
+                } else {
+                    CHUNK
+                };
                 buf[17] = 16;
                 TrailerBytes::Std(buf)
             },
@@ -107,19 +126,23 @@ mod tests {
     use bytes::Bytes;
     use quickcheck::{Arbitrary, Gen, quickcheck};
 
-    use super::{NO_ACK, STD, Trailer};
+    use super::{CHUNK, NO_ACK, STD, Trailer};
     use crate::msg::{MsgId, Slot};
 
     impl Arbitrary for Trailer {
         fn arbitrary(g: &mut Gen) -> Self {
-            match bool::arbitrary(g) {
-                true => Self::Std {
+            match u8::arbitrary(g) % 3 {
+                0 => Self::Std {
                     slot: Slot(u64::arbitrary(g)),
                     id: MsgId(u64::arbitrary(g)),
                 },
-                false => Self::NoAck {
+                1 => Self::NoAck {
                     slot: Slot(u64::arbitrary(g)),
                 },
+                _ => Self::Chunk {
+                    slot: Slot(u64::arbitrary(g)),
+                    id: MsgId(u64::arbitrary(g)),
+                },
             }
         }
     }
@@ -136,7 +159,7 @@ mod tests {
             let mut bytes: Vec<u8> = (0..body_len).map(|_| u8::arbitrary(g)).collect();
 
             let rand_typ = u8::arbitrary(g);
-            let typ = *g.choose(&[STD, NO_ACK, rand_typ]).unwrap();
+            let typ = *g.choose(&[STD, NO_ACK, CHUNK, rand_typ]).unwrap();
             let rand_len = u8::arbitrary(g);
             let trailer_len = *g.choose(&[16u8, 8, rand_len]).unwrap();
             bytes.push(typ);
@@ -176,4 +199,10 @@ mod tests {
         let mut b = Bytes::copy_from_slice(&[NO_ACK, 8]);
         assert_eq!(Trailer::from_bytes(&mut b), None);
     }
+
+    #[test]
+    fn regression_chunk_trailer_short_input_does_not_panic() {
+        let mut b = Bytes::copy_from_slice(&[CHUNK, 16]);
+        assert_eq!(Trailer::from_bytes(&mut b), None);
+    }
 }
@@ -1,3 +1,5 @@
+#[cfg(feature = "sim")]
+mod detached;
 pub mod peer;
 pub mod server;
 
@@ -5,19 +7,18 @@ use std::{fmt, ops::Deref, sync::Arc};
 
 use bon::Builder;
 use bytes::Bytes;
-use tokio::{
-    net::TcpListener,
-    sync::{
-        OwnedSemaphorePermit,
-        mpsc::{self, UnboundedReceiver, UnboundedSender},
-        oneshot, watch,
-    },
+#[cfg(feature = "sim")]
+pub use detached::{Detached, Inbound, Outgoing};
+use tokio::sync::{
+    OwnedSemaphorePermit,
+    mpsc::{self, UnboundedReceiver, UnboundedSender},
+    oneshot, watch,
 };
 use tracing::{debug, info, warn};
 
 use crate::{
-    Config, Metrics, NetAddr, Role, error::NetworkError, metrics::NoMetrics, msg::Slot,
-    net::server::Server, x25519::PublicKey,
+    Config, Metrics, NetAddr, Reputation, Role, connection::Listener, error::NetworkError,
+    metrics::NoMetrics, msg::Slot, net::server::Server, x25519::PublicKey,
 };
 
 type PeerMessage = (PublicKey, Bytes, Option<OwnedSemaphorePermit>);
@@ -57,6 +58,7 @@ impl fmt::Debug for NetworkSender {
 enum Command {
     Peer(PeerCommand),
     Send(SendCommand),
+    Reputation(PublicKey, oneshot::Sender<Option<Reputation>>),
     Shutdown(oneshot::Sender<()>),
 }
 
@@ -102,13 +104,27 @@ pub enum SendAction {
     Multicast(Vec<PublicKey>, Vec<u8>),
     /// Send a message to all peers with `Role::Active`.
     Broadcast(Vec<u8>),
+    /// Send a message to all peers with `Role::Active` as erasure-coded
+    /// chunks, which the peers relay to each other.
+    ///
+    /// Each peer is sent only a fraction of the message, which reduces the
+    /// bandwidth the sender needs for large messages. Recipients reconstruct
+    /// the message once they have enough chunks and enough of them agree to
+    /// deliver it, so either all correct recipients deliver the message or
+    /// none, provided less than a third of them is faulty and all parties
+    /// agree on the active ones. Every chunk is sent with the retry policy
+    /// of the command, relays always retry. A party may send several coded
+    /// broadcasts in the same slot, each is delivered on its own.
+    ///
+    /// Parties running a version without coded broadcasts do not understand
+    /// the chunks and hand them to their application as regular messages.
+    /// All parties need to be upgraded before coded broadcasts are used.
+    CodedBroadcast(Vec<u8>),
 }
 
 impl Network {
     pub async fn create(conf: Config) -> Result<Self, NetworkError> {
-        let listener = TcpListener::bind(conf.bind.to_string())
-            .await
-            .map_err(|e| NetworkError::Bind(conf.bind.clone(), e))?;
+        let listener = Listener::bind(&conf).await?;
 
         let addr = listener.local_addr()?;
         let node = conf.keypair.public_key();
@@ -314,6 +330,17 @@ impl NetworkSender {
         Ok(())
     }
 
+    /// Query the reputation of a party.
+    ///
+    /// Returns `None` if the party is not known.
+    pub async fn reputation(&self, key: PublicKey) -> Result<Option<Reputation>, NetworkError> {
+        let (tx, rx) = oneshot::channel();
+        self.tx
+            .send(Command::Reputation(key, tx))
+            .map_err(|_| NetworkError::ChannelClosed)?;
+        rx.await.map_err(|_| NetworkError::ChannelClosed)
+    }
+
     /// Trigger network shutdown.
     ///
     /// The returned future will resolve once the server task finished.
@@ -365,5 +392,6 @@ fn msg_bytes(cmd: &SendCommand) -> &[u8] {
         SendAction::Unicast(_, b) => b,
         SendAction::Multicast(_, b) => b,
         SendAction::Broadcast(b) => b,
+        SendAction::CodedBroadcast(b) => b,
     }
 }
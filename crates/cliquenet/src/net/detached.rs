@@ -0,0 +1,203 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use bytes::Bytes;
+use tokio::sync::{
+    mpsc::{self, UnboundedReceiver, UnboundedSender},
+    watch,
+};
+use tracing::debug;
+
+use super::{Command, PeerCommand, PeerMessage, SendAction};
+use crate::{
+    Config, Network, NetworkReceiver, NetworkSender, Reputation, Role, error::NetworkError,
+    metrics::NoMetrics, msg::Slot, x25519::PublicKey,
+};
+
+/// The transport side of a [`Network`] created with [`Network::detached`].
+///
+/// Instead of a server task talking to peers over TCP, whoever holds this
+/// value decides when and if messages reach their destination. This allows
+/// connecting networks in-process, e.g. in a simulator with virtual time.
+///
+/// Broadcast and multicast are resolved against the peers and roles of the
+/// network, mirroring the server, so every [`Outgoing`] message lists its
+/// concrete recipients, including the sender itself where applicable.
+#[derive(Debug)]
+pub struct Detached {
+    key: PublicKey,
+    role: Role,
+    peers: BTreeMap<PublicKey, Role>,
+    obound: UnboundedReceiver<Command>,
+    ibound: Inbound,
+    next_slot: watch::Receiver<Slot>,
+}
+
+/// A message sent by the application of a detached network.
+#[derive(Debug, Clone)]
+pub struct Outgoing {
+    pub slot: Slot,
+    pub to: Vec<PublicKey>,
+    pub msg: Bytes,
+}
+
+/// Handle to deliver messages to the application of a detached network.
+#[derive(Debug, Clone)]
+pub struct Inbound {
+    tx: UnboundedSender<PeerMessage>,
+}
+
+impl Network {
+    /// Create a network without a server task.
+    ///
+    /// Nothing is bound to the configured address. All messages sent are
+    /// handed to the returned [`Detached`] value.
+    pub fn detached(conf: Config) -> (Self, Detached) {
+        let node = conf.keypair.public_key();
+
+        let (otx, orx) = mpsc::unbounded_channel();
+        let (itx, irx) = mpsc::unbounded_channel();
+        let (etx, erx) = watch::channel(Slot::MIN);
+
+        let peers = conf
+            .parties
+            .iter()
+            .filter(|(k, _)| *k != node)
+            .map(|(k, _)| (*k, Role::Active))
+            .collect();
+
+        let metr = conf.metrics.clone().unwrap_or_else(|| Arc::new(NoMetrics));
+
+        let net = Self {
+            recv: NetworkReceiver { rx: irx },
+            send: NetworkSender {
+                conf: Arc::new(conf),
+                node,
+                tx: otx,
+                next_slot: etx,
+                metrics: metr,
+            },
+        };
+
+        let det = Detached {
+            key: node,
+            role: Role::Active,
+            peers,
+            obound: orx,
+            ibound: Inbound { tx: itx },
+            next_slot: erx,
+        };
+
+        (net, det)
+    }
+}
+
+impl Detached {
+    pub fn public_key(&self) -> PublicKey {
+        self.key
+    }
+
+    /// Get a handle to deliver messages to this network's application.
+    pub fn inbound(&self) -> Inbound {
+        self.ibound.clone()
+    }
+
+    /// The lower bound below which the application garbage collected slots.
+    pub fn lower_bound(&self) -> Slot {
+        *self.next_slot.borrow()
+    }
+
+    /// Wait for the next message the application sends.
+    ///
+    /// Peer updates are applied in passing. Returns `None` after shutdown or
+    /// once all senders have been dropped.
+    pub async fn next(&mut self) -> Option<Outgoing> {
+        loop {
+            match self.obound.recv().await? {
+                Command::Peer(cmd) => self.update_peers(cmd),
+                Command::Send(cmd) => {
+                    if cmd.slot < self.lower_bound() {
+                        continue;
+                    }
+                    let (to, msg) = match cmd.action {
+                        SendAction::Unicast(to, m) => (vec![to], m),
+                        SendAction::Multicast(to, m) => (to, m),
+                        // Coding only matters on the wire, hence coded broadcasts
+                        // are delivered like plain ones.
+                        SendAction::Broadcast(m) | SendAction::CodedBroadcast(m) => {
+                            let to = self
+                                .role
+                                .is_active()
+                                .then_some(self.key)
+                                .into_iter()
+                                .chain(
+                                    self.peers
+                                        .iter()
+                                        .filter(|(_, r)| r.is_active())
+                                        .map(|(k, _)| *k),
+                                )
+                                .collect();
+                            (to, m)
+                        },
+                    };
+                    return Some(Outgoing {
+                        slot: cmd.slot,
+                        to,
+                        msg: msg.into(),
+                    });
+                },
+                Command::Reputation(key, tx) => {
+                    // Nothing can misbehave without a server.
+                    let r = self.peers.contains_key(&key).then(Reputation::default);
+                    let _ = tx.send(r);
+                },
+                Command::Shutdown(tx) => {
+                    debug!(node = %self.key, "detached network shutting down");
+                    self.obound.close();
+                    let _ = tx.send(());
+                    return None;
+                },
+            }
+        }
+    }
+
+    fn update_peers(&mut self, cmd: PeerCommand) {
+        match cmd {
+            PeerCommand::Add(role, peers) => {
+                for (k, _) in peers {
+                    if k == self.key {
+                        self.role = role
+                    } else {
+                        self.peers.insert(k, role);
+                    }
+                }
+            },
+            PeerCommand::Remove(peers) => {
+                for k in &peers {
+                    if *k == self.key {
+                        self.role = Role::Passive
+                    } else {
+                        self.peers.remove(k);
+                    }
+                }
+            },
+            PeerCommand::Assign(role, peers) => {
+                for k in peers {
+                    if k == self.key {
+                        self.role = role
+                    } else if let Some(r) = self.peers.get_mut(&k) {
+                        *r = role
+                    }
+                }
+            },
+        }
+    }
+}
+
+impl Inbound {
+    /// Deliver a message from `src` to the application.
+    pub fn deliver(&self, src: PublicKey, msg: Bytes) -> Result<(), NetworkError> {
+        self.tx
+            .send((src, msg, None))
+            .map_err(|_| NetworkError::ChannelClosed)
+    }
+}
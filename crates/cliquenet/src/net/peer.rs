@@ -1,3 +1,4 @@
+mod quic;
 #[cfg(test)]
 mod tests;
 
@@ -18,8 +19,8 @@ use tokio_util::sync::CancellationToken;
 use tracing::{info, trace, warn};
 
 use crate::{
-    Config, Metrics, PublicKey,
-    connection::Connection,
+    Config, Metrics, PublicKey, coded,
+    connection::{Connection, Stream},
     delay::DelayQueue,
     error::{Empty, NetworkError},
     msg::{
@@ -57,6 +58,9 @@ pub struct Peer {
     /// The channel over which to deliver inbound messages to the application.
     tx: UnboundedSender<PeerMessage>,
 
+    /// The channel over which to deliver chunks of coded broadcasts.
+    chunks: UnboundedSender<PeerMessage>,
+
     /// A healthcheck countdown.
     ///
     /// When dropped to 0 the connection should be replaced.
@@ -64,7 +68,7 @@ pub struct Peer {
 
     /// The true max. message size.
     ///
-    /// It accounts for the additional `Trailer` bytes.
+    /// It accounts for the additional `Trailer` and chunk bytes.
     max_message_size: usize,
 
     metrics: Arc<dyn Metrics>,
@@ -92,16 +96,19 @@ impl Peer {
         messages: Queue<(RetryPolicy, Bytes)>,
         retry: DelayQueue,
         inbound: UnboundedSender<PeerMessage>,
+        chunks: UnboundedSender<PeerMessage>,
         metrics: Arc<dyn Metrics>,
     ) -> Self {
         Self {
             max_message_size: config
                 .max_message_size
                 .get()
-                .saturating_add(Trailer::MAX_SIZE),
+                .saturating_add(Trailer::MAX_SIZE)
+                .saturating_add(coded::MAX_OVERHEAD),
             conf: config.clone(),
             budget: Budget::new(budget),
             tx: inbound,
+            chunks,
             msgs: messages,
             retry,
             countdown: Countdown::new(),
@@ -113,11 +120,7 @@ impl Peer {
     ///
     /// This method continues until an error occurs, after which callers may
     /// want to reconnect and resume peer operation with a new `Connection`.
-    pub async fn start(
-        &mut self,
-        mut conn: Connection,
-        cancel: CancellationToken,
-    ) -> Result<Empty> {
+    pub async fn start(&mut self, conn: Connection, cancel: CancellationToken) -> Result<Empty> {
         /// Messages are broken into frames and each frame has a header.
         ///
         /// The `ReadState` tracks what we have received from the remote.
@@ -189,6 +192,18 @@ impl Peer {
             return Err(NetworkError::PeerInterrupt);
         }
 
+        let Connection {
+            key,
+            addr,
+            stream,
+            mut state,
+        } = conn;
+
+        let mut stream = match stream {
+            Stream::Tcp(stream) => stream,
+            Stream::Quic(link) => return self.start_quic(key, addr, link, cancel).await,
+        };
+
         let mut now = Instant::now();
 
         // Reset messages scheduled for another send.
@@ -236,8 +251,8 @@ impl Peer {
         loop {
             trace!(
                 name     = %self.conf.name,
-                peer     = %conn.key,
-                addr     = %conn.addr,
+                peer     = %key,
+                addr     = %addr,
                 writing  = %!wstate.is_idle(),
                 acks     = %obound_acks.len(),
                 retries  = %self.retry.len(),
@@ -253,7 +268,7 @@ impl Peer {
                 // malicious peer never sends ACKs, which would cause our
                 // delay queue to grow unbounded.
                 m = self.msgs.next(), if wstate.is_idle() && self.retry.len() < self.conf.peer_budget.get() => {
-                    trace!(name = %self.conf.name, peer = %conn.key, "next outbound message");
+                    trace!(name = %self.conf.name, peer = %key, "next outbound message");
                     let (slot, id, (policy, bytes)) = m;
                     if policy.is_retry() {
                         self.retry.add(slot, id, bytes.clone(), policy, Instant::now());
@@ -263,7 +278,7 @@ impl Peer {
                     wstate = WriteState::data_frame(
                         &bytes[..chunk],
                         chunk < bytes.len(),
-                        &mut conn.state,
+                        &mut state,
                         &mut wbuf
                     )?;
                     obound_msg = Some((policy, bytes, chunk))
@@ -271,9 +286,9 @@ impl Peer {
 
                 // Pick up an ACK and send it if possible.
                 () = ready(()), if wstate.is_idle() && !obound_acks.is_empty() => {
-                    trace!(name = %self.conf.name, peer = %conn.key, "next outbound ack");
+                    trace!(name = %self.conf.name, peer = %key, "next outbound ack");
                     let ack = obound_acks.pop_front().expect("obound_acks is not empty");
-                    wstate = WriteState::ack_frame(ack, &mut conn.state, &mut wbuf)?
+                    wstate = WriteState::ack_frame(ack, &mut state, &mut wbuf)?
                 }
 
                 // If requested, interrupt all I/O processing.
@@ -281,19 +296,19 @@ impl Peer {
                 // Once a peer has been interrupted, its connection needs to be
                 // replaced before calling start again.
                 _ = cancel.cancelled() => {
-                    info!(name = %self.conf.name, peer = %conn.key, "peer interrupt");
+                    info!(name = %self.conf.name, peer = %key, "peer interrupt");
                     return Err(NetworkError::PeerInterrupt)
                 }
 
                 // Update time and metrics.
                 t = clock.tick() => {
                     now = t;
-                    self.update_metrics(&conn.key);
+                    self.update_metrics(&key);
                 }
 
                 // If messages should be re-sent and we can do so, send it:
                 () = ready(()), if wstate.is_idle() && self.retry.is_due(now) => {
-                    trace!(name = %self.conf.name, peer = %conn.key, "resending message");
+                    trace!(name = %self.conf.name, peer = %key, "resending message");
                     let Some((bytes, policy)) = self.retry.due(now) else {
                         continue
                     };
@@ -301,21 +316,21 @@ impl Peer {
                     wstate = WriteState::data_frame(
                         &bytes[..chunk],
                         chunk < bytes.len(),
-                        &mut conn.state,
+                        &mut state,
                         &mut wbuf
                     )?;
                     obound_msg = Some((policy, bytes, chunk))
                 }
 
                 // Continue an ongoing write operation.
-                r = conn.stream.writable(), if !wstate.is_idle() => {
-                    trace!(name = %self.conf.name, peer = %conn.key, "continue writing");
+                r = stream.writable(), if !wstate.is_idle() => {
+                    trace!(name = %self.conf.name, peer = %key, "continue writing");
                     if let Err(e) = r {
                         return Err(e.into())
                     }
                     match &mut wstate {
                         WriteState::Ack { off, len } => {
-                            match conn.stream.try_write(&wbuf[*off..*len]) {
+                            match stream.try_write(&wbuf[*off..*len]) {
                                 Ok(n) => {
                                     *off += n;
                                     if *off < *len {
@@ -327,7 +342,7 @@ impl Peer {
                                             wstate = WriteState::data_frame(
                                                 &bytes[*chunk..end],
                                                 end < bytes.len(),
-                                                &mut conn.state,
+                                                &mut state,
                                                 &mut wbuf
                                             )?;
                                             *chunk = end;
@@ -348,21 +363,21 @@ impl Peer {
                             }
                         }
                         WriteState::Data { off, len } => {
-                            match conn.stream.try_write(&wbuf[*off..*len]) {
+                            match stream.try_write(&wbuf[*off..*len]) {
                                 Ok(n) => {
                                     *off += n;
                                     if *off < *len {
                                         continue
                                     }
                                     if let Some(ack) = obound_acks.pop_front() {
-                                        wstate = WriteState::ack_frame(ack, &mut conn.state, &mut wbuf)?
+                                        wstate = WriteState::ack_frame(ack, &mut state, &mut wbuf)?
                                     } else if let Some((policy, bytes, chunk)) = &mut obound_msg {
                                         if *chunk < bytes.len() {
                                             let end = min(*chunk + MAX_PAYLOAD_SIZE, bytes.len());
                                             wstate = WriteState::data_frame(
                                                 &bytes[*chunk..end],
                                                 end < bytes.len(),
-                                                &mut conn.state,
+                                                &mut state,
                                                 &mut wbuf
                                             )?;
                                             *chunk = end;
@@ -391,7 +406,7 @@ impl Peer {
                 // The countdown is started after writing a message and reset
                 // when we received a frame.
                 () = &mut self.countdown => {
-                    trace!(name = %self.conf.name, peer = %conn.key, "read timeout");
+                    trace!(name = %self.conf.name, peer = %key, "read timeout");
                     return Err(NetworkError::Timeout)
                 }
 
@@ -401,7 +416,7 @@ impl Peer {
                 // available before we can continue to read from the socket (and
                 // eventually deliver the message to the application).
                 p = self.budget.0.clone().acquire_owned(), if read_permit.is_none() => {
-                    trace!(name = %self.conf.name, peer = %conn.key, "next read permit");
+                    trace!(name = %self.conf.name, peer = %key, "next read permit");
                     read_permit = Some(p.map_err(|_| NetworkError::BudgetClosed)?);
                 }
 
@@ -413,17 +428,17 @@ impl Peer {
                 // because if the remote does not or can not read what we write
                 // but keeps sending us data we would accumulate ACKs without
                 // bound.
-                r = conn.stream.readable(), if read_permit.is_some() => {
-                    trace!(name = %self.conf.name, peer = %conn.key, "continue reading");
+                r = stream.readable(), if read_permit.is_some() => {
+                    trace!(name = %self.conf.name, peer = %key, "continue reading");
                     if let Err(e) = r {
                         return Err(e.into())
                     }
                     if obound_acks.len() > self.conf.peer_budget.get() {
-                        return Err(NetworkError::TooManyPendingAcks(conn.key))
+                        return Err(NetworkError::TooManyPendingAcks(key))
                     }
                     match &mut rstate {
                         ReadState::Header { off, buf } => {
-                            match conn.stream.try_read(&mut buf[*off..]) {
+                            match stream.try_read(&mut buf[*off..]) {
                                 Ok(0) => {
                                     let e = io::ErrorKind::UnexpectedEof.into();
                                     return Err(NetworkError::Io(e))
@@ -442,8 +457,8 @@ impl Peer {
                                                 warn!(
                                                     name = %self.conf.name,
                                                     node = %self.conf.keypair.public_key(),
-                                                    peer = %conn.key,
-                                                    addr = %conn.addr,
+                                                    peer = %key,
+                                                    %addr,
                                                     "ACK header marked as partial"
                                                 );
                                                 return Err(NetworkError::InvalidAck)
@@ -452,8 +467,8 @@ impl Peer {
                                                 warn!(
                                                     name = %self.conf.name,
                                                     node = %self.conf.keypair.public_key(),
-                                                    peer = %conn.key,
-                                                    addr = %conn.addr,
+                                                    peer = %key,
+                                                    %addr,
                                                     len  = %hdr.len(),
                                                     "ACK header length too large"
                                                 );
@@ -474,7 +489,7 @@ impl Peer {
                             }
                         }
                         ReadState::Frame { hdr, typ, off, buf } => {
-                            match conn.stream.try_read(&mut buf[*off..]) {
+                            match stream.try_read(&mut buf[*off..]) {
                                 Ok(0) => {
                                     let e = io::ErrorKind::UnexpectedEof.into();
                                     return Err(NetworkError::Io(e))
@@ -491,7 +506,7 @@ impl Peer {
                                             let i = ibound_msg.len();
                                             ibound_msg.resize(i + n, 0);
 
-                                            let n = conn.state.read_message(buf, &mut ibound_msg[i..])?;
+                                            let n = state.read_message(buf, &mut ibound_msg[i..])?;
                                             ibound_msg.truncate(i + n);
 
                                             if ibound_msg.len() > self.max_message_size {
@@ -504,35 +519,41 @@ impl Peer {
                                                     warn!(
                                                         name = %self.conf.name,
                                                         node = %self.conf.keypair.public_key(),
-                                                        peer = %conn.key,
-                                                        addr = %conn.addr,
+                                                        peer = %key,
+                                                        %addr,
                                                         "invalid trailer"
                                                     );
                                                     return Err(NetworkError::InvalidTrailer);
                                                 };
-                                                match t {
-                                                    Trailer::Std { slot, id } =>
-                                                        obound_acks.push_back(Ack::from((slot, id))),
-                                                    Trailer::NoAck { slot: _ } => (),
-                                                    Trailer::Unknown => ()
-                                                }
+                                                let tx = match t {
+                                                    Trailer::Std { slot, id } => {
+                                                        obound_acks.push_back(Ack::from((slot, id)));
+                                                        &self.tx
+                                                    }
+                                                    Trailer::Chunk { slot, id } => {
+                                                        obound_acks.push_back(Ack::from((slot, id)));
+                                                        &self.chunks
+                                                    }
+                                                    Trailer::NoAck { slot: _ } => &self.tx,
+                                                    Trailer::Unknown => &self.tx
+                                                };
                                                 let p = read_permit.take();
                                                 debug_assert!(p.is_some());
-                                                if self.tx.send((conn.key, msg, p)).is_err() {
+                                                if tx.send((key, msg, p)).is_err() {
                                                     return Err(NetworkError::ChannelClosed)
                                                 }
                                                 trace!(
                                                     name = %self.conf.name,
                                                     node = %self.conf.keypair.public_key(),
-                                                    peer = %conn.key,
-                                                    addr = %conn.addr,
+                                                    peer = %key,
+                                                    %addr,
                                                     "message delivered"
                                                 );
                                             }
                                             rstate = ReadState::Header { off: 0, buf: [0; _] };
                                         }
                                         FrameType::Ack => {
-                                            let n = conn.state.read_message(buf, &mut abuf)?;
+                                            let n = state.read_message(buf, &mut abuf)?;
                                             let Ok(a) = Ack::try_from(&abuf[..n]) else {
                                                 return Err(NetworkError::InvalidAck)
                                             };
@@ -0,0 +1,254 @@
+use std::{future::ready, io, net::SocketAddr, sync::Arc, time::Duration};
+
+use bytes::{Bytes, BytesMut};
+use quinn::{RecvStream, SendStream};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    select,
+    sync::{
+        OwnedSemaphorePermit, Semaphore,
+        mpsc::{self, UnboundedReceiver, UnboundedSender, error::TrySendError},
+    },
+    task::JoinSet,
+    time::{Instant, MissedTickBehavior, interval},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, trace, warn};
+
+use super::{Peer, Result};
+use crate::{
+    PublicKey,
+    delay::DelayQueue,
+    error::{Empty, NetworkError},
+    msg::{Ack, Slot, Trailer},
+    net::PeerMessage,
+    quic::{LANES, Link, lane},
+};
+
+/// A message handed to a lane, with the permit it holds until written.
+type LaneItem = (Bytes, OwnedSemaphorePermit);
+
+impl Peer {
+    /// Start I/O with a peer connected over QUIC.
+    ///
+    /// Like [`Peer::start`], but messages are written to the lane of their
+    /// slot, each lane by a task of its own, so that a lane waiting for the
+    /// remote does not hold up the others. Streams of the remote are read
+    /// by a task each. Liveness is left to the QUIC session, which times
+    /// out if the remote stops responding.
+    pub(super) async fn start_quic(
+        &mut self,
+        key: PublicKey,
+        addr: SocketAddr,
+        link: Link,
+        cancel: CancellationToken,
+    ) -> Result<Empty> {
+        let Link {
+            session,
+            send,
+            recv,
+        } = link;
+
+        let mut now = Instant::now();
+
+        // Reset messages scheduled for another send.
+        self.retry.reset(now);
+
+        // Stream tasks. If one fails, the session is done.
+        let mut tasks = JoinSet::new();
+
+        tasks.spawn(read_acks(recv, self.retry.clone()));
+
+        let peer_budget = self.conf.peer_budget.get();
+
+        // ACKs of messages we received, bounded like in `Peer::start`.
+        let (ack_tx, ack_rx) = mpsc::channel(peer_budget);
+        tasks.spawn(write_acks(send, ack_rx));
+
+        let lanes = (0..LANES)
+            .map(|_| {
+                let (tx, rx) = mpsc::unbounded_channel();
+                tasks.spawn(write_lane(session.clone(), rx));
+                tx
+            })
+            .collect::<Vec<UnboundedSender<LaneItem>>>();
+
+        // Limits how many messages may wait in lanes to be written.
+        let writes = Arc::new(Semaphore::new(peer_budget));
+        let mut write_permit: Option<OwnedSemaphorePermit> = None;
+
+        // Measure time.
+        let mut clock = interval(Duration::from_secs(1));
+        clock.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        loop {
+            trace!(
+                name     = %self.conf.name,
+                peer     = %key,
+                addr     = %addr,
+                retries  = %self.retry.len(),
+                streams  = %tasks.len(),
+                "entering event loop"
+            );
+
+            select! {
+                p = writes.clone().acquire_owned(), if write_permit.is_none() => {
+                    write_permit = Some(p.map_err(|_| NetworkError::BudgetClosed)?);
+                }
+
+                // Hand the next message to its lane.
+                //
+                // As in `Peer::start` we stop if we expect too many ACKs.
+                m = self.msgs.next(), if write_permit.is_some() && self.retry.len() < peer_budget => {
+                    trace!(name = %self.conf.name, peer = %key, "next outbound message");
+                    let (slot, id, (policy, bytes)) = m;
+                    if policy.is_retry() {
+                        self.retry.add(slot, id, bytes.clone(), policy, Instant::now());
+                    }
+                    self.msgs.remove(slot, id);
+                    let p = write_permit.take().expect("write permit is some");
+                    // A lane only goes away with an error, which we see below.
+                    let _ = lanes[lane(slot)].send((bytes, p));
+                }
+
+                // If messages should be re-sent, hand them to their lane.
+                () = ready(()), if write_permit.is_some() && self.retry.is_due(now) => {
+                    trace!(name = %self.conf.name, peer = %key, "resending message");
+                    let Some((bytes, _)) = self.retry.due(now) else {
+                        continue
+                    };
+                    let Some(slot) = slot_of(&bytes) else {
+                        continue
+                    };
+                    let p = write_permit.take().expect("write permit is some");
+                    let _ = lanes[lane(slot)].send((bytes, p));
+                }
+
+                s = session.accept_uni() => {
+                    trace!(name = %self.conf.name, peer = %key, "accepted stream");
+                    tasks.spawn(read_lane(
+                        key,
+                        s?,
+                        self.budget.0.clone(),
+                        self.max_message_size,
+                        self.tx.clone(),
+                        self.chunks.clone(),
+                        ack_tx.clone(),
+                    ));
+                }
+
+                Some(r) = tasks.join_next() => match r {
+                    Ok(Ok(())) => {}
+                    Ok(Err(err)) => return Err(err),
+                    Err(err) => return Err(io::Error::from(err).into()),
+                },
+
+                // If requested, interrupt all I/O processing.
+                _ = cancel.cancelled() => {
+                    info!(name = %self.conf.name, peer = %key, "peer interrupt");
+                    return Err(NetworkError::PeerInterrupt)
+                }
+
+                // Update time and metrics.
+                t = clock.tick() => {
+                    now = t;
+                    self.update_metrics(&key);
+                }
+            }
+        }
+    }
+}
+
+/// Write the messages of a lane to a stream, opened on first use.
+async fn write_lane(session: quinn::Connection, mut rx: UnboundedReceiver<LaneItem>) -> Result<()> {
+    let mut stream: Option<SendStream> = None;
+    while let Some((msg, _permit)) = rx.recv().await {
+        let s = match &mut stream {
+            Some(s) => s,
+            None => stream.insert(session.open_uni().await?),
+        };
+        let len = u32::try_from(msg.len()).map_err(|_| NetworkError::MessageTooLarge)?;
+        s.write_u32(len).await?;
+        AsyncWriteExt::write_all(s, &msg).await?;
+    }
+    Ok(())
+}
+
+/// Read messages from a stream of the remote and deliver them.
+///
+/// A message is only read once there is budget to deliver it.
+async fn read_lane(
+    key: PublicKey,
+    mut stream: RecvStream,
+    budget: Arc<Semaphore>,
+    max_message_size: usize,
+    tx: UnboundedSender<PeerMessage>,
+    chunks: UnboundedSender<PeerMessage>,
+    acks: mpsc::Sender<Ack>,
+) -> Result<()> {
+    loop {
+        let permit = budget
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|_| NetworkError::BudgetClosed)?;
+        let len = match AsyncReadExt::read_u32(&mut stream).await {
+            Ok(len) => len as usize,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        if len > max_message_size {
+            return Err(NetworkError::MessageTooLarge);
+        }
+        let mut buf = BytesMut::zeroed(len);
+        AsyncReadExt::read_exact(&mut stream, &mut buf).await?;
+        let mut msg = buf.freeze();
+        let Some(t) = Trailer::from_bytes(&mut msg) else {
+            warn!(peer = %key, "invalid trailer");
+            return Err(NetworkError::InvalidTrailer);
+        };
+        if let Trailer::Std { slot, id } | Trailer::Chunk { slot, id } = t {
+            acks.try_send(Ack::from((slot, id))).map_err(|e| match e {
+                TrySendError::Full(_) => NetworkError::TooManyPendingAcks(key),
+                TrySendError::Closed(_) => NetworkError::ChannelClosed,
+            })?;
+        }
+        let tx = if matches!(t, Trailer::Chunk { .. }) {
+            &chunks
+        } else {
+            &tx
+        };
+        if tx.send((key, msg, Some(permit))).is_err() {
+            return Err(NetworkError::ChannelClosed);
+        }
+        trace!(peer = %key, "message delivered");
+    }
+}
+
+/// Write ACKs of the messages we received to the control stream.
+async fn write_acks(mut stream: SendStream, mut acks: mpsc::Receiver<Ack>) -> Result<()> {
+    while let Some(a) = acks.recv().await {
+        AsyncWriteExt::write_all(&mut stream, &a.0).await?;
+    }
+    Ok(())
+}
+
+/// Read ACKs of the messages we sent from the control stream.
+async fn read_acks(mut stream: RecvStream, retry: DelayQueue) -> Result<()> {
+    let mut a = Ack([0; 16]);
+    loop {
+        AsyncReadExt::read_exact(&mut stream, &mut a.0).await?;
+        let (s, i) = a.into();
+        retry.remove(s, i);
+    }
+}
+
+/// The slot of a message, given by its trailer.
+fn slot_of(msg: &Bytes) -> Option<Slot> {
+    match Trailer::from_bytes(&mut msg.clone())? {
+        Trailer::Std { slot, .. } | Trailer::Chunk { slot, .. } | Trailer::NoAck { slot } => {
+            Some(slot)
+        },
+        Trailer::Unknown => None,
+    }
+}
@@ -10,7 +10,7 @@ use tokio_util::sync::CancellationToken;
 
 use crate::{
     Config, Keypair, NetAddr, PublicKey,
-    connection::Connection,
+    connection::{Connection, Dialer, Incoming},
     delay::DelayQueue,
     error::NetworkError,
     metrics::NoMetrics,
@@ -51,13 +51,15 @@ async fn connection_pair(
     let (cb, ca) = tokio::join!(
         async {
             let (stream, _) = listener.accept().await.unwrap();
-            let mut conn = Connection::accept(conf_b, stream).await.unwrap();
+            let mut conn = Connection::accept(conf_b, Incoming::Tcp(stream))
+                .await
+                .unwrap();
             let h = conn.recv_hello().await.unwrap();
             assert!(h.is_ok());
             conn.send_hello(Hello::Ok).await.unwrap();
             conn
         },
-        Connection::connect(conf_a, pkb, addr),
+        Connection::connect(conf_a, Dialer::Tcp, pkb, addr),
     );
     (ca, cb)
 }
@@ -79,7 +81,8 @@ fn make_peer(conf: Arc<Config>, budget: usize) -> (Peer, Rx) {
         .budget(NonZeroUsize::new(budget).unwrap())
         .messages(Queue::new())
         .retry(DelayQueue::new(conf))
-        .inbound(tx)
+        .inbound(tx.clone())
+        .chunks(tx)
         .metrics(Arc::new(NoMetrics))
         .build();
     (peer, rx)
@@ -361,7 +364,8 @@ async fn receive_timeout() {
         .budget(NonZeroUsize::new(10).unwrap())
         .messages(outbox.clone())
         .retry(DelayQueue::new(conf_a))
-        .inbound(tx)
+        .inbound(tx.clone())
+        .chunks(tx)
         .metrics(Arc::new(NoMetrics))
         .build();
 
@@ -458,7 +462,8 @@ async fn channel_closed() {
         .budget(NonZeroUsize::new(10).unwrap())
         .messages(outbox_b)
         .retry(DelayQueue::new(conf_b))
-        .inbound(tx_b)
+        .inbound(tx_b.clone())
+        .chunks(tx_b)
         .metrics(Arc::new(NoMetrics))
         .build();
     drop(rx_b);
@@ -502,7 +507,8 @@ async fn connection_reset() {
         .budget(NonZeroUsize::new(10).unwrap())
         .messages(outbox)
         .retry(DelayQueue::new(conf_a))
-        .inbound(tx)
+        .inbound(tx.clone())
+        .chunks(tx)
         .metrics(Arc::new(NoMetrics))
         .build();
 
@@ -828,7 +834,8 @@ async fn retry_after_reconnect() {
         .budget(NonZeroUsize::new(10).unwrap())
         .messages(outbox_a.clone())
         .retry(DelayQueue::new(conf_a.clone()))
-        .inbound(tx_a)
+        .inbound(tx_a.clone())
+        .chunks(tx_a)
         .metrics(Arc::new(NoMetrics))
         .build();
 
@@ -883,7 +890,8 @@ async fn retry_after_reconnect_multiple() {
         .budget(NonZeroUsize::new(10).unwrap())
         .messages(outbox_a.clone())
         .retry(DelayQueue::new(conf_a.clone()))
-        .inbound(tx_a)
+        .inbound(tx_a.clone())
+        .chunks(tx_a)
         .metrics(Arc::new(NoMetrics))
         .build();
 
@@ -1,26 +1,37 @@
-use std::{collections::HashMap, mem, net::IpAddr, sync::Arc, time::Duration};
+#[cfg(test)]
+mod tests;
+
+use std::{
+    collections::HashMap,
+    mem,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
 
 use bytes::{Bytes, BytesMut};
 use tokio::{
-    net::{TcpListener, TcpStream},
     select, spawn,
     sync::{
-        mpsc::{UnboundedReceiver, UnboundedSender},
+        mpsc::{self, UnboundedReceiver, UnboundedSender},
         watch,
     },
     task::{JoinHandle, JoinSet},
+    time::{Instant, sleep},
 };
 use tokio_util::{sync::CancellationToken, task::JoinMap};
 use tracing::{debug, error, info, trace, warn};
 
 use crate::{
     Config, Metrics, NetAddr, PublicKey, Role,
-    connection::Connection,
+    coded::{self, Decoder, Message, Status},
+    connection::{Connection, Dialer, Incoming, Listener},
     delay::DelayQueue,
     error::NetworkError,
     msg::{MsgId, Slot, Trailer, hello::Hello},
     net::{Command, PeerCommand, PeerMessage, RetryPolicy, SendAction, peer::Peer},
     queue::Queue,
+    reputation::{Offence, Standing},
     util::until,
 };
 
@@ -33,12 +44,20 @@ pub struct Server {
     parties: HashMap<PublicKey, Party>,
     ibound: UnboundedSender<PeerMessage>,
     obound: UnboundedReceiver<Command>,
+    chunks_tx: UnboundedSender<PeerMessage>,
+    chunks_rx: UnboundedReceiver<PeerMessage>,
+    decoder: Decoder,
     next_slot: watch::Receiver<Slot>,
-    accept_tasks: JoinSet<Result<Connection, NetworkError>>,
+    dialer: Dialer,
+    accept_tasks: JoinSet<(IpAddr, Result<Connection, NetworkError>)>,
     #[allow(clippy::type_complexity)]
     hello_tasks: JoinMap<PublicKey, Result<(Hello, Connection, Option<Hello>), NetworkError>>,
     connect_tasks: JoinMap<PublicKey, Connection>,
-    peer_tasks: JoinMap<PublicKey, Peer>,
+    peer_tasks: JoinMap<PublicKey, (Peer, Option<Offence>)>,
+    /// Standing of remote addresses which failed the handshake.
+    ///
+    /// Until the handshake completes we do not know the party's key.
+    strangers: HashMap<IpAddr, Standing>,
     metrics: Arc<dyn Metrics>,
 }
 
@@ -48,6 +67,9 @@ struct Party {
     outbox: Queue<(RetryPolicy, Bytes)>,
     retry: DelayQueue,
     peer: PeerState,
+    /// Was the current connection accepted from, rather than opened to, the party?
+    inbound: bool,
+    standing: Standing,
 }
 
 /// The states of a peer.
@@ -88,7 +110,7 @@ enum PeerState {
 impl Server {
     pub(super) fn spawn(
         conf: Arc<Config>,
-        listener: TcpListener,
+        listener: Listener,
         role: Role,
         tx: UnboundedSender<PeerMessage>,
         rx: UnboundedReceiver<Command>,
@@ -106,19 +128,29 @@ impl Server {
             })
             .collect();
 
+        // Chunks of coded broadcasts, which peers hand to us instead of the
+        // application.
+        let (ctx, crx) = mpsc::unbounded_channel();
+        let decoder = Decoder::new(conf.peer_budget.get());
+
         let this = Self {
             key: our_key,
             conf,
             role,
             ibound: tx,
             obound: rx,
+            chunks_tx: ctx,
+            chunks_rx: crx,
+            decoder,
             parties,
             accept_tasks: JoinSet::new(),
             connect_tasks: JoinMap::new(),
             hello_tasks: JoinMap::new(),
             peer_tasks: JoinMap::new(),
+            strangers: HashMap::new(),
             msgid: MsgId::new(0),
             next_slot: sx,
+            dialer: listener.dialer(),
             lower_bound: Slot::MIN,
             metrics,
         };
@@ -126,7 +158,7 @@ impl Server {
         spawn(this.run(listener))
     }
 
-    async fn run(mut self, listener: TcpListener) {
+    async fn run(mut self, listener: Listener) {
         // Connect to all peers.
         for (k, a) in self
             .parties
@@ -141,28 +173,37 @@ impl Server {
             select! {
                 x = listener.accept(), if self.accept_tasks.len() < self.conf.max_accept_tasks => {
                     match x {
-                        Ok((stream, addr)) => {
+                        Ok((incoming, addr)) => {
+                            if self.stranger_banned(addr.ip()) {
+                                debug!(
+                                    name = %self.conf.name,
+                                    node = %self.key,
+                                    %addr,
+                                    "rejecting connection of banned address"
+                                );
+                                continue
+                            }
                             debug!(
                                 name = %self.conf.name,
                                 node = %self.key,
                                 %addr,
-                                "accepted new tcp connection"
+                                "accepted new connection"
                             );
-                            self.spawn_accept(stream)
+                            self.spawn_accept(incoming, addr)
                         }
                         Err(err) => {
                             warn!(
                                 name = %self.conf.name,
                                 node = %self.key,
                                 %err,
-                                "error accepting tcp connection"
+                                "error accepting connection"
                             )
                         }
                     }
                 }
 
                 Some(h) = self.accept_tasks.join_next() => match h {
-                    Ok(Ok(conn)) => {
+                    Ok((_, Ok(conn))) => {
                         self.metrics.set(&self.key, ACCEPT_TASKS, self.accept_tasks.len());
                         if conn.key == self.key {
                             warn!(
@@ -186,6 +227,17 @@ impl Server {
                             self.spawn_hello(conn, Hello::BackOff(self.conf.backoff_duration));
                             continue
                         };
+                        if let Some(d) = party.standing.banned(Instant::now()) {
+                            info!(
+                                name = %self.conf.name,
+                                node = %self.key,
+                                peer = %conn.key,
+                                addr = %conn.addr,
+                                "rejecting connection of banned party"
+                            );
+                            self.spawn_hello(conn, Hello::BackOff(d.max(Duration::from_secs(1))));
+                            continue
+                        }
                         if party.ip_addr_mismatch(conn.addr.ip()) {
                             warn!(
                                 name = %self.conf.name,
@@ -199,9 +251,18 @@ impl Server {
                         }
                         self.spawn_hello(conn, Hello::Ok);
                     }
-                    Ok(Err(err)) => {
+                    Ok((ip, Err(err))) => {
                         self.metrics.set(&self.key, ACCEPT_TASKS, self.accept_tasks.len());
-                        warn!(name = %self.conf.name, node = %self.key, %err, "handshake failed")
+                        warn!(
+                            name = %self.conf.name,
+                            node = %self.key,
+                            %ip,
+                            %err,
+                            "handshake failed"
+                        );
+                        if let Some(o) = Offence::of_handshake_error(&err) {
+                            self.penalise_stranger(ip, o)
+                        }
                     }
                     Err(err) => {
                         self.metrics.set(&self.key, ACCEPT_TASKS, self.accept_tasks.len());
@@ -246,6 +307,29 @@ impl Server {
                             );
                             continue
                         }
+                        if party.standing.banned(Instant::now()).is_some() {
+                            debug!(
+                                name = %self.conf.name,
+                                node = %self.key,
+                                peer = %key,
+                                addr = %conn.addr,
+                                "party is banned"
+                            );
+                            continue
+                        }
+                        // A party which connects again while the connection it opened
+                        // before is live adds a little to its penalty score, so that it
+                        // is eventually banned. Reconnects after a peer failure and
+                        // simultaneous dials are no offence.
+                        if matches!(party.peer, PeerState::Connected(_))
+                            && party.inbound
+                            && self.penalise(key, Offence::Reconnect)
+                        {
+                            continue
+                        }
+                        let Some(party) = self.parties.get_mut(&key) else {
+                            continue
+                        };
                         match party.peer.take() {
                             PeerState::None => {
                                 self.connect_tasks.abort(&key);
@@ -253,18 +337,21 @@ impl Server {
                                     .config(self.conf.clone())
                                     .budget(self.conf.peer_budget)
                                     .inbound(self.ibound.clone())
+                                    .chunks(self.chunks_tx.clone())
                                     .messages(party.outbox.clone())
                                     .retry(party.retry.clone())
                                     .metrics(self.metrics.clone())
                                     .build();
                                 let cancel = CancellationToken::new();
                                 party.peer = PeerState::Connected(cancel.clone());
+                                party.inbound = true;
                                 self.spawn_peer(key, peer, conn, cancel);
                             }
                             PeerState::Reconnect(peer) => {
                                 self.connect_tasks.abort(&key);
                                 let cancel = CancellationToken::new();
                                 party.peer = PeerState::Connected(cancel.clone());
+                                party.inbound = true;
                                 self.spawn_peer(key, peer, conn, cancel);
                             }
                             PeerState::Connected(cancel) => {
@@ -278,12 +365,14 @@ impl Server {
                                     );
                                     cancel.cancel();
                                     party.peer = PeerState::Replace(conn);
+                                    party.inbound = true;
                                 } else {
                                     party.peer = PeerState::Connected(cancel);
                                 }
                             }
                             PeerState::Replace(_) => {
                                 party.peer = PeerState::Replace(conn);
+                                party.inbound = true;
                             }
                         }
                     }
@@ -295,7 +384,10 @@ impl Server {
                             peer = %key,
                             %err,
                             "hello task error"
-                        )
+                        );
+                        if let Some(o) = Offence::of_handshake_error(&err) {
+                            self.penalise(key, o);
+                        }
                     }
                     (key, Err(err)) => {
                         self.metrics.set(&self.key, HELLO_TASKS, self.hello_tasks.len());
@@ -324,23 +416,40 @@ impl Server {
                             );
                             continue
                         };
+                        if party.standing.banned(Instant::now()).is_some() {
+                            debug!(
+                                name = %self.conf.name,
+                                node = %self.key,
+                                peer = %key,
+                                addr = %conn.addr,
+                                "party is banned"
+                            );
+                            if matches!(party.peer, PeerState::None | PeerState::Reconnect(_)) {
+                                let addr = party.addr.clone();
+                                self.spawn_connect(key, addr);
+                            }
+                            continue
+                        }
                         match party.peer.take() {
                             PeerState::None => {
                                 let peer = Peer::builder()
                                     .config(self.conf.clone())
                                     .budget(self.conf.peer_budget)
                                     .inbound(self.ibound.clone())
+                                    .chunks(self.chunks_tx.clone())
                                     .messages(party.outbox.clone())
                                     .retry(party.retry.clone())
                                     .metrics(self.metrics.clone())
                                     .build();
                                 let cancel = CancellationToken::new();
                                 party.peer = PeerState::Connected(cancel.clone());
+                                party.inbound = false;
                                 self.spawn_peer(key, peer, conn, cancel);
                             }
                             PeerState::Reconnect(peer) => {
                                 let cancel = CancellationToken::new();
                                 party.peer = PeerState::Connected(cancel.clone());
+                                party.inbound = false;
                                 self.spawn_peer(key, peer, conn, cancel);
                             }
                             PeerState::Connected(cancel) => {
@@ -354,12 +463,14 @@ impl Server {
                                     );
                                     cancel.cancel();
                                     party.peer = PeerState::Replace(conn);
+                                    party.inbound = false;
                                 } else {
                                     party.peer = PeerState::Connected(cancel);
                                 }
                             }
                             PeerState::Replace(_) => {
                                 party.peer = PeerState::Replace(conn);
+                                party.inbound = false;
                             }
                         }
                     }
@@ -384,11 +495,14 @@ impl Server {
                 },
 
                 Some(p) = self.peer_tasks.join_next() => match p {
-                    (key, Ok(peer)) => {
+                    (key, Ok((peer, offence))) => {
                         self.metrics.set(&self.key, PEER_TASKS, self.peer_tasks.len());
                         if self.ibound.is_closed() {
                             return
                         }
+                        if let Some(o) = offence {
+                            self.penalise(key, o);
+                        }
                         let Some(party) = self.parties.get_mut(&key) else {
                             debug!(
                                 name = %self.conf.name,
@@ -398,7 +512,9 @@ impl Server {
                             );
                             continue
                         };
-                        if let PeerState::Replace(conn) = party.peer.take() {
+                        if let PeerState::Replace(conn) = party.peer.take()
+                            && party.standing.banned(Instant::now()).is_none()
+                        {
                             let cancel = CancellationToken::new();
                             party.peer = PeerState::Connected(cancel.clone());
                             self.spawn_peer(key, peer, conn, cancel);
@@ -442,6 +558,14 @@ impl Server {
                         party.outbox.gc(s);
                         party.retry.gc(s);
                     }
+                    self.decoder.gc(s);
+                }
+
+                Some((src, bytes, _permit)) = self.chunks_rx.recv() => {
+                    if self.on_chunk(src, bytes).is_err() {
+                        warn!(name = %self.conf.name, node = %self.key, "channel closed");
+                        return
+                    }
                 }
 
                 cmd = self.obound.recv() => {
@@ -634,6 +758,66 @@ impl Server {
                                     }
                                 }
                             }
+                            SendAction::CodedBroadcast(m) => {
+                                if cmd.slot < self.lower_bound {
+                                    continue
+                                }
+
+                                let to = self.recipients(self.key);
+                                let msgid = self.next_msgid();
+
+                                let chunks = coded::encode(self.key, cmd.slot, msgid, &m, to.len());
+                                let chunks = match chunks {
+                                    Some(c) => c,
+                                    None if to.is_empty() => Vec::new(),
+                                    None => {
+                                        warn!(
+                                            name    = %self.conf.name,
+                                            node    = %self.key,
+                                            parties = %to.len(),
+                                            "failed to encode message"
+                                        );
+                                        continue
+                                    }
+                                };
+
+                                if self.role.is_active() {
+                                    trace!(name = %self.conf.name, node = %self.key, "sending message");
+                                    if let Err(err) = self.ibound.send((self.key, m.into(), None)) {
+                                        warn!(
+                                            name = %self.conf.name,
+                                            node = %self.key,
+                                            err  = %err,
+                                            "channel closed"
+                                        );
+                                        return
+                                    }
+                                    trace!(name = %self.conf.name, node = %self.key, "message delivered");
+                                }
+
+                                for (key, chunk) in to.into_iter().zip(chunks) {
+                                    trace!(
+                                        name  = %self.conf.name,
+                                        node  = %self.key,
+                                        to    = %key,
+                                        index = %chunk.index,
+                                        "sending chunk"
+                                    );
+                                    let chunk = Message::Chunk(chunk).to_bytes();
+                                    let bytes = append_chunk_trailer(cmd.slot, msgid, chunk);
+                                    if let Some(party) = self.parties.get(&key) {
+                                        party.outbox.enqueue(cmd.slot, msgid, (cmd.retry, bytes));
+                                    }
+                                }
+                            }
+                        }
+                        Some(Command::Reputation(key, tx)) => {
+                            let now = Instant::now();
+                            let r = self
+                                .parties
+                                .get_mut(&key)
+                                .map(|p| p.standing.reputation(&self.conf, now));
+                            let _ = tx.send(r);
                         }
                         Some(Command::Shutdown(tx)) => {
                             debug!(name = %self.conf.name, node = %self.key, "shutting down");
@@ -658,17 +842,27 @@ impl Server {
             addr = %addr,
             "spawning connect task"
         );
-        let conn = Connection::connect(self.conf.clone(), key, addr);
-        self.connect_tasks.spawn(key, conn);
+        let conn = Connection::connect(self.conf.clone(), self.dialer.clone(), key, addr);
+        let ban = self
+            .parties
+            .get(&key)
+            .and_then(|p| p.standing.banned(Instant::now()));
+        self.connect_tasks.spawn(key, async move {
+            if let Some(d) = ban {
+                sleep(d).await
+            }
+            conn.await
+        });
         self.metrics.add(&key, CONNECT_ATTEMPTS, 1);
         self.metrics
             .set(&self.key, CONNECT_TASKS, self.connect_tasks.len());
     }
 
-    fn spawn_accept(&mut self, stream: TcpStream) {
-        debug!(name = %self.conf.name, node = %self.key, "spawning accept task");
-        let conn = Connection::accept(self.conf.clone(), stream);
-        self.accept_tasks.spawn(conn);
+    fn spawn_accept(&mut self, incoming: Incoming, addr: SocketAddr) {
+        debug!(name = %self.conf.name, node = %self.key, %addr, "spawning accept task");
+        let conn = Connection::accept(self.conf.clone(), incoming);
+        self.accept_tasks
+            .spawn(async move { (addr.ip(), conn.await) });
         self.metrics
             .set(&self.key, ACCEPT_TASKS, self.accept_tasks.len());
     }
@@ -733,6 +927,7 @@ impl Server {
         let addr = conn.addr;
         self.peer_tasks.spawn(key, async move {
             let Err(err) = peer.start(conn, cancel).await;
+            let offence = Offence::of_peer_error(&err);
             if !matches!(err, NetworkError::PeerInterrupt) {
                 warn!(
                     %name,
@@ -744,12 +939,219 @@ impl Server {
                 );
                 metrics.add(&key, ERRORS, 1)
             }
-            peer
+            (peer, offence)
         });
         self.metrics
             .set(&self.key, PEER_TASKS, self.peer_tasks.len());
     }
 
+    /// Penalise a party for misbehaving and ban it if its score gets too high.
+    ///
+    /// Returns true if the party has been banned as a result. We reconnect to
+    /// a banned party once its ban is over.
+    fn penalise(&mut self, key: PublicKey, o: Offence) -> bool {
+        let Some(party) = self.parties.get_mut(&key) else {
+            return false;
+        };
+        self.metrics.add(&key, o.label(), 1);
+        let ban = party.standing.offend(&self.conf, o, Instant::now());
+        self.metrics
+            .set(&key, PENALTY_SCORE, party.standing.score() as usize);
+        let Some(d) = ban else {
+            return false;
+        };
+        warn!(
+            name     = %self.conf.name,
+            node     = %self.key,
+            peer     = %key,
+            offence  = ?o,
+            duration = ?d,
+            "banning party"
+        );
+        self.metrics.add(&key, BANS, 1);
+        let addr = party.addr.clone();
+        if let PeerState::Connected(cancel) = &party.peer {
+            cancel.cancel()
+        } else if self.connect_tasks.abort(&key) {
+            self.spawn_connect(key, addr)
+        }
+        true
+    }
+
+    /// Penalise a remote address for failing the handshake.
+    ///
+    /// Offences are accounted to our own key in the metrics. Once the map
+    /// of strangers is full, only addresses already in it are tracked.
+    fn penalise_stranger(&mut self, ip: IpAddr, o: Offence) {
+        let now = Instant::now();
+        if self.strangers.len() >= MAX_STRANGERS && !self.strangers.contains_key(&ip) {
+            let conf = &self.conf;
+            self.strangers
+                .retain(|_, s| s.banned(now).is_some() || s.reputation(conf, now).score > 0);
+            if self.strangers.len() >= MAX_STRANGERS {
+                return;
+            }
+        }
+        self.metrics.add(&self.key, o.label(), 1);
+        let standing = self.strangers.entry(ip).or_insert_with(Standing::new);
+        let Some(d) = standing.offend(&self.conf, o, now) else {
+            return;
+        };
+        warn!(
+            name     = %self.conf.name,
+            node     = %self.key,
+            %ip,
+            offence  = ?o,
+            duration = ?d,
+            "banning address"
+        );
+        self.metrics.add(&self.key, BANS, 1);
+    }
+
+    /// Is the given remote address banned?
+    ///
+    /// Addresses of known parties are never banned as other hosts may share
+    /// them. Misbehaving parties are banned by key instead.
+    fn stranger_banned(&self, ip: IpAddr) -> bool {
+        let is_party = |p: &Party| matches!(p.addr, NetAddr::Inet(a, _) if a == ip);
+        if self.parties.values().any(is_party) {
+            return false;
+        }
+        self.strangers
+            .get(&ip)
+            .is_some_and(|s| s.banned(Instant::now()).is_some())
+    }
+
+    /// Handle a chunk or ready of a coded broadcast.
+    ///
+    /// A chunk we got from its origin is echoed to all other recipients.
+    /// Once enough recipients are ready and enough chunks of a message are
+    /// there, it is decoded and delivered to the application.
+    fn on_chunk(&mut self, src: PublicKey, bytes: Bytes) -> Result<(), NetworkError> {
+        let max = self.conf.max_message_size.get();
+        let msg = Message::from_bytes(bytes.clone()).filter(|m| match m {
+            Message::Chunk(c) => c.verify(max),
+            Message::Ready(_) => true,
+        });
+        let Some(msg) = msg else {
+            warn!(
+                name = %self.conf.name,
+                node = %self.key,
+                peer = %src,
+                "invalid chunk"
+            );
+            self.penalise(src, Offence::Decode);
+            return Ok(());
+        };
+        let (origin, slot) = (msg.origin(), msg.slot());
+        if slot < self.lower_bound || origin == self.key {
+            return Ok(());
+        }
+        let recipients = self.recipients(origin);
+        let n = recipients.len();
+        let mut status = match msg {
+            Message::Chunk(chunk) => {
+                // The origin codes a message into one shard per recipient. If
+                // we disagree about the recipients, shard indices do not match.
+                if usize::from(chunk.total) != n || usize::from(chunk.data) < coded::data_shards(n)
+                {
+                    debug!(
+                        name  = %self.conf.name,
+                        node  = %self.key,
+                        peer  = %src,
+                        %origin,
+                        total = %chunk.total,
+                        data  = %chunk.data,
+                        "unexpected coding parameters"
+                    );
+                    return Ok(());
+                }
+                // The origin sends us our shard, which we echo. Everyone else
+                // may only echo their own shard.
+                let echoer = if src == origin { self.key } else { src };
+                if recipients.get(usize::from(chunk.index)) != Some(&echoer) {
+                    debug!(
+                        name  = %self.conf.name,
+                        node  = %self.key,
+                        peer  = %src,
+                        %origin,
+                        index = %chunk.index,
+                        "unexpected shard index"
+                    );
+                    return Ok(());
+                }
+                if src == origin {
+                    if !self.decoder.echo(&chunk) {
+                        return Ok(());
+                    }
+                    self.send_coded(slot, &recipients, bytes);
+                }
+                self.decoder.add(echoer, chunk, n)
+            },
+            Message::Ready(r) => {
+                if src == self.key || recipients.binary_search(&src).is_err() {
+                    return Ok(());
+                }
+                self.decoder.add_ready(src, r, n)
+            },
+        };
+        loop {
+            match status {
+                Status::Ready(r) => {
+                    trace!(name = %self.conf.name, node = %self.key, %origin, "message ready");
+                    self.send_coded(slot, &recipients, Message::Ready(r).to_bytes());
+                    status = self.decoder.add_ready(self.key, r, n)
+                },
+                Status::Decoded(msg) => {
+                    trace!(name = %self.conf.name, node = %self.key, %origin, "message decoded");
+                    if self.ibound.send((origin, msg, None)).is_err() {
+                        return Err(NetworkError::ChannelClosed);
+                    }
+                    return Ok(());
+                },
+                Status::Failed => {
+                    warn!(
+                        name = %self.conf.name,
+                        node = %self.key,
+                        peer = %origin,
+                        "inconsistent coded message"
+                    );
+                    self.penalise(origin, Offence::Decode);
+                    return Ok(());
+                },
+                Status::Added | Status::Ignored => return Ok(()),
+            }
+        }
+    }
+
+    /// The recipients of a coded broadcast of the given origin, in shard order.
+    ///
+    /// These are all active parties, except for the origin.
+    fn recipients(&self, origin: PublicKey) -> Vec<PublicKey> {
+        let mut keys = self
+            .parties
+            .iter()
+            .filter(|(k, p)| **k != origin && p.role.is_active())
+            .map(|(k, _)| *k)
+            .chain((self.role.is_active() && self.key != origin).then_some(self.key))
+            .collect::<Vec<_>>();
+        keys.sort();
+        keys
+    }
+
+    /// Send a chunk or ready of a coded broadcast to all other recipients.
+    fn send_coded(&mut self, slot: Slot, recipients: &[PublicKey], bytes: Bytes) {
+        let msgid = self.next_msgid();
+        let bytes = append_chunk_trailer(slot, msgid, bytes);
+        for key in recipients {
+            if let Some(party) = self.parties.get(key) {
+                trace!(name = %self.conf.name, node = %self.key, to = %key, "relaying chunk");
+                let m = (RetryPolicy::Default, bytes.clone());
+                party.outbox.enqueue(slot, msgid, m);
+            }
+        }
+    }
+
     fn next_msgid(&mut self) -> MsgId {
         let current = self.msgid;
         self.msgid = MsgId::new(self.msgid.0.wrapping_add(1));
@@ -765,6 +1167,8 @@ impl Party {
             outbox: Queue::new(),
             retry: DelayQueue::new(c),
             peer: PeerState::None,
+            inbound: false,
+            standing: Standing::new(),
         }
     }
 
@@ -792,17 +1196,29 @@ fn append_trailer(pol: RetryPolicy, slot: Slot, id: MsgId, bytes: Vec<u8>) -> By
     msg.freeze()
 }
 
+fn append_chunk_trailer(slot: Slot, id: MsgId, bytes: Bytes) -> Bytes {
+    let mut msg = BytesMut::from(bytes);
+    msg.extend_from_slice(Trailer::Chunk { slot, id }.to_bytes().as_ref());
+    msg.freeze()
+}
+
 fn remove_trailer(mut bytes: Bytes) -> Bytes {
     let _t = Trailer::from_bytes(&mut bytes);
     debug_assert!(_t.is_some());
     bytes
 }
 
+/// Max. number of remote addresses to track the standing of.
+const MAX_STRANGERS: usize = 1024;
+
 // Metrics labels /////////////////////////////////////////////////////////////
 
 /// Current number of accept tasks.
 const ACCEPT_TASKS: &str = "accept_tasks";
 
+/// Total number of bans.
+const BANS: &str = "bans";
+
 /// Current number of channel items.
 const CHANNEL_SIZE: &str = "channel_size";
 
@@ -826,3 +1242,6 @@ const LOWER_BOUND: &str = "lower_bound";
 
 /// Current number of peer tasks.
 const PEER_TASKS: &str = "peer_tasks";
+
+/// Current penalty score.
+const PENALTY_SCORE: &str = "penalty_score";
@@ -0,0 +1,184 @@
+use std::{net::Ipv4Addr, num::NonZeroUsize, sync::Arc, time::Duration};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    spawn,
+    sync::mpsc,
+    time::{sleep, timeout},
+};
+use tokio_util::sync::CancellationToken;
+
+use super::append_chunk_trailer;
+use crate::{
+    Config, Keypair, NetAddr, Network, PublicKey,
+    coded::{self, Message, Ready},
+    connection::{Connection, Dialer},
+    delay::DelayQueue,
+    metrics::NoMetrics,
+    msg::{Header, MsgId, Slot},
+    net::{RetryPolicy, peer::Peer},
+    noise::Protocol,
+    queue::Queue,
+};
+
+// -- Helpers -----------------------------------------------------------------
+
+fn reserve_port() -> u16 {
+    let s = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let a = s.local_addr().unwrap();
+    let _ = std::net::TcpStream::connect(a).unwrap();
+    let _ = s.accept().unwrap();
+    a.port()
+}
+
+fn config(kp: Keypair, bind: NetAddr, parties: &[(PublicKey, NetAddr)]) -> Config {
+    Config::builder()
+        .name("test")
+        .keypair(kp)
+        .bind(bind)
+        .parties(parties.iter().cloned())
+        .random_connect_delay(false)
+        .connect_retry_delays(vec![1])
+        .noise_protocols([(1.into(), Protocol::IK_25519_AesGcm_Blake2s)])
+        .build()
+}
+
+// -- Tests -------------------------------------------------------------------
+
+/// A party sending frames that can not be decrypted is banned.
+///
+/// Party A is impersonated by a bare connection which writes garbage after
+/// the hello exchange. Every such connection costs A a decode error.
+#[tokio::test]
+async fn malformed_frames_ban() {
+    let ka = Keypair::generate().unwrap();
+    let kb = Keypair::generate().unwrap();
+    let pka = ka.public_key();
+    let pkb = kb.public_key();
+
+    let addr_a = NetAddr::from((Ipv4Addr::LOCALHOST, reserve_port()));
+    let addr_b = NetAddr::from((Ipv4Addr::LOCALHOST, reserve_port()));
+    let parties = [(pka, addr_a.clone()), (pkb, addr_b.clone())];
+
+    let conf_a = Arc::new(config(ka, addr_a, &parties));
+    let net_b = Network::create(config(kb, addr_b.clone(), &parties))
+        .await
+        .unwrap();
+
+    // 16 bytes which are not a valid Noise message.
+    let mut frame = Header::data(16).to_bytes().to_vec();
+    frame.extend_from_slice(&[0xab; 16]);
+
+    for _ in 0..5 {
+        let mut conn = Connection::connect(conf_a.clone(), Dialer::Tcp, pkb, addr_b.clone()).await;
+        conn.stream.write_all(&frame).await.unwrap();
+        // B drops the connection once it fails to decrypt the frame.
+        let mut buf = [0; 64];
+        timeout(Duration::from_secs(5), async {
+            while conn.stream.read(&mut buf).await.is_ok_and(|n| n > 0) {}
+        })
+        .await
+        .expect("connection is closed");
+        // Give B time to record the offence before the next connection.
+        sleep(Duration::from_millis(100)).await
+    }
+
+    let r = net_b
+        .reputation(pka)
+        .await
+        .unwrap()
+        .expect("party is known");
+    assert_eq!(r.decode_errors, 5);
+    assert_eq!(r.reconnects, 0);
+    assert_eq!(r.bans, 1);
+    assert!(r.banned_for.is_some());
+
+    // A banned party is told to back off, i.e. it does not get a connection.
+    let conn = Connection::connect(conf_a, Dialer::Tcp, pkb, addr_b);
+    assert!(timeout(Duration::from_secs(2), conn).await.is_err());
+}
+
+/// Chunks made up by a party are not delivered and invalid ones are penalised.
+///
+/// Party A is impersonated by a peer with a bare connection. It sends B a
+/// chunk of a message on behalf of C and claims to be ready to deliver it.
+/// A single party can not make B deliver that, even though the chunk is
+/// consistent with the root. A chunk which is not is a decode error.
+#[tokio::test]
+async fn forged_chunks() {
+    let keys = [(); 5].map(|()| Keypair::generate().unwrap());
+    let parties = keys
+        .iter()
+        .map(|k| {
+            let a = NetAddr::from((Ipv4Addr::LOCALHOST, reserve_port()));
+            (k.public_key(), a)
+        })
+        .collect::<Vec<_>>();
+    let [(pka, addr_a), (pkb, addr_b), (pkc, _), ..] = &parties[..] else {
+        unreachable!()
+    };
+
+    let conf_a = Arc::new(config(keys[0].clone(), addr_a.clone(), &parties));
+    let mut net_b = Network::create(config(keys[1].clone(), addr_b.clone(), &parties))
+        .await
+        .unwrap();
+
+    let conn = Connection::connect(conf_a.clone(), Dialer::Tcp, *pkb, addr_b.clone()).await;
+    let (tx, _rx) = mpsc::unbounded_channel();
+    let outbox = Queue::new();
+    let mut peer = Peer::builder()
+        .config(conf_a.clone())
+        .budget(NonZeroUsize::new(10).unwrap())
+        .messages(outbox.clone())
+        .retry(DelayQueue::new(conf_a))
+        .inbound(tx.clone())
+        .chunks(tx)
+        .metrics(Arc::new(NoMetrics))
+        .build();
+    let _peer = spawn(async move { peer.start(conn, CancellationToken::new()).await });
+
+    // The recipients of C's broadcasts in shard order, of which A is one.
+    let mut recipients = parties
+        .iter()
+        .map(|(k, _)| *k)
+        .filter(|k| k != pkc)
+        .collect::<Vec<_>>();
+    recipients.sort();
+    let i = recipients.iter().position(|k| k == pka).unwrap();
+    let chunk = coded::encode(*pkc, Slot::MIN, MsgId::new(0), b"forged", 4)
+        .unwrap()
+        .remove(i);
+    let ready = Ready {
+        origin: chunk.origin,
+        slot: chunk.slot,
+        id: chunk.id,
+        root: chunk.root,
+    };
+    let mut invalid = Message::Chunk(chunk.clone()).to_bytes().to_vec();
+    *invalid.last_mut().unwrap() ^= 1;
+
+    let msgs = [
+        Message::Chunk(chunk).to_bytes(),
+        Message::Ready(ready).to_bytes(),
+        invalid.into(),
+    ];
+    for (id, m) in (0..).zip(msgs) {
+        let id = MsgId::new(id);
+        let bytes = append_chunk_trailer(Slot::MIN, id, m);
+        outbox.enqueue(Slot::MIN, id, (RetryPolicy::Default, bytes))
+    }
+
+    assert!(
+        timeout(Duration::from_secs(2), net_b.receive())
+            .await
+            .is_err(),
+        "forged message delivered"
+    );
+
+    let r = net_b
+        .reputation(*pka)
+        .await
+        .unwrap()
+        .expect("party is known");
+    assert_eq!(r.decode_errors, 1);
+}
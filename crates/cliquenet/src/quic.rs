@@ -0,0 +1,175 @@
+//! QUIC sessions between parties.
+//!
+//! QUIC requires TLS, but parties are identified by their x25519 keys and
+//! not by certificates. Every endpoint therefore presents a self-signed
+//! certificate which is accepted as is, and parties authenticate each other
+//! with the same Noise handshake as over TCP, run over the first stream of a
+//! session. The Noise prologue includes keying material exported from the
+//! TLS session, so the handshake only succeeds if both ends share the same
+//! TLS session, i.e. if no one sits in between.
+//!
+//! After the handshake the first stream carries ACKs. Messages are sent over
+//! unidirectional streams, one per lane. The lane of a message is given by
+//! its slot, so messages of a slot arrive in order, while messages of
+//! different slots do not wait for each other, unless they share a lane.
+
+use std::{io, net::SocketAddr, sync::Arc};
+
+use quinn::{
+    ClientConfig, Endpoint, IdleTimeout, RecvStream, SendStream, ServerConfig, TransportConfig,
+    VarInt,
+    crypto::rustls::{QuicClientConfig, QuicServerConfig},
+};
+use rustls::{
+    DigitallySignedStruct, SignatureScheme,
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    crypto::{CryptoProvider, verify_tls12_signature, verify_tls13_signature},
+    pki_types::{CertificateDer, PrivatePkcs8KeyDer, ServerName, UnixTime},
+};
+use tokio::net::lookup_host;
+
+use crate::{Config, NetworkError, msg::Slot};
+
+/// Number of unidirectional streams messages are spread over.
+pub const LANES: usize = 8;
+
+/// The application protocol negotiated during the TLS handshake.
+const ALPN: &[u8] = b"cliquenet";
+
+/// Server name of certificates (never verified).
+const SERVER_NAME: &str = "cliquenet";
+
+/// Label of the keying material the Noise handshake is bound to.
+const BINDING_LABEL: &[u8] = b"EXPORTER-cliquenet-noise";
+
+/// A QUIC session and its control stream.
+pub struct Link {
+    pub session: quinn::Connection,
+    pub send: SendStream,
+    pub recv: RecvStream,
+}
+
+impl Link {
+    /// Keying material of the TLS session to bind the Noise handshake to.
+    pub fn binding(&self, conf: &Config) -> [u8; 32] {
+        let mut k = [0; 32];
+        self.session
+            .export_keying_material(&mut k, BINDING_LABEL, conf.name.as_bytes())
+            .expect("32 bytes of keying material can be exported");
+        k
+    }
+}
+
+/// Create the endpoint to accept and open sessions on.
+pub fn endpoint(conf: &Config, addr: SocketAddr) -> Result<Endpoint, NetworkError> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+
+    let mut transport = TransportConfig::default();
+    transport
+        .max_concurrent_bidi_streams(VarInt::from_u32(1))
+        .max_concurrent_uni_streams(VarInt::from_u32(LANES as u32))
+        .max_idle_timeout(IdleTimeout::try_from(conf.receive_timeout).ok())
+        .keep_alive_interval(Some(conf.keep_alive_interval));
+    let transport = Arc::new(transport);
+
+    let cert = rcgen::generate_simple_self_signed([SERVER_NAME.to_string()])
+        .map_err(|e| rustls::Error::General(e.to_string()))?;
+
+    let mut tls = rustls::ServerConfig::builder_with_provider(provider.clone())
+        .with_protocol_versions(&[&rustls::version::TLS13])?
+        .with_no_client_auth()
+        .with_single_cert(
+            vec![cert.cert.der().clone()],
+            PrivatePkcs8KeyDer::from(cert.key_pair.serialize_der()).into(),
+        )?;
+    tls.alpn_protocols = vec![ALPN.to_vec()];
+    let tls = QuicServerConfig::try_from(tls).expect("TLS 1.3 has an initial cipher suite");
+    let mut server = ServerConfig::with_crypto(Arc::new(tls));
+    server.transport_config(transport.clone());
+
+    let mut tls = rustls::ClientConfig::builder_with_provider(provider.clone())
+        .with_protocol_versions(&[&rustls::version::TLS13])?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(AnyCertificate(provider)))
+        .with_no_client_auth();
+    tls.alpn_protocols = vec![ALPN.to_vec()];
+    let tls = QuicClientConfig::try_from(tls).expect("TLS 1.3 has an initial cipher suite");
+    let mut client = ClientConfig::new(Arc::new(tls));
+    client.transport_config(transport);
+
+    let mut endpoint =
+        Endpoint::server(server, addr).map_err(|e| NetworkError::Bind(conf.bind.clone(), e))?;
+    endpoint.set_default_client_config(client);
+    Ok(endpoint)
+}
+
+/// Open a session with the endpoint at the given address.
+pub async fn connect(endpoint: &Endpoint, addr: &str) -> Result<Link, NetworkError> {
+    let Some(addr) = lookup_host(addr).await?.next() else {
+        return Err(io::Error::from(io::ErrorKind::AddrNotAvailable).into());
+    };
+    let session = endpoint.connect(addr, SERVER_NAME)?.await?;
+    let (send, recv) = session.open_bi().await?;
+    Ok(Link {
+        session,
+        send,
+        recv,
+    })
+}
+
+/// Accept a session.
+pub async fn accept(incoming: quinn::Incoming) -> Result<Link, NetworkError> {
+    let session = incoming.await?;
+    let (send, recv) = session.accept_bi().await?;
+    Ok(Link {
+        session,
+        send,
+        recv,
+    })
+}
+
+/// The lane messages of the given slot are sent on.
+pub fn lane(s: Slot) -> usize {
+    (s.0 % LANES as u64) as usize
+}
+
+/// Accepts any certificate, provided the server holds its private key.
+///
+/// Servers are authenticated by the Noise handshake instead.
+#[derive(Debug)]
+struct AnyCertificate(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for AnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _: &CertificateDer<'_>,
+        _: &[CertificateDer<'_>],
+        _: &ServerName<'_>,
+        _: &[u8],
+        _: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        msg: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(msg, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        msg: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(msg, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
@@ -0,0 +1,263 @@
+//! Peer reputation.
+//!
+//! Parties are penalised for misbehaving, e.g. for sending frames we can not
+//! decode. Each offence adds to a party's penalty score, which decays over
+//! time. Once the score reaches the configured threshold, the party is banned
+//! for a while: we do not connect to it and tell it to back off if it connects
+//! to us. Every further ban lasts twice as long as the one before.
+
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::{Config, NetworkError};
+
+/// Upper bound of the number of times a ban duration doubles.
+const MAX_BAN_DOUBLINGS: u32 = 6;
+
+/// Kinds of misbehaviour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Offence {
+    /// A frame or message could not be decoded.
+    Decode,
+    /// A message exceeded the maximum size.
+    Oversize,
+    /// The party failed to complete the handshake.
+    Handshake,
+    /// The party connected again while the connection it opened before was live.
+    Reconnect,
+}
+
+impl Offence {
+    /// The offence a failed peer connection amounts to, if any.
+    pub(crate) fn of_peer_error(e: &NetworkError) -> Option<Self> {
+        match e {
+            NetworkError::InvalidFrameHeader(_)
+            | NetworkError::InvalidTrailer
+            | NetworkError::InvalidAck
+            | NetworkError::UnknownFrameType(_)
+            | NetworkError::Noise(_) => Some(Self::Decode),
+            NetworkError::MessageTooLarge => Some(Self::Oversize),
+            _ => None,
+        }
+    }
+
+    /// The offence a failed handshake or hello exchange amounts to, if any.
+    ///
+    /// Timeouts are no offence as a slow or overloaded party can not be told
+    /// apart from one that stalls on purpose.
+    pub(crate) fn of_handshake_error(e: &NetworkError) -> Option<Self> {
+        match e {
+            NetworkError::InvalidFrameHeader(_)
+            | NetworkError::InvalidHello
+            | NetworkError::InvalidHandshakeMessage
+            | NetworkError::Noise(_) => Some(Self::Handshake),
+            _ => None,
+        }
+    }
+
+    /// The penalty score of this offence.
+    fn penalty(self) -> u32 {
+        match self {
+            Self::Decode => 20,
+            Self::Oversize => 40,
+            Self::Handshake => 10,
+            Self::Reconnect => 5,
+        }
+    }
+
+    /// The metrics label counting this offence.
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Self::Decode => "decode_errors",
+            Self::Oversize => "oversize_frames",
+            Self::Handshake => "handshake_failures",
+            Self::Reconnect => "reconnects",
+        }
+    }
+}
+
+/// The reputation of a party, as returned by [`NetworkSender::reputation`].
+///
+/// [`NetworkSender::reputation`]: crate::NetworkSender::reputation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Reputation {
+    /// The current penalty score.
+    pub score: u32,
+    /// Total number of frames or messages that could not be decoded.
+    pub decode_errors: u32,
+    /// Total number of messages exceeding the maximum size.
+    pub oversize_frames: u32,
+    /// Total number of failed handshakes.
+    pub handshake_failures: u32,
+    /// Total number of connections opened while a previous one was live.
+    pub reconnects: u32,
+    /// Total number of bans.
+    pub bans: u32,
+    /// The remaining time of the current ban, if any.
+    pub banned_for: Option<Duration>,
+}
+
+/// Tracks the reputation of a party.
+#[derive(Debug)]
+pub(crate) struct Standing {
+    rep: Reputation,
+    /// When the penalty score last decayed.
+    decayed: Instant,
+    banned_until: Option<Instant>,
+}
+
+impl Standing {
+    pub(crate) fn new() -> Self {
+        Self {
+            rep: Reputation::default(),
+            decayed: Instant::now(),
+            banned_until: None,
+        }
+    }
+
+    /// Record an offence.
+    ///
+    /// If the party is banned as a result, the ban duration is returned.
+    pub(crate) fn offend(&mut self, conf: &Config, o: Offence, now: Instant) -> Option<Duration> {
+        self.decay(conf, now);
+        match o {
+            Offence::Decode => self.rep.decode_errors += 1,
+            Offence::Oversize => self.rep.oversize_frames += 1,
+            Offence::Handshake => self.rep.handshake_failures += 1,
+            Offence::Reconnect => self.rep.reconnects += 1,
+        }
+        self.rep.score = self.rep.score.saturating_add(o.penalty());
+        if self.rep.score < conf.ban_threshold || self.banned(now).is_some() {
+            return None;
+        }
+        let d = conf
+            .ban_duration
+            .saturating_mul(1 << self.rep.bans.min(MAX_BAN_DOUBLINGS));
+        self.rep.bans += 1;
+        self.rep.score = 0;
+        self.banned_until = Some(now + d);
+        Some(d)
+    }
+
+    /// The remaining time of the current ban, if any.
+    pub(crate) fn banned(&self, now: Instant) -> Option<Duration> {
+        self.banned_until
+            .map(|t| t.saturating_duration_since(now))
+            .filter(|d| !d.is_zero())
+    }
+
+    /// The current penalty score.
+    pub(crate) fn score(&self) -> u32 {
+        self.rep.score
+    }
+
+    pub(crate) fn reputation(&mut self, conf: &Config, now: Instant) -> Reputation {
+        self.decay(conf, now);
+        Reputation {
+            banned_for: self.banned(now),
+            ..self.rep
+        }
+    }
+
+    /// Halve the penalty score for every half-life that has passed.
+    fn decay(&mut self, conf: &Config, now: Instant) {
+        let half_life = conf.penalty_half_life.max(Duration::from_millis(1));
+        let n = now.saturating_duration_since(self.decayed).as_nanos() / half_life.as_nanos();
+        if n == 0 {
+            return;
+        }
+        if n >= u32::BITS.into() {
+            self.rep.score = 0;
+            self.decayed = now;
+        } else {
+            self.rep.score >>= n;
+            self.decayed += half_life * n as u32;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::Instant;
+
+    use super::{Offence, Standing};
+    use crate::{Config, Keypair, NetAddr, PublicKey, noise::Protocol};
+
+    fn config() -> Config {
+        Config::builder()
+            .name("test")
+            .keypair(Keypair::generate().unwrap())
+            .bind(NetAddr::from((std::net::Ipv4Addr::LOCALHOST, 0u16)))
+            .parties(std::iter::empty::<(PublicKey, NetAddr)>())
+            .noise_protocols([(1.into(), Protocol::IK_25519_AesGcm_Blake2s)])
+            .ban_threshold(100)
+            .ban_duration(Duration::from_secs(10))
+            .penalty_half_life(Duration::from_secs(60))
+            .build()
+    }
+
+    #[test]
+    fn ban_backoff() {
+        let conf = config();
+        let mut s = Standing::new();
+        let mut now = Instant::now();
+
+        for _ in 0..4 {
+            assert_eq!(s.offend(&conf, Offence::Decode, now), None);
+        }
+        assert_eq!(
+            s.offend(&conf, Offence::Decode, now),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(s.banned(now), Some(Duration::from_secs(10)));
+
+        // Offences during a ban do not extend it:
+        for _ in 0..5 {
+            assert_eq!(s.offend(&conf, Offence::Decode, now), None);
+        }
+
+        now += Duration::from_secs(10);
+        assert_eq!(s.banned(now), None);
+
+        // The next ban lasts twice as long:
+        assert_eq!(
+            s.offend(&conf, Offence::Oversize, now),
+            Some(Duration::from_secs(20))
+        );
+
+        let r = s.reputation(&conf, now);
+        assert_eq!(r.decode_errors, 10);
+        assert_eq!(r.oversize_frames, 1);
+        assert_eq!(r.bans, 2);
+        assert_eq!(r.banned_for, Some(Duration::from_secs(20)));
+    }
+
+    #[test]
+    fn score_decays() {
+        let conf = config();
+        let mut s = Standing::new();
+        let now = Instant::now();
+
+        for _ in 0..4 {
+            s.offend(&conf, Offence::Decode, now);
+        }
+        assert_eq!(s.score(), 80);
+
+        let r = s.reputation(&conf, now + Duration::from_secs(60));
+        assert_eq!(r.score, 40);
+
+        // Without the decay this would have been a ban:
+        assert_eq!(
+            s.offend(&conf, Offence::Decode, now + Duration::from_secs(60)),
+            None
+        );
+        assert_eq!(s.score(), 60);
+
+        let r = s.reputation(&conf, now + Duration::from_secs(3600));
+        assert_eq!(r.score, 0);
+    }
+}
@@ -0,0 +1,62 @@
+use std::net::Ipv4Addr;
+
+use cliquenet::{Config, NetAddr, Network, Role, Slot, noise::Protocol, x25519::Keypair};
+
+fn config(k: &Keypair, parties: &[Keypair]) -> Config {
+    Config::builder()
+        .name("detached")
+        .keypair(k.clone())
+        .bind(NetAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .parties(
+            parties
+                .iter()
+                .map(|k| (k.public_key(), NetAddr::from((Ipv4Addr::LOCALHOST, 0)))),
+        )
+        .noise_protocols([(1.into(), Protocol::IK_25519_AesGcm_Blake2s)])
+        .build()
+}
+
+/// Messages are resolved to their recipients and handed to the transport.
+#[tokio::test]
+async fn send_and_deliver() {
+    let keys = (0..3)
+        .map(|_| Keypair::generate().unwrap())
+        .collect::<Vec<_>>();
+    let [a, b, c] = [0, 1, 2].map(|i| keys[i].public_key());
+
+    let (mut net, mut det) = Network::detached(config(&keys[0], &keys));
+
+    net.unicast(Slot::MIN, b, b"unicast".to_vec()).unwrap();
+    let out = det.next().await.unwrap();
+    assert_eq!(out.to, vec![b]);
+    assert_eq!(&out.msg[..], b"unicast");
+
+    net.broadcast(Slot::MIN, b"broadcast".to_vec()).unwrap();
+    let mut out = det.next().await.unwrap();
+    out.to.sort();
+    let mut all = vec![a, b, c];
+    all.sort();
+    assert_eq!(out.to, all);
+
+    // Passive peers are excluded from broadcasts.
+    net.assign_peers(Role::Passive, [c]).unwrap();
+    net.broadcast(Slot::MIN, b"broadcast".to_vec()).unwrap();
+    let out = det.next().await.unwrap();
+    assert!(!out.to.contains(&c));
+    assert!(out.to.contains(&a));
+
+    // Messages below the lower bound are dropped.
+    net.gc(Slot::new(2)).unwrap();
+    net.unicast(Slot::new(1), b, b"old".to_vec()).unwrap();
+    net.unicast(Slot::new(2), b, b"new".to_vec()).unwrap();
+    assert_eq!(&det.next().await.unwrap().msg[..], b"new");
+
+    det.inbound().deliver(b, "hello".into()).unwrap();
+    let (src, msg) = net.receive().await.unwrap();
+    assert_eq!(src, b);
+    assert_eq!(&msg[..], b"hello");
+
+    let done = net.shutdown().unwrap();
+    assert!(det.next().await.is_none());
+    done.await
+}
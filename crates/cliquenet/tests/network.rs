@@ -2,7 +2,7 @@ use std::{net::Ipv4Addr, time::Duration};
 
 use bytes::Bytes;
 use cliquenet::{
-    Config, Network, Role, Slot,
+    Config, Network, Role, SendAction, SendCommand, Slot, Transport,
     error::NetworkError,
     noise::Protocol,
     x25519::{Keypair, PublicKey},
@@ -11,6 +11,31 @@ use tokio::time::{sleep, timeout};
 
 // -- Helpers -----------------------------------------------------------------
 
+/// Run the given tests with every transport.
+macro_rules! transports {
+    ($($name:ident),* $(,)?) => {
+        mod tcp {
+            use super::Transport;
+            $(
+                #[tokio::test]
+                async fn $name() {
+                    super::$name(Transport::Tcp).await
+                }
+            )*
+        }
+
+        mod quic {
+            use super::Transport;
+            $(
+                #[tokio::test]
+                async fn $name() {
+                    super::$name(Transport::Quic).await
+                }
+            )*
+        }
+    };
+}
+
 const TIMEOUT: Duration = Duration::from_secs(10);
 
 /// Time to wait for connections to establish after creating networks.
@@ -43,11 +68,12 @@ fn reserve_port() -> u16 {
 }
 
 /// Create a Config for a node, listing all nodes as parties.
-fn make_config(node: &Node, all: &[&Node]) -> Config {
+fn make_config(node: &Node, all: &[&Node], t: Transport) -> Config {
     Config::builder()
         .name("test")
         .keypair(node.keypair.clone())
         .bind(node.addr().into())
+        .transport(t)
         .parties(all.iter().map(|n| (n.key, n.addr().into())))
         .receive_timeout(Duration::from_secs(5))
         .connect_retry_delays(vec![1, 3])
@@ -57,15 +83,15 @@ fn make_config(node: &Node, all: &[&Node]) -> Config {
 }
 
 /// Create a two-node network and wait for connections.
-async fn two_nodes() -> (Network, Network, PublicKey, PublicKey) {
+async fn two_nodes(t: Transport) -> (Network, Network, PublicKey, PublicKey) {
     let a = Node::new(reserve_port());
     let b = Node::new(reserve_port());
     let pka = a.key;
     let pkb = b.key;
     let all = [&a, &b];
 
-    let net_a = Network::create(make_config(&a, &all)).await.unwrap();
-    let net_b = Network::create(make_config(&b, &all)).await.unwrap();
+    let net_a = Network::create(make_config(&a, &all, t)).await.unwrap();
+    let net_b = Network::create(make_config(&b, &all, t)).await.unwrap();
 
     sleep(SETTLE).await;
 
@@ -74,10 +100,37 @@ async fn two_nodes() -> (Network, Network, PublicKey, PublicKey) {
 
 // -- Tests -------------------------------------------------------------------
 
+transports! {
+    unicast,
+    unicast_multiple,
+    broadcast,
+    bidirectional,
+    multicast,
+    self_unicast,
+    large_message,
+    message_too_large,
+    shutdown_on_drop,
+    three_node_broadcast,
+    coded_broadcast,
+    coded_broadcasts_in_one_slot,
+    coded_broadcast_via_relays,
+    empty_payload,
+    gc,
+    add_peer,
+    remove_peer,
+    passive_role,
+    simultaneous_connect,
+    reconnect_after_restart,
+    unknown_peer_backs_off,
+    update_party_address,
+    no_relay_between_disconnected_peers,
+    update_party_address_live_connection,
+    reputation,
+}
+
 /// Unicast from A to B.
-#[tokio::test]
-async fn unicast() {
-    let (net_a, mut net_b, pka, pkb) = two_nodes().await;
+async fn unicast(t: Transport) {
+    let (net_a, mut net_b, pka, pkb) = two_nodes(t).await;
 
     net_a.unicast(Slot::MIN, pkb, b"hello".to_vec()).unwrap();
 
@@ -91,9 +144,8 @@ async fn unicast() {
 }
 
 /// Unicast multiple messages, all arrive in order.
-#[tokio::test]
-async fn unicast_multiple() {
-    let (net_a, mut net_b, _pka, pkb) = two_nodes().await;
+async fn unicast_multiple(t: Transport) {
+    let (net_a, mut net_b, _pka, pkb) = two_nodes(t).await;
 
     let n = 10usize;
     for i in 0..n {
@@ -112,9 +164,8 @@ async fn unicast_multiple() {
 }
 
 /// Broadcast delivers to all active parties including self.
-#[tokio::test]
-async fn broadcast() {
-    let (mut net_a, mut net_b, pka, _pkb) = two_nodes().await;
+async fn broadcast(t: Transport) {
+    let (mut net_a, mut net_b, pka, _pkb) = two_nodes(t).await;
 
     net_a.broadcast(Slot::MIN, b"bcast".to_vec()).unwrap();
 
@@ -139,9 +190,8 @@ async fn broadcast() {
 }
 
 /// Bidirectional: both nodes send to each other.
-#[tokio::test]
-async fn bidirectional() {
-    let (mut net_a, mut net_b, pka, pkb) = two_nodes().await;
+async fn bidirectional(t: Transport) {
+    let (mut net_a, mut net_b, pka, pkb) = two_nodes(t).await;
 
     net_a.unicast(Slot::MIN, pkb, b"from-a".to_vec()).unwrap();
     net_b.unicast(Slot::MIN, pka, b"from-b".to_vec()).unwrap();
@@ -162,8 +212,7 @@ async fn bidirectional() {
 }
 
 /// Multicast delivers to selected peers and self (if included).
-#[tokio::test]
-async fn multicast() {
+async fn multicast(t: Transport) {
     let a = Node::new(reserve_port());
     let b = Node::new(reserve_port());
     let c = Node::new(reserve_port());
@@ -171,9 +220,9 @@ async fn multicast() {
     let pkb = b.key;
     let all = [&a, &b, &c];
 
-    let mut net_a = Network::create(make_config(&a, &all)).await.unwrap();
-    let mut net_b = Network::create(make_config(&b, &all)).await.unwrap();
-    let mut net_c = Network::create(make_config(&c, &all)).await.unwrap();
+    let mut net_a = Network::create(make_config(&a, &all, t)).await.unwrap();
+    let mut net_b = Network::create(make_config(&b, &all, t)).await.unwrap();
+    let mut net_c = Network::create(make_config(&c, &all, t)).await.unwrap();
 
     sleep(SETTLE).await;
 
@@ -205,9 +254,8 @@ async fn multicast() {
 }
 
 /// Self-unicast delivers locally without going through the network.
-#[tokio::test]
-async fn self_unicast() {
-    let (mut net_a, _net_b, pka, _pkb) = two_nodes().await;
+async fn self_unicast(t: Transport) {
+    let (mut net_a, _net_b, pka, _pkb) = two_nodes(t).await;
 
     net_a.unicast(Slot::MIN, pka, b"self".to_vec()).unwrap();
 
@@ -220,9 +268,8 @@ async fn self_unicast() {
 }
 
 /// Large message spanning multiple Noise frames.
-#[tokio::test]
-async fn large_message() {
-    let (net_a, mut net_b, _pka, pkb) = two_nodes().await;
+async fn large_message(t: Transport) {
+    let (net_a, mut net_b, _pka, pkb) = two_nodes(t).await;
 
     let big: Vec<u8> = (0..200 * 1024).map(|i| (i % 251) as u8).collect();
     net_a.unicast(Slot::MIN, pkb, big.clone()).unwrap();
@@ -235,9 +282,8 @@ async fn large_message() {
 }
 
 /// Message too large is rejected at the API level.
-#[tokio::test]
-async fn message_too_large() {
-    let (net_a, _net_b, _pka, pkb) = two_nodes().await;
+async fn message_too_large(t: Transport) {
+    let (net_a, _net_b, _pka, pkb) = two_nodes(t).await;
 
     let huge = vec![0u8; 11 * 1024 * 1024]; // > 10 MiB default
     let result = net_a.unicast(Slot::MIN, pkb, huge);
@@ -245,9 +291,8 @@ async fn message_too_large() {
 }
 
 /// Dropping all senders shuts down the network.
-#[tokio::test]
-async fn shutdown_on_drop() {
-    let (net_a, _net_b, _pka, _pkb) = two_nodes().await;
+async fn shutdown_on_drop(t: Transport) {
+    let (net_a, _net_b, _pka, _pkb) = two_nodes(t).await;
 
     let (send_a, mut rx_a) = net_a.split_into();
     drop(send_a);
@@ -261,16 +306,15 @@ async fn shutdown_on_drop() {
 }
 
 /// Three nodes: broadcast from each, all receive from all.
-#[tokio::test]
-async fn three_node_broadcast() {
+async fn three_node_broadcast(t: Transport) {
     let a = Node::new(reserve_port());
     let b = Node::new(reserve_port());
     let c = Node::new(reserve_port());
     let all = [&a, &b, &c];
 
-    let mut net_a = Network::create(make_config(&a, &all)).await.unwrap();
-    let mut net_b = Network::create(make_config(&b, &all)).await.unwrap();
-    let mut net_c = Network::create(make_config(&c, &all)).await.unwrap();
+    let mut net_a = Network::create(make_config(&a, &all, t)).await.unwrap();
+    let mut net_b = Network::create(make_config(&b, &all, t)).await.unwrap();
+    let mut net_c = Network::create(make_config(&c, &all, t)).await.unwrap();
 
     sleep(SETTLE).await;
 
@@ -299,10 +343,148 @@ async fn three_node_broadcast() {
     }
 }
 
+/// Coded broadcasts are reconstructed by all active parties, including self.
+async fn coded_broadcast(t: Transport) {
+    let nodes = [(); 4].map(|()| Node::new(reserve_port()));
+    let all = nodes.each_ref();
+
+    let mut nets = Vec::new();
+    for n in &nodes {
+        nets.push(Network::create(make_config(n, &all, t)).await.unwrap());
+    }
+
+    sleep(SETTLE).await;
+
+    // Messages of different sizes, including one which is not a multiple of
+    // the number of chunks and an empty one.
+    let msgs: [Vec<u8>; 4] = [
+        (0..100_003).map(|i| i as u8).collect(),
+        b"coded".to_vec(),
+        vec![7; 4096],
+        Vec::new(),
+    ];
+
+    for (net, m) in nets.iter().zip(&msgs) {
+        let cmd = SendCommand::builder()
+            .slot(Slot::MIN)
+            .action(SendAction::CodedBroadcast(m.clone()))
+            .build();
+        net.send(cmd).unwrap();
+    }
+
+    for net in &mut nets {
+        let mut received = Vec::new();
+        for _ in 0..msgs.len() {
+            let (src, data) = timeout(TIMEOUT, net.receive())
+                .await
+                .expect("timed out")
+                .expect("channel closed");
+            received.push((src, data));
+        }
+        received.sort();
+        let mut expected = nodes
+            .iter()
+            .zip(&msgs)
+            .map(|(n, m)| (n.key, Bytes::from(m.clone())))
+            .collect::<Vec<_>>();
+        expected.sort();
+        assert_eq!(received, expected);
+    }
+}
+
+/// Several coded broadcasts of one origin in the same slot are all delivered.
+async fn coded_broadcasts_in_one_slot(t: Transport) {
+    let nodes = [(); 4].map(|()| Node::new(reserve_port()));
+    let all = nodes.each_ref();
+
+    let mut nets = Vec::new();
+    for n in &nodes {
+        nets.push(Network::create(make_config(n, &all, t)).await.unwrap());
+    }
+
+    sleep(SETTLE).await;
+
+    let msgs = [b"first".to_vec(), b"second".to_vec(), vec![3; 4096]];
+
+    for m in &msgs {
+        let cmd = SendCommand::builder()
+            .slot(Slot::MIN)
+            .action(SendAction::CodedBroadcast(m.clone()))
+            .build();
+        nets[0].send(cmd).unwrap();
+    }
+
+    for net in &mut nets {
+        let mut received = Vec::new();
+        for _ in 0..msgs.len() {
+            let (src, data) = timeout(TIMEOUT, net.receive())
+                .await
+                .expect("timed out")
+                .expect("channel closed");
+            assert_eq!(src, nodes[0].key);
+            received.push(data);
+        }
+        received.sort();
+        let mut expected = msgs.iter().cloned().map(Bytes::from).collect::<Vec<_>>();
+        expected.sort();
+        assert_eq!(received, expected);
+    }
+}
+
+/// A recipient the origin can not reach never gets its shard from the origin,
+/// but reconstructs the message from the shards the other recipients echo.
+async fn coded_broadcast_via_relays(t: Transport) {
+    let nodes = [(); 5].map(|()| Node::new(reserve_port()));
+    let [o, a, b, c, r] = &nodes;
+
+    let dead_o = Node {
+        key: o.key,
+        port: reserve_port(),
+        keypair: o.keypair.clone(),
+    };
+    let dead_r = Node {
+        key: r.key,
+        port: reserve_port(),
+        keypair: r.keypair.clone(),
+    };
+
+    let net_o = Network::create(make_config(o, &[o, a, b, c, &dead_r], t))
+        .await
+        .unwrap();
+    let mut nets = Vec::new();
+    for n in [a, b, c] {
+        nets.push(
+            Network::create(make_config(n, &nodes.each_ref(), t))
+                .await
+                .unwrap(),
+        );
+    }
+    let mut net_r = Network::create(make_config(r, &[&dead_o, a, b, c, r], t))
+        .await
+        .unwrap();
+
+    sleep(SETTLE).await;
+
+    let msg = (0..10_000).map(|i| i as u8).collect::<Vec<_>>();
+    let cmd = SendCommand::builder()
+        .slot(Slot::MIN)
+        .action(SendAction::CodedBroadcast(msg.clone()))
+        .build();
+    net_o.send(cmd).unwrap();
+
+    for net in nets.iter_mut().chain([&mut net_r]) {
+        let (src, data) = timeout(TIMEOUT, net.receive())
+            .await
+            .expect("timed out")
+            .expect("channel closed");
+        assert_eq!(src, o.key);
+        assert_eq!(data, Bytes::from(msg.clone()));
+    }
+}
+
 /// Empty payload is delivered correctly.
-#[tokio::test]
-async fn empty_payload() {
-    let (net_a, mut net_b, _pka, pkb) = two_nodes().await;
+async fn empty_payload(t: Transport) {
+    let (net_a, mut net_b, _pka, pkb) = two_nodes(t).await;
 
     net_a.unicast(Slot::MIN, pkb, vec![]).unwrap();
 
@@ -314,9 +496,8 @@ async fn empty_payload() {
 }
 
 /// GC discards old-slot messages while current-slot messages are delivered.
-#[tokio::test]
-async fn gc() {
-    let (net_a, mut net_b, _pka, pkb) = two_nodes().await;
+async fn gc(t: Transport) {
+    let (net_a, mut net_b, _pka, pkb) = two_nodes(t).await;
 
     // Send messages in slots 1 and 3.
     net_a.unicast(Slot::new(1), pkb, b"old".to_vec()).unwrap();
@@ -341,8 +522,7 @@ async fn gc() {
 }
 
 /// Adding a peer mid-session allows sending messages to it.
-#[tokio::test]
-async fn add_peer() {
+async fn add_peer(t: Transport) {
     let a = Node::new(reserve_port());
     let b = Node::new(reserve_port());
     let c = Node::new(reserve_port());
@@ -350,12 +530,12 @@ async fn add_peer() {
 
     // Start A and B knowing only each other.
     let all_ab = [&a, &b];
-    let net_a = Network::create(make_config(&a, &all_ab)).await.unwrap();
-    let _net_b = Network::create(make_config(&b, &all_ab)).await.unwrap();
+    let net_a = Network::create(make_config(&a, &all_ab, t)).await.unwrap();
+    let _net_b = Network::create(make_config(&b, &all_ab, t)).await.unwrap();
 
     // Start C knowing A and B.
     let all_abc = [&a, &b, &c];
-    let mut net_c = Network::create(make_config(&c, &all_abc)).await.unwrap();
+    let mut net_c = Network::create(make_config(&c, &all_abc, t)).await.unwrap();
 
     // A adds C dynamically.
     net_a
@@ -374,17 +554,16 @@ async fn add_peer() {
 }
 
 /// Removing a peer stops delivery to it; messages to remaining peers still work.
-#[tokio::test]
-async fn remove_peer() {
+async fn remove_peer(t: Transport) {
     let a = Node::new(reserve_port());
     let b = Node::new(reserve_port());
     let c = Node::new(reserve_port());
     let pkb = b.key;
     let all = [&a, &b, &c];
 
-    let mut net_a = Network::create(make_config(&a, &all)).await.unwrap();
-    let mut net_b = Network::create(make_config(&b, &all)).await.unwrap();
-    let mut net_c = Network::create(make_config(&c, &all)).await.unwrap();
+    let mut net_a = Network::create(make_config(&a, &all, t)).await.unwrap();
+    let mut net_b = Network::create(make_config(&b, &all, t)).await.unwrap();
+    let mut net_c = Network::create(make_config(&c, &all, t)).await.unwrap();
 
     sleep(SETTLE).await;
 
@@ -419,16 +598,15 @@ async fn remove_peer() {
 }
 
 /// Passive peers are excluded from broadcasts but can receive unicasts.
-#[tokio::test]
-async fn passive_role() {
+async fn passive_role(t: Transport) {
     let a = Node::new(reserve_port());
     let b = Node::new(reserve_port());
     let pka = a.key;
     let pkb = b.key;
     let all = [&a, &b];
 
-    let mut net_a = Network::create(make_config(&a, &all)).await.unwrap();
-    let mut net_b = Network::create(make_config(&b, &all)).await.unwrap();
+    let mut net_a = Network::create(make_config(&a, &all, t)).await.unwrap();
+    let mut net_b = Network::create(make_config(&b, &all, t)).await.unwrap();
 
     sleep(SETTLE).await;
 
@@ -468,8 +646,7 @@ async fn passive_role() {
 }
 
 /// Two nodes connecting simultaneously stabilise with one connection per pair.
-#[tokio::test]
-async fn simultaneous_connect() {
+async fn simultaneous_connect(t: Transport) {
     let a = Node::new(reserve_port());
     let b = Node::new(reserve_port());
     let pka = a.key;
@@ -479,8 +656,8 @@ async fn simultaneous_connect() {
     // Create both networks at the same time to maximise chance of
     // simultaneous connect attempts.
     let (net_a, net_b) = tokio::join!(
-        Network::create(make_config(&a, &all)),
-        Network::create(make_config(&b, &all)),
+        Network::create(make_config(&a, &all, t)),
+        Network::create(make_config(&b, &all, t)),
     );
     let mut net_a = net_a.unwrap();
     let mut net_b = net_b.unwrap();
@@ -518,8 +695,7 @@ async fn simultaneous_connect() {
 }
 
 /// A node that restarts eventually receives messages queued by its peer.
-#[tokio::test]
-async fn reconnect_after_restart() {
+async fn reconnect_after_restart(t: Transport) {
     let a = Node::new(reserve_port());
     let b_port = reserve_port();
     let b = Node::new(b_port);
@@ -539,8 +715,8 @@ async fn reconnect_after_restart() {
     // B knows itself and A.
     let all_b = [&a, &b];
 
-    let net_a = Network::create(make_config(&a, &all_a)).await.unwrap();
-    let net_b = Network::create(make_config(&b, &all_b)).await.unwrap();
+    let net_a = Network::create(make_config(&a, &all_a, t)).await.unwrap();
+    let net_b = Network::create(make_config(&b, &all_b, t)).await.unwrap();
 
     sleep(SETTLE).await;
 
@@ -552,7 +728,7 @@ async fn reconnect_after_restart() {
 
     // B comes back on a new port with the same keys.
     let all_b2 = [&a, &b2];
-    let mut net_b = Network::create(make_config(&b2, &all_b2)).await.unwrap();
+    let mut net_b = Network::create(make_config(&b2, &all_b2, t)).await.unwrap();
 
     // The message should eventually be delivered via retry.
     let (src, data) = timeout(Duration::from_secs(15), net_b.receive())
@@ -572,8 +748,7 @@ async fn reconnect_after_restart() {
 /// We configure A with a 60 s backoff and C with a 1 s retry delay. After
 /// the first handshake, C sleeps 60 s. Since A never adds C, C has no peer
 /// and cannot deliver the queued message within the 5 s window.
-#[tokio::test]
-async fn unknown_peer_backs_off() {
+async fn unknown_peer_backs_off(t: Transport) {
     let a = Node::new(reserve_port());
     let c = Node::new(reserve_port());
 
@@ -583,6 +758,7 @@ async fn unknown_peer_backs_off() {
             .name("test")
             .keypair(a.keypair.clone())
             .bind(a.addr().into())
+            .transport(t)
             .parties([(a.key, a.addr().into())])
             .receive_timeout(Duration::from_secs(5))
             .connect_retry_delays(vec![1])
@@ -602,6 +778,7 @@ async fn unknown_peer_backs_off() {
             .name("test")
             .keypair(c.keypair.clone())
             .bind(c.addr().into())
+            .transport(t)
             .parties([(a.key, a.addr().into()), (c.key, c.addr().into())])
             .receive_timeout(Duration::from_secs(5))
             .connect_retry_delays(vec![1])
@@ -631,8 +808,7 @@ async fn unknown_peer_backs_off() {
 /// Updating a party's address via `add_peers` reconnects to the new address
 /// and preserves the peer's retry state (messages queued before the update
 /// are still delivered after reconnecting).
-#[tokio::test]
-async fn update_party_address() {
+async fn update_party_address(t: Transport) {
     let a = Node::new(reserve_port());
     let b1 = Node::new(reserve_port());
     let pka = a.key;
@@ -640,8 +816,8 @@ async fn update_party_address() {
 
     // A knows B at its first address.
     let all = [&a, &b1];
-    let net_a = Network::create(make_config(&a, &all)).await.unwrap();
-    let mut net_b1 = Network::create(make_config(&b1, &all)).await.unwrap();
+    let net_a = Network::create(make_config(&a, &all, t)).await.unwrap();
+    let mut net_b1 = Network::create(make_config(&b1, &all, t)).await.unwrap();
 
     sleep(SETTLE).await;
 
@@ -664,7 +840,7 @@ async fn update_party_address() {
         keypair: b1.keypair.clone(),
     };
     let all_b2 = [&a, &b2];
-    let mut net_b2 = Network::create(make_config(&b2, &all_b2)).await.unwrap();
+    let mut net_b2 = Network::create(make_config(&b2, &all_b2, t)).await.unwrap();
 
     // Send a message while B is down — it goes into A's retry queue.
     net_a.unicast(Slot::MIN, pkb, b"during".to_vec()).unwrap();
@@ -696,8 +872,7 @@ async fn update_party_address() {
 /// Cliquenet does not relay: B and C have unreachable addresses for one
 /// another, so neither ever dials the other and B's messages to C are not
 /// delivered, even though both can talk to A.
-#[tokio::test]
-async fn no_relay_between_disconnected_peers() {
+async fn no_relay_between_disconnected_peers(t: Transport) {
     let a = Node::new(reserve_port());
     let b = Node::new(reserve_port());
     let c = Node::new(reserve_port());
@@ -713,13 +888,13 @@ async fn no_relay_between_disconnected_peers() {
         keypair: c.keypair.clone(),
     };
 
-    let mut net_a = Network::create(make_config(&a, &[&a, &b, &c]))
+    let mut net_a = Network::create(make_config(&a, &[&a, &b, &c], t))
         .await
         .unwrap();
-    let mut net_b = Network::create(make_config(&b, &[&a, &b, &dead_c]))
+    let mut net_b = Network::create(make_config(&b, &[&a, &b, &dead_c], t))
         .await
         .unwrap();
-    let mut net_c = Network::create(make_config(&c, &[&a, &dead_b, &c]))
+    let mut net_c = Network::create(make_config(&c, &[&a, &dead_b, &c], t))
         .await
         .unwrap();
 
@@ -755,16 +930,15 @@ async fn no_relay_between_disconnected_peers() {
 /// Like [`update_party_address`], but the update arrives while the
 /// connection to the old address is alive, exercising the cancellation of
 /// an established connection instead of a redirected reconnect.
-#[tokio::test]
-async fn update_party_address_live_connection() {
+async fn update_party_address_live_connection(t: Transport) {
     let a = Node::new(reserve_port());
     let b1 = Node::new(reserve_port());
     let pka = a.key;
     let pkb = b1.key;
 
     let all = [&a, &b1];
-    let net_a = Network::create(make_config(&a, &all)).await.unwrap();
-    let mut net_b1 = Network::create(make_config(&b1, &all)).await.unwrap();
+    let net_a = Network::create(make_config(&a, &all, t)).await.unwrap();
+    let mut net_b1 = Network::create(make_config(&b1, &all, t)).await.unwrap();
 
     sleep(SETTLE).await;
 
@@ -795,7 +969,7 @@ async fn update_party_address_live_connection() {
     net_a.unicast(Slot::MIN, pkb, b"during".to_vec()).unwrap();
 
     let all_b2 = [&a, &b2];
-    let mut net_b2 = Network::create(make_config(&b2, &all_b2)).await.unwrap();
+    let mut net_b2 = Network::create(make_config(&b2, &all_b2, t)).await.unwrap();
 
     let (src, data) = timeout(Duration::from_secs(15), net_b2.receive())
         .await
@@ -812,3 +986,20 @@ async fn update_party_address_live_connection() {
     assert_eq!(src, pka);
     assert_eq!(data, Bytes::from("after"));
 }
+
+/// Well-behaved parties are not penalised and unknown parties have no reputation.
+async fn reputation(t: Transport) {
+    let (net_a, net_b, pka, pkb) = two_nodes(t).await;
+
+    for (net, peer) in [(&net_a, pkb), (&net_b, pka)] {
+        let r = net.reputation(peer).await.unwrap().expect("party is known");
+        assert_eq!(r.decode_errors, 0);
+        assert_eq!(r.oversize_frames, 0);
+        assert_eq!(r.handshake_failures, 0);
+        assert_eq!(r.bans, 0);
+        assert_eq!(r.banned_for, None);
+    }
+
+    let unknown = Keypair::generate().unwrap().public_key();
+    assert!(net_a.reputation(unknown).await.unwrap().is_none());
+}
@@ -0,0 +1,344 @@
+//! Manual control over block production, shared by every node of the dev node's network.
+
+use std::{collections::VecDeque, sync::Mutex};
+
+use anyhow::ensure;
+use espresso_types::{Payload, dev_hooks::BlockHooks};
+use hotshot_types::{traits::BlockPayload, utils::BuilderCommitment};
+use time::{Duration, OffsetDateTime};
+
+/// Manual control over block production, installed as the [`BlockHooks`] of every node in the
+/// process.
+///
+/// It lets the operator:
+///
+/// * pause block production, and later resume it or release a fixed number of blocks,
+/// * shift the clock used to timestamp (and validate) block headers,
+/// * pin the L1 head referenced by the next block,
+/// * replace the payload of upcoming blocks with crafted ones, which the
+///   [`DevBuilder`](crate::builder::DevBuilder) offers to leaders.
+///
+/// Pausing works by refusing to build headers above a fixed height. Consensus keeps running
+/// (views simply time out without a proposal), so a block only counts towards the limit once it
+/// can be decided: a block is decided by a proposal `decide_lag` blocks above it, which must
+/// itself be built. The decided height must be reported via
+/// [`record_decided`](Self::record_decided), since header construction does not observe decides.
+#[derive(Debug)]
+pub(crate) struct BlockControl {
+    /// Number of blocks proposed on top of a block before it is decided.
+    decide_lag: u64,
+    inner: Mutex<BlockControlState>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct BlockControlState {
+    /// Highest block height which may be decided, or [`None`] if production is not limited.
+    limit: Option<u64>,
+    /// Highest decided block height reported so far.
+    decided: u64,
+    /// Highest block height for which a header has been built.
+    proposed: u64,
+    /// Offset added to the system clock, in milliseconds.
+    clock_offset_ms: i64,
+    /// L1 head to reference in blocks at or above the given height, until one of them is decided.
+    l1_head: Option<(u64, u64)>,
+    /// Payloads to use for upcoming blocks, in order, with their builder commitments.
+    payloads: VecDeque<(Payload, BuilderCommitment)>,
+}
+
+impl BlockControlState {
+    /// The height the chain stops at if no more headers are built.
+    fn pending_height(&self, decide_lag: u64) -> u64 {
+        self.proposed.saturating_sub(decide_lag).max(self.decided)
+    }
+}
+
+impl BlockControl {
+    /// Control a network whose consensus protocol decides a block once `decide_lag` more blocks
+    /// have been proposed on top of it.
+    pub(crate) fn new(decide_lag: u64) -> Self {
+        Self {
+            decide_lag,
+            inner: Default::default(),
+        }
+    }
+
+    /// Whether block production is currently limited.
+    pub(crate) fn is_paused(&self) -> bool {
+        self.inner.lock().unwrap().limit.is_some()
+    }
+
+    /// Stop producing blocks beyond those already proposed.
+    ///
+    /// Returns the height at which production stops, once the proposed blocks are decided.
+    pub(crate) fn pause(&self) -> u64 {
+        let mut state = self.inner.lock().unwrap();
+        let height = state.pending_height(self.decide_lag);
+        *state.limit.get_or_insert(height)
+    }
+
+    /// Remove any limit on block production.
+    pub(crate) fn resume(&self) {
+        self.inner.lock().unwrap().limit = None;
+    }
+
+    /// Allow `n` more blocks to be decided while paused.
+    ///
+    /// If production is not paused, it is paused first. Returns the new limit, i.e. the height the
+    /// chain will stop at.
+    pub(crate) fn mine(&self, n: u64) -> u64 {
+        let mut state = self.inner.lock().unwrap();
+        let limit = state
+            .limit
+            .unwrap_or_else(|| state.pending_height(self.decide_lag))
+            + n;
+        state.limit = Some(limit);
+        limit
+    }
+
+    /// The height at which block production currently stops, if paused.
+    pub(crate) fn limit(&self) -> Option<u64> {
+        self.inner.lock().unwrap().limit
+    }
+
+    /// Highest decided block height reported so far.
+    pub(crate) fn decided(&self) -> u64 {
+        self.inner.lock().unwrap().decided
+    }
+
+    /// Height of the next block header that will be built.
+    pub(crate) fn next_height(&self) -> u64 {
+        self.inner.lock().unwrap().proposed + 1
+    }
+
+    /// Report a newly decided block height.
+    pub(crate) fn record_decided(&self, height: u64) {
+        let mut state = self.inner.lock().unwrap();
+        state.decided = state.decided.max(height);
+        state.proposed = state.proposed.max(height);
+        if let Some((from, _)) = state.l1_head
+            && state.decided >= from
+        {
+            state.l1_head = None;
+        }
+    }
+
+    /// Reset the tracked chain position, e.g. after restoring the network from a snapshot.
+    ///
+    /// Any pending L1 head override or injected payloads are dropped. If `paused`, production is
+    /// limited to `height`.
+    pub(crate) fn reset(&self, height: u64, paused: bool) {
+        let mut state = self.inner.lock().unwrap();
+        *state = BlockControlState {
+            limit: paused.then_some(height),
+            decided: height,
+            proposed: height,
+            clock_offset_ms: state.clock_offset_ms,
+            l1_head: None,
+            payloads: VecDeque::new(),
+        };
+    }
+
+    /// Move the clock so that it currently reads `timestamp` (in seconds since the epoch).
+    ///
+    /// The next block is timestamped accordingly and the clock keeps running from there, like
+    /// Anvil's `evm_setNextBlockTimestamp`. Timestamps never decrease across blocks, so moving
+    /// the clock backwards only holds timestamps still until it catches up with the parent.
+    pub(crate) fn set_next_timestamp(&self, timestamp: u64) {
+        let now_ms = OffsetDateTime::now_utc().unix_timestamp_nanos() / 1_000_000;
+        let offset = i128::from(timestamp) * 1_000 - now_ms;
+        self.inner.lock().unwrap().clock_offset_ms = offset as i64;
+    }
+
+    /// Reference L1 block `head` in the next block.
+    ///
+    /// The L1 head of a block never decreases, so subsequent blocks keep referencing at least
+    /// `head`. Returns the height of the block the override applies to.
+    pub(crate) fn set_next_l1_head(&self, head: u64) -> u64 {
+        let mut state = self.inner.lock().unwrap();
+        let height = state.proposed + 1;
+        state.l1_head = Some((height, head));
+        height
+    }
+
+    /// Use `payload` for an upcoming block instead of one built from pending transactions.
+    ///
+    /// Injected payloads are used in order, one per block, and pending transactions wait until
+    /// the queue is empty. A payload is consumed once a header committing to it is built. It is
+    /// not validated here, so a payload that consensus rejects costs one failed view and is then
+    /// dropped. Returns the number of payloads now queued.
+    pub(crate) fn inject_payload(&self, payload: Payload) -> usize {
+        let commitment = payload.builder_commitment(payload.ns_table());
+        let mut state = self.inner.lock().unwrap();
+        state.payloads.push_back((payload, commitment));
+        state.payloads.len()
+    }
+
+    /// Number of injected payloads not yet proposed.
+    pub(crate) fn injected_payloads(&self) -> usize {
+        self.inner.lock().unwrap().payloads.len()
+    }
+
+    /// The payload to build the next block from, if one has been injected.
+    pub(crate) fn next_payload(&self) -> Option<Payload> {
+        let state = self.inner.lock().unwrap();
+        state.payloads.front().map(|(payload, _)| payload.clone())
+    }
+
+    /// The queued payload with the given builder commitment, if any.
+    pub(crate) fn injected_payload(
+        &self,
+        builder_commitment: &BuilderCommitment,
+    ) -> Option<Payload> {
+        let state = self.inner.lock().unwrap();
+        state
+            .payloads
+            .iter()
+            .find(|(_, commitment)| commitment == builder_commitment)
+            .map(|(payload, _)| payload.clone())
+    }
+}
+
+impl BlockHooks for BlockControl {
+    /// Check whether a header at `height` may be built, and record it if so.
+    ///
+    /// Headers up to `decide_lag` blocks above the limit may be built, since they are needed to
+    /// decide the block at the limit. If the header commits to the next injected payload, that
+    /// payload is consumed.
+    fn begin_proposal(
+        &self,
+        height: u64,
+        builder_commitment: &BuilderCommitment,
+    ) -> anyhow::Result<()> {
+        let mut state = self.inner.lock().unwrap();
+        if let Some(limit) = state.limit {
+            ensure!(
+                height <= limit + self.decide_lag,
+                "block production is paused at height {limit}"
+            );
+        }
+        state.proposed = state.proposed.max(height);
+        if state
+            .payloads
+            .front()
+            .is_some_and(|(_, commitment)| commitment == builder_commitment)
+        {
+            state.payloads.pop_front();
+        }
+        Ok(())
+    }
+
+    fn l1_head(&self, height: u64) -> Option<u64> {
+        match self.inner.lock().unwrap().l1_head {
+            Some((from, head)) if height >= from => Some(head),
+            _ => None,
+        }
+    }
+
+    fn now(&self) -> OffsetDateTime {
+        let offset = self.inner.lock().unwrap().clock_offset_ms;
+        OffsetDateTime::now_utc() + Duration::milliseconds(offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use espresso_types::NsTable;
+
+    use super::*;
+
+    #[test]
+    fn test_block_control_mine_while_paused() {
+        let control = BlockControl::new(2);
+        let (empty, ns_table) = Payload::empty();
+        let commit = empty.builder_commitment(&ns_table);
+        control.record_decided(5);
+        assert!(control.begin_proposal(6, &commit).is_ok());
+        assert!(control.begin_proposal(7, &commit).is_ok());
+
+        // Blocks 6 and 7 are in flight, and decide block 5 once the next one is proposed.
+        assert_eq!(control.pause(), 5);
+        assert!(control.begin_proposal(7, &commit).is_ok());
+        assert!(control.begin_proposal(8, &commit).is_err());
+
+        // Stale reports of the decided height don't release any blocks.
+        control.record_decided(4);
+        assert!(control.begin_proposal(8, &commit).is_err());
+
+        assert_eq!(control.mine(2), 7);
+        assert!(control.begin_proposal(9, &commit).is_ok());
+        assert!(control.begin_proposal(10, &commit).is_err());
+        control.record_decided(7);
+        assert!(control.begin_proposal(10, &commit).is_err());
+
+        control.resume();
+        assert!(control.begin_proposal(10, &commit).is_ok());
+        assert_eq!(control.next_height(), 11);
+    }
+
+    #[test]
+    fn test_block_control_pause_without_lag() {
+        let control = BlockControl::new(0);
+        let (empty, ns_table) = Payload::empty();
+        let commit = empty.builder_commitment(&ns_table);
+        control.record_decided(2);
+        assert!(control.begin_proposal(3, &commit).is_ok());
+
+        assert_eq!(control.pause(), 3);
+        assert!(control.begin_proposal(3, &commit).is_ok());
+        assert!(control.begin_proposal(4, &commit).is_err());
+    }
+
+    #[test]
+    fn test_block_control_l1_head_override() {
+        let control = BlockControl::new(2);
+        control.record_decided(3);
+        assert_eq!(control.set_next_l1_head(42), 4);
+        assert_eq!(control.l1_head(3), None);
+        assert_eq!(control.l1_head(4), Some(42));
+        assert_eq!(control.l1_head(5), Some(42));
+
+        control.record_decided(4);
+        assert_eq!(control.l1_head(5), None);
+    }
+
+    #[test]
+    fn test_block_control_injected_payloads() {
+        let control = BlockControl::new(2);
+        let (empty, _) = Payload::empty();
+        let ns_table = NsTable::from_bytes_unchecked(&[1, 0, 0, 0, 7, 0, 0, 0, 3, 0, 0, 0]);
+        let crafted = Payload::from_bytes(&[0xff; 3], &ns_table);
+        assert_eq!(control.inject_payload(crafted.clone()), 1);
+        assert_eq!(control.inject_payload(empty.clone()), 2);
+
+        // Proposing some other payload, e.g. a fallback empty block, doesn't consume anything.
+        assert_eq!(control.next_payload(), Some(crafted.clone()));
+        assert_eq!(
+            control.injected_payload(&empty.builder_commitment(empty.ns_table())),
+            Some(empty.clone())
+        );
+        let other = Payload::from_bytes(&[], &ns_table);
+        control
+            .begin_proposal(1, &other.builder_commitment(&ns_table))
+            .unwrap();
+        assert_eq!(control.injected_payloads(), 2);
+
+        control
+            .begin_proposal(2, &crafted.builder_commitment(&ns_table))
+            .unwrap();
+        assert_eq!(control.next_payload(), Some(empty.clone()));
+        control
+            .begin_proposal(3, &empty.builder_commitment(empty.ns_table()))
+            .unwrap();
+        assert_eq!(control.next_payload(), None);
+    }
+
+    #[test]
+    fn test_block_control_clock() {
+        let control = BlockControl::new(2);
+        let target = OffsetDateTime::now_utc().unix_timestamp() as u64 + 3600;
+        control.set_next_timestamp(target);
+        let now = control.now().unix_timestamp() as u64;
+        assert!(now.abs_diff(target) <= 1, "{now} != {target}");
+    }
+}
@@ -0,0 +1,225 @@
+//! The builder API the dev node's sequencer nodes get their blocks from.
+
+use std::{marker::PhantomData, sync::Arc, time::Duration};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use espresso_types::{
+    EpochVersion, FeeAccount, Payload, PubKey, SeqTypes, eth_signature_key::EthKeyPair,
+};
+use futures::future::BoxFuture;
+use hotshot_builder_api::v0_1::{
+    block_info::{AvailableBlockData, AvailableBlockHeaderInputV1, AvailableBlockInfo},
+    builder::{BuildError, Error, Options, define_api},
+    data_source::BuilderDataSource,
+};
+use hotshot_task_impls::builder::{BuilderClientError, v0_1::BuilderClient};
+use hotshot_types::{
+    constants::LEGACY_BUILDER_MODULE,
+    data::VidCommitment,
+    traits::{
+        BlockPayload, EncodeBytes,
+        signature_key::{BuilderSignatureKey, SignatureKey},
+    },
+    utils::BuilderCommitment,
+};
+use tide_disco::{App, method::ReadState};
+use url::Url;
+use vbs::version::StaticVersionType;
+
+use crate::block_control::BlockControl;
+
+type Signature = <PubKey as SignatureKey>::PureAssembledSignatureType;
+
+/// How long to wait for the legacy builder to respond.
+const LEGACY_BUILDER_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A builder in front of the legacy builder which offers payloads injected into [`BlockControl`].
+///
+/// While a payload is queued, it is the only block offered to leaders, so submitted transactions
+/// wait in the legacy builder until the queue is empty. Otherwise every request is forwarded to
+/// the legacy builder. Injected payloads are signed with the legacy builder's key, whose fee
+/// account is funded at genesis.
+#[derive(Clone)]
+pub(crate) struct DevBuilder {
+    control: Arc<BlockControl>,
+    legacy: Arc<BuilderClient<SeqTypes>>,
+    keys: (FeeAccount, EthKeyPair),
+    base_fee: u64,
+}
+
+impl DevBuilder {
+    /// Wrap the legacy builder at `legacy_url`, charging `base_fee` per byte for injected payloads.
+    pub(crate) fn new(
+        control: Arc<BlockControl>,
+        legacy_url: Url,
+        key: EthKeyPair,
+        base_fee: u64,
+    ) -> Self {
+        Self {
+            control,
+            legacy: Arc::new(BuilderClient::new(legacy_url, LEGACY_BUILDER_TIMEOUT)),
+            keys: (key.fee_account(), key),
+            base_fee,
+        }
+    }
+
+    /// Serve the builder API on `port`.
+    pub(crate) async fn serve(self, port: u16) -> anyhow::Result<()> {
+        let api = define_api::<Self, SeqTypes>(&Options::default())
+            .context("constructing the builder API")?;
+        let mut app: App<Self, Error> = App::with_state(self);
+        app.register_module(LEGACY_BUILDER_MODULE, api)
+            .context("registering the builder API")?;
+        let url: Url = format!("http://0.0.0.0:{port}").parse()?;
+        app.serve(url, EpochVersion::instance())
+            .await
+            .context("serving the builder API")
+    }
+
+    fn offered_fee(&self, payload: &Payload) -> u64 {
+        self.base_fee * payload.encode().len() as u64
+    }
+
+    fn block_data(&self, payload: Payload) -> Result<AvailableBlockData<SeqTypes>, BuildError> {
+        let metadata = payload.ns_table().clone();
+        let signature = FeeAccount::sign_builder_message(
+            &self.keys.1,
+            payload.builder_commitment(&metadata).as_ref(),
+        )
+        .map_err(|err| BuildError::Error(err.to_string()))?;
+        Ok(AvailableBlockData {
+            block_payload: payload,
+            metadata,
+            signature,
+            sender: self.keys.0,
+        })
+    }
+}
+
+#[async_trait]
+impl ReadState for DevBuilder {
+    type State = Self;
+
+    async fn read<T>(
+        &self,
+        op: impl Send + for<'a> FnOnce(&'a Self::State) -> BoxFuture<'a, T> + 'async_trait,
+    ) -> T {
+        op(self).await
+    }
+}
+
+#[async_trait]
+impl BuilderDataSource<SeqTypes> for DevBuilder {
+    async fn available_blocks(
+        &self,
+        for_parent: &VidCommitment,
+        view_number: u64,
+        sender: PubKey,
+        signature: &Signature,
+    ) -> Result<Vec<AvailableBlockInfo<SeqTypes>>, BuildError> {
+        let Some(payload) = self.control.next_payload() else {
+            return self
+                .legacy
+                .available_blocks(*for_parent, view_number, sender, signature)
+                .await
+                .map_err(build_error);
+        };
+
+        let block_hash = payload.builder_commitment(payload.ns_table());
+        let block_size = payload.encode().len() as u64;
+        let offered_fee = self.offered_fee(&payload);
+        let signature =
+            FeeAccount::sign_block_info(&self.keys.1, block_size, offered_fee, &block_hash)
+                .map_err(|err| BuildError::Error(err.to_string()))?;
+        Ok(vec![AvailableBlockInfo {
+            block_hash,
+            block_size,
+            offered_fee,
+            signature,
+            sender: self.keys.0,
+            _phantom: PhantomData,
+        }])
+    }
+
+    async fn claim_block(
+        &self,
+        block_hash: &BuilderCommitment,
+        view_number: u64,
+        sender: PubKey,
+        signature: &Signature,
+    ) -> Result<AvailableBlockData<SeqTypes>, BuildError> {
+        match self.control.injected_payload(block_hash) {
+            Some(payload) => self.block_data(payload),
+            None => self
+                .legacy
+                .claim_block(block_hash.clone(), view_number, sender, signature)
+                .await
+                .map_err(build_error),
+        }
+    }
+
+    async fn claim_block_with_num_nodes(
+        &self,
+        block_hash: &BuilderCommitment,
+        view_number: u64,
+        sender: PubKey,
+        signature: &Signature,
+        num_nodes: usize,
+    ) -> Result<AvailableBlockData<SeqTypes>, BuildError> {
+        match self.control.injected_payload(block_hash) {
+            Some(payload) => self.block_data(payload),
+            None => self
+                .legacy
+                .claim_block_with_num_nodes(
+                    block_hash.clone(),
+                    view_number,
+                    sender,
+                    signature,
+                    num_nodes,
+                )
+                .await
+                .map_err(build_error),
+        }
+    }
+
+    async fn claim_block_header_input(
+        &self,
+        block_hash: &BuilderCommitment,
+        view_number: u64,
+        sender: PubKey,
+        signature: &Signature,
+    ) -> Result<AvailableBlockHeaderInputV1<SeqTypes>, BuildError> {
+        let Some(payload) = self.control.injected_payload(block_hash) else {
+            let input = self
+                .legacy
+                .claim_block_header_input(block_hash.clone(), view_number, sender, signature)
+                .await
+                .map_err(build_error)?;
+            return Ok(AvailableBlockHeaderInputV1 {
+                fee_signature: input.fee_signature,
+                sender: input.sender,
+            });
+        };
+
+        let fee_signature =
+            FeeAccount::sign_fee(&self.keys.1, self.offered_fee(&payload), payload.ns_table())
+                .map_err(|err| BuildError::Error(err.to_string()))?;
+        Ok(AvailableBlockHeaderInputV1 {
+            fee_signature,
+            sender: self.keys.0,
+        })
+    }
+
+    async fn builder_address(&self) -> Result<FeeAccount, BuildError> {
+        Ok(self.keys.0)
+    }
+}
+
+fn build_error(err: BuilderClientError) -> BuildError {
+    match err {
+        BuilderClientError::BlockNotFound => BuildError::NotFound,
+        BuilderClientError::BlockMissing => BuildError::Missing,
+        BuilderClientError::Api(message) => BuildError::Error(message),
+    }
+}
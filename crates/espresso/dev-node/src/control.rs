@@ -0,0 +1,368 @@
+//! Block production control and whole-network snapshots for the dev node.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use alloy::{
+    primitives::U256,
+    providers::{ProviderBuilder, ext::AnvilApi},
+};
+use anyhow::{Context, anyhow, bail, ensure};
+use espresso_dev_node::{BlockProductionStatus, InjectPayloadReqBody, SnapshotInfo};
+use espresso_node::{
+    api::test_helpers::{TestNetwork, TestNetworkConfigBuilder},
+    catchup::NullStateCatchup,
+    persistence,
+};
+use espresso_types::{
+    NamespaceId, NsPayloadBuilder, NsTable, NsTableBuilder, Payload, Transaction,
+};
+use hotshot_types::traits::BlockPayload;
+use sqlx::{ConnectOptions, sqlite::SqliteConnectOptions};
+use tempfile::TempDir;
+use tokio::{
+    sync::Mutex,
+    time::{sleep, timeout},
+};
+use url::Url;
+use versions::Upgrade;
+
+use crate::{NUM_NODES, block_control::BlockControl};
+
+type Network = TestNetwork<persistence::sql::Options, NUM_NODES>;
+type NetworkConfig =
+    TestNetworkConfigBuilder<NUM_NODES, persistence::sql::Options, NullStateCatchup>;
+
+/// How long to wait for each block when mining.
+const MINE_TIMEOUT_PER_BLOCK: Duration = Duration::from_secs(30);
+
+/// How often the decided height is polled.
+const DECIDE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The dev node's sequencer network, along with everything needed to snapshot it and restart it
+/// from a snapshot.
+pub(crate) struct DevNetwork {
+    network: Option<Network>,
+    config: NetworkConfig,
+    upgrade: Upgrade,
+    control: Arc<BlockControl>,
+    /// SQLite databases backing the network: each node's consensus storage and the query service.
+    databases: Vec<PathBuf>,
+    /// Addresses the network listens on, which must be released before it can be restarted.
+    listen_addrs: Vec<String>,
+    /// RPC endpoints of the L1 and any alternate chains, which must all be Anvil nodes.
+    providers: Vec<Url>,
+    snapshot_dir: TempDir,
+    snapshots: BTreeMap<u64, Snapshot>,
+    next_snapshot_id: u64,
+}
+
+struct Snapshot {
+    height: u64,
+    paused: bool,
+    /// Anvil snapshot ID for each of the providers.
+    anvil_ids: Vec<U256>,
+}
+
+impl DevNetwork {
+    /// Start the network.
+    ///
+    /// `control` must be installed as the block hooks of this process, and `databases` must
+    /// include every SQLite database the network writes to.
+    pub(crate) async fn start(
+        config: NetworkConfig,
+        upgrade: Upgrade,
+        control: Arc<BlockControl>,
+        databases: Vec<PathBuf>,
+        listen_addrs: Vec<String>,
+        providers: Vec<Url>,
+    ) -> anyhow::Result<Self> {
+        let network = TestNetwork::new(config.clone().build(), upgrade).await;
+        Ok(Self {
+            network: Some(network),
+            config,
+            upgrade,
+            control,
+            databases,
+            listen_addrs,
+            providers,
+            snapshot_dir: TempDir::new().context("creating snapshot directory")?,
+            snapshots: BTreeMap::new(),
+            next_snapshot_id: 0,
+        })
+    }
+
+    pub(crate) fn network(&self) -> &Network {
+        self.network.as_ref().expect("network is running")
+    }
+
+    /// Report the latest decided height to the block production control.
+    ///
+    /// The control relies on this to know when to stop producing blocks.
+    pub(crate) async fn track_decided(network: Arc<Mutex<Self>>) {
+        loop {
+            {
+                let network = network.lock().await;
+                if let Some(running) = &network.network {
+                    let height = running.server.decided_leaf().await.height();
+                    network.control.record_decided(height);
+                }
+            }
+            sleep(DECIDE_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Take a snapshot of the sequencer storage and every Anvil chain.
+    ///
+    /// Block production is paused while the databases are copied, and resumed afterwards unless
+    /// it was already paused. Blocks proposed before pausing are decided first, so the snapshot
+    /// is taken at the height the chain stops at.
+    pub(crate) async fn snapshot(&mut self) -> anyhow::Result<SnapshotInfo> {
+        let paused = self.control.is_paused();
+        let height = self.control.pause();
+        let res = match self.wait_for_decided(height).await {
+            Ok(()) => self.snapshot_at(height, paused).await,
+            Err(err) => Err(err),
+        };
+        if !paused {
+            self.control.resume();
+        }
+        res
+    }
+
+    /// Wait for the network to decide `height`.
+    ///
+    /// This polls the network directly, since [`track_decided`](Self::track_decided) can't report
+    /// decides while we hold the network.
+    async fn wait_for_decided(&self, height: u64) -> anyhow::Result<()> {
+        timeout(MINE_TIMEOUT_PER_BLOCK, async {
+            loop {
+                let decided = self.network().server.decided_leaf().await.height();
+                self.control.record_decided(decided);
+                if decided >= height {
+                    break;
+                }
+                sleep(DECIDE_POLL_INTERVAL).await;
+            }
+        })
+        .await
+        .with_context(|| format!("timed out waiting for block {height}"))
+    }
+
+    async fn snapshot_at(&mut self, height: u64, paused: bool) -> anyhow::Result<SnapshotInfo> {
+        let id = self.next_snapshot_id;
+        let dir = self.snapshot_path(id);
+        fs::create_dir_all(&dir).context("creating snapshot directory")?;
+        for (i, db) in self.databases.iter().enumerate() {
+            copy_database(db, &dir.join(format!("{i}.db")))
+                .await
+                .with_context(|| format!("snapshotting database {}", db.display()))?;
+        }
+
+        let mut anvil_ids = Vec::with_capacity(self.providers.len());
+        for url in &self.providers {
+            let provider = ProviderBuilder::new().connect_http(url.clone());
+            let anvil_id = provider
+                .evm_snapshot()
+                .await
+                .with_context(|| format!("snapshotting Anvil at {url}"))?;
+            anvil_ids.push(anvil_id);
+        }
+
+        self.next_snapshot_id += 1;
+        self.snapshots.insert(
+            id,
+            Snapshot {
+                height,
+                paused,
+                anvil_ids,
+            },
+        );
+        tracing::info!(id, height, "took dev node snapshot");
+        Ok(SnapshotInfo { id, height })
+    }
+
+    /// Restore the sequencer storage and every Anvil chain to a previous snapshot.
+    ///
+    /// The sequencer network is shut down and restarted from the restored storage, so this takes
+    /// a few seconds. Block production is restored to the state it was in when the snapshot was
+    /// taken. Like Anvil, reverting discards all snapshots taken after `id`, but the snapshot
+    /// itself can be reverted to again.
+    pub(crate) async fn revert(&mut self, id: u64) -> anyhow::Result<BlockProductionStatus> {
+        let Some(snapshot) = self.snapshots.get(&id) else {
+            bail!("unknown snapshot {id}");
+        };
+        let (height, paused) = (snapshot.height, snapshot.paused);
+
+        self.control.pause();
+        if let Some(network) = self.network.take() {
+            network.shut_down().await;
+        }
+        for addr in &self.listen_addrs {
+            wait_for_release(addr).await?;
+        }
+
+        let dir = self.snapshot_path(id);
+        for (i, db) in self.databases.iter().enumerate() {
+            restore_database(&dir.join(format!("{i}.db")), db)
+                .with_context(|| format!("restoring database {}", db.display()))?;
+        }
+
+        let snapshot = self.snapshots.get_mut(&id).expect("snapshot exists");
+        for (url, anvil_id) in self.providers.iter().zip(&mut snapshot.anvil_ids) {
+            let provider = ProviderBuilder::new().connect_http(url.clone());
+            let reverted = provider
+                .evm_revert(*anvil_id)
+                .await
+                .with_context(|| format!("reverting Anvil at {url}"))?;
+            ensure!(reverted, "Anvil at {url} has no snapshot {anvil_id}");
+            // Anvil consumes a snapshot when reverting to it, so take it again to allow reverting
+            // to the same point more than once.
+            *anvil_id = provider
+                .evm_snapshot()
+                .await
+                .with_context(|| format!("snapshotting Anvil at {url}"))?;
+        }
+        let discarded = self.snapshots.split_off(&(id + 1));
+        for later in discarded.keys() {
+            let _ = fs::remove_dir_all(self.snapshot_path(*later));
+        }
+        self.next_snapshot_id = id + 1;
+
+        self.control.reset(height, paused);
+        self.network = Some(TestNetwork::new(self.config.clone().build(), self.upgrade).await);
+        tracing::info!(id, height, "reverted dev node to snapshot");
+        Ok(block_production_status(&self.control))
+    }
+
+    pub(crate) fn has_snapshot(&self, id: u64) -> bool {
+        self.snapshots.contains_key(&id)
+    }
+
+    fn snapshot_path(&self, id: u64) -> PathBuf {
+        self.snapshot_dir.path().join(id.to_string())
+    }
+}
+
+/// Allow `blocks` more blocks and wait for them to be decided.
+pub(crate) async fn mine(
+    control: &BlockControl,
+    blocks: u64,
+) -> anyhow::Result<BlockProductionStatus> {
+    let target = control.mine(blocks);
+    timeout(MINE_TIMEOUT_PER_BLOCK * blocks.max(1) as u32, async {
+        while control.decided() < target {
+            sleep(DECIDE_POLL_INTERVAL).await;
+        }
+    })
+    .await
+    .with_context(|| format!("timed out waiting for block {target}"))?;
+    Ok(block_production_status(control))
+}
+
+pub(crate) fn block_production_status(control: &BlockControl) -> BlockProductionStatus {
+    BlockProductionStatus {
+        paused: control.is_paused(),
+        height: control.decided(),
+        limit: control.limit(),
+        next_height: control.next_height(),
+        injected_payloads: control.injected_payloads(),
+    }
+}
+
+/// Assemble a payload to inject from a request.
+///
+/// The namespace table must pass the checks consensus applies to it, since a block with an invalid
+/// table can never be decided. The contents of each namespace are used as given.
+pub(crate) fn build_payload(
+    body: InjectPayloadReqBody,
+    max_block_size: u64,
+) -> anyhow::Result<Payload> {
+    let mut raw_payload = Vec::new();
+    let mut ns_table = NsTableBuilder::new();
+    for ns in body.namespaces {
+        let id = NamespaceId::from(ns.id);
+        match ns.raw {
+            Some(raw) => {
+                ensure!(
+                    ns.transactions.is_empty(),
+                    "namespace {} has both raw bytes and transactions",
+                    ns.id
+                );
+                raw_payload.extend_from_slice(&raw);
+            },
+            None => {
+                let mut builder = NsPayloadBuilder::default();
+                for tx in ns.transactions {
+                    builder.append_tx(Transaction::new(id, tx.to_vec()));
+                }
+                raw_payload.extend(builder.into_bytes());
+            },
+        }
+        ns_table.append_entry(id, raw_payload.len());
+    }
+    let mut ns_table = ns_table.into_ns_table();
+
+    if let Some(bytes) = body.ns_table {
+        ns_table = NsTable::from_bytes_unchecked(&bytes);
+    }
+    if let Some(bytes) = body.raw_payload {
+        raw_payload = bytes.to_vec();
+    }
+    ensure!(
+        raw_payload.len() as u64 <= max_block_size,
+        "payload of {} bytes exceeds the maximum block size of {max_block_size} bytes",
+        raw_payload.len()
+    );
+
+    let payload = Payload::from_bytes(&raw_payload, &ns_table);
+    payload
+        .ns_table()
+        .validate(&payload.byte_len())
+        .map_err(|err| anyhow!("invalid namespace table: {err}"))?;
+    Ok(payload)
+}
+
+/// Copy a live SQLite database into a new file.
+///
+/// `VACUUM INTO` gives a consistent copy even while other connections are writing.
+async fn copy_database(src: &Path, dest: &Path) -> anyhow::Result<()> {
+    let mut conn = SqliteConnectOptions::new().filename(src).connect().await?;
+    sqlx::query("VACUUM INTO $1")
+        .bind(dest.to_string_lossy())
+        .execute(&mut conn)
+        .await?;
+    Ok(())
+}
+
+/// Replace a database, which must not be in use, with a snapshot.
+fn restore_database(snapshot: &Path, db: &Path) -> anyhow::Result<()> {
+    // Stale write-ahead log files would otherwise be replayed on top of the restored database.
+    for suffix in ["-wal", "-shm"] {
+        let mut path = db.as_os_str().to_owned();
+        path.push(suffix);
+        if let Err(err) = fs::remove_file(&path)
+            && err.kind() != std::io::ErrorKind::NotFound
+        {
+            return Err(err.into());
+        }
+    }
+    fs::copy(snapshot, db)?;
+    Ok(())
+}
+
+/// Wait until nothing is listening on `addr` any more.
+async fn wait_for_release(addr: &str) -> anyhow::Result<()> {
+    timeout(Duration::from_secs(60), async {
+        while std::net::TcpListener::bind(addr).is_err() {
+            sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+    .with_context(|| format!("{addr} was not released after shutting down the network"))
+}
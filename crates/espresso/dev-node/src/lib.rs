@@ -1,6 +1,6 @@
 use std::fmt;
 
-use alloy::primitives::Address;
+use alloy::primitives::{Address, Bytes};
 use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 use url::Url;
@@ -34,6 +34,82 @@ pub struct SetHotshotUpReqBody {
     pub chain_id: u64,
 }
 
+/// Current state of block production, as returned by the block control endpoints.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockProductionStatus {
+    /// Whether block production is paused (possibly with some blocks left to mine).
+    pub paused: bool,
+    /// Latest decided block height.
+    pub height: u64,
+    /// Height at which block production stops, if paused.
+    pub limit: Option<u64>,
+    /// Height of the next block to be built, which `set-next-block` overrides apply to.
+    pub next_height: u64,
+    /// Number of injected payloads waiting to be included in a block.
+    pub injected_payloads: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MineReqBody {
+    pub blocks: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SetNextBlockReqBody {
+    /// Timestamp of the next block, in seconds since the Unix epoch.
+    pub timestamp: Option<u64>,
+    /// L1 block number for the next block to reference as its L1 head.
+    pub l1_head: Option<u64>,
+}
+
+/// A crafted payload to use for an upcoming block.
+///
+/// The payload is assembled from `namespaces`, in order, with a well-formed namespace table.
+/// `ns_table` and `raw_payload` replace either half with arbitrary bytes. An empty body forces an
+/// empty block.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct InjectPayloadReqBody {
+    #[serde(default)]
+    pub namespaces: Vec<InjectedNamespace>,
+    /// Namespace table bytes to use instead of the table derived from `namespaces`.
+    pub ns_table: Option<Bytes>,
+    /// Payload bytes to use instead of the concatenated `namespaces`.
+    pub raw_payload: Option<Bytes>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct InjectedNamespace {
+    pub id: u32,
+    /// Transactions to encode with a well-formed transaction table.
+    #[serde(default)]
+    pub transactions: Vec<Bytes>,
+    /// Namespace bytes to use verbatim instead of `transactions`, e.g. a malformed transaction
+    /// table.
+    pub raw: Option<Bytes>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InjectedPayloadInfo {
+    /// Number of injected payloads waiting to be included, including this one.
+    pub queued: usize,
+    /// Byte length of the payload.
+    pub size: u64,
+    /// Namespace table of the payload.
+    pub ns_table: Bytes,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotInfo {
+    pub id: u64,
+    /// Decided block height the snapshot was taken at.
+    pub height: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RevertReqBody {
+    pub id: u64,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 pub enum DevNodeVersion {
     #[value(name = "0.3")]
@@ -32,7 +32,9 @@ use espresso_contract_deployer::{
     HttpProviderWithWallet, network_config::light_client_genesis_from_stake_table,
 };
 use espresso_dev_node::{
-    AltChainInfo, DevInfo, DevNodeVersion, SetHotshotDownReqBody, SetHotshotUpReqBody,
+    AltChainInfo, BlockProductionStatus, DevInfo, DevNodeVersion, InjectPayloadReqBody,
+    InjectedPayloadInfo, MineReqBody, RevertReqBody, SetHotshotDownReqBody, SetHotshotUpReqBody,
+    SetNextBlockReqBody, SnapshotInfo,
 };
 use espresso_node::{
     SequencerApiVersion,
@@ -40,14 +42,14 @@ use espresso_node::{
         data_source::testing::TestableSequencerDataSource,
         options,
         sql::DataSource,
-        test_helpers::{STAKE_TABLE_CAPACITY_FOR_TEST, TestNetwork, TestNetworkConfigBuilder},
+        test_helpers::{STAKE_TABLE_CAPACITY_FOR_TEST, TestNetworkConfigBuilder},
     },
     persistence,
     state_signature::relay_server::{StateRelayServerState, run_relay_server_with_state},
-    testing::TestConfigBuilder,
+    testing::{TestConfig, TestConfigBuilder},
 };
 use espresso_types::{
-    L1ClientOptions, SeqTypes, ValidatedState, parse_duration, v0_3::ChainConfig,
+    L1ClientOptions, SeqTypes, ValidatedState, dev_hooks, parse_duration, v0_3::ChainConfig,
 };
 use espresso_utils::logging;
 use futures::{StreamExt, future::join_all, stream::FuturesUnordered};
@@ -55,6 +57,7 @@ use hotshot_contract_adapter::sol_types::LightClientV2Mock::{self, LightClientV2
 use hotshot_state_prover::{StateProverConfig, v2::service::run_prover_service};
 use hotshot_types::{
     stake_table::{HSStakeTable, one_honest_threshold},
+    traits::EncodeBytes,
     utils::epoch_from_block_number,
 };
 use http_wire::{cors_layer, healthcheck_response};
@@ -63,11 +66,19 @@ use serde::{Deserialize, Serialize};
 use staking_cli::demo::{DelegationConfig, StakingTransactions};
 use tempfile::NamedTempFile;
 use test_utils::reserve_tcp_port;
-use tokio::spawn;
+use tokio::{spawn, sync::Mutex};
 use url::Url;
 use vbs::version::StaticVersionType;
 use versions::Upgrade;
 
+use crate::{block_control::BlockControl, builder::DevBuilder, control::DevNetwork};
+
+mod block_control;
+mod builder;
+mod control;
+
+const NUM_NODES: usize = 2;
+
 #[derive(Default, Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 enum L1Deployment {
     /// Deploy everything
@@ -329,9 +340,25 @@ async fn async_main(migrated_envs: Vec<(&str, &str)>) -> anyhow::Result<()> {
         .parse()
         .unwrap();
 
+    // Use a fixed builder port, so the builder URL stays the same when the network is restarted
+    // from a snapshot.
+    let builder_port = builder_port.unwrap_or_else(|| reserve_tcp_port().unwrap());
+    // HotShot decides a block once two more blocks are proposed on top of it, while the new
+    // protocol decides each block in its own view.
+    let decide_lag = if matches!(version, DevNodeVersion::V0_6) {
+        0
+    } else {
+        2
+    };
+    let block_control = Arc::new(BlockControl::new(decide_lag));
+    dev_hooks::install(block_control.clone())?;
+    // Nodes get their blocks from a builder in front of the legacy builder, which offers injected
+    // payloads. It outlives network restarts, so it is not part of the network.
+    let dev_builder_port = reserve_tcp_port().unwrap();
     let network_config = TestConfigBuilder::default()
         .epoch_height(epoch_height)
-        .builder_port(builder_port)
+        .builder_port(Some(builder_port))
+        .node_builder_url(format!("http://localhost:{dev_builder_port}").parse()?)
         .stake_table_capacity(STAKE_TABLE_CAPACITY_FOR_TEST)
         .state_relay_url(relay_server_url.clone())
         .l1_url(l1_url.clone())
@@ -636,8 +663,6 @@ async fn async_main(migrated_envs: Vec<(&str, &str)>) -> anyhow::Result<()> {
         return Ok(());
     }
 
-    const NUM_NODES: usize = 2;
-
     let stake_table_address = l1_contracts
         .address(Contract::StakeTableProxy)
         .expect("stake table deployed");
@@ -650,6 +675,17 @@ async fn async_main(migrated_envs: Vec<(&str, &str)>) -> anyhow::Result<()> {
     };
     tracing::info!("Chain config: {chain_config:?}");
 
+    let dev_builder = DevBuilder::new(
+        block_control.clone(),
+        format!("http://localhost:{builder_port}").parse()?,
+        TestConfig::<NUM_NODES>::builder_key(),
+        chain_config
+            .base_fee
+            .as_u64()
+            .context("base fee overflows u64")?,
+    );
+    handles.push(spawn(dev_builder.serve(dev_builder_port)));
+
     let state = ValidatedState {
         chain_config: chain_config.into(),
         ..Default::default()
@@ -660,6 +696,7 @@ async fn async_main(migrated_envs: Vec<(&str, &str)>) -> anyhow::Result<()> {
     let config = network_config.hotshot_config();
     tracing::info!("Hotshot config {config:?}");
 
+    let query_db = sql.sqlite_path().to_path_buf();
     let api_options = options::Options::from(options::Http {
         port: sequencer_api_port,
         max_connections: sequencer_api_max_connections,
@@ -679,23 +716,44 @@ async fn async_main(migrated_envs: Vec<(&str, &str)>) -> anyhow::Result<()> {
         .try_into()
         .expect("one persistence per node");
 
+    // Everything the network listens on, which must be released before restarting it.
+    let listen_addrs = once(sequencer_api_port)
+        .chain(tonic_port)
+        .chain(once(builder_port))
+        .map(|port| format!("0.0.0.0:{port}"))
+        .chain((0..NUM_NODES).map(|i| network_config.coordinator_addr(i).to_string()))
+        .collect();
+    let databases = consensus_dbs
+        .iter()
+        .map(|db| db.path())
+        .chain(once(query_db))
+        .collect();
+
     let config = TestNetworkConfigBuilder::<NUM_NODES, _, _>::with_num_nodes()
         .api_config(api_options)
         .network_config(network_config)
         .states(states)
-        .persistences(persistences)
-        .build();
+        .persistences(persistences);
 
     // Start the nodes
-    let network = {
-        let u = match version {
-            DevNodeVersion::V0_3 => Upgrade::trivial(versions::version(0, 3)),
-            DevNodeVersion::V0_4 => Upgrade::trivial(versions::version(0, 4)),
-            DevNodeVersion::V0_5 => Upgrade::trivial(versions::version(0, 5)),
-            DevNodeVersion::V0_6 => Upgrade::trivial(versions::NEW_PROTOCOL_VERSION),
-        };
-        TestNetwork::new(config, u).await
+    let upgrade = match version {
+        DevNodeVersion::V0_3 => Upgrade::trivial(versions::version(0, 3)),
+        DevNodeVersion::V0_4 => Upgrade::trivial(versions::version(0, 4)),
+        DevNodeVersion::V0_5 => Upgrade::trivial(versions::version(0, 5)),
+        DevNodeVersion::V0_6 => Upgrade::trivial(versions::NEW_PROTOCOL_VERSION),
     };
+    let network = DevNetwork::start(
+        config,
+        upgrade,
+        block_control.clone(),
+        databases,
+        listen_addrs,
+        client_states.provider_urls.values().cloned().collect(),
+    )
+    .await?;
+    let builder_url: Url = format!("http://localhost:{builder_port}").parse()?;
+    let network = Arc::new(Mutex::new(network));
+    spawn(DevNetwork::track_decided(network.clone()));
 
     let relay_server_handle = spawn(async move {
         // using explicit relayer state will avoid it calling the dev-node on `/config/hotshot` for epoch info,
@@ -740,7 +798,7 @@ async fn async_main(migrated_envs: Vec<(&str, &str)>) -> anyhow::Result<()> {
     let l1_prover_port = prover_ports.remove(0);
 
     let dev_info = DevInfo {
-        builder_url: network.cfg.hotshot_config().builder_urls[0].clone(),
+        builder_url,
         sequencer_api_port,
         l1_prover_port,
         l1_url,
@@ -764,6 +822,9 @@ async fn async_main(migrated_envs: Vec<(&str, &str)>) -> anyhow::Result<()> {
         dev_node_port,
         client_states,
         dev_info,
+        network,
+        block_control,
+        max_block_size,
         SequencerApiVersion::instance(),
     ));
     handles.push(dev_node_handle);
@@ -838,6 +899,12 @@ impl ApiState {
 struct DevNodeState {
     api: ApiState,
     dev_info: Arc<DevInfo>,
+    network: Arc<Mutex<DevNetwork>>,
+    block_control: Arc<BlockControl>,
+    max_block_size: u64,
+    /// Whether the network runs the new protocol, which builds blocks locally instead of
+    /// requesting them from a builder.
+    new_protocol: bool,
 }
 
 /// Error envelope matching tide-disco's `ServerError`: `{"status": <code>, "message": <text>}`,
@@ -855,6 +922,10 @@ impl DevNodeError {
             message,
         }
     }
+
+    fn internal(err: anyhow::Error) -> Self {
+        Self::catch_all(StatusCode::INTERNAL_SERVER_ERROR, format!("{err:#}"))
+    }
 }
 
 impl IntoResponse for DevNodeError {
@@ -933,13 +1004,110 @@ async fn set_hotshot_up(
     Ok(Json(()))
 }
 
+async fn get_block_production(State(state): State<DevNodeState>) -> Json<BlockProductionStatus> {
+    Json(control::block_production_status(&state.block_control))
+}
+
+async fn pause_block_production(State(state): State<DevNodeState>) -> Json<BlockProductionStatus> {
+    state.block_control.pause();
+    Json(control::block_production_status(&state.block_control))
+}
+
+async fn resume_block_production(State(state): State<DevNodeState>) -> Json<BlockProductionStatus> {
+    state.block_control.resume();
+    Json(control::block_production_status(&state.block_control))
+}
+
+async fn mine(
+    State(state): State<DevNodeState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<BlockProductionStatus>, DevNodeError> {
+    let body: MineReqBody = json_body(&headers, &body)?;
+    let status = control::mine(&state.block_control, body.blocks)
+        .await
+        .map_err(DevNodeError::internal)?;
+    Ok(Json(status))
+}
+
+async fn set_next_block(
+    State(state): State<DevNodeState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<BlockProductionStatus>, DevNodeError> {
+    let body: SetNextBlockReqBody = json_body(&headers, &body)?;
+    if let Some(timestamp) = body.timestamp {
+        state.block_control.set_next_timestamp(timestamp);
+    }
+    if let Some(l1_head) = body.l1_head {
+        state.block_control.set_next_l1_head(l1_head);
+    }
+    Ok(Json(control::block_production_status(&state.block_control)))
+}
+
+async fn inject_payload(
+    State(state): State<DevNodeState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<InjectedPayloadInfo>, DevNodeError> {
+    if state.new_protocol {
+        return Err(DevNodeError::catch_all(
+            StatusCode::BAD_REQUEST,
+            "payload injection is not supported by the new protocol, which builds blocks locally"
+                .into(),
+        ));
+    }
+    let body: InjectPayloadReqBody = json_body(&headers, &body)?;
+    let payload = control::build_payload(body, state.max_block_size)
+        .map_err(|err| DevNodeError::catch_all(StatusCode::BAD_REQUEST, format!("{err:#}")))?;
+    let size = payload.byte_len().as_usize() as u64;
+    let ns_table = payload.ns_table().encode().to_vec().into();
+    let queued = state.block_control.inject_payload(payload);
+    Ok(Json(InjectedPayloadInfo {
+        queued,
+        size,
+        ns_table,
+    }))
+}
+
+async fn snapshot(State(state): State<DevNodeState>) -> Result<Json<SnapshotInfo>, DevNodeError> {
+    let snapshot = state
+        .network
+        .lock()
+        .await
+        .snapshot()
+        .await
+        .map_err(DevNodeError::internal)?;
+    Ok(Json(snapshot))
+}
+
+async fn revert(
+    State(state): State<DevNodeState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<BlockProductionStatus>, DevNodeError> {
+    let body: RevertReqBody = json_body(&headers, &body)?;
+    let mut network = state.network.lock().await;
+    if !network.has_snapshot(body.id) {
+        return Err(DevNodeError::catch_all(
+            StatusCode::NOT_FOUND,
+            format!("Snapshot {} not found", body.id),
+        ));
+    }
+    let status = network
+        .revert(body.id)
+        .await
+        .map_err(DevNodeError::internal)?;
+    Ok(Json(status))
+}
+
 async fn healthcheck(headers: HeaderMap) -> Response {
     healthcheck_response(&headers)
 }
 
-/// Serves the dev-info/set-hotshot-down/set-hotshot-up routes at both the `/v0/api/...` forms
-/// tide-disco served directly and the unversioned `/api/...` forms it served via a redirect
-/// (used by the Go SDK and our HTTP clients, respectively).
+/// Serves the dev node routes at both the `/v0/api/...` forms tide-disco served directly and the
+/// unversioned `/api/...` forms it served via a redirect (used by the Go SDK and our HTTP clients,
+/// respectively).
 fn dev_node_router(state: DevNodeState) -> Router {
     let api = Router::new().nest(
         "/api",
@@ -947,6 +1115,14 @@ fn dev_node_router(state: DevNodeState) -> Router {
             .route("/dev-info", get(get_dev_info))
             .route("/set-hotshot-down", post(set_hotshot_down))
             .route("/set-hotshot-up", post(set_hotshot_up))
+            .route("/block-production", get(get_block_production))
+            .route("/pause", post(pause_block_production))
+            .route("/resume", post(resume_block_production))
+            .route("/mine", post(mine))
+            .route("/set-next-block", post(set_next_block))
+            .route("/inject-payload", post(inject_payload))
+            .route("/snapshot", post(snapshot))
+            .route("/revert", post(revert))
             .with_state(state),
     );
 
@@ -961,11 +1137,18 @@ async fn run_dev_node_server<ApiVer: StaticVersionType + 'static>(
     port: u16,
     client_states: ApiState,
     dev_info: DevInfo,
+    network: Arc<Mutex<DevNetwork>>,
+    block_control: Arc<BlockControl>,
+    max_block_size: u64,
     _bind_version: ApiVer,
 ) -> anyhow::Result<()> {
     let state = DevNodeState {
         api: client_states,
         dev_info: Arc::new(dev_info),
+        network,
+        block_control,
+        max_block_size,
+        new_protocol: matches!(version, DevNodeVersion::V0_6),
     };
     let router = dev_node_router(state);
 
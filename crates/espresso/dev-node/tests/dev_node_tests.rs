@@ -4,13 +4,15 @@ use alloy::{
     network::EthereumWallet,
     node_bindings::{Anvil, AnvilInstance},
     primitives::U256,
-    providers::{Provider, ProviderBuilder},
+    providers::{Provider, ProviderBuilder, ext::AnvilApi},
     signers::local::{MnemonicBuilder, coins_bip39::English},
 };
 use committable::{Commitment, Committable};
 use escargot::CargoBuild;
 use espresso_dev_node::{
-    AltChainInfo, DevInfo, DevNodeVersion, SetHotshotDownReqBody, SetHotshotUpReqBody,
+    AltChainInfo, BlockProductionStatus, DevInfo, DevNodeVersion, InjectPayloadReqBody,
+    InjectedNamespace, InjectedPayloadInfo, MineReqBody, RevertReqBody, SetHotshotDownReqBody,
+    SetHotshotUpReqBody, SetNextBlockReqBody, SnapshotInfo,
 };
 use espresso_node::SequencerApiVersion;
 use espresso_types::{BlockMerkleTree, Header, NamespaceProofQueryData, SeqTypes, Transaction};
@@ -20,6 +22,7 @@ use hotshot_query_service::{
     availability::{BlockQueryData, TransactionQueryData, VidCommonQueryData},
     explorer::TransactionDetailResponse,
 };
+use hotshot_types::traits::EncodeBytes;
 use http_client::{Client, error::ClientErr};
 use jf_merkle_tree_compat::MerkleTreeScheme;
 use rand::Rng;
@@ -548,3 +551,335 @@ async fn slow_dev_node_multiple_lc_providers_test() {
     drop(process);
     drop(alt_providers);
 }
+
+/// Wait for the query service to reach block `height`, returning its latest block height.
+async fn wait_for_query_height(
+    api_client: &Client<ClientErr, SequencerApiVersion>,
+    height: u64,
+) -> u64 {
+    loop {
+        match api_client.get::<u64>("status/block-height").send().await {
+            Ok(block_height) if block_height > height => return block_height - 1,
+            res => {
+                tracing::info!(?res, height, "waiting for query service");
+                sleep(Duration::from_secs(1)).await;
+            },
+        }
+    }
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn slow_dev_node_block_control_test() {
+    let builder_port = reserve_tcp_port().unwrap();
+    let api_port = reserve_tcp_port().unwrap();
+    let dev_node_port = reserve_tcp_port().unwrap();
+
+    let instance = Anvil::new().spawn();
+    let l1_url = instance.endpoint_url();
+    let l1 = ProviderBuilder::new().connect_http(l1_url.clone());
+
+    let tmp_dir = tempfile::tempdir().unwrap();
+
+    let process = CargoBuild::new()
+        .bin("espresso-dev-node")
+        .current_target()
+        .run()
+        .unwrap()
+        .command()
+        .env("ESPRESSO_L1_PROVIDER", l1_url.to_string())
+        .env("ESPRESSO_BUILDER_PORT", builder_port.to_string())
+        .env("ESPRESSO_NODE_API_PORT", api_port.to_string())
+        .env("ESPRESSO_ETH_MNEMONIC", TEST_MNEMONIC)
+        .env("ESPRESSO_DEPLOYER_ACCOUNT_INDEX", "0")
+        .env("ESPRESSO_DEV_NODE_PORT", dev_node_port.to_string())
+        .env("ESPRESSO_NODE_STORAGE_PATH", tmp_dir.path().as_os_str())
+        .env("ESPRESSO_NODE_DATABASE_MAX_CONNECTIONS", "25")
+        .spawn()
+        .unwrap();
+
+    let process = BackgroundProcess(process);
+
+    let api_client: Client<ClientErr, SequencerApiVersion> =
+        Client::new(format!("http://localhost:{api_port}").parse().unwrap());
+    api_client.connect(None).await;
+
+    tracing::info!("waiting for blocks");
+    let _ = api_client
+        .socket("availability/stream/blocks/0")
+        .subscribe::<BlockQueryData<SeqTypes>>()
+        .await
+        .unwrap()
+        .take(3)
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+
+    let dev_node_client: Client<ClientErr, SequencerApiVersion> =
+        Client::new(format!("http://localhost:{dev_node_port}").parse().unwrap());
+    dev_node_client.connect(None).await;
+
+    // Pausing stops the chain at the current height.
+    let status = dev_node_client
+        .post::<BlockProductionStatus>("api/pause")
+        .send()
+        .await
+        .unwrap();
+    assert!(status.paused);
+    let paused_at = status.limit.unwrap();
+    wait_for_query_height(&api_client, paused_at).await;
+    sleep(Duration::from_secs(5)).await;
+    let status = dev_node_client
+        .get::<BlockProductionStatus>("api/block-production")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(status.height, paused_at);
+    assert_eq!(
+        wait_for_query_height(&api_client, paused_at).await,
+        paused_at
+    );
+
+    // Override the timestamp and L1 head of the next block, then mine up to it.
+    let timestamp = header_timestamp(&api_client, paused_at).await + 3600;
+    let l1_head = l1.get_block_number().await.unwrap();
+    let status = dev_node_client
+        .post::<BlockProductionStatus>("api/set-next-block")
+        .body_json(&SetNextBlockReqBody {
+            timestamp: Some(timestamp),
+            l1_head: Some(l1_head),
+        })
+        .unwrap()
+        .send()
+        .await
+        .unwrap();
+    let next_height = status.next_height;
+    assert!(next_height > paused_at);
+    let status = dev_node_client
+        .post::<BlockProductionStatus>("api/mine")
+        .body_json(&MineReqBody {
+            blocks: next_height - paused_at,
+        })
+        .unwrap()
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(status.height, next_height);
+    wait_for_query_height(&api_client, next_height).await;
+    let header = api_client
+        .get::<Header>(&format!("availability/header/{next_height}"))
+        .send()
+        .await
+        .unwrap();
+    assert!(header.timestamp() >= timestamp);
+    assert!(header.timestamp() < timestamp + 60);
+    assert_eq!(header.l1_head(), l1_head);
+
+    // Snapshot, mine some more, and revert.
+    let snapshot = dev_node_client
+        .post::<SnapshotInfo>("api/snapshot")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(snapshot.height, next_height);
+    let snapshot_l1_height = l1.get_block_number().await.unwrap();
+
+    let status = dev_node_client
+        .post::<BlockProductionStatus>("api/mine")
+        .body_json(&MineReqBody { blocks: 3 })
+        .unwrap()
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(status.height, snapshot.height + 3);
+    wait_for_query_height(&api_client, snapshot.height + 3).await;
+    l1.anvil_mine(Some(10), None).await.unwrap();
+    assert!(l1.get_block_number().await.unwrap() >= snapshot_l1_height + 10);
+
+    let status = dev_node_client
+        .post::<BlockProductionStatus>("api/revert")
+        .body_json(&RevertReqBody { id: snapshot.id })
+        .unwrap()
+        .send()
+        .await
+        .unwrap();
+    assert!(status.paused);
+    assert_eq!(status.height, snapshot.height);
+    assert!(l1.get_block_number().await.unwrap() < snapshot_l1_height + 10);
+    api_client.connect(None).await;
+    assert_eq!(
+        wait_for_query_height(&api_client, snapshot.height).await,
+        snapshot.height
+    );
+
+    // Unknown snapshots are rejected.
+    dev_node_client
+        .post::<BlockProductionStatus>("api/revert")
+        .body_json(&RevertReqBody {
+            id: snapshot.id + 1,
+        })
+        .unwrap()
+        .send()
+        .await
+        .unwrap_err();
+
+    // The chain continues after resuming.
+    dev_node_client
+        .post::<BlockProductionStatus>("api/resume")
+        .send()
+        .await
+        .unwrap();
+    wait_for_query_height(&api_client, snapshot.height + 2).await;
+
+    drop(process);
+}
+
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn slow_dev_node_inject_payload_test() {
+    let builder_port = reserve_tcp_port().unwrap();
+    let api_port = reserve_tcp_port().unwrap();
+    let dev_node_port = reserve_tcp_port().unwrap();
+
+    let instance = Anvil::new().spawn();
+    let l1_url = instance.endpoint_url();
+
+    let tmp_dir = tempfile::tempdir().unwrap();
+
+    let process = CargoBuild::new()
+        .bin("espresso-dev-node")
+        .current_target()
+        .run()
+        .unwrap()
+        .command()
+        .env("ESPRESSO_L1_PROVIDER", l1_url.to_string())
+        .env("ESPRESSO_BUILDER_PORT", builder_port.to_string())
+        .env("ESPRESSO_NODE_API_PORT", api_port.to_string())
+        .env("ESPRESSO_ETH_MNEMONIC", TEST_MNEMONIC)
+        .env("ESPRESSO_DEPLOYER_ACCOUNT_INDEX", "0")
+        .env("ESPRESSO_DEV_NODE_PORT", dev_node_port.to_string())
+        .env("ESPRESSO_NODE_STORAGE_PATH", tmp_dir.path().as_os_str())
+        .env("ESPRESSO_NODE_DATABASE_MAX_CONNECTIONS", "25")
+        .spawn()
+        .unwrap();
+
+    let process = BackgroundProcess(process);
+
+    let api_client: Client<ClientErr, SequencerApiVersion> =
+        Client::new(format!("http://localhost:{api_port}").parse().unwrap());
+    api_client.connect(None).await;
+    wait_for_query_height(&api_client, 3).await;
+
+    let dev_node_client: Client<ClientErr, SequencerApiVersion> =
+        Client::new(format!("http://localhost:{dev_node_port}").parse().unwrap());
+    dev_node_client.connect(None).await;
+
+    let status = dev_node_client
+        .post::<BlockProductionStatus>("api/pause")
+        .send()
+        .await
+        .unwrap();
+    let paused_at = status.limit.unwrap();
+    wait_for_query_height(&api_client, paused_at).await;
+    sleep(Duration::from_secs(5)).await;
+
+    // Queue a forced-empty block, then a block with one well-formed namespace and one whose
+    // transaction table claims far more transactions than it holds.
+    let empty = dev_node_client
+        .post::<InjectedPayloadInfo>("api/inject-payload")
+        .body_json(&InjectPayloadReqBody::default())
+        .unwrap()
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(empty.queued, 1);
+    assert_eq!(empty.size, 0);
+
+    let malformed = dev_node_client
+        .post::<InjectedPayloadInfo>("api/inject-payload")
+        .body_json(&InjectPayloadReqBody {
+            namespaces: vec![
+                InjectedNamespace {
+                    id: 7,
+                    transactions: vec![vec![1, 2, 3].into()],
+                    raw: None,
+                },
+                InjectedNamespace {
+                    id: 8,
+                    transactions: vec![],
+                    raw: Some(vec![0xff; 5].into()),
+                },
+            ],
+            ..Default::default()
+        })
+        .unwrap()
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(malformed.queued, 2);
+
+    // Namespace tables consensus would reject are refused up front.
+    dev_node_client
+        .post::<InjectedPayloadInfo>("api/inject-payload")
+        .body_json(&InjectPayloadReqBody {
+            ns_table: Some(vec![1, 0, 0, 0].into()),
+            ..Default::default()
+        })
+        .unwrap()
+        .send()
+        .await
+        .unwrap_err();
+
+    let status = dev_node_client
+        .get::<BlockProductionStatus>("api/block-production")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(status.injected_payloads, 2);
+    let height = status.next_height;
+    let status = dev_node_client
+        .post::<BlockProductionStatus>("api/mine")
+        .body_json(&MineReqBody {
+            blocks: height + 1 - paused_at,
+        })
+        .unwrap()
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(status.injected_payloads, 0);
+    wait_for_query_height(&api_client, height + 1).await;
+
+    let block = api_client
+        .get::<BlockQueryData<SeqTypes>>(&format!("availability/block/{height}"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(block.size(), 0);
+    assert_eq!(block.num_transactions(), 0);
+    assert_eq!(
+        block.header().ns_table().encode().as_ref(),
+        empty.ns_table.as_ref()
+    );
+
+    let block = api_client
+        .get::<BlockQueryData<SeqTypes>>(&format!("availability/block/{}", height + 1))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(block.size(), malformed.size);
+    assert_eq!(
+        block.header().ns_table().encode().as_ref(),
+        malformed.ns_table.as_ref()
+    );
+    let (_, tx) = block.enumerate().next().unwrap();
+    assert_eq!(tx, Transaction::new(7u32.into(), vec![1, 2, 3]));
+
+    drop(process);
+}
+
+async fn header_timestamp(api_client: &Client<ClientErr, SequencerApiVersion>, height: u64) -> u64 {
+    api_client
+        .get::<Header>(&format!("availability/header/{height}"))
+        .send()
+        .await
+        .unwrap()
+        .timestamp()
+}
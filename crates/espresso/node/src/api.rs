@@ -47,7 +47,7 @@ use hotshot_query_service::{
 };
 use hotshot_types::{
     PeerConfig,
-    data::{EpochNumber, VidCommitment, VidCommon, VidShare, ViewNumber},
+    data::{EpochNumber, VidShare, ViewNumber},
     event::{Event, LegacyEvent},
     light_client::LCV3StateSignatureRequestBody,
     network::NetworkConfig,
@@ -58,7 +58,6 @@ use hotshot_types::{
         network::ConnectedNetwork,
     },
     utils::epoch_from_block_number,
-    vid::avidm::{AvidMScheme, init_avidm_param},
     vote::HasViewNumber,
 };
 use itertools::Itertools;
@@ -77,7 +76,7 @@ use self::data_source::{
 };
 use crate::{
     SeqTypes, SequencerApiVersion, SequencerContext,
-    api::data_source::TokenDataSource,
+    api::{data_source::TokenDataSource, ns_recovery::ShareCollector},
     catchup::{
         CatchupStorage, add_fee_accounts_to_state, add_v1_reward_accounts_to_state,
         add_v2_reward_accounts_to_state,
@@ -95,6 +94,7 @@ use crate::{
 pub mod data_source;
 pub mod fs;
 pub mod light_client;
+pub mod ns_recovery;
 pub mod options;
 pub mod sql;
 pub mod state;
@@ -581,62 +581,36 @@ impl<N: ConnectedNetwork<PubKey>, P: SequencerPersistence> RequestResponseDataSo
             .clone();
 
         async move {
-            // Get the total VID weight based on the VID common data
-            let total_weight = match vid_common_data.common() {
-                VidCommon::V0(_) => {
-                    // TODO: This needs to be done via the stake table
-                    return Err(anyhow::anyhow!(
-                        "V0 total weight calculation not supported yet"
-                    ));
-                },
-                VidCommon::V1(v1) => v1.total_weights,
-                VidCommon::V2(v2) => v2.param.total_weights,
-            };
-
-            // Create the AvidM parameters from the total weight
-            let avidm_param = init_avidm_param(total_weight)
-                .with_context(|| "failed to initialize avidm param")?;
-
-            // Get the payload hash for verification
-            let VidCommitment::V1(local_payload_hash) = vid_common_data.payload_hash() else {
-                bail!("V0 share verification not supported yet");
-            };
+            // Verifies each share against the payload commitment, according to its VID version
+            let collector = Arc::new(parking_lot::Mutex::new(ShareCollector::new(
+                &vid_common_data,
+            )?));
 
             // Create a random request id
             let request_id = rand::thread_rng().r#gen();
 
-            // Request and verify the shares from all other nodes, timing out after `duration` seconds
-            let received_shares = Arc::new(parking_lot::Mutex::new(Vec::new()));
-            let received_shares_clone = received_shares.clone();
+            // Request and verify the shares from all other nodes until we have enough to recover
+            // the payload, timing out after `duration` seconds
+            let collector_clone = collector.clone();
             let request_result: anyhow::Result<_, _> = timeout(
                 duration,
                 request_response_protocol.request_indefinitely::<_, _, _>(
                     Request::VidShare(block_number, request_id),
                     RequestType::Batched,
                     move |_request, response| {
-                        let avidm_param = avidm_param.clone();
-                        let received_shares = received_shares_clone.clone();
+                        let collector = collector_clone.clone();
                         async move {
-                            // Make sure the response was a V1 share
-                            let Response::VidShare(VidShare::V1(received_share)) = response else {
-                                bail!("V0 share verification not supported yet");
+                            // Make sure the response was a share
+                            let Response::VidShare(received_share) = response else {
+                                bail!("expected a VID share");
                             };
 
-                            // Verify the share
-                            let Ok(Ok(_)) = AvidMScheme::verify_share(
-                                &avidm_param,
-                                &local_payload_hash,
-                                &received_share,
-                            ) else {
-                                bail!("share verification failed");
-                            };
+                            // Verify the share and add it to the ones we have collected
+                            let mut collector = collector.lock();
+                            collector.add(received_share)?;
 
-                            // Add the share to the list of received shares
-                            received_shares.lock().push(received_share);
-
-                            bail!("waiting for more shares");
-
-                            #[allow(unreachable_code)]
+                            // Keep waiting until we have enough weight to recover
+                            ensure!(collector.is_complete(), "waiting for more shares");
                             Ok(())
                         }
                     },
@@ -644,24 +618,13 @@ impl<N: ConnectedNetwork<PubKey>, P: SequencerPersistence> RequestResponseDataSo
             )
             .await;
 
-            // If the request timed out, return the shares we have collected so far
-            match request_result {
-                Err(_) => {
-                    // If it timed out, this was successful. Return the shares we have collected so far
-                    Ok(received_shares
-                        .lock()
-                        .clone()
-                        .into_iter()
-                        .map(VidShare::V1)
-                        .collect())
-                },
-
-                // If it was an error from the inner request, return that error
-                Ok(Err(e)) => Err(e).with_context(|| "failed to request vid shares"),
-
-                // If it was successful, this was unexpected.
-                Ok(Ok(_)) => bail!("this should not be possible"),
+            // If it timed out, return the shares we have collected so far; together with our own
+            // they may still be enough. If it was an error from the inner request, return that
+            // error
+            if let Ok(Err(e)) = request_result {
+                return Err(e).with_context(|| "failed to request vid shares");
             }
+            Ok(collector.lock().take_shares())
         }
         .boxed()
     }
@@ -1939,6 +1902,7 @@ pub mod test_helpers {
     use tempfile::TempDir;
     use test_utils::reserve_tcp_port;
     use tokio::time::sleep;
+    use tokio_util::task::AbortOnDropHandle;
     use vbs::version::StaticVersion;
     use versions::{EPOCH_VERSION, Upgrade};
 
@@ -1964,6 +1928,8 @@ pub mod test_helpers {
         pub contracts: Option<Contracts>,
         /// Deferred node indices not yet started (see [`Self::start_deferred_node`]).
         deferred: Vec<usize>,
+        /// Keeps the builder API running for as long as the network.
+        _builder_server: AbortOnDropHandle<()>,
     }
 
     pub struct TestNetworkConfig<const NUM_NODES: usize, P, C>
@@ -2273,14 +2239,18 @@ pub mod test_helpers {
             if chain_config.is_none() {
                 tracing::warn!("Chain config is not set, using default max_block_size");
             }
-            let (task, builder_url) = run_legacy_builder::<{ NUM_NODES }>(
+            let (task, builder_url, builder_server) = run_legacy_builder::<{ NUM_NODES }>(
                 cfg.network_config.builder_port(),
                 chain_config.map(|c| *c.max_block_size),
             )
             .await;
             builder_tasks.push(task);
+            let node_builder_url = cfg
+                .network_config
+                .node_builder_url()
+                .unwrap_or_else(|| builder_url.clone());
             cfg.network_config
-                .set_builder_urls(vec1::vec1![builder_url.clone()]);
+                .set_builder_urls(vec1::vec1![node_builder_url]);
 
             // add default storage if none is provided as query module is now required
             let mut opt = cfg.api_config.clone();
@@ -2387,6 +2357,7 @@ pub mod test_helpers {
                 temp_dir,
                 contracts: cfg.contracts,
                 deferred,
+                _builder_server: builder_server,
             }
         }
 
@@ -2483,6 +2454,17 @@ pub mod test_helpers {
             ctx
         }
 
+        /// Shuts down every node and the builder.
+        ///
+        /// Afterwards the network can be recreated from the same configuration, picking up
+        /// where it left off from each node's persistence.
+        pub async fn shut_down(mut self) {
+            self.server.shut_down().await;
+            for ctx in &mut self.peers {
+                ctx.shut_down().await;
+            }
+        }
+
         pub async fn stop_consensus(&mut self) {
             self.server.shutdown_consensus().await;
 
@@ -5590,6 +5572,92 @@ mod test {
         Ok(())
     }
 
+    /// A node records its consensus trace from the anchor it started from,
+    /// and replays it with its own keys and stake table.
+    #[test_log::test(tokio::test(flavor = "multi_thread"))]
+    async fn test_new_protocol_replay_consensus_trace() -> anyhow::Result<()> {
+        const EPOCH_HEIGHT: u64 = 100;
+        const NUM_NODES: usize = 5;
+        const TARGET_BLOCK_HEIGHT: u64 = 10;
+
+        const NEW_PROTOCOL: Upgrade = Upgrade::trivial(NEW_PROTOCOL_VERSION);
+
+        let trace_dir = tempfile::tempdir()?;
+        let trace = trace_dir.path().join("consensus.trace");
+
+        let mut network_config = TestConfigBuilder::default()
+            .epoch_height(EPOCH_HEIGHT)
+            .epoch_start_block(0)
+            .build();
+        network_config.set_consensus_trace_file(1, trace.clone());
+
+        let api_port = reserve_tcp_port().expect("No ports free for query service");
+
+        let storage = join_all((0..NUM_NODES).map(|_| SqlDataSource::create_storage())).await;
+        let persistence: [_; NUM_NODES] = storage
+            .iter()
+            .map(<SqlDataSource as TestableSequencerDataSource>::persistence_options)
+            .collect::<Vec<_>>()
+            .try_into()
+            .unwrap();
+
+        let config = TestNetworkConfigBuilder::<NUM_NODES, _, _>::with_num_nodes()
+            .api_config(SqlDataSource::options(
+                &storage[0],
+                Options::with_port(api_port),
+            ))
+            .network_config(network_config)
+            .persistences(persistence)
+            .catchups(std::array::from_fn(|_| {
+                StatePeers::<SequencerApiVersion>::from_urls(
+                    vec![format!("http://localhost:{api_port}").parse().unwrap()],
+                    Default::default(),
+                    Duration::from_secs(2),
+                    &NoMetrics,
+                )
+            }))
+            .pos_hook(
+                DelegationConfig::MultipleDelegators,
+                StakeTableContractVersion::V3,
+                NEW_PROTOCOL,
+            )
+            .await
+            .unwrap()
+            .build();
+
+        let network = TestNetwork::new(config, NEW_PROTOCOL).await;
+
+        let client: Client<ClientErr, SequencerApiVersion> =
+            Client::new(format!("http://localhost:{api_port}").parse().unwrap());
+        client.connect(Some(Duration::from_secs(30))).await;
+
+        let mut leaves = client
+            .socket("availability/stream/leaves/0")
+            .subscribe::<LeafQueryData<SeqTypes>>()
+            .await
+            .expect("subscribe to leaf stream");
+
+        let mut height = 0;
+        while height < TARGET_BLOCK_HEIGHT {
+            let leaf = leaves
+                .next()
+                .await
+                .expect("leaf stream ended early")
+                .expect("leaf stream yielded an error");
+            height = leaf.header().height();
+        }
+
+        // Stopping consensus flushes the trace of node 1.
+        let node = &network.peers[0];
+        node.shutdown_consensus().await;
+
+        let report = node.replay_consensus_trace(&trace).await?;
+        assert!(report.inputs > 0);
+        assert!(report.divergence.is_none(), "{report}");
+
+        Ok(())
+    }
+
     /// Run entirely without the legacy consensus stack: with base version
     /// `NEW_PROTOCOL_VERSION` it is torn down at startup, and the explicit
     /// mid-run `shut_down_legacy` calls below (what the decide-count trigger
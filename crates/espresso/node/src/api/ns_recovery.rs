@@ -0,0 +1,501 @@
+//! Recovery of individual namespaces from VID shares.
+//!
+//! A node which is not on the DA committee for a block only stores its own VID share, and fetching
+//! the full payload from peers can be slow or impossible if no peer has it. Since AvidM (and
+//! AvidM-GF2) disperses each namespace independently, a client asking for one namespace does not
+//! need the rest of the payload: it is enough to collect a threshold of shares from peers and
+//! decode the requested namespace alone. For legacy ADVZ blocks the whole payload is recovered
+//! from shares and the namespace is extracted from it.
+//!
+//! This module exposes that as a query service [`Provider`], so it composes with the rest of the
+//! fetching machinery, though the recovered namespace is served directly and never stored.
+
+use std::{ops::Deref, time::Duration};
+
+use anyhow::{Context, bail, ensure};
+use async_trait::async_trait;
+use espresso_types::{NamespaceId, NamespaceProofQueryData, NsProof, SeqTypes};
+use hotshot_query_service::{
+    availability::{AvailabilityDataSource, BlockId, VidCommonQueryData},
+    fetching::{Provider, Request},
+    node::NodeDataSource,
+};
+use hotshot_types::{
+    data::{VidCommitment, VidCommon, VidShare},
+    vid::{
+        advz::{ADVZCommitment, ADVZCommon, ADVZScheme, advz_recovery_threshold, advz_scheme},
+        avidm::{AvidMCommitment, AvidMParam, AvidMScheme, init_avidm_param},
+        avidm_gf2::{AvidmGf2Commitment, AvidmGf2Common, AvidmGf2Scheme, init_avidm_gf2_param},
+    },
+};
+use jf_advz::VidScheme;
+
+use super::data_source::RequestResponseDataSource;
+
+/// A request for a single namespace of the block at `height`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NamespaceRequest {
+    pub height: u64,
+    pub namespace: NamespaceId,
+}
+
+impl Request<SeqTypes> for NamespaceRequest {
+    type Response = NamespaceProofQueryData;
+}
+
+/// VID shares of one block collected from peers.
+///
+/// Each share is verified against the payload commitment with the scheme of the block's VID
+/// version before it is counted. Collection is complete once the weight of the verified shares
+/// reaches the recovery threshold, at which point there is no reason to wait for more peers.
+pub(crate) struct ShareCollector {
+    verifier: ShareVerifier,
+    threshold: usize,
+    weight: usize,
+    shares: Vec<VidShare>,
+}
+
+enum ShareVerifier {
+    V0 {
+        vid: ADVZScheme,
+        common: ADVZCommon,
+        commit: ADVZCommitment,
+    },
+    V1 {
+        param: AvidMParam,
+        commit: AvidMCommitment,
+    },
+    V2 {
+        common: AvidmGf2Common,
+        commit: AvidmGf2Commitment,
+    },
+}
+
+impl ShareCollector {
+    /// Start collecting shares of the block described by `vid_common`.
+    ///
+    /// The recovery threshold is derived from the total weight rather than taken from the common
+    /// data, which is not covered by the payload commitment.
+    pub(crate) fn new(vid_common: &VidCommonQueryData<SeqTypes>) -> anyhow::Result<Self> {
+        let (verifier, threshold) = match (vid_common.common(), vid_common.payload_hash()) {
+            (VidCommon::V0(common), VidCommitment::V0(commit)) => {
+                let num_storage_nodes = ADVZScheme::get_num_storage_nodes(common) as usize;
+                ensure!(num_storage_nodes > 0, "VID common has no storage nodes");
+                // ADVZ shares all carry the same multiplicity, so each one counts once.
+                let verifier = ShareVerifier::V0 {
+                    vid: advz_scheme(num_storage_nodes),
+                    common: common.clone(),
+                    commit,
+                };
+                (verifier, advz_recovery_threshold(num_storage_nodes))
+            },
+            (VidCommon::V1(param), VidCommitment::V1(commit)) => {
+                let param = init_avidm_param(param.total_weights)
+                    .context("failed to initialize AvidM param")?;
+                let threshold = param.recovery_threshold;
+                (ShareVerifier::V1 { param, commit }, threshold)
+            },
+            (VidCommon::V2(common), VidCommitment::V2(commit)) => {
+                let param = init_avidm_gf2_param(common.param.total_weights)
+                    .context("failed to initialize AvidM-GF2 param")?;
+                ensure!(
+                    AvidmGf2Scheme::is_consistent(&commit, common),
+                    "VID common does not match payload commitment"
+                );
+                let verifier = ShareVerifier::V2 {
+                    common: common.clone(),
+                    commit,
+                };
+                (verifier, param.recovery_threshold)
+            },
+            _ => bail!("VID common and payload commitment have different versions"),
+        };
+        Ok(Self {
+            verifier,
+            threshold,
+            weight: 0,
+            shares: vec![],
+        })
+    }
+
+    /// Verify `share` and add it to the collection.
+    pub(crate) fn add(&mut self, share: VidShare) -> anyhow::Result<()> {
+        ensure!(!self.shares.contains(&share), "duplicate share");
+        let weight = match (&self.verifier, &share) {
+            (
+                ShareVerifier::V0 {
+                    vid,
+                    common,
+                    commit,
+                },
+                VidShare::V0(s),
+            ) => {
+                ensure!(
+                    vid.verify_share(s, common, commit).is_ok_and(|r| r.is_ok()),
+                    "share verification failed"
+                );
+                1
+            },
+            (ShareVerifier::V1 { param, commit }, VidShare::V1(s)) => {
+                ensure!(
+                    AvidMScheme::verify_share(param, commit, s).is_ok_and(|r| r.is_ok()),
+                    "share verification failed"
+                );
+                s.weight()
+            },
+            (ShareVerifier::V2 { common, commit }, VidShare::V2(s)) => {
+                ensure!(
+                    AvidmGf2Scheme::verify_share(commit, common, s).is_ok_and(|r| r.is_ok()),
+                    "share verification failed"
+                );
+                s.weight()
+            },
+            _ => bail!("share version does not match VID common"),
+        };
+        self.weight += weight;
+        self.shares.push(share);
+        Ok(())
+    }
+
+    /// Whether the collected shares are enough to recover the payload.
+    pub(crate) fn is_complete(&self) -> bool {
+        self.weight >= self.threshold
+    }
+
+    /// Take the shares collected so far.
+    pub(crate) fn take_shares(&mut self) -> Vec<VidShare> {
+        self.weight = 0;
+        std::mem::take(&mut self.shares)
+    }
+}
+
+/// Provider which recovers a single namespace from VID shares collected from peers.
+///
+/// The block header and VID common data must be available locally (or fetchable by the underlying
+/// data source); only the payload itself is reconstructed from shares. The resulting proof is
+/// verified against the payload commitment in the header before it is returned. A namespace which
+/// is absent from the block, or present but empty, yields an empty response, exactly as if the
+/// payload were available locally.
+#[derive(Clone, Debug)]
+pub struct VidShareNamespaceProvider<D> {
+    data_source: D,
+    fetch_timeout: Duration,
+    share_timeout: Duration,
+}
+
+impl<D> VidShareNamespaceProvider<D> {
+    /// Create a provider on top of `data_source`.
+    ///
+    /// `fetch_timeout` bounds how long we wait for the header and VID common, and `share_timeout`
+    /// bounds how long we wait for peers to send enough VID shares to recover the payload.
+    pub fn new(data_source: D, fetch_timeout: Duration, share_timeout: Duration) -> Self {
+        Self {
+            data_source,
+            fetch_timeout,
+            share_timeout,
+        }
+    }
+}
+
+impl<D> VidShareNamespaceProvider<D>
+where
+    D: Deref + Send + Sync,
+    D::Target: AvailabilityDataSource<SeqTypes>
+        + NodeDataSource<SeqTypes>
+        + RequestResponseDataSource<SeqTypes>
+        + Send
+        + Sync,
+{
+    async fn recover(&self, req: NamespaceRequest) -> anyhow::Result<NamespaceProofQueryData> {
+        let ds = &*self.data_source;
+        let block_id = BlockId::<SeqTypes>::Number(req.height as usize);
+
+        let header = ds
+            .get_header(block_id)
+            .await
+            .with_timeout(self.fetch_timeout)
+            .await
+            .context("header not available")?;
+        let vid_common = ds
+            .get_vid_common(block_id)
+            .await
+            .with_timeout(self.fetch_timeout)
+            .await
+            .context("VID common not available")?;
+        ensure!(
+            vid_common.payload_hash() == header.payload_commitment(),
+            "VID common does not match header"
+        );
+
+        let Some(ns_index) = header.ns_table().find_ns_id(&req.namespace) else {
+            return Ok(NamespaceProofQueryData {
+                transactions: vec![],
+                proof: None,
+            });
+        };
+
+        // Collect enough shares to recover, or as many as peers give us within the timeout, plus
+        // our own.
+        let mut shares = ds
+            .request_vid_shares(req.height, vid_common.clone(), self.share_timeout)
+            .await
+            .await
+            .context("requesting VID shares")?;
+        if let Ok(local_share) = ds.vid_share(block_id).await {
+            shares.push(local_share);
+        }
+        tracing::debug!(
+            height = req.height,
+            %req.namespace,
+            num_shares = shares.len(),
+            "recovering namespace from VID shares"
+        );
+
+        let proof = NsProof::new_from_shares(
+            &shares,
+            header.ns_table(),
+            &ns_index,
+            &header.payload_commitment(),
+            vid_common.common(),
+        )
+        .context("failed to recover namespace from shares")?;
+        Ok(match proof {
+            Some(proof) => NamespaceProofQueryData {
+                transactions: proof.export_all_txs(&req.namespace),
+                proof: Some(proof),
+            },
+            None => NamespaceProofQueryData {
+                transactions: vec![],
+                proof: None,
+            },
+        })
+    }
+}
+
+#[async_trait]
+impl<D> Provider<SeqTypes, NamespaceRequest> for VidShareNamespaceProvider<D>
+where
+    D: Deref + Send + Sync,
+    D::Target: AvailabilityDataSource<SeqTypes>
+        + NodeDataSource<SeqTypes>
+        + RequestResponseDataSource<SeqTypes>
+        + Send
+        + Sync,
+{
+    async fn fetch(&self, req: NamespaceRequest) -> Option<NamespaceProofQueryData> {
+        match self.recover(req).await {
+            Ok(proof) => Some(proof),
+            Err(err) => {
+                tracing::warn!(?req, "failed to recover namespace: {err:#}");
+                None
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use espresso_types::{Payload, Transaction};
+    use futures::future::{BoxFuture, FutureExt};
+    use hotshot_query_service::data_source::{
+        ExtensibleDataSource, Transaction as _, storage::UpdateAvailabilityStorage,
+    };
+    use hotshot_types::{
+        data::ns_table::parse_ns_table,
+        traits::{BlockPayload, EncodeBytes},
+    };
+    use light_client::testing::custom_leaf_chain;
+    use versions::{EPOCH_VERSION, Upgrade};
+
+    use super::*;
+    use crate::api::{
+        data_source::{SequencerDataSource, testing::TestableSequencerDataSource},
+        sql::DataSource,
+    };
+
+    const NUM_STORAGE_NODES: usize = 10;
+
+    /// Peers which answer a request for VID shares with a fixed list of shares.
+    ///
+    /// Responses are handled as the request-response protocol handles them, so invalid shares are
+    /// dropped and no more are taken once there are enough to recover.
+    struct MockPeers {
+        shares: Vec<VidShare>,
+    }
+
+    impl RequestResponseDataSource<SeqTypes> for ExtensibleDataSource<DataSource, MockPeers> {
+        async fn request_vid_shares(
+            &self,
+            _block_number: u64,
+            vid_common_data: VidCommonQueryData<SeqTypes>,
+            _duration: Duration,
+        ) -> BoxFuture<'static, anyhow::Result<Vec<VidShare>>> {
+            let shares = self.as_ref().shares.clone();
+            async move {
+                let mut collector = ShareCollector::new(&vid_common_data)?;
+                for share in shares {
+                    if collector.add(share).is_ok() && collector.is_complete() {
+                        break;
+                    }
+                }
+                Ok(collector.take_shares())
+            }
+            .boxed()
+        }
+    }
+
+    /// Disperse `payload` among equally weighted storage nodes using VID `version`.
+    fn disperse(version: u8, payload: &Payload) -> (VidCommitment, VidCommon, Vec<VidShare>) {
+        let bytes = payload.encode();
+        let ns_ranges = parse_ns_table(bytes.len(), &payload.ns_table().encode());
+        let weights = [1; NUM_STORAGE_NODES];
+        match version {
+            0 => {
+                let vid = advz_scheme(NUM_STORAGE_NODES).disperse(&bytes).unwrap();
+                (
+                    VidCommitment::V0(vid.commit),
+                    VidCommon::V0(vid.common),
+                    vid.shares.into_iter().map(VidShare::V0).collect(),
+                )
+            },
+            1 => {
+                let param = init_avidm_param(NUM_STORAGE_NODES).unwrap();
+                let (commit, shares) =
+                    AvidMScheme::ns_disperse(&param, &weights, &bytes, ns_ranges).unwrap();
+                (
+                    VidCommitment::V1(commit),
+                    VidCommon::V1(param),
+                    shares.into_iter().map(VidShare::V1).collect(),
+                )
+            },
+            2 => {
+                let param = init_avidm_gf2_param(NUM_STORAGE_NODES).unwrap();
+                let (commit, common, shares) =
+                    AvidmGf2Scheme::ns_disperse(&param, &weights, &bytes, ns_ranges).unwrap();
+                (
+                    VidCommitment::V2(commit),
+                    VidCommon::V2(common),
+                    shares.into_iter().map(VidShare::V2).collect(),
+                )
+            },
+            _ => unreachable!("unknown VID version {version}"),
+        }
+    }
+
+    async fn build_payload(txs: impl IntoIterator<Item = (u32, Vec<u8>)>) -> Payload {
+        let txs = txs
+            .into_iter()
+            .map(|(ns, tx)| Transaction::new(ns.into(), tx))
+            .collect::<Vec<_>>();
+        Payload::from_transactions(txs, &Default::default(), &Default::default())
+            .await
+            .unwrap()
+            .0
+    }
+
+    #[rstest::rstest]
+    #[case::advz(0)]
+    #[case::avidm(1)]
+    #[case::avidm_gf2(2)]
+    #[test_log::test(tokio::test(flavor = "multi_thread"))]
+    async fn test_recover_namespace(#[case] version: u8) {
+        let payload = build_payload([
+            (1, vec![1; 5]),
+            (1, vec![2; 8]),
+            (2, vec![3; 7]),
+            (3, vec![4; 10]),
+            (3, vec![5; 3]),
+        ])
+        .await;
+        let ns_table = payload.ns_table().clone();
+        let (commit, common, shares) = disperse(version, &payload);
+
+        // Shares of a different block with the same VID version must be rejected.
+        let other = build_payload([(1, vec![6; 5]), (2, vec![7; 7])]).await;
+        let (_, _, foreign_shares) = disperse(version, &other);
+
+        // Store the header and VID common, but not the payload, along with our own share.
+        let leaf = custom_leaf_chain(Upgrade::trivial(EPOCH_VERSION), [0], |proposal| {
+            *proposal.block_header.payload_commitment_mut() = commit;
+            *proposal.block_header.ns_table_mut() = ns_table.clone();
+        })
+        .await
+        .remove(0);
+        let storage = <DataSource as TestableSequencerDataSource>::create_storage().await;
+        let ds = DataSource::create(
+            DataSource::persistence_options(&storage),
+            Default::default(),
+            false,
+        )
+        .await
+        .unwrap();
+        {
+            let mut tx = ds.write().await.unwrap();
+            tx.insert_leaf(&leaf).await.unwrap();
+            tx.insert_vid(
+                &VidCommonQueryData::new(leaf.header().clone(), common.clone()),
+                Some(&shares[0]),
+            )
+            .await
+            .unwrap();
+            tx.commit().await.unwrap();
+        }
+
+        // Peers send a foreign share and a duplicate along with their own shares.
+        let mut peer_shares = vec![foreign_shares[1].clone(), shares[1].clone()];
+        peer_shares.extend(shares[1..].iter().cloned());
+        let ds = Arc::new(ExtensibleDataSource::new(
+            ds,
+            MockPeers {
+                shares: peer_shares,
+            },
+        ));
+
+        // Collection stops as soon as there are enough shares to recover.
+        let collected = ds
+            .request_vid_shares(
+                0,
+                VidCommonQueryData::new(leaf.header().clone(), common.clone()),
+                Duration::MAX,
+            )
+            .await
+            .await
+            .unwrap();
+        assert!(collected.len() < NUM_STORAGE_NODES - 1);
+        assert!(collected.iter().all(|share| shares.contains(share)));
+
+        let provider =
+            VidShareNamespaceProvider::new(ds, Duration::from_secs(1), Duration::from_secs(1));
+        for (ns, num_txs) in [(1u32, 2), (2, 1), (3, 2)] {
+            let ns = NamespaceId::from(ns);
+            let res = provider
+                .fetch(NamespaceRequest {
+                    height: 0,
+                    namespace: ns,
+                })
+                .await
+                .unwrap();
+            assert_eq!(res.transactions.len(), num_txs);
+            let (txs, proven_ns) = res
+                .proof
+                .unwrap()
+                .verify(&ns_table, &commit, &common)
+                .unwrap();
+            assert_eq!(txs, res.transactions);
+            assert_eq!(proven_ns, ns);
+        }
+
+        // A namespace which is not in the block is empty.
+        let res = provider
+            .fetch(NamespaceRequest {
+                height: 0,
+                namespace: 4u32.into(),
+            })
+            .await
+            .unwrap();
+        assert!(res.transactions.is_empty());
+        assert!(res.proof.is_none());
+    }
+}
@@ -38,6 +38,7 @@ use hotshot_query_service::{
         GetTransactionSummariesRequest, TransactionIdentifier, TransactionRange,
         TransactionSummaryFilter,
     },
+    fetching::Provider as _,
     merklized_state::{
         MerklizedStateDataSource, MerklizedStateHeightPersistence, Snapshot as HsSnapshot,
     },
@@ -71,6 +72,7 @@ use super::{
         RequestResponseDataSource as _, StakeTableDataSource, StateCertDataSource,
         StateCertFetchingDataSource, StateSignatureDataSource, TokenDataSource as _,
     },
+    ns_recovery::{NamespaceRequest, VidShareNamespaceProvider},
 };
 
 /// Timeout for failing requests due to missing data.
@@ -82,6 +84,12 @@ use super::{
 /// Matches the `hotshot_query_service` availability API default.
 const FETCH_TIMEOUT: Duration = Duration::from_millis(500);
 
+/// How long to wait for VID shares from peers when recovering a single namespace.
+///
+/// Collection stops as soon as the shares received are enough to recover the payload; this only
+/// bounds how long we wait when too few peers answer.
+const NS_RECOVERY_SHARE_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// Node API state implementation
 ///
 /// This struct implements both v1::RewardApi (internal types) and v2::RewardApi (proto types).
@@ -90,17 +98,25 @@ pub struct NodeApiStateImpl<D> {
     data_source: D,
     env_vars: std::sync::Arc<Vec<String>>,
     public_node_config: Option<std::sync::Arc<crate::options::PublicNodeConfig>>,
+    ns_recovery: VidShareNamespaceProvider<D>,
 }
 
-impl<D> NodeApiStateImpl<D> {
+impl<D: Clone> NodeApiStateImpl<D> {
     pub fn new(data_source: D) -> Self {
         Self {
+            ns_recovery: VidShareNamespaceProvider::new(
+                data_source.clone(),
+                FETCH_TIMEOUT,
+                NS_RECOVERY_SHARE_TIMEOUT,
+            ),
             data_source,
             env_vars: std::sync::Arc::new(Vec::new()),
             public_node_config: None,
         }
     }
+}
 
+impl<D> NodeApiStateImpl<D> {
     pub fn with_env_vars(mut self, env_vars: Vec<String>) -> Self {
         self.env_vars = std::sync::Arc::new(env_vars);
         self
@@ -1283,6 +1299,41 @@ where
 // v1::AvailabilityApi implementation
 // ============================================================================
 
+impl<D> NodeApiStateImpl<D>
+where
+    D: std::ops::Deref + Clone + Send + Sync + 'static,
+    D::Target: hotshot_query_service::availability::AvailabilityDataSource<espresso_types::SeqTypes>
+        + hotshot_query_service::node::NodeDataSource<espresso_types::SeqTypes>
+        + super::data_source::RequestResponseDataSource<espresso_types::SeqTypes>
+        + Send
+        + Sync,
+{
+    /// Serve a namespace proof for a block whose payload is not available locally, by recovering
+    /// only the requested namespace from VID shares.
+    ///
+    /// Returns [`None`] if the block header is not available or recovery fails. If the namespace
+    /// is not present in the block, returns an empty result, as for blocks we do have.
+    async fn recover_namespace_proof(
+        &self,
+        block_id: HsBlockId<SeqTypes>,
+        ns_id: NamespaceId,
+    ) -> Option<NamespaceProofQueryData> {
+        // Resolve the block ID to a height, which is how recovery requests are keyed.
+        let header = self
+            .data_source
+            .get_header(block_id)
+            .await
+            .with_timeout(FETCH_TIMEOUT)
+            .await?;
+        self.ns_recovery
+            .fetch(NamespaceRequest {
+                height: header.height(),
+                namespace: ns_id,
+            })
+            .await
+    }
+}
+
 #[async_trait]
 impl<D> espresso_api::v1::AvailabilityApi for NodeApiStateImpl<D>
 where
@@ -1337,14 +1388,20 @@ where
             vid_fetch.with_timeout(timeout)
         );
 
-        let block =
-            block.ok_or_else(|| not_found(format!("block {} not available", hs_block_id)))?;
         let vid_common = vid_common.ok_or_else(|| {
             not_found(format!(
                 "VID common for block {} not available",
                 hs_block_id
             ))
         })?;
+        let Some(block) = block else {
+            // We don't have the payload, but we may be able to recover just the requested
+            // namespace from VID shares held by our peers.
+            return self
+                .recover_namespace_proof(hs_block_id, ns_id)
+                .await
+                .ok_or_else(|| not_found(format!("block {} not available", hs_block_id)));
+        };
 
         // Namespace absent from the block: an empty result, not an error.
         let ns_table = block.payload().ns_table();
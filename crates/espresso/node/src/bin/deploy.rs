@@ -15,6 +15,7 @@ use espresso_contract_deployer::{
     network_config::{light_client_genesis, light_client_genesis_from_stake_table},
     proposals::{
         deployment_info::Multisig,
+        simulate::{SimulateProposalArgs, run_simulate_standalone},
         timelock::TimelockOperationType,
         verify::{VerifyProposalArgs, run_verify_standalone},
     },
@@ -503,6 +504,8 @@ enum Command {
     Balance,
     /// Verify a Safe-tx-builder upgrade proposal without trusting Etherscan.
     VerifyProposal(VerifyProposalArgs),
+    /// Execute an upgrade proposal on a local Anvil fork and report its effects.
+    SimulateProposal(SimulateProposalArgs),
 }
 
 fn main() -> anyhow::Result<()> {
@@ -526,6 +529,11 @@ async fn async_main(migrated_envs: Vec<(&str, &str)>) -> anyhow::Result<()> {
         report.print();
         std::process::exit(report.exit_code());
     }
+    if let Some(Command::SimulateProposal(args)) = &opt.command {
+        let report = run_simulate_standalone(args).await?;
+        report.print();
+        std::process::exit(report.exit_code());
+    }
 
     let provider = if opt.ledger {
         let signer = connect_ledger(opt.account_index as usize).await?;
@@ -565,6 +573,9 @@ async fn async_main(migrated_envs: Vec<(&str, &str)>) -> anyhow::Result<()> {
             Command::VerifyProposal(_) => {
                 unreachable!("VerifyProposal handled before wallet provider construction")
             },
+            Command::SimulateProposal(_) => {
+                unreachable!("SimulateProposal handled before wallet provider construction")
+            },
         };
     };
 
@@ -2,6 +2,7 @@ use std::{
     fmt::{Debug, Display},
     future::Future,
     marker::PhantomData,
+    path::Path,
     sync::Arc,
     time::{Duration, Instant},
 };
@@ -20,8 +21,10 @@ use futures::{
 use hotshot::{HotShotInitializer, SystemContext};
 use hotshot_events_service::events_source::{EventConsumer, EventsStreamer};
 use hotshot_new_protocol::{
+    consensus::{Consensus as NewConsensus, RestartAnchor},
     coordinator::Coordinator,
     network::{Cliquenet, NetworkError},
+    trace::{ReplayReport, TraceRecorder, replay_file_from_anchor},
 };
 use hotshot_orchestrator::client::OrchestratorClient;
 use hotshot_types::{
@@ -103,6 +106,8 @@ pub struct SequencerContext<N: ConnectedNetwork<PubKey>, P: SequencerPersistence
 
     #[derivative(Debug = "ignore")]
     validator_config: ValidatorConfig<SeqTypes>,
+
+    stake_table_capacity: usize,
 }
 
 impl<N, P> SequencerContext<N, P>
@@ -130,6 +135,7 @@ where
         event_consumer: impl PersistenceEventConsumer + 'static,
         proposal_fetcher_cfg: ProposalFetcherConfig,
         bootstrap_epoch_catchup_timeout: Duration,
+        consensus_trace: Option<TraceRecorder<SeqTypes>>,
     ) -> anyhow::Result<Self>
     where
         F: AsyncFnOnce(UpgradeLock<SeqTypes>) -> Result<Cliquenet<SeqTypes>, NetworkError>,
@@ -234,6 +240,7 @@ where
             .metrics(metrics)
             .consensus_metrics(consensus_metrics)
             .maybe_locked_qc(locked_qc)
+            .maybe_trace(consensus_trace)
             .make();
 
         let legacy_event_rx = handle.event_stream_known_impl().deactivate();
@@ -329,6 +336,7 @@ where
             instance_state,
             network_config,
             validator_config,
+            stake_table_capacity,
             event_consumer,
             anchor_view,
             proposal_fetcher_cfg,
@@ -349,6 +357,7 @@ where
         node_state: NodeState,
         network_config: NetworkConfig<SeqTypes>,
         validator_config: ValidatorConfig<SeqTypes>,
+        stake_table_capacity: usize,
         event_consumer: impl PersistenceEventConsumer + 'static,
         anchor_view: Option<ViewNumber>,
         proposal_fetcher_cfg: ProposalFetcherConfig,
@@ -368,6 +377,7 @@ where
             node_state,
             network_config,
             validator_config,
+            stake_table_capacity,
         };
 
         // Spawn proposal fetching tasks.
@@ -484,6 +494,33 @@ where
         self.node_state.clone()
     }
 
+    /// Replay a consensus trace this node recorded with `--consensus-trace-file`.
+    ///
+    /// Consensus is built from the anchor the trace starts with, using this
+    /// node's keys, stake table and upgrade lock, the way the coordinator
+    /// built it when recording.
+    pub async fn replay_consensus_trace(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> anyhow::Result<ReplayReport> {
+        let path = path.as_ref();
+        let upgrade_lock = self.upgrade_lock().await;
+        let new_consensus = |anchor: &RestartAnchor<SeqTypes>| {
+            NewConsensus::new(
+                self.node_state.coordinator.clone(),
+                self.validator_config.public_key,
+                self.validator_config.private_key.clone(),
+                self.validator_config.state_private_key.clone(),
+                self.stake_table_capacity,
+                upgrade_lock,
+                anchor.leaf.clone(),
+                anchor.epoch_height,
+            )
+        };
+        replay_file_from_anchor(new_consensus, path)
+            .with_context(|| format!("replaying consensus trace {}", path.display()))
+    }
+
     /// Start participating in consensus.
     pub async fn start_consensus(&self) {
         if let Some(orchestrator_client) = &self.wait_for_orchestrator {
@@ -19,7 +19,7 @@ pub mod state_cert;
 pub mod state_signature;
 pub mod util;
 
-use std::{fmt::Debug, marker::PhantomData, sync::Arc, time::Duration};
+use std::{fmt::Debug, marker::PhantomData, path::PathBuf, sync::Arc, time::Duration};
 
 use alloy::primitives::U256;
 use anyhow::Context;
@@ -61,13 +61,13 @@ use hotshot::{
     HotShotInitializer,
     traits::implementations::{
         CdnMetricsValue, CdnTopic, CombinedNetworks, GossipConfig, KeyPair, Libp2pNetwork,
-        MemoryNetwork, PushCdnNetwork, RequestResponseConfig, WrappedSignatureKey,
-        derive_libp2p_multiaddr, derive_libp2p_peer_id,
+        MemoryNetwork, PeerScoringConfig, PushCdnNetwork, RequestResponseConfig,
+        WrappedSignatureKey, derive_libp2p_multiaddr, derive_libp2p_peer_id,
     },
     types::SignatureKey,
 };
 use hotshot_libp2p_networking::network::behaviours::dht::store::persistent::DhtPersistentStorage;
-use hotshot_new_protocol::network::Cliquenet;
+use hotshot_new_protocol::{network::Cliquenet, trace::TraceRecorder};
 use hotshot_orchestrator::client::{OrchestratorClient, get_complete_config};
 use hotshot_types::{
     ValidatorConfig,
@@ -151,6 +151,8 @@ pub struct NetworkParams {
     /// Per-step timeout for the startup stake-table catchup walk
     /// (`bootstrap_epoch_window`).
     pub bootstrap_epoch_catchup_timeout: Duration,
+    /// File to record a consensus trace to.
+    pub consensus_trace_file: Option<PathBuf>,
     /// The address to advertise as our public API's URL
     pub public_api_url: Option<Url>,
     /// Cliquenet network address.
@@ -227,6 +229,8 @@ pub struct NetworkParams {
 
     /// Minimum number of Libp2p peers to emit gossip to during a heartbeat
     pub libp2p_gossip_lazy: usize,
+    /// Whether to score Libp2p peers by stake and link quality
+    pub libp2p_peer_scoring: bool,
 
     pub libp2p_dht_put_quorum: Option<std::num::NonZeroUsize>,
 }
@@ -649,6 +653,9 @@ where
         heartbeat_initial_delay: network_params.libp2p_heartbeat_initial_delay,
         gossip_factor: network_params.libp2p_gossip_factor,
         gossip_lazy: network_params.libp2p_gossip_lazy,
+        peer_scoring: network_params
+            .libp2p_peer_scoring
+            .then(PeerScoringConfig::default),
     };
 
     // Configure request/response based on the command line options
@@ -868,6 +875,15 @@ where
 
     let network = Arc::new(combined_network);
 
+    let consensus_trace = network_params
+        .consensus_trace_file
+        .map(|path| {
+            info!(path = %path.display(), "recording consensus trace");
+            TraceRecorder::create(&path)
+                .with_context(|| format!("creating consensus trace {}", path.display()))
+        })
+        .transpose()?;
+
     let mut ctx = SequencerContext::init(
         network_config,
         version_upgrade,
@@ -886,6 +902,7 @@ where
         event_consumer,
         proposal_fetcher_config,
         network_params.bootstrap_epoch_catchup_timeout,
+        consensus_trace,
     )
     .await?;
 
@@ -1055,6 +1072,7 @@ pub mod testing {
     use staking_cli::demo::{DelegationConfig, StakingKeySet, StakingTransactions};
     use test_utils::reserve_tcp_port;
     use tokio::{spawn, time::timeout};
+    use tokio_util::task::AbortOnDropHandle;
     use vbs::version::{StaticVersionType, Version};
     use versions::EPOCH_VERSION;
 
@@ -1096,10 +1114,13 @@ pub mod testing {
         }
     }
 
+    /// Start a legacy builder, serving its API on `port` (or a free port if not specified).
+    ///
+    /// The returned handle stops the builder API when dropped.
     pub async fn run_legacy_builder<const NUM_NODES: usize>(
         port: Option<u16>,
         max_block_size: Option<u64>,
-    ) -> (Box<dyn BuilderTask<SeqTypes>>, Url) {
+    ) -> (Box<dyn BuilderTask<SeqTypes>>, Url, AbortOnDropHandle<()>) {
         let builder_key_pair = TestConfig::<0>::builder_key();
         let port = match port {
             Some(p) => p,
@@ -1133,18 +1154,26 @@ pub mod testing {
             .into_app()
             .expect("Failed to create builder tide-disco app");
 
-        spawn(async move {
-            app.serve(
-                format!("http://0.0.0.0:{port}")
-                    .parse::<Url>()
-                    .expect("Failed to parse builder listener"),
-                EpochVersion::instance(),
-            )
-            .await
-        });
+        let server = AbortOnDropHandle::new(spawn(async move {
+            if let Err(err) = app
+                .serve(
+                    format!("http://0.0.0.0:{port}")
+                        .parse::<Url>()
+                        .expect("Failed to parse builder listener"),
+                    EpochVersion::instance(),
+                )
+                .await
+            {
+                tracing::error!(%err, "testing legacy builder API exited");
+            }
+        }));
 
         // Pass on the builder task to be injected in the testing harness
-        (Box::new(LegacyBuilderImplementation { global_state }), url)
+        (
+            Box::new(LegacyBuilderImplementation { global_state }),
+            url,
+            server,
+        )
     }
 
     pub async fn run_test_builder<const NUM_NODES: usize>(
@@ -1185,6 +1214,7 @@ pub mod testing {
         signer: LocalSigner<SigningKey>,
         state_relay_url: Option<Url>,
         builder_port: Option<u16>,
+        node_builder_url: Option<Url>,
         upgrades: BTreeMap<Version, Upgrade>,
         coordinator_addrs: Vec<NetAddr>,
         contracts: Option<Contracts>,
@@ -1257,6 +1287,13 @@ pub mod testing {
             self
         }
 
+        /// Have nodes request blocks from `url` (e.g. a builder wrapping the legacy builder)
+        /// instead of directly from the legacy builder.
+        pub fn node_builder_url(mut self, url: Url) -> Self {
+            self.node_builder_url = Some(url);
+            self
+        }
+
         pub fn state_relay_url(mut self, url: Url) -> Self {
             self.state_relay_url = Some(url);
             self
@@ -1452,10 +1489,12 @@ pub mod testing {
                 signer: self.signer,
                 state_relay_url: self.state_relay_url,
                 builder_port: self.builder_port,
+                node_builder_url: self.node_builder_url,
                 upgrades: self.upgrades,
                 anvil_provider: self.anvil_provider,
                 coordinator_addrs: self.coordinator_addrs,
                 contracts: self.contracts,
+                consensus_trace_files: Default::default(),
             }
         }
 
@@ -1571,6 +1610,7 @@ pub mod testing {
                 signer,
                 state_relay_url: None,
                 builder_port: None,
+                node_builder_url: None,
                 upgrades: Default::default(),
                 coordinator_addrs,
                 contracts: None,
@@ -1590,11 +1630,15 @@ pub mod testing {
         signer: LocalSigner<SigningKey>,
         state_relay_url: Option<Url>,
         builder_port: Option<u16>,
+        /// Builder the nodes request blocks from, if not the legacy builder.
+        node_builder_url: Option<Url>,
         upgrades: BTreeMap<Version, Upgrade>,
         /// Per-node cliquenet coordinator bind addresses, indexed by node.
         coordinator_addrs: Vec<NetAddr>,
         /// Contracts deployed by [`TestConfigBuilder::set_upgrades_with`], if any.
         contracts: Option<Contracts>,
+        /// Per-node files to record a consensus trace to, indexed by node.
+        consensus_trace_files: BTreeMap<usize, PathBuf>,
     }
 
     impl<const NUM_NODES: usize> TestConfig<NUM_NODES> {
@@ -1614,6 +1658,10 @@ pub mod testing {
             self.builder_port
         }
 
+        pub fn node_builder_url(&self) -> Option<Url> {
+            self.node_builder_url.clone()
+        }
+
         pub fn signer(&self) -> LocalSigner<SigningKey> {
             self.signer.clone()
         }
@@ -1683,6 +1731,12 @@ pub mod testing {
             self.state_key_pairs[i] = state;
         }
 
+        /// Records node `i`'s consensus trace to `path`, taking effect the
+        /// next time the node is initialized.
+        pub fn set_consensus_trace_file(&mut self, i: usize, path: PathBuf) {
+            self.consensus_trace_files.insert(i, path);
+        }
+
         /// Contracts deployed by [`TestConfigBuilder::set_upgrades_with`], if
         /// that was used to set up this config.
         pub fn contracts(&self) -> Option<Contracts> {
@@ -1890,6 +1944,11 @@ pub mod testing {
                 .await
                 .unwrap();
 
+            let consensus_trace = self
+                .consensus_trace_files
+                .get(&i)
+                .map(|path| TraceRecorder::create(path).expect("failed to create consensus trace"));
+
             SequencerContext::init(
                 NetworkConfig {
                     config,
@@ -1913,6 +1972,7 @@ pub mod testing {
                 event_consumer,
                 Default::default(),
                 Duration::from_secs(2),
+                consensus_trace,
             )
             .await
             .unwrap()
@@ -199,6 +199,11 @@ pub struct Options {
     #[clap(long, env = "ESPRESSO_NODE_LIBP2P_GOSSIP_LAZY", default_value = "6")]
     pub libp2p_gossip_lazy: usize,
 
+    /// Score Libp2p peers by stake, round-trip time and delivery outcomes when selecting
+    /// gossip mesh peers
+    #[clap(long, env = "ESPRESSO_NODE_LIBP2P_PEER_SCORING")]
+    pub libp2p_peer_scoring: bool,
+
     /// The maximum number of bytes we will send in a single Libp2p gossip message
     #[clap(
         long,
@@ -353,6 +358,25 @@ pub struct Options {
     #[clap(long, env = "ESPRESSO_NODE_BOOTSTRAP_EPOCH_CATCHUP_TIMEOUT", default_value = "30s", value_parser = parse_duration)]
     pub bootstrap_epoch_catchup_timeout: Duration,
 
+    /// Record a trace of the new protocol's consensus to this file.
+    ///
+    /// The trace starts from the state the node restarted from and can be
+    /// replayed offline to find where consensus diverged.
+    #[clap(long, env = "ESPRESSO_NODE_CONSENSUS_TRACE_FILE")]
+    pub consensus_trace_file: Option<PathBuf>,
+
+    /// Replay a consensus trace recorded with `--consensus-trace-file` and exit.
+    ///
+    /// The node starts up as usual but does not run consensus. The trace is
+    /// replayed from the anchor it was recorded from, with this node's keys
+    /// and stake table, and the node exits with an error if it diverges.
+    #[clap(
+        long,
+        env = "ESPRESSO_NODE_REPLAY_CONSENSUS_TRACE",
+        conflicts_with = "consensus_trace_file"
+    )]
+    pub replay_consensus_trace: Option<PathBuf>,
+
     #[clap(flatten)]
     pub logging: logging::Config,
 
@@ -923,6 +947,7 @@ pub struct Libp2pTuning {
     pub fanout_ttl: Duration,
     pub duplicate_cache_time: Duration,
     pub flood_publish: bool,
+    pub peer_scoring: bool,
 }
 
 #[derive(Clone, Debug, Serialize)]
@@ -966,6 +991,7 @@ impl From<&Options> for Libp2pTuning {
             fanout_ttl: o.libp2p_fanout_ttl,
             duplicate_cache_time: o.libp2p_duplicate_cache_time,
             flood_publish: o.libp2p_flood_publish,
+            peer_scoring: o.libp2p_peer_scoring,
         }
     }
 }
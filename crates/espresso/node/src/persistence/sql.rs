@@ -437,6 +437,15 @@ impl From<SqliteOptions> for Options {
         }
     }
 }
+
+#[cfg(feature = "embedded-db")]
+impl Options {
+    /// Path of the SQLite database file.
+    pub fn sqlite_path(&self) -> &std::path::Path {
+        &self.sqlite_options.path
+    }
+}
+
 impl TryFrom<&Options> for Config {
     type Error = anyhow::Error;
 
@@ -1,3 +1,4 @@
+use anyhow::ensure;
 use clap::Parser;
 use espresso_telemetry as telemetry;
 use espresso_types::traits::NullEventConsumer;
@@ -128,8 +129,22 @@ async fn run_with_storage<S>(
 where
     S: DataSourceOptions,
 {
+    let replay_consensus_trace = opt.replay_consensus_trace.clone();
     let mut ctx = init_with_storage(genesis, modules, opt, storage_opt, public_node_config).await?;
 
+    if let Some(path) = replay_consensus_trace {
+        let report = ctx.replay_consensus_trace(&path).await;
+        ctx.shut_down().await;
+        let report = report?;
+        tracing::warn!(path = %path.display(), "replayed consensus trace\n{report}");
+        ensure!(
+            report.divergence.is_none(),
+            "{} diverged on replay",
+            path.display()
+        );
+        return Ok(());
+    }
+
     // The API setup deposited the prometheus Registry into `telemetry::REGISTRY`
     // (if the HTTP module was configured). Attach the metrics push task now,
     // before consensus starts churning.
@@ -188,6 +203,7 @@ where
         catchup_base_timeout: opt.catchup_base_timeout,
         local_catchup_timeout: opt.local_catchup_timeout,
         bootstrap_epoch_catchup_timeout: opt.bootstrap_epoch_catchup_timeout,
+        consensus_trace_file: opt.consensus_trace_file,
         libp2p_history_gossip: opt.libp2p_history_gossip,
         libp2p_history_length: opt.libp2p_history_length,
         libp2p_max_ihave_length: opt.libp2p_max_ihave_length,
@@ -209,6 +225,7 @@ where
         libp2p_heartbeat_initial_delay: opt.libp2p_heartbeat_initial_delay,
         libp2p_gossip_factor: opt.libp2p_gossip_factor,
         libp2p_gossip_lazy: opt.libp2p_gossip_lazy,
+        libp2p_peer_scoring: opt.libp2p_peer_scoring,
         libp2p_dht_put_quorum: opt.libp2p_dht_put_quorum,
     };
 
@@ -304,6 +304,7 @@ impl PublicNetworkConfig {
             builder: self.builder,
             random_builder: self.random_builder,
             public_keys: Vec::new(),
+            fault_schedule: Default::default(),
         })
     }
 
@@ -2,8 +2,8 @@
 
 use hotshot_types::{
     data::VidCommitment,
-    traits::EncodeBytes,
-    vid::advz::{ADVZCommon, ADVZScheme, advz_scheme},
+    traits::{BlockPayload, EncodeBytes},
+    vid::advz::{ADVZCommon, ADVZScheme, ADVZShare, advz_scheme},
 };
 use jf_advz::{
     VidScheme,
@@ -64,6 +64,36 @@ impl ADVZNsProof {
         })
     }
 
+    /// Build a namespace proof from VID shares.
+    ///
+    /// ADVZ does not disperse namespaces separately, so the entire payload is
+    /// recovered first. The shares must already be verified against `commit`.
+    /// The result is checked against `commit` before being returned. Returns
+    /// `None` on error.
+    pub fn from_shares(
+        shares: &[ADVZShare],
+        ns_table: &NsTable,
+        index: &NsIndex,
+        commit: &VidCommitment,
+        common: &ADVZCommon,
+    ) -> Option<ADVZNsProof> {
+        let vid = advz_scheme(ADVZScheme::get_num_storage_nodes(common).try_into().ok()?);
+        let bytes = match vid.recover_payload(shares, common) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                tracing::warn!("error recovering payload from shares: {:?}", e);
+                return None;
+            },
+        };
+        let payload = Payload::from_bytes(&bytes, ns_table);
+        let proof = Self::new(&payload, index, common)?;
+        if proof.verify(ns_table, commit, common).is_none() {
+            tracing::warn!("recovered namespace {:?} does not match commitment", index);
+            return None;
+        }
+        Some(proof)
+    }
+
     /// Verify a [`NsProof`] against a payload commitment. Returns `None` on
     /// error or if verification fails.
     ///
@@ -174,7 +204,11 @@ mod tests {
     };
     use jf_advz::{VidDisperse, VidScheme};
 
-    use crate::{Payload, v0::impls::block::test::ValidTest, v0_1::ADVZNsProof};
+    use crate::{
+        Payload,
+        v0::impls::block::test::{ValidTest, ns_recovery_payload},
+        v0_1::ADVZNsProof,
+    };
 
     #[test_log::test(tokio::test(flavor = "multi_thread"))]
     async fn ns_proof() {
@@ -339,4 +373,39 @@ mod tests {
             );
         }
     }
+
+    #[test_log::test(tokio::test(flavor = "multi_thread"))]
+    async fn ns_proof_from_shares() {
+        let (block, _) = ns_recovery_payload().await;
+        let ns_table = block.ns_table();
+
+        // The recovery threshold of 10 storage nodes is 8.
+        let vid = advz_scheme(10).disperse(block.encode()).unwrap();
+        let commit = VidCommitment::V0(vid.commit);
+
+        // A threshold of shares is enough to prove each namespace, and the result matches the
+        // proof generated from the full payload.
+        for ns_index in ns_table.iter() {
+            let proof = ADVZNsProof::from_shares(
+                &vid.shares[2..],
+                ns_table,
+                &ns_index,
+                &commit,
+                &vid.common,
+            )
+            .unwrap();
+            assert_eq!(
+                proof,
+                ADVZNsProof::new(&block, &ns_index, &vid.common).unwrap()
+            );
+            assert!(proof.verify(ns_table, &commit, &vid.common).is_some());
+        }
+
+        // Too few shares.
+        let ns_index = ns_table.iter().next().unwrap();
+        assert!(
+            ADVZNsProof::from_shares(&vid.shares[..7], ns_table, &ns_index, &commit, &vid.common)
+                .is_none()
+        );
+    }
 }
@@ -39,6 +39,50 @@ impl AvidMNsProof {
         }
     }
 
+    /// Build a namespace proof by recovering only the requested namespace from VID shares.
+    ///
+    /// The shares must already be verified against the payload commitment. The result is checked
+    /// against `commit` before being returned, so a proof is only produced if the recovered
+    /// namespace is consistent with the dispersal.
+    pub fn from_shares(
+        shares: &[AvidMShare],
+        index: &NsIndex,
+        commit: &VidCommitment,
+        common: &AvidMCommon,
+    ) -> Option<AvidMNsProof> {
+        let VidCommitment::V1(commit) = commit else {
+            tracing::error!("Error recovering namespace: invalid vid commitment");
+            return None;
+        };
+        let index = index.0;
+        if shares.is_empty() || !shares[0].contains_ns(index) {
+            tracing::warn!("ns_index {:?} not present in shares", index);
+            return None;
+        }
+        if shares[0].ns_len(index) == 0 {
+            return None;
+        }
+
+        let proof = match NsAvidMScheme::namespace_proof_from_shares(common, index, shares) {
+            Ok(proof) => proof,
+            Err(e) => {
+                tracing::warn!("error recovering namespace {index} from shares: {:?}", e);
+                return None;
+            },
+        };
+        match NsAvidMScheme::verify_namespace_proof(common, commit, &proof) {
+            Ok(Ok(_)) => Some(AvidMNsProof(proof)),
+            Ok(Err(_)) => {
+                tracing::warn!("recovered namespace {index} does not match commitment");
+                None
+            },
+            Err(e) => {
+                tracing::warn!("error verifying recovered namespace {index}: {:?}", e);
+                None
+            },
+        }
+    }
+
     /// Unlike the ADVZ scheme, this function won't fail with a wrong `ns_table`.
     /// It only uses `ns_table` to get the namespace id.
     pub fn verify(
@@ -153,7 +197,11 @@ mod tests {
         vid::avidm::{AvidMParam, AvidMScheme},
     };
 
-    use crate::{NsIndex, Payload, v0::impls::block::test::ValidTest, v0_3::AvidMNsProof};
+    use crate::{
+        NsIndex, Payload,
+        v0::impls::block::test::{ValidTest, ns_recovery_payload},
+        v0_3::AvidMNsProof,
+    };
 
     #[test_log::test(tokio::test(flavor = "multi_thread"))]
     async fn ns_proof() {
@@ -260,4 +308,29 @@ mod tests {
             );
         }
     }
+
+    #[test_log::test(tokio::test(flavor = "multi_thread"))]
+    async fn ns_proof_from_shares() {
+        let (block, ns_ranges) = ns_recovery_payload().await;
+        let ns_table = block.ns_table();
+
+        let param = AvidMParam::new(3usize, 9usize).unwrap();
+        let (commit, shares) =
+            AvidMScheme::ns_disperse(&param, &[1; 9], &block.encode(), ns_ranges).unwrap();
+        let vid_commit = VidCommitment::V1(commit);
+
+        // A threshold of shares is enough to prove each namespace, and the result matches the
+        // proof generated from the full payload.
+        for ns_index in ns_table.iter() {
+            let proof =
+                AvidMNsProof::from_shares(&shares[3..6], &ns_index, &vid_commit, &param).unwrap();
+            assert_eq!(proof, AvidMNsProof::new(&block, &ns_index, &param).unwrap());
+            assert!(proof.verify(ns_table, &vid_commit, &param).is_some());
+        }
+
+        // Too few shares.
+        let ns_index = ns_table.iter().next().unwrap();
+        assert!(AvidMNsProof::from_shares(&shares[..2], &ns_index, &vid_commit, &param).is_none());
+        assert!(AvidMNsProof::from_shares(&[], &ns_index, &vid_commit, &param).is_none());
+    }
 }
@@ -2,7 +2,7 @@
 
 use hotshot_types::{
     data::{VidCommitment, VidCommon},
-    vid::avidm_gf2::AvidmGf2Common,
+    vid::avidm_gf2::{AvidmGf2Common, AvidmGf2Share},
 };
 use vid::avidm_gf2::namespaced::NsAvidmGf2Scheme;
 
@@ -42,6 +42,51 @@ impl AvidmGf2NsProof {
         }
     }
 
+    /// Build a namespace proof by recovering only the requested namespace from VID shares.
+    ///
+    /// The shares must already be verified against the payload commitment. The result is checked
+    /// against `commit` before being returned, so a proof is only produced if the recovered
+    /// namespace is consistent with the dispersal.
+    pub fn from_shares(
+        shares: &[AvidmGf2Share],
+        index: &NsIndex,
+        commit: &VidCommitment,
+        common: &AvidmGf2Common,
+    ) -> Option<AvidmGf2NsProof> {
+        let VidCommitment::V2(commit) = commit else {
+            tracing::error!("Error recovering namespace: invalid vid commitment");
+            return None;
+        };
+        let index = index.0;
+        match common.ns_lens.get(index) {
+            None => {
+                tracing::warn!("ns_index {:?} out of bounds", index);
+                return None;
+            },
+            Some(0) => return None,
+            Some(_) => {},
+        }
+
+        let proof = match NsAvidmGf2Scheme::namespace_proof_from_shares(common, index, shares) {
+            Ok(proof) => proof,
+            Err(e) => {
+                tracing::warn!("error recovering namespace {index} from shares: {:?}", e);
+                return None;
+            },
+        };
+        match NsAvidmGf2Scheme::verify_namespace_proof(commit, common, &proof) {
+            Ok(Ok(_)) => Some(AvidmGf2NsProof(proof)),
+            Ok(Err(_)) => {
+                tracing::warn!("recovered namespace {index} does not match commitment");
+                None
+            },
+            Err(e) => {
+                tracing::warn!("error verifying recovered namespace {index}: {:?}", e);
+                None
+            },
+        }
+    }
+
     /// Unlike the ADVZ scheme, this function won't fail with a wrong `ns_table`.
     /// It only uses `ns_table` to get the namespace id.
     pub fn verify(
@@ -81,7 +126,11 @@ mod tests {
         vid::avidm_gf2::{AvidmGf2Param, AvidmGf2Scheme},
     };
 
-    use crate::{NsIndex, Payload, v0::impls::block::test::ValidTest, v0_6::AvidmGf2NsProof};
+    use crate::{
+        NsIndex, Payload,
+        v0::impls::block::test::{ValidTest, ns_recovery_payload},
+        v0_6::AvidmGf2NsProof,
+    };
 
     #[test_log::test(tokio::test(flavor = "multi_thread"))]
     async fn ns_proof() {
@@ -194,4 +243,36 @@ mod tests {
             );
         }
     }
+
+    #[test_log::test(tokio::test(flavor = "multi_thread"))]
+    async fn ns_proof_from_shares() {
+        let (block, ns_ranges) = ns_recovery_payload().await;
+        let ns_table = block.ns_table();
+
+        let param = AvidmGf2Param::new(3usize, 9usize).unwrap();
+        let (commit, common, shares) =
+            AvidmGf2Scheme::ns_disperse(&param, &[1; 9], &block.encode(), ns_ranges).unwrap();
+        let vid_commit = VidCommitment::V2(commit);
+        let vid_common = VidCommon::V2(common.clone());
+
+        // A threshold of shares is enough to prove each namespace, and the result matches the
+        // proof generated from the full payload.
+        for ns_index in ns_table.iter() {
+            let proof =
+                AvidmGf2NsProof::from_shares(&shares[3..6], &ns_index, &vid_commit, &common)
+                    .unwrap();
+            assert_eq!(
+                proof,
+                AvidmGf2NsProof::new(&block, &ns_index, &common).unwrap()
+            );
+            assert!(proof.verify(ns_table, &vid_commit, &vid_common).is_some());
+        }
+
+        // Too few shares.
+        let ns_index = ns_table.iter().next().unwrap();
+        assert!(
+            AvidmGf2NsProof::from_shares(&shares[..2], &ns_index, &vid_commit, &common).is_none()
+        );
+        assert!(AvidmGf2NsProof::from_shares(&[], &ns_index, &vid_commit, &common).is_none());
+    }
 }
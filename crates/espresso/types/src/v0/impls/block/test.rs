@@ -1,5 +1,5 @@
 #![cfg(test)]
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, ops::Range};
 
 use hotshot::traits::BlockPayload;
 use hotshot_query_service::availability::{QueryablePayload, VerifiableInclusion};
@@ -194,6 +194,29 @@ impl ValidTest {
     }
 }
 
+/// A payload with three non-empty namespaces, and the byte range of each namespace.
+///
+/// Shared by the tests which recover namespace proofs from VID shares.
+pub async fn ns_recovery_payload() -> (Payload, Vec<Range<usize>>) {
+    let mut rng = jf_utils::test_rng();
+    let test = ValidTest::from_tx_lengths(
+        vec![vec![5, 8, 8], vec![7, 9, 11], vec![10, 5, 8]],
+        &mut rng,
+    );
+    let block =
+        Payload::from_transactions(test.all_txs(), &Default::default(), &Default::default())
+            .await
+            .unwrap()
+            .0;
+    let payload_byte_len = block.byte_len();
+    let ns_table = block.ns_table();
+    let ns_ranges = ns_table
+        .iter()
+        .map(|index| ns_table.ns_range(&index, &payload_byte_len).0)
+        .collect();
+    (block, ns_ranges)
+}
+
 fn random_bytes<R: RngCore>(len: usize, rng: &mut R) -> Vec<u8> {
     let mut result = vec![0; len];
     rng.fill_bytes(&mut result);
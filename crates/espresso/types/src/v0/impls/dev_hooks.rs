@@ -0,0 +1,59 @@
+//! Hooks letting a single-process development network steer block production.
+//!
+//! This module only exists with the `testing` feature, so production nodes always build and
+//! validate headers against the system clock and their own L1 client.
+
+use std::sync::{Arc, OnceLock};
+
+use anyhow::anyhow;
+use hotshot_types::utils::BuilderCommitment;
+use time::OffsetDateTime;
+
+/// Overrides consulted by every node in the process when building and validating headers.
+pub trait BlockHooks: Send + Sync {
+    /// Called before building a header at `height` which commits to `builder_commitment`.
+    ///
+    /// Returning an error aborts the proposal.
+    fn begin_proposal(
+        &self,
+        height: u64,
+        builder_commitment: &BuilderCommitment,
+    ) -> anyhow::Result<()>;
+
+    /// The L1 head to reference in a header at `height`, if overridden.
+    fn l1_head(&self, height: u64) -> Option<u64>;
+
+    /// The current time, used to timestamp new headers and to check the drift of proposed ones.
+    fn now(&self) -> OffsetDateTime;
+}
+
+static HOOKS: OnceLock<Arc<dyn BlockHooks>> = OnceLock::new();
+
+/// Install `hooks` for the rest of the process.
+///
+/// Fails if hooks have already been installed.
+pub fn install(hooks: Arc<dyn BlockHooks>) -> anyhow::Result<()> {
+    HOOKS
+        .set(hooks)
+        .map_err(|_| anyhow!("block hooks are already installed"))
+}
+
+pub(crate) fn begin_proposal(
+    height: u64,
+    builder_commitment: &BuilderCommitment,
+) -> anyhow::Result<()> {
+    match HOOKS.get() {
+        Some(hooks) => hooks.begin_proposal(height, builder_commitment),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn l1_head(height: u64) -> Option<u64> {
+    HOOKS.get().and_then(|hooks| hooks.l1_head(height))
+}
+
+pub(crate) fn now() -> OffsetDateTime {
+    HOOKS
+        .get()
+        .map_or_else(OffsetDateTime::now_utc, |hooks| hooks.now())
+}
@@ -30,8 +30,6 @@ use serde::{
 };
 use serde_json::{Map, Value};
 use thiserror::Error;
-#[cfg(feature = "node")]
-use time::OffsetDateTime;
 use vbs::version::Version;
 use versions::EPOCH_REWARD_VERSION;
 #[cfg(feature = "node")]
@@ -1309,6 +1307,9 @@ impl BlockHeader<SeqTypes> for Header {
             let height = parent_leaf.height();
             let view = parent_leaf.view_number();
 
+            #[cfg(any(test, feature = "testing"))]
+            super::dev_hooks::begin_proposal(height + 1, &builder_commitment)?;
+
             let mut validated_state = parent_state.clone();
 
             let chain_config = if version > instance_state.current_version {
@@ -1330,6 +1331,11 @@ impl BlockHeader<SeqTypes> for Header {
 
             // Fetch the latest L1 snapshot.
             let l1_snapshot = instance_state.l1_client.snapshot().await;
+            #[cfg(any(test, feature = "testing"))]
+            let l1_snapshot = L1Snapshot {
+                head: super::dev_hooks::l1_head(height + 1).unwrap_or(l1_snapshot.head),
+                ..l1_snapshot
+            };
             // Fetch the new L1 deposits between parent and current finalized L1 block.
             let l1_deposits = if let (Some(addr), Some(block_info)) =
                 (chain_config.fee_contract, l1_snapshot.finalized)
@@ -1491,7 +1497,10 @@ impl BlockHeader<SeqTypes> for Header {
                 }
             }
 
-            let now = OffsetDateTime::now_utc();
+            #[cfg(not(any(test, feature = "testing")))]
+            let now = time::OffsetDateTime::now_utc();
+            #[cfg(any(test, feature = "testing"))]
+            let now = super::dev_hooks::now();
 
             let timestamp = now.unix_timestamp() as u64;
             let timestamp_millis = TimestampMillis::from_time(&now).u64();
@@ -3,6 +3,8 @@ pub use super::*;
 mod block;
 mod chain_config;
 mod committee;
+#[cfg(any(test, feature = "testing"))]
+pub mod dev_hooks;
 mod fee_info;
 mod header;
 mod instance_state;
@@ -1329,7 +1329,10 @@ impl HotShotState<SeqTypes> for ValidatedState {
             // would require some refactoring of the header validation code that is out of scope for
             // now. Record the time when validation started to later use it to validate the
             // timestamp drift.
+            #[cfg(not(any(test, feature = "testing")))]
             let validation_start_time = OffsetDateTime::now_utc();
+            #[cfg(any(test, feature = "testing"))]
+            let validation_start_time = super::dev_hooks::now();
 
             let (validated_state, delta, total_rewards_distributed) = self
                 // TODO We can add this logic to `ValidatedTransition` or do something similar to that here.
@@ -17,6 +17,8 @@ mod utils;
 
 pub use header::Header;
 #[cfg(any(test, feature = "testing"))]
+pub use impls::dev_hooks;
+#[cfg(any(test, feature = "testing"))]
 pub use impls::mock;
 // export reward types for staking-ui-service
 pub use impls::reward::{
@@ -1,5 +1,6 @@
+use anyhow::{Context, ensure};
 use hotshot_types::{
-    data::{VidCommitment, VidCommon},
+    data::{VidCommitment, VidCommon, VidShare},
     vid::avidm::AvidMShare,
 };
 use serde::{Deserialize, Serialize};
@@ -47,6 +48,75 @@ impl NsProof {
         }
     }
 
+    /// Build a namespace proof from VID shares, without the full payload.
+    ///
+    /// AvidM dispersals (V1 and V2) recover only the requested namespace, for ADVZ (V0) the
+    /// entire payload is recovered first. Shares of another version than `common` are ignored.
+    ///
+    /// As with [`NsProof::new`], there is no AvidM proof for an empty namespace, in which case
+    /// `Ok(None)` is returned.
+    pub fn new_from_shares(
+        shares: &[VidShare],
+        ns_table: &NsTable,
+        index: &NsIndex,
+        commit: &VidCommitment,
+        common: &VidCommon,
+    ) -> anyhow::Result<Option<NsProof>> {
+        match common {
+            VidCommon::V0(common) => {
+                let shares = shares
+                    .iter()
+                    .filter_map(|share| match share {
+                        VidShare::V0(share) => Some(share.clone()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                let proof = ADVZNsProof::from_shares(&shares, ns_table, index, commit, common)
+                    .context("failed to recover ADVZ namespace from shares")?;
+                Ok(Some(NsProof::V0(proof)))
+            },
+            VidCommon::V1(common) => {
+                let shares = shares
+                    .iter()
+                    .filter_map(|share| match share {
+                        VidShare::V1(share) => Some(share.clone()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                let share = shares.first().context("no AvidM shares")?;
+                ensure!(
+                    share.contains_ns(index.0),
+                    "namespace not present in shares"
+                );
+                if share.ns_len(index.0) == 0 {
+                    return Ok(None);
+                }
+                let proof = AvidMNsProof::from_shares(&shares, index, commit, common)
+                    .context("failed to recover AvidM namespace from shares")?;
+                Ok(Some(NsProof::V1(proof)))
+            },
+            VidCommon::V2(common) => {
+                let shares = shares
+                    .iter()
+                    .filter_map(|share| match share {
+                        VidShare::V2(share) => Some(share.clone()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                let len = common
+                    .ns_lens
+                    .get(index.0)
+                    .context("namespace not present in VID common")?;
+                if *len == 0 {
+                    return Ok(None);
+                }
+                let proof = AvidmGf2NsProof::from_shares(&shares, index, commit, common)
+                    .context("failed to recover AvidmGf2 namespace from shares")?;
+                Ok(Some(NsProof::V2(proof)))
+            },
+        }
+    }
+
     pub fn v1_1_new_with_incorrect_encoding(
         shares: &[AvidMShare],
         ns_table: &NsTable,
@@ -95,3 +165,148 @@ impl NsProof {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use hotshot::traits::BlockPayload;
+    use hotshot_types::{
+        data::{VidCommitment, VidCommon, VidShare},
+        traits::EncodeBytes,
+        vid::{
+            advz::advz_scheme,
+            avidm::{AvidMParam, AvidMScheme},
+            avidm_gf2::{AvidmGf2Param, AvidmGf2Scheme},
+        },
+    };
+    use jf_advz::VidScheme;
+
+    use super::NsProof;
+    use crate::{NamespaceId, NsTableBuilder, Payload, v0::impls::block::test::ValidTest};
+
+    /// A payload whose last namespace is empty.
+    async fn payload_with_empty_ns() -> Payload {
+        let mut rng = jf_utils::test_rng();
+        let test = ValidTest::from_tx_lengths(
+            vec![vec![5, 8, 8], vec![7, 9, 11], vec![10, 5, 8]],
+            &mut rng,
+        );
+        let block =
+            Payload::from_transactions(test.all_txs(), &Default::default(), &Default::default())
+                .await
+                .unwrap()
+                .0;
+        let ns_table = block.ns_table();
+        let payload_byte_len = block.byte_len();
+
+        // An offset beyond the end of the payload yields an empty namespace range.
+        let mut builder = NsTableBuilder::new();
+        for index in ns_table.iter() {
+            builder.append_entry(
+                ns_table.read_ns_id_unchecked(&index),
+                ns_table.ns_range(&index, &payload_byte_len).0.end,
+            );
+        }
+        builder.append_entry(NamespaceId::random(&mut rng), block.encode().len() + 1);
+        let payload = Payload::from_bytes(&block.encode(), &builder.into_ns_table());
+
+        let payload_byte_len = payload.byte_len();
+        let last = payload.ns_table().iter().last().unwrap();
+        assert!(
+            payload
+                .ns_table()
+                .ns_range(&last, &payload_byte_len)
+                .0
+                .is_empty()
+        );
+        payload
+    }
+
+    fn ns_ranges(payload: &Payload) -> Vec<std::ops::Range<usize>> {
+        let payload_byte_len = payload.byte_len();
+        let ns_table = payload.ns_table();
+        ns_table
+            .iter()
+            .map(|index| ns_table.ns_range(&index, &payload_byte_len).0)
+            .collect()
+    }
+
+    /// Recover every namespace from `shares` and compare with the proof from the full payload.
+    fn check_recovery(
+        payload: &Payload,
+        shares: &[VidShare],
+        commit: &VidCommitment,
+        common: &VidCommon,
+    ) {
+        let ns_table = payload.ns_table();
+        for index in ns_table.iter() {
+            let proof = NsProof::new_from_shares(shares, ns_table, &index, commit, common).unwrap();
+            assert_eq!(proof, NsProof::new(payload, &index, common));
+            if let Some(proof) = proof {
+                assert!(proof.verify(ns_table, commit, common).is_some());
+            }
+        }
+        let index = ns_table.iter().next().unwrap();
+        assert!(NsProof::new_from_shares(&[], ns_table, &index, commit, common).is_err());
+    }
+
+    #[test_log::test(tokio::test(flavor = "multi_thread"))]
+    async fn ns_proof_from_v0_shares() {
+        let payload = payload_with_empty_ns().await;
+        let vid = advz_scheme(10).disperse(payload.encode()).unwrap();
+
+        // The recovery threshold of 10 storage nodes is 8.
+        let shares = vid.shares[2..]
+            .iter()
+            .cloned()
+            .map(VidShare::V0)
+            .collect::<Vec<_>>();
+        check_recovery(
+            &payload,
+            &shares,
+            &VidCommitment::V0(vid.commit),
+            &VidCommon::V0(vid.common),
+        );
+    }
+
+    #[test_log::test(tokio::test(flavor = "multi_thread"))]
+    async fn ns_proof_from_v1_shares() {
+        let payload = payload_with_empty_ns().await;
+        let param = AvidMParam::new(3usize, 9usize).unwrap();
+        let (commit, shares) =
+            AvidMScheme::ns_disperse(&param, &[1; 9], &payload.encode(), ns_ranges(&payload))
+                .unwrap();
+
+        let shares = shares[3..6]
+            .iter()
+            .cloned()
+            .map(VidShare::V1)
+            .collect::<Vec<_>>();
+        check_recovery(
+            &payload,
+            &shares,
+            &VidCommitment::V1(commit),
+            &VidCommon::V1(param),
+        );
+    }
+
+    #[test_log::test(tokio::test(flavor = "multi_thread"))]
+    async fn ns_proof_from_v2_shares() {
+        let payload = payload_with_empty_ns().await;
+        let param = AvidmGf2Param::new(3usize, 9usize).unwrap();
+        let (commit, common, shares) =
+            AvidmGf2Scheme::ns_disperse(&param, &[1; 9], &payload.encode(), ns_ranges(&payload))
+                .unwrap();
+
+        let shares = shares[3..6]
+            .iter()
+            .cloned()
+            .map(VidShare::V2)
+            .collect::<Vec<_>>();
+        check_recovery(
+            &payload,
+            &shares,
+            &VidCommitment::V2(commit),
+            &VidCommon::V2(common),
+        );
+    }
+}
@@ -8,7 +8,7 @@ use std::fmt::Debug;
 
 use hotshot::traits::implementations::CombinedNetworks;
 use hotshot_example_types::{state_types::TestTypes, storage_types::TestStorage};
-use hotshot_examples::infra::CombinedDaRun;
+use hotshot_examples::{faults::FaultyNetwork, infra::CombinedDaRun};
 use hotshot_types::traits::node_implementation::NodeImplementation;
 use serde::{Deserialize, Serialize};
 
@@ -17,7 +17,7 @@ use serde::{Deserialize, Serialize};
 pub struct NodeImpl {}
 
 /// Convenience type alias
-pub type Network = CombinedNetworks<TestTypes>;
+pub type Network = FaultyNetwork<TestTypes, CombinedNetworks<TestTypes>>;
 
 impl NodeImplementation<TestTypes> for NodeImpl {
     type Network = Network;
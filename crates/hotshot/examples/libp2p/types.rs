@@ -8,7 +8,7 @@ use std::fmt::Debug;
 
 use hotshot::traits::implementations::Libp2pNetwork;
 use hotshot_example_types::{state_types::TestTypes, storage_types::TestStorage};
-use hotshot_examples::infra::Libp2pDaRun;
+use hotshot_examples::{faults::FaultyNetwork, infra::Libp2pDaRun};
 use hotshot_types::traits::node_implementation::NodeImplementation;
 use serde::{Deserialize, Serialize};
 
@@ -17,7 +17,7 @@ use serde::{Deserialize, Serialize};
 pub struct NodeImpl {}
 
 /// Convenience type alias
-pub type Network = Libp2pNetwork<TestTypes>;
+pub type Network = FaultyNetwork<TestTypes, Libp2pNetwork<TestTypes>>;
 
 impl NodeImplementation<TestTypes> for NodeImpl {
     type Network = Network;
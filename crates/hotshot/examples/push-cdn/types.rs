@@ -6,7 +6,7 @@
 
 use hotshot::traits::{NodeImplementation, implementations::PushCdnNetwork};
 use hotshot_example_types::{state_types::TestTypes, storage_types::TestStorage};
-use hotshot_examples::infra::PushCdnDaRun;
+use hotshot_examples::{faults::FaultyNetwork, infra::PushCdnDaRun};
 use hotshot_types::traits::node_implementation::NodeType;
 use serde::{Deserialize, Serialize};
 
@@ -15,7 +15,7 @@ use serde::{Deserialize, Serialize};
 pub struct NodeImpl {}
 
 /// Convenience type alias
-pub type Network = PushCdnNetwork<<TestTypes as NodeType>::SignatureKey>;
+pub type Network = FaultyNetwork<TestTypes, PushCdnNetwork<<TestTypes as NodeType>::SignatureKey>>;
 
 impl NodeImplementation<TestTypes> for NodeImpl {
     type Network = Network;
@@ -0,0 +1,586 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Applies the fault schedule of a benchmark run to a node's network.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use async_lock::{Mutex, RwLock};
+use async_trait::async_trait;
+use hotshot_orchestrator::client::OrchestratorClient;
+use hotshot_types::{
+    BoxSyncFuture,
+    data::{EpochNumber, ViewNumber},
+    epoch_membership::EpochMembershipCoordinator,
+    fault_schedule::{FaultSchedule, FaultState},
+    network::NetworkConfig,
+    traits::{
+        network::{BroadcastDelay, ConnectedNetwork, NetworkError, Topic},
+        node_implementation::NodeType,
+        signature_key::StakeTableEntryType,
+    },
+};
+use tokio::{
+    select, spawn,
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender, error::TrySendError},
+    time::sleep,
+};
+use tracing::{info, warn};
+
+/// Length of the header carrying the sender's node index, prepended to every message.
+const HEADER_LEN: usize = size_of::<u64>();
+
+/// A network that drops and delays messages according to the run's fault schedule.
+///
+/// Whenever the schedule changes at the current view, the faults in effect are fetched from the
+/// orchestrator. Every message is prefixed with the node index of its sender, so that the faults
+/// can be applied on both ends of a link: a killed node neither sends nor receives, messages
+/// across a partition are dropped by the sender if it knows the recipient and otherwise by the
+/// recipient, and link delays are applied by the recipient. Broadcasts always go through the
+/// underlying network, so they still reach nodes outside the stake table and keep their
+/// [`BroadcastDelay`]. With an empty schedule every call is passed through unchanged.
+#[derive(Clone)]
+pub struct FaultyNetwork<TYPES: NodeType, N> {
+    /// the underlying network
+    inner: N,
+    /// the faults shared by all clones of the network
+    faults: Arc<Faults<TYPES>>,
+}
+
+/// The faults in effect for this node.
+struct Faults<TYPES: NodeType> {
+    /// client used to fetch the faults
+    client: OrchestratorClient,
+    /// the schedule from the run config, used if the orchestrator cannot be reached
+    schedule: FaultSchedule,
+    /// views at which the schedule changes
+    event_views: BTreeSet<u64>,
+    /// our node index
+    node_index: u64,
+    /// whether we are in the stake table, nodes outside of it are not subject to faults
+    staked: bool,
+    /// node indices of all nodes by public key
+    indices: HashMap<TYPES::SignatureKey, u64>,
+    /// the highest view we fetched the faults for
+    polled_view: AtomicU64,
+    /// the view the current faults were fetched for, and the faults
+    current: RwLock<(u64, FaultState)>,
+    /// received messages whose link delay has passed
+    delayed_sender: UnboundedSender<Vec<u8>>,
+    /// receiving end of `delayed_sender`
+    delayed: Mutex<UnboundedReceiver<Vec<u8>>>,
+}
+
+impl<TYPES: NodeType, N: ConnectedNetwork<TYPES::SignatureKey>> FaultyNetwork<TYPES, N> {
+    /// Wrap `inner`, applying the fault schedule of `config`.
+    pub fn new(inner: N, config: &NetworkConfig<TYPES>, client: OrchestratorClient) -> Self {
+        let indices = config
+            .config
+            .known_nodes_with_stake
+            .iter()
+            .map(|peer| peer.stake_table_entry.public_key())
+            .zip(0..)
+            .collect();
+        let schedule = config.fault_schedule.clone();
+        let event_views = schedule.events.iter().map(|event| event.view).collect();
+        let (delayed_sender, delayed) = mpsc::unbounded_channel();
+
+        Self {
+            inner,
+            faults: Arc::new(Faults {
+                client,
+                event_views,
+                node_index: config.node_index,
+                staked: config.node_index < config.config.known_nodes_with_stake.len() as u64,
+                indices,
+                polled_view: AtomicU64::new(0),
+                current: RwLock::new((0, schedule.state_at(0))),
+                schedule,
+                delayed_sender,
+                delayed: Mutex::new(delayed),
+            }),
+        }
+    }
+
+    /// The faults in effect.
+    async fn state(&self) -> FaultState {
+        self.faults.current.read().await.1.clone()
+    }
+
+    /// Whether messages are passed through without a header, because no faults are ever applied.
+    fn passthrough(&self) -> bool {
+        self.faults.schedule.is_empty()
+    }
+
+    /// Prefix `message` with our node index.
+    fn frame(&self, message: Vec<u8>) -> Vec<u8> {
+        if self.passthrough() {
+            return message;
+        }
+        let mut framed = Vec::with_capacity(HEADER_LEN + message.len());
+        framed.extend_from_slice(&self.faults.node_index.to_be_bytes());
+        framed.extend(message);
+        framed
+    }
+
+    /// Split a received message into the node index of its sender and the message itself.
+    fn unframe(&self, mut framed: Vec<u8>) -> Result<(Option<u64>, Vec<u8>), NetworkError> {
+        if self.passthrough() {
+            return Ok((None, framed));
+        }
+        let header: [u8; HEADER_LEN] = framed
+            .get(..HEADER_LEN)
+            .and_then(|header| header.try_into().ok())
+            .ok_or_else(|| {
+                NetworkError::FailedToDeserialize("message without fault header".to_string())
+            })?;
+        framed.drain(..HEADER_LEN);
+        Ok((Some(u64::from_be_bytes(header)), framed))
+    }
+
+    /// Whether a message from us reaches `recipient`. Nodes outside the stake table are not
+    /// subject to faults.
+    fn reaches(&self, state: &FaultState, recipient: &TYPES::SignatureKey) -> bool {
+        !self.faults.staked
+            || self
+                .faults
+                .indices
+                .get(recipient)
+                .is_none_or(|&to| state.can_communicate(self.faults.node_index, to))
+    }
+
+    /// The next received message whose link delay has passed.
+    async fn next_delayed(&self) -> Option<Vec<u8>> {
+        self.faults.delayed.lock().await.recv().await
+    }
+
+    /// Fetch the faults of `view` in the background if the schedule changes since the last fetch.
+    fn poll_faults(&self, view: u64) {
+        let previous = self.faults.polled_view.fetch_max(view, Ordering::Relaxed);
+        if previous >= view
+            || self
+                .faults
+                .event_views
+                .range(previous + 1..=view)
+                .next()
+                .is_none()
+        {
+            return;
+        }
+
+        let faults = Arc::clone(&self.faults);
+        spawn(async move {
+            let state = match faults.client.get_faults(view).await {
+                Ok(state) => state,
+                Err(err) => {
+                    warn!(
+                        "Failed to fetch faults for view {view}, using the local schedule: {err}"
+                    );
+                    faults.schedule.state_at(view)
+                },
+            };
+            let mut current = faults.current.write().await;
+            if current.0 <= view {
+                info!("Applying faults of view {view}: {state:?}");
+                *current = (view, state);
+            }
+        });
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, N: ConnectedNetwork<TYPES::SignatureKey>>
+    ConnectedNetwork<TYPES::SignatureKey> for FaultyNetwork<TYPES, N>
+{
+    fn pause(&self) {
+        self.inner.pause();
+    }
+
+    fn resume(&self) {
+        self.inner.resume();
+    }
+
+    async fn wait_for_ready(&self) {
+        self.inner.wait_for_ready().await;
+    }
+
+    fn shut_down<'a, 'b>(&'a self) -> BoxSyncFuture<'b, ()>
+    where
+        'a: 'b,
+        Self: 'b,
+    {
+        self.inner.shut_down()
+    }
+
+    async fn broadcast_message(
+        &self,
+        view: ViewNumber,
+        message: Vec<u8>,
+        topic: Topic,
+        broadcast_delay: BroadcastDelay,
+    ) -> Result<(), NetworkError> {
+        // Partitions and delays are applied by the recipients
+        if self.state().await.is_killed(self.faults.node_index) {
+            return Ok(());
+        }
+        self.inner
+            .broadcast_message(view, self.frame(message), topic, broadcast_delay)
+            .await
+    }
+
+    async fn da_broadcast_message(
+        &self,
+        view: ViewNumber,
+        message: Vec<u8>,
+        recipients: Vec<TYPES::SignatureKey>,
+        broadcast_delay: BroadcastDelay,
+    ) -> Result<(), NetworkError> {
+        let state = self.state().await;
+        if state.is_killed(self.faults.node_index) {
+            return Ok(());
+        }
+        let recipients = recipients
+            .into_iter()
+            .filter(|recipient| self.reaches(&state, recipient))
+            .collect();
+        self.inner
+            .da_broadcast_message(view, self.frame(message), recipients, broadcast_delay)
+            .await
+    }
+
+    async fn vid_broadcast_message(
+        &self,
+        messages: HashMap<TYPES::SignatureKey, (ViewNumber, Vec<u8>)>,
+    ) -> Result<(), NetworkError> {
+        let state = self.state().await;
+        if state.is_killed(self.faults.node_index) {
+            return Ok(());
+        }
+        let messages = messages
+            .into_iter()
+            .filter(|(recipient, _)| self.reaches(&state, recipient))
+            .map(|(recipient, (view, message))| (recipient, (view, self.frame(message))))
+            .collect();
+        self.inner.vid_broadcast_message(messages).await
+    }
+
+    async fn direct_message(
+        &self,
+        view: ViewNumber,
+        message: Vec<u8>,
+        recipient: TYPES::SignatureKey,
+    ) -> Result<(), NetworkError> {
+        let state = self.state().await;
+        if state.is_killed(self.faults.node_index) || !self.reaches(&state, &recipient) {
+            return Ok(());
+        }
+        self.inner
+            .direct_message(view, self.frame(message), recipient)
+            .await
+    }
+
+    async fn recv_message(&self) -> Result<Vec<u8>, NetworkError> {
+        loop {
+            let (sender, message) = select! {
+                message = self.inner.recv_message() => self.unframe(message?)?,
+                Some(message) = self.next_delayed() => return Ok(message),
+            };
+
+            let state = self.state().await;
+            let me = self.faults.node_index;
+            // A killed node drops everything it receives
+            if state.is_killed(me) {
+                continue;
+            }
+            let Some(sender) = sender.filter(|_| self.faults.staked) else {
+                return Ok(message);
+            };
+            // The sender may not have applied the faults yet, or not known where we are
+            if !state.can_communicate(sender, me) {
+                continue;
+            }
+
+            let delay = state.link_delay(sender, me);
+            if delay.is_zero() {
+                return Ok(message);
+            }
+            let delayed = self.faults.delayed_sender.clone();
+            spawn(async move {
+                sleep(delay).await;
+                let _ = delayed.send(message);
+            });
+        }
+    }
+
+    fn queue_node_lookup(
+        &self,
+        view_number: ViewNumber,
+        pk: TYPES::SignatureKey,
+    ) -> Result<(), TrySendError<Option<(ViewNumber, TYPES::SignatureKey)>>> {
+        self.inner.queue_node_lookup(view_number, pk)
+    }
+
+    async fn update_view<T>(
+        &self,
+        view: ViewNumber,
+        epoch: Option<EpochNumber>,
+        membership: EpochMembershipCoordinator<T>,
+    ) where
+        T: NodeType<SignatureKey = TYPES::SignatureKey>,
+    {
+        self.poll_faults(*view);
+        self.inner.update_view::<T>(view, epoch, membership).await;
+    }
+
+    fn is_primary_down(&self) -> bool {
+        self.inner.is_primary_down()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use hotshot::traits::implementations::{MasterMap, MemoryNetwork};
+    use hotshot_example_types::node_types::TestTypes;
+    use hotshot_types::{
+        PeerConfig,
+        fault_schedule::{FaultAction, FaultEvent, LinkDelay},
+        signature_key::BLSPubKey,
+        traits::signature_key::SignatureKey,
+    };
+    use tokio::time::{Instant, timeout};
+    use url::Url;
+
+    use super::*;
+
+    type Network = FaultyNetwork<TestTypes, MemoryNetwork<BLSPubKey>>;
+
+    /// Number of nodes in the stake table.
+    const NUM_STAKED: u64 = 3;
+
+    /// Staked nodes `0..NUM_STAKED`, followed by one node outside the stake table.
+    fn networks() -> Vec<(BLSPubKey, Network)> {
+        let keys: Vec<_> = (0..=NUM_STAKED)
+            .map(|i| BLSPubKey::generated_from_seed_indexed([0u8; 32], i).0)
+            .collect();
+        let stake = PeerConfig::<TestTypes>::test_default()
+            .stake_table_entry
+            .stake();
+
+        let mut config = NetworkConfig::<TestTypes>::default();
+        config.config.known_nodes_with_stake = keys[..NUM_STAKED as usize]
+            .iter()
+            .map(|key| PeerConfig {
+                stake_table_entry: key.stake_table_entry(stake),
+                ..PeerConfig::test_default()
+            })
+            .collect();
+        // Never reached, but makes the network apply faults
+        config.fault_schedule = FaultSchedule {
+            events: vec![FaultEvent {
+                view: u64::MAX,
+                action: FaultAction::Heal,
+            }],
+        };
+        // Nothing listens here, the tests set the faults directly
+        let client = OrchestratorClient::new(Url::parse("http://localhost:1").unwrap());
+
+        let master_map = MasterMap::new();
+        keys.into_iter()
+            .zip(0..)
+            .map(|(key, node_index)| {
+                let inner =
+                    MemoryNetwork::new(&key, &master_map, &[Topic::Global, Topic::Da], None);
+                config.node_index = node_index;
+                (key, FaultyNetwork::new(inner, &config, client.clone()))
+            })
+            .collect()
+    }
+
+    async fn set_faults(network: &Network, state: FaultState) {
+        *network.faults.current.write().await = (0, state);
+    }
+
+    async fn set_all_faults(networks: &[(BLSPubKey, Network)], state: &FaultState) {
+        for (_, network) in networks {
+            set_faults(network, state.clone()).await;
+        }
+    }
+
+    /// The next message received within a short timeout.
+    async fn recv(network: &Network) -> Option<Vec<u8>> {
+        timeout(Duration::from_millis(200), network.recv_message())
+            .await
+            .ok()
+            .map(|message| message.unwrap())
+    }
+
+    async fn broadcast(network: &Network, message: &[u8]) {
+        network
+            .broadcast_message(
+                ViewNumber::new(1),
+                message.to_vec(),
+                Topic::Global,
+                BroadcastDelay::None,
+            )
+            .await
+            .unwrap();
+    }
+
+    async fn direct(network: &Network, message: &[u8], recipient: BLSPubKey) {
+        network
+            .direct_message(ViewNumber::new(1), message.to_vec(), recipient)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_kill() {
+        let networks = networks();
+        set_all_faults(
+            &networks,
+            &FaultState {
+                killed: [1].into(),
+                ..Default::default()
+            },
+        )
+        .await;
+
+        // A killed node receives nothing, everyone else still gets broadcasts
+        broadcast(&networks[0].1, b"broadcast").await;
+        assert_eq!(recv(&networks[0].1).await.unwrap(), b"broadcast");
+        assert_eq!(recv(&networks[2].1).await.unwrap(), b"broadcast");
+        assert_eq!(recv(&networks[3].1).await.unwrap(), b"broadcast");
+        assert_eq!(recv(&networks[1].1).await, None);
+
+        // A killed node sends nothing
+        broadcast(&networks[1].1, b"killed").await;
+        direct(&networks[1].1, b"killed", networks[0].0).await;
+        for (_, network) in &networks {
+            assert_eq!(recv(network).await, None);
+        }
+
+        // A killed node drops messages from senders that do not know it is down
+        set_faults(&networks[0].1, FaultState::default()).await;
+        direct(&networks[0].1, b"direct", networks[1].0).await;
+        assert_eq!(recv(&networks[1].1).await, None);
+
+        // Restarted nodes communicate again
+        set_all_faults(&networks, &FaultState::default()).await;
+        direct(&networks[1].1, b"restarted", networks[0].0).await;
+        assert_eq!(recv(&networks[0].1).await.unwrap(), b"restarted");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_partition() {
+        let networks = networks();
+        set_all_faults(
+            &networks,
+            &FaultState {
+                partitions: vec![[0, 1].into()],
+                ..Default::default()
+            },
+        )
+        .await;
+
+        // Broadcasts only reach the sender's group, and nodes outside the stake table
+        broadcast(&networks[0].1, b"broadcast").await;
+        assert_eq!(recv(&networks[0].1).await.unwrap(), b"broadcast");
+        assert_eq!(recv(&networks[1].1).await.unwrap(), b"broadcast");
+        assert_eq!(recv(&networks[3].1).await.unwrap(), b"broadcast");
+        assert_eq!(recv(&networks[2].1).await, None);
+
+        // Direct and DA messages are not sent across the partition
+        direct(&networks[0].1, b"direct", networks[2].0).await;
+        assert_eq!(recv(&networks[2].1).await, None);
+        networks[0]
+            .1
+            .da_broadcast_message(
+                ViewNumber::new(1),
+                b"da".to_vec(),
+                vec![networks[1].0, networks[2].0],
+                BroadcastDelay::None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(recv(&networks[1].1).await.unwrap(), b"da");
+        assert_eq!(recv(&networks[2].1).await, None);
+
+        // The recipient drops messages from senders that are not partitioned yet
+        set_faults(&networks[0].1, FaultState::default()).await;
+        direct(&networks[0].1, b"direct", networks[2].0).await;
+        assert_eq!(recv(&networks[2].1).await, None);
+
+        // Healing the partition restores communication
+        set_all_faults(&networks, &FaultState::default()).await;
+        direct(&networks[0].1, b"healed", networks[2].0).await;
+        assert_eq!(recv(&networks[2].1).await.unwrap(), b"healed");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_delay() {
+        const DELAY_MS: u64 = 500;
+        let delay = Duration::from_millis(DELAY_MS);
+        let networks = networks();
+        set_all_faults(
+            &networks,
+            &FaultState {
+                delays: vec![LinkDelay {
+                    from: vec![0],
+                    to: vec![1],
+                    delay_ms: DELAY_MS,
+                }],
+                ..Default::default()
+            },
+        )
+        .await;
+
+        let start = Instant::now();
+        direct(&networks[0].1, b"delayed", networks[1].0).await;
+        direct(&networks[2].1, b"undelayed", networks[1].0).await;
+
+        // Messages on other links overtake the delayed one
+        assert_eq!(recv(&networks[1].1).await.unwrap(), b"undelayed");
+        assert!(start.elapsed() < delay);
+        let message = timeout(2 * delay, networks[1].1.recv_message())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(message, b"delayed");
+        assert!(start.elapsed() >= delay);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_passthrough() {
+        let mut networks = networks();
+        let (key, network) = networks.swap_remove(1);
+        let passthrough = FaultyNetwork::<TestTypes, _> {
+            inner: network.inner,
+            faults: Arc::new(Faults {
+                schedule: FaultSchedule::default(),
+                ..Arc::into_inner(network.faults).unwrap()
+            }),
+        };
+
+        // Without a schedule messages are not framed, so they reach the inner network as is
+        direct(&passthrough, b"raw", networks[0].0).await;
+        assert_eq!(networks[0].1.inner.recv_message().await.unwrap(), b"raw");
+        networks[0]
+            .1
+            .inner
+            .direct_message(ViewNumber::new(1), b"raw".to_vec(), key)
+            .await
+            .unwrap();
+        assert_eq!(recv(&passthrough).await.unwrap(), b"raw");
+    }
+}
@@ -34,7 +34,6 @@ use hotshot::{
 use hotshot_example_types::{
     block_types::{TestBlockHeader, TestBlockPayload, TestTransaction},
     membership::TestableMembership,
-    node_types::{Libp2pImpl, PushCdnImpl},
     state_types::TestInstanceState,
     storage_types::TestStorage,
 };
@@ -70,6 +69,8 @@ use tracing::{debug, error, info, warn};
 use url::Url;
 use versions::{MIN_SUPPORTED_VERSION, Upgrade};
 
+use crate::faults::FaultyNetwork;
+
 #[derive(Debug, Clone)]
 /// Arguments passed to the orchestrator
 pub struct OrchestratorArgs<TYPES: NodeType> {
@@ -351,10 +352,13 @@ pub trait RunDa<
     Self: Sync,
 {
     /// Initializes networking, returns self
+    ///
+    /// The orchestrator client is used to fetch the faults of the run's fault schedule.
     async fn initialize_networking(
         config: NetworkConfig<TYPES>,
         validator_config: ValidatorConfig<TYPES>,
         libp2p_advertise_address: Option<String>,
+        orchestrator_client: OrchestratorClient,
     ) -> Self;
 
     /// Initializes the genesis state and HotShot instance; does not start HotShot consensus
@@ -432,6 +436,7 @@ pub trait RunDa<
         let mut maximum_latency = 0;
         let mut total_latency = 0;
         let mut num_latency = 0;
+        let mut latencies = Vec::new();
 
         info!("Starting HotShot example!");
         let start = Instant::now();
@@ -477,6 +482,7 @@ pub trait RunDa<
                                         let cur_latency = current_timestamp - restored_timestamp;
                                         total_latency += cur_latency;
                                         num_latency += 1;
+                                        latencies.push(cur_latency);
                                         minimum_latency =
                                             std::cmp::min(minimum_latency, cur_latency);
                                         maximum_latency =
@@ -567,6 +573,7 @@ pub trait RunDa<
                 * (transaction_size_in_bytes + 8)
                 / total_time_elapsed_sec;
             let avg_latency_in_sec = total_latency / num_latency;
+            latencies.sort_unstable();
             println!(
                 "[{node_index}]: throughput: {throughput_bytes_per_sec} bytes/sec, avg_latency: \
                  {avg_latency_in_sec} sec."
@@ -578,6 +585,9 @@ pub trait RunDa<
                 num_latency,
                 minimum_latency_in_sec: minimum_latency,
                 maximum_latency_in_sec: maximum_latency,
+                p50_latency_in_sec: percentile(&latencies, 50),
+                p90_latency_in_sec: percentile(&latencies, 90),
+                p99_latency_in_sec: percentile(&latencies, 99),
                 throughput_bytes_per_sec,
                 total_transactions_committed,
                 transaction_size_in_bytes: transaction_size_in_bytes + 8, // extra 8 bytes for timestamp
@@ -605,8 +615,51 @@ pub trait RunDa<
     fn validator_config(&self) -> ValidatorConfig<TYPES>;
 }
 
+/// The `p`th percentile of the sorted `values`, or 0 if there are none
+fn percentile(values: &[i64], p: usize) -> i64 {
+    if values.is_empty() {
+        return 0;
+    }
+    values[(values.len() * p).div_ceil(100).saturating_sub(1)]
+}
+
 // Push CDN
 
+/// Creates the Push CDN network and waits for the initial connection
+async fn initialize_push_cdn_network<TYPES: NodeType>(
+    config: &NetworkConfig<TYPES>,
+    validator_config: &ValidatorConfig<TYPES>,
+) -> PushCdnNetwork<TYPES::SignatureKey> {
+    // Convert to the Push-CDN-compatible type
+    let keypair = KeyPair {
+        public_key: WrappedSignatureKey(validator_config.public_key.clone()),
+        private_key: validator_config.private_key.clone(),
+    };
+
+    // See if we should be DA, subscribe to the DA topic if so
+    let mut topics = vec![CdnTopic::Global];
+    if validator_config.is_da {
+        topics.push(CdnTopic::Da);
+    }
+
+    // Create the network and await the initial connection
+    let network = PushCdnNetwork::new(
+        config
+            .cdn_marshal_address
+            .clone()
+            .expect("`cdn_marshal_address` needs to be supplied for a push CDN run"),
+        topics,
+        keypair,
+        CdnMetricsValue::default(),
+    )
+    .expect("failed to create network");
+
+    // Wait for the network to be ready
+    network.wait_for_ready().await;
+
+    network
+}
+
 /// Represents a Push CDN-based run
 pub struct PushCdnDaRun<TYPES: NodeType> {
     /// The underlying configuration
@@ -614,7 +667,7 @@ pub struct PushCdnDaRun<TYPES: NodeType> {
     /// The private validator config
     validator_config: ValidatorConfig<TYPES>,
     /// The underlying network
-    network: PushCdnNetwork<TYPES::SignatureKey>,
+    network: FaultyNetwork<TYPES, PushCdnNetwork<TYPES::SignatureKey>>,
 }
 
 #[async_trait]
@@ -627,10 +680,11 @@ impl<
         >,
     NODE: NodeImplementation<
             TYPES,
-            Network = PushCdnNetwork<TYPES::SignatureKey>,
+            Network = FaultyNetwork<TYPES, PushCdnNetwork<TYPES::SignatureKey>>,
             Storage = TestStorage<TYPES>,
         >,
-> RunDa<TYPES, PushCdnNetwork<TYPES::SignatureKey>, NODE> for PushCdnDaRun<TYPES>
+> RunDa<TYPES, FaultyNetwork<TYPES, PushCdnNetwork<TYPES::SignatureKey>>, NODE>
+    for PushCdnDaRun<TYPES>
 where
     <TYPES as NodeType>::ValidatedState: TestableState<TYPES>,
     <TYPES as NodeType>::BlockPayload: TestableBlock<TYPES>,
@@ -642,42 +696,18 @@ where
         config: NetworkConfig<TYPES>,
         validator_config: ValidatorConfig<TYPES>,
         _libp2p_advertise_address: Option<String>,
+        orchestrator_client: OrchestratorClient,
     ) -> PushCdnDaRun<TYPES> {
-        // Convert to the Push-CDN-compatible type
-        let keypair = KeyPair {
-            public_key: WrappedSignatureKey(validator_config.public_key.clone()),
-            private_key: validator_config.private_key.clone(),
-        };
-
-        // See if we should be DA, subscribe to the DA topic if so
-        let mut topics = vec![CdnTopic::Global];
-        if validator_config.is_da {
-            topics.push(CdnTopic::Da);
-        }
-
-        // Create the network and await the initial connection
-        let network = PushCdnNetwork::new(
-            config
-                .cdn_marshal_address
-                .clone()
-                .expect("`cdn_marshal_address` needs to be supplied for a push CDN run"),
-            topics,
-            keypair,
-            CdnMetricsValue::default(),
-        )
-        .expect("failed to create network");
-
-        // Wait for the network to be ready
-        network.wait_for_ready().await;
+        let network = initialize_push_cdn_network(&config, &validator_config).await;
 
         PushCdnDaRun {
+            network: FaultyNetwork::new(network, &config, orchestrator_client),
             config,
             validator_config,
-            network,
         }
     }
 
-    fn network(&self) -> PushCdnNetwork<TYPES::SignatureKey> {
+    fn network(&self) -> FaultyNetwork<TYPES, PushCdnNetwork<TYPES::SignatureKey>> {
         self.network.clone()
     }
 
@@ -692,6 +722,65 @@ where
 
 // Libp2p
 
+/// Creates the Libp2p network and waits for it to be ready
+async fn initialize_libp2p_network<TYPES: NodeType>(
+    config: &NetworkConfig<TYPES>,
+    validator_config: &ValidatorConfig<TYPES>,
+    libp2p_advertise_address: Option<String>,
+) -> Libp2pNetwork<TYPES> {
+    // Extrapolate keys for ease of use
+    let public_key = &validator_config.public_key;
+    let private_key = &validator_config.private_key;
+
+    // In an example, we can calculate the libp2p bind address as a function
+    // of the advertise address.
+    let bind_address = if let Some(libp2p_advertise_address) = libp2p_advertise_address {
+        let libp2p_advertise_address: SocketAddrV4 = libp2p_advertise_address
+            .parse()
+            .expect("failed to parse advertise address");
+
+        // If we have supplied one, use it
+        SocketAddr::new(
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            libp2p_advertise_address.port(),
+        )
+        .to_string()
+    } else {
+        // If not, index a base port with our node index
+        SocketAddr::new(
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            8000 + (u16::try_from(config.node_index).expect("failed to create advertise address")),
+        )
+        .to_string()
+    };
+
+    // Derive the bind address
+    let bind_address =
+        derive_libp2p_multiaddr(&bind_address).expect("failed to derive bind address");
+
+    // Create the Libp2p network
+    let libp2p_network = Libp2pNetwork::from_config(
+        config.clone(),
+        DhtNoPersistence,
+        GossipConfig::default(),
+        RequestResponseConfig::default(),
+        bind_address,
+        Vec::new(),
+        public_key,
+        private_key,
+        Libp2pMetricsValue::default(),
+        None,
+        None,
+    )
+    .await
+    .expect("failed to create libp2p network");
+
+    // Wait for the network to be ready
+    libp2p_network.wait_for_ready().await;
+
+    libp2p_network
+}
+
 /// Represents a libp2p-based run
 pub struct Libp2pDaRun<TYPES: NodeType> {
     /// The underlying network configuration
@@ -699,7 +788,7 @@ pub struct Libp2pDaRun<TYPES: NodeType> {
     /// The private validator config
     validator_config: ValidatorConfig<TYPES>,
     /// The underlying network
-    network: Libp2pNetwork<TYPES>,
+    network: FaultyNetwork<TYPES, Libp2pNetwork<TYPES>>,
 }
 
 #[async_trait]
@@ -710,8 +799,12 @@ impl<
             BlockHeader = TestBlockHeader,
             InstanceState = TestInstanceState,
         >,
-    NODE: NodeImplementation<TYPES, Network = Libp2pNetwork<TYPES>, Storage = TestStorage<TYPES>>,
-> RunDa<TYPES, Libp2pNetwork<TYPES>, NODE> for Libp2pDaRun<TYPES>
+    NODE: NodeImplementation<
+            TYPES,
+            Network = FaultyNetwork<TYPES, Libp2pNetwork<TYPES>>,
+            Storage = TestStorage<TYPES>,
+        >,
+> RunDa<TYPES, FaultyNetwork<TYPES, Libp2pNetwork<TYPES>>, NODE> for Libp2pDaRun<TYPES>
 where
     <TYPES as NodeType>::ValidatedState: TestableState<TYPES>,
     <TYPES as NodeType>::BlockPayload: TestableBlock<TYPES>,
@@ -723,66 +816,19 @@ where
         config: NetworkConfig<TYPES>,
         validator_config: ValidatorConfig<TYPES>,
         libp2p_advertise_address: Option<String>,
+        orchestrator_client: OrchestratorClient,
     ) -> Libp2pDaRun<TYPES> {
-        // Extrapolate keys for ease of use
-        let public_key = &validator_config.public_key;
-        let private_key = &validator_config.private_key;
-
-        // In an example, we can calculate the libp2p bind address as a function
-        // of the advertise address.
-        let bind_address = if let Some(libp2p_advertise_address) = libp2p_advertise_address {
-            let libp2p_advertise_address: SocketAddrV4 = libp2p_advertise_address
-                .parse()
-                .expect("failed to parse advertise address");
-
-            // If we have supplied one, use it
-            SocketAddr::new(
-                IpAddr::V4(Ipv4Addr::UNSPECIFIED),
-                libp2p_advertise_address.port(),
-            )
-            .to_string()
-        } else {
-            // If not, index a base port with our node index
-            SocketAddr::new(
-                IpAddr::V4(Ipv4Addr::UNSPECIFIED),
-                8000 + (u16::try_from(config.node_index)
-                    .expect("failed to create advertise address")),
-            )
-            .to_string()
-        };
-
-        // Derive the bind address
-        let bind_address =
-            derive_libp2p_multiaddr(&bind_address).expect("failed to derive bind address");
-
-        // Create the Libp2p network
-        let libp2p_network = Libp2pNetwork::from_config(
-            config.clone(),
-            DhtNoPersistence,
-            GossipConfig::default(),
-            RequestResponseConfig::default(),
-            bind_address,
-            Vec::new(),
-            public_key,
-            private_key,
-            Libp2pMetricsValue::default(),
-            None,
-            None,
-        )
-        .await
-        .expect("failed to create libp2p network");
-
-        // Wait for the network to be ready
-        libp2p_network.wait_for_ready().await;
+        let network =
+            initialize_libp2p_network(&config, &validator_config, libp2p_advertise_address).await;
 
         Libp2pDaRun {
+            network: FaultyNetwork::new(network, &config, orchestrator_client),
             config,
             validator_config,
-            network: libp2p_network,
         }
     }
 
-    fn network(&self) -> Libp2pNetwork<TYPES> {
+    fn network(&self) -> FaultyNetwork<TYPES, Libp2pNetwork<TYPES>> {
         self.network.clone()
     }
 
@@ -804,7 +850,7 @@ pub struct CombinedDaRun<TYPES: NodeType> {
     /// The private validator config
     validator_config: ValidatorConfig<TYPES>,
     /// The underlying network
-    network: CombinedNetworks<TYPES>,
+    network: FaultyNetwork<TYPES, CombinedNetworks<TYPES>>,
 }
 
 #[async_trait]
@@ -815,8 +861,12 @@ impl<
             BlockHeader = TestBlockHeader,
             InstanceState = TestInstanceState,
         >,
-    NODE: NodeImplementation<TYPES, Network = CombinedNetworks<TYPES>, Storage = TestStorage<TYPES>>,
-> RunDa<TYPES, CombinedNetworks<TYPES>, NODE> for CombinedDaRun<TYPES>
+    NODE: NodeImplementation<
+            TYPES,
+            Network = FaultyNetwork<TYPES, CombinedNetworks<TYPES>>,
+            Storage = TestStorage<TYPES>,
+        >,
+> RunDa<TYPES, FaultyNetwork<TYPES, CombinedNetworks<TYPES>>, NODE> for CombinedDaRun<TYPES>
 where
     <TYPES as NodeType>::ValidatedState: TestableState<TYPES>,
     <TYPES as NodeType>::BlockPayload: TestableBlock<TYPES>,
@@ -828,30 +878,14 @@ where
         config: NetworkConfig<TYPES>,
         validator_config: ValidatorConfig<TYPES>,
         libp2p_advertise_address: Option<String>,
+        orchestrator_client: OrchestratorClient,
     ) -> CombinedDaRun<TYPES> {
         // Initialize our Libp2p network
-        let libp2p_network: Libp2pDaRun<TYPES> = <Libp2pDaRun<TYPES> as RunDa<
-            TYPES,
-            Libp2pNetwork<TYPES>,
-            Libp2pImpl,
-        >>::initialize_networking(
-            config.clone(),
-            validator_config.clone(),
-            libp2p_advertise_address.clone(),
-        )
-        .await;
+        let libp2p_network =
+            initialize_libp2p_network(&config, &validator_config, libp2p_advertise_address).await;
 
         // Initialize our CDN network
-        let cdn_network: PushCdnDaRun<TYPES> = <PushCdnDaRun<TYPES> as RunDa<
-            TYPES,
-            PushCdnNetwork<TYPES::SignatureKey>,
-            PushCdnImpl,
-        >>::initialize_networking(
-            config.clone(),
-            validator_config.clone(),
-            libp2p_advertise_address,
-        )
-        .await;
+        let cdn_network = initialize_push_cdn_network(&config, &validator_config).await;
 
         // Create our combined network config
         let delay_duration = config
@@ -860,18 +894,17 @@ where
             .map(|config| config.delay_duration);
 
         // Create our combined network
-        let network =
-            CombinedNetworks::new(cdn_network.network, libp2p_network.network, delay_duration);
+        let network = CombinedNetworks::new(cdn_network, libp2p_network, delay_duration);
 
         // Return the run configuration
         CombinedDaRun {
+            network: FaultyNetwork::new(network, &config, orchestrator_client),
             config,
             validator_config,
-            network,
         }
     }
 
-    fn network(&self) -> CombinedNetworks<TYPES> {
+    fn network(&self) -> FaultyNetwork<TYPES, CombinedNetworks<TYPES>> {
         self.network.clone()
     }
 
@@ -976,9 +1009,13 @@ pub async fn main_entry_point<
     );
 
     info!("Initializing networking");
-    let run =
-        RUNDA::initialize_networking(run_config.clone(), validator_config, args.advertise_address)
-            .await;
+    let run = RUNDA::initialize_networking(
+        run_config.clone(),
+        validator_config,
+        args.advertise_address,
+        orchestrator_client.clone(),
+    )
+    .await;
 
     let hotshot = run.initialize_state_and_hotshot().await;
 
@@ -7,9 +7,11 @@
 //! Libp2p based/production networking implementation
 //! This module provides a libp2p based networking implementation where each node in the
 //! network forms a tcp or udp connection to a subset of other nodes in the network
+#[cfg(feature = "hotshot-testing")]
+use std::str::FromStr;
 use std::{
     cmp::min,
-    collections::{BTreeSet, HashSet},
+    collections::{BTreeSet, HashMap, HashSet},
     fmt::Debug,
     net::{IpAddr, ToSocketAddrs},
     num::NonZeroUsize,
@@ -19,8 +21,6 @@ use std::{
     },
     time::Duration,
 };
-#[cfg(feature = "hotshot-testing")]
-use std::{collections::HashMap, str::FromStr};
 
 use alloy::primitives::U256;
 use anyhow::{Context, anyhow};
@@ -30,15 +30,20 @@ use bimap::BiMap;
 use futures::future::join_all;
 #[cfg(feature = "hotshot-testing")]
 use hotshot_libp2p_networking::network::behaviours::dht::store::persistent::DhtNoPersistence;
-pub use hotshot_libp2p_networking::network::{GossipConfig, RequestResponseConfig};
+pub use hotshot_libp2p_networking::network::{
+    GossipConfig, PeerScoringConfig, RequestResponseConfig,
+};
 use hotshot_libp2p_networking::{
     network::{
         DEFAULT_REPLICATION_FACTOR,
         NetworkEvent::{self, DirectRequest, DirectResponse, GossipMsg},
         NetworkNodeConfig, NetworkNodeConfigBuilder, NetworkNodeHandle, NetworkNodeReceiver,
-        behaviours::dht::{
-            record::{Namespace, RecordKey, RecordValue},
-            store::persistent::DhtPersistentStorage,
+        behaviours::{
+            dht::{
+                record::{Namespace, RecordKey, RecordValue},
+                store::persistent::DhtPersistentStorage,
+            },
+            peer_scoring::MeshPeerMetrics,
         },
         log_summary::LogEvent,
         spawn_network_node,
@@ -50,12 +55,13 @@ use hotshot_types::{
     BoxSyncFuture, boxed_sync,
     constants::LOOK_AHEAD,
     data::{EpochNumber, ViewNumber},
+    epoch_membership::EpochMembership,
     network::NetworkConfig,
     traits::{
-        metrics::{Counter, Gauge, Metrics, NoMetrics},
+        metrics::{Counter, Gauge, GaugeFamily, Metrics, MetricsFamily, NoMetrics},
         network::{ConnectedNetwork, NetworkError, Topic},
         node_implementation::NodeType,
-        signature_key::{PrivateSignatureKey, SignatureKey},
+        signature_key::{PrivateSignatureKey, SignatureKey, StakeTableEntryType},
     },
 };
 #[cfg(feature = "hotshot-testing")]
@@ -89,6 +95,14 @@ pub struct Libp2pMetricsValue {
     pub num_failed_messages: Box<dyn Counter>,
     /// Whether or not the network is considered ready
     pub is_ready: Box<dyn Gauge>,
+    /// The number of peers in our gossip meshes
+    pub num_mesh_peers: Box<dyn Gauge>,
+    /// The round-trip time of direct messages to each mesh peer, in milliseconds
+    pub mesh_peer_rtt_ms: Arc<dyn GaugeFamily>,
+    /// The number of direct messages delivered to each mesh peer
+    pub mesh_peer_delivered: Arc<dyn GaugeFamily>,
+    /// The number of direct messages to each mesh peer that failed
+    pub mesh_peer_failed: Arc<dyn GaugeFamily>,
 }
 
 impl Libp2pMetricsValue {
@@ -102,6 +116,16 @@ impl Libp2pMetricsValue {
             num_connected_peers: subgroup.create_gauge("num_connected_peers".into(), None),
             num_failed_messages: subgroup.create_counter("num_failed_messages".into(), None),
             is_ready: subgroup.create_gauge("is_ready".into(), None),
+            num_mesh_peers: subgroup.create_gauge("num_mesh_peers".into(), None),
+            mesh_peer_rtt_ms: subgroup
+                .gauge_family("mesh_peer_rtt_ms".into(), vec!["peer".into()])
+                .into(),
+            mesh_peer_delivered: subgroup
+                .gauge_family("mesh_peer_delivered".into(), vec!["peer".into()])
+                .into(),
+            mesh_peer_failed: subgroup
+                .gauge_family("mesh_peer_failed".into(), vec!["peer".into()])
+                .into(),
         }
     }
 }
@@ -113,6 +137,63 @@ impl Default for Libp2pMetricsValue {
     }
 }
 
+/// How often the metrics of the gossip mesh peers are refreshed
+const MESH_METRICS_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The gauges of a single mesh peer
+struct MeshPeerGauges {
+    /// round-trip time in milliseconds
+    rtt_ms: Box<dyn Gauge>,
+    /// direct messages delivered
+    delivered: Box<dyn Gauge>,
+    /// direct messages failed
+    failed: Box<dyn Gauge>,
+}
+
+impl Libp2pMetricsValue {
+    /// Update the per-peer gauges from `mesh`, removing those of peers that left the mesh
+    #[allow(clippy::cast_possible_truncation)]
+    fn update_mesh_peers(
+        &self,
+        gauges: &mut HashMap<PeerId, MeshPeerGauges>,
+        mesh: &[MeshPeerMetrics],
+    ) {
+        self.num_mesh_peers.set(mesh.len());
+
+        let in_mesh: HashSet<_> = mesh.iter().map(|peer| peer.peer_id).collect();
+        gauges.retain(|peer_id, _| {
+            if in_mesh.contains(peer_id) {
+                return true;
+            }
+            let label = peer_id.to_string();
+            self.mesh_peer_rtt_ms.destroy(&[&label]);
+            self.mesh_peer_delivered.destroy(&[&label]);
+            self.mesh_peer_failed.destroy(&[&label]);
+            false
+        });
+
+        for peer in mesh {
+            let peer_gauges = gauges.entry(peer.peer_id).or_insert_with(|| {
+                let label = vec![peer.peer_id.to_string()];
+                MeshPeerGauges {
+                    rtt_ms: self.mesh_peer_rtt_ms.create(label.clone()),
+                    delivered: self.mesh_peer_delivered.create(label.clone()),
+                    failed: self.mesh_peer_failed.create(label),
+                }
+            });
+            if let Some(rtt) = peer.rtt {
+                peer_gauges.rtt_ms.set(rtt.as_millis() as usize);
+            }
+            peer_gauges.delivered.set(peer.delivered as usize);
+            peer_gauges.failed.set(peer.failed as usize);
+            trace!(
+                "Mesh peer {}: score {:?}, app score {:.2}, stake {:.3}, rtt {:?}",
+                peer.peer_id, peer.score, peer.app_score, peer.stake_fraction, peer.rtt
+            );
+        }
+    }
+}
+
 /// convenience alias for the type for bootstrap addresses
 /// concurrency primitives are needed for having tests
 pub type BootstrapAddrs = Arc<RwLock<Vec<(PeerId, Multiaddr)>>>;
@@ -179,6 +260,9 @@ struct Libp2pNetworkInner<T: NodeType> {
     reliability_config: Option<Box<dyn NetworkReliability>>,
     /// Killswitch sender
     kill_switch: Sender<()>,
+    /// The epoch of the stake table we last passed to the network for peer scoring,
+    /// `None` if we have not passed one yet
+    stake_table_epoch: parking_lot::Mutex<Option<Option<EpochNumber>>>,
 }
 
 /// Networking implementation that uses libp2p
@@ -578,6 +662,7 @@ impl<T: NodeType> Libp2pNetwork<T> {
                 #[cfg(feature = "hotshot-testing")]
                 reliability_config,
                 kill_switch: kill_tx,
+                stake_table_epoch: parking_lot::Mutex::new(None),
             }),
         };
 
@@ -586,6 +671,7 @@ impl<T: NodeType> Libp2pNetwork<T> {
 
         result.handle_event_generator(sender, rx);
         result.spawn_node_lookup(node_lookup_recv);
+        result.spawn_mesh_metrics();
         result.spawn_connect(id, lookup_record_value);
 
         Ok(result)
@@ -623,6 +709,49 @@ impl<T: NodeType> Libp2pNetwork<T> {
         });
     }
 
+    /// Spawns task for periodically exporting the metrics of our gossip mesh peers
+    fn spawn_mesh_metrics(&self) {
+        let handle = Arc::clone(&self.inner.handle);
+        let metrics = self.inner.metrics.clone();
+
+        spawn(async move {
+            let mut gauges = HashMap::new();
+            // Stops once the network node has shut down
+            while let Ok(mesh) = handle.mesh_peer_metrics().await {
+                metrics.update_mesh_peers(&mut gauges, &mesh);
+                sleep(MESH_METRICS_INTERVAL).await;
+            }
+        });
+    }
+
+    /// Pass the stake table of `epoch` to the network so gossip peers are scored by stake,
+    /// unless we already did.
+    fn update_stake_table<TYPES>(
+        &self,
+        epoch: Option<EpochNumber>,
+        membership: &EpochMembership<TYPES>,
+    ) where
+        TYPES: NodeType<SignatureKey = T::SignatureKey>,
+    {
+        {
+            let mut stake_table_epoch = self.inner.stake_table_epoch.lock();
+            if *stake_table_epoch == Some(epoch) {
+                return;
+            }
+            *stake_table_epoch = Some(epoch);
+        }
+
+        let stakes = membership.stake_table().map(|peer| {
+            (
+                peer.stake_table_entry.public_key(),
+                peer.stake_table_entry.stake(),
+            )
+        });
+        if let Err(err) = self.inner.handle.set_stake_table(stakes) {
+            warn!("Failed to update the stake table of the network: {err}");
+        }
+    }
+
     /// Initiates connection to the outside world
     fn spawn_connect(&mut self, id: usize, lookup_record_value: RecordValue<T::SignatureKey>) {
         let pk = self.inner.pk.clone();
@@ -1022,6 +1151,8 @@ impl<T: NodeType> ConnectedNetwork<T::SignatureKey> for Libp2pNetwork<T> {
                 return tracing::warn!(e.message);
             },
         };
+        self.update_stake_table(epoch, &membership);
+
         let future_leader = match membership.leader(future_view) {
             Ok(l) => l,
             Err(e) => {
@@ -4,7 +4,10 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
 
 use libp2p::request_response::{Event, Message, OutboundRequestId, ResponseChannel};
 use libp2p_identity::PeerId;
@@ -25,6 +28,10 @@ pub struct DMRequest {
     pub backoff: ExponentialBackoff,
     /// the number of remaining retries before giving up
     pub(crate) retry_count: u8,
+    /// when this attempt was sent over an established connection, to measure the round-trip
+    /// time. `None` if the peer had to be dialed first. Retries are sent as new requests, so
+    /// the backoff before a retry is never part of the measurement
+    pub(crate) sent_at: Option<Instant>,
 }
 
 /// Wrapper metadata around libp2p's request response
@@ -33,6 +40,17 @@ pub struct DMRequest {
 pub struct DMBehaviour {
     /// In progress queries
     in_progress_rr: HashMap<OutboundRequestId, DMRequest>,
+    /// Outcomes of our requests since the last call to `take_outcomes`
+    outcomes: Vec<DMOutcome>,
+}
+
+/// The outcome of a single attempt of one of our direct requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DMOutcome {
+    /// The request was answered, after the given round-trip time if it was measured
+    Delivered(PeerId, Option<Duration>),
+    /// The request failed
+    Failed(PeerId),
 }
 
 /// Lilst of direct message output events
@@ -70,6 +88,7 @@ impl DMBehaviour {
             } => {
                 LogEvent::DirectMessageOutboundFailure.record();
                 debug!("Outbound message failure to {:?}: {:?}", peer, error);
+                self.outcomes.push(DMOutcome::Failed(peer));
                 if let Some(mut req) = self.in_progress_rr.remove(&request_id) {
                     if req.retry_count == 0 {
                         return None;
@@ -106,6 +125,8 @@ impl DMBehaviour {
                     // success, finished.
                     if let Some(req) = self.in_progress_rr.remove(&request_id) {
                         debug!("Received direct response {:?}", msg);
+                        let rtt = req.sent_at.map(|sent_at| sent_at.elapsed());
+                        self.outcomes.push(DMOutcome::Delivered(req.peer_id, rtt));
                         Some(NetworkEvent::DirectResponse(msg, req.peer_id))
                     } else {
                         debug!("Received response for unknown request id {:?}", request_id);
@@ -134,4 +155,9 @@ impl DMBehaviour {
 
         self.in_progress_rr.insert(request_id, req);
     }
+
+    /// The outcomes of our requests since the last call
+    pub(crate) fn take_outcomes(&mut self) -> Vec<DMOutcome> {
+        std::mem::take(&mut self.outcomes)
+    }
 }
@@ -12,3 +12,6 @@ pub mod exponential_backoff;
 
 /// Wrapper around Kademlia
 pub mod dht;
+
+/// Stake- and link-quality-based scoring of gossip peers
+pub mod peer_scoring;
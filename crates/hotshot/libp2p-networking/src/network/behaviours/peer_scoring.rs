@@ -0,0 +1,434 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use alloy::primitives::U256;
+use libp2p::{
+    gossipsub::{PeerScoreParams, PeerScoreThresholds},
+    kad::Event as KademliaEvent,
+};
+use libp2p_identity::PeerId;
+
+use crate::network::PeerScoringConfig;
+
+/// Weight of a new round-trip time sample in the moving average
+const RTT_SMOOTHING: f64 = 0.2;
+
+/// Weight of a new delivery outcome in the moving delivery ratio
+const DELIVERY_SMOOTHING: f64 = 0.1;
+
+/// Resolution of a peer's stake relative to the largest stake
+const STAKE_RESOLUTION: u64 = 1_000_000;
+
+/// What we know about a connected peer
+#[derive(Clone, Debug)]
+pub struct PeerStats {
+    /// the peer's serialized staking key, if it is known
+    key: Option<Vec<u8>>,
+    /// moving average of the round-trip time of direct messages
+    pub rtt: Option<Duration>,
+    /// moving fraction of direct messages that were delivered
+    pub delivery_ratio: f64,
+    /// number of direct messages delivered
+    pub delivered: u64,
+    /// number of direct messages that failed
+    pub failed: u64,
+    /// whether the peer is in our Kademlia routing table
+    pub in_routing_table: bool,
+}
+
+impl Default for PeerStats {
+    fn default() -> Self {
+        Self {
+            key: None,
+            rtt: None,
+            delivery_ratio: 1.0,
+            delivered: 0,
+            failed: 0,
+            in_routing_table: false,
+        }
+    }
+}
+
+/// Link quality and score of a gossip mesh peer
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeshPeerMetrics {
+    /// the mesh peer
+    pub peer_id: PeerId,
+    /// the overall score computed by `GossipSub`, `None` if peer scoring is disabled
+    pub score: Option<f64>,
+    /// the application-specific part of the score
+    pub app_score: f64,
+    /// the peer's stake relative to the largest stake
+    pub stake_fraction: f64,
+    /// moving average of the round-trip time of direct messages
+    pub rtt: Option<Duration>,
+    /// number of direct messages delivered
+    pub delivered: u64,
+    /// number of direct messages that failed
+    pub failed: u64,
+    /// whether the peer is in our Kademlia routing table
+    pub in_routing_table: bool,
+}
+
+/// Tracks the stake and link quality of connected peers and turns them into
+/// the application-specific `GossipSub` score.
+///
+/// Scores are recomputed lazily: every update marks the peer, and
+/// [`PeerScoring::take_updated_scores`] returns the new scores of the marked peers.
+#[derive(Debug)]
+pub struct PeerScoring {
+    /// score weights
+    config: PeerScoringConfig,
+    /// stake of each staking key, by serialized key
+    stakes: HashMap<Vec<u8>, U256>,
+    /// the largest stake in `stakes`
+    max_stake: U256,
+    /// stats of connected peers
+    peers: HashMap<PeerId, PeerStats>,
+    /// peers whose score changed since the last call to `take_updated_scores`
+    updated: HashSet<PeerId>,
+}
+
+impl PeerScoring {
+    /// Create a new `PeerScoring` with the given weights
+    #[must_use]
+    pub fn new(config: PeerScoringConfig) -> Self {
+        Self {
+            config,
+            stakes: HashMap::new(),
+            max_stake: U256::ZERO,
+            peers: HashMap::new(),
+            updated: HashSet::new(),
+        }
+    }
+
+    /// The `GossipSub` score parameters, only weighing the application-specific score
+    #[must_use]
+    pub fn score_params(&self) -> PeerScoreParams {
+        PeerScoreParams {
+            app_specific_weight: 1.0,
+            // Peers are authenticated against the stake table, so many peers behind the
+            // same IP are not a sybil attack
+            ip_colocation_factor_weight: 0.0,
+            ..Default::default()
+        }
+    }
+
+    /// The `GossipSub` score thresholds
+    #[must_use]
+    pub fn score_thresholds(&self) -> PeerScoreThresholds {
+        PeerScoreThresholds {
+            opportunistic_graft_threshold: self.config.opportunistic_graft_threshold,
+            ..Default::default()
+        }
+    }
+
+    /// Start tracking a newly connected peer
+    pub fn add_peer(&mut self, peer_id: PeerId) {
+        self.peers.entry(peer_id).or_default();
+        self.updated.insert(peer_id);
+    }
+
+    /// Stop tracking a disconnected peer
+    pub fn remove_peer(&mut self, peer_id: &PeerId) {
+        self.peers.remove(peer_id);
+        self.updated.remove(peer_id);
+    }
+
+    /// The peers we are tracking
+    pub fn peers(&self) -> impl Iterator<Item = &PeerId> {
+        self.peers.keys()
+    }
+
+    /// Whether we are connected to `peer_id`
+    #[must_use]
+    pub fn is_connected(&self, peer_id: &PeerId) -> bool {
+        self.peers.contains_key(peer_id)
+    }
+
+    /// Whether we know the staking key of `peer_id`
+    #[must_use]
+    pub fn has_key(&self, peer_id: &PeerId) -> bool {
+        self.peers
+            .get(peer_id)
+            .is_some_and(|stats| stats.key.is_some())
+    }
+
+    /// Set the serialized staking key of a tracked peer
+    pub fn set_peer_key(&mut self, peer_id: PeerId, key: Vec<u8>) {
+        if let Some(stats) = self.peers.get_mut(&peer_id) {
+            stats.key = Some(key);
+            self.updated.insert(peer_id);
+        }
+    }
+
+    /// Replace the stake table, keyed by serialized staking key
+    pub fn set_stake_table(&mut self, stakes: HashMap<Vec<u8>, U256>) {
+        self.max_stake = stakes.values().max().copied().unwrap_or_default();
+        self.stakes = stakes;
+        self.updated.extend(self.peers.keys().copied());
+    }
+
+    /// Record a direct message delivered to `peer_id`, after `rtt` if it was measured
+    pub fn record_delivery(&mut self, peer_id: PeerId, rtt: Option<Duration>) {
+        let Some(stats) = self.peers.get_mut(&peer_id) else {
+            return;
+        };
+        stats.delivered += 1;
+        stats.delivery_ratio += DELIVERY_SMOOTHING * (1.0 - stats.delivery_ratio);
+        if let Some(rtt) = rtt {
+            stats.rtt = Some(match stats.rtt {
+                Some(average) => average.mul_f64(1.0 - RTT_SMOOTHING) + rtt.mul_f64(RTT_SMOOTHING),
+                None => rtt,
+            });
+        }
+        self.updated.insert(peer_id);
+    }
+
+    /// Record a direct message to `peer_id` that failed
+    pub fn record_failure(&mut self, peer_id: PeerId) {
+        let Some(stats) = self.peers.get_mut(&peer_id) else {
+            return;
+        };
+        stats.failed += 1;
+        stats.delivery_ratio -= DELIVERY_SMOOTHING * stats.delivery_ratio;
+        self.updated.insert(peer_id);
+    }
+
+    /// Track which peers enter or leave our Kademlia routing table
+    pub fn handle_dht_event(&mut self, event: &KademliaEvent) {
+        match event {
+            KademliaEvent::RoutingUpdated { peer, old_peer, .. } => {
+                self.set_in_routing_table(*peer, true);
+                if let Some(old_peer) = old_peer {
+                    self.set_in_routing_table(*old_peer, false);
+                }
+            },
+            KademliaEvent::UnroutablePeer { peer } => self.set_in_routing_table(*peer, false),
+            _ => {},
+        }
+    }
+
+    /// Set whether a tracked peer is in our Kademlia routing table
+    fn set_in_routing_table(&mut self, peer_id: PeerId, in_routing_table: bool) {
+        if let Some(stats) = self.peers.get_mut(&peer_id)
+            && stats.in_routing_table != in_routing_table
+        {
+            stats.in_routing_table = in_routing_table;
+            self.updated.insert(peer_id);
+        }
+    }
+
+    /// The stake of `stats`'s peer relative to the largest stake, between 0 and 1
+    fn stake_fraction(&self, stats: &PeerStats) -> f64 {
+        let Some(stake) = stats.key.as_ref().and_then(|key| self.stakes.get(key)) else {
+            return 0.0;
+        };
+        if self.max_stake.is_zero() {
+            return 0.0;
+        }
+        let fraction = stake.saturating_mul(U256::from(STAKE_RESOLUTION)) / self.max_stake;
+        fraction.saturating_to::<u64>() as f64 / STAKE_RESOLUTION as f64
+    }
+
+    /// The application-specific score of `stats`'s peer
+    fn score_of(&self, stats: &PeerStats) -> f64 {
+        let stake = self.config.stake_weight * self.stake_fraction(stats);
+
+        // Peers we have not measured yet get no RTT score, so measured fast peers are preferred
+        let rtt = stats.rtt.map_or(0.0, |rtt| {
+            let target = self.config.target_rtt.as_secs_f64();
+            self.config.rtt_weight * (target / rtt.as_secs_f64().max(target))
+        });
+
+        let dht = if stats.in_routing_table {
+            self.config.dht_weight
+        } else {
+            0.0
+        };
+
+        let failures = self.config.failure_weight * (1.0 - stats.delivery_ratio);
+
+        stake + rtt + dht - failures
+    }
+
+    /// The application-specific score of `peer_id`, `None` if it is not tracked
+    #[must_use]
+    pub fn score(&self, peer_id: &PeerId) -> Option<f64> {
+        self.peers.get(peer_id).map(|stats| self.score_of(stats))
+    }
+
+    /// The scores of all peers that changed since the last call
+    pub fn take_updated_scores(&mut self) -> Vec<(PeerId, f64)> {
+        let updated = std::mem::take(&mut self.updated);
+        updated
+            .into_iter()
+            .filter_map(|peer_id| Some((peer_id, self.score(&peer_id)?)))
+            .collect()
+    }
+
+    /// The metrics of a mesh peer, given its overall `GossipSub` score
+    #[must_use]
+    pub fn mesh_peer_metrics(&self, peer_id: PeerId, score: Option<f64>) -> MeshPeerMetrics {
+        let stats = self.peers.get(&peer_id).cloned().unwrap_or_default();
+        MeshPeerMetrics {
+            peer_id,
+            score,
+            app_score: self.score_of(&stats),
+            stake_fraction: self.stake_fraction(&stats),
+            rtt: stats.rtt,
+            delivered: stats.delivered,
+            failed: stats.failed,
+            in_routing_table: stats.in_routing_table,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Record `samples` deliveries to `peer_id` with the given simulated latency
+    fn simulate_latency(scoring: &mut PeerScoring, peer_id: PeerId, rtt_ms: u64, samples: usize) {
+        for _ in 0..samples {
+            scoring.record_delivery(peer_id, Some(Duration::from_millis(rtt_ms)));
+        }
+    }
+
+    fn scoring_with_peers(n: usize) -> (PeerScoring, Vec<PeerId>) {
+        let mut scoring = PeerScoring::new(PeerScoringConfig::default());
+        let peers: Vec<_> = (0..n).map(|_| PeerId::random()).collect();
+        for (i, peer) in peers.iter().enumerate() {
+            scoring.add_peer(*peer);
+            scoring.set_peer_key(*peer, vec![i as u8]);
+        }
+        (scoring, peers)
+    }
+
+    #[test]
+    fn test_faster_peers_score_higher() {
+        let (mut scoring, peers) = scoring_with_peers(3);
+        simulate_latency(&mut scoring, peers[0], 20, 10);
+        simulate_latency(&mut scoring, peers[1], 150, 10);
+        simulate_latency(&mut scoring, peers[2], 600, 10);
+
+        let scores: Vec<_> = peers.iter().map(|p| scoring.score(p).unwrap()).collect();
+        assert!(scores[0] > scores[1] && scores[1] > scores[2], "{scores:?}");
+
+        // Everything at or below the target RTT scores the full RTT weight
+        let config = PeerScoringConfig::default();
+        assert!((scores[0] - config.rtt_weight).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rtt_moving_average() {
+        let (mut scoring, peers) = scoring_with_peers(1);
+        simulate_latency(&mut scoring, peers[0], 500, 1);
+        assert_eq!(
+            scoring.peers[&peers[0]].rtt,
+            Some(Duration::from_millis(500))
+        );
+
+        // A single fast sample does not erase a history of slow ones
+        simulate_latency(&mut scoring, peers[0], 10, 1);
+        let rtt = scoring.peers[&peers[0]].rtt.unwrap();
+        assert!(rtt > Duration::from_millis(300), "{rtt:?}");
+
+        // But a sustained improvement does
+        simulate_latency(&mut scoring, peers[0], 10, 50);
+        let rtt = scoring.peers[&peers[0]].rtt.unwrap();
+        assert!(rtt < Duration::from_millis(20), "{rtt:?}");
+
+        // Deliveries that were not timed only count towards the delivery ratio
+        scoring.record_delivery(peers[0], None);
+        assert_eq!(scoring.peers[&peers[0]].rtt, Some(rtt));
+        assert_eq!(scoring.peers[&peers[0]].delivered, 53);
+    }
+
+    #[test]
+    fn test_stake_weighting() {
+        let (mut scoring, peers) = scoring_with_peers(3);
+        scoring.set_stake_table(HashMap::from([
+            (vec![0], U256::from(100)),
+            (vec![1], U256::from(50)),
+        ]));
+
+        let config = PeerScoringConfig::default();
+        let scores: Vec<_> = peers.iter().map(|p| scoring.score(p).unwrap()).collect();
+        assert!((scores[0] - config.stake_weight).abs() < 1e-9);
+        assert!((scores[1] - config.stake_weight / 2.0).abs() < 1e-9);
+        // Not in the stake table
+        assert_eq!(scores[2], 0.0);
+
+        // With equal latencies, stake decides
+        for peer in &peers {
+            simulate_latency(&mut scoring, *peer, 80, 5);
+        }
+        let scores: Vec<_> = peers.iter().map(|p| scoring.score(p).unwrap()).collect();
+        assert!(scores[0] > scores[1] && scores[1] > scores[2], "{scores:?}");
+    }
+
+    #[test]
+    fn test_failures_and_dht() {
+        let (mut scoring, peers) = scoring_with_peers(2);
+        simulate_latency(&mut scoring, peers[0], 20, 5);
+        simulate_latency(&mut scoring, peers[1], 20, 5);
+        for _ in 0..10 {
+            scoring.record_failure(peers[1]);
+        }
+        assert!(scoring.score(&peers[0]) > scoring.score(&peers[1]));
+        assert_eq!(scoring.peers[&peers[1]].failed, 10);
+
+        // A peer that never answers scores negatively and is pruned from the mesh,
+        // but stays above the default gossip threshold
+        let silent = PeerId::random();
+        scoring.add_peer(silent);
+        for _ in 0..100 {
+            scoring.record_failure(silent);
+        }
+        let score = scoring.score(&silent).unwrap();
+        assert!(score < 0.0);
+        assert!(score > PeerScoreThresholds::default().gossip_threshold);
+
+        let before = scoring.score(&peers[0]).unwrap();
+        scoring.handle_dht_event(&KademliaEvent::UnroutablePeer { peer: peers[0] });
+        assert_eq!(scoring.score(&peers[0]).unwrap(), before);
+        scoring.set_in_routing_table(peers[0], true);
+        let config = PeerScoringConfig::default();
+        assert!((scoring.score(&peers[0]).unwrap() - before - config.dht_weight).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_updated_scores() {
+        let (mut scoring, peers) = scoring_with_peers(2);
+        assert_eq!(scoring.take_updated_scores().len(), 2);
+        assert!(scoring.take_updated_scores().is_empty());
+
+        simulate_latency(&mut scoring, peers[1], 20, 1);
+        let updated = scoring.take_updated_scores();
+        assert_eq!(updated.len(), 1);
+        assert_eq!(updated[0].0, peers[1]);
+
+        // Untracked peers are ignored
+        scoring.record_failure(PeerId::random());
+        assert!(scoring.take_updated_scores().is_empty());
+
+        // A new stake table rescores everyone
+        scoring.set_stake_table(HashMap::from([(vec![0], U256::from(1))]));
+        assert_eq!(scoring.take_updated_scores().len(), 2);
+
+        scoring.remove_peer(&peers[0]);
+        assert_eq!(scoring.score(&peers[0]), None);
+        let metrics = scoring.mesh_peer_metrics(peers[1], Some(1.0));
+        assert_eq!(metrics.delivered, 1);
+        assert_eq!(metrics.stake_fraction, 0.0);
+    }
+}
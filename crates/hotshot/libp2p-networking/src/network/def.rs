@@ -111,6 +111,24 @@ impl<K: SignatureKey + 'static, D: DhtPersistentStorage> NetworkDef<K, D> {
     }
 }
 
+/// Peer scoring functions
+impl<K: SignatureKey + 'static, D: DhtPersistentStorage> NetworkDef<K, D> {
+    /// Set the application-specific gossip score of a peer
+    pub fn set_peer_score(&mut self, peer_id: &PeerId, score: f64) {
+        // Returns false if peer scoring is disabled, in which case the score is unused anyway
+        let _ = self.gossipsub.set_application_score(peer_id, score);
+    }
+
+    /// The peers in any of our gossip meshes, with their overall score if scoring is enabled
+    #[must_use]
+    pub fn mesh_peers(&self) -> Vec<(PeerId, Option<f64>)> {
+        self.gossipsub
+            .all_mesh_peers()
+            .map(|peer_id| (*peer_id, self.gossipsub.peer_score(peer_id)))
+            .collect()
+    }
+}
+
 /// Request/response functions
 impl<K: SignatureKey + 'static, D: DhtPersistentStorage> NetworkDef<K, D> {
     /// Add a direct request for a given peer
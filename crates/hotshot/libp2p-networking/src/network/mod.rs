@@ -20,6 +20,8 @@ pub mod cbor;
 
 use std::{collections::HashSet, fmt::Debug, sync::Arc};
 
+use alloy::primitives::U256;
+use behaviours::peer_scoring::MeshPeerMetrics;
 use bimap::BiMap;
 use futures::channel::oneshot::Sender;
 use hotshot_types::traits::{network::NetworkError, node_implementation::NodeType};
@@ -44,7 +46,8 @@ pub use self::{
     node::{
         DEFAULT_REPLICATION_FACTOR, GossipConfig, NetworkNode, NetworkNodeConfig,
         NetworkNodeConfigBuilder, NetworkNodeConfigBuilderError, NetworkNodeHandle,
-        NetworkNodeReceiver, RequestResponseConfig, SwarmTaskHandle, spawn_network_node,
+        NetworkNodeReceiver, PeerScoringConfig, RequestResponseConfig, SwarmTaskHandle,
+        spawn_network_node,
     },
 };
 
@@ -108,6 +111,10 @@ pub enum ClientRequest {
     LookupPeer(PeerId, Sender<()>),
     /// Return the set of peer IDs currently in the Kademlia routing table
     GetKadRoutingPeers(Sender<HashSet<PeerId>>),
+    /// Set the stake of each serialized staking key, used to score gossip peers
+    SetStakeTable(Vec<(Vec<u8>, U256)>),
+    /// Request the link quality and score of our gossip mesh peers
+    GetMeshPeerMetrics(Sender<Vec<MeshPeerMetrics>>),
 }
 
 /// events generated by the swarm that we wish
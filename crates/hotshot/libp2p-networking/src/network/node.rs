@@ -24,7 +24,8 @@ use alloy::primitives::U256;
 use bimap::BiMap;
 use futures::{SinkExt, StreamExt, channel::mpsc};
 use hotshot_types::{
-    constants::KAD_DEFAULT_REPUB_INTERVAL_SEC, traits::node_implementation::NodeType,
+    constants::KAD_DEFAULT_REPUB_INTERVAL_SEC,
+    traits::{node_implementation::NodeType, signature_key::SignatureKey},
 };
 use libp2p::{
     Multiaddr, StreamProtocol, Swarm, SwarmBuilder,
@@ -58,7 +59,7 @@ use tracing::{Instrument, debug, error, info, info_span, instrument, warn};
 pub use self::{
     config::{
         DEFAULT_REPLICATION_FACTOR, GossipConfig, NetworkNodeConfig, NetworkNodeConfigBuilder,
-        NetworkNodeConfigBuilderError, RequestResponseConfig,
+        NetworkNodeConfigBuilderError, PeerScoringConfig, RequestResponseConfig,
     },
     handle::{NetworkNodeHandle, NetworkNodeReceiver, spawn_network_node},
 };
@@ -77,8 +78,9 @@ use super::{
 use crate::network::{
     behaviours::{
         dht::{DHTBehaviour, DHTProgress, KadPutQuery},
-        direct_message::{DMBehaviour, DMRequest},
+        direct_message::{DMBehaviour, DMOutcome, DMRequest},
         exponential_backoff::ExponentialBackoff,
+        peer_scoring::PeerScoring,
     },
     log_summary::LogEvent,
 };
@@ -199,6 +201,8 @@ pub struct NetworkNode<T: NodeType, D: DhtPersistentStorage> {
     /// `NewExternalAddrOfPeer`, whose event carries no protocols to check directly.
     same_network_peers: HashSet<PeerId>,
     dht_put_quorum: Option<NonZeroUsize>,
+    /// Stake and link quality of connected peers, fed into the gossip peer score
+    peer_scoring: PeerScoring,
 }
 
 impl<T: NodeType, D: DhtPersistentStorage> NetworkNode<T, D> {
@@ -299,6 +303,15 @@ impl<T: NodeType, D: DhtPersistentStorage> NetworkNode<T, D> {
 
         let expected_kad_protocol = kad_protocol(config.network_discriminator)?;
 
+        // Peer stats are tracked for the mesh metrics even if they do not affect the mesh
+        let peer_scoring = PeerScoring::new(
+            config
+                .gossip_config
+                .peer_scoring
+                .clone()
+                .unwrap_or_default(),
+        );
+
         // Generate the swarm
         let mut swarm: Swarm<NetworkDef<T::SignatureKey, D>> = {
             // Use the `Blake3` hash of the message's contents as the ID
@@ -343,7 +356,7 @@ impl<T: NodeType, D: DhtPersistentStorage> NetworkNode<T, D> {
                 })?;
 
             // - Build a gossipsub network behavior
-            let gossipsub: Gossipsub = Gossipsub::new(
+            let mut gossipsub: Gossipsub = Gossipsub::new(
                 MessageAuthenticity::Signed(keypair.clone()),
                 gossipsub_config,
             )
@@ -351,6 +364,17 @@ impl<T: NodeType, D: DhtPersistentStorage> NetworkNode<T, D> {
                 NetworkError::ConfigError(format!("error building gossipsub behaviour: {err:?}"))
             })?;
 
+            // - Select mesh peers by stake and link quality if enabled
+            if config.gossip_config.peer_scoring.is_some() {
+                gossipsub
+                    .with_peer_score(peer_scoring.score_params(), peer_scoring.score_thresholds())
+                    .map_err(|err| {
+                        NetworkError::ConfigError(format!(
+                            "error enabling gossipsub peer scoring: {err}"
+                        ))
+                    })?;
+            }
+
             //   Build a identify network behavior needed for own
             //   node connection information
             //   E.g. this will answer the question: how are other nodes
@@ -455,9 +479,43 @@ impl<T: NodeType, D: DhtPersistentStorage> NetworkNode<T, D> {
             expected_kad_protocol,
             same_network_peers: HashSet::new(),
             dht_put_quorum: config.dht_put_quorum,
+            peer_scoring,
         })
     }
 
+    /// Look up the staking key of `peer_id` if we do not know it yet, so its stake counts
+    /// towards its score.
+    fn resolve_peer_key(&mut self, peer_id: PeerId) {
+        if self.peer_scoring.has_key(&peer_id) {
+            return;
+        }
+        let key = self
+            .consensus_key_to_pid_map
+            .lock()
+            .get_by_right(&peer_id)
+            .map(|key| key.to_bytes());
+        if let Some(key) = key {
+            self.peer_scoring.set_peer_key(peer_id, key);
+        }
+    }
+
+    /// Record the outcomes of our direct requests and pass the scores that changed to gossipsub
+    fn apply_peer_scores(&mut self) {
+        for outcome in self.direct_message_state.take_outcomes() {
+            match outcome {
+                DMOutcome::Delivered(peer_id, rtt) => {
+                    self.peer_scoring.record_delivery(peer_id, rtt)
+                },
+                DMOutcome::Failed(peer_id) => self.peer_scoring.record_failure(peer_id),
+            }
+        }
+
+        let behaviour = self.swarm.behaviour_mut();
+        for (peer_id, score) in self.peer_scoring.take_updated_scores() {
+            behaviour.set_peer_score(&peer_id, score);
+        }
+    }
+
     /// Identify is the first point a peer's network is detectable; drop other networks.
     fn on_identify_received(&mut self, peer_id: PeerId, info: IdentifyInfo) {
         if should_keep_peer(&self.expected_kad_protocol, &info.protocols) {
@@ -594,6 +652,29 @@ impl<T: NodeType, D: DhtPersistentStorage> NetworkNode<T, D> {
                             &mut self.swarm.behaviour_mut().dht,
                         );
                     },
+                    ClientRequest::SetStakeTable(stakes) => {
+                        self.peer_scoring
+                            .set_stake_table(stakes.into_iter().collect());
+                        let peers: Vec<_> = self.peer_scoring.peers().copied().collect();
+                        for peer_id in peers {
+                            self.resolve_peer_key(peer_id);
+                        }
+                        self.apply_peer_scores();
+                    },
+                    ClientRequest::GetMeshPeerMetrics(s) => {
+                        let metrics = self
+                            .swarm
+                            .behaviour()
+                            .mesh_peers()
+                            .into_iter()
+                            .map(|(peer_id, score)| {
+                                self.peer_scoring.mesh_peer_metrics(peer_id, score)
+                            })
+                            .collect();
+                        if s.send(metrics).is_err() {
+                            error!("error sending mesh peer metrics to client");
+                        }
+                    },
                     ClientRequest::IgnorePeers(_peers) => {
                         // NOTE used by test with conductor only
                     },
@@ -629,12 +710,16 @@ impl<T: NodeType, D: DhtPersistentStorage> NetworkNode<T, D> {
                         retry_count,
                     } => {
                         debug!("Sending direct request to {pid:?}");
+                        // Only time requests over an established connection, so that the
+                        // round-trip time does not include dialing the peer
+                        let sent_at = self.peer_scoring.is_connected(&pid).then(Instant::now);
                         let id = behaviour.add_direct_request(pid, contents.clone());
                         let req = DMRequest {
                             peer_id: pid,
                             data: contents,
                             backoff: ExponentialBackoff::default(),
                             retry_count,
+                            sent_at,
                         };
                         self.direct_message_state.add_direct_request(req, id);
                     },
@@ -695,6 +780,9 @@ impl<T: NodeType, D: DhtPersistentStorage> NetworkNode<T, D> {
                     self.saw_inbound_connection = true;
                 }
 
+                self.peer_scoring.add_peer(peer_id);
+                self.resolve_peer_key(peer_id);
+
                 // Send the number of connected peers to the client
                 send_to_client
                     .send(NetworkEvent::ConnectedPeersUpdate(self.num_connected()))
@@ -723,6 +811,7 @@ impl<T: NodeType, D: DhtPersistentStorage> NetworkNode<T, D> {
                         .lock()
                         .remove_by_right(&peer_id);
                     self.same_network_peers.remove(&peer_id);
+                    self.peer_scoring.remove_peer(&peer_id);
                 }
 
                 // Send the number of connected peers to the client
@@ -757,6 +846,9 @@ impl<T: NodeType, D: DhtPersistentStorage> NetworkNode<T, D> {
                 send_back_addr: _,
             } => {},
             SwarmEvent::Behaviour(b) => {
+                if let NetworkEventInternal::DHTEvent(e) = &b {
+                    self.peer_scoring.handle_dht_event(e);
+                }
                 let maybe_event = match b {
                     NetworkEventInternal::DHTEvent(e) => self
                         .dht_handler
@@ -857,6 +949,7 @@ impl<T: NodeType, D: DhtPersistentStorage> NetworkNode<T, D> {
                 debug!("Unhandled swarm event {event:?}");
             },
         }
+        self.apply_peer_scores();
         Ok(())
     }
 
@@ -967,34 +1060,38 @@ mod tests {
         resolve_put_quorum, should_keep_peer,
     };
     use crate::network::{
-        NetworkNodeConfigBuilder, behaviours::dht::store::persistent::DhtNoPersistence,
-        node::handle::spawn_network_node,
+        GossipConfig, NetworkEvent, NetworkNodeConfig, NetworkNodeConfigBuilder, PeerScoringConfig,
+        behaviours::dht::store::persistent::DhtNoPersistence,
+        node::handle::{NetworkNodeHandle, NetworkNodeReceiver, spawn_network_node},
     };
 
+    /// Spawn a node with the given config, returning its event receiver and handle.
+    async fn spawn_node_with_config(
+        config: NetworkNodeConfig,
+        id: usize,
+    ) -> (NetworkNodeReceiver, NetworkNodeHandle<TestTypes>) {
+        use bimap::BiMap;
+
+        let key_map = Arc::new(Mutex::new(BiMap::default()));
+        spawn_network_node::<TestTypes, DhtNoPersistence>(config, DhtNoPersistence, key_map, id)
+            .await
+            .expect("spawn node")
+    }
+
     /// Spawn a node with the given discriminator and initial peer set. Returns the handle and
     /// a background task draining the event receiver so the swarm loop never stalls on backpressure.
     async fn spawn_node(
         discriminator: Option<U256>,
         connect_to: HashSet<(libp2p_identity::PeerId, libp2p::Multiaddr)>,
         id: usize,
-    ) -> crate::network::node::handle::NetworkNodeHandle<TestTypes> {
-        use bimap::BiMap;
-
+    ) -> NetworkNodeHandle<TestTypes> {
         let config = NetworkNodeConfigBuilder::default()
             .network_discriminator(discriminator)
             .to_connect_addrs(connect_to)
             .build()
             .expect("config build");
 
-        let key_map = Arc::new(Mutex::new(BiMap::default()));
-        let (mut receiver, handle) = spawn_network_node::<TestTypes, DhtNoPersistence>(
-            config,
-            DhtNoPersistence,
-            key_map,
-            id,
-        )
-        .await
-        .expect("spawn node");
+        let (mut receiver, handle) = spawn_node_with_config(config, id).await;
 
         // Drain events so the swarm loop is never blocked on a full channel.
         tokio::spawn(async move {
@@ -1147,6 +1244,113 @@ mod tests {
         .expect("test timed out");
     }
 
+    /// Spawn a node connecting to `connect_to` that answers direct requests after `latency`,
+    /// simulating a link with that round-trip time.
+    async fn spawn_responder(
+        connect_to: HashSet<(libp2p_identity::PeerId, libp2p::Multiaddr)>,
+        latency: Duration,
+        id: usize,
+    ) -> NetworkNodeHandle<TestTypes> {
+        let config = NetworkNodeConfigBuilder::default()
+            .to_connect_addrs(connect_to)
+            .build()
+            .expect("config build");
+        let (mut receiver, handle) = spawn_node_with_config(config, id).await;
+
+        let responder = handle.clone();
+        tokio::spawn(async move {
+            while let Ok(event) = receiver.recv().await {
+                if let NetworkEvent::DirectRequest(_, _, chan) = event {
+                    tokio::time::sleep(latency).await;
+                    let _ = responder.direct_response(chan, &[]);
+                }
+            }
+        });
+
+        handle
+            .subscribe("global".to_string())
+            .await
+            .expect("subscribe");
+        handle.begin_bootstrap().expect("begin_bootstrap");
+        handle
+    }
+
+    /// With peer scoring enabled, a mesh peer that answers direct messages slowly scores below
+    /// one that answers quickly, so it is the first to go when the mesh is pruned.
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn demotes_slow_peers() {
+        const REQUESTS: usize = 10;
+        let slow_latency = Duration::from_millis(300);
+
+        tokio::time::timeout(Duration::from_secs(90), async {
+            let config = NetworkNodeConfigBuilder::default()
+                .gossip_config(GossipConfig {
+                    heartbeat_initial_delay: Duration::from_millis(100),
+                    peer_scoring: Some(PeerScoringConfig::default()),
+                    ..Default::default()
+                })
+                .build()
+                .expect("config build");
+            let (mut receiver, a) = spawn_node_with_config(config, 0).await;
+            a.subscribe("global".to_string()).await.expect("subscribe");
+
+            // Forward the acknowledgements of our direct requests, dropping everything else
+            let (responses_tx, mut responses) = tokio::sync::mpsc::unbounded_channel();
+            tokio::spawn(async move {
+                while let Ok(event) = receiver.recv().await {
+                    if let NetworkEvent::DirectResponse(_, peer_id) = event {
+                        let _ = responses_tx.send(peer_id);
+                    }
+                }
+            });
+
+            let hub = HashSet::from([(a.peer_id(), a.listen_addr())]);
+            let fast = spawn_responder(hub.clone(), Duration::ZERO, 1).await;
+            let slow = spawn_responder(hub, slow_latency, 2).await;
+            let (fast_pid, slow_pid) = (fast.peer_id(), slow.peer_id());
+
+            while !a
+                .connected_pids()
+                .await
+                .expect("connected_pids")
+                .is_superset(&HashSet::from([fast_pid, slow_pid]))
+            {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+
+            for _ in 0..REQUESTS {
+                for pid in [fast_pid, slow_pid] {
+                    a.direct_request(pid, b"ping").expect("direct_request");
+                    while responses.recv().await.expect("response") != pid {}
+                }
+            }
+
+            // Wait for the heartbeat to graft both peers into the mesh
+            loop {
+                let mesh = a.mesh_peer_metrics().await.expect("mesh_peer_metrics");
+                let find = |pid| mesh.iter().find(|peer| peer.peer_id == pid);
+                if let (Some(fast), Some(slow)) = (find(fast_pid), find(slow_pid)) {
+                    assert_eq!(fast.delivered, REQUESTS as u64);
+                    assert_eq!(slow.delivered, REQUESTS as u64);
+                    assert!(slow.rtt.expect("slow rtt") >= slow_latency, "{slow:?}");
+                    assert!(slow.app_score < fast.app_score, "{slow:?} {fast:?}");
+                    assert!(
+                        slow.score.expect("slow score") < fast.score.expect("fast score"),
+                        "{slow:?} {fast:?}"
+                    );
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(500)).await;
+            }
+
+            slow.shutdown().await.expect("shutdown slow");
+            fast.shutdown().await.expect("shutdown fast");
+            a.shutdown().await.expect("shutdown a");
+        })
+        .await
+        .expect("test timed out");
+    }
+
     #[test]
     fn put_quorum_override() {
         let nz = |n| NonZeroUsize::new(n).unwrap();
@@ -160,6 +160,9 @@ pub struct GossipConfig {
 
     /// Minimum number of peers to emit gossip to during a heartbeat
     pub gossip_lazy: usize,
+
+    /// Score peers by stake and link quality when selecting mesh peers, disabled if `None`
+    pub peer_scoring: Option<PeerScoringConfig>,
 }
 
 impl Default for GossipConfig {
@@ -190,12 +193,54 @@ impl Default for GossipConfig {
             heartbeat_initial_delay: Duration::from_secs(5),
             gossip_factor: 0.25,
             gossip_lazy: 6,
+            peer_scoring: None,
 
             max_transmit_size: MAX_GOSSIP_MSG_SIZE, // The maximum gossip message size
         }
     }
 }
 
+/// Configuration of the application-specific peer score fed into `GossipSub`.
+///
+/// A peer's score is the sum of its stake, round-trip time and DHT components, minus a penalty
+/// for failed direct messages. `GossipSub` prunes peers with a negative score from the mesh,
+/// keeps the best-scoring peers when the mesh is oversubscribed and opportunistically grafts
+/// better peers when the median mesh score drops below `opportunistic_graft_threshold`.
+#[derive(Clone, Debug)]
+pub struct PeerScoringConfig {
+    /// The score of the peer with the largest stake, other peers are scored proportionally
+    pub stake_weight: f64,
+
+    /// The score of a peer with a round-trip time of at most `target_rtt`
+    pub rtt_weight: f64,
+    /// Round-trip times up to this are considered ideal, slower peers score inversely to
+    /// their round-trip time
+    pub target_rtt: Duration,
+
+    /// The penalty of a peer none of our direct messages are delivered to
+    pub failure_weight: f64,
+
+    /// The score of a peer that is in our Kademlia routing table
+    pub dht_weight: f64,
+
+    /// Median mesh score below which better-scoring peers are grafted
+    pub opportunistic_graft_threshold: f64,
+}
+
+impl Default for PeerScoringConfig {
+    fn default() -> Self {
+        Self {
+            stake_weight: 40.0,
+            rtt_weight: 30.0,
+            target_rtt: Duration::from_millis(50),
+            // Kept small enough that an unreachable peer stays above the gossip threshold
+            failure_weight: 10.0,
+            dht_weight: 10.0,
+            opportunistic_graft_threshold: 20.0,
+        }
+    }
+}
+
 /// Configuration for Libp2p's request-response
 #[derive(Clone, Debug)]
 pub struct RequestResponseConfig {
@@ -6,6 +6,7 @@
 
 use std::{collections::HashSet, fmt::Debug, sync::Arc, time::Duration};
 
+use alloy::primitives::U256;
 use bimap::BiMap;
 use hotshot_types::traits::{
     network::NetworkError, node_implementation::NodeType, signature_key::SignatureKey,
@@ -21,9 +22,12 @@ use tracing::{debug, info, instrument};
 
 use crate::network::{
     ClientRequest, NetworkEvent, NetworkNode, NetworkNodeConfig, SwarmTaskHandle,
-    behaviours::dht::{
-        record::{Namespace, RecordKey, RecordValue},
-        store::persistent::DhtPersistentStorage,
+    behaviours::{
+        dht::{
+            record::{Namespace, RecordKey, RecordValue},
+            store::persistent::DhtPersistentStorage,
+        },
+        peer_scoring::MeshPeerMetrics,
     },
     gen_multiaddr, log_summary,
 };
@@ -500,6 +504,31 @@ impl<T: NodeType> NetworkNodeHandle<T> {
         Ok(r.await.unwrap())
     }
 
+    /// Set the stake of each staking key, so that gossip peers are scored by their stake.
+    /// # Errors
+    /// - Will return [`NetworkError::ChannelSendError`] when underlying `NetworkNode` has been killed
+    pub fn set_stake_table(
+        &self,
+        stakes: impl IntoIterator<Item = (T::SignatureKey, U256)>,
+    ) -> Result<(), NetworkError> {
+        let stakes = stakes
+            .into_iter()
+            .map(|(key, stake)| (key.to_bytes(), stake))
+            .collect();
+        self.send_request(ClientRequest::SetStakeTable(stakes))
+    }
+
+    /// Return the link quality and score of our gossip mesh peers.
+    /// # Errors
+    /// If the channel is closed somehow
+    pub async fn mesh_peer_metrics(&self) -> Result<Vec<MeshPeerMetrics>, NetworkError> {
+        let (s, r) = futures::channel::oneshot::channel();
+        self.send_request(ClientRequest::GetMeshPeerMetrics(s))?;
+        r.await.map_err(|_| {
+            NetworkError::ChannelReceiveError("network node dropped the request".to_string())
+        })
+    }
+
     /// Get a reference to the network node handle's id.
     #[must_use]
     pub fn id(&self) -> usize {
@@ -0,0 +1,12 @@
+use anyhow::Result;
+use clap::Parser;
+use hotshot_new_protocol::logging::init_logging;
+use hotshot_new_protocol_bench::replay::ReplayConfig;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    init_logging();
+
+    let cfg = ReplayConfig::parse();
+    hotshot_new_protocol_bench::replay::run(cfg).await
+}
@@ -37,6 +37,10 @@ pub struct NodeConfig {
     /// Block payload size in bytes.
     #[arg(long, default_value_t = 0)]
     pub block_size: usize,
+
+    /// Record a consensus trace to this file, for `new-protocol-replay`.
+    #[arg(long)]
+    pub trace_file: Option<String>,
 }
 
 impl NodeConfig {
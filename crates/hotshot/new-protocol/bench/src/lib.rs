@@ -2,3 +2,4 @@ pub mod config;
 pub mod membership;
 pub mod metrics;
 pub mod node;
+pub mod replay;
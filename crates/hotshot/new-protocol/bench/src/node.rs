@@ -20,6 +20,7 @@ use hotshot_new_protocol::{
     outbox::Outbox,
     proposal::{ProposalValidator, VidShareValidator},
     state::StateManager,
+    trace::TraceRecorder,
     vid::{VidDisperser, VidReconstructor},
     vote::VoteCollector,
 };
@@ -48,7 +49,7 @@ pub async fn run(cfg: NodeConfig) -> Result<()> {
     let network = create_network(cfg.node_id, &public_key, &private_key, &cfg).await?;
 
     let coordinator =
-        build_coordinator(public_key, private_key, membership, network, client, &cfg).await;
+        build_coordinator(public_key, private_key, membership, network, client, &cfg).await?;
 
     run_instrumented(coordinator, &cfg).await
 }
@@ -100,6 +101,44 @@ async fn create_network(
     Ok(net)
 }
 
+/// Consensus of node `node_id` at genesis, as every benchmark node starts.
+pub async fn genesis_consensus(
+    node_id: u64,
+    total_nodes: usize,
+    membership: EpochMembershipCoordinator<TestTypes>,
+) -> Consensus<TestTypes> {
+    let (public_key, private_key) = BLSPubKey::generated_from_seed_indexed([0u8; 32], node_id);
+    let instance = TestInstanceState::default();
+    let genesis_leaf = Leaf2::<TestTypes>::genesis(
+        &TestValidatedState::default(),
+        &instance,
+        TEST_VERSIONS.test.base,
+    )
+    .await;
+
+    let state_key_pair =
+        hotshot_types::light_client::StateKeyPair::generate_from_seed_indexed([0u8; 32], node_id);
+    let state_private_key = state_key_pair.sign_key_ref().clone();
+
+    let mut consensus = Consensus::new(
+        membership,
+        public_key,
+        private_key,
+        state_private_key,
+        total_nodes,
+        bench_upgrade_lock(),
+        genesis_leaf.clone(),
+        u64::MAX,
+    );
+
+    // Seed consensus with genesis cert + proposal so the view-1 leader
+    // can self-start without external injection from the orchestrator.
+    let genesis_cert1 = build_genesis_cert1(&genesis_leaf);
+    let genesis_proposal = build_genesis_proposal(&genesis_leaf, &genesis_cert1);
+    consensus.seed_parent(genesis_cert1, genesis_proposal, std::iter::empty());
+    consensus
+}
+
 async fn build_coordinator(
     public_key: BLSPubKey,
     private_key: <BLSPubKey as SignatureKey>::PrivateKey,
@@ -107,7 +146,7 @@ async fn build_coordinator(
     network: Cliquenet<TestTypes>,
     client: CoordinatorClient<TestTypes>,
     cfg: &NodeConfig,
-) -> BenchCoordinator {
+) -> Result<BenchCoordinator> {
     let instance = Arc::new(TestInstanceState::default());
     let epoch_height = u64::MAX;
 
@@ -116,22 +155,7 @@ async fn build_coordinator(
         Leaf2::<TestTypes>::genesis(&genesis_state, &instance, TEST_VERSIONS.test.base).await;
     let upgrade_lock = bench_upgrade_lock();
 
-    let state_key_pair = hotshot_types::light_client::StateKeyPair::generate_from_seed_indexed(
-        [0u8; 32],
-        cfg.node_id,
-    );
-    let state_private_key = state_key_pair.sign_key_ref().clone();
-
-    let mut consensus = Consensus::new(
-        membership.clone(),
-        public_key,
-        private_key.clone(),
-        state_private_key,
-        cfg.total_nodes,
-        upgrade_lock.clone(),
-        genesis_leaf.clone(),
-        epoch_height,
-    );
+    let consensus = genesis_consensus(cfg.node_id, cfg.total_nodes, membership.clone()).await;
 
     let vote1_collector = VoteCollector::new(membership.clone(), upgrade_lock.clone());
     let vote2_collector = VoteCollector::new(membership.clone(), upgrade_lock.clone());
@@ -166,21 +190,18 @@ async fn build_coordinator(
         genesis_leaf.clone(),
     );
 
-    // Seed consensus with genesis cert + proposal so the view-1 leader
-    // can self-start without external injection from the orchestrator.
+    // The synthetic genesis proposal `genesis_consensus` seeds has a
+    // non-null justify_qc so the leaf derived from it has a different
+    // commitment than `genesis_leaf`. `request_header` for view 1 looks up
+    // the parent state by the proposal's leaf commitment, so seed the same
+    // state under that commitment too (mirrors coordinator builder behavior).
     let genesis_cert1 = build_genesis_cert1(&genesis_leaf);
     let genesis_proposal = build_genesis_proposal(&genesis_leaf, &genesis_cert1);
-    // The synthetic genesis proposal has a non-null justify_qc so the leaf
-    // derived from it has a different commitment than `genesis_leaf`.
-    // `request_header` for view 1 looks up the parent state by the
-    // proposal's leaf commitment, so seed the same state under that
-    // commitment too (mirrors coordinator builder behavior).
     state_manager.seed_state(
         ViewNumber::genesis(),
         genesis_state,
-        Leaf2::from(genesis_proposal.clone()),
+        Leaf2::from(genesis_proposal),
     );
-    consensus.seed_parent(genesis_cert1, genesis_proposal, std::iter::empty());
 
     let proposal_validator =
         ProposalValidator::new(membership.clone(), epoch_height, upgrade_lock.clone());
@@ -193,7 +214,7 @@ async fn build_coordinator(
         EpochNumber::genesis(),
     );
 
-    let mut coordinator = Coordinator::builder()
+    let coordinator = Coordinator::builder()
         .consensus(consensus)
         .network(network)
         .state_manager(state_manager)
@@ -220,6 +241,14 @@ async fn build_coordinator(
         .public_key(public_key)
         .build();
 
+    let mut coordinator = match &cfg.trace_file {
+        Some(path) => {
+            info!(node_id = cfg.node_id, %path, "recording consensus trace");
+            coordinator.with_trace(TraceRecorder::create(path)?)
+        },
+        None => coordinator,
+    };
+
     // Emit initial ViewChanged and (for the leader) RequestBlockAndHeader.
     coordinator.start(None);
 
@@ -230,7 +259,7 @@ async fn build_coordinator(
         }
     }
 
-    coordinator
+    Ok(coordinator)
 }
 
 /// Run coordinator with metrics instrumentation and block injection.
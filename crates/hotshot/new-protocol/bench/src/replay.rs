@@ -0,0 +1,42 @@
+use anyhow::{Result, bail};
+use clap::Parser;
+use hotshot::types::BLSPubKey;
+use hotshot_new_protocol::trace::{ReplayReport, replay_file};
+use hotshot_types::traits::signature_key::SignatureKey;
+
+use crate::{membership::make_membership, node::genesis_consensus};
+
+#[derive(Parser, Clone)]
+#[command(name = "new-protocol-replay")]
+#[command(about = "Replay a consensus trace recorded by a benchmark node")]
+pub struct ReplayConfig {
+    /// Trace recorded with `--trace-file`.
+    #[arg(long)]
+    pub trace: String,
+
+    /// Index of the node that recorded the trace.
+    #[arg(long)]
+    pub node_id: u64,
+
+    /// Total number of consensus nodes in the recorded run.
+    #[arg(long)]
+    pub total_nodes: usize,
+}
+
+/// Replay a trace into the consensus the recording node started from.
+pub async fn replay(cfg: &ReplayConfig) -> Result<ReplayReport> {
+    let (public_key, _) = BLSPubKey::generated_from_seed_indexed([0u8; 32], cfg.node_id);
+    let (membership, _client) = make_membership(cfg.total_nodes, public_key).await;
+    let consensus = genesis_consensus(cfg.node_id, cfg.total_nodes, membership).await;
+    Ok(replay_file(consensus, &cfg.trace)?)
+}
+
+/// Replay a trace and fail if it diverges.
+pub async fn run(cfg: ReplayConfig) -> Result<()> {
+    let report = replay(&cfg).await?;
+    println!("{report}");
+    if report.divergence.is_some() {
+        bail!("{} diverged on replay", cfg.trace)
+    }
+    Ok(())
+}
@@ -2,7 +2,10 @@
 //! and verify that consensus advances and produces CSV metrics.
 use std::time::Duration;
 
-use hotshot_new_protocol_bench::config::NodeConfig;
+use hotshot_new_protocol_bench::{
+    config::NodeConfig,
+    replay::{ReplayConfig, replay},
+};
 use tempfile::TempDir;
 use tokio::time::timeout;
 
@@ -39,6 +42,7 @@ fn node_config(
             .to_string_lossy()
             .into_owned(),
         block_size,
+        trace_file: None,
     }
 }
 
@@ -49,7 +53,7 @@ async fn smoke_5_nodes_empty_blocks() {
     let tmp = TempDir::new().expect("failed to create temp dir");
     let ports = allocate_ports(NUM_NODES);
 
-    let result = timeout(TEST_TIMEOUT, run_benchmark(tmp.path(), 0, &ports)).await;
+    let result = timeout(TEST_TIMEOUT, run_benchmark(tmp.path(), 0, &ports, false)).await;
 
     match result {
         Ok(Ok(())) => {},
@@ -65,7 +69,7 @@ async fn smoke_5_nodes_1kb_blocks() {
     let tmp = TempDir::new().expect("failed to create temp dir");
     let ports = allocate_ports(NUM_NODES);
 
-    let result = timeout(TEST_TIMEOUT, run_benchmark(tmp.path(), 1024, &ports)).await;
+    let result = timeout(TEST_TIMEOUT, run_benchmark(tmp.path(), 1024, &ports, false)).await;
 
     match result {
         Ok(Ok(())) => {},
@@ -74,15 +78,56 @@ async fn smoke_5_nodes_1kb_blocks() {
     }
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn smoke_5_nodes_trace_replay() {
+    hotshot_new_protocol::logging::init_logging();
+
+    let tmp = TempDir::new().expect("failed to create temp dir");
+    let ports = allocate_ports(NUM_NODES);
+
+    let result = timeout(TEST_TIMEOUT, run_benchmark(tmp.path(), 1024, &ports, true)).await;
+
+    match result {
+        Ok(Ok(())) => {},
+        Ok(Err(e)) => panic!("benchmark failed: {e:#}"),
+        Err(_) => panic!("benchmark timed out after {TEST_TIMEOUT:?}"),
+    }
+
+    // Every node's trace must replay to the outputs it recorded.
+    for i in 0..NUM_NODES as u64 {
+        let cfg = ReplayConfig {
+            trace: trace_path(tmp.path(), i),
+            node_id: i,
+            total_nodes: NUM_NODES,
+        };
+        let report = replay(&cfg)
+            .await
+            .unwrap_or_else(|e| panic!("failed to replay trace of node {i}: {e:#}"));
+        assert!(report.inputs > 0, "node {i} recorded no inputs");
+        assert!(report.divergence.is_none(), "node {i}: {report}");
+    }
+}
+
+fn trace_path(output_dir: &std::path::Path, node_id: u64) -> String {
+    output_dir
+        .join(format!("node_{node_id}.trace"))
+        .to_string_lossy()
+        .into_owned()
+}
+
 async fn run_benchmark(
     output_dir: &std::path::Path,
     block_size: usize,
     ports: &[u16],
+    trace: bool,
 ) -> anyhow::Result<()> {
     let mut node_handles = Vec::new();
 
     for i in 0..NUM_NODES as u64 {
-        let cfg = node_config(i, output_dir, block_size, ports);
+        let mut cfg = node_config(i, output_dir, block_size, ports);
+        if trace {
+            cfg.trace_file = Some(trace_path(output_dir, i));
+        }
         node_handles.push(tokio::spawn(async move {
             hotshot_new_protocol_bench::node::run(cfg).await
         }));
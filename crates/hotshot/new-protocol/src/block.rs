@@ -22,6 +22,7 @@ use hotshot_types::{
     },
     utils::BuilderCommitment,
 };
+use serde::Serialize;
 use tokio::{
     task::{AbortHandle, JoinSet},
     time::sleep,
@@ -47,7 +48,7 @@ pub enum BlockError {
     BuilderSignature,
 }
 
-#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(Clone, Eq, PartialEq, Debug, Serialize)]
 pub struct BlockAndHeaderRequest<T: NodeType> {
     pub view: ViewNumber,
     pub epoch: EpochNumber,
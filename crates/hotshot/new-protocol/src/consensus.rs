@@ -6,7 +6,7 @@ use std::{
 };
 
 use committable::{Commitment, CommitmentBoundsArkless, Committable};
-use hotshot::traits::BlockPayload;
+use hotshot::{HotShotInitializer, traits::BlockPayload};
 use hotshot_contract_adapter::light_client::derive_signed_state_digest;
 use hotshot_types::{
     data::{
@@ -36,6 +36,8 @@ use hotshot_types::{
     vote::{Certificate, HasViewNumber},
 };
 use hotshot_utils::anytrace;
+use serde::{Deserialize, Serialize};
+use strum::IntoStaticStr;
 use tracing::{debug, info, instrument, warn};
 
 use crate::{
@@ -77,6 +79,77 @@ pub struct PreCutoverSeed<T: NodeType> {
     pub cutover_view: ViewNumber,
 }
 
+/// Persisted state a restarted node seeds consensus from, see
+/// [`Consensus::seed_anchor`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+pub struct RestartAnchor<T: NodeType> {
+    /// Highest decided leaf. `Consensus::new` is given this leaf.
+    pub leaf: Leaf2<T>,
+    pub epoch_height: u64,
+    /// QC of the anchor leaf.
+    pub high_qc: Certificate1<T>,
+    /// The anchor leaf as a proposal, installed as the parent.
+    pub parent: Proposal<T>,
+    /// Undecided proposals persisted above the anchor.
+    pub proposals: Vec<Proposal<T>>,
+    /// Blocks this node had reconstructed before it went down.
+    pub reconstructed: Vec<(ViewNumber, VidCommitment2)>,
+    /// Persisted lock, which can be newer than `high_qc`.
+    pub locked_qc: Option<Certificate1<T>>,
+    pub start_view: ViewNumber,
+    pub last_actioned_view: ViewNumber,
+    pub state_cert: Option<LightClientStateUpdateCertificateV2<T>>,
+}
+
+impl<T: NodeType> RestartAnchor<T> {
+    pub fn new(initializer: &HotShotInitializer<T>, locked_qc: Option<Certificate1<T>>) -> Self {
+        let leaf = initializer.anchor_leaf().clone();
+        let epoch_height = initializer.epoch_height();
+        let parent = Proposal {
+            block_header: leaf.block_header().clone(),
+            view_number: leaf.view_number(),
+            epoch: leaf.epoch(epoch_height).unwrap_or(EpochNumber::genesis()),
+            justify_qc: leaf.justify_qc(),
+            next_epoch_justify_qc: None,
+            upgrade_certificate: leaf.upgrade_certificate(),
+            view_change_evidence: leaf.view_change_evidence.clone().and_then(|e| match e {
+                ViewChangeEvidence2::Timeout(tc) => Some(tc),
+                ViewChangeEvidence2::ViewSync(_) => None,
+            }),
+            next_drb_result: leaf.next_drb_result,
+            state_cert: None,
+        };
+        let proposals: Vec<_> = initializer
+            .saved_proposals()
+            .values()
+            .map(|p| Proposal::from(p.data.clone()))
+            .collect();
+        // The anchor leaf and persisted proposals are blocks this node had
+        // reconstructed before it went down, so treat them as reconstructed on
+        // restart
+        let reconstructed = std::iter::once(&parent)
+            .chain(&proposals)
+            .filter_map(|p| match p.block_header.payload_commitment() {
+                VidCommitment::V2(commitment) => Some((p.view_number, commitment)),
+                _ => None,
+            })
+            .collect();
+        Self {
+            high_qc: initializer.high_qc().clone(),
+            start_view: initializer.start_view(),
+            last_actioned_view: initializer.last_actioned_view(),
+            state_cert: initializer.state_cert().cloned(),
+            leaf,
+            epoch_height,
+            parent,
+            proposals,
+            reconstructed,
+            locked_qc,
+        }
+    }
+}
+
 #[derive(Eq, PartialEq, Debug, Clone)]
 #[allow(clippy::large_enum_variant)]
 pub enum ConsensusInput<T: NodeType> {
@@ -120,7 +193,7 @@ pub enum ConsensusInput<T: NodeType> {
     DrbResult(EpochNumber, DrbResult),
 }
 
-#[derive(Eq, PartialEq, Debug, Clone)]
+#[derive(Eq, PartialEq, Debug, Clone, Serialize, IntoStaticStr)]
 pub enum ConsensusOutput<T: NodeType> {
     RequestBlockAndHeader(BlockAndHeaderRequest<T>),
     RequestState(StateRequest<T>),
@@ -402,6 +475,28 @@ impl<T: NodeType> Consensus<T> {
         }
     }
 
+    /// Seed the state a node restarts from. `self` must have been created
+    /// with the anchor's leaf and epoch height.
+    pub fn seed_anchor(&mut self, anchor: RestartAnchor<T>) {
+        let anchor_view = anchor.leaf.view_number();
+        // Seed every persisted proposal before `seed_parent` so its authoritative anchor wins.
+        self.seed_proposals(anchor.proposals);
+        // `seed_parent` sets the current epoch from the anchor proposal;
+        // `resume_from_restart` positions the view so the node never
+        // re-enters a view it may have voted or proposed in before it went
+        // down.
+        self.seed_parent(anchor.high_qc, anchor.parent, anchor.reconstructed);
+        // Restore the persisted lock; it can be newer than the anchor QC, so
+        // this must run after `seed_parent`.
+        if let Some(locked_qc) = anchor.locked_qc {
+            self.seed_locked_cert(locked_qc);
+        }
+        self.resume_from_restart(anchor_view, anchor.start_view, anchor.last_actioned_view);
+        if let Some(state_cert) = anchor.state_cert {
+            self.seed_state_cert(state_cert);
+        }
+    }
+
     /// Seed proposals loaded from storage on restart so the decide chain-walk
     /// can follow `justify_qc` back through views the node had already seen,
     /// and so `maybe_vote_1`/`maybe_propose` can find the parent of the first
@@ -857,6 +952,10 @@ impl<T: NodeType> Consensus<T> {
             .map(|(_, leaf)| leaf)
     }
 
+    pub fn public_key(&self) -> &T::SignatureKey {
+        &self.public_key
+    }
+
     pub fn current_view(&self) -> ViewNumber {
         self.current_view
     }
@@ -29,6 +29,7 @@ use hotshot_types::{
     vid::avidm_gf2::{AvidmGf2Param, init_avidm_gf2_param},
     vote::{HasViewNumber, Vote},
 };
+use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
 use tokio::{select, sync::oneshot};
 use tracing::{debug, error, info, warn};
@@ -37,7 +38,7 @@ use crate::{
     block::{BlockAndHeaderRequest, BlockBuilder, BlockBuilderConfig},
     cert_verifier::CertVerifiers,
     client::{ClientApi, ClientRequest, CoordinatorClient, QueryError},
-    consensus::{Consensus, ConsensusInput, ConsensusOutput, PreCutoverSeed},
+    consensus::{Consensus, ConsensusInput, ConsensusOutput, PreCutoverSeed, RestartAnchor},
     coordinator::{
         error::{CoordinatorError, ErrorSource, Severity},
         timer::Timer,
@@ -55,6 +56,7 @@ use crate::{
     proposal::{ProposalValidator, VidShareValidator},
     state::{HeaderRequest, StateEntry, StateManager, StateManagerOutput},
     storage::{NewProtocolStorage, Storage},
+    trace::{TraceRecorder, TracedInput},
     vid::{VidDisperseRequest, VidDisperser, VidFragmentAccumulator, VidReconstructor},
     vote::{EpochRootTally, SimpleTally, VoteCollector},
 };
@@ -144,6 +146,8 @@ pub struct Coordinator<T: NodeType, S> {
     invalid_certs_at_decide: u64,
     #[builder(skip)]
     payload_txn_bytes: BTreeMap<ViewNumber, usize>,
+    #[builder(skip)]
+    trace: Option<TraceRecorder<T>>,
 }
 
 #[bon]
@@ -169,7 +173,10 @@ where
         consensus_metrics: ConsensusMetricsValue,
         /// Locked QC persisted on a prior run; restored so the lock survives restart.
         locked_qc: Option<Certificate1<T>>,
+        /// Record a consensus trace that replays from the persisted anchor.
+        trace: Option<TraceRecorder<T>>,
     ) -> Self {
+        let anchor = RestartAnchor::new(initializer, locked_qc);
         let mut consensus = Consensus::new(
             membership_coordinator.clone(),
             public_key.clone(),
@@ -177,33 +184,13 @@ where
             state_private_key,
             stake_table_capacity,
             upgrade_lock.clone(),
-            initializer.anchor_leaf().clone(),
-            initializer.epoch_height(),
+            anchor.leaf.clone(),
+            anchor.epoch_height,
         );
 
         let anchor_leaf = initializer.anchor_leaf();
         let anchor_view = anchor_leaf.view_number();
-        let anchor_epoch = anchor_leaf
-            .epoch(initializer.epoch_height())
-            .unwrap_or(EpochNumber::genesis());
-        let cert1 = initializer.high_qc().clone();
-        let parent_proposal = message::Proposal {
-            block_header: anchor_leaf.block_header().clone(),
-            view_number: anchor_view,
-            epoch: anchor_epoch,
-            justify_qc: anchor_leaf.justify_qc(),
-            next_epoch_justify_qc: None,
-            upgrade_certificate: anchor_leaf.upgrade_certificate(),
-            view_change_evidence: anchor_leaf
-                .view_change_evidence
-                .clone()
-                .and_then(|e| match e {
-                    hotshot_types::data::ViewChangeEvidence2::Timeout(tc) => Some(tc),
-                    hotshot_types::data::ViewChangeEvidence2::ViewSync(_) => None,
-                }),
-            next_drb_result: anchor_leaf.next_drb_result,
-            state_cert: None,
-        };
+        let anchor_epoch = anchor.parent.epoch;
 
         let coordinator_metrics = metrics
             .is_recording()
@@ -234,45 +221,11 @@ where
             initializer.anchor_state().clone(),
             anchor_leaf.clone(),
         );
-        // The anchor leaf and persisted proposals are blocks this node had
-        // reconstructed before it went down, so treat them as reconstructed on
-        // restart
-        let reconstructed_blocks =
-            std::iter::once((anchor_view, anchor_leaf.block_header().clone()))
-                .chain(
-                    initializer
-                        .saved_proposals()
-                        .iter()
-                        .map(|(view, p)| (*view, p.data.block_header().clone())),
-                )
-                .filter_map(|(view, header)| match header.payload_commitment() {
-                    VidCommitment::V2(commitment) => Some((view, commitment)),
-                    _ => None,
-                });
-        // Seed every persisted proposal before `seed_parent` so its authoritative anchor wins.
-        let saved_proposals = initializer
-            .saved_proposals()
-            .values()
-            .map(|p| message::Proposal::from(p.data.clone()));
-        consensus.seed_proposals(saved_proposals);
-        // `seed_parent` sets the current epoch from the anchor proposal;
-        // `resume_from_restart` positions the view so the node never
-        // re-enters a view it may have voted or proposed in before it went
-        // down.
-        consensus.seed_parent(cert1, parent_proposal, reconstructed_blocks);
-        // Restore the persisted lock; it can be newer than the anchor QC, so
-        // this must run after `seed_parent`.
-        if let Some(locked_qc) = locked_qc {
-            consensus.seed_locked_cert(locked_qc);
-        }
-        consensus.resume_from_restart(
-            anchor_view,
-            initializer.start_view(),
-            initializer.last_actioned_view(),
-        );
-        if let Some(state_cert) = initializer.state_cert().cloned() {
-            consensus.seed_state_cert(state_cert);
-        }
+        let trace = trace.map(|mut trace| {
+            trace.anchor(&anchor);
+            trace
+        });
+        consensus.seed_anchor(anchor);
 
         let participation = ParticipationTracker::new(&membership_coordinator, anchor_epoch);
 
@@ -289,7 +242,7 @@ where
         );
 
         let lock = upgrade_lock.clone();
-        Self::builder()
+        let coordinator = Self::builder()
             .consensus(consensus)
             .network(network)
             .state_manager(state_manager)
@@ -345,7 +298,11 @@ where
             .public_key(public_key)
             .maybe_metrics(coordinator_metrics)
             .participation(participation)
-            .build()
+            .build();
+        match trace {
+            Some(trace) => coordinator.with_trace(trace),
+            None => coordinator,
+        }
     }
 
     /// Emit `ViewChanged(current_view + 1)` and, if leader, a
@@ -417,7 +374,21 @@ where
         }
     }
 
+    /// Record a trace of this coordinator's consensus.
+    ///
+    /// Attach before [`Self::start`]. A replay must start from consensus set
+    /// up as this coordinator's is at this point; a cutover seed passed to
+    /// `start` is recorded.
+    pub fn with_trace(mut self, mut recorder: TraceRecorder<T>) -> Self {
+        recorder.start(&self.consensus);
+        self.trace = Some(recorder);
+        self
+    }
+
     pub async fn stop(mut self) {
+        if let Some(trace) = &mut self.trace {
+            trace.flush()
+        }
         futures::join!(self.network.shutdown(), self.storage.flush());
     }
 
@@ -656,7 +627,13 @@ where
     }
 
     pub fn apply_consensus(&mut self, input: ConsensusInput<T>) {
-        self.consensus.apply(input, &mut self.outbox)
+        let Some(trace) = &mut self.trace else {
+            return self.consensus.apply(input, &mut self.outbox);
+        };
+        let traced = TracedInput::from(&input);
+        let before = self.outbox.len();
+        self.consensus.apply(input, &mut self.outbox);
+        trace.input(traced, self.outbox.iter().skip(before))
     }
 
     pub fn process_consensus_output(
@@ -1020,6 +997,11 @@ where
         self.consensus.current_view()
     }
 
+    /// View of the QC this node is locked on, if any.
+    pub fn locked_view(&self) -> Option<ViewNumber> {
+        self.consensus.locked_view()
+    }
+
     pub fn state(&self, v: ViewNumber) -> Option<&StateEntry<T>> {
         self.state_manager.get_state(v)
     }
@@ -1618,6 +1600,9 @@ where
                 let qc_view = qc.view_number();
                 let cutover_view = qc_view + 1;
                 self.consensus.register_legacy_qc(&qc);
+                if let Some(trace) = &mut self.trace {
+                    trace.legacy_qc(&qc)
+                }
 
                 // Still parked on the last legacy view (seed landed without this
                 // QC, waiting out the timer) and not yet skipped via TC2: propose
@@ -1799,6 +1784,9 @@ where
 
     fn gc(&mut self, epoch: EpochNumber, scope: GcScope) -> Result<(), CoordinatorError> {
         self.consensus.gc(scope);
+        if let Some(trace) = &mut self.trace {
+            trace.gc(scope)
+        }
         match scope {
             GcScope::Local(view) => {
                 self.block_builder.gc(view);
@@ -1890,6 +1878,9 @@ where
         ));
         let cutover_view = seed.cutover_view;
 
+        if let Some(trace) = &mut self.trace {
+            trace.cutover(&seed)
+        }
         self.consensus.apply_pre_cutover_seed(seed);
 
         // Refresh peers for the cutover epoch before kicking the
@@ -1964,7 +1955,7 @@ where
 }
 
 /// Garbage collection scope.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum GcScope {
     /// GC is invoked on local view changes.
     Local(ViewNumber),
@@ -84,16 +84,34 @@ impl<T: NodeType> Cliquenet<T> {
     where
         P: IntoIterator<Item = (T::SignatureKey, PeerConnectInfo)>,
     {
-        let public_key = config.public_key();
         let metrics = CliquenetMetrics::new(metrics);
         let network = cliquenet::Network::create(config.with_metrics(metrics)).await?;
+        Ok(Self::from_network(
+            signing_key,
+            upgrade_lock,
+            network,
+            parties,
+        ))
+    }
+
+    /// Wrap an already created cliquenet network.
+    pub(crate) fn from_network<P>(
+        signing_key: T::SignatureKey,
+        upgrade_lock: UpgradeLock<T>,
+        network: cliquenet::Network,
+        parties: P,
+    ) -> Self
+    where
+        P: IntoIterator<Item = (T::SignatureKey, PeerConnectInfo)>,
+    {
+        let public_key = network.config().public_key();
         let peers: HashMap<_, _> = parties.into_iter().collect();
 
         info!(peers = %peers.len(), "cliquenet created");
 
         let (send, recv) = network.split_into();
 
-        Ok(Self {
+        Self {
             inner: Sender {
                 my_keys: (signing_key, public_key),
                 sender: send,
@@ -104,7 +122,7 @@ impl<T: NodeType> Cliquenet<T> {
                 upgrade_lock,
             },
             receiver: recv,
-        })
+        }
     }
 
     pub fn sender(&self) -> &Sender<T> {
@@ -16,6 +16,7 @@ use hotshot_types::{
     utils::BuilderCommitment,
     vote::HasViewNumber,
 };
+use serde::{Deserialize, Serialize};
 use tokio::task::{AbortHandle, JoinSet};
 use tracing::{error, warn};
 
@@ -32,7 +33,7 @@ pub struct UpdateLeaf<T: NodeType> {
     pub delta: Option<Delta<T>>,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
 pub struct StateRequest<T: NodeType> {
     pub view: ViewNumber,
     pub parent_view: ViewNumber,
@@ -54,7 +55,7 @@ pub struct HeaderRequest<T: NodeType> {
     pub builder_fee: BuilderFee<T>,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct StateResponse<T: NodeType> {
     pub view: ViewNumber,
     pub commitment: Commitment<Leaf2<T>>,
@@ -1,4 +1,4 @@
-use std::{collections::BTreeMap, marker::PhantomData, sync::Arc, time::Duration};
+use std::{collections::BTreeMap, future::Future, marker::PhantomData, sync::Arc, time::Duration};
 
 use async_trait::async_trait;
 use committable::Commitment;
@@ -21,6 +21,7 @@ use hotshot_types::{
     utils::EpochTransitionIndicator,
     vote::HasViewNumber,
 };
+use serde::{Deserialize, Serialize};
 use tokio::{
     task::{AbortHandle, JoinSet},
     time::sleep,
@@ -46,9 +47,18 @@ pub trait NewProtocolStorage<T: NodeType>: StorageTrait<T> {
 
     /// Load the persisted locked QC, if any.
     async fn load_high_qc2(&self) -> anyhow::Result<Option<Certificate1<T>>>;
+
+    /// Make every acknowledged write durable.
+    ///
+    /// [`Storage::flush`] calls this once all in-flight writes have completed.
+    /// Backends that buffer writes commit them here; by default writes are
+    /// durable once acknowledged and there is nothing to do.
+    async fn sync(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum ActionKind {
     Vote,
     Propose,
@@ -63,7 +73,7 @@ impl From<ActionKind> for HotShotAction {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum StorageOutput<T: NodeType> {
     Proposal(ViewNumber, Commitment<Leaf2<T>>),
     Vid(ViewNumber),
@@ -83,6 +93,51 @@ impl<T: NodeType> StorageOutput<T> {
     }
 }
 
+/// What a write issued by [`Storage`] persists.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WriteKind {
+    Vid,
+    Da,
+    Cert2,
+    HighQc,
+    StateCert,
+    Proposal,
+    Action(ActionKind),
+}
+
+/// A write issued by [`Storage`] that the backend has not acknowledged yet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InFlightWrite {
+    /// Position of the write in issue order.
+    pub seq: u64,
+    pub view: ViewNumber,
+    pub kind: WriteKind,
+}
+
+/// In-flight write tracking of [`Storage`].
+///
+/// A write is tracked from the moment it is handed to the backend until the
+/// backend acknowledges it or it is garbage collected. Writes run concurrently
+/// and may land in any order; this is bookkeeping only and persists nothing.
+#[derive(Debug, Default)]
+struct InFlight {
+    next_seq: u64,
+    pending: BTreeMap<u64, InFlightWrite>,
+}
+
+impl InFlight {
+    fn record(&mut self, view: ViewNumber, kind: WriteKind) -> u64 {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.pending.insert(seq, InFlightWrite { seq, view, kind });
+        seq
+    }
+
+    fn complete(&mut self, seq: u64) {
+        self.pending.remove(&seq);
+    }
+}
+
 /// Request-to-completion latency of each storage operation, including task
 /// queueing, backend lock waits, and error-retry loops. These latencies gate
 /// consensus progress: proposal release waits on `append_proposal` and
@@ -122,8 +177,9 @@ impl StorageMetrics {
 pub struct Storage<T: NodeType, S> {
     storage: S,
     private_key: <T::SignatureKey as SignatureKey>::PrivateKey,
-    tasks: JoinSet<Option<StorageOutput<T>>>,
-    handles: BTreeMap<ViewNumber, Vec<AbortHandle>>,
+    in_flight: InFlight,
+    tasks: JoinSet<(u64, Option<StorageOutput<T>>)>,
+    handles: BTreeMap<ViewNumber, Vec<(u64, AbortHandle)>>,
     metrics: Option<StorageMetrics>,
 }
 
@@ -132,6 +188,7 @@ impl<T: NodeType, S: NewProtocolStorage<T>> Storage<T, S> {
         Self {
             storage,
             private_key,
+            in_flight: InFlight::default(),
             tasks: JoinSet::new(),
             handles: BTreeMap::new(),
             metrics: None,
@@ -153,7 +210,7 @@ impl<T: NodeType, S: NewProtocolStorage<T>> Storage<T, S> {
             .metrics
             .as_ref()
             .map(|m| Measurement::start(m.append_vid.clone()));
-        let handle = self.tasks.spawn(async move {
+        self.spawn(view, WriteKind::Vid, async move {
             let share: VidDisperseShare<T> = VidDisperseShare::V2(vid_share);
             let Some(proposal) = share.to_proposal(&private_key) else {
                 error!("failed to sign VID share for storage");
@@ -172,7 +229,6 @@ impl<T: NodeType, S: NewProtocolStorage<T>> Storage<T, S> {
                 }
             }
         });
-        self.handles.entry(view).or_default().push(handle);
     }
 
     pub fn append_da(
@@ -189,7 +245,7 @@ impl<T: NodeType, S: NewProtocolStorage<T>> Storage<T, S> {
             .metrics
             .as_ref()
             .map(|m| Measurement::start(m.append_da.clone()));
-        let handle = self.tasks.spawn(async move {
+        self.spawn(view_number, WriteKind::Da, async move {
             let data = DaProposal2 {
                 encoded_transactions: block_payload.encode(),
                 metadata,
@@ -219,7 +275,6 @@ impl<T: NodeType, S: NewProtocolStorage<T>> Storage<T, S> {
                 }
             }
         });
-        self.handles.entry(view_number).or_default().push(handle);
     }
 
     pub fn append_cert2(&mut self, view: ViewNumber, cert2: Certificate2<T>) {
@@ -228,7 +283,7 @@ impl<T: NodeType, S: NewProtocolStorage<T>> Storage<T, S> {
             .metrics
             .as_ref()
             .map(|m| Measurement::start(m.append_cert2.clone()));
-        let handle = self.tasks.spawn(async move {
+        self.spawn(view, WriteKind::Cert2, async move {
             loop {
                 match storage.append_cert2(view, cert2.clone()).await {
                     Ok(()) => {
@@ -242,7 +297,6 @@ impl<T: NodeType, S: NewProtocolStorage<T>> Storage<T, S> {
                 }
             }
         });
-        self.handles.entry(view).or_default().push(handle);
     }
 
     /// Persist the locked QC; on success emits [`StorageOutput::HighQc`], which
@@ -254,7 +308,7 @@ impl<T: NodeType, S: NewProtocolStorage<T>> Storage<T, S> {
             .metrics
             .as_ref()
             .map(|m| Measurement::start(m.append_high_qc.clone()));
-        let handle = self.tasks.spawn(async move {
+        self.spawn(view, WriteKind::HighQc, async move {
             loop {
                 match storage.append_high_qc2(high_qc.clone()).await {
                     Ok(()) => {
@@ -268,7 +322,6 @@ impl<T: NodeType, S: NewProtocolStorage<T>> Storage<T, S> {
                 }
             }
         });
-        self.handles.entry(view).or_default().push(handle);
     }
 
     pub fn append_state_cert(
@@ -281,7 +334,7 @@ impl<T: NodeType, S: NewProtocolStorage<T>> Storage<T, S> {
             .metrics
             .as_ref()
             .map(|m| Measurement::start(m.append_state_cert.clone()));
-        let handle = self.tasks.spawn(async move {
+        self.spawn(view, WriteKind::StateCert, async move {
             loop {
                 match storage.update_state_cert(state_cert.clone()).await {
                     Ok(()) => {
@@ -295,7 +348,6 @@ impl<T: NodeType, S: NewProtocolStorage<T>> Storage<T, S> {
                 }
             }
         });
-        self.handles.entry(view).or_default().push(handle);
     }
 
     pub fn append_proposal(&mut self, proposal: Proposal<T>) {
@@ -307,7 +359,7 @@ impl<T: NodeType, S: NewProtocolStorage<T>> Storage<T, S> {
             .metrics
             .as_ref()
             .map(|m| Measurement::start(m.append_proposal.clone()));
-        let handle = self.tasks.spawn(async move {
+        self.spawn(view, WriteKind::Proposal, async move {
             let data = QuorumProposalWrapper {
                 proposal: QuorumProposal2 {
                     block_header: proposal.block_header,
@@ -345,7 +397,6 @@ impl<T: NodeType, S: NewProtocolStorage<T>> Storage<T, S> {
                 }
             }
         });
-        self.handles.entry(view).or_default().push(handle);
     }
 
     pub fn record_action(
@@ -359,7 +410,7 @@ impl<T: NodeType, S: NewProtocolStorage<T>> Storage<T, S> {
             .metrics
             .as_ref()
             .map(|m| Measurement::start(m.record_action.clone()));
-        let handle = self.tasks.spawn(async move {
+        self.spawn(view, WriteKind::Action(kind), async move {
             loop {
                 match storage.record_action(view, epoch, kind.into()).await {
                     Ok(()) => {
@@ -373,14 +424,33 @@ impl<T: NodeType, S: NewProtocolStorage<T>> Storage<T, S> {
                 }
             }
         });
-        self.handles.entry(view).or_default().push(handle);
+    }
+
+    /// Track a write for `view` and run it in the background.
+    fn spawn<F>(&mut self, view: ViewNumber, kind: WriteKind, write: F)
+    where
+        F: Future<Output = Option<StorageOutput<T>>> + Send + 'static,
+    {
+        let seq = self.in_flight.record(view, kind);
+        let handle = self.tasks.spawn(async move { (seq, write.await) });
+        self.handles.entry(view).or_default().push((seq, handle));
+    }
+
+    /// Writes the backend has not acknowledged yet, oldest first.
+    pub fn in_flight(&self) -> impl Iterator<Item = &InFlightWrite> {
+        self.in_flight.pending.values()
     }
 
     pub async fn next(&mut self) -> Option<StorageOutput<T>> {
         loop {
             match self.tasks.join_next().await? {
-                Ok(Some(output)) => return Some(output),
-                Ok(None) | Err(_) => continue,
+                Ok((seq, output)) => {
+                    self.in_flight.complete(seq);
+                    if let Some(output) = output {
+                        return Some(output);
+                    }
+                },
+                Err(_) => continue,
             }
         }
     }
@@ -388,24 +458,37 @@ impl<T: NodeType, S: NewProtocolStorage<T>> Storage<T, S> {
     pub fn gc(&mut self, view_number: ViewNumber) {
         let keep = self.handles.split_off(&view_number);
         for handles in self.handles.values() {
-            for handle in handles {
+            for (seq, handle) in handles {
                 handle.abort();
+                self.in_flight.complete(*seq);
             }
         }
         self.handles = keep;
     }
 
-    /// Wait for all current storage writes to complete.
+    /// Wait for all in-flight writes to complete, then ask the backend to
+    /// sync them.
     pub async fn flush(mut self) {
         info!(
-            tasks = self.tasks.len(),
-            "flushing storage tasks during shutdown"
+            pending = self.in_flight.pending.len(),
+            "flushing in-flight storage writes during shutdown"
         );
         while let Some(result) = self.tasks.join_next().await {
-            if let Err(err) = result {
-                warn!(%err, "storage task failed during shutdown");
+            match result {
+                Ok((seq, _)) => self.in_flight.complete(seq),
+                Err(err) => warn!(%err, "storage task failed during shutdown"),
             }
         }
+        if let Some(write) = self.in_flight.pending.values().next() {
+            warn!(
+                ?write,
+                pending = self.in_flight.pending.len(),
+                "in-flight writes lost"
+            );
+        }
+        if let Err(err) = self.storage.sync().await {
+            warn!(%err, "failed to sync storage during shutdown");
+        }
         info!("storage flush complete");
     }
 }
@@ -1,8 +1,10 @@
 pub(crate) mod common;
 
 mod block;
+mod byzantine;
 mod cliquenet;
 mod consensus;
+mod crash;
 mod cutover;
 mod epoch_change;
 mod failures;
@@ -10,6 +12,7 @@ mod integration;
 mod legacy_cutover;
 mod restarts;
 mod safety;
+mod sim;
 mod stake_table_changes;
 mod state;
 mod storage;
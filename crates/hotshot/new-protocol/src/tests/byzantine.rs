@@ -0,0 +1,107 @@
+//! Byzantine minorities against honest majorities.
+//!
+//! Every adversary runs on `f` nodes of committees of `3f + 1`, over the
+//! deterministic network simulator. The honest nodes must keep deciding and
+//! never decide different leaves, while every adversary must actually have
+//! deviated from the protocol. A failing run reports its seed; replay it
+//! with `SIM_SEED=<seed> cargo test -p hotshot-new-protocol byzantine_`.
+
+use std::collections::BTreeMap;
+
+use crate::tests::common::{
+    byzantine::{
+        Adversary, DoubleVoter, EpochBoundaryTamperer, EquivocatingLeader, StaleCertReplayer,
+        WithholdingDisperser,
+    },
+    sim::{SimReport, Simulation},
+};
+
+/// Fail unless every adversary deviated from the protocol at least once.
+fn assert_deviated(report: &SimReport, context: &str) {
+    for (node, deviations) in &report.deviations {
+        assert!(
+            *deviations > 0,
+            "{context}: adversary of node {node} never deviated"
+        );
+    }
+}
+
+/// Committee sizes and their byzantine nodes. Leaders rotate through all
+/// nodes, so every adversary leads some views.
+const COMMITTEES: [(usize, &[usize]); 2] = [(4, &[1]), (7, &[2, 5])];
+
+/// Run `make`'s adversary on the byzantine nodes of every committee.
+async fn against_honest_majorities(
+    seed: u64,
+    epoch_height: u64,
+    target_decisions: usize,
+    make: impl Fn(usize, usize) -> Box<dyn Adversary>,
+) {
+    for (num_nodes, byzantine) in COMMITTEES {
+        let adversaries: BTreeMap<_, _> =
+            byzantine.iter().map(|&i| (i, make(i, num_nodes))).collect();
+        let context = format!("{num_nodes} nodes, byzantine {byzantine:?}");
+        let report = Simulation::builder()
+            .seed(Simulation::seed_from_env(seed))
+            .num_nodes(num_nodes)
+            .epoch_height(epoch_height)
+            .target_decisions(target_decisions)
+            .adversaries(adversaries)
+            .build()
+            .run()
+            .await
+            .unwrap_or_else(|err| panic!("{context}: {err}"));
+        assert_deviated(&report, &context);
+    }
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn byzantine_equivocating_leader() {
+    against_honest_majorities(10, 100, 10, |_, _| Box::new(EquivocatingLeader)).await
+}
+
+/// Each adversary withholds shares from the `f` nodes after it.
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn byzantine_withholding_disperser() {
+    against_honest_majorities(11, 100, 10, |i, n| {
+        let f = (n - 1) / 3;
+        Box::new(WithholdingDisperser::new((1..=f).map(|k| (i + k) % n)))
+    })
+    .await
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn byzantine_double_voter() {
+    against_honest_majorities(12, 100, 10, |_, _| Box::<DoubleVoter>::default()).await
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn byzantine_stale_certificates() {
+    against_honest_majorities(13, 100, 10, |_, _| Box::<StaleCertReplayer>::default()).await
+}
+
+/// Crosses two epoch boundaries.
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn byzantine_epoch_boundary() {
+    against_honest_majorities(14, 10, 25, |_, _| Box::<EpochBoundaryTamperer>::default()).await
+}
+
+/// A leader equivocates and a second byzantine node votes for both of its
+/// proposals, which is as close as `f` nodes get to two quorums.
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn byzantine_colluding_equivocation() {
+    let adversaries: BTreeMap<usize, Box<dyn Adversary>> = BTreeMap::from([
+        (2, Box::new(EquivocatingLeader) as Box<dyn Adversary>),
+        (5, Box::<DoubleVoter>::default()),
+    ]);
+    let report = Simulation::builder()
+        .seed(Simulation::seed_from_env(15))
+        .num_nodes(7)
+        .target_decisions(15)
+        .adversaries(adversaries)
+        .build()
+        .run()
+        .await
+        .unwrap_or_else(|err| panic!("{err}"));
+    assert_deviated(&report, "colluding equivocation");
+}
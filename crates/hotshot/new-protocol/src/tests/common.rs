@@ -1,8 +1,11 @@
 pub(crate) mod assertions;
+pub(crate) mod byzantine;
 pub(crate) mod coordinator_builder;
+pub(crate) mod crash;
 pub(crate) mod harness;
 pub(crate) mod mock;
 pub(crate) mod runner;
+pub(crate) mod sim;
 pub(crate) mod utils;
 
 use std::collections::BTreeSet;
@@ -0,0 +1,580 @@
+//! Byzantine behaviour for simulated new-protocol nodes.
+//!
+//! A byzantine node runs an honest coordinator. Its [`Adversary`] sits
+//! between that coordinator and the simulated network: it sees every message
+//! delivered to the node and rewrites every message the node sends. Holding
+//! the node's signing key, it can drop, redirect and forge whatever the node
+//! could sign itself.
+//!
+//! The coordinator never learns what was sent on its behalf, so what a
+//! byzantine node decides is meaningless and [`Simulation`] only checks the
+//! honest nodes. Adversaries count their deviations, so tests can check that
+//! the behaviour they exercise actually occurred.
+//!
+//! [`Simulation`]: super::sim::Simulation
+
+use std::{
+    cell::Cell,
+    collections::{BTreeMap, BTreeSet},
+    marker::PhantomData,
+};
+
+use committable::Commitment;
+use hotshot::types::BLSPubKey;
+use hotshot_example_types::node_types::TestTypes;
+use hotshot_types::{
+    data::{Leaf2, ViewNumber},
+    message::{Proposal as SignedProposal, UpgradeLock},
+    simple_vote::{QuorumData2, SimpleVote, Vote2Data, Voteable},
+    traits::signature_key::SignatureKey,
+    vote::HasViewNumber,
+};
+use tracing::warn;
+
+use crate::{
+    helpers::proposal_commitment,
+    message::{
+        CatchupEvidence, Certificate1, Certificate2, ConsensusMessage, EpochChangeMessage, Message,
+        MessageType, Proposal, ProposalMessage, Unchecked, Validated, Vote1,
+    },
+};
+
+/// A message and the nodes it goes to.
+pub(crate) type Envelope = (Vec<usize>, Message<TestTypes, Validated>);
+
+/// Deviation of a node from the protocol.
+pub(crate) trait Adversary {
+    /// A message was delivered to the node.
+    fn observe(&mut self, _ctx: &NodeContext, _msg: &Message<TestTypes, Unchecked>) {}
+
+    /// The node sends `msg` to `to`. Returns what goes out instead.
+    fn send(
+        &mut self,
+        ctx: &NodeContext,
+        msg: Message<TestTypes, Validated>,
+        to: Vec<usize>,
+    ) -> Vec<Envelope>;
+}
+
+/// Identity and keys of the node an adversary controls.
+pub(crate) struct NodeContext {
+    pub idx: usize,
+    pub public_key: BLSPubKey,
+    private_key: <BLSPubKey as SignatureKey>::PrivateKey,
+    upgrade_lock: UpgradeLock<TestTypes>,
+    deviations: Cell<u64>,
+}
+
+impl NodeContext {
+    fn new(idx: usize, upgrade_lock: UpgradeLock<TestTypes>) -> Self {
+        let (public_key, private_key) =
+            BLSPubKey::generated_from_seed_indexed([0u8; 32], idx as u64);
+        Self {
+            idx,
+            public_key,
+            private_key,
+            upgrade_lock,
+            deviations: Cell::new(0),
+        }
+    }
+
+    /// Record that the adversary altered, dropped or forged a message.
+    pub fn deviate(&self) {
+        self.deviations.set(self.deviations.get() + 1)
+    }
+
+    /// Wrap a consensus message as sent by this node.
+    pub fn message(
+        &self,
+        msg: ConsensusMessage<TestTypes, Validated>,
+    ) -> Message<TestTypes, Validated> {
+        Message {
+            sender: self.public_key,
+            message_type: MessageType::Consensus(msg),
+        }
+    }
+
+    pub fn sign_proposal(
+        &self,
+        proposal: Proposal<TestTypes>,
+    ) -> SignedProposal<TestTypes, Proposal<TestTypes>> {
+        let signature = BLSPubKey::sign(&self.private_key, proposal_commitment(&proposal).as_ref())
+            .expect("sign proposal");
+        SignedProposal {
+            data: proposal,
+            signature,
+            _pd: PhantomData,
+        }
+    }
+
+    pub fn sign_vote<DATA>(&self, data: DATA, view: ViewNumber) -> SimpleVote<TestTypes, DATA>
+    where
+        DATA: Voteable<TestTypes> + 'static,
+    {
+        SimpleVote::create_signed_vote(
+            data,
+            view,
+            &self.public_key,
+            &self.private_key,
+            &self.upgrade_lock,
+        )
+        .expect("sign vote")
+    }
+}
+
+/// An adversary attached to a simulated node, working on the wire format.
+pub(crate) struct Byzantine {
+    ctx: NodeContext,
+    adversary: Box<dyn Adversary>,
+}
+
+impl Byzantine {
+    pub fn new(
+        idx: usize,
+        upgrade_lock: UpgradeLock<TestTypes>,
+        adversary: Box<dyn Adversary>,
+    ) -> Self {
+        Self {
+            ctx: NodeContext::new(idx, upgrade_lock),
+            adversary,
+        }
+    }
+
+    /// Number of times the adversary deviated from the protocol so far.
+    pub fn deviations(&self) -> u64 {
+        self.ctx.deviations.get()
+    }
+
+    /// Let the adversary see a message delivered to its node.
+    pub fn receive(&mut self, bytes: &[u8]) {
+        if let Some(msg) = self.decode(bytes) {
+            self.adversary.observe(&self.ctx, &msg)
+        }
+    }
+
+    /// Pass a message the node sends through the adversary.
+    ///
+    /// Messages that do not decode are not consensus traffic and go out
+    /// unchanged.
+    pub fn send<B>(&mut self, to: Vec<usize>, bytes: B) -> Vec<(Vec<usize>, B)>
+    where
+        B: AsRef<[u8]> + From<Vec<u8>>,
+    {
+        let Some(msg) = self.decode(bytes.as_ref()) else {
+            return vec![(to, bytes)];
+        };
+        self.adversary
+            .send(&self.ctx, vouch(msg), to)
+            .into_iter()
+            .filter_map(|(to, msg)| match self.ctx.upgrade_lock.serialize(&msg) {
+                Ok(bytes) => Some((to, bytes.into())),
+                Err(err) => {
+                    warn!(node = self.ctx.idx, %err, "failed to serialize forged message");
+                    None
+                },
+            })
+            .collect()
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Option<Message<TestTypes, Unchecked>> {
+        let (msg, _) = self
+            .ctx
+            .upgrade_lock
+            .deserialize::<Message<TestTypes, Unchecked>>(bytes)
+            .ok()?;
+        (!msg.is_external()).then_some(msg)
+    }
+}
+
+/// Mark a decoded message as validated so it can be sent again.
+///
+/// The adversary vouches for everything its node sends, forged or not.
+fn vouch(msg: Message<TestTypes, Unchecked>) -> Message<TestTypes, Validated> {
+    let message_type = match msg.message_type {
+        MessageType::Consensus(m) => MessageType::Consensus(match m {
+            ConsensusMessage::Proposal(p) => {
+                ConsensusMessage::Proposal(ProposalMessage::validated(p.proposal))
+            },
+            ConsensusMessage::Vote1(v) => ConsensusMessage::Vote1(v),
+            ConsensusMessage::Vote2(v) => ConsensusMessage::Vote2(v),
+            ConsensusMessage::Certificate1(c, k) => ConsensusMessage::Certificate1(c, k),
+            ConsensusMessage::Certificate2(c, k) => ConsensusMessage::Certificate2(c, k),
+            ConsensusMessage::TimeoutVote(v) => ConsensusMessage::TimeoutVote(v),
+            ConsensusMessage::TimeoutCertificate(c) => ConsensusMessage::TimeoutCertificate(c),
+            ConsensusMessage::EpochChange(e) => ConsensusMessage::EpochChange(e.into_validated()),
+            ConsensusMessage::VidShareFragment(f) => ConsensusMessage::VidShareFragment(f),
+            ConsensusMessage::VidShareBroadcast(s) => ConsensusMessage::VidShareBroadcast(s),
+            ConsensusMessage::HighQc(c) => ConsensusMessage::HighQc(c),
+        }),
+        MessageType::Block(b) => MessageType::Block(b),
+        MessageType::ProposalFetch(f) => MessageType::ProposalFetch(f),
+        MessageType::External(d) => MessageType::External(d),
+    };
+    Message {
+        sender: msg.sender,
+        message_type,
+    }
+}
+
+/// Split recipients into two halves of (almost) equal size.
+fn halves(mut to: Vec<usize>) -> (Vec<usize>, Vec<usize>) {
+    to.sort_unstable();
+    let rest = to.split_off(to.len() / 2);
+    (to, rest)
+}
+
+/// A proposal for the same view and parent that differs from `proposal`.
+fn twin(proposal: &Proposal<TestTypes>) -> Proposal<TestTypes> {
+    let mut twin = proposal.clone();
+    twin.block_header.random = twin.block_header.random.wrapping_add(1);
+    twin
+}
+
+/// Number of views adversaries remember what they have seen for.
+const MEMORY: usize = 32;
+
+fn remember<V>(map: &mut BTreeMap<ViewNumber, V>, view: ViewNumber, value: V) {
+    map.insert(view, value);
+    while map.len() > MEMORY {
+        map.pop_first();
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Adversaries
+// ---------------------------------------------------------------------------
+
+/// Leader that sends its proposal to half of the committee and a conflicting
+/// proposal for the same view and parent to the other half.
+pub(crate) struct EquivocatingLeader;
+
+impl Adversary for EquivocatingLeader {
+    fn send(
+        &mut self,
+        ctx: &NodeContext,
+        msg: Message<TestTypes, Validated>,
+        to: Vec<usize>,
+    ) -> Vec<Envelope> {
+        let MessageType::Consensus(ConsensusMessage::Proposal(p)) = &msg.message_type else {
+            return vec![(to, msg)];
+        };
+        let forged = ProposalMessage::validated(ctx.sign_proposal(twin(&p.proposal.data)));
+        let forged = ctx.message(ConsensusMessage::Proposal(forged));
+        ctx.deviate();
+        let (a, b) = halves(to);
+        vec![(a, msg), (b, forged)]
+    }
+}
+
+/// Leader that never sends VID shares to `victims` and never broadcasts its
+/// own share.
+///
+/// The victims receive proposals they cannot pair with a share, so they
+/// cannot vote for them.
+pub(crate) struct WithholdingDisperser {
+    victims: BTreeSet<usize>,
+}
+
+impl WithholdingDisperser {
+    pub fn new(victims: impl IntoIterator<Item = usize>) -> Self {
+        Self {
+            victims: victims.into_iter().collect(),
+        }
+    }
+}
+
+impl Adversary for WithholdingDisperser {
+    fn send(
+        &mut self,
+        ctx: &NodeContext,
+        msg: Message<TestTypes, Validated>,
+        to: Vec<usize>,
+    ) -> Vec<Envelope> {
+        match &msg.message_type {
+            MessageType::Consensus(ConsensusMessage::VidShareFragment(_)) => {
+                let kept: Vec<_> = to
+                    .iter()
+                    .copied()
+                    .filter(|i| !self.victims.contains(i))
+                    .collect();
+                if kept.len() < to.len() {
+                    ctx.deviate();
+                }
+                vec![(kept, msg)]
+            },
+            MessageType::Consensus(ConsensusMessage::VidShareBroadcast(_)) => {
+                ctx.deviate();
+                Vec::new()
+            },
+            _ => vec![(to, msg)],
+        }
+    }
+}
+
+/// Voter that signs both phases for every proposal it has seen in a view,
+/// and for a forged one when it has seen only the one it votes for.
+///
+/// Paired with an [`EquivocatingLeader`] it backs both sides of the
+/// equivocation.
+#[derive(Default)]
+pub(crate) struct DoubleVoter {
+    proposals: BTreeMap<ViewNumber, Vec<Proposal<TestTypes>>>,
+}
+
+impl DoubleVoter {
+    fn record(&mut self, proposal: &Proposal<TestTypes>) {
+        let view = proposal.view_number;
+        let mut seen = self.proposals.remove(&view).unwrap_or_default();
+        if !seen.contains(proposal) {
+            seen.push(proposal.clone());
+        }
+        remember(&mut self.proposals, view, seen);
+    }
+
+    /// Leaves in `view` other than `voted`.
+    fn alternatives(
+        &self,
+        view: ViewNumber,
+        voted: Commitment<Leaf2<TestTypes>>,
+    ) -> Vec<Commitment<Leaf2<TestTypes>>> {
+        let seen = self
+            .proposals
+            .get(&view)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let others: Vec<_> = seen
+            .iter()
+            .map(proposal_commitment)
+            .filter(|c| *c != voted)
+            .collect();
+        if !others.is_empty() {
+            return others;
+        }
+        seen.iter()
+            .find(|p| proposal_commitment(p) == voted)
+            .map(|p| proposal_commitment(&twin(p)))
+            .into_iter()
+            .collect()
+    }
+}
+
+impl Adversary for DoubleVoter {
+    fn observe(&mut self, _: &NodeContext, msg: &Message<TestTypes, Unchecked>) {
+        if let MessageType::Consensus(ConsensusMessage::Proposal(p)) = &msg.message_type {
+            self.record(&p.proposal.data)
+        }
+    }
+
+    fn send(
+        &mut self,
+        ctx: &NodeContext,
+        msg: Message<TestTypes, Validated>,
+        to: Vec<usize>,
+    ) -> Vec<Envelope> {
+        let extra: Vec<_> = match &msg.message_type {
+            MessageType::Consensus(ConsensusMessage::Proposal(p)) => {
+                self.record(&p.proposal.data);
+                Vec::new()
+            },
+            MessageType::Consensus(ConsensusMessage::Vote1(v)) => {
+                let view = v.vote.view_number();
+                self.alternatives(view, v.vote.data.leaf_commit)
+                    .into_iter()
+                    .map(|leaf_commit| {
+                        let data = QuorumData2 {
+                            leaf_commit,
+                            ..v.vote.data.clone()
+                        };
+                        ConsensusMessage::Vote1(Vote1 {
+                            vote: ctx.sign_vote(data, view),
+                            state_vote: v.state_vote.clone(),
+                        })
+                    })
+                    .collect()
+            },
+            MessageType::Consensus(ConsensusMessage::Vote2(v)) => {
+                let view = v.view_number();
+                self.alternatives(view, v.data.leaf_commit)
+                    .into_iter()
+                    .map(|leaf_commit| {
+                        let data = Vote2Data {
+                            leaf_commit,
+                            ..v.data.clone()
+                        };
+                        ConsensusMessage::Vote2(ctx.sign_vote(data, view))
+                    })
+                    .collect()
+            },
+            _ => Vec::new(),
+        };
+        if !extra.is_empty() {
+            ctx.deviate();
+        }
+        let mut out = vec![(to.clone(), msg)];
+        out.extend(extra.into_iter().map(|m| (to.clone(), ctx.message(m))));
+        out
+    }
+}
+
+/// Node that replays old certificates.
+///
+/// As leader it justifies its proposals with an outdated QC, its timeout
+/// votes carry outdated evidence, and with every phase-1 vote it
+/// re-broadcasts an outdated QC as both a certificate and a high QC.
+#[derive(Default)]
+pub(crate) struct StaleCertReplayer {
+    certs: BTreeMap<ViewNumber, Certificate1<TestTypes>>,
+}
+
+impl StaleCertReplayer {
+    /// How many certified views the replayed QC lags behind.
+    const LAG: usize = 3;
+
+    fn record(&mut self, cert: &Certificate1<TestTypes>) {
+        remember(&mut self.certs, cert.view_number(), cert.clone())
+    }
+
+    fn record_from<S>(&mut self, msg: &Message<TestTypes, S>) {
+        let MessageType::Consensus(msg) = &msg.message_type else {
+            return;
+        };
+        match msg {
+            ConsensusMessage::Proposal(p) => self.record(&p.proposal.data.justify_qc),
+            ConsensusMessage::Certificate1(c, _) | ConsensusMessage::HighQc(c) => self.record(c),
+            ConsensusMessage::TimeoutVote(v) => {
+                if let Some(CatchupEvidence::Qc(c)) = &v.evidence {
+                    self.record(c)
+                }
+            },
+            _ => {},
+        }
+    }
+
+    fn stale(&self) -> Option<Certificate1<TestTypes>> {
+        self.certs.values().rev().nth(Self::LAG).cloned()
+    }
+}
+
+impl Adversary for StaleCertReplayer {
+    fn observe(&mut self, _: &NodeContext, msg: &Message<TestTypes, Unchecked>) {
+        self.record_from(msg)
+    }
+
+    fn send(
+        &mut self,
+        ctx: &NodeContext,
+        mut msg: Message<TestTypes, Validated>,
+        to: Vec<usize>,
+    ) -> Vec<Envelope> {
+        self.record_from(&msg);
+        let Some(stale) = self.stale() else {
+            return vec![(to, msg)];
+        };
+        let MessageType::Consensus(inner) = &mut msg.message_type else {
+            return vec![(to, msg)];
+        };
+        match inner {
+            ConsensusMessage::Proposal(p)
+                if stale.view_number() < p.proposal.data.justify_qc.view_number() =>
+            {
+                let mut data = p.proposal.data.clone();
+                data.justify_qc = stale;
+                let forged = ProposalMessage::validated(ctx.sign_proposal(data));
+                ctx.deviate();
+                vec![(to, ctx.message(ConsensusMessage::Proposal(forged)))]
+            },
+            ConsensusMessage::TimeoutVote(v) => {
+                v.evidence = Some(CatchupEvidence::Qc(stale));
+                ctx.deviate();
+                vec![(to, msg)]
+            },
+            ConsensusMessage::Vote1(_) => {
+                ctx.deviate();
+                let cert = ConsensusMessage::Certificate1(stale.clone(), ctx.public_key);
+                let high = ConsensusMessage::HighQc(stale);
+                vec![
+                    (to.clone(), msg),
+                    (to.clone(), ctx.message(cert)),
+                    (to, ctx.message(high)),
+                ]
+            },
+            _ => vec![(to, msg)],
+        }
+    }
+}
+
+/// Node that misbehaves where epochs change.
+///
+/// Its phase-1 votes on epoch roots lack the light client state vote. Half of
+/// the committee receives its epoch change messages with an unrelated Cert2,
+/// and with each new epoch change message it replays the previous one.
+#[derive(Default)]
+pub(crate) struct EpochBoundaryTamperer {
+    certs2: BTreeMap<ViewNumber, Certificate2<TestTypes>>,
+    last_change: Option<EpochChangeMessage<TestTypes, Validated>>,
+}
+
+impl EpochBoundaryTamperer {
+    fn record(&mut self, cert: &Certificate2<TestTypes>) {
+        remember(&mut self.certs2, cert.view_number(), cert.clone())
+    }
+}
+
+impl Adversary for EpochBoundaryTamperer {
+    fn observe(&mut self, _: &NodeContext, msg: &Message<TestTypes, Unchecked>) {
+        if let MessageType::Consensus(ConsensusMessage::Certificate2(c, _)) = &msg.message_type {
+            self.record(c)
+        }
+    }
+
+    fn send(
+        &mut self,
+        ctx: &NodeContext,
+        msg: Message<TestTypes, Validated>,
+        to: Vec<usize>,
+    ) -> Vec<Envelope> {
+        let MessageType::Consensus(inner) = &msg.message_type else {
+            return vec![(to, msg)];
+        };
+        match inner {
+            ConsensusMessage::Vote1(v) if v.state_vote.is_some() => {
+                let vote = Vote1 {
+                    vote: v.vote.clone(),
+                    state_vote: None,
+                };
+                ctx.deviate();
+                vec![(to, ctx.message(ConsensusMessage::Vote1(vote)))]
+            },
+            ConsensusMessage::Certificate2(c, _) => {
+                self.record(c);
+                vec![(to, msg)]
+            },
+            ConsensusMessage::EpochChange(change) => {
+                let change = change.clone();
+                let mut out = Vec::new();
+                if let Some(prev) = self.last_change.replace(change.clone()) {
+                    ctx.deviate();
+                    out.push((to.clone(), ctx.message(ConsensusMessage::EpochChange(prev))));
+                }
+                let unrelated = self
+                    .certs2
+                    .range(..change.cert2.view_number())
+                    .next_back()
+                    .map(|(_, c)| c.clone());
+                match unrelated {
+                    Some(cert2) => {
+                        let forged =
+                            EpochChangeMessage::validated(change.cert1, cert2, change.proposal);
+                        ctx.deviate();
+                        let (a, b) = halves(to);
+                        out.push((a, ctx.message(ConsensusMessage::EpochChange(forged))));
+                        out.push((b, msg));
+                    },
+                    None => out.push((to, msg)),
+                }
+                out
+            },
+            _ => vec![(to, msg)],
+        }
+    }
+}
@@ -32,6 +32,7 @@ use crate::{
     outbox::Outbox,
     proposal::{ProposalValidator, VidShareValidator},
     state::StateManager,
+    storage::NewProtocolStorage,
     vid::{VidDisperser, VidReconstructor},
     vote::VoteCollector,
 };
@@ -47,6 +48,36 @@ pub async fn build_test_coordinator(
     view_timeout: Duration,
     pre_cutover_seed: Option<PreCutoverSeed<TestTypes>>,
 ) -> Coordinator<TestTypes, TestStorage<TestTypes>> {
+    build_coordinator(
+        node_index,
+        network,
+        membership,
+        storage.clone(),
+        storage,
+        None,
+        client,
+        epoch_height,
+        view_timeout,
+        pre_cutover_seed,
+    )
+    .await
+}
+
+/// Build a coordinator that writes through `storage` and resumes from what
+/// `durable` holds, additionally locked on `locked_qc` if given.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn build_coordinator<S: NewProtocolStorage<TestTypes>>(
+    node_index: u64,
+    network: Cliquenet<TestTypes>,
+    membership: EpochMembershipCoordinator<TestTypes>,
+    durable: TestStorage<TestTypes>,
+    storage: S,
+    locked_qc: Option<Certificate1<TestTypes>>,
+    client: CoordinatorClient<TestTypes>,
+    epoch_height: u64,
+    view_timeout: Duration,
+    pre_cutover_seed: Option<PreCutoverSeed<TestTypes>>,
+) -> Coordinator<TestTypes, S> {
     let (public_key, private_key) = BLSPubKey::generated_from_seed_indexed([0; 32], node_index);
     let state_key_pair = StateKeyPair::generate_from_seed_indexed([0u8; 32], node_index);
     let state_private_key = state_key_pair.sign_key_ref().clone();
@@ -69,7 +100,7 @@ pub async fn build_test_coordinator(
     // decided anchor (mirroring production's `Coordinator::maker`, which
     // gets the anchor from the `HotShotInitializer`); otherwise it starts
     // from genesis.
-    let restart_anchor = storage.anchor_leaf().await;
+    let restart_anchor = durable.anchor_leaf().await;
     let initial_leaf = restart_anchor
         .as_ref()
         .map(|(leaf, _)| leaf.clone())
@@ -159,7 +190,7 @@ pub async fn build_test_coordinator(
         state_manager.seed_state(anchor_view, Arc::new(anchor_state), anchor_leaf.clone());
         let reconstructed = reconstructed_blocks(
             std::iter::once((anchor_view, anchor_leaf.block_header().clone())).chain(
-                storage
+                durable
                     .proposals_cloned()
                     .await
                     .into_iter()
@@ -169,7 +200,7 @@ pub async fn build_test_coordinator(
         // Seed persisted proposals before `seed_parent` so its authoritative
         // anchor wins (mirrors `Coordinator::maker`).
         consensus.seed_proposals(
-            storage
+            durable
                 .proposals_cloned()
                 .await
                 .into_values()
@@ -200,15 +231,19 @@ pub async fn build_test_coordinator(
         consensus.apply_pre_cutover_seed(seed);
     }
 
+    if let Some(locked_qc) = locked_qc {
+        consensus.seed_locked_cert(locked_qc);
+    }
+
     // Restarted nodes must not act again in views they acted in before.
-    let restart_view = storage.restart_view().await;
-    let last_actioned_view = storage.last_actioned_view().await;
+    let restart_view = durable.restart_view().await;
+    let last_actioned_view = durable.last_actioned_view().await;
     consensus.resume_from_restart(anchor_view, restart_view, last_actioned_view);
 
     // A leader proposing on an epoch-root parent QC right after restart
     // needs the persisted light-client state cert (as in production, where
     // it arrives via `HotShotInitializer::state_cert`).
-    if let Some(state_cert) = storage.state_cert_cloned().await {
+    if let Some(state_cert) = durable.state_cert_cloned().await {
         consensus.seed_state_cert(state_cert);
     }
 
@@ -230,7 +265,7 @@ pub async fn build_test_coordinator(
         signature: BLSPubKey::sign(&private_key, &[]).expect("sign genesis"),
         _pd: PhantomData,
     };
-    storage
+    durable
         .append_proposal_wrapper(&genesis_signed)
         .await
         .expect("seed genesis proposal");
@@ -0,0 +1,450 @@
+//! Crash injection for new-protocol storage.
+//!
+//! [`CrashStorage`] sits in front of a node's [`TestStorage`], which plays the
+//! disk. It journals every persistence step the node takes and can kill the
+//! node at one of them, either before the write lands or after it landed but
+//! before the node learns it did. From then on nothing the node writes reaches
+//! the disk and no write completes, so the node can act on nothing it has not
+//! durably written. Restarting the node from the same [`TestStorage`] shows
+//! what it remembers.
+//!
+//! `TestStorage` applies each write atomically, so a crash before or after each
+//! write also covers a crash anywhere in between. The node's [`Storage`] runs
+//! its writes concurrently: every write yields once before it lands, so writes
+//! issued together are in flight together, and a crash can let a later write
+//! of such a batch land while the earlier ones are lost.
+//!
+//! A node can also be stopped gracefully at a step, after which it flushes
+//! its in-flight storage writes and ends with an [`Op::Flush`] step, which
+//! crash points can hit like any other.
+//!
+//! [`Storage`]: crate::storage::Storage
+
+use std::{future::Future, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use hotshot_example_types::{node_types::TestTypes, storage_types::TestStorage};
+use hotshot_types::{
+    data::{
+        DaProposal, DaProposal2, EpochNumber, Leaf2, QuorumProposal, QuorumProposal2,
+        QuorumProposalWrapper, VidCommitment, VidDisperseShare, ViewNumber,
+    },
+    drb::{DrbInput, DrbResult},
+    epoch_membership::EpochMembershipCoordinator,
+    event::HotShotAction,
+    message::Proposal as SignedProposal,
+    simple_certificate::{
+        LightClientStateUpdateCertificateV2, NextEpochQuorumCertificate2, QuorumCertificate,
+        QuorumCertificate2, UpgradeCertificate,
+    },
+    traits::{node_implementation::NodeType, storage::Storage as StorageTrait},
+    vote::HasViewNumber,
+};
+use parking_lot::Mutex;
+
+use super::coordinator_builder::build_coordinator;
+use crate::{
+    client::CoordinatorClient,
+    coordinator::Coordinator,
+    message::{Certificate1, Certificate2},
+    network::Cliquenet,
+    storage::NewProtocolStorage,
+};
+
+/// Storage behind a test coordinator.
+#[async_trait]
+pub(crate) trait TestBackend: NewProtocolStorage<TestTypes> {
+    /// The state a restarted node resumes from.
+    fn durable(&self) -> &TestStorage<TestTypes>;
+
+    /// Persist the newest decided leaf and the QC certifying it, as the
+    /// application's persistence layer does on decide.
+    async fn persist_anchor(&self, leaf: Leaf2<TestTypes>, qc: Certificate1<TestTypes>);
+}
+
+#[async_trait]
+impl TestBackend for TestStorage<TestTypes> {
+    fn durable(&self) -> &TestStorage<TestTypes> {
+        self
+    }
+
+    async fn persist_anchor(&self, leaf: Leaf2<TestTypes>, qc: Certificate1<TestTypes>) {
+        self.update_anchor_leaf(leaf, qc).await
+    }
+}
+
+/// What a persistence step writes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Op {
+    Vid,
+    Da,
+    Proposal,
+    Action(HotShotAction),
+    HighQc,
+    Cert2,
+    StateCert,
+    Anchor,
+    /// DRB results, epoch roots and other epoch data.
+    Epoch,
+    /// The sync at the end of a graceful shutdown.
+    Flush,
+}
+
+/// One persistence step of a node, in the order the node started them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Step {
+    pub op: Op,
+    pub view: Option<ViewNumber>,
+}
+
+/// Where in a persistence step the node dies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Side {
+    /// The write is lost.
+    Before,
+    /// The write is durable, but the node never learns it completed.
+    After,
+    /// The write lands ahead of the node's earlier writes still in flight,
+    /// which are lost, and the node never learns it completed.
+    Reordered,
+}
+
+/// The step a node dies at, counted from 0 in the node's journal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct CrashPoint {
+    pub step: usize,
+    pub side: Side,
+}
+
+/// Storage that journals persistence steps and can crash at one of them.
+#[derive(Clone)]
+pub(crate) struct CrashStorage {
+    durable: TestStorage<TestTypes>,
+    state: Arc<Mutex<State>>,
+}
+
+struct State {
+    journal: Vec<Step>,
+    crash_at: Option<CrashPoint>,
+    crashed: bool,
+    on_crash: Option<Box<dyn FnOnce() + Send>>,
+    stop_at: Option<usize>,
+    on_stop: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl CrashStorage {
+    /// Journal the steps written to `durable` and die at `crash_at`, if any.
+    ///
+    /// `on_crash` is called once, when the node dies, and should make sure
+    /// the node stops.
+    pub fn new(
+        durable: TestStorage<TestTypes>,
+        crash_at: Option<CrashPoint>,
+        on_crash: impl FnOnce() + Send + 'static,
+    ) -> Self {
+        Self {
+            durable,
+            state: Arc::new(Mutex::new(State {
+                journal: Vec::new(),
+                crash_at,
+                crashed: false,
+                on_crash: Some(Box::new(on_crash)),
+                stop_at: None,
+                on_stop: None,
+            })),
+        }
+    }
+
+    /// Once step `step` has landed, call `on_stop`, which should stop the
+    /// node gracefully.
+    pub fn stop_at(self, step: usize, on_stop: impl FnOnce() + Send + 'static) -> Self {
+        {
+            let mut state = self.state.lock();
+            state.stop_at = Some(step);
+            state.on_stop = Some(Box::new(on_stop));
+        }
+        self
+    }
+
+    /// Steps started so far.
+    pub fn journal(&self) -> Vec<Step> {
+        self.state.lock().journal.clone()
+    }
+
+    /// Whether the node died at its crash point.
+    pub fn crashed(&self) -> bool {
+        self.state.lock().crashed
+    }
+
+    /// Run one persistence step. Never completes once the node is dead.
+    async fn step<F>(&self, op: Op, view: Option<ViewNumber>, write: F) -> Result<()>
+    where
+        F: Future<Output = Result<()>> + Send,
+    {
+        let step = {
+            let mut state = self.state.lock();
+            if state.crashed {
+                None
+            } else {
+                state.journal.push(Step { op, view });
+                Some(state.journal.len() - 1)
+            }
+        };
+        let Some(step) = step else {
+            return std::future::pending().await;
+        };
+        let point = |side| Some(CrashPoint { step, side });
+        if self.state.lock().crash_at == point(Side::Reordered) {
+            write.await?;
+            self.dies_at(step, Side::Reordered);
+            return std::future::pending().await;
+        }
+        // Let the writes issued together with this one start first.
+        tokio::task::yield_now().await;
+        if self.dies_at(step, Side::Before) {
+            return std::future::pending().await;
+        }
+        write.await?;
+        if self.dies_at(step, Side::After) {
+            return std::future::pending().await;
+        }
+        self.stops_at(step);
+        Ok(())
+    }
+
+    /// Stop the node gracefully if `step` is where it stops.
+    fn stops_at(&self, step: usize) {
+        let mut state = self.state.lock();
+        if state.stop_at == Some(step)
+            && let Some(on_stop) = state.on_stop.take()
+        {
+            on_stop()
+        }
+    }
+
+    /// Whether the node is dead at this point of `step`, crashing it if this
+    /// is its crash point.
+    fn dies_at(&self, step: usize, side: Side) -> bool {
+        let mut state = self.state.lock();
+        if !state.crashed && state.crash_at == Some(CrashPoint { step, side }) {
+            state.crashed = true;
+            if let Some(on_crash) = state.on_crash.take() {
+                on_crash()
+            }
+        }
+        state.crashed
+    }
+}
+
+#[async_trait]
+impl TestBackend for CrashStorage {
+    fn durable(&self) -> &TestStorage<TestTypes> {
+        &self.durable
+    }
+
+    async fn persist_anchor(&self, leaf: Leaf2<TestTypes>, qc: Certificate1<TestTypes>) {
+        let view = leaf.view_number();
+        let write = async {
+            self.durable.update_anchor_leaf(leaf, qc).await;
+            Ok(())
+        };
+        let _ = self.step(Op::Anchor, Some(view), write).await;
+    }
+}
+
+#[async_trait]
+impl StorageTrait<TestTypes> for CrashStorage {
+    async fn append_vid(
+        &self,
+        proposal: &SignedProposal<TestTypes, VidDisperseShare<TestTypes>>,
+    ) -> Result<()> {
+        let view = proposal.data.view_number();
+        self.step(Op::Vid, Some(view), self.durable.append_vid(proposal))
+            .await
+    }
+
+    async fn append_da(
+        &self,
+        proposal: &SignedProposal<TestTypes, DaProposal<TestTypes>>,
+        vid_commit: VidCommitment,
+    ) -> Result<()> {
+        let view = proposal.data.view_number;
+        let write = self.durable.append_da(proposal, vid_commit);
+        self.step(Op::Da, Some(view), write).await
+    }
+
+    async fn append_da2(
+        &self,
+        proposal: &SignedProposal<TestTypes, DaProposal2<TestTypes>>,
+        vid_commit: VidCommitment,
+    ) -> Result<()> {
+        let view = proposal.data.view_number;
+        let write = self.durable.append_da2(proposal, vid_commit);
+        self.step(Op::Da, Some(view), write).await
+    }
+
+    async fn append_proposal(
+        &self,
+        proposal: &SignedProposal<TestTypes, QuorumProposal<TestTypes>>,
+    ) -> Result<()> {
+        let view = proposal.data.view_number;
+        let write = self.durable.append_proposal(proposal);
+        self.step(Op::Proposal, Some(view), write).await
+    }
+
+    async fn append_proposal2(
+        &self,
+        proposal: &SignedProposal<TestTypes, QuorumProposal2<TestTypes>>,
+    ) -> Result<()> {
+        let view = proposal.data.view_number;
+        let write = self.durable.append_proposal2(proposal);
+        self.step(Op::Proposal, Some(view), write).await
+    }
+
+    async fn append_proposal_wrapper(
+        &self,
+        proposal: &SignedProposal<TestTypes, QuorumProposalWrapper<TestTypes>>,
+    ) -> Result<()> {
+        let view = proposal.data.view_number();
+        let write = self.durable.append_proposal_wrapper(proposal);
+        self.step(Op::Proposal, Some(view), write).await
+    }
+
+    async fn record_action(
+        &self,
+        view: ViewNumber,
+        epoch: Option<EpochNumber>,
+        action: HotShotAction,
+    ) -> Result<()> {
+        let write = self.durable.record_action(view, epoch, action);
+        self.step(Op::Action(action), Some(view), write).await
+    }
+
+    async fn update_high_qc(&self, high_qc: QuorumCertificate<TestTypes>) -> Result<()> {
+        let view = high_qc.view_number();
+        let write = self.durable.update_high_qc(high_qc);
+        self.step(Op::HighQc, Some(view), write).await
+    }
+
+    async fn update_high_qc2(&self, high_qc: QuorumCertificate2<TestTypes>) -> Result<()> {
+        let view = high_qc.view_number();
+        let write = self.durable.update_high_qc2(high_qc);
+        self.step(Op::HighQc, Some(view), write).await
+    }
+
+    async fn update_state_cert(
+        &self,
+        state_cert: LightClientStateUpdateCertificateV2<TestTypes>,
+    ) -> Result<()> {
+        let write = self.durable.update_state_cert(state_cert);
+        self.step(Op::StateCert, None, write).await
+    }
+
+    async fn update_next_epoch_high_qc2(
+        &self,
+        next_epoch_high_qc: NextEpochQuorumCertificate2<TestTypes>,
+    ) -> Result<()> {
+        let view = next_epoch_high_qc.view_number();
+        let write = self.durable.update_next_epoch_high_qc2(next_epoch_high_qc);
+        self.step(Op::Epoch, Some(view), write).await
+    }
+
+    async fn update_eqc(
+        &self,
+        high_qc: QuorumCertificate2<TestTypes>,
+        next_epoch_high_qc: NextEpochQuorumCertificate2<TestTypes>,
+    ) -> Result<()> {
+        let view = high_qc.view_number();
+        let write = self.durable.update_eqc(high_qc, next_epoch_high_qc);
+        self.step(Op::Epoch, Some(view), write).await
+    }
+
+    async fn update_decided_upgrade_certificate(
+        &self,
+        decided_upgrade_certificate: Option<UpgradeCertificate<TestTypes>>,
+    ) -> Result<()> {
+        let write = self
+            .durable
+            .update_decided_upgrade_certificate(decided_upgrade_certificate);
+        self.step(Op::Epoch, None, write).await
+    }
+
+    async fn store_drb_result(&self, epoch: EpochNumber, drb_result: DrbResult) -> Result<()> {
+        let write = self.durable.store_drb_result(epoch, drb_result);
+        self.step(Op::Epoch, None, write).await
+    }
+
+    async fn store_epoch_root(
+        &self,
+        epoch: EpochNumber,
+        block_header: <TestTypes as NodeType>::BlockHeader,
+    ) -> Result<()> {
+        let write = self.durable.store_epoch_root(epoch, block_header);
+        self.step(Op::Epoch, None, write).await
+    }
+
+    async fn load_drb_result(&self, epoch: EpochNumber) -> Result<DrbResult> {
+        self.durable.load_drb_result(epoch).await
+    }
+
+    async fn store_drb_input(&self, drb_input: DrbInput) -> Result<()> {
+        let write = self.durable.store_drb_input(drb_input);
+        self.step(Op::Epoch, None, write).await
+    }
+
+    async fn load_drb_input(&self, epoch: u64) -> Result<DrbInput> {
+        self.durable.load_drb_input(epoch).await
+    }
+}
+
+#[async_trait]
+impl NewProtocolStorage<TestTypes> for CrashStorage {
+    async fn append_cert2(&self, view: ViewNumber, cert: Certificate2<TestTypes>) -> Result<()> {
+        let write = NewProtocolStorage::append_cert2(&self.durable, view, cert);
+        self.step(Op::Cert2, Some(view), write).await
+    }
+
+    async fn append_high_qc2(&self, high_qc: Certificate1<TestTypes>) -> Result<()> {
+        let view = high_qc.view_number();
+        let write = self.durable.append_high_qc2(high_qc);
+        self.step(Op::HighQc, Some(view), write).await
+    }
+
+    async fn load_high_qc2(&self) -> Result<Option<Certificate1<TestTypes>>> {
+        self.durable.load_high_qc2().await
+    }
+
+    async fn sync(&self) -> Result<()> {
+        let write = NewProtocolStorage::sync(&self.durable);
+        self.step(Op::Flush, None, write).await
+    }
+}
+
+/// Build a coordinator on `storage` that resumes from its durable state,
+/// including the persisted lock, as `Coordinator::maker` does in production.
+pub(crate) async fn build_crash_coordinator(
+    node_index: u64,
+    network: Cliquenet<TestTypes>,
+    membership: EpochMembershipCoordinator<TestTypes>,
+    storage: CrashStorage,
+    client: CoordinatorClient<TestTypes>,
+    epoch_height: u64,
+    view_timeout: Duration,
+) -> Coordinator<TestTypes, CrashStorage> {
+    let durable = storage.durable().clone();
+    let locked_qc = durable.load_high_qc2().await.expect("load locked qc");
+    build_coordinator(
+        node_index,
+        network,
+        membership,
+        durable,
+        storage,
+        locked_qc,
+        client,
+        epoch_height,
+        view_timeout,
+        None,
+    )
+    .await
+}
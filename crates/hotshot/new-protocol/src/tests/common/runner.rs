@@ -43,6 +43,7 @@ use crate::{
     network::Cliquenet,
     tests::common::{
         coordinator_builder::build_test_coordinator,
+        crash::TestBackend,
         utils::{
             StakeTableSchedule, mock_membership_with_client,
             mock_membership_with_client_and_schedule,
@@ -240,15 +241,15 @@ impl fmt::Display for TestError {
     }
 }
 
-enum NodeEvent {
+pub(crate) enum NodeEvent {
     Decided(BTreeMap<ViewNumber, [u8; 32]>),
     TimedOut(ViewNumber),
 }
 
-struct TaggedEvent {
-    idx: usize,
-    generation: u64,
-    event: NodeEvent,
+pub(crate) struct TaggedEvent {
+    pub(crate) idx: usize,
+    pub(crate) generation: u64,
+    pub(crate) event: NodeEvent,
 }
 
 impl TestRunner {
@@ -769,9 +770,9 @@ async fn create_network(
 }
 
 #[allow(clippy::too_many_arguments)]
-async fn run_node(
-    mut coord: Coordinator<TestTypes, TestStorage<TestTypes>>,
-    storage: TestStorage<TestTypes>,
+pub(crate) async fn run_node<S: TestBackend>(
+    mut coord: Coordinator<TestTypes, S>,
+    storage: S,
     output_tx: UnboundedSender<TaggedEvent>,
     idx: usize,
     generation: u64,
@@ -812,9 +813,7 @@ async fn run_node(
                 // leaf and the QC certifying it. A node restarted with
                 // persistent storage resumes consensus from this anchor.
                 if let Some(newest) = leaves.first() {
-                    storage
-                        .update_anchor_leaf(newest.clone(), cert1.clone())
-                        .await;
+                    storage.persist_anchor(newest.clone(), cert1.clone()).await;
                 }
                 for leaf in leaves {
                     let commit: [u8; 32] = leaf.commit().into();
@@ -0,0 +1,694 @@
+//! Deterministic network simulator.
+//!
+//! Runs a full set of coordinators in one runtime on virtual time and
+//! replaces cliquenet's TCP transport with a simulated one. Every message
+//! goes through a single event queue and is delivered after a latency drawn
+//! from a seeded per-link RNG, or dropped. Ties are broken by link and
+//! per-link sequence number, so the network schedule is a function of the
+//! seed alone and a failing seed found by random search can be replayed.
+//!
+//! Tests must run on a paused single threaded runtime, i.e.
+//! `#[tokio::test(flavor = "current_thread", start_paused = true)]`. Work
+//! coordinators hand to blocking tasks takes no virtual time, so view timers
+//! never fire because the machine running the test is slow. What remains
+//! up to the runtime is the order in which one node's internal tasks finish
+//! within the same instant.
+//!
+//! Nodes given an [`Adversary`] deviate from the protocol. Progress and
+//! agreement are only required of the others.
+//!
+//! Nodes given a [`CrashPoint`] die at that step of persisting their state
+//! and restart from what they had written. Nodes given a stop step shut down
+//! gracefully once that step landed and restart as well. An honest node,
+//! crashed or not, must never send conflicting votes in a view and must come
+//! back locked on at least what it last voted to lock on.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    ops::Range,
+    time::Duration,
+};
+
+use bon::Builder;
+use cliquenet::{Detached, Inbound, Outgoing, noise::Protocol};
+use committable::Commitment;
+use futures::{
+    StreamExt,
+    stream::{self, BoxStream, SelectAll},
+};
+use hotshot::types::BLSPubKey;
+use hotshot_example_types::{node_types::TestTypes, storage_types::TestStorage};
+use hotshot_types::{
+    PeerConnectInfo,
+    addr::NetAddr,
+    data::{Leaf2, ViewNumber},
+    message::UpgradeLock,
+    traits::signature_key::SignatureKey,
+    vote::HasViewNumber,
+    x25519::Keypair,
+};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use tokio::{
+    select,
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+    time::{Instant, sleep_until},
+};
+use tracing::info;
+
+use crate::{
+    helpers::test_upgrade_lock,
+    message::{ConsensusMessage, Message, MessageType, Unchecked},
+    network::Cliquenet,
+    tests::common::{
+        byzantine::{Adversary, Byzantine},
+        crash::{CrashPoint, CrashStorage, Step, TestBackend, build_crash_coordinator},
+        runner::{NodeEvent, TaggedEvent, run_node},
+        utils::mock_membership_with_client,
+    },
+};
+
+/// Latency, loss and reordering of a directed link.
+#[derive(Clone, Debug)]
+pub(crate) struct LinkModel {
+    /// One-way latency range in milliseconds.
+    pub latency_ms: Range<u64>,
+    /// Probability that a message is lost.
+    pub drop: f64,
+    /// Probability that a message is not queued behind earlier messages on
+    /// the same link and may overtake them.
+    pub reorder: f64,
+}
+
+impl Default for LinkModel {
+    fn default() -> Self {
+        Self {
+            latency_ms: 5..50,
+            drop: 0.0,
+            reorder: 0.0,
+        }
+    }
+}
+
+impl LinkModel {
+    /// Draw a link model for random search.
+    ///
+    /// Losses stay low enough for a quorum to make progress eventually.
+    pub fn random(rng: &mut impl Rng) -> Self {
+        let min = rng.gen_range(1..50);
+        Self {
+            latency_ms: min..min + rng.gen_range(1..500),
+            drop: rng.gen_range(0.0..0.1),
+            reorder: rng.gen_range(0.0..0.5),
+        }
+    }
+}
+
+/// Nodes cut off from all other nodes for a window of virtual time.
+#[derive(Clone, Debug)]
+pub(crate) struct Partition {
+    /// Start and end, relative to the start of the simulation.
+    pub during: Range<Duration>,
+    pub nodes: BTreeSet<usize>,
+}
+
+impl Partition {
+    fn separates(&self, elapsed: Duration, a: usize, b: usize) -> bool {
+        self.during.contains(&elapsed) && self.nodes.contains(&a) != self.nodes.contains(&b)
+    }
+}
+
+/// Configuration of a simulated network run.
+#[derive(Builder)]
+pub(crate) struct Simulation {
+    /// Seed of all network randomness.
+    seed: u64,
+
+    #[builder(default = 5)]
+    num_nodes: usize,
+
+    /// Number of leaves each node must decide.
+    #[builder(default = 10)]
+    target_decisions: usize,
+
+    #[builder(default = 100)]
+    epoch_height: u64,
+
+    #[builder(default = Duration::from_secs(2))]
+    view_timeout: Duration,
+
+    /// Virtual time after which the run counts as a liveness failure.
+    #[builder(default = Duration::from_secs(600))]
+    max_time: Duration,
+
+    /// Model of every link without an override in `links`.
+    #[builder(default)]
+    link: LinkModel,
+
+    /// Per-link overrides, keyed by `(from, to)`.
+    #[builder(default)]
+    links: BTreeMap<(usize, usize), LinkModel>,
+
+    #[builder(default)]
+    partitions: Vec<Partition>,
+
+    /// Byzantine nodes and how they behave.
+    #[builder(default)]
+    adversaries: BTreeMap<usize, Box<dyn Adversary>>,
+
+    /// Nodes that crash once, and where. They are restarted right away.
+    #[builder(default)]
+    crashes: BTreeMap<usize, CrashPoint>,
+
+    /// Nodes that stop gracefully once, after the given step of their
+    /// journal landed. They are restarted once their storage is flushed.
+    #[builder(default)]
+    stops: BTreeMap<usize, usize>,
+}
+
+/// Outcome of a successful run.
+///
+/// Runs with the same configuration and seed have equal reports.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct SimReport {
+    pub elapsed: Duration,
+    pub delivered: u64,
+    pub dropped: u64,
+    pub decided: Vec<BTreeMap<ViewNumber, [u8; 32]>>,
+    pub timeouts: Vec<BTreeSet<ViewNumber>>,
+    /// Persistence steps of each node's first run, which crash points count.
+    pub journals: Vec<Vec<Step>>,
+    /// Nodes that crashed or stopped and were restarted.
+    pub restarted: BTreeSet<usize>,
+    /// Nodes that reached their crash point.
+    pub crashed: BTreeSet<usize>,
+    /// How often the adversary of each byzantine node deviated from the
+    /// protocol.
+    pub deviations: BTreeMap<usize, u64>,
+}
+
+#[derive(Debug)]
+pub(crate) enum SimError {
+    /// Not every node reached its target within `max_time`.
+    Timeout { seed: u64, decided: Vec<usize> },
+    /// Two nodes decided different leaves for the same view.
+    Divergence {
+        seed: u64,
+        view: ViewNumber,
+        nodes: (usize, usize),
+    },
+    /// An honest node sent votes for different leaves in the same phase of a
+    /// view.
+    Equivocation {
+        seed: u64,
+        node: usize,
+        view: ViewNumber,
+    },
+    /// A node restarted locked on less than it had voted to lock on.
+    ForgottenLock {
+        seed: u64,
+        node: usize,
+        voted: ViewNumber,
+        locked: Option<ViewNumber>,
+    },
+}
+
+impl fmt::Display for SimError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Timeout { seed, decided } => {
+                write!(
+                    f,
+                    "seed {seed}: timed out with decisions per node {decided:?}"
+                )
+            },
+            Self::Divergence { seed, view, nodes } => write!(
+                f,
+                "seed {seed}: nodes {} and {} decided different leaves for view {view}",
+                nodes.0, nodes.1
+            ),
+            Self::Equivocation { seed, node, view } => write!(
+                f,
+                "seed {seed}: node {node} sent conflicting votes in view {view}"
+            ),
+            Self::ForgottenLock {
+                seed,
+                node,
+                voted,
+                locked,
+            } => write!(
+                f,
+                "seed {seed}: node {node} voted to lock in view {voted} but restarted locked on \
+                 {locked:?}"
+            ),
+        }
+    }
+}
+
+/// Key of a message in the event queue: delivery time, then link, then
+/// order of sending on that link.
+type QueueKey = (Instant, usize, usize, u64);
+
+impl Simulation {
+    /// The seed to use for `default`, unless `SIM_SEED` asks for a replay.
+    pub fn seed_from_env(default: u64) -> u64 {
+        std::env::var("SIM_SEED")
+            .map(|s| s.parse().expect("SIM_SEED must be a u64"))
+            .unwrap_or(default)
+    }
+
+    fn link(&self, from: usize, to: usize) -> &LinkModel {
+        self.links.get(&(from, to)).unwrap_or(&self.link)
+    }
+
+    /// RNG of the link `from -> to`, independent of every other link so
+    /// scheduling on one link never shifts the draws on another.
+    fn link_rng(&self, from: usize, to: usize) -> ChaCha8Rng {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        rng.set_stream((from * self.num_nodes + to) as u64);
+        rng
+    }
+
+    pub async fn run(&mut self) -> Result<SimReport, SimError> {
+        crate::logging::init_test_logging();
+        info!(
+            seed = self.seed,
+            nodes = self.num_nodes,
+            byzantine = ?self.adversaries.keys().collect::<Vec<_>>(),
+            crashes = ?self.crashes,
+            stops = ?self.stops,
+            "starting simulation"
+        );
+
+        let upgrade_lock = test_upgrade_lock();
+        let mut byzantine: BTreeMap<usize, Byzantine> = std::mem::take(&mut self.adversaries)
+            .into_iter()
+            .map(|(i, a)| (i, Byzantine::new(i, upgrade_lock.clone(), a)))
+            .collect();
+        let honest: Vec<bool> = (0..self.num_nodes)
+            .map(|i| !byzantine.contains_key(&i))
+            .collect();
+        let unbound = NetAddr::Inet(std::net::Ipv4Addr::LOCALHOST.into(), 0);
+
+        let parties = (0..self.num_nodes)
+            .map(|i| {
+                let (public_key, private_key) =
+                    BLSPubKey::generated_from_seed_indexed([0u8; 32], i as u64);
+                let keypair = Keypair::derive_from::<BLSPubKey>(&private_key).unwrap();
+                (keypair, public_key)
+            })
+            .collect::<Vec<_>>();
+        let peer_infos = parties
+            .iter()
+            .map(|(kp, pk)| {
+                let info = PeerConnectInfo {
+                    x25519_key: kp.public_key(),
+                    p2p_addr: unbound.clone(),
+                };
+                (*pk, info)
+            })
+            .collect::<Vec<_>>();
+        let index: BTreeMap<cliquenet::x25519::PublicKey, usize> = parties
+            .iter()
+            .enumerate()
+            .map(|(i, (kp, _))| (kp.public_key().into(), i))
+            .collect();
+
+        let (event_tx, mut event_rx) = mpsc::unbounded_channel::<TaggedEvent>();
+        // Nodes to restart, with the generation that ended.
+        let (restart_tx, mut restart_rx) = mpsc::unbounded_channel::<(usize, u64)>();
+        let (stop_tx, mut stop_rx) = mpsc::unbounded_channel::<usize>();
+        let wiring = Wiring {
+            upgrade_lock: upgrade_lock.clone(),
+            parties,
+            peer_infos,
+            events: event_tx,
+        };
+        let mut outgoing = SelectAll::new();
+        let mut nodes = Vec::with_capacity(self.num_nodes);
+        let mut first_runs = Vec::with_capacity(self.num_nodes);
+
+        for i in 0..self.num_nodes {
+            let restart_tx = restart_tx.clone();
+            let mut storage = CrashStorage::new(
+                TestStorage::default(),
+                self.crashes.get(&i).copied(),
+                move || {
+                    let _ = restart_tx.send((i, 0));
+                },
+            );
+            if let Some(&step) = self.stops.get(&i) {
+                let stop_tx = stop_tx.clone();
+                storage = storage.stop_at(step, move || {
+                    let _ = stop_tx.send(i);
+                });
+            }
+            first_runs.push(storage.clone());
+            let (node, detached) = self.start_node(&wiring, i, 0, storage).await;
+            outgoing.push(outgoing_of(i, 0, detached));
+            nodes.push(node);
+        }
+
+        let start = Instant::now();
+        let deadline = start + self.max_time;
+        let mut rngs = BTreeMap::new();
+        let mut seqs: BTreeMap<(usize, usize), u64> = BTreeMap::new();
+        let mut last_due: BTreeMap<(usize, usize), Instant> = BTreeMap::new();
+        let mut queue: BTreeMap<QueueKey, _> = BTreeMap::new();
+        let mut decided = vec![BTreeMap::new(); self.num_nodes];
+        let mut timeouts = vec![BTreeSet::new(); self.num_nodes];
+        let mut chain: BTreeMap<ViewNumber, ([u8; 32], usize)> = BTreeMap::new();
+        let mut votes: BTreeMap<(usize, Phase, ViewNumber), Commitment<Leaf2<TestTypes>>> =
+            BTreeMap::new();
+        let mut restarted = BTreeSet::new();
+        let (mut delivered, mut dropped) = (0, 0);
+
+        let result = 'sim: loop {
+            if decided
+                .iter()
+                .zip(&honest)
+                .all(|(d, h)| !h || d.len() >= self.target_decisions)
+            {
+                break 'sim Ok(());
+            }
+            let wakeup = queue
+                .first_key_value()
+                .map(|((t, ..), _)| *t)
+                .unwrap_or(deadline)
+                .min(deadline);
+
+            select! {
+                biased;
+
+                // Before anything else the dead node may have sent.
+                Some((i, ended)) = restart_rx.recv() => {
+                    if ended != nodes[i].generation {
+                        continue 'sim
+                    }
+                    nodes[i].handle.abort();
+                    let durable = nodes[i].storage.durable().clone();
+                    let generation = nodes[i].generation + 1;
+                    info!(node = i, generation, "restarting node");
+                    let storage = CrashStorage::new(durable, None, || ());
+                    let (node, detached) = self.start_node(&wiring, i, generation, storage).await;
+                    outgoing.push(outgoing_of(i, generation, detached));
+                    // A phase-2 vote is only sent once the lock it votes
+                    // on is durable.
+                    let voted = votes
+                        .keys()
+                        .filter(|(n, phase, _)| *n == i && *phase == Phase::Two)
+                        .map(|(.., view)| *view)
+                        .max();
+                    if let Some(voted) = voted
+                        && node.locked_view.is_none_or(|locked| locked < voted)
+                    {
+                        break 'sim Err(SimError::ForgottenLock {
+                            seed: self.seed,
+                            node: i,
+                            voted,
+                            locked: node.locked_view,
+                        });
+                    }
+                    nodes[i] = node;
+                    restarted.insert(i);
+                },
+
+                Some(i) = stop_rx.recv() => {
+                    let Some(cancel) = nodes[i].cancel.take() else {
+                        continue 'sim
+                    };
+                    info!(node = i, "stopping node");
+                    // The node may crash while flushing, in which case it
+                    // never reports back and is restarted as crashed.
+                    let (done_tx, done_rx) = oneshot::channel();
+                    let _ = cancel.send(done_tx);
+                    let restart_tx = restart_tx.clone();
+                    let generation = nodes[i].generation;
+                    tokio::spawn(async move {
+                        if done_rx.await.is_ok() {
+                            let _ = restart_tx.send((i, generation));
+                        }
+                    });
+                },
+
+                Some(TaggedEvent { idx, generation, event }) = event_rx.recv() => {
+                    if generation != nodes[idx].generation {
+                        continue 'sim
+                    }
+                    match event {
+                        NodeEvent::Decided(commits) => {
+                            for (view, commit) in commits.iter().filter(|_| honest[idx]) {
+                                let (expected, by) =
+                                    *chain.entry(*view).or_insert((*commit, idx));
+                                if expected != *commit {
+                                    break 'sim Err(SimError::Divergence {
+                                        seed: self.seed,
+                                        view: *view,
+                                        nodes: (by, idx),
+                                    });
+                                }
+                            }
+                            // A restarted node reports only what it decided
+                            // since.
+                            decided[idx].extend(commits);
+                        },
+                        NodeEvent::TimedOut(view) => {
+                            timeouts[idx].insert(view);
+                        },
+                    }
+                },
+
+                Some((from, generation, out)) = outgoing.next() => {
+                    if generation != nodes[from].generation || nodes[from].storage.crashed() {
+                        continue 'sim
+                    }
+                    if honest[from]
+                        && let Some((phase, view, leaf)) = vote_of(&upgrade_lock, &out.msg)
+                        && *votes.entry((from, phase, view)).or_insert(leaf) != leaf
+                    {
+                        break 'sim Err(SimError::Equivocation {
+                            seed: self.seed,
+                            node: from,
+                            view,
+                        });
+                    }
+                    let now = Instant::now();
+                    let to: Vec<usize> =
+                        out.to.iter().filter_map(|k| index.get(k).copied()).collect();
+                    let sends = match byzantine.get_mut(&from) {
+                        None => vec![(to, out.msg)],
+                        Some(b) => b.send(to, out.msg),
+                    };
+                    let deliveries = sends
+                        .iter()
+                        .flat_map(|(to, msg)| to.iter().map(move |to| (*to, msg)));
+                    for (to, msg) in deliveries {
+                        let seq = seqs.entry((from, to)).or_default();
+                        *seq += 1;
+                        if from == to {
+                            queue.insert((now, from, to, *seq), msg.clone());
+                            continue
+                        }
+                        let elapsed = now - start;
+                        if self.partitions.iter().any(|p| p.separates(elapsed, from, to)) {
+                            dropped += 1;
+                            continue
+                        }
+                        let model = self.link(from, to);
+                        let rng = rngs
+                            .entry((from, to))
+                            .or_insert_with(|| self.link_rng(from, to));
+                        if rng.gen_bool(model.drop) {
+                            dropped += 1;
+                            continue
+                        }
+                        let latency = rng.gen_range(model.latency_ms.clone());
+                        let mut due = now + Duration::from_millis(latency);
+                        if !rng.gen_bool(model.reorder) {
+                            let last = last_due.entry((from, to)).or_insert(due);
+                            due = due.max(*last);
+                            *last = due;
+                        }
+                        queue.insert((due, from, to, *seq), msg.clone());
+                    }
+                },
+
+                () = sleep_until(wakeup) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break 'sim Err(SimError::Timeout {
+                            seed: self.seed,
+                            decided: decided.iter().map(BTreeMap::len).collect(),
+                        });
+                    }
+                    while let Some(entry) = queue.first_entry() {
+                        if entry.key().0 > now {
+                            break
+                        }
+                        let ((_, from, to, _), msg) = entry.remove_entry();
+                        let src = wiring.parties[from].0.public_key().into();
+                        if let Some(b) = byzantine.get_mut(&to) {
+                            b.receive(&msg)
+                        }
+                        if nodes[to].inbound.deliver(src, msg).is_ok() {
+                            delivered += 1;
+                        }
+                    }
+                },
+            }
+        };
+
+        for node in &nodes {
+            node.handle.abort();
+        }
+
+        result.map(|()| SimReport {
+            elapsed: Instant::now() - start,
+            delivered,
+            dropped,
+            decided,
+            timeouts,
+            journals: first_runs.iter().map(CrashStorage::journal).collect(),
+            restarted,
+            crashed: (0..self.num_nodes)
+                .filter(|i| first_runs[*i].crashed())
+                .collect(),
+            deviations: byzantine
+                .iter()
+                .map(|(i, b)| (*i, b.deviations()))
+                .collect(),
+        })
+    }
+
+    /// Build node `i` on `storage` and spawn it on a fresh detached network.
+    async fn start_node(
+        &self,
+        wiring: &Wiring,
+        i: usize,
+        generation: u64,
+        storage: CrashStorage,
+    ) -> (Node, Detached) {
+        let (keypair, public_key) = &wiring.parties[i];
+        let unbound = NetAddr::Inet(std::net::Ipv4Addr::LOCALHOST.into(), 0);
+        let config = cliquenet::Config::builder()
+            .name("sim")
+            .keypair(keypair.clone().into())
+            .bind(unbound)
+            .parties(
+                wiring
+                    .peer_infos
+                    .iter()
+                    .map(|(_, info)| (info.x25519_key.into(), info.p2p_addr.clone())),
+            )
+            .noise_protocols([(1.into(), Protocol::IK_25519_AesGcm_Blake2s)])
+            .build();
+        let (net, detached) = cliquenet::Network::detached(config);
+        let network = Cliquenet::from_network(
+            *public_key,
+            wiring.upgrade_lock.clone(),
+            net,
+            wiring.peer_infos.clone(),
+        );
+        let (membership, _, client, external_events_tx) = mock_membership_with_client(
+            self.num_nodes,
+            self.epoch_height,
+            *public_key,
+            storage.durable().clone(),
+        );
+        let coord = build_crash_coordinator(
+            i as u64,
+            network,
+            membership,
+            storage.clone(),
+            client,
+            self.epoch_height,
+            self.view_timeout,
+        )
+        .await;
+        let locked_view = coord.locked_view();
+
+        // Dropping the sender would stop the node.
+        let (cancel, cancel_rx) = oneshot::channel();
+        let handle = tokio::spawn(run_node(
+            coord,
+            storage.clone(),
+            wiring.events.clone(),
+            i,
+            generation,
+            external_events_tx,
+            cancel_rx,
+            BTreeMap::new(),
+        ));
+        let node = Node {
+            generation,
+            storage,
+            inbound: detached.inbound(),
+            handle,
+            cancel: Some(cancel),
+            locked_view,
+        };
+        (node, detached)
+    }
+}
+
+/// What all nodes of a run share.
+struct Wiring {
+    upgrade_lock: UpgradeLock<TestTypes>,
+    parties: Vec<(Keypair, BLSPubKey)>,
+    peer_infos: Vec<(BLSPubKey, PeerConnectInfo)>,
+    events: mpsc::UnboundedSender<TaggedEvent>,
+}
+
+/// The current run of a node.
+struct Node {
+    /// Incremented on every restart.
+    generation: u64,
+    storage: CrashStorage,
+    inbound: Inbound,
+    handle: JoinHandle<()>,
+    /// Stops the node gracefully. Dropping it stops the node as well.
+    cancel: Option<oneshot::Sender<oneshot::Sender<()>>>,
+    /// View of the QC the node was locked on when it started.
+    locked_view: Option<ViewNumber>,
+}
+
+/// Messages a node sends, tagged with the node and its generation.
+fn outgoing_of(
+    i: usize,
+    generation: u64,
+    detached: Detached,
+) -> BoxStream<'static, (usize, u64, Outgoing)> {
+    stream::unfold(detached, move |mut d| async move {
+        let out = d.next().await?;
+        Some(((i, generation, out), d))
+    })
+    .boxed()
+}
+
+/// Phase of a vote on a leaf.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Phase {
+    One,
+    Two,
+}
+
+/// The phase, view and leaf of a vote on the wire.
+fn vote_of(
+    upgrade_lock: &UpgradeLock<TestTypes>,
+    bytes: &[u8],
+) -> Option<(Phase, ViewNumber, Commitment<Leaf2<TestTypes>>)> {
+    let (msg, _) = upgrade_lock
+        .deserialize::<Message<TestTypes, Unchecked>>(bytes)
+        .ok()?;
+    match msg.message_type {
+        MessageType::Consensus(ConsensusMessage::Vote1(v)) => {
+            Some((Phase::One, v.vote.view_number(), v.vote.data.leaf_commit))
+        },
+        MessageType::Consensus(ConsensusMessage::Vote2(v)) => {
+            Some((Phase::Two, v.view_number(), v.data.leaf_commit))
+        },
+        _ => None,
+    }
+}
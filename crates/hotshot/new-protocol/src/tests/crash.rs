@@ -0,0 +1,141 @@
+//! Crash consistency of new-protocol storage.
+//!
+//! A reference run journals every persistence step of one node. The node is
+//! then killed before and after each of those steps in turn, and with each
+//! step overtaking the earlier writes still in flight, and restarted from
+//! what it had written. Every run must still decide, and the simulator fails
+//! it if the node votes for two leaves in a view or comes back with an older
+//! lock than it had voted on.
+//!
+//! Replay a failing crash point with
+//! `SIM_SEED=<seed> cargo test -p hotshot-new-protocol crash_`.
+
+use std::collections::BTreeMap;
+
+use hotshot_types::event::HotShotAction;
+
+use crate::tests::common::{
+    crash::{CrashPoint, Op, Side, Step},
+    sim::{SimReport, Simulation},
+};
+
+const NUM_NODES: usize = 4;
+const TARGET_DECISIONS: usize = 6;
+
+/// The node that crashes. Not the first leader, so that it has voted before
+/// it first proposes.
+const VICTIM: usize = 1;
+
+const SIDES: [Side; 3] = [Side::Before, Side::After, Side::Reordered];
+
+fn simulation(
+    seed: u64,
+    crashes: BTreeMap<usize, CrashPoint>,
+    stops: BTreeMap<usize, usize>,
+) -> Simulation {
+    Simulation::builder()
+        .seed(seed)
+        .num_nodes(NUM_NODES)
+        .target_decisions(TARGET_DECISIONS)
+        .crashes(crashes)
+        .stops(stops)
+        .build()
+}
+
+/// Crash the victim at `at`, after stopping it at `stop` if given.
+async fn crash_victim(seed: u64, at: CrashPoint, stop: Option<usize>, expected: &Step) {
+    let stops = stop.map(|s| (VICTIM, s)).into_iter().collect();
+    let report = simulation(seed, BTreeMap::from([(VICTIM, at)]), stops)
+        .run()
+        .await
+        .unwrap_or_else(|err| panic!("crash at {at:?} ({expected:?}): {err}"));
+    assert!(
+        report.crashed.contains(&VICTIM),
+        "crash at {at:?} ({expected:?}) was never reached"
+    );
+}
+
+fn victim_journal(report: &SimReport) -> &[Step] {
+    &report.journals[VICTIM]
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn crash_at_every_persistence_step() {
+    let seed = Simulation::seed_from_env(20);
+    let reference = simulation(seed, BTreeMap::new(), BTreeMap::new())
+        .run()
+        .await
+        .unwrap_or_else(|err| panic!("reference run: {err}"));
+    let journal = victim_journal(&reference);
+
+    let wrote = |op: Op| journal.iter().any(|s| s.op == op);
+    assert!(wrote(Op::Action(HotShotAction::Vote)), "{journal:?}");
+    assert!(wrote(Op::Action(HotShotAction::Propose)), "{journal:?}");
+    assert!(wrote(Op::HighQc), "{journal:?}");
+    assert!(wrote(Op::Proposal), "{journal:?}");
+    assert!(wrote(Op::Vid), "{journal:?}");
+    assert!(wrote(Op::Anchor), "{journal:?}");
+
+    // Every step the node takes on its way to the target, so a crash point
+    // past the reference run's last step is never needed.
+    for (step, expected) in journal.iter().enumerate() {
+        for side in SIDES {
+            crash_victim(seed, CrashPoint { step, side }, None, expected).await;
+        }
+    }
+}
+
+/// The victim shuts down gracefully in the middle of the run and crashes at
+/// every step of flushing its storage journal.
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn crash_while_flushing() {
+    let seed = Simulation::seed_from_env(22);
+    let clean = simulation(seed, BTreeMap::new(), BTreeMap::new())
+        .run()
+        .await
+        .unwrap_or_else(|err| panic!("reference run: {err}"));
+    // Stop right after a vote is recorded, when the writes issued with it are
+    // still in flight.
+    let journal = victim_journal(&clean);
+    let stop = journal
+        .iter()
+        .enumerate()
+        .skip(journal.len() / 2)
+        .find(|(_, s)| s.op == Op::Action(HotShotAction::Vote))
+        .map(|(i, _)| i)
+        .unwrap_or_else(|| panic!("no vote in the second half of {journal:?}"));
+
+    let reference = simulation(seed, BTreeMap::new(), BTreeMap::from([(VICTIM, stop)]))
+        .run()
+        .await
+        .unwrap_or_else(|err| panic!("stop at step {stop}: {err}"));
+    assert!(reference.restarted.contains(&VICTIM));
+    let journal = victim_journal(&reference);
+    assert_eq!(journal.last().map(|s| s.op), Some(Op::Flush), "{journal:?}");
+
+    for (step, expected) in journal.iter().enumerate().skip(stop + 1) {
+        for side in SIDES {
+            crash_victim(seed, CrashPoint { step, side }, Some(stop), expected).await;
+        }
+    }
+}
+
+/// Every node crashes once, at different points of the run.
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn crash_every_node() {
+    let crashes = (0..NUM_NODES)
+        .map(|i| {
+            let at = CrashPoint {
+                step: 5 + 7 * i,
+                side: SIDES[i % SIDES.len()],
+            };
+            (i, at)
+        })
+        .collect();
+    let report = simulation(Simulation::seed_from_env(21), crashes, BTreeMap::new())
+        .run()
+        .await
+        .unwrap_or_else(|err| panic!("{err}"));
+    assert_eq!(report.crashed.len(), NUM_NODES);
+    assert_eq!(report.restarted.len(), NUM_NODES);
+}
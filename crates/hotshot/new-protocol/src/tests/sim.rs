@@ -0,0 +1,133 @@
+//! Consensus over the deterministic network simulator.
+//!
+//! A failing run reports its seed. Replay it with
+//! `SIM_SEED=<seed> cargo test -p hotshot-new-protocol sim_`.
+
+use std::{collections::BTreeSet, time::Duration};
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::tests::common::sim::{LinkModel, Partition, Simulation};
+
+/// Number of seeds the random search tries per test run.
+const RANDOM_SEARCH_RUNS: u64 = 4;
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn sim_happy_path() {
+    let report = Simulation::builder()
+        .seed(Simulation::seed_from_env(0))
+        .build()
+        .run()
+        .await
+        .unwrap_or_else(|err| panic!("{err}"));
+    assert_eq!(report.dropped, 0);
+    assert!(report.delivered > 0);
+    assert!(report.decided.iter().all(|d| d.len() >= 10));
+}
+
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn sim_lossy_reordering_links() {
+    Simulation::builder()
+        .seed(Simulation::seed_from_env(1))
+        .link(LinkModel {
+            latency_ms: 10..300,
+            drop: 0.05,
+            reorder: 0.3,
+        })
+        .build()
+        .run()
+        .await
+        .unwrap_or_else(|err| panic!("{err}"));
+}
+
+/// One slow leader must not stall the others.
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn sim_slow_node() {
+    let slow = LinkModel {
+        latency_ms: 1500..2500,
+        ..LinkModel::default()
+    };
+    Simulation::builder()
+        .seed(Simulation::seed_from_env(2))
+        .links(
+            (0..5)
+                .flat_map(|i| [((0, i), slow.clone()), ((i, 0), slow.clone())])
+                .collect(),
+        )
+        .build()
+        .run()
+        .await
+        .unwrap_or_else(|err| panic!("{err}"));
+}
+
+/// Without a quorum on either side nothing is decided until the partition
+/// heals.
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn sim_partition_heals() {
+    let report = Simulation::builder()
+        .seed(Simulation::seed_from_env(3))
+        .partitions(vec![Partition {
+            during: Duration::ZERO..Duration::from_secs(30),
+            nodes: BTreeSet::from([0, 1]),
+        }])
+        .build()
+        .run()
+        .await
+        .unwrap_or_else(|err| panic!("{err}"));
+    assert!(report.elapsed > Duration::from_secs(30));
+    assert!(report.timeouts.iter().any(|t| !t.is_empty()));
+}
+
+/// The same seed replays the same run, down to every decision, persistence
+/// step and message, even over lossy, reordering and partitioned links.
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn sim_same_seed_same_report() {
+    let seed = Simulation::seed_from_env(4);
+    let simulation = || {
+        Simulation::builder()
+            .seed(seed)
+            .link(LinkModel {
+                latency_ms: 10..300,
+                drop: 0.05,
+                reorder: 0.3,
+            })
+            .partitions(vec![Partition {
+                during: Duration::from_secs(5)..Duration::from_secs(15),
+                nodes: BTreeSet::from([0, 1]),
+            }])
+            .build()
+    };
+    let first = simulation()
+        .run()
+        .await
+        .unwrap_or_else(|err| panic!("{err}"));
+    let second = simulation()
+        .run()
+        .await
+        .unwrap_or_else(|err| panic!("{err}"));
+    assert!(first.dropped > 0);
+    assert!(first.journals.iter().all(|j| !j.is_empty()));
+    assert_eq!(first, second);
+}
+
+/// Random link models for consecutive seeds. Set `SIM_SEED` to replay one.
+#[tokio::test(flavor = "current_thread", start_paused = true)]
+async fn sim_random_search() {
+    let seeds = match std::env::var("SIM_SEED") {
+        Ok(_) => Simulation::seed_from_env(0)..Simulation::seed_from_env(0) + 1,
+        Err(_) => 100..100 + RANDOM_SEARCH_RUNS,
+    };
+    for seed in seeds {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let link = LinkModel::random(&mut rng);
+        tracing::info!(seed, ?link, "random search");
+        Simulation::builder()
+            .seed(seed)
+            .link(link)
+            .build()
+            .run()
+            .await
+            .unwrap_or_else(|err| panic!("{err}"));
+    }
+}
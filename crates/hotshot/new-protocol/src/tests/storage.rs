@@ -5,7 +5,7 @@ use hotshot_example_types::{node_types::TestTypes, storage_types::TestStorage};
 use hotshot_types::{data::ViewNumber, traits::signature_key::SignatureKey};
 use tokio::time::timeout;
 
-use crate::storage::{ActionKind, Storage, StorageOutput};
+use crate::storage::{ActionKind, InFlightWrite, Storage, StorageOutput, WriteKind};
 
 fn test_storage() -> (
     Storage<TestTypes, TestStorage<TestTypes>>,
@@ -67,3 +67,44 @@ async fn test_gc_aborts_pending_writes() {
 
     assert!(storage.next().await.is_none());
 }
+
+/// A write is in flight until the backend acknowledges it.
+#[tokio::test]
+async fn test_tracks_in_flight_writes() {
+    let (mut storage, inner) = test_storage();
+    let view = ViewNumber::new(4);
+
+    inner.should_return_err.store(true, Ordering::Relaxed);
+    storage.record_action(view, None, ActionKind::Vote);
+    let pending: Vec<_> = storage.in_flight().copied().collect();
+    assert_eq!(
+        pending,
+        [InFlightWrite {
+            seq: 0,
+            view,
+            kind: WriteKind::Action(ActionKind::Vote),
+        }]
+    );
+
+    inner.should_return_err.store(false, Ordering::Relaxed);
+    let stored = timeout(Duration::from_secs(5), storage.next())
+        .await
+        .expect("completes once the write succeeds")
+        .expect("completion");
+    assert_eq!(stored, StorageOutput::Action(view, ActionKind::Vote));
+    assert_eq!(storage.in_flight().count(), 0);
+}
+
+/// gc stops tracking the writes it aborts.
+#[tokio::test]
+async fn test_gc_clears_in_flight_writes() {
+    let (mut storage, inner) = test_storage();
+
+    inner.should_return_err.store(true, Ordering::Relaxed);
+    storage.record_action(ViewNumber::new(2), None, ActionKind::Vote);
+    storage.record_action(ViewNumber::new(6), None, ActionKind::Vote);
+    storage.gc(ViewNumber::new(5));
+
+    let views: Vec<_> = storage.in_flight().map(|e| e.view).collect();
+    assert_eq!(views, [ViewNumber::new(6)]);
+}
@@ -0,0 +1,840 @@
+//! Consensus traces.
+//!
+//! A [`TraceRecorder`] attached to a [`Coordinator`] writes what drives the
+//! node's consensus to a binary trace: every input it applies to
+//! [`Consensus`], timer firings and storage results included, together with a
+//! digest of each output consensus produced for it. [`replay`] feeds the
+//! inputs of a trace into a fresh `Consensus` and reports the first input for
+//! which its outputs differ from the recorded ones.
+//!
+//! A node that restarted from storage records the [`RestartAnchor`] it
+//! resumed from, and [`replay_from_anchor`] seeds consensus from it, so such
+//! a trace replays without the node's storage.
+//!
+//! A trace is a magic number followed by length-prefixed, bincode encoded
+//! records. An output digest is the SHA-256 hash of the bincode encoding of
+//! the output.
+//!
+//! [`Coordinator`]: crate::coordinator::Coordinator
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    marker::PhantomData,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use committable::Commitment;
+use hotshot::traits::BlockPayload;
+use hotshot_types::{
+    data::{EpochNumber, Leaf2, VidCommitment, VidCommitment2, VidDisperseShare2, ViewNumber},
+    drb::DrbResult,
+    message::Proposal as SignedProposal,
+    simple_certificate::{
+        LightClientStateUpdateCertificateV2, QuorumCertificate2, TimeoutCertificate2,
+    },
+    traits::node_implementation::NodeType,
+    vote::HasViewNumber,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tracing::error;
+
+use crate::{
+    cert_verifier::ValidCert,
+    consensus::{Consensus, ConsensusInput, ConsensusOutput, PreCutoverSeed, RestartAnchor},
+    coordinator::GcScope,
+    message::{Certificate1, Certificate2, EpochChangeMessage, Proposal, ProposalMessage},
+    outbox::Outbox,
+    state::StateResponse,
+    storage::StorageOutput,
+};
+
+/// First bytes of every trace, ending in the format version.
+const MAGIC: &[u8; 8] = b"NPTRACE\x02";
+
+/// Largest record a trace may contain.
+const MAX_RECORD_SIZE: u32 = 256 * 1024 * 1024;
+
+#[derive(Debug, Error)]
+pub enum TraceError {
+    #[error("i/o error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("codec error: {0}")]
+    Codec(#[from] bincode::Error),
+
+    #[error("not a consensus trace")]
+    NotATrace,
+
+    #[error("record of {0} bytes exceeds the maximum size")]
+    RecordSize(u32),
+
+    #[error("trace does not start with a header")]
+    MissingHeader,
+
+    #[error("trace was not recorded from a restart anchor")]
+    MissingAnchor,
+
+    #[error("trace repeats its header")]
+    RepeatedHeader,
+
+    #[error("trace starts {trace}, but consensus starts {consensus}")]
+    StartMismatch { trace: String, consensus: String },
+}
+
+/// Writes the trace of a coordinator.
+pub struct TraceRecorder<T: NodeType> {
+    out: Option<Box<dyn Write + Send + Sync>>,
+    start: Instant,
+    _types: PhantomData<fn() -> T>,
+}
+
+impl<T: NodeType> TraceRecorder<T> {
+    /// Record to a new file at `path`.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+
+    /// Record to `out`.
+    pub fn new<W: Write + Send + Sync + 'static>(mut out: W) -> io::Result<Self> {
+        out.write_all(MAGIC)?;
+        Ok(Self {
+            out: Some(Box::new(out)),
+            start: Instant::now(),
+            _types: PhantomData,
+        })
+    }
+
+    /// Record the anchor consensus is seeded from. Called before [`Self::start`].
+    pub(crate) fn anchor(&mut self, anchor: &RestartAnchor<T>) {
+        self.write(&Record::Anchor(anchor.clone()))
+    }
+
+    /// Record where `consensus` starts. Called once, before any input.
+    pub(crate) fn start(&mut self, consensus: &Consensus<T>) {
+        self.write(&Record::Header(Start::of(consensus)))
+    }
+
+    /// Record an input and the outputs consensus produced for it.
+    pub(crate) fn input<'a, I>(&mut self, input: TracedInput<T>, outputs: I)
+    where
+        I: IntoIterator<Item = &'a ConsensusOutput<T>>,
+    {
+        let at = self.elapsed();
+        match outputs.into_iter().map(OutputDigest::of).collect() {
+            Ok(outputs) => self.write(&Record::Input { at, input, outputs }),
+            Err(err) => self.abandon(err.into()),
+        }
+    }
+
+    /// Record a cutover seed about to be applied to consensus.
+    pub(crate) fn cutover(&mut self, seed: &PreCutoverSeed<T>) {
+        self.write(&Record::Cutover {
+            decided_anchor: seed.decided_anchor.clone(),
+            undecided: seed.undecided.clone(),
+            high_qc: seed.high_qc.clone(),
+            cutover_view: seed.cutover_view,
+        })
+    }
+
+    pub(crate) fn gc(&mut self, scope: GcScope) {
+        self.write(&Record::Gc(scope))
+    }
+
+    pub(crate) fn legacy_qc(&mut self, qc: &Certificate1<T>) {
+        self.write(&Record::LegacyQc(qc.clone()))
+    }
+
+    pub(crate) fn flush(&mut self) {
+        if let Some(out) = &mut self.out
+            && let Err(err) = out.flush()
+        {
+            error!(%err, "failed to flush consensus trace, recording stopped");
+            self.out = None
+        }
+    }
+
+    fn elapsed(&self) -> u64 {
+        self.start.elapsed().as_millis() as u64
+    }
+
+    /// Append a record. A trace that cannot be written is abandoned, the
+    /// node keeps running.
+    fn write(&mut self, record: &Record<T>) {
+        let Some(out) = &mut self.out else {
+            return;
+        };
+        let result = bincode::serialize(record)
+            .map_err(TraceError::from)
+            .and_then(|bytes| {
+                out.write_all(&(bytes.len() as u32).to_le_bytes())?;
+                out.write_all(&bytes)?;
+                Ok(())
+            });
+        if let Err(err) = result {
+            self.abandon(err)
+        }
+    }
+
+    fn abandon(&mut self, err: TraceError) {
+        error!(%err, "failed to write consensus trace, recording stopped");
+        self.out = None
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[allow(clippy::large_enum_variant)]
+enum Record<T: NodeType> {
+    /// Only ever the first record.
+    Anchor(RestartAnchor<T>),
+    Header(Start<T>),
+    /// `at` is in milliseconds since recording started.
+    Input {
+        at: u64,
+        input: TracedInput<T>,
+        outputs: Vec<OutputDigest>,
+    },
+    Gc(GcScope),
+    LegacyQc(Certificate1<T>),
+    /// A [`PreCutoverSeed`] without its validated states, which consensus
+    /// does not read.
+    Cutover {
+        decided_anchor: Leaf2<T>,
+        undecided: Vec<Leaf2<T>>,
+        high_qc: Option<QuorumCertificate2<T>>,
+        cutover_view: ViewNumber,
+    },
+}
+
+/// State consensus was in when recording started.
+#[derive(Serialize, Deserialize, PartialEq, Eq)]
+struct Start<T: NodeType> {
+    public_key: T::SignatureKey,
+    view: ViewNumber,
+    decided: ViewNumber,
+}
+
+impl<T: NodeType> Start<T> {
+    fn of(consensus: &Consensus<T>) -> Self {
+        Self {
+            public_key: consensus.public_key().clone(),
+            view: consensus.current_view(),
+            decided: consensus.last_decided_view(),
+        }
+    }
+}
+
+impl<T: NodeType> fmt::Display for Start<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "as {} in view {} with view {} decided",
+            self.public_key, self.view, self.decided
+        )
+    }
+}
+
+/// A [`ConsensusInput`] as written to a trace.
+///
+/// Validated messages and certificates are recorded as plain data. Replay
+/// treats them as validated again: the node that recorded them did.
+#[derive(Serialize, Deserialize)]
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum TracedInput<T: NodeType> {
+    BlockBuilt {
+        view: ViewNumber,
+        epoch: EpochNumber,
+        payload: T::BlockPayload,
+        metadata: <T::BlockPayload as BlockPayload<T>>::Metadata,
+        payload_commitment: VidCommitment,
+    },
+    BlockReconstructed(ViewNumber, VidCommitment2),
+    Certificate1(Certificate1<T>, EpochNumber),
+    Certificate2(Certificate2<T>, EpochNumber),
+    AdvanceView(Certificate1<T>, EpochNumber),
+    EpochRootCertificates {
+        cert1: Certificate1<T>,
+        epoch: EpochNumber,
+        state_cert: LightClientStateUpdateCertificateV2<T>,
+    },
+    EpochChange {
+        cert1: Certificate1<T>,
+        cert2: Certificate2<T>,
+        proposal: Proposal<T>,
+    },
+    HeaderCreated(ViewNumber, Commitment<Leaf2<T>>, T::BlockHeader),
+    Proposal(T::SignatureKey, SignedProposal<T, Proposal<T>>),
+    VidShare(VidDisperseShare2<T>),
+    FetchedProposal(SignedProposal<T, Proposal<T>>),
+    StateValidated(StateResponse<T>),
+    StateValidationFailed(StateResponse<T>),
+    Stored(StorageOutput<T>),
+    Timeout(ViewNumber, EpochNumber),
+    TimeoutCertificate(TimeoutCertificate2<T>, EpochNumber),
+    TimeoutOneHonest(ViewNumber, EpochNumber),
+    VidDisperseCreated(ViewNumber, VidCommitment2),
+    DrbResult(EpochNumber, DrbResult),
+}
+
+impl<T: NodeType> From<&ConsensusInput<T>> for TracedInput<T> {
+    fn from(input: &ConsensusInput<T>) -> Self {
+        match input.clone() {
+            ConsensusInput::BlockBuilt {
+                view,
+                epoch,
+                payload,
+                metadata,
+                payload_commitment,
+            } => Self::BlockBuilt {
+                view,
+                epoch,
+                payload,
+                metadata,
+                payload_commitment,
+            },
+            ConsensusInput::BlockReconstructed(view, commitment) => {
+                Self::BlockReconstructed(view, commitment)
+            },
+            ConsensusInput::Certificate1(c) => Self::Certificate1(c.cert().clone(), c.epoch()),
+            ConsensusInput::Certificate2(c) => Self::Certificate2(c.cert().clone(), c.epoch()),
+            ConsensusInput::AdvanceView(c) => Self::AdvanceView(c.cert().clone(), c.epoch()),
+            ConsensusInput::EpochRootCertificates { cert1, state_cert } => {
+                Self::EpochRootCertificates {
+                    epoch: cert1.epoch(),
+                    cert1: cert1.into_cert(),
+                    state_cert,
+                }
+            },
+            ConsensusInput::EpochChange(e) => Self::EpochChange {
+                cert1: e.cert1,
+                cert2: e.cert2,
+                proposal: e.proposal,
+            },
+            ConsensusInput::HeaderCreated(view, commitment, header) => {
+                Self::HeaderCreated(view, commitment, header)
+            },
+            ConsensusInput::Proposal(sender, p) => Self::Proposal(sender, p.proposal),
+            ConsensusInput::VidShare(share) => Self::VidShare(share),
+            ConsensusInput::FetchedProposal(p) => Self::FetchedProposal(p.proposal),
+            ConsensusInput::StateValidated(response) => Self::StateValidated(response),
+            ConsensusInput::StateValidationFailed(response) => {
+                Self::StateValidationFailed(response)
+            },
+            ConsensusInput::Stored(stored) => Self::Stored(stored),
+            ConsensusInput::Timeout(view, epoch) => Self::Timeout(view, epoch),
+            ConsensusInput::TimeoutCertificate(c) => {
+                Self::TimeoutCertificate(c.cert().clone(), c.epoch())
+            },
+            ConsensusInput::TimeoutOneHonest(view, epoch) => Self::TimeoutOneHonest(view, epoch),
+            ConsensusInput::VidDisperseCreated(view, commitment) => {
+                Self::VidDisperseCreated(view, commitment)
+            },
+            ConsensusInput::DrbResult(epoch, result) => Self::DrbResult(epoch, result),
+        }
+    }
+}
+
+impl<T: NodeType> TracedInput<T> {
+    fn into_input(self) -> ConsensusInput<T> {
+        match self {
+            Self::BlockBuilt {
+                view,
+                epoch,
+                payload,
+                metadata,
+                payload_commitment,
+            } => ConsensusInput::BlockBuilt {
+                view,
+                epoch,
+                payload,
+                metadata,
+                payload_commitment,
+            },
+            Self::BlockReconstructed(view, commitment) => {
+                ConsensusInput::BlockReconstructed(view, commitment)
+            },
+            Self::Certificate1(c, epoch) => ConsensusInput::Certificate1(ValidCert::new(c, epoch)),
+            Self::Certificate2(c, epoch) => ConsensusInput::Certificate2(ValidCert::new(c, epoch)),
+            Self::AdvanceView(c, epoch) => ConsensusInput::AdvanceView(ValidCert::new(c, epoch)),
+            Self::EpochRootCertificates {
+                cert1,
+                epoch,
+                state_cert,
+            } => ConsensusInput::EpochRootCertificates {
+                cert1: ValidCert::new(cert1, epoch),
+                state_cert,
+            },
+            Self::EpochChange {
+                cert1,
+                cert2,
+                proposal,
+            } => ConsensusInput::EpochChange(EpochChangeMessage::validated(cert1, cert2, proposal)),
+            Self::HeaderCreated(view, commitment, header) => {
+                ConsensusInput::HeaderCreated(view, commitment, header)
+            },
+            Self::Proposal(sender, p) => {
+                ConsensusInput::Proposal(sender, ProposalMessage::validated(p))
+            },
+            Self::VidShare(share) => ConsensusInput::VidShare(share),
+            Self::FetchedProposal(p) => {
+                ConsensusInput::FetchedProposal(ProposalMessage::validated(p))
+            },
+            Self::StateValidated(response) => ConsensusInput::StateValidated(response),
+            Self::StateValidationFailed(response) => {
+                ConsensusInput::StateValidationFailed(response)
+            },
+            Self::Stored(stored) => ConsensusInput::Stored(stored),
+            Self::Timeout(view, epoch) => ConsensusInput::Timeout(view, epoch),
+            Self::TimeoutCertificate(c, epoch) => {
+                ConsensusInput::TimeoutCertificate(ValidCert::new(c, epoch))
+            },
+            Self::TimeoutOneHonest(view, epoch) => ConsensusInput::TimeoutOneHonest(view, epoch),
+            Self::VidDisperseCreated(view, commitment) => {
+                ConsensusInput::VidDisperseCreated(view, commitment)
+            },
+            Self::DrbResult(epoch, result) => ConsensusInput::DrbResult(epoch, result),
+        }
+    }
+
+    /// Variant name and view, for reports.
+    fn describe(&self) -> String {
+        let (kind, view) = match self {
+            Self::BlockBuilt { view, .. } => ("BlockBuilt", Some(*view)),
+            Self::BlockReconstructed(view, _) => ("BlockReconstructed", Some(*view)),
+            Self::Certificate1(c, _) => ("Certificate1", Some(c.view_number())),
+            Self::Certificate2(c, _) => ("Certificate2", Some(c.view_number())),
+            Self::AdvanceView(c, _) => ("AdvanceView", Some(c.view_number())),
+            Self::EpochRootCertificates { cert1, .. } => {
+                ("EpochRootCertificates", Some(cert1.view_number()))
+            },
+            Self::EpochChange { cert1, .. } => ("EpochChange", Some(cert1.view_number())),
+            Self::HeaderCreated(view, ..) => ("HeaderCreated", Some(*view)),
+            Self::Proposal(_, p) => ("Proposal", Some(p.data.view_number)),
+            Self::VidShare(share) => ("VidShare", Some(share.view_number())),
+            Self::FetchedProposal(p) => ("FetchedProposal", Some(p.data.view_number)),
+            Self::StateValidated(response) => ("StateValidated", Some(response.view)),
+            Self::StateValidationFailed(response) => ("StateValidationFailed", Some(response.view)),
+            Self::Stored(stored) => ("Stored", Some(stored.view_number())),
+            Self::Timeout(view, _) => ("Timeout", Some(*view)),
+            Self::TimeoutCertificate(c, _) => ("TimeoutCertificate", Some(c.view_number())),
+            Self::TimeoutOneHonest(view, _) => ("TimeoutOneHonest", Some(*view)),
+            Self::VidDisperseCreated(view, _) => ("VidDisperseCreated", Some(*view)),
+            Self::DrbResult(..) => ("DrbResult", None),
+        };
+        match view {
+            Some(view) => format!("{kind} in view {view}"),
+            None => kind.to_string(),
+        }
+    }
+}
+
+/// Variant and content hash of a [`ConsensusOutput`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputDigest {
+    pub kind: String,
+    /// SHA-256 of the bincode encoding of the output.
+    pub hash: [u8; 32],
+}
+
+impl OutputDigest {
+    fn of<T: NodeType>(output: &ConsensusOutput<T>) -> Result<Self, bincode::Error> {
+        let kind: &'static str = output.into();
+        Ok(Self {
+            kind: kind.to_string(),
+            hash: Sha256::digest(bincode::serialize(output)?).into(),
+        })
+    }
+}
+
+impl fmt::Display for OutputDigest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}#", self.kind)?;
+        for b in &self.hash[..8] {
+            write!(f, "{b:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of replaying a trace.
+#[derive(Debug, Default)]
+pub struct ReplayReport {
+    /// Inputs replayed, including a diverging one.
+    pub inputs: u64,
+    /// Where replay first produced different outputs, if it did.
+    pub divergence: Option<Divergence>,
+}
+
+/// An input for which replayed consensus produced different outputs.
+#[derive(Debug)]
+pub struct Divergence {
+    /// Index of the input in the trace, counting from 0.
+    pub index: u64,
+    /// Time since recording started.
+    pub at: Duration,
+    pub input: String,
+    pub recorded: Vec<OutputDigest>,
+    pub replayed: Vec<OutputDigest>,
+}
+
+impl fmt::Display for ReplayReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "replayed {} inputs", self.inputs)?;
+        let Some(d) = &self.divergence else {
+            return write!(f, ", no divergence");
+        };
+        writeln!(
+            f,
+            "\ndiverged at input {} ({}) after {:?}",
+            d.index, d.input, d.at
+        )?;
+        let list = |digests: &[OutputDigest]| {
+            digests
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        writeln!(f, "  recorded: [{}]", list(&d.recorded))?;
+        write!(f, "  replayed: [{}]", list(&d.replayed))
+    }
+}
+
+/// Replay the trace at `path`.
+pub fn replay_file<T: NodeType, P: AsRef<Path>>(
+    consensus: Consensus<T>,
+    path: P,
+) -> Result<ReplayReport, TraceError> {
+    replay(consensus, BufReader::new(File::open(path)?))
+}
+
+/// Feed the inputs of a trace into `consensus`, which must be set up the
+/// way the recording node's consensus was, and compare their outputs with
+/// the recorded ones. Stops at the first divergence.
+pub fn replay<T: NodeType, R: Read>(
+    consensus: Consensus<T>,
+    mut trace: R,
+) -> Result<ReplayReport, TraceError> {
+    let (_, start) = read_header(&mut trace)?;
+    replay_inputs(consensus, start, trace)
+}
+
+/// Like [`replay`], for a trace recorded by a node that restarted from
+/// storage. `new_consensus` creates consensus as the recording node did,
+/// from the anchor's leaf and epoch height; the anchor is seeded into it
+/// before replay.
+pub fn replay_from_anchor<T, R, F>(
+    new_consensus: F,
+    mut trace: R,
+) -> Result<ReplayReport, TraceError>
+where
+    T: NodeType,
+    R: Read,
+    F: FnOnce(&RestartAnchor<T>) -> Consensus<T>,
+{
+    let (anchor, start) = read_header(&mut trace)?;
+    let anchor = anchor.ok_or(TraceError::MissingAnchor)?;
+    let mut consensus = new_consensus(&anchor);
+    consensus.seed_anchor(anchor);
+    replay_inputs(consensus, start, trace)
+}
+
+/// Replay the trace at `path` from its anchor, see [`replay_from_anchor`].
+pub fn replay_file_from_anchor<T, P, F>(
+    new_consensus: F,
+    path: P,
+) -> Result<ReplayReport, TraceError>
+where
+    T: NodeType,
+    P: AsRef<Path>,
+    F: FnOnce(&RestartAnchor<T>) -> Consensus<T>,
+{
+    replay_from_anchor(new_consensus, BufReader::new(File::open(path)?))
+}
+
+/// Read the magic number, the anchor if there is one, and the header.
+fn read_header<T: NodeType, R: Read>(
+    trace: &mut R,
+) -> Result<(Option<RestartAnchor<T>>, Start<T>), TraceError> {
+    let mut magic = [0; MAGIC.len()];
+    trace.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(TraceError::NotATrace);
+    }
+    match read_record::<T, _>(trace)? {
+        Some(Record::Header(start)) => Ok((None, start)),
+        Some(Record::Anchor(anchor)) => match read_record::<T, _>(trace)? {
+            Some(Record::Header(start)) => Ok((Some(anchor), start)),
+            _ => Err(TraceError::MissingHeader),
+        },
+        _ => Err(TraceError::MissingHeader),
+    }
+}
+
+fn replay_inputs<T: NodeType, R: Read>(
+    mut consensus: Consensus<T>,
+    start: Start<T>,
+    mut trace: R,
+) -> Result<ReplayReport, TraceError> {
+    let actual = Start::of(&consensus);
+    if start != actual {
+        return Err(TraceError::StartMismatch {
+            trace: start.to_string(),
+            consensus: actual.to_string(),
+        });
+    }
+
+    let mut report = ReplayReport::default();
+    let mut outbox = Outbox::new();
+    while let Some(record) = read_record::<T, _>(&mut trace)? {
+        match record {
+            Record::Anchor(_) | Record::Header(_) => return Err(TraceError::RepeatedHeader),
+            Record::Input { at, input, outputs } => {
+                let description = input.describe();
+                consensus.apply(input.into_input(), &mut outbox);
+                let replayed = outbox
+                    .take()
+                    .iter()
+                    .map(OutputDigest::of)
+                    .collect::<Result<Vec<_>, _>>()?;
+                report.inputs += 1;
+                if replayed != outputs {
+                    report.divergence = Some(Divergence {
+                        index: report.inputs - 1,
+                        at: Duration::from_millis(at),
+                        input: description,
+                        recorded: outputs,
+                        replayed,
+                    });
+                    break;
+                }
+            },
+            Record::Gc(scope) => consensus.gc(scope),
+            Record::LegacyQc(qc) => consensus.register_legacy_qc(&qc),
+            Record::Cutover {
+                decided_anchor,
+                undecided,
+                high_qc,
+                cutover_view,
+            } => consensus.apply_pre_cutover_seed(PreCutoverSeed {
+                decided_anchor,
+                undecided,
+                high_qc,
+                validated_states: BTreeMap::new(),
+                cutover_view,
+            }),
+        }
+    }
+    Ok(report)
+}
+
+/// Read the next record, or `None` at the end of the trace.
+///
+/// A record cut short by a node that died while writing it also ends the
+/// trace.
+fn read_record<T: NodeType, R: Read>(trace: &mut R) -> Result<Option<Record<T>>, TraceError> {
+    let mut len = [0; 4];
+    match trace.read_exact(&mut len) {
+        Ok(()) => {},
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err.into()),
+    }
+    let len = u32::from_le_bytes(len);
+    if len > MAX_RECORD_SIZE {
+        return Err(TraceError::RecordSize(len));
+    }
+    let mut bytes = vec![0; len as usize];
+    match trace.read_exact(&mut bytes) {
+        Ok(()) => Ok(Some(bincode::deserialize(&bytes)?)),
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use hotshot::types::BLSPubKey;
+    use hotshot_example_types::node_types::TestTypes;
+    use hotshot_types::{light_client::StateKeyPair, traits::signature_key::SignatureKey};
+    use parking_lot::Mutex;
+
+    use super::*;
+    use crate::{
+        helpers::test_upgrade_lock,
+        tests::common::utils::{ConsensusHarness, TestData, mock_membership},
+    };
+
+    /// Trace sink the test can read back.
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Apply `inputs` to `consensus`, recording them. `tamper` may change the
+    /// recorded outputs of an input, given its index.
+    fn record(
+        consensus: &mut Consensus<TestTypes>,
+        recorder: &mut TraceRecorder<TestTypes>,
+        inputs: Vec<ConsensusInput<TestTypes>>,
+        mut tamper: impl FnMut(usize, &mut Vec<ConsensusOutput<TestTypes>>),
+    ) {
+        let mut outbox = Outbox::new();
+        for (i, input) in inputs.into_iter().enumerate() {
+            let traced = TracedInput::from(&input);
+            consensus.apply(input, &mut outbox);
+            let mut outputs: Vec<_> = outbox.take().into_iter().collect();
+            tamper(i, &mut outputs);
+            recorder.input(traced, &outputs);
+        }
+    }
+
+    /// Inputs with which node 0 decides the second test view.
+    async fn decide_inputs() -> Vec<ConsensusInput<TestTypes>> {
+        let test_data = TestData::new(3).await;
+        let node_key = BLSPubKey::generated_from_seed_indexed([0; 32], 0).0;
+        let mut inputs = Vec::new();
+        for view in &test_data.views[..2] {
+            let (proposal, vid_share) = view.proposal_input_consensus(&node_key);
+            inputs.extend([proposal, vid_share, view.block_reconstructed_input()]);
+        }
+        inputs.extend([
+            test_data.views[1].cert1_input(),
+            test_data.views[1].cert2_input(),
+        ]);
+        inputs
+    }
+
+    /// Record the decide inputs on a fresh node 0.
+    async fn trace_of_decide(
+        tamper: impl FnMut(usize, &mut Vec<ConsensusOutput<TestTypes>>),
+    ) -> (Vec<u8>, usize) {
+        let buffer = Buffer::default();
+        let mut recorder = TraceRecorder::new(buffer.clone()).unwrap();
+        let mut consensus = ConsensusHarness::new(0).await.consensus;
+        recorder.start(&consensus);
+        let inputs = decide_inputs().await;
+        let n = inputs.len();
+        record(&mut consensus, &mut recorder, inputs, tamper);
+        let bytes = buffer.0.lock().clone();
+        (bytes, n)
+    }
+
+    #[tokio::test]
+    async fn test_replay_round_trip() {
+        let (trace, n) = trace_of_decide(|_, _| {}).await;
+
+        let report = replay(ConsensusHarness::new(0).await.consensus, &trace[..]).unwrap();
+        assert_eq!(report.inputs, n as u64);
+        assert!(report.divergence.is_none(), "{report}");
+    }
+
+    /// A record cut short by a node dying mid-write ends the trace.
+    #[tokio::test]
+    async fn test_replay_truncated_record() {
+        let (trace, n) = trace_of_decide(|_, _| {}).await;
+
+        let truncated = &trace[..trace.len() - 1];
+        let report = replay(ConsensusHarness::new(0).await.consensus, truncated).unwrap();
+        assert_eq!(report.inputs, n as u64 - 1);
+        assert!(report.divergence.is_none(), "{report}");
+    }
+
+    #[tokio::test]
+    async fn test_replay_reports_divergence() {
+        const DIVERGING: usize = 3;
+        let (trace, _) = trace_of_decide(|i, outputs| {
+            if i == DIVERGING {
+                outputs.push(ConsensusOutput::ViewTimedOut(ViewNumber::new(99)));
+            }
+        })
+        .await;
+
+        let report = replay(ConsensusHarness::new(0).await.consensus, &trace[..]).unwrap();
+        let divergence = report.divergence.expect("replay diverges");
+        assert_eq!(divergence.index, DIVERGING as u64);
+        assert_eq!(report.inputs, DIVERGING as u64 + 1);
+        assert_eq!(
+            divergence.recorded.last().map(|d| d.kind.as_str()),
+            Some("ViewTimedOut")
+        );
+        assert_eq!(divergence.recorded.len(), divergence.replayed.len() + 1);
+    }
+
+    /// A trace recorded after a restart replays from its anchor alone.
+    #[tokio::test]
+    async fn test_replay_from_anchor() {
+        let test_data = TestData::new(4).await;
+        let node_key = BLSPubKey::generated_from_seed_indexed([0; 32], 0).0;
+        let anchor_view = &test_data.views[1];
+        let anchor = RestartAnchor {
+            leaf: anchor_view.leaf.clone(),
+            epoch_height: 10,
+            high_qc: anchor_view.cert1.clone(),
+            parent: anchor_view.proposal.data.clone(),
+            proposals: Vec::new(),
+            reconstructed: Vec::new(),
+            locked_qc: None,
+            start_view: anchor_view.view_number + 1,
+            last_actioned_view: anchor_view.view_number,
+            state_cert: None,
+        };
+        let new_consensus = |anchor: &RestartAnchor<TestTypes>| {
+            let (public_key, private_key) = BLSPubKey::generated_from_seed_indexed([0; 32], 0);
+            let state_private_key = StateKeyPair::generate_from_seed_indexed([0u8; 32], 0)
+                .sign_key_ref()
+                .clone();
+            Consensus::new(
+                mock_membership(),
+                public_key,
+                private_key,
+                state_private_key,
+                10,
+                test_upgrade_lock(),
+                anchor.leaf.clone(),
+                anchor.epoch_height,
+            )
+        };
+
+        let buffer = Buffer::default();
+        let mut recorder = TraceRecorder::new(buffer.clone()).unwrap();
+        let mut consensus = new_consensus(&anchor);
+        recorder.anchor(&anchor);
+        consensus.seed_anchor(anchor);
+        recorder.start(&consensus);
+        let view = &test_data.views[2];
+        let (proposal, vid_share) = view.proposal_input_consensus(&node_key);
+        let inputs = vec![
+            proposal,
+            vid_share,
+            view.block_reconstructed_input(),
+            view.cert1_input(),
+            view.cert2_input(),
+        ];
+        let n = inputs.len();
+        record(&mut consensus, &mut recorder, inputs, |_, _| {});
+        let trace = buffer.0.lock().clone();
+
+        let report = replay_from_anchor(new_consensus, &trace[..]).unwrap();
+        assert_eq!(report.inputs, n as u64);
+        assert!(report.divergence.is_none(), "{report}");
+
+        let err = replay(ConsensusHarness::new(0).await.consensus, &trace[..]).unwrap_err();
+        assert!(matches!(err, TraceError::StartMismatch { .. }), "{err}");
+    }
+}
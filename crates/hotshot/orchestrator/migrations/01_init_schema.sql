@@ -0,0 +1,10 @@
+-- One row for every benchmark run, updated as more nodes post their results.
+CREATE TABLE run (
+    id          INTEGER PRIMARY KEY AUTOINCREMENT,
+    -- Unix timestamp of the last update of the run, in seconds.
+    recorded_at BIGINT  NOT NULL,
+    commit_sha  TEXT    NOT NULL,
+    -- JSON encoded config and aggregated results of the run.
+    results     TEXT    NOT NULL
+);
+CREATE INDEX run_commit_sha ON run (commit_sha);
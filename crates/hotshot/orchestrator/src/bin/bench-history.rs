@@ -0,0 +1,122 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Inspect the benchmark history recorded by the orchestrator
+
+use std::path::PathBuf;
+
+use anyhow::{Context, bail};
+use clap::{Parser, Subcommand};
+use hotshot_orchestrator::history::{HistoryStore, RegressionThresholds, diff_runs};
+
+#[derive(Parser, Debug)]
+#[command(about = "List and compare benchmark runs recorded by the orchestrator")]
+struct Args {
+    /// The history database the orchestrator writes to
+    #[arg(long, env = "ORCHESTRATOR_HISTORY_DB")]
+    db: PathBuf,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// List the most recent runs
+    List {
+        /// Maximum number of runs to list
+        #[arg(long, default_value_t = 20)]
+        limit: u32,
+    },
+    /// Compare two runs, failing if the candidate regressed
+    ///
+    /// Without run ids the two most recent runs are compared.
+    Diff {
+        /// The run to compare against
+        #[arg(requires = "candidate")]
+        baseline: Option<i64>,
+        /// The run to check for regressions
+        candidate: Option<i64>,
+        /// Maximum allowed drop of the throughput, in percent
+        #[arg(long, default_value_t = RegressionThresholds::default().max_throughput_drop_pct)]
+        max_throughput_drop_pct: f64,
+        /// Maximum allowed increase of the average and percentile latencies, in percent
+        #[arg(long, default_value_t = RegressionThresholds::default().max_latency_increase_pct)]
+        max_latency_increase_pct: f64,
+        /// Maximum allowed increase of the number of failed views
+        #[arg(long, default_value_t = RegressionThresholds::default().max_failed_views_increase)]
+        max_failed_views_increase: u64,
+    },
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let history = HistoryStore::connect(&args.db).await?;
+
+    match args.command {
+        Command::List { limit } => {
+            println!(
+                "{:>6} {:<12} {:<8} {:>6} {:>16} {:>8} {:>8} {:>8}",
+                "run", "commit", "results", "nodes", "throughput B/s", "p50 s", "p99 s", "failed"
+            );
+            for run in history.runs(limit).await? {
+                let results = run.results;
+                println!(
+                    "{:>6} {:<12} {:<8} {:>6} {:>16} {:>8} {:>8} {:>8}",
+                    run.id,
+                    results.commit_sha.get(..12).unwrap_or(&results.commit_sha),
+                    results.partial_results,
+                    results.total_nodes,
+                    results.throughput_bytes_per_sec,
+                    results.p50_latency_in_sec,
+                    results.p99_latency_in_sec,
+                    results.failed_num_views,
+                );
+            }
+        },
+        Command::Diff {
+            baseline,
+            candidate,
+            max_throughput_drop_pct,
+            max_latency_increase_pct,
+            max_failed_views_increase,
+        } => {
+            let (baseline, candidate) = match (baseline, candidate) {
+                (Some(baseline), Some(candidate)) => (
+                    history
+                        .run(baseline)
+                        .await?
+                        .with_context(|| format!("run {baseline} not found"))?,
+                    history
+                        .run(candidate)
+                        .await?
+                        .with_context(|| format!("run {candidate} not found"))?,
+                ),
+                _ => {
+                    let mut runs = history.runs(2).await?;
+                    if runs.len() < 2 {
+                        bail!("the history contains fewer than two runs");
+                    }
+                    let candidate = runs.remove(0);
+                    (runs.remove(0), candidate)
+                },
+            };
+
+            let thresholds = RegressionThresholds {
+                max_throughput_drop_pct,
+                max_latency_increase_pct,
+                max_failed_views_increase,
+            };
+            let diff = diff_runs(&baseline, &candidate, &thresholds);
+            print!("{diff}");
+            if diff.regressed() {
+                bail!("run {} regressed against run {}", candidate.id, baseline.id);
+            }
+        },
+    }
+    Ok(())
+}
@@ -10,6 +10,7 @@ use clap::Parser;
 use futures::{Future, FutureExt};
 use hotshot_types::{
     PeerConfig, ValidatorConfig,
+    fault_schedule::FaultState,
     network::{NetworkConfig, NetworkConfigSource},
     traits::node_implementation::NodeType,
 };
@@ -23,6 +24,7 @@ use vbs::BinarySerializer;
 use crate::OrchestratorVersion;
 
 /// Holds the client connection to the orchestrator
+#[derive(Clone)]
 pub struct OrchestratorClient {
     /// the client
     pub client: Client<ClientErr, OrchestratorVersion>,
@@ -41,6 +43,15 @@ pub struct BenchResults {
     pub minimum_latency_in_sec: i64,
     /// The maximum latency of the transactions
     pub maximum_latency_in_sec: i64,
+    /// The median latency of the transactions
+    #[serde(default)]
+    pub p50_latency_in_sec: i64,
+    /// The 90th percentile latency of the transactions
+    #[serde(default)]
+    pub p90_latency_in_sec: i64,
+    /// The 99th percentile latency of the transactions
+    #[serde(default)]
+    pub p99_latency_in_sec: i64,
     /// The throughput of the consensus protocol = number of transactions committed per second * transaction size in bytes
     pub throughput_bytes_per_sec: u64,
     /// The number of transactions committed during benchmarking
@@ -67,6 +78,10 @@ impl BenchResults {
             "Average latency: {} seconds, Minimum latency: {} seconds, Maximum latency: {} seconds",
             self.avg_latency_in_sec, self.minimum_latency_in_sec, self.maximum_latency_in_sec
         );
+        println!(
+            "Latency percentiles: p50 {} seconds, p90 {} seconds, p99 {} seconds",
+            self.p50_latency_in_sec, self.p90_latency_in_sec, self.p99_latency_in_sec
+        );
         println!("Throughput: {} bytes/sec", self.throughput_bytes_per_sec);
         println!(
             "Total transactions committed: {}",
@@ -111,6 +126,15 @@ pub struct BenchResultsDownloadConfig {
     pub minimum_latency_in_sec: i64,
     /// The maximum latency of the transactions
     pub maximum_latency_in_sec: i64,
+    /// The median latency of the transactions
+    #[serde(default)]
+    pub p50_latency_in_sec: i64,
+    /// The 90th percentile latency of the transactions
+    #[serde(default)]
+    pub p90_latency_in_sec: i64,
+    /// The 99th percentile latency of the transactions
+    #[serde(default)]
+    pub p99_latency_in_sec: i64,
     /// The throughput of the consensus protocol = number of transactions committed per second * transaction size in bytes
     pub throughput_bytes_per_sec: u64,
     /// The number of transactions committed during benchmarking
@@ -123,6 +147,8 @@ pub struct BenchResultsDownloadConfig {
     pub failed_num_views: usize,
     /// The membership committee type used
     pub committee_type: String,
+    /// The fault schedule of the run, as JSON
+    pub fault_schedule: String,
 }
 
 // VALIDATOR
@@ -503,6 +529,17 @@ impl OrchestratorClient {
             .inspect_err(|err| tracing::warn!("{err}"));
     }
 
+    /// Gets the faults the orchestrator's fault schedule applies at `view`
+    /// # Errors
+    /// If the orchestrator could not be reached
+    pub async fn get_faults(&self, view: u64) -> Result<FaultState, ClientErr> {
+        self.client
+            .get(&format!("api/faults/{view}"))
+            .send()
+            .await
+            .inspect_err(|err| tracing::warn!("{err}"))
+    }
+
     /// Generic function that waits for the orchestrator to return a non-error
     /// Returns whatever type the given function returns
     #[instrument(skip_all, name = "waiting for orchestrator")]
@@ -0,0 +1,472 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! History of benchmark runs
+//!
+//! The orchestrator can persist the config and aggregated results of every run to a SQLite
+//! database, so runs of different commits can be compared with [`diff_runs`] to detect
+//! performance regressions.
+
+use std::{
+    fmt,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    SqlitePool, query, query_as,
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+};
+
+use crate::client::BenchResultsDownloadConfig;
+
+/// A benchmark run stored in the history
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RecordedRun {
+    /// The id of the run
+    pub id: i64,
+    /// Unix timestamp of the last update of the run, in seconds
+    pub recorded_at: i64,
+    /// The config and aggregated results of the run
+    pub results: BenchResultsDownloadConfig,
+}
+
+/// Persists benchmark runs to a SQLite database
+#[derive(Clone, Debug)]
+pub struct HistoryStore {
+    /// the connection pool
+    pool: SqlitePool,
+}
+
+impl HistoryStore {
+    /// Open the database at `path`, creating it if it does not exist yet.
+    /// # Errors
+    /// If the database cannot be opened or migrated
+    pub async fn connect(path: &Path) -> anyhow::Result<Self> {
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true);
+        // The orchestrator writes a handful of rows per run, a single connection avoids lock
+        // contention between writers.
+        let pool = SqlitePoolOptions::default()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .with_context(|| format!("failed to open benchmark history {}", path.display()))?;
+        sqlx::migrate!("./migrations")
+            .run(&pool)
+            .await
+            .context("failed to migrate benchmark history")?;
+        Ok(Self { pool })
+    }
+
+    /// Record the results of a run, replacing the previous results of run `id` if given.
+    ///
+    /// Returns the id of the run.
+    /// # Errors
+    /// If the results cannot be written
+    pub async fn record_run(
+        &self,
+        id: Option<i64>,
+        results: &BenchResultsDownloadConfig,
+    ) -> anyhow::Result<i64> {
+        let recorded_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs() as i64)
+            .unwrap_or_default();
+        let json = serde_json::to_string(results)?;
+
+        let id = match id {
+            Some(id) => {
+                query(
+                    "UPDATE run SET recorded_at = $1, commit_sha = $2, results = $3 WHERE id = $4",
+                )
+                .bind(recorded_at)
+                .bind(&results.commit_sha)
+                .bind(json)
+                .bind(id)
+                .execute(&self.pool)
+                .await?;
+                id
+            },
+            None => {
+                let (id,): (i64,) = query_as(
+                    "INSERT INTO run (recorded_at, commit_sha, results) VALUES ($1, $2, $3)
+                        RETURNING id",
+                )
+                .bind(recorded_at)
+                .bind(&results.commit_sha)
+                .bind(json)
+                .fetch_one(&self.pool)
+                .await?;
+                id
+            },
+        };
+        Ok(id)
+    }
+
+    /// The most recent runs, newest first.
+    /// # Errors
+    /// If the history cannot be read
+    pub async fn runs(&self, limit: u32) -> anyhow::Result<Vec<RecordedRun>> {
+        let rows: Vec<(i64, i64, String)> =
+            query_as("SELECT id, recorded_at, results FROM run ORDER BY id DESC LIMIT $1")
+                .bind(i64::from(limit))
+                .fetch_all(&self.pool)
+                .await?;
+        rows.into_iter().map(decode_run).collect()
+    }
+
+    /// The run with the given id, if it exists.
+    /// # Errors
+    /// If the history cannot be read
+    pub async fn run(&self, id: i64) -> anyhow::Result<Option<RecordedRun>> {
+        let row: Option<(i64, i64, String)> =
+            query_as("SELECT id, recorded_at, results FROM run WHERE id = $1")
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await?;
+        row.map(decode_run).transpose()
+    }
+}
+
+/// Decode a row of the `run` table
+fn decode_run((id, recorded_at, results): (i64, i64, String)) -> anyhow::Result<RecordedRun> {
+    Ok(RecordedRun {
+        id,
+        recorded_at,
+        results: serde_json::from_str(&results)
+            .with_context(|| format!("invalid results of run {id}"))?,
+    })
+}
+
+/// How much worse a run may be than its baseline before it counts as a regression
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct RegressionThresholds {
+    /// Maximum allowed drop of the throughput, in percent
+    pub max_throughput_drop_pct: f64,
+    /// Maximum allowed increase of the average and percentile latencies, in percent
+    pub max_latency_increase_pct: f64,
+    /// Maximum allowed increase of the number of failed views
+    pub max_failed_views_increase: u64,
+}
+
+impl Default for RegressionThresholds {
+    fn default() -> Self {
+        Self {
+            max_throughput_drop_pct: 5.0,
+            max_latency_increase_pct: 10.0,
+            max_failed_views_increase: 0,
+        }
+    }
+}
+
+/// The change of a single metric between two runs
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct MetricDiff {
+    /// The name of the metric
+    pub metric: String,
+    /// The value in the baseline run
+    pub baseline: f64,
+    /// The value in the candidate run
+    pub candidate: f64,
+    /// The relative change in percent, `None` if the baseline is zero
+    pub change_pct: Option<f64>,
+    /// Whether the change exceeds the regression threshold
+    pub regression: bool,
+}
+
+impl MetricDiff {
+    /// Compare a metric where lower values are better, allowing an increase of `max_increase`.
+    fn lower_is_better(metric: &str, baseline: f64, candidate: f64, max_increase: f64) -> Self {
+        Self {
+            metric: metric.to_string(),
+            baseline,
+            candidate,
+            change_pct: change_pct(baseline, candidate),
+            regression: candidate - baseline > max_increase,
+        }
+    }
+}
+
+/// The relative change from `baseline` to `candidate`, in percent
+fn change_pct(baseline: f64, candidate: f64) -> Option<f64> {
+    (baseline != 0.0).then(|| (candidate - baseline) / baseline * 100.0)
+}
+
+/// The comparison of a candidate run against a baseline run
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RunDiff {
+    /// The id of the baseline run
+    pub baseline: i64,
+    /// The id of the candidate run
+    pub candidate: i64,
+    /// Config parameters that differ between the runs, which make the comparison unreliable
+    pub config_mismatches: Vec<String>,
+    /// The change of each compared metric
+    pub metrics: Vec<MetricDiff>,
+}
+
+impl RunDiff {
+    /// Whether any metric regressed beyond its threshold
+    #[must_use]
+    pub fn regressed(&self) -> bool {
+        self.metrics.iter().any(|metric| metric.regression)
+    }
+}
+
+impl fmt::Display for RunDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "run {} -> run {}", self.baseline, self.candidate)?;
+        for mismatch in &self.config_mismatches {
+            writeln!(f, "warning: {mismatch}")?;
+        }
+        writeln!(
+            f,
+            "{:<28} {:>16} {:>16} {:>10}",
+            "metric", "baseline", "candidate", "change"
+        )?;
+        for metric in &self.metrics {
+            let change = metric
+                .change_pct
+                .map_or_else(|| "n/a".to_string(), |pct| format!("{pct:+.1}%"));
+            writeln!(
+                f,
+                "{:<28} {:>16} {:>16} {:>10}{}",
+                metric.metric,
+                metric.baseline,
+                metric.candidate,
+                change,
+                if metric.regression {
+                    "  REGRESSION"
+                } else {
+                    ""
+                }
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare `candidate` against `baseline`.
+#[must_use]
+pub fn diff_runs(
+    baseline: &RecordedRun,
+    candidate: &RecordedRun,
+    thresholds: &RegressionThresholds,
+) -> RunDiff {
+    let (old, new) = (&baseline.results, &candidate.results);
+
+    let mut config_mismatches = vec![];
+    let mut check = |name: &str, old: String, new: String| {
+        if old != new {
+            config_mismatches.push(format!("{name} differs: {old} vs {new}"));
+        }
+    };
+    check(
+        "total_nodes",
+        old.total_nodes.to_string(),
+        new.total_nodes.to_string(),
+    );
+    check(
+        "da_committee_size",
+        old.da_committee_size.to_string(),
+        new.da_committee_size.to_string(),
+    );
+    check(
+        "transactions_per_round",
+        old.transactions_per_round.to_string(),
+        new.transactions_per_round.to_string(),
+    );
+    check(
+        "transaction_size",
+        old.transaction_size.to_string(),
+        new.transaction_size.to_string(),
+    );
+    check("rounds", old.rounds.to_string(), new.rounds.to_string());
+    check(
+        "committee_type",
+        old.committee_type.clone(),
+        new.committee_type.clone(),
+    );
+    check(
+        "fault_schedule",
+        old.fault_schedule.clone(),
+        new.fault_schedule.clone(),
+    );
+    check(
+        "partial_results",
+        old.partial_results.clone(),
+        new.partial_results.clone(),
+    );
+
+    let old_throughput = old.throughput_bytes_per_sec as f64;
+    let new_throughput = new.throughput_bytes_per_sec as f64;
+    let mut metrics = vec![MetricDiff {
+        metric: "throughput_bytes_per_sec".to_string(),
+        baseline: old_throughput,
+        candidate: new_throughput,
+        change_pct: change_pct(old_throughput, new_throughput),
+        regression: old_throughput - new_throughput
+            > old_throughput * thresholds.max_throughput_drop_pct / 100.0,
+    }];
+
+    for (metric, old, new) in [
+        (
+            "avg_latency_in_sec",
+            old.avg_latency_in_sec,
+            new.avg_latency_in_sec,
+        ),
+        (
+            "p50_latency_in_sec",
+            old.p50_latency_in_sec,
+            new.p50_latency_in_sec,
+        ),
+        (
+            "p90_latency_in_sec",
+            old.p90_latency_in_sec,
+            new.p90_latency_in_sec,
+        ),
+        (
+            "p99_latency_in_sec",
+            old.p99_latency_in_sec,
+            new.p99_latency_in_sec,
+        ),
+    ] {
+        let old = old as f64;
+        metrics.push(MetricDiff::lower_is_better(
+            metric,
+            old,
+            new as f64,
+            old * thresholds.max_latency_increase_pct / 100.0,
+        ));
+    }
+
+    metrics.push(MetricDiff::lower_is_better(
+        "failed_num_views",
+        old.failed_num_views as f64,
+        new.failed_num_views as f64,
+        thresholds.max_failed_views_increase as f64,
+    ));
+
+    RunDiff {
+        baseline: baseline.id,
+        candidate: candidate.id,
+        config_mismatches,
+        metrics,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn results(
+        throughput: u64,
+        p99_latency: i64,
+        failed_views: usize,
+    ) -> BenchResultsDownloadConfig {
+        BenchResultsDownloadConfig {
+            commit_sha: "abc".to_string(),
+            total_nodes: 10,
+            partial_results: "Full".to_string(),
+            avg_latency_in_sec: 2,
+            p50_latency_in_sec: 2,
+            p90_latency_in_sec: 3,
+            p99_latency_in_sec: p99_latency,
+            throughput_bytes_per_sec: throughput,
+            failed_num_views: failed_views,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_history_roundtrip() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("history.db");
+
+        let store = HistoryStore::connect(&path).await.unwrap();
+        let id = store.record_run(None, &results(100, 4, 0)).await.unwrap();
+        // Later partial results of the same run replace the earlier ones
+        assert_eq!(
+            store
+                .record_run(Some(id), &results(200, 4, 0))
+                .await
+                .unwrap(),
+            id
+        );
+        let second = store.record_run(None, &results(300, 4, 0)).await.unwrap();
+        drop(store);
+
+        let store = HistoryStore::connect(&path).await.unwrap();
+        let runs = store.runs(10).await.unwrap();
+        assert_eq!(
+            runs.iter().map(|run| run.id).collect::<Vec<_>>(),
+            vec![second, id]
+        );
+        assert_eq!(runs[1].results, results(200, 4, 0));
+        assert_eq!(store.run(id).await.unwrap().unwrap(), runs[1]);
+        assert!(store.run(second + 1).await.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_diff_runs() {
+        let run = |id, results| RecordedRun {
+            id,
+            recorded_at: 0,
+            results,
+        };
+        let thresholds = RegressionThresholds::default();
+
+        // Within the thresholds
+        let diff = diff_runs(
+            &run(1, results(1000, 10, 1)),
+            &run(2, results(960, 11, 1)),
+            &thresholds,
+        );
+        assert!(!diff.regressed(), "{diff}");
+        assert!(diff.config_mismatches.is_empty());
+
+        // Throughput drop, latency increase and more failed views
+        let diff = diff_runs(
+            &run(1, results(1000, 10, 1)),
+            &run(2, results(900, 12, 2)),
+            &thresholds,
+        );
+        let regressed: Vec<_> = diff
+            .metrics
+            .iter()
+            .filter(|metric| metric.regression)
+            .map(|metric| metric.metric.as_str())
+            .collect();
+        assert_eq!(
+            regressed,
+            [
+                "throughput_bytes_per_sec",
+                "p99_latency_in_sec",
+                "failed_num_views"
+            ]
+        );
+
+        // Improvements are never regressions
+        let diff = diff_runs(
+            &run(1, results(1000, 10, 2)),
+            &run(2, results(2000, 5, 0)),
+            &thresholds,
+        );
+        assert!(!diff.regressed(), "{diff}");
+
+        let mut other = results(1000, 10, 1);
+        other.total_nodes = 20;
+        let diff = diff_runs(&run(1, results(1000, 10, 1)), &run(2, other), &thresholds);
+        assert_eq!(diff.config_mismatches, ["total_nodes differs: 10 vs 20"]);
+    }
+}
@@ -8,12 +8,15 @@
 
 /// The orchestrator's clients
 pub mod client;
+/// History of benchmark runs
+pub mod history;
 
 use std::{
     collections::{HashMap, HashSet},
     fs,
     fs::OpenOptions,
     io,
+    path::{Path, PathBuf},
     sync::Arc,
     time::Duration,
 };
@@ -23,16 +26,18 @@ use async_lock::RwLock;
 use axum::{
     Router,
     body::Bytes,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::{HeaderMap, StatusCode},
     response::Response,
     routing::{get, post},
 };
 use client::{BenchResults, BenchResultsDownloadConfig};
-use csv::Writer;
+use csv::{Writer, WriterBuilder};
 use futures::{StreamExt, stream::FuturesUnordered};
+use history::{HistoryStore, RecordedRun, RegressionThresholds, RunDiff, diff_runs};
 use hotshot_types::{
     PeerConfig,
+    fault_schedule::FaultState,
     network::{BuilderType, NetworkConfig, PublicKeysFile},
     traits::{
         node_implementation::NodeType,
@@ -61,6 +66,9 @@ pub type OrchestratorVersion =
 /// Orchestrator Version as a type-binding instance
 pub const ORCHESTRATOR_VERSION: OrchestratorVersion = StaticVersion {};
 
+/// Number of runs returned by the history listing if no limit is given
+const DEFAULT_HISTORY_LIMIT: u32 = 20;
+
 /// Generate an keypair based on a `seed` and an `index`
 /// # Panics
 /// This panics if libp2p is unable to generate a secret key from the seed
@@ -105,6 +113,10 @@ struct OrchestratorState<TYPES: NodeType> {
     builders: Vec<Url>,
     /// whether we are using a fixed stake table, disabling public key registration
     fixed_stake_table: bool,
+    /// Where the results of each run are persisted, if enabled
+    history: Option<HistoryStore>,
+    /// The id of this run in the history, once it has results
+    history_run_id: Option<i64>,
 }
 
 impl<TYPES: NodeType> OrchestratorState<TYPES> {
@@ -144,12 +156,14 @@ impl<TYPES: NodeType> OrchestratorState<TYPES> {
             accepting_new_keys: true,
             builders,
             fixed_stake_table,
+            history: None,
+            history_run_id: None,
         }
     }
 
-    /// Output the results to a csv file according to orchestrator state
-    pub fn output_to_csv(&self) {
-        let output_csv = BenchResultsDownloadConfig {
+    /// The config and aggregated results of the run
+    fn results_summary(&self) -> BenchResultsDownloadConfig {
+        BenchResultsDownloadConfig {
             commit_sha: self.config.commit_sha.clone(),
             total_nodes: self.config.config.num_nodes_with_stake.into(),
             da_committee_size: self.config.config.da_staked_committee_size,
@@ -161,27 +175,90 @@ impl<TYPES: NodeType> OrchestratorState<TYPES> {
             avg_latency_in_sec: self.bench_results.avg_latency_in_sec,
             minimum_latency_in_sec: self.bench_results.minimum_latency_in_sec,
             maximum_latency_in_sec: self.bench_results.maximum_latency_in_sec,
+            p50_latency_in_sec: self.bench_results.p50_latency_in_sec,
+            p90_latency_in_sec: self.bench_results.p90_latency_in_sec,
+            p99_latency_in_sec: self.bench_results.p99_latency_in_sec,
             throughput_bytes_per_sec: self.bench_results.throughput_bytes_per_sec,
             total_transactions_committed: self.bench_results.total_transactions_committed,
             total_time_elapsed_in_sec: self.bench_results.total_time_elapsed_in_sec,
             total_num_views: self.bench_results.total_num_views,
             failed_num_views: self.bench_results.failed_num_views,
             committee_type: self.bench_results.committee_type.clone(),
+            fault_schedule: serde_json::to_string(&self.config.fault_schedule).unwrap_or_default(),
+        }
+    }
+
+    /// Output the results to a csv file according to orchestrator state
+    pub fn output_to_csv(&self) {
+        let path = Path::new("scripts/benchmarks_results/results.csv");
+        match append_results_csv(path, &self.results_summary()) {
+            Ok(()) => println!("Results successfully saved in {}", path.display()),
+            Err(err) => tracing::error!("Failed to save results in {}: {err}", path.display()),
+        }
+    }
+
+    /// Persist the results of the run to the history, if enabled
+    async fn record_history(&mut self) {
+        let Some(history) = &self.history else {
+            return;
         };
-        // Open the CSV file in append mode
-        let results_csv_file = OpenOptions::new()
-            .create(true)
-            .append(true) // Open in append mode
-            .open("scripts/benchmarks_results/results.csv")
-            .unwrap();
-        // Open a file for writing
-        let mut wtr = Writer::from_writer(results_csv_file);
-        let _ = wtr.serialize(output_csv);
-        let _ = wtr.flush();
-        println!("Results successfully saved in scripts/benchmarks_results/results.csv");
+        match history
+            .record_run(self.history_run_id, &self.results_summary())
+            .await
+        {
+            Ok(id) => {
+                println!("Results saved as run {id} in the benchmark history");
+                self.history_run_id = Some(id);
+            },
+            Err(err) => tracing::error!("Failed to record benchmark history: {err:#}"),
+        }
     }
 }
 
+/// Append `results` to the csv file at `path`.
+///
+/// The header is only written to a new file. If the existing file has a different header, e.g.
+/// because it was written before a column was added, it is moved aside and a new file is started.
+fn append_results_csv(path: &Path, results: &BenchResultsDownloadConfig) -> io::Result<()> {
+    let mut row = Writer::from_writer(vec![]);
+    row.serialize(results)?;
+    let row = row.into_inner().map_err(|err| err.into_error())?;
+    let header = row.split(|&byte| byte == b'\n').next().unwrap_or_default();
+
+    let existing_header = match fs::read(path) {
+        Ok(contents) => contents
+            .split(|&byte| byte == b'\n')
+            .next()
+            .map(<[u8]>::to_vec)
+            .filter(|line| !line.is_empty()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+        Err(err) => return Err(err),
+    };
+    let has_header = match existing_header {
+        Some(existing) if existing == header => true,
+        Some(_) => {
+            let mut old = path.as_os_str().to_owned();
+            old.push(".old");
+            let old = PathBuf::from(old);
+            tracing::warn!(
+                "The header of {} is outdated, moving it to {}",
+                path.display(),
+                old.display()
+            );
+            fs::rename(path, old)?;
+            false
+        },
+        None => false,
+    };
+
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let mut writer = WriterBuilder::new()
+        .has_headers(!has_header)
+        .from_writer(file);
+    writer.serialize(results)?;
+    writer.flush()
+}
+
 /// An api exposed by the orchestrator
 pub trait OrchestratorApi<TYPES: NodeType> {
     /// Post an identity to the orchestrator. Takes in optional
@@ -243,6 +320,10 @@ pub trait OrchestratorApi<TYPES: NodeType> {
     /// # Errors
     /// if not all builders are registered yet
     fn get_builders(&self) -> Result<Vec<Url>, ServerError>;
+    /// get endpoint for the faults nodes should apply at `view`
+    /// # Errors
+    /// if unable to serve
+    fn get_faults(&self, view: u64) -> Result<FaultState, ServerError>;
 }
 
 impl<TYPES: NodeType> OrchestratorState<TYPES>
@@ -608,6 +689,16 @@ where
                 self.bench_results.maximum_latency_in_sec = metrics
                     .maximum_latency_in_sec
                     .max(cur_metrics.maximum_latency_in_sec);
+                // Percentiles cannot be merged, report those of the slowest node
+                self.bench_results.p50_latency_in_sec = metrics
+                    .p50_latency_in_sec
+                    .max(cur_metrics.p50_latency_in_sec);
+                self.bench_results.p90_latency_in_sec = metrics
+                    .p90_latency_in_sec
+                    .max(cur_metrics.p90_latency_in_sec);
+                self.bench_results.p99_latency_in_sec = metrics
+                    .p99_latency_in_sec
+                    .max(cur_metrics.p99_latency_in_sec);
                 self.bench_results.throughput_bytes_per_sec = metrics
                     .throughput_bytes_per_sec
                     .max(cur_metrics.throughput_bytes_per_sec);
@@ -669,6 +760,10 @@ where
         }
         Ok(self.builders.clone())
     }
+
+    fn get_faults(&self, view: u64) -> Result<FaultState, ServerError> {
+        Ok(self.config.fault_schedule.state_at(view))
+    }
 }
 
 /// Shared, lock-guarded orchestrator state, cloned into every axum handler via `State`.
@@ -833,7 +928,98 @@ async fn post_results<TYPES: NodeType>(
     // Panics on a malformed body: the only production
     // caller (`client.rs`'s `post_bench_results`) always sends well-formed JSON.
     let metrics: BenchResults = serde_json::from_slice(&body).unwrap();
-    let result = state.write().await.post_run_results(metrics);
+    let mut state = state.write().await;
+    let stage = state.bench_results.partial_results.clone();
+    let result = state.post_run_results(metrics);
+    // Record the run whenever the aggregated results were written out
+    if state.bench_results.partial_results != stage {
+        state.record_history().await;
+    }
+    drop(state);
+    respond(&headers, result)
+}
+
+/// The history store of the orchestrator, or an error if history is disabled
+async fn history_store<TYPES: NodeType>(
+    state: &SharedOrchestratorState<TYPES>,
+) -> Result<HistoryStore, ServerError> {
+    state
+        .read()
+        .await
+        .history
+        .clone()
+        .ok_or_else(|| ServerError {
+            status: StatusCode::NOT_FOUND,
+            message: "Benchmark history is not enabled".to_string(),
+        })
+}
+
+/// A run from the history, or a not found error
+async fn history_run(history: &HistoryStore, id: i64) -> Result<RecordedRun, ServerError> {
+    history
+        .run(id)
+        .await
+        .map_err(history_error)?
+        .ok_or_else(|| ServerError {
+            status: StatusCode::NOT_FOUND,
+            message: format!("Run {id} not found"),
+        })
+}
+
+fn history_error(err: anyhow::Error) -> ServerError {
+    ServerError {
+        status: StatusCode::INTERNAL_SERVER_ERROR,
+        message: format!("{err:#}"),
+    }
+}
+
+/// Query parameters of the history listing
+#[derive(serde::Deserialize)]
+struct HistoryQuery {
+    /// maximum number of runs to return
+    limit: Option<u32>,
+}
+
+async fn get_history<TYPES: NodeType>(
+    State(state): State<SharedOrchestratorState<TYPES>>,
+    Query(query): Query<HistoryQuery>,
+    headers: HeaderMap,
+) -> Response {
+    let result = match history_store(&state).await {
+        Ok(history) => history
+            .runs(query.limit.unwrap_or(DEFAULT_HISTORY_LIMIT))
+            .await
+            .map_err(history_error),
+        Err(err) => Err(err),
+    };
+    respond(&headers, result)
+}
+
+async fn get_history_run<TYPES: NodeType>(
+    State(state): State<SharedOrchestratorState<TYPES>>,
+    Path(id): Path<i64>,
+    headers: HeaderMap,
+) -> Response {
+    let result = match history_store(&state).await {
+        Ok(history) => history_run(&history, id).await,
+        Err(err) => Err(err),
+    };
+    respond(&headers, result)
+}
+
+async fn get_history_diff<TYPES: NodeType>(
+    State(state): State<SharedOrchestratorState<TYPES>>,
+    Path((baseline, candidate)): Path<(i64, i64)>,
+    Query(thresholds): Query<RegressionThresholds>,
+    headers: HeaderMap,
+) -> Response {
+    let result: Result<RunDiff, ServerError> = async {
+        let history = history_store(&state).await?;
+        let baseline = history_run(&history, baseline).await?;
+        let candidate = history_run(&history, candidate).await?;
+        Ok(diff_runs(&baseline, &candidate, &thresholds))
+    }
+    .await;
     respond(&headers, result)
 }
 
@@ -877,6 +1063,15 @@ async fn get_builders<TYPES: NodeType>(
     respond(&headers, result)
 }
 
+async fn get_faults<TYPES: NodeType>(
+    State(state): State<SharedOrchestratorState<TYPES>>,
+    Path(view): Path<u64>,
+    headers: HeaderMap,
+) -> Response {
+    let result = state.read().await.get_faults(view);
+    respond(&headers, result)
+}
+
 /// Builds the `api` module's routes. Existing clients call these both directly (e.g.
 /// `api/identity`) and under a major-version prefix (`v0/api/identity`), so the `/api` tree is
 /// mounted at the root and under `/v0`.
@@ -898,6 +1093,13 @@ fn api_router<TYPES: NodeType>() -> Router<SharedOrchestratorState<TYPES>> {
         .route("/manual_start", post(post_manual_start::<TYPES>))
         .route("/builders", get(get_builders::<TYPES>))
         .route("/builder", post(post_builder::<TYPES>))
+        .route("/faults/{view}", get(get_faults::<TYPES>))
+        .route("/history", get(get_history::<TYPES>))
+        .route("/history/{id}", get(get_history_run::<TYPES>))
+        .route(
+            "/history/diff/{baseline}/{candidate}",
+            get(get_history_diff::<TYPES>),
+        )
 }
 
 /// Builds the full router: the app-level `healthcheck`, plus the `api` module served both
@@ -973,8 +1175,19 @@ pub async fn run_orchestrator<TYPES: NodeType>(
         })
         .collect();
 
-    let state: SharedOrchestratorState<TYPES> =
-        Arc::new(RwLock::new(OrchestratorState::new(network_config)));
+    network_config
+        .fault_schedule
+        .validate(network_config.config.num_nodes_with_stake.get())
+        .map_err(|err| io::Error::other(format!("invalid fault schedule: {err}")))?;
+
+    let mut state = OrchestratorState::new(network_config);
+    if let Ok(path) = std::env::var("ORCHESTRATOR_HISTORY_DB") {
+        let history = HistoryStore::connect(&PathBuf::from(path))
+            .await
+            .map_err(|err| io::Error::other(format!("{err:#}")))?;
+        state.history = Some(history);
+    }
+    let state: SharedOrchestratorState<TYPES> = Arc::new(RwLock::new(state));
     let app = app::<TYPES>(state);
 
     let host = url
@@ -993,6 +1206,7 @@ pub async fn run_orchestrator<TYPES: NodeType>(
 mod tests {
     use axum::http::{Request, header};
     use hotshot_example_types::node_types::TestTypes;
+    use hotshot_types::fault_schedule::{FaultAction, FaultEvent, FaultSchedule};
 
     use super::*;
 
@@ -1034,4 +1248,91 @@ mod tests {
             assert_ne!(resp.status(), StatusCode::NOT_FOUND, "{uri} did not route");
         }
     }
+
+    /// The history routes report that history is disabled unless a database is configured.
+    #[tokio::test]
+    async fn history_routes_require_database() {
+        for uri in ["/api/history", "/api/history/1", "/api/history/diff/1/2"] {
+            let req = Request::builder()
+                .uri(uri)
+                .body(axum::body::Body::empty())
+                .unwrap();
+            let resp = tower::ServiceExt::oneshot(test_app(), req).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::NOT_FOUND, "{uri}");
+            let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert!(
+                String::from_utf8_lossy(&body).contains("not enabled"),
+                "{uri}"
+            );
+        }
+    }
+
+    /// Nodes get the faults of the requested view from the configured schedule.
+    #[tokio::test]
+    async fn faults_follow_schedule() {
+        let mut config = NetworkConfig::<TestTypes>::default();
+        config.fault_schedule = FaultSchedule {
+            events: vec![FaultEvent {
+                view: 5,
+                action: FaultAction::Kill { nodes: vec![1] },
+            }],
+        };
+        let app = app::<TestTypes>(Arc::new(RwLock::new(OrchestratorState::new(config))));
+
+        for (view, killed) in [(4, false), (5, true)] {
+            let req = Request::builder()
+                .uri(format!("/api/faults/{view}"))
+                .body(axum::body::Body::empty())
+                .unwrap();
+            let resp = tower::ServiceExt::oneshot(app.clone(), req).await.unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+            let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let state: FaultState = serde_json::from_slice(&body).unwrap();
+            assert_eq!(state.is_killed(1), killed, "view {view}");
+        }
+    }
+
+    /// Results are appended under a single header, and a file with an outdated header is moved
+    /// aside instead of getting rows that do not match it.
+    #[test]
+    fn results_csv_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("results.csv");
+        let results = BenchResultsDownloadConfig {
+            fault_schedule: "[]".to_string(),
+            ..Default::default()
+        };
+
+        append_results_csv(&path, &results).unwrap();
+        append_results_csv(&path, &results).unwrap();
+        let mut reader = csv::Reader::from_path(&path).unwrap();
+        assert!(
+            reader
+                .headers()
+                .unwrap()
+                .iter()
+                .any(|column| column == "fault_schedule")
+        );
+        let rows: Vec<BenchResultsDownloadConfig> =
+            reader.deserialize().collect::<Result<_, _>>().unwrap();
+        assert_eq!(rows, vec![results.clone(), results.clone()]);
+
+        // A file written before the fault schedule was recorded
+        fs::write(&path, "commit_sha,total_nodes\nabc,4\n").unwrap();
+        append_results_csv(&path, &results).unwrap();
+        let rows: Vec<BenchResultsDownloadConfig> = csv::Reader::from_path(&path)
+            .unwrap()
+            .deserialize()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(rows, vec![results]);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("results.csv.old")).unwrap(),
+            "commit_sha,total_nodes\nabc,4\n"
+        );
+    }
 }
@@ -0,0 +1,350 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Timelines of network faults for benchmark runs.
+//!
+//! A [`FaultSchedule`] is part of the orchestrator's network config. Nodes ask the orchestrator
+//! for the [`FaultState`] of the current view and apply it to their network, so that every node
+//! of a run sees the same faults at the same views.
+
+use std::{collections::BTreeSet, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// A fault that takes effect from `view` onwards.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct FaultEvent {
+    /// the first view the fault applies to
+    pub view: u64,
+    /// what happens at that view
+    pub action: FaultAction,
+}
+
+/// A change to the network conditions.
+///
+/// Nodes are identified by the index the orchestrator assigned to them.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FaultAction {
+    /// Stop the given nodes from sending or receiving any messages.
+    Kill {
+        /// the nodes to kill
+        nodes: Vec<u64>,
+    },
+    /// Bring killed nodes back.
+    Restart {
+        /// the nodes to restart
+        nodes: Vec<u64>,
+    },
+    /// Split the network into groups that cannot reach each other.
+    ///
+    /// Nodes that are not listed in any group form one additional group.
+    Partition {
+        /// the disjoint groups of nodes
+        groups: Vec<Vec<u64>>,
+    },
+    /// Remove all partitions and link delays.
+    Heal,
+    /// Delay every message sent from a node in `from` to a node in `to`.
+    Delay(LinkDelay),
+}
+
+/// Extra latency on the links between two sets of nodes.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LinkDelay {
+    /// the senders affected, all nodes if empty
+    #[serde(default)]
+    pub from: Vec<u64>,
+    /// the recipients affected, all nodes if empty
+    #[serde(default)]
+    pub to: Vec<u64>,
+    /// the delay in milliseconds
+    pub delay_ms: u64,
+}
+
+impl LinkDelay {
+    /// Whether the delay applies to messages from `from` to `to`.
+    #[must_use]
+    pub fn applies(&self, from: u64, to: u64) -> bool {
+        (self.from.is_empty() || self.from.contains(&from))
+            && (self.to.is_empty() || self.to.contains(&to))
+    }
+}
+
+/// Error in a fault schedule
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum FaultScheduleError {
+    /// A fault refers to a node that is not part of the network
+    #[error("fault at view {view} refers to node {node}, but the network has {num_nodes} nodes")]
+    UnknownNode {
+        /// view of the fault
+        view: u64,
+        /// the unknown node index
+        node: u64,
+        /// number of nodes in the network
+        num_nodes: usize,
+    },
+    /// A node is listed in more than one group of a partition
+    #[error("partition at view {view} lists node {node} in more than one group")]
+    OverlappingPartition {
+        /// view of the partition
+        view: u64,
+        /// the node listed twice
+        node: u64,
+    },
+}
+
+/// The faults to inject during a run, in any order.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct FaultSchedule {
+    /// the fault events
+    pub events: Vec<FaultEvent>,
+}
+
+impl FaultSchedule {
+    /// Whether the schedule contains no faults.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Check that the schedule only refers to existing nodes and that partitions are disjoint.
+    ///
+    /// # Errors
+    /// Returns the first problem found in the schedule.
+    pub fn validate(&self, num_nodes: usize) -> Result<(), FaultScheduleError> {
+        for event in &self.events {
+            let view = event.view;
+            let nodes: Vec<u64> = match &event.action {
+                FaultAction::Kill { nodes } | FaultAction::Restart { nodes } => nodes.clone(),
+                FaultAction::Partition { groups } => {
+                    let mut seen = BTreeSet::new();
+                    for &node in groups.iter().flatten() {
+                        if !seen.insert(node) {
+                            return Err(FaultScheduleError::OverlappingPartition { view, node });
+                        }
+                    }
+                    seen.into_iter().collect()
+                },
+                FaultAction::Heal => vec![],
+                FaultAction::Delay(delay) => delay.from.iter().chain(&delay.to).copied().collect(),
+            };
+            if let Some(&node) = nodes.iter().find(|&&node| node >= num_nodes as u64) {
+                return Err(FaultScheduleError::UnknownNode {
+                    view,
+                    node,
+                    num_nodes,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// The faults in effect at `view`, after applying all events up to and including `view`.
+    ///
+    /// Events of the same view are applied in the order they are listed.
+    #[must_use]
+    pub fn state_at(&self, view: u64) -> FaultState {
+        let mut events: Vec<_> = self.events.iter().filter(|e| e.view <= view).collect();
+        events.sort_by_key(|e| e.view);
+
+        let mut state = FaultState::default();
+        for event in events {
+            match &event.action {
+                FaultAction::Kill { nodes } => state.killed.extend(nodes),
+                FaultAction::Restart { nodes } => {
+                    for node in nodes {
+                        state.killed.remove(node);
+                    }
+                },
+                FaultAction::Partition { groups } => {
+                    state.partitions = groups
+                        .iter()
+                        .map(|group| group.iter().copied().collect())
+                        .collect();
+                },
+                FaultAction::Heal => {
+                    state.partitions.clear();
+                    state.delays.clear();
+                },
+                FaultAction::Delay(delay) => state.delays.push(delay.clone()),
+            }
+        }
+        state
+    }
+}
+
+/// The network faults in effect at a given view.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FaultState {
+    /// nodes that are down
+    pub killed: BTreeSet<u64>,
+    /// groups of nodes that can only reach each other, empty if the network is not partitioned
+    pub partitions: Vec<BTreeSet<u64>>,
+    /// extra latency on some links
+    pub delays: Vec<LinkDelay>,
+}
+
+impl FaultState {
+    /// Whether no faults are in effect.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.killed.is_empty() && self.partitions.is_empty() && self.delays.is_empty()
+    }
+
+    /// Whether `node` is down.
+    #[must_use]
+    pub fn is_killed(&self, node: u64) -> bool {
+        self.killed.contains(&node)
+    }
+
+    /// Whether a message from `from` reaches `to`.
+    #[must_use]
+    pub fn can_communicate(&self, from: u64, to: u64) -> bool {
+        !self.is_killed(from) && !self.is_killed(to) && self.group(from) == self.group(to)
+    }
+
+    /// The extra latency of messages from `from` to `to`, the largest matching delay.
+    #[must_use]
+    pub fn link_delay(&self, from: u64, to: u64) -> Duration {
+        self.delays
+            .iter()
+            .filter(|delay| delay.applies(from, to))
+            .map(|delay| Duration::from_millis(delay.delay_ms))
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// The partition `node` belongs to, `None` for the implicit group of unlisted nodes.
+    fn group(&self, node: u64) -> Option<usize> {
+        self.partitions
+            .iter()
+            .position(|group| group.contains(&node))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule() -> FaultSchedule {
+        FaultSchedule {
+            events: vec![
+                FaultEvent {
+                    view: 10,
+                    action: FaultAction::Kill { nodes: vec![0, 1] },
+                },
+                FaultEvent {
+                    view: 20,
+                    action: FaultAction::Partition {
+                        groups: vec![vec![2, 3]],
+                    },
+                },
+                FaultEvent {
+                    view: 20,
+                    action: FaultAction::Restart { nodes: vec![1] },
+                },
+                FaultEvent {
+                    view: 30,
+                    action: FaultAction::Delay(LinkDelay {
+                        from: vec![4],
+                        to: vec![],
+                        delay_ms: 500,
+                    }),
+                },
+                FaultEvent {
+                    view: 40,
+                    action: FaultAction::Heal,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_fault_schedule_state() {
+        let schedule = schedule();
+        assert!(schedule.state_at(9).is_empty());
+
+        let state = schedule.state_at(10);
+        assert!(state.is_killed(0) && state.is_killed(1));
+        assert!(!state.can_communicate(0, 2));
+        assert!(state.can_communicate(2, 3));
+
+        let state = schedule.state_at(25);
+        assert!(state.is_killed(0) && !state.is_killed(1));
+        assert!(state.can_communicate(2, 3));
+        assert!(state.can_communicate(1, 4));
+        assert!(!state.can_communicate(1, 2));
+
+        let state = schedule.state_at(30);
+        assert_eq!(state.link_delay(4, 1), Duration::from_millis(500));
+        assert_eq!(state.link_delay(1, 4), Duration::ZERO);
+
+        let state = schedule.state_at(40);
+        assert!(state.can_communicate(1, 2));
+        assert_eq!(state.link_delay(4, 1), Duration::ZERO);
+        assert!(state.is_killed(0));
+    }
+
+    #[test]
+    fn test_fault_schedule_validate() {
+        assert!(schedule().validate(5).is_ok());
+        assert_eq!(
+            schedule().validate(4),
+            Err(FaultScheduleError::UnknownNode {
+                view: 30,
+                node: 4,
+                num_nodes: 4
+            })
+        );
+
+        let overlapping = FaultSchedule {
+            events: vec![FaultEvent {
+                view: 1,
+                action: FaultAction::Partition {
+                    groups: vec![vec![0, 1], vec![1, 2]],
+                },
+            }],
+        };
+        assert_eq!(
+            overlapping.validate(3),
+            Err(FaultScheduleError::OverlappingPartition { view: 1, node: 1 })
+        );
+    }
+
+    #[test]
+    fn test_fault_schedule_toml() {
+        let toml = r#"
+            [[faults]]
+            view = 10
+            action = { kill = { nodes = [0] } }
+
+            [[faults]]
+            view = 20
+            action = { delay = { to = [1], delay_ms = 200 } }
+
+            [[faults]]
+            view = 30
+            action = "heal"
+        "#;
+        #[derive(Deserialize)]
+        struct Config {
+            faults: FaultSchedule,
+        }
+        let config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(config.faults.events.len(), 3);
+        assert_eq!(
+            config.faults.events[1].action,
+            FaultAction::Delay(LinkDelay {
+                from: vec![],
+                to: vec![1],
+                delay_ms: 200
+            })
+        );
+    }
+}
@@ -36,6 +36,8 @@ pub mod drb;
 pub mod epoch_membership;
 pub mod error;
 pub mod event;
+/// Timelines of network faults for benchmark runs.
+pub mod fault_schedule;
 /// Holds the configuration file specification for a HotShot node.
 pub mod hotshot_config_file;
 pub mod light_client;
@@ -19,6 +19,7 @@ use crate::{
         ORCHESTRATOR_DEFAULT_NUM_ROUNDS, ORCHESTRATOR_DEFAULT_TRANSACTION_SIZE,
         ORCHESTRATOR_DEFAULT_TRANSACTIONS_PER_ROUND, REQUEST_DATA_DELAY,
     },
+    fault_schedule::FaultSchedule,
     hotshot_config_file::HotShotConfigFile,
 };
 
@@ -154,6 +155,9 @@ pub struct NetworkConfig<TYPES: NodeType> {
     pub random_builder: Option<RandomBuilderConfig>,
     /// The list of public keys that are allowed to connect to the orchestrator
     pub public_keys: Vec<PeerConfigKeys<TYPES>>,
+    /// faults to inject into the network during the run
+    #[serde(default)]
+    pub fault_schedule: FaultSchedule,
 }
 
 /// the source of the network config
@@ -296,6 +300,7 @@ impl<TYPES: NodeType> Default for NetworkConfig<TYPES> {
             builder: BuilderType::default(),
             random_builder: None,
             public_keys: vec![],
+            fault_schedule: FaultSchedule::default(),
         }
     }
 }
@@ -359,6 +364,9 @@ pub struct NetworkConfigFile<TYPES: NodeType> {
     /// If nonempty, this list becomes the stake table and is used to determine DA membership (ignoring the node's request).
     #[serde(default)]
     pub public_keys: Vec<PeerConfigKeys<TYPES>>,
+    /// faults to inject into the network during the run, see [`FaultSchedule`]
+    #[serde(default)]
+    pub fault_schedule: FaultSchedule,
 }
 
 impl<TYPES: NodeType> From<NetworkConfigFile<TYPES>> for NetworkConfig<TYPES> {
@@ -390,6 +398,7 @@ impl<TYPES: NodeType> From<NetworkConfigFile<TYPES>> for NetworkConfig<TYPES> {
             builder: val.builder,
             random_builder: val.random_builder,
             public_keys: val.public_keys,
+            fault_schedule: val.fault_schedule,
         }
     }
 }
@@ -47,10 +47,7 @@ use sha2::Sha256;
 #[must_use]
 #[memoize::memoize(SharedCache, Capacity: 10)]
 pub fn advz_scheme(num_storage_nodes: usize) -> ADVZScheme {
-    // recovery_threshold is currently num_storage_nodes rounded down to a power of two
-    // TODO recovery_threshold should be a function of the desired erasure code rate
-    // https://github.com/EspressoSystems/HotShot/issues/2152
-    let recovery_threshold = 1 << num_storage_nodes.ilog2();
+    let recovery_threshold = advz_recovery_threshold(num_storage_nodes) as u32;
 
     #[allow(clippy::panic)]
     let num_storage_nodes = u32::try_from(num_storage_nodes).unwrap_or_else(|err| {
@@ -70,6 +67,18 @@ pub fn advz_scheme(num_storage_nodes: usize) -> ADVZScheme {
     )
 }
 
+/// Number of shares needed to recover a payload dispersed by [`advz_scheme`].
+///
+/// # Panics
+/// If `num_storage_nodes` is zero.
+#[must_use]
+pub fn advz_recovery_threshold(num_storage_nodes: usize) -> usize {
+    // recovery_threshold is currently num_storage_nodes rounded down to a power of two
+    // TODO recovery_threshold should be a function of the desired erasure code rate
+    // https://github.com/EspressoSystems/HotShot/issues/2152
+    1 << num_storage_nodes.ilog2()
+}
+
 /// VID commitment type
 pub type ADVZCommitment = <ADVZScheme as VidScheme>::Commit;
 /// VID common type
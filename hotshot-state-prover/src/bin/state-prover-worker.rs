@@ -0,0 +1,50 @@
+use std::net::{Ipv4Addr, SocketAddr};
+
+use clap::Parser;
+use espresso_utils::logging;
+use hotshot_state_prover::v3::prover::{LocalProver, WorkerOptions, run_worker};
+use hotshot_types::light_client::DEFAULT_STAKE_TABLE_CAPACITY;
+
+/// Generate light client state update proofs on behalf of a remote `state-prover`.
+#[derive(Parser)]
+struct Args {
+    /// Port to serve the proving job queue on.
+    #[clap(
+        short,
+        long,
+        env = "ESPRESSO_STATE_PROVER_WORKER_PORT",
+        default_value = "8090"
+    )]
+    port: u16,
+
+    /// Stake table capacity for the prover circuit
+    #[clap(short, long, env = "ESPRESSO_STAKE_TABLE_CAPACITY", default_value_t = DEFAULT_STAKE_TABLE_CAPACITY)]
+    stake_table_capacity: usize,
+
+    /// Maximum number of jobs waiting to be proved.
+    #[clap(
+        long,
+        env = "ESPRESSO_STATE_PROVER_WORKER_QUEUE_CAPACITY",
+        default_value = "16"
+    )]
+    queue_capacity: usize,
+
+    #[clap(flatten)]
+    logging: logging::Config,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    args.logging.init();
+
+    let prover = LocalProver::load(args.stake_table_capacity).await?;
+    run_worker(
+        WorkerOptions {
+            bind: SocketAddr::from((Ipv4Addr::UNSPECIFIED, args.port)),
+            queue_capacity: args.queue_capacity,
+        },
+        prover,
+    )
+    .await
+}
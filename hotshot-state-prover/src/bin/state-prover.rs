@@ -1,4 +1,4 @@
-use std::time::Duration;
+use std::{path::PathBuf, time::Duration};
 
 use alloy::{
     primitives::{Address, utils::parse_units},
@@ -9,12 +9,21 @@ use alloy::{
         local::{MnemonicBuilder, coins_bip39::English},
     },
 };
+use anyhow::{Context, anyhow, ensure};
 use clap::Parser;
 use espresso_contract_deployer::network_config::fetch_epoch_config_from_sequencer;
 use espresso_types::{L1ClientOptions, parse_duration, v0_1::SwitchingTransport};
 use espresso_utils::logging;
 use hotshot_contract_adapter::sol_types;
-use hotshot_state_prover::{StateProverConfig, utils::ChainIdRetry};
+use hotshot_state_prover::{
+    StateProverConfig,
+    utils::ChainIdRetry,
+    v3::{
+        audit::AuditOptions,
+        multichain::{ChainSpec, ChainTarget, load_chain_specs},
+        prover::ProverBackendOptions,
+    },
+};
 use hotshot_types::light_client::DEFAULT_STAKE_TABLE_CAPACITY;
 use url::Url;
 use vbs::version::StaticVersion;
@@ -98,10 +107,121 @@ struct Args {
     #[clap(short, long, env = "ESPRESSO_STATE_PROVER_MAX_GAS_PRICE_IN_GWEI")]
     pub max_gas_price: Option<String>,
 
+    /// URL of a remote prover worker (`state-prover-worker`) to offload proof generation to.
+    ///
+    /// If not provided, proofs are generated in-process. Only supported for LightClient V3.
+    #[clap(long, env = "ESPRESSO_STATE_PROVER_WORKER_URL")]
+    pub worker_url: Option<Url>,
+
+    /// Interval at which to poll the remote prover worker for finished proofs.
+    #[clap(long, value_parser = parse_duration, env = "ESPRESSO_STATE_PROVER_WORKER_POLL_INTERVAL")]
+    pub worker_poll_interval: Option<Duration>,
+
+    /// How long to wait for the remote prover worker to return a proof before giving up.
+    #[clap(long, value_parser = parse_duration, env = "ESPRESSO_STATE_PROVER_WORKER_TIMEOUT")]
+    pub worker_timeout: Option<Duration>,
+
+    /// Directory in which to cache generated proofs, so they are reused after a restart.
+    ///
+    /// Only supported for LightClient V3.
+    #[clap(long, env = "ESPRESSO_STATE_PROVER_PROOF_CACHE_DIR")]
+    pub proof_cache_dir: Option<PathBuf>,
+
+    /// Name of the chain the LightClient contract above is deployed on, used in logs and metrics.
+    #[clap(long, env = "ESPRESSO_STATE_PROVER_CHAIN_NAME", default_value = "l1")]
+    pub chain_name: String,
+
+    /// TOML file listing additional LightClient deployments to submit each update to.
+    ///
+    /// Each update is proved once and submitted to every chain. Only supported in daemon mode
+    /// for LightClient V3. See `hotshot_state_prover::v3::multichain::ChainSpec` for the format.
+    #[clap(
+        long,
+        env = "ESPRESSO_STATE_PROVER_ADDITIONAL_CHAINS",
+        requires = "daemon"
+    )]
+    pub additional_chains: Option<PathBuf>,
+
+    /// Generate and verify the next proof without submitting it, then write an audit report.
+    ///
+    /// Only supported for LightClient V3. Cannot be combined with `--daemon`.
+    #[clap(long, conflicts_with = "daemon")]
+    pub dry_run: bool,
+
+    /// File to write the dry run audit report to. If not provided, it is printed to stdout.
+    #[clap(long, requires = "dry_run")]
+    pub audit_report: Option<PathBuf>,
+
+    /// Verifying key generated by `gen-vk-contract --vk-json` to check proofs against.
+    ///
+    /// Used for the dry run proof and for proofs returned by a remote prover worker. If not
+    /// provided, the dry run reads the verifying key from the LightClient contract, and proofs from
+    /// a remote worker are checked against a verifying key generated from the SRS.
+    #[clap(long, env = "ESPRESSO_STATE_PROVER_VERIFYING_KEY")]
+    pub verifying_key: Option<PathBuf>,
+
     #[clap(flatten)]
     logging: logging::Config,
 }
 
+/// Connect to an additional chain described in a chains file.
+async fn chain_target(
+    spec: ChainSpec,
+    l1_options: &L1ClientOptions,
+    mnemonic: &str,
+    default_account_index: u32,
+) -> anyhow::Result<ChainTarget> {
+    let sync_timeout = spec.sync_timeout()?;
+    let transport = SwitchingTransport::new(l1_options.clone(), spec.rpc_url)
+        .with_context(|| format!("creating transport for {}", spec.name))?;
+    let l1_rpc_client = RpcClient::new(transport, false);
+    let provider = ProviderBuilder::new().connect_client(l1_rpc_client.clone());
+    let chain_id = ChainIdRetry::default()
+        .get_chain_id(&provider)
+        .await
+        .with_context(|| format!("getting chain ID of {}", spec.name))?;
+
+    let version = sol_types::LightClient::new(spec.light_client_address, &provider)
+        .getVersion()
+        .call()
+        .await
+        .with_context(|| format!("checking LightClient version on {}", spec.name))?;
+    ensure!(
+        version.majorVersion == 3,
+        "LightClient on {} is v{}, only v3 is supported for additional chains",
+        spec.name,
+        version.majorVersion
+    );
+
+    let signer = MnemonicBuilder::<English>::default()
+        .phrase(mnemonic)
+        .index(spec.account_index.unwrap_or(default_account_index))?
+        .build()?
+        .with_chain_id(Some(chain_id));
+    let max_gas_price = spec
+        .max_gas_price
+        .map(|v| -> anyhow::Result<u128> {
+            parse_units(&v, "gwei")?
+                .try_into()
+                .map_err(|_| anyhow!("max gas price for {} is out of range", spec.name))
+        })
+        .transpose()?;
+
+    tracing::info!(
+        "Additional chain {}: chain ID {chain_id}, LightClient {:?}",
+        spec.name,
+        spec.light_client_address
+    );
+    Ok(ChainTarget {
+        name: spec.name,
+        l1_rpc_client,
+        light_client_address: spec.light_client_address,
+        signer,
+        max_gas_price,
+        sync_timeout,
+    })
+}
+
 fn main() {
     let migrated_envs = espresso_utils::env_compat::migrate_legacy_env_vars();
     tokio::runtime::Runtime::new()
@@ -132,7 +252,7 @@ async fn async_main(migrated_envs: Vec<(&str, &str)>) {
         .expect("failed to get chain ID from L1 providers");
 
     let signer = MnemonicBuilder::<English>::default()
-        .phrase(args.eth_mnemonic)
+        .phrase(args.eth_mnemonic.clone())
         .index(args.eth_account_index)
         .expect("wrong mnemonic or index")
         .build()
@@ -168,6 +288,14 @@ async fn async_main(migrated_envs: Vec<(&str, &str)>) {
             .expect("fail to convert gas price to u128")
     });
 
+    let backend = ProverBackendOptions {
+        remote_url: args.worker_url,
+        poll_interval: args.worker_poll_interval,
+        timeout: args.worker_timeout,
+        verifying_key: args.verifying_key.clone(),
+        cache_dir: args.proof_cache_dir,
+    };
+
     let config = StateProverConfig {
         relay_server: args.relay_server,
         update_interval: args.update_interval,
@@ -203,12 +331,86 @@ async fn async_main(migrated_envs: Vec<(&str, &str)>) {
     // This bind version doesn't represent anything now, but it's required by the service trait
     let bind_version = StaticVersion::<0, 1> {};
 
-    if args.daemon {
+    if args.dry_run {
+        if contract_version != 3 {
+            tracing::error!("Dry run is not supported for contract version v{contract_version}");
+            return;
+        }
+        let audit = AuditOptions {
+            report_path: args.audit_report,
+            verifying_key: args.verifying_key,
+        };
+        match hotshot_state_prover::v3::audit::run_prover_dry_run(
+            config,
+            backend,
+            audit,
+            bind_version,
+        )
+        .await
+        {
+            Ok(report) if report.passed() => tracing::info!("Dry run passed"),
+            Ok(report) => {
+                tracing::error!("Dry run failed: {:?}", report.error);
+                std::process::exit(1);
+            },
+            Err(err) => {
+                tracing::error!("Error running dry run: {err:#}");
+                std::process::exit(1);
+            },
+        }
+    } else if args.daemon {
         // Launching the prover service daemon
         let result = match contract_version {
+            1 | 2 if args.additional_chains.is_some() => {
+                tracing::error!(
+                    "Additional chains are not supported for contract version v{contract_version}"
+                );
+                return;
+            },
             1 => hotshot_state_prover::v1::service::run_prover_service(config, bind_version).await,
             2 => hotshot_state_prover::v2::service::run_prover_service(config, bind_version).await,
-            3 => hotshot_state_prover::v3::service::run_prover_service(config, bind_version).await,
+            3 if args.additional_chains.is_some() => {
+                let path = args.additional_chains.as_ref().unwrap();
+                let mut chains = vec![ChainTarget::primary(args.chain_name.clone(), &config)];
+                let specs = match load_chain_specs(path) {
+                    Ok(specs) => specs,
+                    Err(err) => {
+                        tracing::error!("{err:#}");
+                        return;
+                    },
+                };
+                for spec in specs {
+                    match chain_target(
+                        spec,
+                        &args.l1_options,
+                        &args.eth_mnemonic,
+                        args.eth_account_index,
+                    )
+                    .await
+                    {
+                        Ok(chain) => chains.push(chain),
+                        Err(err) => {
+                            tracing::error!("{err:#}");
+                            return;
+                        },
+                    }
+                }
+                hotshot_state_prover::v3::multichain::run_multichain_prover_service(
+                    config,
+                    chains,
+                    backend,
+                    bind_version,
+                )
+                .await
+            },
+            3 => {
+                hotshot_state_prover::v3::service::run_prover_service_with_backend(
+                    config,
+                    backend,
+                    bind_version,
+                )
+                .await
+            },
             _ => {
                 tracing::error!("Unsupported contract version: {contract_version}");
                 return;
@@ -222,7 +424,14 @@ async fn async_main(migrated_envs: Vec<(&str, &str)>) {
         let result = match contract_version {
             1 => hotshot_state_prover::v1::service::run_prover_once(config, bind_version).await,
             2 => hotshot_state_prover::v2::service::run_prover_once(config, bind_version).await,
-            3 => hotshot_state_prover::v3::service::run_prover_once(config, bind_version).await,
+            3 => {
+                hotshot_state_prover::v3::service::run_prover_once_with_backend(
+                    config,
+                    backend,
+                    bind_version,
+                )
+                .await
+            },
             _ => {
                 tracing::error!("Unsupported contract version: {contract_version}");
                 return;
@@ -1,8 +1,17 @@
 //! Minimal HTTP server exposing the light client contract address, shared by the v1/v2/v3
 //! prover services.
 
+use std::sync::Arc;
+
 use alloy::primitives::Address;
-use axum::{Json, Router, http::HeaderMap, response::Response, routing::get};
+use axum::{
+    Json, Router,
+    extract::State,
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use hotshot_query_service::metrics::PrometheusMetrics;
 use http_wire::{cors_layer, healthcheck_response};
 
 /// Serves the light client contract address at the paths tide-disco used to expose it:
@@ -10,6 +19,25 @@ use http_wire::{cors_layer, healthcheck_response};
 /// served via a redirect to the versioned path). Also serves `/healthcheck`. Like tide-disco,
 /// every response carries permissive CORS headers.
 fn router(light_client_address: Address) -> Router {
+    routes(light_client_address).layer(cors_layer())
+}
+
+/// [`router`], plus Prometheus metrics at `/status/metrics` and `/v0/status/metrics`.
+fn router_with_metrics(light_client_address: Address, metrics: PrometheusMetrics) -> Router {
+    let metrics = Arc::new(metrics);
+    routes(light_client_address)
+        .route(
+            "/status/metrics",
+            get(status_metrics).with_state(metrics.clone()),
+        )
+        .route(
+            "/v0/status/metrics",
+            get(status_metrics).with_state(metrics),
+        )
+        .layer(cors_layer())
+}
+
+fn routes(light_client_address: Address) -> Router {
     Router::new()
         .route(
             "/api/lightclient_contract",
@@ -20,14 +48,24 @@ fn router(light_client_address: Address) -> Router {
             get(move || async move { Json(light_client_address) }),
         )
         .route("/healthcheck", get(healthcheck))
-        .layer(cors_layer())
 }
 
 /// Runs [`router`] until the process exits; bind failures are logged, not propagated, since this
 /// server only provides a healthcheck ahead of the prover's (fallible) main loop.
 pub(crate) fn start_light_client_contract_server(port: u16, light_client_address: Address) {
-    let router = router(light_client_address);
+    serve(port, router(light_client_address));
+}
 
+/// Like [`start_light_client_contract_server`], but also exports `metrics`.
+pub(crate) fn start_light_client_contract_server_with_metrics(
+    port: u16,
+    light_client_address: Address,
+    metrics: PrometheusMetrics,
+) {
+    serve(port, router_with_metrics(light_client_address, metrics));
+}
+
+fn serve(port: u16, router: Router) {
     tokio::spawn(async move {
         let addr = format!("0.0.0.0:{port}");
         let listener = match tokio::net::TcpListener::bind(&addr).await {
@@ -43,6 +81,16 @@ pub(crate) fn start_light_client_contract_server(port: u16, light_client_address
     });
 }
 
+async fn status_metrics(State(metrics): State<Arc<PrometheusMetrics>>) -> Response {
+    match metrics.export() {
+        Ok(text) => ([(header::CONTENT_TYPE, "text/plain; charset=utf-8")], text).into_response(),
+        Err(err) => {
+            tracing::error!("failed to export metrics: {err:#}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        },
+    }
+}
+
 async fn healthcheck(headers: HeaderMap) -> Response {
     healthcheck_response(&headers)
 }
@@ -84,6 +132,28 @@ mod tests {
         assert_eq!(&body[..], br#"{"status":"available","modules":{}}"#);
     }
 
+    #[tokio::test]
+    async fn metrics_route() {
+        use hotshot_types::traits::metrics::Metrics;
+
+        let metrics = PrometheusMetrics::default();
+        metrics
+            .create_counter("light_client_syncs".into(), None)
+            .add(1);
+        let req = Request::builder()
+            .uri("/status/metrics")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let resp = tower::ServiceExt::oneshot(router_with_metrics(Address::ZERO, metrics), req)
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(resp.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("light_client_syncs 1"));
+    }
+
     /// Like tide-disco, every response carries permissive CORS headers.
     #[tokio::test]
     async fn responses_carry_cors_headers() {
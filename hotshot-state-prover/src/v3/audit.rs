@@ -0,0 +1,542 @@
+//! Dry-run mode for the prover service.
+//!
+//! Instead of submitting to the LightClient contract, a dry run generates a proof for the next
+//! update the contract would accept, verifies it locally against the contract's verifying key,
+//! simulates the `newFinalizedState` call without sending a transaction, and writes an
+//! [`AuditReport`] describing the result. This lets operators audit a prover against Anvil or a
+//! fork of a live chain before enabling submissions.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use alloy::{
+    primitives::{Address, FixedBytes, U256},
+    providers::{Provider, ProviderBuilder},
+    sol_types::SolValue,
+};
+use anyhow::{Context, Result};
+use ark_bn254::Bn254;
+use hotshot_contract_adapter::{
+    field_to_u256,
+    sol_types::{
+        LightClientStateSol, LightClientV3, PlonkProofSol, StakeTableStateSol, VerifyingKeySol,
+    },
+};
+use hotshot_types::{
+    light_client::{
+        CircuitField, LCV3StateSignaturesBundle, LightClientState, StakeTableState, StateSignature,
+        StateVerKey,
+    },
+    utils::{is_ge_epoch_root, option_epoch_from_block_number},
+};
+use http_client::{Client, error::ClientErr};
+use jf_plonk::{
+    proof_system::{PlonkKzgSnark, UniversalSNARK},
+    transcript::SolidityTranscript,
+};
+use serde::{Deserialize, Serialize};
+use vbs::version::StaticVersionType;
+
+use crate::{
+    ProverError, ProverServiceState, StateProverConfig,
+    v3::{
+        prover::{ProverBackend, ProverBackendOptions},
+        service::{
+            build_proof_request, contract_epoch, fetch_epoch_state_from_sequencer,
+            fetch_latest_state, first_catchup_epoch, read_contract_state,
+        },
+        snark::{Proof, PublicInput, VerifyingKey},
+    },
+};
+
+/// Options for a dry run.
+#[derive(Clone, Debug, Default)]
+pub struct AuditOptions {
+    /// File to write the report to. If not provided, the report is printed to stdout.
+    pub report_path: Option<PathBuf>,
+    /// Verifying key written by `gen-vk-contract --vk-json`.
+    ///
+    /// If provided, the proof is verified against this key and the report records whether it
+    /// matches the key compiled into the contract. Otherwise the contract's key is used.
+    pub verifying_key: Option<PathBuf>,
+}
+
+/// Where the verifying key used for local verification came from.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerifyingKeySource {
+    /// Read from the LightClient contract.
+    Contract,
+    /// Loaded from a file generated by `gen-vk-contract`.
+    File(PathBuf),
+}
+
+/// Machine-readable result of a dry run.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AuditReport {
+    /// Address of the LightClient contract audited against.
+    pub light_client_address: Address,
+    /// The finalized state currently stored in the contract.
+    pub contract_state: LightClientStateSol,
+    /// The state which would be submitted.
+    pub state: LightClientStateSol,
+    /// The voting stake table the proof is checked against.
+    pub stake_table_state: StakeTableStateSol,
+    /// The stake table state which would become the next voting stake table.
+    pub next_stake_table_state: StakeTableStateSol,
+    pub auth_root: FixedBytes<32>,
+    /// Digest of the signed states, the only public input besides the stake table.
+    pub signed_state_digest: U256,
+    /// Whether this update is an epoch root update needed to catch the contract up.
+    pub epoch_catchup: bool,
+    /// Number of entries in the voting stake table.
+    pub num_stakers: usize,
+    /// Number of valid signatures collected.
+    pub num_signers: usize,
+    /// Accumulated stake of the valid signers.
+    pub signature_weight: U256,
+    /// Stake needed for the contract to accept the update.
+    pub threshold: U256,
+    pub verifying_key_source: VerifyingKeySource,
+    /// Whether the verifying key from a file matches the contract's, if a file was used.
+    pub verifying_key_matches_contract: Option<bool>,
+    /// Time spent generating the proof, in seconds.
+    pub proof_generation_secs: Option<f64>,
+    /// Time spent verifying the proof locally, in seconds.
+    pub verification_secs: Option<f64>,
+    /// Whether the proof verified locally.
+    pub verified: bool,
+    /// Whether simulating the update transaction against the contract succeeded.
+    pub contract_call_succeeded: Option<bool>,
+    /// The first error encountered, if any.
+    pub error: Option<String>,
+}
+
+impl AuditReport {
+    /// Whether the update would have been accepted.
+    pub fn passed(&self) -> bool {
+        self.error.is_none()
+            && self.verified
+            && self.contract_call_succeeded != Some(false)
+            && self.verifying_key_matches_contract != Some(false)
+    }
+}
+
+/// Load a verifying key written by `gen-vk-contract --vk-json`.
+pub fn load_verifying_key(path: &Path) -> Result<VerifyingKeySol> {
+    let bytes =
+        std::fs::read(path).with_context(|| format!("reading verifying key {}", path.display()))?;
+    serde_json::from_slice(&bytes)
+        .with_context(|| format!("decoding verifying key {}", path.display()))
+}
+
+/// Read the verifying key compiled into the LightClient contract.
+pub async fn read_contract_verifying_key(
+    provider: impl Provider,
+    address: Address,
+) -> Result<VerifyingKeySol, ProverError> {
+    let contract = LightClientV3::new(address, &provider);
+    match contract._getVk().call().await {
+        Ok(vk) => Ok(vk.into()),
+        Err(e) => {
+            tracing::error!("unable to read verifying key from contract: {}", e);
+            Err(ProverError::ContractError(e.into()))
+        },
+    }
+}
+
+/// Verify a state update proof against `vk`.
+pub fn verify_proof(
+    vk: &VerifyingKey,
+    public_input: &PublicInput,
+    proof: &Proof,
+) -> Result<(), ProverError> {
+    PlonkKzgSnark::<Bn254>::verify::<SolidityTranscript>(vk, &public_input.to_vec(), proof, None)?;
+    Ok(())
+}
+
+/// Simulate submitting `proof` to the contract from `from`, without sending a transaction.
+pub async fn simulate_submission(
+    provider: impl Provider,
+    address: Address,
+    from: Address,
+    proof: Proof,
+    lc_state: LightClientState,
+    next_st_state: StakeTableState,
+    auth_root: FixedBytes<32>,
+) -> Result<(), ProverError> {
+    let contract = LightClientV3::new(address, &provider);
+    let proof: PlonkProofSol = proof.into();
+    let new_state: LightClientStateSol = lc_state.into();
+    let next_stake_table: StakeTableStateSol = next_st_state.into();
+    let auth_root = U256::from_be_bytes(auth_root.0);
+
+    contract
+        .newFinalizedState_2(
+            new_state.into(),
+            next_stake_table.into(),
+            auth_root,
+            proof.into(),
+        )
+        .from(from)
+        .call()
+        .await
+        .map_err(|err| ProverError::ContractError(err.into()))?;
+    Ok(())
+}
+
+/// The next update the contract would accept.
+struct PendingUpdate {
+    state: LightClientState,
+    next_stake_table_state: StakeTableState,
+    auth_root: FixedBytes<32>,
+    signatures: HashMap<StateVerKey, StateSignature>,
+    epoch_catchup: bool,
+}
+
+/// Work out which update to prove, following the same rules as the prover service.
+///
+/// If the contract is behind by one or more epochs, the service would first submit the epoch root
+/// update for the contract's epoch, so that is the update we audit.
+async fn pending_update(
+    state: &mut ProverServiceState,
+    contract_state: &LightClientState,
+    contract_st_state: StakeTableState,
+    bundle: LCV3StateSignaturesBundle,
+) -> Result<PendingUpdate, ProverError> {
+    let blocks_per_epoch = state.config.blocks_per_epoch;
+
+    let latest = PendingUpdate {
+        state: bundle.state,
+        next_stake_table_state: contract_st_state,
+        auth_root: bundle.auth_root,
+        signatures: bundle.signatures,
+        epoch_catchup: false,
+    };
+    if bundle.state.block_height < state.config.epoch_start_block {
+        return Ok(latest);
+    }
+
+    let contract_epoch = contract_epoch(&state.config, contract_state);
+    let bundle_epoch =
+        option_epoch_from_block_number(true, bundle.state.block_height, blocks_per_epoch);
+
+    if contract_epoch != state.epoch {
+        state
+            .sync_with_epoch(contract_epoch)
+            .await
+            .map_err(ProverError::Internal)?;
+    }
+
+    let root_epoch = if bundle_epoch > contract_epoch {
+        first_catchup_epoch(&state.config, contract_epoch)
+    } else if is_ge_epoch_root(bundle.state.block_height as u64, blocks_per_epoch) {
+        bundle_epoch.map(|en| en.u64()).unwrap_or(0)
+    } else {
+        return Ok(latest);
+    };
+
+    tracing::info!("Auditing epoch root state update for epoch {root_epoch}");
+    let state_cert =
+        fetch_epoch_state_from_sequencer(&state.config.sequencer_url, root_epoch).await?;
+    Ok(PendingUpdate {
+        state: state_cert.light_client_state,
+        next_stake_table_state: state_cert.next_stake_table_state,
+        auth_root: state_cert.auth_root,
+        signatures: state_cert
+            .signatures
+            .into_iter()
+            .map(|(key, sig, _)| (key, sig))
+            .collect(),
+        epoch_catchup: true,
+    })
+}
+
+/// Generate, verify and simulate the next update, recording the outcome in `report`.
+async fn audit_update(
+    state: &ProverServiceState,
+    provider: impl Provider,
+    prover: &dyn ProverBackend,
+    vk: &VerifyingKey,
+    stake_table_state: StakeTableState,
+    update: PendingUpdate,
+    report: &mut AuditReport,
+) -> Result<(), ProverError> {
+    let (req, weight) = build_proof_request(
+        state,
+        update.state,
+        stake_table_state,
+        update.next_stake_table_state,
+        update.auth_root,
+        &update.signatures,
+    )?;
+    report.signed_state_digest = field_to_u256(req.signed_state_digest);
+    report.num_stakers = req.entries.len();
+    report.num_signers = req.signer_bit_vec.iter().filter(|signed| **signed).count();
+    report.signature_weight = weight;
+    if weight < report.threshold {
+        return Err(ProverError::InvalidState(
+            "The signers' total weight doesn't reach the threshold.".to_string(),
+        ));
+    }
+
+    let public_input = PublicInput::new(stake_table_state, req.signed_state_digest);
+    let start = Instant::now();
+    let proof = prover.prove(req).await?;
+    report.proof_generation_secs = Some(start.elapsed().as_secs_f64());
+
+    let start = Instant::now();
+    let verified = verify_proof(vk, &public_input, &proof);
+    report.verification_secs = Some(start.elapsed().as_secs_f64());
+    verified?;
+    report.verified = true;
+
+    let res = simulate_submission(
+        &provider,
+        report.light_client_address,
+        state.config.signer.address(),
+        proof,
+        update.state,
+        update.next_stake_table_state,
+        update.auth_root,
+    )
+    .await;
+    report.contract_call_succeeded = Some(res.is_ok());
+    res
+}
+
+/// Run a dry run of the prover: generate and check the next update without submitting it.
+///
+/// Failures of the update itself (insufficient signatures, an invalid proof, a reverted call) are
+/// recorded in the report rather than returned as errors.
+pub async fn run_prover_dry_run<ApiVer: StaticVersionType>(
+    config: StateProverConfig,
+    backend: ProverBackendOptions,
+    audit: AuditOptions,
+    _: ApiVer,
+) -> Result<AuditReport> {
+    let mut state = ProverServiceState::new_genesis(config).await?;
+    let light_client_address = state.config.light_client_address;
+    let provider = ProviderBuilder::new().connect_client(state.config.l1_rpc_client.clone());
+    let relay_server_client = Client::<ClientErr, ApiVer>::new(state.config.relay_server.clone());
+
+    let (contract_state, contract_st_state) =
+        read_contract_state(&provider, light_client_address).await?;
+    let contract_vk = read_contract_verifying_key(&provider, light_client_address).await?;
+    let (vk, verifying_key_source, verifying_key_matches_contract) = match &audit.verifying_key {
+        Some(path) => {
+            let vk = load_verifying_key(path)?;
+            let matches = vk.abi_encode() == contract_vk.abi_encode();
+            if !matches {
+                tracing::warn!(
+                    path = %path.display(),
+                    "verifying key does not match the contract's"
+                );
+            }
+            (vk, VerifyingKeySource::File(path.clone()), Some(matches))
+        },
+        None => (contract_vk, VerifyingKeySource::Contract, None),
+    };
+    let vk: VerifyingKey = vk.into();
+
+    let bundle = fetch_latest_state(&relay_server_client).await?;
+    tracing::info!(
+        "Contract block height: {}, latest HotShot block height: {}",
+        contract_state.block_height,
+        bundle.state.block_height
+    );
+
+    let mut report = AuditReport {
+        light_client_address,
+        contract_state: contract_state.into(),
+        state: bundle.state.into(),
+        stake_table_state: contract_st_state.into(),
+        next_stake_table_state: contract_st_state.into(),
+        auth_root: bundle.auth_root,
+        signed_state_digest: U256::ZERO,
+        epoch_catchup: false,
+        num_stakers: 0,
+        num_signers: 0,
+        signature_weight: U256::ZERO,
+        threshold: field_to_u256(contract_st_state.threshold),
+        verifying_key_source,
+        verifying_key_matches_contract,
+        proof_generation_secs: None,
+        verification_secs: None,
+        verified: false,
+        contract_call_succeeded: None,
+        error: None,
+    };
+
+    let res = async {
+        if contract_state.block_height >= bundle.state.block_height {
+            return Err(ProverError::InvalidState(
+                "Contract is already up to date with the latest state.".to_string(),
+            ));
+        }
+        let update = pending_update(&mut state, &contract_state, contract_st_state, bundle).await?;
+        report.state = update.state.into();
+        report.next_stake_table_state = update.next_stake_table_state.into();
+        report.auth_root = update.auth_root;
+        report.epoch_catchup = update.epoch_catchup;
+
+        let prover = backend
+            .build(state.config.stake_table_capacity)
+            .await
+            .map_err(ProverError::Internal)?;
+        audit_update(
+            &state,
+            &provider,
+            &*prover,
+            &vk,
+            contract_st_state,
+            update,
+            &mut report,
+        )
+        .await
+    }
+    .await;
+    if let Err(err) = res {
+        tracing::error!("Dry run failed: {err}");
+        report.error = Some(err.to_string());
+    }
+
+    let json = serde_json::to_string_pretty(&report)?;
+    match &audit.report_path {
+        Some(path) => {
+            std::fs::write(path, json)
+                .with_context(|| format!("writing audit report to {}", path.display()))?;
+            tracing::info!(path = %path.display(), "Wrote audit report");
+        },
+        None => println!("{json}"),
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::node_bindings::Anvil;
+    use espresso_contract_deployer::{
+        Contracts, deploy_light_client_proxy, upgrade_light_client_v2, upgrade_light_client_v3,
+    };
+
+    use super::*;
+    use crate::v3::mock_ledger::{
+        EPOCH_HEIGHT_FOR_TEST, EPOCH_START_BLOCK_FOR_TEST, MockLedger, MockSystemParam,
+        STAKE_TABLE_CAPACITY_FOR_TEST,
+    };
+
+    #[test]
+    fn test_report_passed() {
+        let report = AuditReport {
+            light_client_address: Address::ZERO,
+            contract_state: LightClientState::default().into(),
+            state: LightClientState::default().into(),
+            stake_table_state: StakeTableState::default().into(),
+            next_stake_table_state: StakeTableState::default().into(),
+            auth_root: FixedBytes::ZERO,
+            signed_state_digest: U256::ZERO,
+            epoch_catchup: false,
+            num_stakers: 0,
+            num_signers: 0,
+            signature_weight: U256::ZERO,
+            threshold: U256::ZERO,
+            verifying_key_source: VerifyingKeySource::File("vk.json".into()),
+            verifying_key_matches_contract: Some(true),
+            proof_generation_secs: None,
+            verification_secs: None,
+            verified: true,
+            contract_call_succeeded: Some(true),
+            error: None,
+        };
+        assert!(report.passed());
+
+        // A proof checked against a key the contract does not use proves nothing.
+        let mismatched_vk = AuditReport {
+            verifying_key_matches_contract: Some(false),
+            ..report.clone()
+        };
+        assert!(!mismatched_vk.passed());
+
+        let rejected = AuditReport {
+            contract_call_succeeded: Some(false),
+            ..report.clone()
+        };
+        assert!(!rejected.passed());
+
+        let unverified = AuditReport {
+            verified: false,
+            ..report
+        };
+        assert!(!unverified.passed());
+    }
+
+    #[test_log::test(tokio::test(flavor = "multi_thread"))]
+    async fn test_verify_and_simulate() -> Result<()> {
+        let mut ledger =
+            MockLedger::init(MockSystemParam::init(), STAKE_TABLE_CAPACITY_FOR_TEST / 2);
+        let genesis_state: LightClientStateSol = ledger.light_client_state().into();
+        let genesis_stake: StakeTableStateSol = ledger.voting_stake_table_state().into();
+
+        let anvil = Anvil::new().spawn();
+        let wallet = anvil.wallet().unwrap();
+        let provider = ProviderBuilder::new()
+            .wallet(wallet)
+            .connect_http(anvil.endpoint_url());
+        let admin = provider.get_accounts().await?[0];
+        let mut contracts = Contracts::new();
+        let lc_proxy_addr = deploy_light_client_proxy(
+            &provider,
+            &mut contracts,
+            false,
+            genesis_state.clone(),
+            genesis_stake,
+            admin,
+            Some(admin),
+        )
+        .await?;
+        upgrade_light_client_v2(
+            &provider,
+            &mut contracts,
+            true,
+            EPOCH_HEIGHT_FOR_TEST,
+            EPOCH_START_BLOCK_FOR_TEST,
+        )
+        .await?;
+        upgrade_light_client_v3(&provider, &mut contracts, true).await?;
+
+        let vk: VerifyingKey = read_contract_verifying_key(&provider, lc_proxy_addr)
+            .await?
+            .into();
+
+        while ledger.light_client_state().block_height < 2 * EPOCH_HEIGHT_FOR_TEST - 5 {
+            ledger.elapse_with_block();
+        }
+        let (pi, proof) = ledger.gen_state_proof();
+        verify_proof(&vk, &pi, &proof)?;
+
+        // A proof for different public inputs does not verify.
+        let mut bad_pi = pi.clone();
+        bad_pi.signed_state_digest += CircuitField::from(1u64);
+        assert!(verify_proof(&vk, &bad_pi, &proof).is_err());
+
+        // Simulating the submission succeeds, but does not change the contract state.
+        simulate_submission(
+            &provider,
+            lc_proxy_addr,
+            admin,
+            proof,
+            ledger.light_client_state(),
+            ledger.next_stake_table_state(),
+            ledger.auth_root().into(),
+        )
+        .await?;
+        let (contract_state, _) = read_contract_state(&provider, lc_proxy_addr).await?;
+        assert_eq!(contract_state, LightClientState::from(genesis_state));
+
+        Ok(())
+    }
+}
@@ -1,9 +1,15 @@
 //! Light client V3 prover
 
+/// Dry-run and proof audit mode
+pub mod audit;
 /// State verifier circuit builder
 pub mod circuit;
 /// Utilities for test
 pub mod mock_ledger;
+/// Submitting updates to several chains
+pub mod multichain;
+/// Pluggable proof generation backends
+pub mod prover;
 /// Prover service related functionalities
 pub mod service;
 /// SNARK proof generation
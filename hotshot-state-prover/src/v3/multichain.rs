@@ -0,0 +1,691 @@
+//! Submitting light client updates to several LightClient deployments from one prover.
+//!
+//! Each update is proved once and then submitted to every configured chain, for example Ethereum
+//! and an Arbitrum deployment of the LightClient contract. Each chain has its own RPC client,
+//! signer and gas price cap, so nonce and gas management are independent. Every chain is driven by
+//! its own task with its own deadline, so a chain which is slow or fails to sync is retried without
+//! holding back the others; once it recovers it moves straight on to the latest update.
+
+use std::{
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use alloy::{
+    primitives::Address,
+    providers::ProviderBuilder,
+    rpc::client::RpcClient,
+    signers::{k256::ecdsa::SigningKey, local::LocalSigner},
+};
+use anyhow::{Context, Result, ensure};
+use espresso_types::parse_duration;
+use futures::future::try_join_all;
+use hotshot_query_service::metrics::PrometheusMetrics;
+use hotshot_types::{
+    light_client::LCV3StateSignaturesBundle,
+    traits::metrics::{
+        Counter, CounterFamily, Gauge, GaugeFamily, Histogram, HistogramFamily, Metrics,
+    },
+};
+use http_client::{Client, error::ClientErr};
+use serde::Deserialize;
+use tokio::{
+    sync::watch,
+    task::JoinSet,
+    time::{sleep, timeout},
+};
+use tracing::Instrument;
+use url::Url;
+use vbs::version::StaticVersionType;
+
+use crate::{
+    ProverServiceState, StateProverConfig,
+    v3::{
+        prover::{MemoizingProver, ProverBackend, ProverBackendOptions},
+        service::{fetch_latest_state, read_contract_state, sync_state_with_bundle},
+    },
+};
+
+/// How long a chain may spend on one update, including retries, if not configured otherwise.
+pub const DEFAULT_SYNC_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// A LightClient deployment to submit updates to.
+#[derive(Clone, Debug)]
+pub struct ChainTarget {
+    /// Name of the chain, used in logs and metric labels.
+    pub name: String,
+    /// RPC client for the chain.
+    pub l1_rpc_client: RpcClient,
+    /// Address of the LightClient proxy contract on this chain.
+    pub light_client_address: Address,
+    /// Transaction signing key for this chain.
+    pub signer: LocalSigner<SigningKey>,
+    /// Optional gas price cap **in wei** for this chain.
+    pub max_gas_price: Option<u128>,
+    /// How long this chain may spend on one update, including retries, before it gives up and
+    /// moves on to the latest update. Defaults to [`DEFAULT_SYNC_TIMEOUT`].
+    pub sync_timeout: Option<Duration>,
+}
+
+impl ChainTarget {
+    /// The chain `config` is already set up to submit to.
+    pub fn primary(name: impl Into<String>, config: &StateProverConfig) -> Self {
+        Self {
+            name: name.into(),
+            l1_rpc_client: config.l1_rpc_client.clone(),
+            light_client_address: config.light_client_address,
+            signer: config.signer.clone(),
+            max_gas_price: config.max_gas_price,
+            sync_timeout: None,
+        }
+    }
+
+    /// A copy of `config` which submits to this chain.
+    fn config(&self, config: &StateProverConfig) -> StateProverConfig {
+        StateProverConfig {
+            l1_rpc_client: self.l1_rpc_client.clone(),
+            light_client_address: self.light_client_address,
+            signer: self.signer.clone(),
+            max_gas_price: self.max_gas_price,
+            ..config.clone()
+        }
+    }
+}
+
+/// Description of an additional chain, as read from a chains file.
+///
+/// A chains file is a TOML file with one `[[chain]]` table per chain:
+///
+/// ```toml
+/// [[chain]]
+/// name = "arbitrum"
+/// rpc_url = ["https://arb1.example.com"]
+/// light_client_address = "0x..."
+/// # Optional: account index derived from the prover's mnemonic, defaults to the prover's.
+/// account_index = 1
+/// # Optional: max gas price in gwei.
+/// max_gas_price = "0.5"
+/// # Optional: how long to spend on one update, including retries.
+/// sync_timeout = "15m"
+/// ```
+#[derive(Clone, Debug, Deserialize)]
+pub struct ChainSpec {
+    pub name: String,
+    pub rpc_url: Vec<Url>,
+    pub light_client_address: Address,
+    #[serde(default)]
+    pub account_index: Option<u32>,
+    #[serde(default)]
+    pub max_gas_price: Option<String>,
+    #[serde(default)]
+    pub sync_timeout: Option<String>,
+}
+
+impl ChainSpec {
+    /// The configured sync timeout, if any.
+    pub fn sync_timeout(&self) -> Result<Option<Duration>> {
+        self.sync_timeout
+            .as_deref()
+            .map(|timeout| {
+                parse_duration(timeout)
+                    .with_context(|| format!("invalid sync timeout for {}", self.name))
+            })
+            .transpose()
+    }
+}
+
+#[derive(Deserialize)]
+struct ChainsFile {
+    #[serde(default, rename = "chain")]
+    chains: Vec<ChainSpec>,
+}
+
+/// Load additional chains from a TOML chains file.
+pub fn load_chain_specs(path: &Path) -> Result<Vec<ChainSpec>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("reading chains file {}", path.display()))?;
+    let file: ChainsFile = toml::from_str(&contents)
+        .with_context(|| format!("parsing chains file {}", path.display()))?;
+    Ok(file.chains)
+}
+
+/// Per-chain metrics, labelled by chain name.
+struct ChainMetrics {
+    syncs: Box<dyn Counter>,
+    failures: Box<dyn Counter>,
+    consecutive_failures: Box<dyn Gauge>,
+    block_height: Box<dyn Gauge>,
+    sync_duration: Box<dyn Histogram>,
+}
+
+struct MetricFamilies {
+    syncs: Box<dyn CounterFamily>,
+    failures: Box<dyn CounterFamily>,
+    consecutive_failures: Box<dyn GaugeFamily>,
+    block_height: Box<dyn GaugeFamily>,
+    sync_duration: Box<dyn HistogramFamily>,
+}
+
+impl MetricFamilies {
+    fn new(metrics: &dyn Metrics) -> Self {
+        let labels = || vec!["chain".to_string()];
+        Self {
+            syncs: metrics.counter_family("light_client_syncs".into(), labels()),
+            failures: metrics.counter_family("light_client_sync_failures".into(), labels()),
+            consecutive_failures: metrics
+                .gauge_family("light_client_consecutive_sync_failures".into(), labels()),
+            block_height: metrics.gauge_family("light_client_block_height".into(), labels()),
+            sync_duration: metrics.histogram_family("light_client_sync_duration".into(), labels()),
+        }
+    }
+
+    fn for_chain(&self, name: &str) -> ChainMetrics {
+        let labels = || vec![name.to_string()];
+        ChainMetrics {
+            syncs: self.syncs.create(labels()),
+            failures: self.failures.create(labels()),
+            consecutive_failures: self.consecutive_failures.create(labels()),
+            block_height: self.block_height.create(labels()),
+            sync_duration: self.sync_duration.create(labels()),
+        }
+    }
+}
+
+struct Chain {
+    name: String,
+    state: ProverServiceState,
+    metrics: ChainMetrics,
+    consecutive_failures: usize,
+    sync_timeout: Duration,
+}
+
+impl Chain {
+    async fn new(
+        config: &StateProverConfig,
+        target: ChainTarget,
+        metrics: &MetricFamilies,
+    ) -> Result<Self> {
+        let config = target.config(config);
+        config
+            .validate_light_client_contract()
+            .await
+            .with_context(|| format!("validating LightClient contract on {}", target.name))?;
+        let state = ProverServiceState::new_genesis(config)
+            .await
+            .with_context(|| format!("initializing prover state for {}", target.name))?;
+        Ok(Self {
+            metrics: metrics.for_chain(&target.name),
+            name: target.name,
+            state,
+            consecutive_failures: 0,
+            sync_timeout: target.sync_timeout.unwrap_or(DEFAULT_SYNC_TIMEOUT),
+        })
+    }
+
+    /// Sync this chain to each new bundle published on `bundles`, until the sender is dropped.
+    ///
+    /// Bundles published while a sync is in progress are skipped except for the latest one.
+    async fn run(
+        mut self,
+        prover: Arc<dyn ProverBackend>,
+        mut bundles: watch::Receiver<Option<LCV3StateSignaturesBundle>>,
+    ) {
+        while bundles.changed().await.is_ok() {
+            let Some(bundle) = bundles.borrow_and_update().clone() else {
+                continue;
+            };
+            if timeout(self.sync_timeout, self.sync(&*prover, &bundle))
+                .await
+                .is_err()
+            {
+                self.record_failure();
+                tracing::error!(
+                    "Sync timed out after {:?}, will try again with the next update",
+                    self.sync_timeout
+                );
+            }
+        }
+    }
+
+    fn record_failure(&mut self) {
+        self.metrics.failures.add(1);
+        self.consecutive_failures += 1;
+        self.metrics
+            .consecutive_failures
+            .set(self.consecutive_failures);
+    }
+
+    /// Sync this chain to `bundle`, retrying up to the configured number of times.
+    async fn sync(&mut self, prover: &dyn ProverBackend, bundle: &LCV3StateSignaturesBundle) {
+        let retry_interval = self.state.config.retry_interval;
+        for _ in 0..self.state.config.max_retries.max(1) {
+            let start = Instant::now();
+            match sync_state_with_bundle(&mut self.state, prover, bundle.clone()).await {
+                Ok(()) => {
+                    self.metrics.syncs.add(1);
+                    self.metrics
+                        .sync_duration
+                        .add_point(start.elapsed().as_secs_f64());
+                    self.consecutive_failures = 0;
+                    self.metrics.consecutive_failures.set(0);
+                    self.update_block_height().await;
+                    return;
+                },
+                Err(err) => {
+                    self.record_failure();
+                    tracing::error!(
+                        "Cannot sync the light client state, will retry in {:.1}s: {}",
+                        retry_interval.as_secs_f32(),
+                        err
+                    );
+                    sleep(retry_interval).await;
+                },
+            }
+        }
+        tracing::error!("Giving up on this update, will try again with the next one");
+    }
+
+    async fn update_block_height(&self) {
+        let provider =
+            ProviderBuilder::new().connect_client(self.state.config.l1_rpc_client.clone());
+        match read_contract_state(&provider, self.state.config.light_client_address).await {
+            Ok((state, _)) => self.metrics.block_height.set(state.block_height as usize),
+            Err(err) => tracing::warn!("Failed to read contract state for metrics: {err}"),
+        }
+    }
+}
+
+/// Run the prover in daemon mode, submitting each update to all of `chains`.
+///
+/// `config` provides the settings shared by all chains (relay server, sequencer, intervals and
+/// circuit parameters); the chain-specific fields of `config` are ignored unless it is also listed
+/// in `chains`, e.g. via [`ChainTarget::primary`]. If `config.port` is set, per-chain metrics are
+/// served at `/status/metrics`.
+pub async fn run_multichain_prover_service<ApiVer: StaticVersionType + 'static>(
+    config: StateProverConfig,
+    chains: Vec<ChainTarget>,
+    backend: ProverBackendOptions,
+    _bind_version: ApiVer,
+) -> Result<()> {
+    ensure!(!chains.is_empty(), "no chains configured");
+    tracing::info!(
+        "Submitting light client updates to {} chains: {}",
+        chains.len(),
+        chains
+            .iter()
+            .map(|chain| format!("{} ({:?})", chain.name, chain.light_client_address))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    let metrics = PrometheusMetrics::default();
+    // Start the HTTP server to get a functioning healthcheck before any heavy computations.
+    if let Some(port) = config.port {
+        crate::http::start_light_client_contract_server_with_metrics(
+            port,
+            chains[0].light_client_address,
+            metrics.clone(),
+        );
+    }
+    let chains = init_chains(&config, chains, &metrics).await?;
+
+    // Identical proof requests from different chains are only proved once.
+    let prover: Arc<dyn ProverBackend> = Arc::new(MemoizingProver::new(
+        backend.build(config.stake_table_capacity).await?,
+    ));
+    run_chains::<ApiVer>(&config, chains, prover).await
+}
+
+/// Connect to each of `targets` and set up its prover state and metrics.
+async fn init_chains(
+    config: &StateProverConfig,
+    targets: Vec<ChainTarget>,
+    metrics: &dyn Metrics,
+) -> Result<Vec<Chain>> {
+    let families = MetricFamilies::new(metrics);
+    try_join_all(
+        targets
+            .into_iter()
+            .map(|target| Chain::new(config, target, &families)),
+    )
+    .await
+}
+
+/// Fetch the latest update from the relay server every `config.update_interval` and sync all of
+/// `chains` to it.
+async fn run_chains<ApiVer: StaticVersionType + 'static>(
+    config: &StateProverConfig,
+    chains: Vec<Chain>,
+    prover: Arc<dyn ProverBackend>,
+) -> Result<()> {
+    let relay_server_client = Client::<ClientErr, ApiVer>::new(config.relay_server.clone());
+
+    // Each chain syncs to the latest bundle in its own task.
+    let (bundles, _) = watch::channel(None);
+    let mut tasks = JoinSet::new();
+    for chain in chains {
+        let span = tracing::info_span!("chain", name = %chain.name);
+        tasks.spawn(
+            chain
+                .run(prover.clone(), bundles.subscribe())
+                .instrument(span),
+        );
+    }
+
+    loop {
+        if let Some(res) = tasks.try_join_next() {
+            res.context("chain task failed")?;
+            anyhow::bail!("chain task exited unexpectedly");
+        }
+
+        let bundle = match fetch_latest_state(&relay_server_client).await {
+            Ok(bundle) => bundle,
+            Err(err) => {
+                tracing::error!(
+                    "Cannot fetch the latest state, will retry in {:.1}s: {}",
+                    config.retry_interval.as_secs_f32(),
+                    err
+                );
+                sleep(config.retry_interval).await;
+                continue;
+            },
+        };
+        tracing::info!("Latest HotShot block height: {}", bundle.state.block_height);
+        bundles.send_replace(Some(bundle));
+
+        tracing::info!("Sleeping for {:.1}s", config.update_interval.as_secs_f32());
+        sleep(config.update_interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use alloy::{
+        network::TransactionBuilder,
+        node_bindings::Anvil,
+        primitives::U256,
+        providers::{Provider, ext::AnvilApi},
+        rpc::types::TransactionRequest,
+    };
+    use ark_ed_on_bn254::EdwardsConfig;
+    use axum::{Json, Router, routing::get};
+    use espresso_contract_deployer::Contracts;
+    use hotshot_contract_adapter::{
+        light_client::derive_signed_state_digest,
+        sol_types::{LightClientStateSol, StakeTableStateSol},
+    };
+    use jf_signature::{SignatureScheme, schnorr::SchnorrSignatureScheme};
+    use tokio::net::TcpListener;
+    use vbs::{BinarySerializer, Serializer, version::StaticVersion};
+
+    use super::*;
+    use crate::v3::{
+        mock_ledger::{
+            EPOCH_HEIGHT_FOR_TEST, EPOCH_START_BLOCK_FOR_TEST, MockLedger, MockSystemParam,
+            STAKE_TABLE_CAPACITY_FOR_TEST,
+        },
+        prover::tests::CountingProver,
+        service::tests::deploy_and_upgrade,
+    };
+
+    type TestVersion = StaticVersion<0, 1>;
+
+    async fn serve(app: Router) -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{addr}/").parse().unwrap()
+    }
+
+    /// Poll `cond` until it holds, failing the test if it doesn't within a minute.
+    async fn wait_for<F: Future<Output = bool>>(what: &str, mut cond: impl FnMut() -> F) {
+        timeout(Duration::from_secs(60), async {
+            while !cond().await {
+                sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await
+        .unwrap_or_else(|_| panic!("timed out waiting for {what}"));
+    }
+
+    async fn finalized_height(provider: impl Provider, address: Address) -> u64 {
+        read_contract_state(provider, address)
+            .await
+            .unwrap()
+            .0
+            .block_height
+    }
+
+    #[test_log::test(tokio::test(flavor = "multi_thread"))]
+    async fn test_multichain_prover_service() -> Result<()> {
+        let mut ledger =
+            MockLedger::init(MockSystemParam::init(), STAKE_TABLE_CAPACITY_FOR_TEST / 2);
+        let genesis_state: LightClientStateSol = ledger.light_client_state().into();
+        let genesis_stake: StakeTableStateSol = ledger.voting_stake_table_state().into();
+
+        // Stay before the first epoch, so the update is submitted without any epoch root updates.
+        while ledger.light_client_state().block_height < 5 {
+            ledger.elapse_with_block();
+        }
+        let (_, proof) = ledger.gen_state_proof();
+        let state = ledger.light_client_state();
+        let next_stake = ledger.next_stake_table_state();
+        let auth_root = ledger.auth_root;
+        let digest = derive_signed_state_digest(&state, &next_stake, &auth_root);
+        let signatures = ledger
+            .state_keys
+            .iter()
+            .map(|(sign_key, ver_key)| {
+                let sig = SchnorrSignatureScheme::<EdwardsConfig>::sign(
+                    &(),
+                    sign_key,
+                    [digest],
+                    &mut ledger.rng,
+                )
+                .unwrap();
+                (ver_key.clone(), sig)
+            })
+            .collect();
+        let bundle = LCV3StateSignaturesBundle {
+            state,
+            next_stake,
+            auth_root,
+            signatures,
+            accumulated_weight: ledger.voting_st.iter().fold(U256::ZERO, |sum, peer| {
+                sum + peer.stake_table_entry.stake_amount
+            }),
+        };
+        let height = bundle.state.block_height;
+
+        // The sequencer only has to serve the genesis stake table, and the relay server the bundle.
+        let hotshot_config = serde_json::json!({
+            "config": { "known_nodes_with_stake": ledger.voting_st.iter().collect::<Vec<_>>() }
+        });
+        let sequencer_url = serve(Router::new().route(
+            "/config/hotshot",
+            get(move || {
+                let hotshot_config = hotshot_config.clone();
+                async move { Json(hotshot_config) }
+            }),
+        ))
+        .await;
+        let body = Serializer::<TestVersion>::serialize(&bundle).unwrap();
+        let relay_server = serve(Router::new().route(
+            "/api/lateststate",
+            get(move || {
+                let body = body.clone();
+                async move {
+                    (
+                        [(axum::http::header::CONTENT_TYPE, "application/octet-stream")],
+                        body,
+                    )
+                }
+            }),
+        ))
+        .await;
+
+        let anvils = [Anvil::new().spawn(), Anvil::new().spawn()];
+        let account = anvils[0].addresses()[0];
+        let mut providers = vec![];
+        let mut targets = vec![];
+        for (name, anvil) in ["a", "b"].into_iter().zip(&anvils) {
+            let provider = ProviderBuilder::new()
+                .wallet(anvil.wallet().unwrap())
+                .connect_http(anvil.endpoint_url());
+            let light_client_address = deploy_and_upgrade(
+                &provider,
+                &mut Contracts::new(),
+                true,
+                genesis_state.clone(),
+                genesis_stake.clone(),
+            )
+            .await?;
+            targets.push(ChainTarget {
+                name: name.into(),
+                l1_rpc_client: RpcClient::new_http(anvil.endpoint_url()),
+                light_client_address,
+                signer: anvil.keys()[0].clone().into(),
+                max_gas_price: None,
+                sync_timeout: None,
+            });
+            providers.push(provider);
+        }
+        let addresses = targets
+            .iter()
+            .map(|target| target.light_client_address)
+            .collect::<Vec<_>>();
+
+        // Only chain "a" has a gas price cap (100 gwei), and its nonce is ahead of chain "b"'s.
+        targets[0].max_gas_price = Some(100_000_000_000);
+        for _ in 0..3 {
+            providers[0]
+                .send_transaction(
+                    TransactionRequest::default()
+                        .with_to(account)
+                        .with_value(U256::from(1)),
+                )
+                .await?
+                .watch()
+                .await?;
+        }
+        let mut nonces = vec![];
+        for provider in &providers {
+            nonces.push(provider.get_transaction_count(account).await?);
+        }
+        assert_eq!(nonces[0], nonces[1] + 3);
+
+        // Chain "b" cannot pay for its submission until its balance is restored.
+        let balance = providers[1].get_balance(account).await?;
+        providers[1].anvil_set_balance(account, U256::ZERO).await?;
+
+        let config = StateProverConfig {
+            relay_server,
+            update_interval: Duration::from_secs(1),
+            retry_interval: Duration::from_millis(500),
+            l1_rpc_client: targets[0].l1_rpc_client.clone(),
+            light_client_address: targets[0].light_client_address,
+            signer: targets[0].signer.clone(),
+            sequencer_url,
+            port: None,
+            stake_table_capacity: STAKE_TABLE_CAPACITY_FOR_TEST,
+            blocks_per_epoch: EPOCH_HEIGHT_FOR_TEST,
+            epoch_start_block: EPOCH_START_BLOCK_FOR_TEST,
+            max_retries: 100,
+            max_gas_price: None,
+        };
+        let metrics = PrometheusMetrics::default();
+        let chains = init_chains(&config, targets, &metrics).await?;
+        let prover = CountingProver::new(proof);
+        let calls = prover.calls.clone();
+        let service = tokio::spawn(async move {
+            run_chains::<TestVersion>(&config, chains, Arc::new(MemoizingProver::new(prover))).await
+        });
+
+        let failures = metrics
+            .get_counter_family("light_client_sync_failures")
+            .unwrap();
+        let block_height = metrics.gauge_family("light_client_block_height").unwrap();
+
+        // Chain "a" is updated while chain "b" keeps failing.
+        wait_for("chain a to sync", || async {
+            block_height.get(&["a"]).get() == height as usize
+        })
+        .await;
+        wait_for("chain b to fail", || async {
+            failures.get(&["b"]).get() > 0
+        })
+        .await;
+        assert_eq!(finalized_height(&providers[0], addresses[0]).await, height);
+        assert_eq!(finalized_height(&providers[1], addresses[1]).await, 0);
+
+        // Once it can pay for gas again, chain "b" catches up on its own.
+        providers[1].anvil_set_balance(account, balance).await?;
+        wait_for("chain b to sync", || async {
+            block_height.get(&["b"]).get() == height as usize
+        })
+        .await;
+        assert_eq!(finalized_height(&providers[1], addresses[1]).await, height);
+        service.abort();
+
+        // The update was proved once and submitted once to each chain, from its own nonce.
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        for (provider, nonce) in providers.iter().zip(nonces) {
+            assert_eq!(provider.get_transaction_count(account).await?, nonce + 1);
+        }
+
+        let syncs = metrics.get_counter_family("light_client_syncs").unwrap();
+        assert!(syncs.get(&["a"]).get() > 0);
+        assert!(syncs.get(&["b"]).get() > 0);
+        assert_eq!(failures.get(&["a"]).get(), 0);
+        let consecutive_failures = metrics
+            .gauge_family("light_client_consecutive_sync_failures")
+            .unwrap();
+        assert_eq!(consecutive_failures.get(&["b"]).get(), 0);
+        let export = metrics.export().unwrap();
+        let lines = export.lines().collect::<Vec<_>>();
+        for chain in ["a", "b"] {
+            let line = format!("light_client_block_height{{chain=\"{chain}\"}} {height}");
+            assert!(lines.contains(&line.as_str()), "{lines:?}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_chains_file() {
+        let file: ChainsFile = toml::from_str(
+            r#"
+            [[chain]]
+            name = "arbitrum"
+            rpc_url = ["http://localhost:8547"]
+            light_client_address = "0x0000000000000000000000000000000000000001"
+
+            [[chain]]
+            name = "base"
+            rpc_url = ["http://localhost:8548", "http://localhost:8549"]
+            light_client_address = "0x0000000000000000000000000000000000000002"
+            account_index = 3
+            max_gas_price = "0.5"
+            sync_timeout = "15m"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(file.chains.len(), 2);
+        assert_eq!(file.chains[0].name, "arbitrum");
+        assert_eq!(file.chains[0].account_index, None);
+        assert_eq!(file.chains[1].rpc_url.len(), 2);
+        assert_eq!(file.chains[1].account_index, Some(3));
+        assert_eq!(file.chains[1].max_gas_price.as_deref(), Some("0.5"));
+        assert_eq!(file.chains[0].sync_timeout().unwrap(), None);
+        assert_eq!(
+            file.chains[1].sync_timeout().unwrap(),
+            Some(Duration::from_secs(15 * 60))
+        );
+
+        let empty: ChainsFile = toml::from_str("").unwrap();
+        assert!(empty.chains.is_empty());
+    }
+}
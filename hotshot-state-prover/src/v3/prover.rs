@@ -0,0 +1,370 @@
+//! Pluggable backends for generating light client state update proofs.
+//!
+//! The prover service only needs a way to turn a [`ProofRequest`] into a [`Proof`]. By default
+//! this happens in-process with a locally generated proving key ([`LocalProver`]), but proving can
+//! also be offloaded to a separate worker process ([`RemoteProver`]), and either backend can be
+//! wrapped in a [`CachingProver`] so that proofs which were already generated survive a restart,
+//! or in a [`MemoizingProver`] so that concurrent identical requests are only proved once.
+
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use alloy::primitives::{U256, keccak256};
+use async_trait::async_trait;
+use hotshot_contract_adapter::field_to_u256;
+use hotshot_types::light_client::{
+    CircuitField, LightClientState, StakeTableState, StateSignature, StateVerKey,
+};
+use jf_utils::canonical;
+use serde::{Deserialize, Serialize};
+use tokio::task::spawn_blocking;
+use url::Url;
+
+use crate::{
+    ProverError,
+    v3::snark::{Proof, ProvingKey, VerifyingKey},
+};
+
+mod cache;
+mod memo;
+mod remote;
+
+pub use cache::{CachingProver, ProofCache};
+pub use memo::MemoizingProver;
+pub use remote::{JobId, JobStatus, RemoteProver, WorkerOptions, run_worker};
+
+/// Everything needed to generate a single light client state update proof.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProofRequest {
+    /// The light client state being certified.
+    ///
+    /// This is already bound by `signed_state_digest`; it is carried along to identify the proof
+    /// in logs and caches.
+    pub light_client_state: LightClientState,
+    /// Stake table entries of the voting stake table, in stake table order.
+    pub entries: Vec<(StateVerKey, U256)>,
+    /// Which entries signed the state.
+    pub signer_bit_vec: Vec<bool>,
+    /// Signatures, with a default value for entries which did not sign.
+    pub signatures: Vec<StateSignature>,
+    /// The voting stake table state the proof is checked against.
+    pub stake_table_state: StakeTableState,
+    /// Stake table capacity of the circuit.
+    pub stake_table_capacity: usize,
+    /// Digest of the light client state, next stake table state and auth root.
+    #[serde(with = "canonical")]
+    pub signed_state_digest: CircuitField,
+}
+
+impl ProofRequest {
+    /// A key uniquely identifying the proof this request will produce.
+    ///
+    /// The key covers both public inputs of the proof, the signed state digest and the voting stake
+    /// table state, so requests which only differ in the stake table never share a proof. It is
+    /// safe to use as a file name or URL path segment.
+    pub fn key(&self) -> String {
+        let st = &self.stake_table_state;
+        let stake_table_digest = keccak256(
+            [
+                st.bls_key_comm,
+                st.schnorr_key_comm,
+                st.amount_comm,
+                st.threshold,
+            ]
+            .into_iter()
+            .flat_map(|comm| field_to_u256(comm).to_be_bytes::<32>())
+            .collect::<Vec<_>>(),
+        );
+        format!(
+            "{}-{}-{:x}-{}",
+            self.light_client_state.block_height,
+            self.light_client_state.view_number,
+            field_to_u256(self.signed_state_digest),
+            alloy::hex::encode(stake_table_digest),
+        )
+    }
+}
+
+/// A backend which is able to generate state update proofs.
+#[async_trait]
+pub trait ProverBackend: Send + Sync {
+    /// Generate a proof for `req`.
+    async fn prove(&self, req: ProofRequest) -> Result<Proof, ProverError>;
+}
+
+#[async_trait]
+impl<P: ProverBackend + ?Sized> ProverBackend for Arc<P> {
+    async fn prove(&self, req: ProofRequest) -> Result<Proof, ProverError> {
+        (**self).prove(req).await
+    }
+}
+
+/// Prover backend which generates proofs in-process with a local proving key.
+#[derive(Clone)]
+pub struct LocalProver {
+    proving_key: Arc<ProvingKey>,
+}
+
+impl LocalProver {
+    pub fn new(proving_key: Arc<ProvingKey>) -> Self {
+        Self { proving_key }
+    }
+
+    /// Load the SRS and generate the proving key for the given stake table capacity.
+    ///
+    /// This is expensive, so it runs on a blocking thread.
+    pub async fn load(stake_table_capacity: usize) -> anyhow::Result<Self> {
+        let proving_key = spawn_blocking(move || {
+            Arc::new(super::service::load_proving_key(stake_table_capacity))
+        })
+        .await?;
+        Ok(Self::new(proving_key))
+    }
+}
+
+#[async_trait]
+impl ProverBackend for LocalProver {
+    async fn prove(&self, req: ProofRequest) -> Result<Proof, ProverError> {
+        let proving_key = self.proving_key.clone();
+        let (proof, _) = spawn_blocking(move || {
+            super::snark::generate_state_update_proof(
+                &mut ark_std::rand::thread_rng(),
+                &proving_key,
+                req.entries,
+                req.signer_bit_vec,
+                req.signatures,
+                &req.stake_table_state,
+                req.stake_table_capacity,
+                &req.signed_state_digest,
+            )
+        })
+        .await
+        .map_err(|err| ProverError::Internal(err.into()))??;
+        Ok(proof)
+    }
+}
+
+/// Options selecting and configuring the prover backend.
+#[derive(Clone, Debug, Default)]
+pub struct ProverBackendOptions {
+    /// URL of a remote prover worker. If not provided, proofs are generated in-process.
+    pub remote_url: Option<Url>,
+    /// Interval at which to poll the remote worker for a finished proof.
+    pub poll_interval: Option<Duration>,
+    /// How long to wait for the remote worker to return a proof before giving up.
+    pub timeout: Option<Duration>,
+    /// Verifying key generated by `gen-vk-contract --vk-json`, used to check proofs returned by
+    /// the remote worker. If not provided, it is generated locally from the SRS.
+    pub verifying_key: Option<PathBuf>,
+    /// Directory in which to cache generated proofs, so they are not regenerated after a restart.
+    pub cache_dir: Option<PathBuf>,
+}
+
+impl ProverBackendOptions {
+    /// Instantiate the configured backend.
+    pub async fn build(
+        &self,
+        stake_table_capacity: usize,
+    ) -> anyhow::Result<Arc<dyn ProverBackend>> {
+        let backend: Arc<dyn ProverBackend> = match &self.remote_url {
+            Some(url) => {
+                tracing::info!(%url, "Using remote prover backend");
+                let vk = match &self.verifying_key {
+                    Some(path) => super::audit::load_verifying_key(path)?.into(),
+                    None => {
+                        spawn_blocking(move || {
+                            super::service::load_verifying_key(stake_table_capacity)
+                        })
+                        .await?
+                    },
+                };
+                let mut prover = RemoteProver::new(url.clone(), Arc::new(vk));
+                if let Some(interval) = self.poll_interval {
+                    prover = prover.with_poll_interval(interval);
+                }
+                if let Some(timeout) = self.timeout {
+                    prover = prover.with_timeout(timeout);
+                }
+                Arc::new(prover)
+            },
+            None => {
+                tracing::info!("Using local prover backend");
+                Arc::new(LocalProver::load(stake_table_capacity).await?)
+            },
+        };
+        match &self.cache_dir {
+            Some(dir) => {
+                tracing::info!(dir = %dir.display(), "Caching proofs");
+                let cache = ProofCache::open(dir.clone()).await?;
+                Ok(Arc::new(CachingProver::new(backend, cache)))
+            },
+            None => Ok(backend),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use hotshot_types::light_client::StateKeyPair;
+
+    use super::*;
+    use crate::v3::mock_ledger::{MockLedger, MockSystemParam, STAKE_TABLE_CAPACITY_FOR_TEST};
+
+    /// A request and a valid proof to return for it.
+    ///
+    /// The proof verifies against the public inputs of the request, but backends under test never
+    /// look at the witness, so the stake table entries and signatures are only well formed.
+    pub(crate) fn test_request() -> (ProofRequest, Proof) {
+        let mut ledger = MockLedger::init(MockSystemParam::init(), 5);
+        ledger.elapse_with_block();
+        let (public_input, proof) = ledger.gen_state_proof();
+        let key = StateKeyPair::generate().ver_key();
+        let req = ProofRequest {
+            light_client_state: ledger.light_client_state(),
+            entries: vec![(key, U256::from(1))],
+            signer_bit_vec: vec![true],
+            signatures: vec![Default::default()],
+            stake_table_state: public_input.voting_st_state,
+            stake_table_capacity: STAKE_TABLE_CAPACITY_FOR_TEST,
+            signed_state_digest: public_input.signed_state_digest,
+        };
+        (req, proof)
+    }
+
+    /// The verifying key for proofs returned by [`test_request`].
+    pub(crate) fn test_verifying_key() -> VerifyingKey {
+        super::super::service::load_verifying_key(STAKE_TABLE_CAPACITY_FOR_TEST)
+    }
+
+    /// Backend which returns a fixed proof and counts how often it was asked to prove.
+    #[derive(Clone)]
+    pub(crate) struct CountingProver {
+        proof: Proof,
+        pub(crate) calls: Arc<AtomicUsize>,
+    }
+
+    impl CountingProver {
+        pub(crate) fn new(proof: Proof) -> Self {
+            Self {
+                proof,
+                calls: Default::default(),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl ProverBackend for CountingProver {
+        async fn prove(&self, _req: ProofRequest) -> Result<Proof, ProverError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(self.proof.clone())
+        }
+    }
+
+    #[test_log::test(tokio::test(flavor = "multi_thread"))]
+    async fn test_caching_prover() {
+        let dir = tempfile::tempdir().unwrap();
+        let (req, proof) = test_request();
+
+        let backend = CountingProver::new(proof.clone());
+        let prover = CachingProver::new(
+            backend.clone(),
+            ProofCache::open(dir.path().into()).await.unwrap(),
+        );
+        assert_eq!(prover.prove(req.clone()).await.unwrap(), proof);
+        assert_eq!(prover.prove(req.clone()).await.unwrap(), proof);
+        assert_eq!(backend.calls.load(Ordering::SeqCst), 1);
+
+        // The cache survives a restart.
+        let backend = CountingProver::new(proof.clone());
+        let prover = CachingProver::new(
+            backend.clone(),
+            ProofCache::open(dir.path().into()).await.unwrap(),
+        );
+        assert_eq!(prover.prove(req.clone()).await.unwrap(), proof);
+        assert_eq!(backend.calls.load(Ordering::SeqCst), 0);
+
+        // A request for a different stake table is not served from the cache.
+        let mut other = req;
+        other.stake_table_state = StakeTableState::default();
+        prover.prove(other).await.unwrap();
+        assert_eq!(backend.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test_log::test(tokio::test(flavor = "multi_thread"))]
+    async fn test_memoizing_prover() {
+        let (req, proof) = test_request();
+        let backend = CountingProver::new(proof.clone());
+        let prover = Arc::new(MemoizingProver::new(backend.clone()));
+
+        // Concurrent identical requests are proved once.
+        let results = futures::future::join_all((0..3).map(|_| {
+            let prover = prover.clone();
+            let req = req.clone();
+            async move { prover.prove(req).await.unwrap() }
+        }))
+        .await;
+        assert!(results.iter().all(|res| *res == proof));
+        assert_eq!(backend.calls.load(Ordering::SeqCst), 1);
+
+        let mut other = req;
+        other.stake_table_state = StakeTableState::default();
+        prover.prove(other).await.unwrap();
+        assert_eq!(backend.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test_log::test(tokio::test(flavor = "multi_thread"))]
+    async fn test_memoizing_prover_distinct_requests() {
+        /// Backend which only finishes once two proofs are in progress at the same time.
+        struct BarrierProver {
+            proof: Proof,
+            barrier: tokio::sync::Barrier,
+        }
+
+        #[async_trait]
+        impl ProverBackend for BarrierProver {
+            async fn prove(&self, _req: ProofRequest) -> Result<Proof, ProverError> {
+                self.barrier.wait().await;
+                Ok(self.proof.clone())
+            }
+        }
+
+        let (req, proof) = test_request();
+        let prover = MemoizingProver::new(BarrierProver {
+            proof: proof.clone(),
+            barrier: tokio::sync::Barrier::new(2),
+        });
+
+        // Different requests are proved concurrently, not one after the other.
+        let mut other = req.clone();
+        other.stake_table_state = StakeTableState::default();
+        let (res1, res2) = tokio::time::timeout(
+            Duration::from_secs(10),
+            futures::future::join(prover.prove(req), prover.prove(other)),
+        )
+        .await
+        .expect("requests for different proofs should not wait for each other");
+        assert_eq!(res1.unwrap(), proof);
+        assert_eq!(res2.unwrap(), proof);
+    }
+
+    #[test]
+    fn test_request_key() {
+        let (req, _) = test_request();
+        assert_eq!(req.key(), req.clone().key());
+
+        // Both public inputs are part of the key.
+        let mut other = req.clone();
+        other.stake_table_state.threshold += CircuitField::from(1u64);
+        assert_ne!(other.key(), req.key());
+        let mut other = req.clone();
+        other.signed_state_digest += CircuitField::from(1u64);
+        assert_ne!(other.key(), req.key());
+
+        // Keys start with the block height, which the cache relies on for eviction.
+        assert!(
+            req.key()
+                .starts_with(&format!("{}-", req.light_client_state.block_height))
+        );
+    }
+}
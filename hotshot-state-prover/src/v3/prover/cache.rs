@@ -0,0 +1,153 @@
+//! On-disk cache of generated proofs.
+
+use std::path::PathBuf;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use hotshot_types::light_client::{CircuitField, StakeTableState};
+use jf_utils::canonical;
+use serde::{Deserialize, Serialize};
+
+use super::{ProofRequest, ProverBackend};
+use crate::{ProverError, v3::snark::Proof};
+
+/// Maximum number of proofs to keep in the cache.
+///
+/// Only the most recent proofs are ever useful: once a state has been submitted, the contract will
+/// not accept a proof for an older one.
+const MAX_CACHED_PROOFS: usize = 64;
+
+/// A proof stored in the cache, along with the public inputs it was generated for.
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedProof {
+    stake_table_state: StakeTableState,
+    #[serde(with = "canonical")]
+    signed_state_digest: CircuitField,
+    #[serde(with = "canonical")]
+    proof: Proof,
+}
+
+/// Directory of proofs, keyed by [`ProofRequest::key`].
+#[derive(Clone, Debug)]
+pub struct ProofCache {
+    dir: PathBuf,
+}
+
+impl ProofCache {
+    /// Open a cache in `dir`, creating the directory if necessary.
+    pub async fn open(dir: PathBuf) -> anyhow::Result<Self> {
+        tokio::fs::create_dir_all(&dir)
+            .await
+            .with_context(|| format!("creating proof cache directory {}", dir.display()))?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.json"))
+    }
+
+    /// Look up a cached proof for `req`.
+    ///
+    /// A cached proof is only returned if it was generated for exactly the same public inputs.
+    pub async fn get(&self, req: &ProofRequest) -> Option<Proof> {
+        let path = self.path(&req.key());
+        let bytes = tokio::fs::read(&path).await.ok()?;
+        let cached: CachedProof = match serde_json::from_slice(&bytes) {
+            Ok(cached) => cached,
+            Err(err) => {
+                tracing::warn!(path = %path.display(), "corrupt cached proof: {err:#}");
+                return None;
+            },
+        };
+        if cached.stake_table_state != req.stake_table_state
+            || cached.signed_state_digest != req.signed_state_digest
+        {
+            tracing::warn!(path = %path.display(), "cached proof does not match request");
+            return None;
+        }
+        Some(cached.proof)
+    }
+
+    /// Store a proof for `req`, evicting the oldest proofs if the cache is full.
+    pub async fn insert(&self, req: &ProofRequest, proof: &Proof) -> anyhow::Result<()> {
+        let cached = CachedProof {
+            stake_table_state: req.stake_table_state,
+            signed_state_digest: req.signed_state_digest,
+            proof: proof.clone(),
+        };
+        let bytes = serde_json::to_vec(&cached)?;
+
+        // Write to a temporary file and rename, so a crash never leaves a partial proof behind.
+        let path = self.path(&req.key());
+        let tmp = path.with_extension("json.tmp");
+        tokio::fs::write(&tmp, bytes)
+            .await
+            .with_context(|| format!("writing {}", tmp.display()))?;
+        tokio::fs::rename(&tmp, &path)
+            .await
+            .with_context(|| format!("renaming {} to {}", tmp.display(), path.display()))?;
+
+        self.evict().await
+    }
+
+    async fn evict(&self) -> anyhow::Result<()> {
+        let mut entries = vec![];
+        let mut dir = tokio::fs::read_dir(&self.dir).await?;
+        while let Some(entry) = dir.next_entry().await? {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            // Keys start with the block height.
+            let Some(height) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.split('-').next())
+                .and_then(|height| height.parse::<u64>().ok())
+            else {
+                continue;
+            };
+            entries.push((height, path));
+        }
+        if entries.len() <= MAX_CACHED_PROOFS {
+            return Ok(());
+        }
+
+        entries.sort();
+        for (_, path) in &entries[..entries.len() - MAX_CACHED_PROOFS] {
+            tracing::debug!(path = %path.display(), "evicting cached proof");
+            tokio::fs::remove_file(path).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Prover backend which checks a [`ProofCache`] before delegating to another backend.
+#[derive(Clone, Debug)]
+pub struct CachingProver<P> {
+    inner: P,
+    cache: ProofCache,
+}
+
+impl<P> CachingProver<P> {
+    pub fn new(inner: P, cache: ProofCache) -> Self {
+        Self { inner, cache }
+    }
+}
+
+#[async_trait]
+impl<P: ProverBackend> ProverBackend for CachingProver<P> {
+    async fn prove(&self, req: ProofRequest) -> Result<Proof, ProverError> {
+        if let Some(proof) = self.cache.get(&req).await {
+            tracing::info!(key = req.key(), "Using cached proof");
+            return Ok(proof);
+        }
+
+        let proof = self.inner.prove(req.clone()).await?;
+        // Failing to cache the proof is not fatal; we just won't be able to reuse it.
+        if let Err(err) = self.cache.insert(&req, &proof).await {
+            tracing::warn!(key = req.key(), "failed to cache proof: {err:#}");
+        }
+        Ok(proof)
+    }
+}
@@ -0,0 +1,66 @@
+//! In-memory deduplication of proof requests.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use tokio::sync::OnceCell;
+
+use super::{ProofRequest, ProverBackend};
+use crate::{ProverError, v3::snark::Proof};
+
+/// Number of recent proofs kept in memory.
+const MAX_MEMOIZED_PROOFS: usize = 16;
+
+/// Prover backend which reuses recently generated proofs for identical requests.
+///
+/// Concurrent callers asking for the same proof, for example when submitting one update to several
+/// chains, wait for the first to finish and then share its result instead of proving again.
+/// Requests for different proofs do not wait for each other.
+pub struct MemoizingProver<P> {
+    inner: P,
+    /// Recent proofs, keyed by [`ProofRequest::key`], which covers all public inputs of the proof.
+    ///
+    /// A cell which is not yet initialized is a proof in progress, or one which failed and will be
+    /// retried by the next caller.
+    proofs: Mutex<VecDeque<(String, Arc<OnceCell<Proof>>)>>,
+}
+
+impl<P> MemoizingProver<P> {
+    pub fn new(inner: P) -> Self {
+        Self {
+            inner,
+            proofs: Default::default(),
+        }
+    }
+
+    fn entry(&self, key: &str) -> Arc<OnceCell<Proof>> {
+        let mut proofs = self.proofs.lock().unwrap();
+        if let Some((_, cell)) = proofs.iter().find(|(memoized, _)| memoized == key) {
+            return cell.clone();
+        }
+        let cell = Arc::new(OnceCell::new());
+        proofs.push_back((key.to_string(), cell.clone()));
+        if proofs.len() > MAX_MEMOIZED_PROOFS {
+            proofs.pop_front();
+        }
+        cell
+    }
+}
+
+#[async_trait]
+impl<P: ProverBackend> ProverBackend for MemoizingProver<P> {
+    async fn prove(&self, req: ProofRequest) -> Result<Proof, ProverError> {
+        let key = req.key();
+        let cell = self.entry(&key);
+        if let Some(proof) = cell.get() {
+            tracing::info!(key, "Reusing recently generated proof");
+            return Ok(proof.clone());
+        }
+        cell.get_or_try_init(|| self.inner.prove(req))
+            .await
+            .cloned()
+    }
+}
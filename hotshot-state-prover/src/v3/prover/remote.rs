@@ -0,0 +1,377 @@
+//! Offloading proof generation to a remote worker over HTTP.
+//!
+//! The worker ([`run_worker`]) exposes a simple job queue:
+//! * `POST /v0/jobs` submits a [`ProofRequest`] and returns its [`JobId`]
+//! * `GET /v0/jobs/{id}` returns the [`JobStatus`] of a job
+//!
+//! Job IDs are derived from the request ([`ProofRequest::key`]), so submitting the same request
+//! twice, for example after the prover service restarts, attaches to the existing job instead of
+//! proving the same state again.
+//!
+//! The worker is not trusted: [`RemoteProver`] checks every proof it returns against the verifying
+//! key before handing it to the caller.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    net::SocketAddr,
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{Context, anyhow};
+use async_trait::async_trait;
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{get, post},
+};
+use jf_utils::canonical;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, mpsc};
+use url::Url;
+
+use super::{ProofRequest, ProverBackend};
+use crate::{
+    ProverError,
+    v3::{
+        audit::verify_proof,
+        snark::{Proof, PublicInput, VerifyingKey},
+    },
+};
+
+/// Identifier of a job in the worker queue.
+pub type JobId = String;
+
+/// Default interval at which [`RemoteProver`] polls for a finished job.
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Default time [`RemoteProver`] waits for a proof, including time spent queued on the worker.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Number of finished jobs the worker remembers before forgetting the oldest.
+const MAX_FINISHED_JOBS: usize = 256;
+
+/// Status of a proving job.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum JobStatus {
+    /// The job is waiting for a free prover.
+    Queued,
+    /// The proof is being generated.
+    Proving,
+    /// The proof was generated successfully.
+    Done(#[serde(with = "canonical")] Proof),
+    /// Proof generation failed.
+    Failed(String),
+}
+
+/// Prover backend which submits jobs to a remote worker and waits for the result.
+#[derive(Clone, Debug)]
+pub struct RemoteProver {
+    client: reqwest::Client,
+    url: Url,
+    vk: Arc<VerifyingKey>,
+    poll_interval: Duration,
+    timeout: Duration,
+}
+
+impl RemoteProver {
+    /// Create a backend for the worker at `url`, which checks returned proofs against `vk`.
+    pub fn new(url: Url, vk: Arc<VerifyingKey>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+            vk,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    async fn submit(&self, req: &ProofRequest) -> anyhow::Result<JobId> {
+        let url = self.url.join("v0/jobs")?;
+        let res = self
+            .client
+            .post(url)
+            .json(req)
+            .send()
+            .await
+            .context("submitting proving job")?
+            .error_for_status()
+            .context("submitting proving job")?;
+        res.json().await.context("decoding job id")
+    }
+
+    async fn status(&self, id: &JobId) -> anyhow::Result<Option<JobStatus>> {
+        let url = self.url.join(&format!("v0/jobs/{id}"))?;
+        let res = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .context("polling proving job")?;
+        if res.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let status = res
+            .error_for_status()
+            .context("polling proving job")?
+            .json()
+            .await
+            .context("decoding job status")?;
+        Ok(Some(status))
+    }
+
+    /// Submit `req` and poll until the worker finishes it.
+    async fn wait(&self, req: &ProofRequest) -> Result<Proof, ProverError> {
+        let mut id = self.submit(req).await.map_err(ProverError::Internal)?;
+        tracing::info!(%id, worker = %self.url, "Submitted proving job");
+        loop {
+            tokio::time::sleep(self.poll_interval).await;
+            match self.status(&id).await.map_err(ProverError::Internal)? {
+                Some(JobStatus::Done(proof)) => {
+                    tracing::info!(%id, "Proving job finished");
+                    return Ok(proof);
+                },
+                Some(JobStatus::Failed(err)) => {
+                    return Err(ProverError::Internal(anyhow!(
+                        "proving job {id} failed: {err}"
+                    )));
+                },
+                Some(status) => {
+                    tracing::debug!(%id, ?status, "Waiting for proving job");
+                },
+                None => {
+                    // The worker restarted or forgot the job; submit it again.
+                    tracing::warn!(%id, "Proving job lost, resubmitting");
+                    id = self.submit(req).await.map_err(ProverError::Internal)?;
+                },
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl ProverBackend for RemoteProver {
+    async fn prove(&self, req: ProofRequest) -> Result<Proof, ProverError> {
+        let proof = tokio::time::timeout(self.timeout, self.wait(&req))
+            .await
+            .map_err(|_| {
+                ProverError::Internal(anyhow!(
+                    "proving job {} timed out after {:?}",
+                    req.key(),
+                    self.timeout
+                ))
+            })??;
+
+        let public_input = PublicInput::new(req.stake_table_state, req.signed_state_digest);
+        verify_proof(&self.vk, &public_input, &proof).map_err(|err| {
+            ProverError::Internal(anyhow!(
+                "worker returned an invalid proof for job {}: {err}",
+                req.key()
+            ))
+        })?;
+        Ok(proof)
+    }
+}
+
+/// Configuration for a remote prover worker.
+#[derive(Clone, Debug)]
+pub struct WorkerOptions {
+    /// Address to serve the job queue on.
+    pub bind: SocketAddr,
+    /// Maximum number of jobs waiting to be proved.
+    pub queue_capacity: usize,
+}
+
+#[derive(Default)]
+struct Jobs {
+    status: HashMap<JobId, JobStatus>,
+    finished: VecDeque<JobId>,
+}
+
+impl Jobs {
+    fn finish(&mut self, id: JobId, status: JobStatus) {
+        self.status.insert(id.clone(), status);
+        self.finished.push_back(id);
+        while self.finished.len() > MAX_FINISHED_JOBS {
+            if let Some(id) = self.finished.pop_front() {
+                self.status.remove(&id);
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+struct WorkerState {
+    jobs: Arc<Mutex<Jobs>>,
+    queue: mpsc::Sender<(JobId, ProofRequest)>,
+}
+
+fn router(state: WorkerState) -> Router {
+    Router::new()
+        .route("/v0/jobs", post(submit_job))
+        .route("/v0/jobs/{id}", get(job_status))
+        .route("/healthcheck", get(|| async { StatusCode::OK }))
+        .with_state(state)
+}
+
+async fn submit_job(
+    State(state): State<WorkerState>,
+    Json(req): Json<ProofRequest>,
+) -> Result<Json<JobId>, (StatusCode, String)> {
+    let id = req.key();
+    let mut jobs = state.jobs.lock().await;
+    match jobs.status.get(&id) {
+        // Retry failed jobs, attach to any other existing job.
+        Some(JobStatus::Failed(_)) | None => {},
+        Some(_) => return Ok(Json(id)),
+    }
+    jobs.finished.retain(|finished| finished != &id);
+    state
+        .queue
+        .try_send((id.clone(), req))
+        .map_err(|_| (StatusCode::SERVICE_UNAVAILABLE, "job queue full".into()))?;
+    jobs.status.insert(id.clone(), JobStatus::Queued);
+    tracing::info!(%id, "Queued proving job");
+    Ok(Json(id))
+}
+
+async fn job_status(
+    State(state): State<WorkerState>,
+    Path(id): Path<JobId>,
+) -> Result<Json<JobStatus>, StatusCode> {
+    state
+        .jobs
+        .lock()
+        .await
+        .status
+        .get(&id)
+        .cloned()
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Build the job queue router, spawning a task which generates proofs one at a time with `prover`.
+fn worker(queue_capacity: usize, prover: impl ProverBackend + 'static) -> Router {
+    let (queue, mut jobs_rx) = mpsc::channel(queue_capacity);
+    let state = WorkerState {
+        jobs: Default::default(),
+        queue,
+    };
+
+    let jobs = state.jobs.clone();
+    tokio::spawn(async move {
+        while let Some((id, req)) = jobs_rx.recv().await {
+            jobs.lock()
+                .await
+                .status
+                .insert(id.clone(), JobStatus::Proving);
+            tracing::info!(%id, "Proving");
+            let status = match prover.prove(req).await {
+                Ok(proof) => JobStatus::Done(proof),
+                Err(err) => {
+                    tracing::error!(%id, "Proving job failed: {err}");
+                    JobStatus::Failed(err.to_string())
+                },
+            };
+            jobs.lock().await.finish(id, status);
+        }
+    });
+
+    router(state)
+}
+
+/// Serve a proving job queue, generating proofs one at a time with `prover`.
+pub async fn run_worker(
+    opt: WorkerOptions,
+    prover: impl ProverBackend + 'static,
+) -> anyhow::Result<()> {
+    let listener = tokio::net::TcpListener::bind(opt.bind)
+        .await
+        .with_context(|| format!("binding to {}", opt.bind))?;
+    tracing::info!("Prover worker listening on {}", opt.bind);
+    axum::serve(listener, worker(opt.queue_capacity, prover)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::Ordering;
+
+    use hotshot_types::light_client::StakeTableState;
+
+    use super::*;
+    use crate::v3::prover::tests::{CountingProver, test_request, test_verifying_key};
+
+    /// Serve a worker backed by `backend` on a random local port.
+    async fn spawn_worker(backend: impl ProverBackend + 'static) -> Url {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap())
+            .parse()
+            .unwrap();
+        tokio::spawn(async move { axum::serve(listener, worker(4, backend)).await });
+        url
+    }
+
+    /// Backend which never finishes a proof.
+    struct StuckProver;
+
+    #[async_trait]
+    impl ProverBackend for StuckProver {
+        async fn prove(&self, _req: ProofRequest) -> Result<Proof, ProverError> {
+            futures::future::pending().await
+        }
+    }
+
+    #[test_log::test(tokio::test(flavor = "multi_thread"))]
+    async fn test_remote_prover() {
+        let (req, proof) = test_request();
+        let backend = CountingProver::new(proof.clone());
+        let calls = backend.calls.clone();
+        let url = spawn_worker(backend).await;
+
+        let prover = RemoteProver::new(url, Arc::new(test_verifying_key()))
+            .with_poll_interval(Duration::from_millis(10));
+        assert_eq!(prover.prove(req.clone()).await.unwrap(), proof);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Submitting the same request again attaches to the finished job.
+        assert_eq!(prover.prove(req).await.unwrap(), proof);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test_log::test(tokio::test(flavor = "multi_thread"))]
+    async fn test_remote_prover_rejects_invalid_proof() {
+        let (mut req, proof) = test_request();
+        // The worker returns a proof which does not match the public inputs of the request.
+        req.stake_table_state = StakeTableState::default();
+        let url = spawn_worker(CountingProver::new(proof)).await;
+
+        let prover = RemoteProver::new(url, Arc::new(test_verifying_key()))
+            .with_poll_interval(Duration::from_millis(10));
+        let err = prover.prove(req).await.unwrap_err();
+        assert!(err.to_string().contains("invalid proof"), "{err}");
+    }
+
+    #[test_log::test(tokio::test(flavor = "multi_thread"))]
+    async fn test_remote_prover_timeout() {
+        let (req, _) = test_request();
+        let url = spawn_worker(StuckProver).await;
+
+        let prover = RemoteProver::new(url, Arc::new(test_verifying_key()))
+            .with_poll_interval(Duration::from_millis(10))
+            .with_timeout(Duration::from_millis(200));
+        let err = prover.prove(req).await.unwrap_err();
+        assert!(err.to_string().contains("timed out"), "{err}");
+    }
+}
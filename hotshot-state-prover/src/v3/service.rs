@@ -31,16 +31,30 @@ use http_client::{Client, error::ClientErr};
 use jf_pcs::prelude::UnivariateUniversalParams;
 use jf_relation::Circuit as _;
 use time::ext::InstantExt;
-use tokio::{task::spawn_blocking, time::sleep};
+use tokio::time::sleep;
 use url::Url;
 use vbs::version::{StaticVersion, StaticVersionType};
 
 use crate::{
     ProverError, ProverServiceState, StateProverConfig,
-    v3::snark::{Proof, ProvingKey, PublicInput},
+    v3::{
+        prover::{ProofRequest, ProverBackend, ProverBackendOptions},
+        snark::{Proof, ProvingKey, PublicInput, VerifyingKey},
+    },
 };
 
 pub fn load_proving_key(stake_table_capacity: usize) -> ProvingKey {
+    load_keys(stake_table_capacity).0
+}
+
+/// Generate the verifying key for the given stake table capacity.
+///
+/// This requires loading the SRS and preprocessing the circuit, just like [`load_proving_key`].
+pub fn load_verifying_key(stake_table_capacity: usize) -> VerifyingKey {
+    load_keys(stake_table_capacity).1
+}
+
+fn load_keys(stake_table_capacity: usize) -> (ProvingKey, VerifyingKey) {
     let srs = {
         let num_gates = super::circuit::build_for_preprocessing::<
             CircuitField,
@@ -69,11 +83,11 @@ pub fn load_proving_key(stake_table_capacity: usize) -> ProvingKey {
 
     tracing::info!("Generating proving key and verification key.");
     let key_gen_timer = Instant::now();
-    let (pk, _) = super::snark::preprocess(&srs, stake_table_capacity)
+    let keys = super::snark::preprocess(&srs, stake_table_capacity)
         .expect("Error loading proving key: failed to preprocess state prover circuit.");
     let key_gen_elapsed = Instant::now().signed_duration_since(key_gen_timer);
     tracing::info!("Done in {key_gen_elapsed:.3}");
-    pk
+    keys
 }
 
 #[inline(always)]
@@ -167,7 +181,7 @@ pub async fn submit_state_and_proof(
     Ok(receipt)
 }
 
-async fn fetch_epoch_state_from_sequencer(
+pub(crate) async fn fetch_epoch_state_from_sequencer(
     sequencer_url: &Url,
     epoch: u64,
 ) -> Result<LightClientStateUpdateCertificateV2<SeqTypes>, ProverError> {
@@ -187,15 +201,17 @@ async fn fetch_epoch_state_from_sequencer(
     Ok(state_cert.0)
 }
 
-async fn generate_proof(
-    state: &mut ProverServiceState,
+/// Match the collected signatures against the local stake table and assemble a proof request.
+///
+/// Returns the request along with the accumulated stake of the valid signers.
+pub(crate) fn build_proof_request(
+    state: &ProverServiceState,
     lc_state: LightClientState,
     current_stake_table_state: StakeTableState,
     next_stake_table_state: StakeTableState,
     auth_root: FixedBytes<32>,
-    signature_map: HashMap<StateVerKey, StateSignature>,
-    proving_key: &ProvingKey,
-) -> Result<(Proof, PublicInput), ProverError> {
+    signature_map: &HashMap<StateVerKey, StateSignature>,
+) -> Result<(ProofRequest, U256), ProverError> {
     // Check whether the local stake table matches the one on the contract
     // If there's a mismatch, the contract won't accept the generated proof
     if state.st_state != current_stake_table_state {
@@ -242,6 +258,36 @@ async fn generate_proof(
         accumulated_weight
     );
 
+    let req = ProofRequest {
+        light_client_state: lc_state,
+        entries,
+        signer_bit_vec,
+        signatures,
+        stake_table_state: current_stake_table_state,
+        stake_table_capacity: state.config.stake_table_capacity,
+        signed_state_digest,
+    };
+    Ok((req, accumulated_weight))
+}
+
+async fn generate_proof(
+    state: &mut ProverServiceState,
+    lc_state: LightClientState,
+    current_stake_table_state: StakeTableState,
+    next_stake_table_state: StakeTableState,
+    auth_root: FixedBytes<32>,
+    signature_map: HashMap<StateVerKey, StateSignature>,
+    prover: &dyn ProverBackend,
+) -> Result<(Proof, PublicInput), ProverError> {
+    let (req, accumulated_weight) = build_proof_request(
+        state,
+        lc_state,
+        current_stake_table_state,
+        next_stake_table_state,
+        auth_root,
+        &signature_map,
+    )?;
+
     if accumulated_weight < field_to_u256(current_stake_table_state.threshold) {
         return Err(ProverError::InvalidState(
             "The signers' total weight doesn't reach the threshold.".to_string(),
@@ -250,23 +296,8 @@ async fn generate_proof(
 
     tracing::info!("Collected latest state and signatures. Start generating SNARK proof.");
     let proof_gen_start = Instant::now();
-    let proving_key_clone = proving_key.clone();
-    let stake_table_capacity = state.config.stake_table_capacity;
-    let (proof, public_input) = spawn_blocking(move || {
-        super::snark::generate_state_update_proof(
-            &mut ark_std::rand::thread_rng(),
-            &proving_key_clone,
-            entries,
-            signer_bit_vec,
-            signatures,
-            &current_stake_table_state,
-            stake_table_capacity,
-            &signed_state_digest,
-        )
-    })
-    .await
-    .with_context(|| "Failed to join the proof generation task")
-    .map_err(ProverError::Internal)??;
+    let public_input = PublicInput::new(current_stake_table_state, req.signed_state_digest);
+    let proof = prover.prove(req).await?;
 
     let proof_gen_elapsed = Instant::now().signed_duration_since(proof_gen_start);
     tracing::info!("Proof generation completed. Elapsed: {proof_gen_elapsed:.3}");
@@ -274,6 +305,44 @@ async fn generate_proof(
     Ok((proof, public_input))
 }
 
+/// The epoch of the stake table the contract currently verifies updates against.
+///
+/// Returns `None` if epochs were not enabled at the contract's block height.
+pub(crate) fn contract_epoch(
+    config: &StateProverConfig,
+    contract_state: &LightClientState,
+) -> Option<EpochNumber> {
+    let contract_state_epoch_enabled = contract_state.block_height >= config.epoch_start_block;
+    let contract_epoch = option_epoch_from_block_number(
+        contract_state_epoch_enabled,
+        contract_state.block_height,
+        config.blocks_per_epoch,
+    );
+    // If the last contract update was on an epoch root, it's already on the next epoch.
+    if contract_state_epoch_enabled
+        && is_epoch_root(contract_state.block_height, config.blocks_per_epoch)
+    {
+        contract_epoch.map(|en| en + 1)
+    } else {
+        contract_epoch
+    }
+}
+
+/// The first epoch whose root update must be submitted to move the contract on from
+/// `contract_epoch`.
+pub(crate) fn first_catchup_epoch(
+    config: &StateProverConfig,
+    contract_epoch: Option<EpochNumber>,
+) -> u64 {
+    contract_epoch
+        .map(|en| en.u64())
+        .unwrap_or(0)
+        .max(epoch_from_block_number(
+            config.epoch_start_block,
+            config.blocks_per_epoch,
+        ))
+}
+
 /// This function will fetch the cross epoch state update information from the sequencer query node
 /// and update the light client state in the contract to the `target_epoch`.
 /// In the end, both the locally stored stake table and the contract light client state will correspond
@@ -284,7 +353,7 @@ async fn advance_epoch(
     provider: impl Provider,
     light_client_address: Address,
     mut cur_st_state: StakeTableState,
-    proving_key: &ProvingKey,
+    prover: &dyn ProverBackend,
     contract_epoch: Option<EpochNumber>,
     target_epoch: Option<EpochNumber>,
 ) -> Result<StakeTableState, ProverError> {
@@ -301,13 +370,7 @@ async fn advance_epoch(
             .with_context(|| format!("Failed to sync with epoch {contract_epoch:?}"))
             .map_err(ProverError::Internal)?;
     }
-    let base_epoch = contract_epoch
-        .map(|en| en.u64())
-        .unwrap_or(0)
-        .max(epoch_from_block_number(
-            state.config.epoch_start_block,
-            state.config.blocks_per_epoch,
-        ));
+    let base_epoch = first_catchup_epoch(&state.config, contract_epoch);
     let target_epoch = target_epoch.u64();
     for epoch in base_epoch..target_epoch {
         tracing::info!("Performing epoch root state update for epoch {epoch}...");
@@ -326,7 +389,7 @@ async fn advance_epoch(
             state_cert.next_stake_table_state,
             state_cert.auth_root,
             signature_map,
-            proving_key,
+            prover,
         )
         .await?;
 
@@ -354,8 +417,19 @@ async fn advance_epoch(
 /// Sync the light client state from the relay server and submit the proof to the L1 LightClient contract
 pub async fn sync_state<ApiVer: StaticVersionType>(
     state: &mut ProverServiceState,
-    proving_key: &ProvingKey,
+    prover: &dyn ProverBackend,
     relay_server_client: &Client<ClientErr, ApiVer>,
+) -> Result<(), ProverError> {
+    let bundle = fetch_latest_state(relay_server_client).await?;
+    sync_state_with_bundle(state, prover, bundle).await
+}
+
+/// Submit a proof for `bundle`, or for whichever epoch root updates must precede it, to the L1
+/// LightClient contract
+pub async fn sync_state_with_bundle(
+    state: &mut ProverServiceState,
+    prover: &dyn ProverBackend,
+    bundle: LCV3StateSignaturesBundle,
 ) -> Result<(), ProverError> {
     let light_client_address = state.config.light_client_address;
     let wallet = EthereumWallet::from(state.config.signer.clone());
@@ -389,7 +463,6 @@ pub async fn sync_state<ApiVer: StaticVersionType>(
         contract_state.block_height
     );
 
-    let bundle = fetch_latest_state(relay_server_client).await?;
     tracing::debug!("Bundle accumulated weight: {}", bundle.accumulated_weight);
     tracing::info!("Latest HotShot block height: {}", bundle.state.block_height);
 
@@ -403,7 +476,6 @@ pub async fn sync_state<ApiVer: StaticVersionType>(
     tracing::debug!("Contract stake table state: {contract_st_state}");
     tracing::debug!("Bundle stake table state: {}", bundle.next_stake);
 
-    let contract_state_epoch_enabled = contract_state.block_height >= epoch_start_block;
     let epoch_enabled = bundle.state.block_height >= epoch_start_block;
 
     if !epoch_enabled {
@@ -415,7 +487,7 @@ pub async fn sync_state<ApiVer: StaticVersionType>(
             contract_st_state,
             bundle.auth_root,
             bundle.signatures,
-            proving_key,
+            prover,
         )
         .await?;
 
@@ -432,19 +504,7 @@ pub async fn sync_state<ApiVer: StaticVersionType>(
         tracing::info!("Successfully synced light client state.");
     } else {
         // After the epoch is enabled
-        let contract_epoch = option_epoch_from_block_number(
-            contract_state_epoch_enabled,
-            contract_state.block_height,
-            blocks_per_epoch,
-        );
-        // If the last contract update was on an epoch root, it's already on the next epoch.
-        let contract_epoch = if contract_state_epoch_enabled
-            && is_epoch_root(contract_state.block_height, blocks_per_epoch)
-        {
-            contract_epoch.map(|en| en + 1)
-        } else {
-            contract_epoch
-        };
+        let contract_epoch = contract_epoch(&state.config, &contract_state);
 
         let bundle_epoch = option_epoch_from_block_number(
             epoch_enabled,
@@ -471,7 +531,7 @@ pub async fn sync_state<ApiVer: StaticVersionType>(
                 &provider,
                 light_client_address,
                 contract_st_state,
-                proving_key,
+                prover,
                 contract_epoch,
                 bundle_epoch,
             )
@@ -489,7 +549,7 @@ pub async fn sync_state<ApiVer: StaticVersionType>(
                 &provider,
                 light_client_address,
                 contract_st_state,
-                proving_key,
+                prover,
                 bundle_epoch,
                 bundle_next_epoch,
             )
@@ -503,7 +563,7 @@ pub async fn sync_state<ApiVer: StaticVersionType>(
                 contract_st_state,
                 bundle.auth_root,
                 bundle.signatures,
-                proving_key,
+                prover,
             )
             .await?;
 
@@ -526,6 +586,15 @@ pub async fn sync_state<ApiVer: StaticVersionType>(
 /// Run prover in daemon mode
 pub async fn run_prover_service<ApiVer: StaticVersionType + 'static>(
     config: StateProverConfig,
+    bind_version: ApiVer,
+) -> Result<()> {
+    run_prover_service_with_backend(config, ProverBackendOptions::default(), bind_version).await
+}
+
+/// Run prover in daemon mode, generating proofs with the configured backend
+pub async fn run_prover_service_with_backend<ApiVer: StaticVersionType + 'static>(
+    config: StateProverConfig,
+    backend: ProverBackendOptions,
     _bind_version: ApiVer,
 ) -> Result<()> {
     let mut state = ProverServiceState::new_genesis(config).await?;
@@ -547,14 +616,12 @@ pub async fn run_prover_service<ApiVer: StaticVersionType + 'static>(
         crate::http::start_light_client_contract_server(port, state.config.light_client_address);
     }
 
-    let proving_key =
-        spawn_blocking(move || Arc::new(load_proving_key(state.config.stake_table_capacity)))
-            .await?;
+    let prover = backend.build(stake_table_capacity).await?;
 
     let update_interval = state.config.update_interval;
     let retry_interval = state.config.retry_interval;
     loop {
-        if let Err(err) = sync_state(&mut state, &proving_key, &relay_server_client).await {
+        if let Err(err) = sync_state(&mut state, &*prover, &relay_server_client).await {
             tracing::error!(
                 "Cannot sync the light client state, will retry in {:.1}s: {}",
                 retry_interval.as_secs_f32(),
@@ -571,17 +638,24 @@ pub async fn run_prover_service<ApiVer: StaticVersionType + 'static>(
 /// Run light client state prover once
 pub async fn run_prover_once<ApiVer: StaticVersionType>(
     config: StateProverConfig,
+    bind_version: ApiVer,
+) -> Result<()> {
+    run_prover_once_with_backend(config, ProverBackendOptions::default(), bind_version).await
+}
+
+/// Run light client state prover once, generating proofs with the configured backend
+pub async fn run_prover_once_with_backend<ApiVer: StaticVersionType>(
+    config: StateProverConfig,
+    backend: ProverBackendOptions,
     _: ApiVer,
 ) -> Result<()> {
     let mut state = ProverServiceState::new_genesis(config).await?;
 
-    let stake_table_capacity = state.config.stake_table_capacity;
-    let proving_key =
-        spawn_blocking(move || Arc::new(load_proving_key(stake_table_capacity))).await?;
+    let prover = backend.build(state.config.stake_table_capacity).await?;
     let relay_server_client = Client::<ClientErr, ApiVer>::new(state.config.relay_server.clone());
 
     for _ in 0..state.config.max_retries {
-        match sync_state(&mut state, &proving_key, &relay_server_client).await {
+        match sync_state(&mut state, &*prover, &relay_server_client).await {
             Ok(_) => return Ok(()),
             Err(err) => {
                 tracing::error!(
@@ -598,7 +672,7 @@ pub async fn run_prover_once<ApiVer: StaticVersionType>(
 
 // TODO: No test b/c we don't have LCV3 mock contract
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use alloy::{node_bindings::Anvil, providers::ProviderBuilder, sol_types::SolValue};
     use anyhow::Result;
     use espresso_contract_deployer::{
@@ -618,7 +692,7 @@ mod tests {
 
     /// This helper function deploy LightClient V1, and its Proxy, then deploy V2 and upgrade the proxy.
     /// Returns the address of the proxy, caller can cast the address to be `LightClientV3` or `LightClientV3Mock`
-    async fn deploy_and_upgrade(
+    pub(crate) async fn deploy_and_upgrade(
         provider: impl Provider,
         contracts: &mut Contracts,
         is_mock_v2: bool,
@@ -0,0 +1,30 @@
+-- One row for every block that the service has processed.
+CREATE TABLE block (
+    height    BIGINT PRIMARY KEY,
+    epoch     BIGINT NOT NULL,
+    -- Unix timestamp of the block, in seconds.
+    timestamp BIGINT NOT NULL,
+    -- JSON encoded fee accounts of the block's proposer.
+    proposer  TEXT   NOT NULL
+);
+CREATE INDEX block_epoch ON block (epoch);
+
+-- Whether each member of the stake table signed the QC that decided a block.
+CREATE TABLE vote (
+    height    BIGINT  NOT NULL REFERENCES block (height) ON DELETE CASCADE,
+    validator TEXT    NOT NULL,
+    voted     BOOLEAN NOT NULL,
+    PRIMARY KEY (validator, height)
+);
+CREATE INDEX vote_height ON vote (height);
+
+-- The number of blocks each member of an epoch's stake table proposed, as
+-- reported by the final block of that epoch.
+CREATE TABLE epoch_leader (
+    epoch     BIGINT  NOT NULL,
+    -- Height of the final block of the epoch.
+    height    BIGINT  NOT NULL,
+    validator TEXT    NOT NULL,
+    proposals INTEGER NOT NULL,
+    PRIMARY KEY (validator, epoch)
+);
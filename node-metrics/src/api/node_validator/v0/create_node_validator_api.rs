@@ -19,6 +19,7 @@ use crate::{
         get_node_stake_table_from_sequencer, get_node_validators_from_sequencer,
     },
     service::{
+        alerting::{AlertingConfig, AlertingTask, Notifier, RulesEngine},
         client_message::InternalClientMessage,
         client_state::{
             ClientThreadState, InternalClientMessageProcessingTask,
@@ -26,7 +27,11 @@ use crate::{
             ProcessDistributeStakeTableHandlingTask, ProcessDistributeValidatorHandlingTask,
             ProcessDistributeVotersHandlingTask,
         },
-        data_state::{DataState, ProcessLeafAndBlockPairStreamTask, ProcessNodeIdentityStreamTask},
+        data_state::{
+            DataState, MAX_VOTERS_HISTORY, ProcessLeafAndBlockPairStreamTask,
+            ProcessNodeIdentityStreamTask,
+        },
+        history::HistoryStore,
         server_message::ServerMessage,
     },
 };
@@ -42,6 +47,7 @@ pub struct NodeValidatorAPI<K> {
     pub process_node_identity_stream_handle: Option<ProcessNodeIdentityStreamTask>,
     pub process_url_stream_handle: Option<ProcessNodeIdentityUrlStreamTask>,
     pub submit_public_urls_handle: Option<SubmitPublicUrlsToScrapeTask>,
+    pub process_alerting_handle: Option<AlertingTask>,
     pub url_sender: K,
 }
 
@@ -49,6 +55,8 @@ pub struct NodeValidatorConfig {
     pub stake_table_url_base: Url,
     pub initial_node_public_base_urls: Vec<Url>,
     pub starting_block_height: u64,
+    pub alerting: Option<AlertingConfig>,
+    pub history: Option<HistoryStore>,
 }
 
 #[derive(Debug)]
@@ -160,12 +168,34 @@ pub async fn create_node_validator_processing(
         );
     }
 
-    let data_state = DataState::new(
-        Default::default(),
+    // Restore the voters of the blocks preceding our starting block, so
+    // that the voters snapshot survives a restart of the service.
+    let latest_voters = match &config.history {
+        Some(history) => {
+            let keys: Vec<_> = stake_table
+                .iter()
+                .map(|peer| peer.stake_table_entry.stake_key)
+                .collect();
+            history
+                .latest_voters(config.starting_block_height, MAX_VOTERS_HISTORY, &keys)
+                .await
+                .unwrap_or_else(|err| {
+                    tracing::error!("failed to restore voters from history: {err}");
+                    Vec::new()
+                })
+        },
+        None => Vec::new(),
+    };
+
+    let mut data_state = DataState::new(
         Default::default(),
+        latest_voters.into_iter().collect(),
         stake_table,
         validator_map,
     );
+    if let Some(history) = config.history {
+        data_state.set_history(history);
+    }
 
     let data_state = Arc::new(RwLock::new(data_state));
     let client_thread_state = Arc::new(RwLock::new(client_thread_state));
@@ -193,8 +223,11 @@ pub async fn create_node_validator_processing(
         node_identity_receiver_2,
     );
 
-    let process_distribute_voters_handle =
-        ProcessDistributeVotersHandlingTask::new(client_thread_state.clone(), voters_receiver);
+    let process_distribute_voters_handle = ProcessDistributeVotersHandlingTask::new(
+        client_thread_state.clone(),
+        data_state.clone(),
+        voters_receiver,
+    );
 
     let process_distribute_stake_table_handle = ProcessDistributeStakeTableHandlingTask::new(
         client_thread_state.clone(),
@@ -235,6 +268,18 @@ pub async fn create_node_validator_processing(
         config.initial_node_public_base_urls.clone(),
     );
 
+    // Evaluate the alerting rules, if any were configured.
+    let process_alerting_handle = config.alerting.map(|alerting| {
+        tracing::info!("evaluating {} alerting rules", alerting.rules.len());
+        let evaluation_interval = alerting.evaluation_interval();
+        AlertingTask::new(
+            data_state.clone(),
+            RulesEngine::new(alerting.rules),
+            Notifier::new(alerting.notifier),
+            evaluation_interval,
+        )
+    });
+
     Ok(NodeValidatorAPI {
         process_internal_client_message_handle: Some(process_internal_client_message_handle),
         process_distribute_block_detail_handle: Some(process_distribute_block_detail_handle),
@@ -246,6 +291,7 @@ pub async fn create_node_validator_processing(
         process_node_identity_stream_handle: Some(process_node_identity_stream_handle),
         process_url_stream_handle: Some(process_url_stream_handle),
         submit_public_urls_handle: Some(submit_public_urls_handle),
+        process_alerting_handle,
         url_sender,
     })
 }
@@ -281,6 +327,8 @@ mod test {
                     .unwrap(),
             ],
             port: 9000,
+            alert_rules: None,
+            history_db: None,
         })
         .await;
     }
@@ -54,6 +54,12 @@
 //!      - Block Time
 //!      - Block Space Used
 //!
+//! All of the above is kept in memory.  Optionally, the voter participation
+//! and proposer of every block can additionally be recorded to a SQLite
+//! database (see `--history-db`), which allows clients to request the
+//! participation of validators over ranges that are well beyond the
+//! in-memory window.
+//!
 //! ## Data Streams
 //!
 //! In order for clients to be able to receive the information from the node
@@ -99,6 +105,8 @@
 pub mod api;
 pub mod service;
 
+use std::path::{Path, PathBuf};
+
 use api::node_validator::v0::AvailabilityAPIStream;
 use axum::Router;
 use clap::Parser;
@@ -107,7 +115,7 @@ use futures::{
     channel::mpsc::{self, Sender},
 };
 use http_wire::cors_layer;
-use service::data_state::MAX_VOTERS_HISTORY;
+use service::{alerting::AlertingConfig, data_state::MAX_VOTERS_HISTORY, history::HistoryStore};
 use tokio::{net::TcpListener, spawn};
 use url::Url;
 
@@ -173,6 +181,20 @@ pub struct Options {
         default_value = "9000"
     )]
     port: u16,
+
+    /// alert_rules is the path to a TOML file of alerting rules to evaluate
+    /// against the state of the network.  See [service::alerting] for the
+    /// format of this file.  If not provided, no alerts are raised.
+    #[clap(long, env = "ESPRESSO_NODE_VALIDATOR_ALERT_RULES")]
+    alert_rules: Option<PathBuf>,
+
+    /// history_db is the path to a SQLite database in which the voter
+    /// participation and proposer of every block is recorded.  This allows
+    /// clients to request the participation of validators over arbitrary
+    /// height or epoch ranges.  The database is created if it does not exist.
+    /// If not provided, no history is kept beyond the in-memory window.
+    #[clap(long, env = "ESPRESSO_NODE_VALIDATOR_HISTORY_DB")]
+    history_db: Option<PathBuf>,
 }
 
 impl Options {
@@ -191,6 +213,14 @@ impl Options {
     fn port(&self) -> u16 {
         self.port
     }
+
+    fn alert_rules(&self) -> Option<&Path> {
+        self.alert_rules.as_deref()
+    }
+
+    fn history_db(&self) -> Option<&Path> {
+        self.history_db.as_deref()
+    }
 }
 
 /// MainState represents the State of the application, shared with every axum handler.
@@ -234,6 +264,21 @@ pub async fn run_standalone_service(options: Options) {
 
     let app = app(state);
 
+    let alerting = options
+        .alert_rules()
+        .map(|path| match AlertingConfig::load(path) {
+            Ok(alerting) => alerting,
+            Err(err) => panic!("unable to load alert rules from {}: {err}", path.display()),
+        });
+
+    let history = match options.history_db() {
+        Some(path) => match HistoryStore::connect(path).await {
+            Ok(history) => Some(history),
+            Err(err) => panic!("unable to open history database {}: {err}", path.display()),
+        },
+        None => None,
+    };
+
     let (leaf_and_block_pair_sender, leaf_and_block_pair_receiver) = mpsc::channel(10);
 
     let client = http_client::Client::new(options.leaf_stream_base_url().clone());
@@ -285,6 +330,8 @@ pub async fn run_standalone_service(options: Options) {
             stake_table_url_base: options.stake_table_source_base_url().clone(),
             initial_node_public_base_urls: options.initial_node_public_base_urls().to_vec(),
             starting_block_height: block_height,
+            alerting,
+            history,
         },
         internal_client_message_receiver,
         leaf_and_block_pair_receiver,
@@ -0,0 +1,722 @@
+//! # Alerting
+//!
+//! The alerting subsystem watches the [DataState] on behalf of validator
+//! operators, so that problems are noticed even when nobody has a dashboard
+//! open.  A set of [Rule]s is loaded from a TOML file, and every time a new
+//! block arrives the [RulesEngine] evaluates them per validator.  Whenever a
+//! condition starts or stops holding for a validator, an [Alert] is emitted
+//! to the configured [Notifier].
+//!
+//! An example rules file:
+//!
+//! ```toml
+//! evaluation_interval_secs = 10
+//!
+//! [notifier]
+//! kind = "alertmanager"
+//! url = "http://alertmanager:9093/"
+//!
+//! [[rule]]
+//! name = "missed-votes"
+//! severity = "critical"
+//! kind = "missed_votes"
+//! consecutive = 5
+//! # Optional: only evaluate the rule for these validators.
+//! validators = ["BLS_VER_KEY~..."]
+//!
+//! [[rule]]
+//! name = "no-proposals"
+//! kind = "no_proposals"
+//!
+//! [[rule]]
+//! name = "stake-drop"
+//! kind = "stake_drop"
+//! percent = 10.0
+//!
+//! [[rule]]
+//! name = "left-stake-table"
+//! kind = "identity_disappeared"
+//! ```
+//!
+//! The [RulesEngine] only looks at the [DataState] it is handed, so rules can
+//! be exercised against recorded [DataState] history without a running
+//! network.
+
+pub mod notifier;
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+
+use alloy::primitives::U256;
+use async_lock::RwLock;
+use hotshot_query_service::explorer::Timestamp;
+use hotshot_types::signature_key::BLSPubKey;
+pub use notifier::Notifier;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tokio::{spawn, task::JoinHandle};
+
+use crate::service::data_state::DataState;
+
+/// [Severity] is attached to every [Alert] raised by a [Rule].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    #[default]
+    Warning,
+    Critical,
+}
+
+/// [Condition] is the check that a [Rule] performs for each validator.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Condition {
+    /// The validator is missing from the voters of at least `consecutive`
+    /// of the most recent quorum certificates.
+    MissedVotes { consecutive: usize },
+
+    /// The validator did not propose a single block in the most recently
+    /// completed epoch.
+    NoProposals,
+
+    /// The validator's stake has dropped by at least `percent` percent from
+    /// the highest stake observed for it.
+    StakeDrop { percent: f64 },
+
+    /// The validator was in the stake table, but is no longer.
+    IdentityDisappeared,
+}
+
+/// [Rule] is a single named alerting rule.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Rule {
+    pub name: String,
+    #[serde(default)]
+    pub severity: Severity,
+    /// The validators this rule applies to.  An empty list applies the rule
+    /// to every validator.
+    #[serde(default)]
+    pub validators: Vec<BLSPubKey>,
+    #[serde(flatten)]
+    pub condition: Condition,
+}
+
+impl Rule {
+    fn applies_to(&self, key: &BLSPubKey) -> bool {
+        self.validators.is_empty() || self.validators.contains(key)
+    }
+}
+
+/// [AlertingConfig] is the contents of an alerting rules file.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AlertingConfig {
+    /// How often to check for a new block to evaluate the rules against.
+    #[serde(default = "default_evaluation_interval_secs")]
+    pub evaluation_interval_secs: u64,
+    pub notifier: notifier::NotifierConfig,
+    #[serde(default, rename = "rule")]
+    pub rules: Vec<Rule>,
+}
+
+fn default_evaluation_interval_secs() -> u64 {
+    10
+}
+
+/// [LoadAlertingConfigError] represents the errors that can occur when
+/// loading an [AlertingConfig] from a file.
+#[derive(Debug)]
+pub enum LoadAlertingConfigError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+}
+
+impl std::fmt::Display for LoadAlertingConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadAlertingConfigError::Io(err) => write!(f, "error reading alerting rules: {err}"),
+            LoadAlertingConfigError::Parse(err) => {
+                write!(f, "error parsing alerting rules: {err}")
+            },
+        }
+    }
+}
+
+impl std::error::Error for LoadAlertingConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadAlertingConfigError::Io(err) => Some(err),
+            LoadAlertingConfigError::Parse(err) => Some(err),
+        }
+    }
+}
+
+impl AlertingConfig {
+    pub fn load(path: &Path) -> Result<Self, LoadAlertingConfigError> {
+        let contents = std::fs::read_to_string(path).map_err(LoadAlertingConfigError::Io)?;
+        toml::from_str(&contents).map_err(LoadAlertingConfigError::Parse)
+    }
+
+    pub fn evaluation_interval(&self) -> Duration {
+        Duration::from_secs(self.evaluation_interval_secs)
+    }
+}
+
+/// [AlertStatus] indicates whether an [Alert] has started or stopped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertStatus {
+    Firing,
+    Resolved,
+}
+
+/// [Alert] is a notification that the condition of a [Rule] started, or
+/// stopped, holding for a validator.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Alert {
+    pub rule: String,
+    pub severity: Severity,
+    pub validator: BLSPubKey,
+    pub summary: String,
+    pub status: AlertStatus,
+    pub starts_at: Timestamp,
+    pub ends_at: Option<Timestamp>,
+}
+
+/// [RulesEngine] evaluates a set of [Rule]s against successive [DataState]s,
+/// keeping track of which alerts are currently firing.
+pub struct RulesEngine {
+    rules: Vec<Rule>,
+    firing: HashMap<(usize, BLSPubKey), Alert>,
+    highest_stake: HashMap<BLSPubKey, U256>,
+    seen_stake_table: HashSet<BLSPubKey>,
+}
+
+impl RulesEngine {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Self {
+            rules,
+            firing: Default::default(),
+            highest_stake: Default::default(),
+            seen_stake_table: Default::default(),
+        }
+    }
+
+    /// The alerts that are currently firing.
+    pub fn firing(&self) -> impl Iterator<Item = &Alert> {
+        self.firing.values()
+    }
+
+    /// [evaluate] checks every rule against the given [DataState], and
+    /// returns the alerts that started or stopped firing since the previous
+    /// evaluation.
+    ///
+    /// Alerts are timestamped with the time of the latest block in the
+    /// [DataState], so evaluating recorded history is deterministic.
+    pub fn evaluate(&mut self, data_state: &DataState) -> Vec<Alert> {
+        let now = data_state
+            .latest_blocks()
+            .last()
+            .map(|block| block.time)
+            .unwrap_or(Timestamp(OffsetDateTime::UNIX_EPOCH));
+
+        let stake_table: HashSet<BLSPubKey> = data_state
+            .stake_table()
+            .map(|config| config.stake_table_entry.stake_key)
+            .collect();
+        self.seen_stake_table.extend(stake_table.iter().copied());
+
+        let stakes: Vec<(BLSPubKey, U256)> = data_state
+            .validators()
+            .map(|validator| (*validator.stake_table_key(), validator.stake))
+            .collect();
+        for (key, stake) in &stakes {
+            let highest = self.highest_stake.entry(*key).or_default();
+            *highest = (*highest).max(*stake);
+        }
+
+        let mut holding = HashMap::new();
+        for (index, rule) in self.rules.iter().enumerate() {
+            let summaries = match &rule.condition {
+                Condition::MissedVotes { consecutive } => {
+                    missed_votes(data_state, &stake_table, *consecutive)
+                },
+                Condition::NoProposals => no_proposals(data_state),
+                Condition::StakeDrop { percent } => {
+                    stake_drops(&stakes, &self.highest_stake, *percent)
+                },
+                Condition::IdentityDisappeared => self
+                    .seen_stake_table
+                    .difference(&stake_table)
+                    .map(|key| (*key, "no longer in the stake table".to_string()))
+                    .collect(),
+            };
+
+            for (key, summary) in summaries {
+                if rule.applies_to(&key) {
+                    holding.insert((index, key), summary);
+                }
+            }
+        }
+
+        let mut alerts = Vec::new();
+
+        // Resolve alerts whose condition no longer holds.
+        let resolved: Vec<_> = self
+            .firing
+            .keys()
+            .filter(|id| !holding.contains_key(id))
+            .copied()
+            .collect();
+        for id in resolved {
+            if let Some(mut alert) = self.firing.remove(&id) {
+                alert.status = AlertStatus::Resolved;
+                alert.ends_at = Some(now);
+                alerts.push(alert);
+            }
+        }
+
+        // Raise alerts whose condition started holding.
+        for ((index, key), summary) in holding {
+            if let Some(alert) = self.firing.get_mut(&(index, key)) {
+                // Keep the summary current, without notifying again.
+                alert.summary = summary;
+                continue;
+            }
+
+            let rule = &self.rules[index];
+            let alert = Alert {
+                rule: rule.name.clone(),
+                severity: rule.severity,
+                validator: key,
+                summary,
+                status: AlertStatus::Firing,
+                starts_at: now,
+                ends_at: None,
+            };
+            self.firing.insert((index, key), alert.clone());
+            alerts.push(alert);
+        }
+
+        alerts
+    }
+}
+
+// [missed_votes] returns the stake table members that are absent from at
+// least [consecutive] of the most recent voter sets.
+//
+// The voter [BitVec]s are indexed by the node identity list at the time they
+// were recorded, which only ever grows, so a bit that is missing from an
+// older [BitVec] means we know nothing about that node for that block.
+fn missed_votes(
+    data_state: &DataState,
+    stake_table: &HashSet<BLSPubKey>,
+    consecutive: usize,
+) -> Vec<(BLSPubKey, String)> {
+    if consecutive == 0 {
+        return Vec::new();
+    }
+
+    let voters: Vec<_> = data_state.latest_voters().collect();
+    data_state
+        .node_identity()
+        .enumerate()
+        .filter(|(_, identity)| stake_table.contains(identity.public_key()))
+        .filter_map(|(index, identity)| {
+            let missed = voters
+                .iter()
+                .rev()
+                .take_while(|voters| voters.get(index).is_some_and(|voted| !*voted))
+                .count();
+            (missed >= consecutive).then(|| {
+                (
+                    *identity.public_key(),
+                    format!("missed the last {missed} votes"),
+                )
+            })
+        })
+        .collect()
+}
+
+// [no_proposals] returns the members of the most recently completed epoch's
+// stake table that did not propose a block during that epoch.
+fn no_proposals(data_state: &DataState) -> Vec<(BLSPubKey, String)> {
+    let Some(completed_epoch) = data_state.completed_epoch() else {
+        return Vec::new();
+    };
+
+    completed_epoch
+        .leaders
+        .iter()
+        .filter(|(_, count)| *count == 0)
+        .map(|(key, _)| {
+            (
+                *key,
+                format!("proposed no blocks in epoch {}", completed_epoch.epoch),
+            )
+        })
+        .collect()
+}
+
+// [stake_drops] returns the validators whose current stake is at least
+// [percent] percent lower than the highest stake observed for them.
+fn stake_drops(
+    stakes: &[(BLSPubKey, U256)],
+    highest_stake: &HashMap<BLSPubKey, U256>,
+    percent: f64,
+) -> Vec<(BLSPubKey, String)> {
+    // Work in basis points to stay within integer arithmetic.
+    let basis_points = U256::from((percent.clamp(0.0, 100.0) * 100.0).round() as u64);
+    stakes
+        .iter()
+        .filter_map(|(key, stake)| {
+            let highest = *highest_stake.get(key)?;
+            let drop = highest.saturating_sub(*stake);
+            (!highest.is_zero()
+                && !drop.is_zero()
+                && drop * U256::from(10_000) >= highest * basis_points)
+                .then(|| (*key, format!("stake dropped from {highest} to {stake}")))
+        })
+        .collect()
+}
+
+/// [AlertingTask] represents the task that periodically evaluates the
+/// alerting rules against the [DataState], and delivers the resulting alerts
+/// to a [Notifier].
+pub struct AlertingTask {
+    pub task_handle: Option<JoinHandle<()>>,
+}
+
+impl AlertingTask {
+    /// [new] creates a new [AlertingTask] that will evaluate the given
+    /// [RulesEngine] whenever a new block is added to the [DataState].
+    ///
+    /// Calling this function will create an asynchronous task that will start
+    /// processing immediately. The handle for the task will be stored within
+    /// the returned structure.
+    pub fn new(
+        data_state: Arc<RwLock<DataState>>,
+        engine: RulesEngine,
+        notifier: Notifier,
+        evaluation_interval: Duration,
+    ) -> Self {
+        let task_handle = spawn(Self::process_alerts(
+            data_state,
+            engine,
+            notifier,
+            evaluation_interval,
+        ));
+
+        Self {
+            task_handle: Some(task_handle),
+        }
+    }
+
+    async fn process_alerts(
+        data_state: Arc<RwLock<DataState>>,
+        mut engine: RulesEngine,
+        notifier: Notifier,
+        evaluation_interval: Duration,
+    ) {
+        let mut last_evaluated_height = None;
+        loop {
+            tokio::time::sleep(evaluation_interval).await;
+
+            let alerts = {
+                let data_state_read_lock_guard = data_state.read().await;
+                let height = data_state_read_lock_guard
+                    .latest_blocks()
+                    .last()
+                    .map(|block| block.height);
+                if height.is_none() || height == last_evaluated_height {
+                    continue;
+                }
+                last_evaluated_height = height;
+                engine.evaluate(&data_state_read_lock_guard)
+            };
+
+            for alert in &alerts {
+                tracing::info!(
+                    "alert {:?}: {} for {}: {}",
+                    alert.status,
+                    alert.rule,
+                    alert.validator,
+                    alert.summary
+                );
+            }
+
+            if let Err(err) = notifier.notify(&alerts, engine.firing()).await {
+                tracing::error!("alerting: failed to deliver alerts: {err}");
+            }
+        }
+    }
+}
+
+/// [Drop] implementation for [AlertingTask] that will cancel the task if it
+/// is dropped.
+impl Drop for AlertingTask {
+    fn drop(&mut self) {
+        let task_handle = self.task_handle.take();
+        if let Some(task_handle) = task_handle {
+            task_handle.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitvec::vec::BitVec;
+    use espresso_types::{
+        SeqTypes,
+        v0_3::{AuthenticatedValidator, RegisteredValidator},
+    };
+    use hotshot_types::{PeerConfig, traits::signature_key::SignatureKey};
+    use indexmap::IndexMap;
+
+    use super::*;
+    use crate::service::data_state::EpochLeaders;
+
+    fn key(index: u64) -> BLSPubKey {
+        BLSPubKey::generated_from_seed_indexed([0; 32], index).0
+    }
+
+    fn stake_table(keys: &[BLSPubKey]) -> Vec<PeerConfig<SeqTypes>> {
+        keys.iter()
+            .map(|key| {
+                let mut config = PeerConfig::<SeqTypes>::test_default();
+                config.stake_table_entry = key.stake_table_entry(U256::from(1));
+                config
+            })
+            .collect()
+    }
+
+    fn validator(key: BLSPubKey, stake: u64) -> AuthenticatedValidator<BLSPubKey> {
+        let mut validator = RegisteredValidator::mock();
+        validator.stake_table_key = Some(key);
+        validator.stake = U256::from(stake);
+        validator.try_into().unwrap()
+    }
+
+    fn voters(bits: &[bool]) -> BitVec<u16> {
+        bits.iter().copied().collect()
+    }
+
+    fn rule(name: &str, condition: Condition) -> Rule {
+        Rule {
+            name: name.to_string(),
+            severity: Severity::Warning,
+            validators: Vec::new(),
+            condition,
+        }
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let config: AlertingConfig = toml::from_str(&format!(
+            r#"
+            [notifier]
+            kind = "webhook"
+            url = "http://localhost:8080/alerts"
+
+            [[rule]]
+            name = "missed-votes"
+            severity = "critical"
+            kind = "missed_votes"
+            consecutive = 5
+            validators = ["{}"]
+
+            [[rule]]
+            name = "no-proposals"
+            kind = "no_proposals"
+
+            [[rule]]
+            name = "stake-drop"
+            kind = "stake_drop"
+            percent = 12.5
+
+            [[rule]]
+            name = "left"
+            kind = "identity_disappeared"
+            "#,
+            key(0)
+        ))
+        .unwrap();
+
+        assert_eq!(config.evaluation_interval(), Duration::from_secs(10));
+        assert_eq!(config.rules.len(), 4);
+        assert_eq!(config.rules[0].severity, Severity::Critical);
+        assert_eq!(config.rules[0].validators, vec![key(0)]);
+        assert_eq!(
+            config.rules[0].condition,
+            Condition::MissedVotes { consecutive: 5 }
+        );
+        assert_eq!(config.rules[1].severity, Severity::Warning);
+        assert_eq!(config.rules[1].condition, Condition::NoProposals);
+        assert_eq!(
+            config.rules[2].condition,
+            Condition::StakeDrop { percent: 12.5 }
+        );
+        assert_eq!(config.rules[3].condition, Condition::IdentityDisappeared);
+    }
+
+    #[test]
+    fn test_missed_votes_fire_and_resolve() {
+        let keys = [key(0), key(1), key(2)];
+        let mut data_state = DataState::new(
+            Default::default(),
+            Default::default(),
+            stake_table(&keys),
+            IndexMap::new(),
+        );
+        let mut engine = RulesEngine::new(vec![rule(
+            "missed-votes",
+            Condition::MissedVotes { consecutive: 3 },
+        )]);
+
+        // Node 1 misses two votes, not enough to alert.
+        data_state.add_latest_voters(voters(&[true, false, true]));
+        data_state.add_latest_voters(voters(&[true, false, true]));
+        assert!(engine.evaluate(&data_state).is_empty());
+
+        // The third miss fires the alert.
+        data_state.add_latest_voters(voters(&[true, false, true]));
+        let alerts = engine.evaluate(&data_state);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].validator, keys[1]);
+        assert_eq!(alerts[0].status, AlertStatus::Firing);
+
+        // Further misses do not notify again.
+        data_state.add_latest_voters(voters(&[true, false, true]));
+        assert!(engine.evaluate(&data_state).is_empty());
+        assert_eq!(engine.firing().count(), 1);
+
+        // Voting again resolves it.
+        data_state.add_latest_voters(voters(&[true, true, true]));
+        let alerts = engine.evaluate(&data_state);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].validator, keys[1]);
+        assert_eq!(alerts[0].status, AlertStatus::Resolved);
+        assert!(alerts[0].ends_at.is_some());
+        assert_eq!(engine.firing().count(), 0);
+    }
+
+    #[test]
+    fn test_rule_validator_filter() {
+        let keys = [key(0), key(1)];
+        let mut data_state = DataState::new(
+            Default::default(),
+            Default::default(),
+            stake_table(&keys),
+            IndexMap::new(),
+        );
+        let mut missed = rule("missed-votes", Condition::MissedVotes { consecutive: 1 });
+        missed.validators = vec![keys[0]];
+        let mut engine = RulesEngine::new(vec![missed]);
+
+        data_state.add_latest_voters(voters(&[false, false]));
+        let alerts = engine.evaluate(&data_state);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].validator, keys[0]);
+    }
+
+    #[test]
+    fn test_no_proposals() {
+        let keys = [key(0), key(1)];
+        let mut data_state = DataState::new(
+            Default::default(),
+            Default::default(),
+            stake_table(&keys),
+            IndexMap::new(),
+        );
+        let mut engine = RulesEngine::new(vec![rule("no-proposals", Condition::NoProposals)]);
+
+        // Nothing is known until an epoch completes.
+        assert!(engine.evaluate(&data_state).is_empty());
+
+        data_state.set_completed_epoch(EpochLeaders {
+            epoch: 3,
+            leaders: vec![(keys[0], 5), (keys[1], 0)],
+        });
+        let alerts = engine.evaluate(&data_state);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].validator, keys[1]);
+        assert_eq!(alerts[0].summary, "proposed no blocks in epoch 3");
+
+        data_state.set_completed_epoch(EpochLeaders {
+            epoch: 4,
+            leaders: vec![(keys[0], 5), (keys[1], 1)],
+        });
+        let alerts = engine.evaluate(&data_state);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].status, AlertStatus::Resolved);
+    }
+
+    #[test]
+    fn test_stake_drop() {
+        let keys = [key(0), key(1)];
+        let mut data_state = DataState::new(
+            Default::default(),
+            Default::default(),
+            stake_table(&keys),
+            IndexMap::new(),
+        );
+        let mut engine = RulesEngine::new(vec![rule(
+            "stake-drop",
+            Condition::StakeDrop { percent: 10.0 },
+        )]);
+
+        let mut validators = IndexMap::new();
+        validators.insert(
+            alloy::primitives::Address::with_last_byte(0),
+            validator(keys[0], 1000),
+        );
+        validators.insert(
+            alloy::primitives::Address::with_last_byte(1),
+            validator(keys[1], 1000),
+        );
+        data_state.report_validator_map(validators.clone());
+        assert!(engine.evaluate(&data_state).is_empty());
+
+        // A 5% drop is tolerated, a 10% drop is not.
+        validators.insert(
+            alloy::primitives::Address::with_last_byte(0),
+            validator(keys[0], 950),
+        );
+        validators.insert(
+            alloy::primitives::Address::with_last_byte(1),
+            validator(keys[1], 900),
+        );
+        data_state.report_validator_map(validators.clone());
+        let alerts = engine.evaluate(&data_state);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].validator, keys[1]);
+        assert_eq!(alerts[0].summary, "stake dropped from 1000 to 900");
+    }
+
+    #[test]
+    fn test_identity_disappeared() {
+        let keys = [key(0), key(1)];
+        let mut data_state = DataState::new(
+            Default::default(),
+            Default::default(),
+            stake_table(&keys),
+            IndexMap::new(),
+        );
+        let mut engine = RulesEngine::new(vec![rule("left", Condition::IdentityDisappeared)]);
+        assert!(engine.evaluate(&data_state).is_empty());
+
+        data_state.replace_stake_table(stake_table(&keys[..1]));
+        let alerts = engine.evaluate(&data_state);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].validator, keys[1]);
+
+        data_state.replace_stake_table(stake_table(&keys));
+        let alerts = engine.evaluate(&data_state);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].status, AlertStatus::Resolved);
+    }
+}
@@ -0,0 +1,174 @@
+use std::collections::BTreeMap;
+
+use hotshot_query_service::explorer::Timestamp;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::{Alert, AlertStatus};
+
+/// [NotifierConfig] selects where alerts are delivered.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum NotifierConfig {
+    /// POST every batch of alert changes as JSON to `url`.
+    Webhook { url: Url },
+
+    /// POST alerts to the Alertmanager API rooted at `url`.
+    Alertmanager { url: Url },
+}
+
+/// [Notifier] delivers [Alert]s to a webhook or an Alertmanager-compatible
+/// endpoint.
+#[derive(Clone, Debug)]
+pub struct Notifier {
+    client: reqwest::Client,
+    config: NotifierConfig,
+}
+
+/// [WebhookPayload] is the body POSTed to a webhook.
+#[derive(Debug, Serialize)]
+struct WebhookPayload<'a> {
+    alerts: &'a [Alert],
+}
+
+/// [AlertmanagerAlert] is a single alert in the format expected by the
+/// Alertmanager `/api/v2/alerts` endpoint.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AlertmanagerAlert {
+    labels: BTreeMap<&'static str, String>,
+    annotations: BTreeMap<&'static str, String>,
+    starts_at: Timestamp,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ends_at: Option<Timestamp>,
+}
+
+impl From<&Alert> for AlertmanagerAlert {
+    fn from(alert: &Alert) -> Self {
+        let severity = serde_json::to_value(alert.severity)
+            .ok()
+            .and_then(|value| value.as_str().map(ToString::to_string))
+            .unwrap_or_default();
+        Self {
+            labels: BTreeMap::from([
+                ("alertname", alert.rule.clone()),
+                ("severity", severity),
+                ("validator", alert.validator.to_string()),
+            ]),
+            annotations: BTreeMap::from([("summary", alert.summary.clone())]),
+            starts_at: alert.starts_at,
+            ends_at: alert.ends_at,
+        }
+    }
+}
+
+impl Notifier {
+    pub fn new(config: NotifierConfig) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            config,
+        }
+    }
+
+    /// [notify] delivers the alerts that changed in the latest evaluation.
+    ///
+    /// Alertmanager resolves alerts that have not been re-sent for a while,
+    /// so it is sent every alert that is still [firing] as well, while a
+    /// webhook only receives the changes.
+    ///
+    /// [firing]: super::RulesEngine::firing
+    pub async fn notify<'a>(
+        &self,
+        changes: &[Alert],
+        firing: impl Iterator<Item = &'a Alert>,
+    ) -> Result<(), reqwest::Error> {
+        match &self.config {
+            NotifierConfig::Webhook { url } => {
+                if changes.is_empty() {
+                    return Ok(());
+                }
+                self.client
+                    .post(url.clone())
+                    .json(&WebhookPayload { alerts: changes })
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            },
+            NotifierConfig::Alertmanager { url } => {
+                let alerts = alertmanager_alerts(changes, firing);
+                if alerts.is_empty() {
+                    return Ok(());
+                }
+                let url = url.join("api/v2/alerts").unwrap_or_else(|_| url.clone());
+                self.client
+                    .post(url)
+                    .json(&alerts)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            },
+        }
+        Ok(())
+    }
+}
+
+// [alertmanager_alerts] combines the resolved alerts from [changes] with the
+// alerts that are still firing.
+fn alertmanager_alerts<'a>(
+    changes: &[Alert],
+    firing: impl Iterator<Item = &'a Alert>,
+) -> Vec<AlertmanagerAlert> {
+    changes
+        .iter()
+        .filter(|alert| alert.status == AlertStatus::Resolved)
+        .chain(firing)
+        .map(AlertmanagerAlert::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use hotshot_types::signature_key::BLSPubKey;
+    use time::OffsetDateTime;
+
+    use super::*;
+    use crate::service::alerting::Severity;
+
+    fn alert(status: AlertStatus) -> Alert {
+        Alert {
+            rule: "missed-votes".to_string(),
+            severity: Severity::Critical,
+            validator: BLSPubKey::generated_from_seed_indexed([0; 32], 0).0,
+            summary: "missed the last 5 votes".to_string(),
+            status,
+            starts_at: Timestamp(OffsetDateTime::UNIX_EPOCH),
+            ends_at: (status == AlertStatus::Resolved).then_some(Timestamp(
+                OffsetDateTime::UNIX_EPOCH + time::Duration::seconds(60),
+            )),
+        }
+    }
+
+    #[test]
+    fn test_alertmanager_format() {
+        let resolved = alert(AlertStatus::Resolved);
+        let firing = alert(AlertStatus::Firing);
+        let alerts = alertmanager_alerts(
+            &[resolved.clone(), firing.clone()],
+            std::iter::once(&firing),
+        );
+        // The firing alert is only sent once.
+        assert_eq!(alerts.len(), 2);
+
+        let json = serde_json::to_value(&alerts).unwrap();
+        assert_eq!(json[0]["labels"]["alertname"], "missed-votes");
+        assert_eq!(json[0]["labels"]["severity"], "critical");
+        assert_eq!(
+            json[0]["labels"]["validator"],
+            resolved.validator.to_string()
+        );
+        assert_eq!(json[0]["annotations"]["summary"], "missed the last 5 votes");
+        assert_eq!(json[0]["startsAt"], "1970-01-01T00:00:00Z");
+        assert_eq!(json[0]["endsAt"], "1970-01-01T00:01:00Z");
+        assert!(json[1].get("endsAt").is_none());
+    }
+}
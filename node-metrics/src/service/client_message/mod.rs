@@ -1,6 +1,8 @@
+use alloy::primitives::Address;
+use hotshot_types::signature_key::BLSPubKey;
 use serde::{Deserialize, Serialize};
 
-use super::client_id::ClientId;
+use super::{client_id::ClientId, history::ParticipationRequest};
 
 /// [ClientMessage] represents the messages that the client can send to the
 /// server for a request.
@@ -24,6 +26,21 @@ pub enum ClientMessage {
     RequestValidatorsSnapshot,
     RequestStakeTableSnapshot,
 
+    /// Requests the participation of the given validators over a range of
+    /// heights or epochs.  This is answered from the history database, and
+    /// is only available when the service has been configured with one.
+    RequestValidatorParticipation(ParticipationRequest),
+
+    /// Subscribes to the voters stream, but only for the validators matched
+    /// by the [SubscriptionFilter].  This replaces any previous voters
+    /// subscription of the client.
+    SubscribeFilteredVoters(SubscriptionFilter),
+
+    /// Subscribes to the validators stream, but only for the validators
+    /// matched by the [SubscriptionFilter].  This replaces any previous
+    /// validators subscription of the client.
+    SubscribeFilteredValidators(SubscriptionFilter),
+
     /// This allows the use-case of a user sending a message that is not a
     /// valid recognized request to be handled explicitly by the server,
     /// rather than by the underlying websocket handling mechanism.
@@ -34,6 +51,28 @@ pub enum ClientMessage {
     UnrecognizedCommand(serde_json::Value),
 }
 
+/// [SubscriptionFilter] restricts a subscription to a set of validators.
+/// A validator is matched if either its BLS key is listed in [keys], or its
+/// account address is listed in [addresses].
+///
+/// [keys]: SubscriptionFilter::keys
+/// [addresses]: SubscriptionFilter::addresses
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubscriptionFilter {
+    #[serde(default)]
+    pub keys: Vec<BLSPubKey>,
+    #[serde(default)]
+    pub addresses: Vec<Address>,
+}
+
+impl SubscriptionFilter {
+    /// [matches] returns whether the validator identified by [key], and
+    /// optionally by its [account], is matched by this filter.
+    pub fn matches(&self, key: &BLSPubKey, account: Option<&Address>) -> bool {
+        self.keys.contains(key) || account.is_some_and(|account| self.addresses.contains(account))
+    }
+}
+
 /// InternalClientMessage represents the message requests that the client can
 /// send to the server.  These messages are request that the client can send
 /// in order for the server to send back responses that correspond to the
@@ -61,7 +100,7 @@ mod tests {
     use futures::channel::mpsc::Sender;
 
     use super::{InternalClientMessage, *};
-    use crate::service::server_message::ServerMessage;
+    use crate::service::{history::ParticipationRange, server_message::ServerMessage};
 
     impl<K> PartialEq for InternalClientMessage<K> {
         fn eq(&self, other: &Self) -> bool {
@@ -79,6 +118,38 @@ mod tests {
         }
     }
 
+    fn participation_request() -> ParticipationRequest {
+        ParticipationRequest {
+            validators: vec![BLSPubKey::generated_from_seed_indexed([0; 32], 0).0],
+            range: ParticipationRange::Epochs { from: 1, to: 5 },
+        }
+    }
+
+    fn subscription_filter() -> SubscriptionFilter {
+        SubscriptionFilter {
+            keys: vec![BLSPubKey::generated_from_seed_indexed([0; 32], 1).0],
+            addresses: vec![Address::repeat_byte(1)],
+        }
+    }
+
+    #[test]
+    fn test_subscription_filter_matches() {
+        let filter = subscription_filter();
+        let key = BLSPubKey::generated_from_seed_indexed([0; 32], 1).0;
+        let other_key = BLSPubKey::generated_from_seed_indexed([0; 32], 2).0;
+
+        assert!(filter.matches(&key, None));
+        assert!(filter.matches(&other_key, Some(&Address::repeat_byte(1))));
+        assert!(!filter.matches(&other_key, Some(&Address::repeat_byte(2))));
+        assert!(!filter.matches(&other_key, None));
+
+        // Either list may be omitted.
+        let filter: SubscriptionFilter =
+            serde_json::from_str(&format!(r#"{{"keys":["{key}"]}}"#)).unwrap();
+        assert_eq!(filter.keys, vec![key]);
+        assert!(filter.addresses.is_empty());
+    }
+
     /// [test_client_message_unrecognized_message] is a test that ensures that
     /// the catch-all variants for the [ClientMessage] succeeds in receiving
     /// unrecognized messages.
@@ -112,6 +183,9 @@ mod tests {
             ClientMessage::SubscribeStakeTables,
             ClientMessage::RequestValidatorsSnapshot,
             ClientMessage::RequestStakeTableSnapshot,
+            ClientMessage::RequestValidatorParticipation(participation_request()),
+            ClientMessage::SubscribeFilteredVoters(subscription_filter()),
+            ClientMessage::SubscribeFilteredValidators(subscription_filter()),
         ];
 
         for (l, r) in zip(messages.iter(), messages.iter()) {
@@ -142,6 +216,9 @@ mod tests {
             ClientMessage::SubscribeStakeTables,
             ClientMessage::RequestValidatorsSnapshot,
             ClientMessage::RequestStakeTableSnapshot,
+            ClientMessage::RequestValidatorParticipation(participation_request()),
+            ClientMessage::SubscribeFilteredVoters(subscription_filter()),
+            ClientMessage::SubscribeFilteredValidators(subscription_filter()),
         ];
 
         for message in messages.iter() {
@@ -166,6 +243,9 @@ mod tests {
             ClientMessage::SubscribeStakeTables,
             ClientMessage::RequestValidatorsSnapshot,
             ClientMessage::RequestStakeTableSnapshot,
+            ClientMessage::RequestValidatorParticipation(participation_request()),
+            ClientMessage::SubscribeFilteredVoters(subscription_filter()),
+            ClientMessage::SubscribeFilteredValidators(subscription_filter()),
         ];
 
         for message in messages.iter() {
@@ -175,6 +255,22 @@ mod tests {
         }
     }
 
+    /// [test_client_message_participation_request_format] ensures that the
+    /// participation request is encoded in the same externally tagged form
+    /// as the other requests.
+    #[test]
+    fn test_client_message_participation_request_format() {
+        let message = ClientMessage::RequestValidatorParticipation(participation_request());
+        let json = serde_json::to_value(&message).unwrap();
+        let request = &json["RequestValidatorParticipation"];
+        assert_eq!(request["range"]["Epochs"]["from"], 1);
+        assert_eq!(request["range"]["Epochs"]["to"], 5);
+        assert_eq!(request["validators"].as_array().unwrap().len(), 1);
+
+        let deserialized: ClientMessage = serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized, message);
+    }
+
     #[test]
     fn test_client_message_to_internal_with_client_id() {
         let messages = [
@@ -189,6 +285,9 @@ mod tests {
             ClientMessage::SubscribeStakeTables,
             ClientMessage::RequestValidatorsSnapshot,
             ClientMessage::RequestStakeTableSnapshot,
+            ClientMessage::RequestValidatorParticipation(participation_request()),
+            ClientMessage::SubscribeFilteredVoters(subscription_filter()),
+            ClientMessage::SubscribeFilteredValidators(subscription_filter()),
         ];
 
         for message in messages {
@@ -14,8 +14,9 @@ use tokio::{spawn, task::JoinHandle};
 
 use super::{
     client_id::ClientId,
-    client_message::{ClientMessage, InternalClientMessage},
+    client_message::{ClientMessage, InternalClientMessage, SubscriptionFilter},
     data_state::{DataState, NodeIdentity},
+    history::ParticipationRequest,
     server_message::ServerMessage,
 };
 
@@ -53,10 +54,13 @@ pub struct ClientThreadState<K> {
     subscribed_voters: HashSet<ClientId>,
     subscribed_validators: HashSet<ClientId>,
     subscribed_stake_tables: HashSet<ClientId>,
+    subscribed_filtered_voters: HashMap<ClientId, SubscriptionFilter>,
+    subscribed_filtered_validators: HashMap<ClientId, SubscriptionFilter>,
     connection_id_counter: ClientId,
 }
 
 impl<K> ClientThreadState<K> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         clients: HashMap<ClientId, ClientState<K>>,
         subscribed_latest_block: HashSet<ClientId>,
@@ -64,6 +68,8 @@ impl<K> ClientThreadState<K> {
         subscribed_voters: HashSet<ClientId>,
         subscribed_validators: HashSet<ClientId>,
         subscribed_stake_tables: HashSet<ClientId>,
+        subscribed_filtered_voters: HashMap<ClientId, SubscriptionFilter>,
+        subscribed_filtered_validators: HashMap<ClientId, SubscriptionFilter>,
         connection_id_counter: ClientId,
     ) -> Self {
         Self {
@@ -73,6 +79,8 @@ impl<K> ClientThreadState<K> {
             subscribed_voters,
             subscribed_validators,
             subscribed_stake_tables,
+            subscribed_filtered_voters,
+            subscribed_filtered_validators,
             connection_id_counter,
         }
     }
@@ -87,6 +95,8 @@ impl<K> Default for ClientThreadState<K> {
             subscribed_voters: Default::default(),
             subscribed_validators: Default::default(),
             subscribed_stake_tables: Default::default(),
+            subscribed_filtered_voters: Default::default(),
+            subscribed_filtered_validators: Default::default(),
             connection_id_counter: ClientId::from_count(1),
         }
     }
@@ -105,6 +115,12 @@ fn drop_client_client_thread_state_write_guard<K>(
     client_thread_state_write_guard
         .subscribed_node_identity
         .remove(client_id);
+    client_thread_state_write_guard
+        .subscribed_filtered_voters
+        .remove(client_id);
+    client_thread_state_write_guard
+        .subscribed_filtered_validators
+        .remove(client_id);
 
     client
 }
@@ -228,6 +244,9 @@ pub async fn handle_client_message_subscribe_voters<K>(
 ) {
     let mut client_thread_state_write_lock_guard = client_thread_state.write().await;
 
+    client_thread_state_write_lock_guard
+        .subscribed_filtered_voters
+        .remove(&client_id);
     client_thread_state_write_lock_guard
         .subscribed_voters
         .insert(client_id);
@@ -241,11 +260,55 @@ pub async fn handle_client_message_subscribe_validators<K>(
 ) {
     let mut client_thread_state_write_lock_guard = client_thread_state.write().await;
 
+    client_thread_state_write_lock_guard
+        .subscribed_filtered_validators
+        .remove(&client_id);
     client_thread_state_write_lock_guard
         .subscribed_validators
         .insert(client_id);
 }
 
+/// [handle_client_message_subscribe_filtered_voters] is a function that
+/// processes the client message to subscribe to the voters of only the
+/// validators matched by the given [SubscriptionFilter].
+///
+/// This replaces any unfiltered voters subscription of the client, so that
+/// the client does not receive the same votes twice.
+pub async fn handle_client_message_subscribe_filtered_voters<K>(
+    client_id: ClientId,
+    filter: SubscriptionFilter,
+    client_thread_state: Arc<RwLock<ClientThreadState<K>>>,
+) {
+    let mut client_thread_state_write_lock_guard = client_thread_state.write().await;
+
+    client_thread_state_write_lock_guard
+        .subscribed_voters
+        .remove(&client_id);
+    client_thread_state_write_lock_guard
+        .subscribed_filtered_voters
+        .insert(client_id, filter);
+}
+
+/// [handle_client_message_subscribe_filtered_validators] is a function that
+/// processes the client message to subscribe to only the validators matched
+/// by the given [SubscriptionFilter].
+///
+/// This replaces any unfiltered validators subscription of the client.
+pub async fn handle_client_message_subscribe_filtered_validators<K>(
+    client_id: ClientId,
+    filter: SubscriptionFilter,
+    client_thread_state: Arc<RwLock<ClientThreadState<K>>>,
+) {
+    let mut client_thread_state_write_lock_guard = client_thread_state.write().await;
+
+    client_thread_state_write_lock_guard
+        .subscribed_validators
+        .remove(&client_id);
+    client_thread_state_write_lock_guard
+        .subscribed_filtered_validators
+        .insert(client_id, filter);
+}
+
 pub async fn handle_client_message_subscribe_stake_table<K>(
     client_id: ClientId,
     client_thread_state: Arc<RwLock<ClientThreadState<K>>>,
@@ -667,6 +730,84 @@ where
     Ok(())
 }
 
+#[derive(Debug)]
+pub enum HandleRequestValidatorParticipationError {
+    ClientSendError(SendError),
+}
+
+impl std::fmt::Display for HandleRequestValidatorParticipationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HandleRequestValidatorParticipationError::ClientSendError(err) => {
+                write!(
+                    f,
+                    "handle request validator participation error: client send error: {err}"
+                )
+            },
+        }
+    }
+}
+
+impl std::error::Error for HandleRequestValidatorParticipationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HandleRequestValidatorParticipationError::ClientSendError(err) => Some(err),
+        }
+    }
+}
+
+/// [handle_client_message_request_validator_participation] is a function that
+/// processes the client message request for the participation of validators
+/// over a range of blocks.
+///
+/// The request is answered from the [HistoryStore] of the [DataState].  If no
+/// [HistoryStore] is configured, or the query fails, the client is sent a
+/// [ServerMessage::HistoryUnavailable] rather than being dropped.
+///
+/// [HistoryStore]: super::history::HistoryStore
+pub async fn handle_client_message_request_validator_participation<K>(
+    client_id: ClientId,
+    request: ParticipationRequest,
+    data_state: Arc<RwLock<DataState>>,
+    client_thread_state: Arc<RwLock<ClientThreadState<K>>>,
+) -> Result<(), HandleRequestValidatorParticipationError>
+where
+    K: Sink<ServerMessage, Error = SendError> + Clone + Unpin,
+{
+    // The query may take a while, so we do not want to hold onto the data
+    // state lock while it runs.
+    let history = data_state.read().await.history().cloned();
+
+    let message = match history {
+        None => ServerMessage::HistoryUnavailable(
+            "this service was not configured with a history database".to_string(),
+        ),
+        Some(history) => match history.participation(&request).await {
+            Ok(participation) => ServerMessage::ValidatorParticipation(Arc::new(participation)),
+            Err(err) => {
+                tracing::warn!("failed to query validator participation: {err}");
+                ServerMessage::HistoryUnavailable(err.to_string())
+            },
+        },
+    };
+
+    let mut sender = {
+        let client_thread_state_read_lock_guard = client_thread_state.read().await;
+        match client_thread_state_read_lock_guard.clients.get(&client_id) {
+            Some(client) => client.sender.clone(),
+            None => return Ok(()),
+        }
+    };
+
+    if let Err(err) = sender.send(message).await {
+        drop_client_no_lock_guard(&client_id, client_thread_state.clone()).await;
+        return Err(HandleRequestValidatorParticipationError::ClientSendError(
+            err,
+        ));
+    }
+    Ok(())
+}
+
 #[derive(Debug)]
 pub enum HandleRequestUnrecognizedRequestError {
     ClientSendError(SendError),
@@ -741,6 +882,7 @@ pub enum ProcessClientMessageError {
     VotersSnapshot(HandleRequestVotersSnapshotError),
     ValidatorsSnapshot(HandleRequestValidatorsSnapshotError),
     StakeTableSnapshot(HandleRequestStakeTableSnapshotError),
+    ValidatorParticipation(HandleRequestValidatorParticipationError),
 
     /// This is the special case where the request received by the client is
     ///  not recognized by the server, and we want to handle it gracefully
@@ -790,6 +932,12 @@ impl From<HandleRequestStakeTableSnapshotError> for ProcessClientMessageError {
     }
 }
 
+impl From<HandleRequestValidatorParticipationError> for ProcessClientMessageError {
+    fn from(err: HandleRequestValidatorParticipationError) -> Self {
+        ProcessClientMessageError::ValidatorParticipation(err)
+    }
+}
+
 impl From<HandleRequestUnrecognizedRequestError> for ProcessClientMessageError {
     fn from(err: HandleRequestUnrecognizedRequestError) -> Self {
         ProcessClientMessageError::UnrecognizedRequest(err)
@@ -829,6 +977,12 @@ impl std::fmt::Display for ProcessClientMessageError {
                     "process client message error: stake table snapshot: {err}"
                 )
             },
+            ProcessClientMessageError::ValidatorParticipation(err) => {
+                write!(
+                    f,
+                    "process client message error: validator participation: {err}"
+                )
+            },
             ProcessClientMessageError::UnrecognizedRequest(err) => {
                 write!(f, "process client message error: unknown: {err}")
             },
@@ -846,6 +1000,7 @@ impl std::error::Error for ProcessClientMessageError {
             ProcessClientMessageError::VotersSnapshot(err) => Some(err),
             ProcessClientMessageError::ValidatorsSnapshot(err) => Some(err),
             ProcessClientMessageError::StakeTableSnapshot(err) => Some(err),
+            ProcessClientMessageError::ValidatorParticipation(err) => Some(err),
             ProcessClientMessageError::UnrecognizedRequest(err) => Some(err),
         }
     }
@@ -904,6 +1059,28 @@ where
             Ok(())
         },
 
+        InternalClientMessage::Request(
+            client_id,
+            ClientMessage::SubscribeFilteredVoters(filter),
+        ) => {
+            handle_client_message_subscribe_filtered_voters(client_id, filter, client_thread_state)
+                .await;
+            Ok(())
+        },
+
+        InternalClientMessage::Request(
+            client_id,
+            ClientMessage::SubscribeFilteredValidators(filter),
+        ) => {
+            handle_client_message_subscribe_filtered_validators(
+                client_id,
+                filter,
+                client_thread_state,
+            )
+            .await;
+            Ok(())
+        },
+
         InternalClientMessage::Request(client_id, ClientMessage::RequestBlocksSnapshot) => {
             handle_client_message_request_blocks_snapshot(
                 client_id,
@@ -964,6 +1141,20 @@ where
             Ok(())
         },
 
+        InternalClientMessage::Request(
+            client_id,
+            ClientMessage::RequestValidatorParticipation(request),
+        ) => {
+            handle_client_message_request_validator_participation(
+                client_id,
+                request,
+                data_state,
+                client_thread_state,
+            )
+            .await?;
+            Ok(())
+        },
+
         InternalClientMessage::Request(client_id, ClientMessage::UnrecognizedCommand(raw_str)) => {
             // This is a message that we don't know how to handle, and that we
             // do not recognize. We just want to inform the client that this
@@ -1041,6 +1232,41 @@ where
         .collect()
 }
 
+/// [handle_send_server_messages_to_clients] is a utility function that
+/// handles the sending of a distinct server message to each of the given
+/// clients, as is needed for filtered subscriptions.
+///
+/// Like [handle_send_server_message_to_subscribed_clients], it collects and
+/// returns all failed recipients so they can be dropped.
+async fn handle_send_server_messages_to_clients<K>(
+    client_thread_state_read_lock_guard: &RwLockReadGuard<'_, ClientThreadState<K>>,
+    messages: Vec<(ClientId, ServerMessage)>,
+) -> Vec<ClientId>
+where
+    K: Sink<ServerMessage, Error = SendError> + Clone + Unpin,
+{
+    let client_send_result_future = messages
+        .into_iter()
+        .filter_map(|(client_id, message)| {
+            client_thread_state_read_lock_guard
+                .clients
+                .get(&client_id)
+                .map(|client| (client_id, client.sender.clone(), message))
+        })
+        .map(|(client_id, mut sender, message)| async move {
+            let send_result = sender.send(message).await;
+            (client_id, send_result)
+        });
+
+    let client_send_results = futures::future::join_all(client_send_result_future).await;
+
+    client_send_results
+        .into_iter()
+        .filter(|(_, send_result)| send_result.is_err())
+        .map(|(client_id, _)| client_id)
+        .collect()
+}
+
 /// [handle_received_block_detail] is a function that processes received Block
 /// details and will attempt to distribute the message to all of the clients
 /// that are subscribed to the latest block stream.
@@ -1102,14 +1328,19 @@ async fn handle_received_node_identity<K>(
 /// [handle_received_voters] is a function that processes received voters and
 /// will attempt to distribute the message to all of the clients that are
 /// subscribed to the voters stream.
+///
+/// Clients with a filtered voters subscription instead receive whether each
+/// of the validators matched by their filter voted.  The [DataState] is used
+/// to map the voters [BitVec] back to the public keys of the validators.
 async fn handle_received_voters<K>(
     client_thread_state: Arc<RwLock<ClientThreadState<K>>>,
+    data_state: Arc<RwLock<DataState>>,
     voters: BitVec<u16>,
 ) where
     K: Sink<ServerMessage, Error = SendError> + Clone + Unpin,
 {
     let client_thread_state_read_lock_guard = client_thread_state.read().await;
-    let failed_client_sends = handle_send_server_message_to_subscribed_clients(
+    let mut failed_client_sends = handle_send_server_message_to_subscribed_clients(
         &client_thread_state_read_lock_guard,
         // These are the clients who are subscribed to the voters, that
         // have an active ClientState within the system.
@@ -1118,6 +1349,48 @@ async fn handle_received_voters<K>(
     )
     .await;
 
+    if !client_thread_state_read_lock_guard
+        .subscribed_filtered_voters
+        .is_empty()
+    {
+        let data_state_read_lock_guard = data_state.read().await;
+        let accounts = data_state_read_lock_guard
+            .validators()
+            .map(|validator| (*validator.stake_table_key(), validator.account))
+            .collect::<HashMap<_, _>>();
+
+        // The voters BitVec is in the order of the node identities at the
+        // time that it was created.  New node identities are only ever
+        // appended, so the leading node identities still line up.
+        let votes = data_state_read_lock_guard
+            .node_identity()
+            .zip(voters.iter())
+            .map(|(node_identity, voted)| (*node_identity.public_key(), *voted))
+            .collect::<Vec<_>>();
+        drop(data_state_read_lock_guard);
+
+        let messages = client_thread_state_read_lock_guard
+            .subscribed_filtered_voters
+            .iter()
+            .map(|(client_id, filter)| {
+                let filtered_votes = votes
+                    .iter()
+                    .filter(|(key, _)| filter.matches(key, accounts.get(key)))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                (
+                    *client_id,
+                    ServerMessage::LatestFilteredVoters(Arc::new(filtered_votes)),
+                )
+            })
+            .collect();
+
+        failed_client_sends.extend(
+            handle_send_server_messages_to_clients(&client_thread_state_read_lock_guard, messages)
+                .await,
+        );
+    }
+
     // Explicitly Drop the read lock.
     drop(client_thread_state_read_lock_guard);
 
@@ -1160,16 +1433,36 @@ async fn handle_received_validator<K>(
 ) where
     K: Sink<ServerMessage, Error = SendError> + Clone + Unpin,
 {
+    let validator = Arc::new(validator);
     let client_thread_state_read_lock_guard = client_thread_state.read().await;
-    let failed_client_sends = handle_send_server_message_to_subscribed_clients(
+    let mut failed_client_sends = handle_send_server_message_to_subscribed_clients(
         &client_thread_state_read_lock_guard,
         // These are the clients who are subscribed to the validators, that
         // have an active ClientState within the system.
         &client_thread_state_read_lock_guard.subscribed_validators,
-        ServerMessage::LatestValidator(Arc::new(validator)),
+        ServerMessage::LatestValidator(validator.clone()),
     )
     .await;
 
+    // Clients with a filtered subscription only receive the validator if it
+    // is matched by their filter.
+    let filtered_subscribers = client_thread_state_read_lock_guard
+        .subscribed_filtered_validators
+        .iter()
+        .filter(|(_, filter)| filter.matches(validator.stake_table_key(), Some(&validator.account)))
+        .map(|(client_id, _)| *client_id)
+        .collect::<HashSet<_>>();
+    if !filtered_subscribers.is_empty() {
+        failed_client_sends.extend(
+            handle_send_server_message_to_subscribed_clients(
+                &client_thread_state_read_lock_guard,
+                &filtered_subscribers,
+                ServerMessage::LatestValidator(validator),
+            )
+            .await,
+        );
+    }
+
     // Explicitly Drop the read lock.
     drop(client_thread_state_read_lock_guard);
 
@@ -1410,13 +1703,14 @@ pub struct ProcessDistributeVotersHandlingTask {
 
 impl ProcessDistributeVotersHandlingTask {
     /// [new] creates a new [ProcessDistributeVotersHandlingTask] with the
-    /// given client_thread_state and voters_receiver.
+    /// given client_thread_state, data_state and voters_receiver.
     ///
     /// Calling this function will start an async task that will start
     /// processing.  The handle for the async task is stored within the
     /// returned state.
     pub fn new<S, K>(
         client_thread_state: Arc<RwLock<ClientThreadState<K>>>,
+        data_state: Arc<RwLock<DataState>>,
         voters_receiver: S,
     ) -> Self
     where
@@ -1425,6 +1719,7 @@ impl ProcessDistributeVotersHandlingTask {
     {
         let task_handle = spawn(Self::process_distribute_voters_handling_stream(
             client_thread_state.clone(),
+            data_state,
             voters_receiver,
         ));
 
@@ -1438,6 +1733,7 @@ impl ProcessDistributeVotersHandlingTask {
     /// subscribed clients.
     async fn process_distribute_voters_handling_stream<S, K>(
         client_thread_state: Arc<RwLock<ClientThreadState<K>>>,
+        data_state: Arc<RwLock<DataState>>,
         mut stream: S,
     ) where
         S: Stream<Item = BitVec<u16>> + Unpin,
@@ -1453,7 +1749,7 @@ impl ProcessDistributeVotersHandlingTask {
                 return;
             };
 
-            handle_received_voters(client_thread_state.clone(), voters).await
+            handle_received_voters(client_thread_state.clone(), data_state.clone(), voters).await
         }
     }
 }
@@ -1634,7 +1930,7 @@ pub mod tests {
         api::node_validator::v0::PublicHotShotConfig,
         service::{
             client_id::ClientId,
-            client_message::{ClientMessage, InternalClientMessage},
+            client_message::{ClientMessage, InternalClientMessage, SubscriptionFilter},
             client_state::{
                 ProcessDistributeBlockDetailHandlingTask,
                 ProcessDistributeNodeIdentityHandlingTask, ProcessDistributeStakeTableHandlingTask,
@@ -1644,6 +1940,10 @@ pub mod tests {
                 DataState, LocationDetails, NodeIdentity, ProcessLeafAndBlockPairStreamTask,
                 create_block_detail_from_block,
             },
+            history::{
+                BlockParticipation, HistoryStore, ParticipationRange, ParticipationRequest,
+                ValidatorParticipation,
+            },
             server_message::ServerMessage,
         },
     };
@@ -1800,6 +2100,93 @@ pub mod tests {
         }
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_process_client_handling_stream_request_validator_participation() {
+        let (node_1, node_2, _, data_state_without_history) = create_test_data_state();
+        let (_, _, _, mut data_state) = create_test_data_state();
+        let client_thread_state = Arc::new(RwLock::new(create_test_client_thread_state()));
+
+        let request = ParticipationRequest {
+            validators: vec![*node_1.public_key()],
+            range: ParticipationRange::Heights { from: 0, to: 10 },
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let history = HistoryStore::connect(&dir.path().join("history.db"))
+            .await
+            .unwrap();
+        history
+            .record_block(&BlockParticipation {
+                height: 1,
+                epoch: 0,
+                timestamp: 0,
+                proposer: Default::default(),
+                votes: vec![(*node_1.public_key(), true), (*node_2.public_key(), false)],
+            })
+            .await
+            .unwrap();
+
+        data_state.set_history(history);
+        let data_state = Arc::new(RwLock::new(data_state));
+        let data_state_without_history = Arc::new(RwLock::new(data_state_without_history));
+
+        // Without a history database, the client is told that the history is
+        // unavailable.
+        for (data_state, expected) in [
+            (
+                data_state_without_history,
+                ServerMessage::HistoryUnavailable(
+                    "this service was not configured with a history database".to_string(),
+                ),
+            ),
+            (
+                data_state,
+                ServerMessage::ValidatorParticipation(Arc::new(vec![ValidatorParticipation {
+                    validator: *node_1.public_key(),
+                    eligible_blocks: 1,
+                    votes: 1,
+                    proposals: 0,
+                }])),
+            ),
+        ] {
+            let (mut internal_client_message_sender, internal_client_message_receiver) =
+                mpsc::channel(1);
+            let (server_message_sender, mut server_message_receiver) = mpsc::channel(1);
+            let mut process_internal_client_message_handle =
+                InternalClientMessageProcessingTask::new(
+                    internal_client_message_receiver,
+                    data_state,
+                    client_thread_state.clone(),
+                );
+
+            assert_eq!(
+                internal_client_message_sender
+                    .send(InternalClientMessage::Connected(server_message_sender))
+                    .await,
+                Ok(())
+            );
+            let client_id = match server_message_receiver.next().await {
+                Some(ServerMessage::YouAre(client_id)) => client_id,
+                message => panic!("unexpected message: {message:?}"),
+            };
+
+            assert_eq!(
+                internal_client_message_sender
+                    .send(InternalClientMessage::Request(
+                        client_id,
+                        ClientMessage::RequestValidatorParticipation(request.clone()),
+                    ))
+                    .await,
+                Ok(()),
+            );
+            assert_eq!(server_message_receiver.next().await, Some(expected));
+
+            if let Some(task_handle) = process_internal_client_message_handle.task_handle.take() {
+                task_handle.abort();
+            }
+        }
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     #[cfg(feature = "testing")]
     async fn test_process_client_handling_stream_request_latest_blocks_snapshot() {
@@ -1977,8 +2364,11 @@ pub mod tests {
                 block_detail_receiver,
             );
 
-        let mut process_distribute_voters_handle =
-            ProcessDistributeVotersHandlingTask::new(client_thread_state.clone(), voters_receiver);
+        let mut process_distribute_voters_handle = ProcessDistributeVotersHandlingTask::new(
+            client_thread_state.clone(),
+            data_state.clone(),
+            voters_receiver,
+        );
 
         let mut process_distribute_stake_table_handle =
             ProcessDistributeStakeTableHandlingTask::new(
@@ -2268,7 +2658,7 @@ pub mod tests {
 
     #[tokio::test(flavor = "multi_thread")]
     async fn test_process_client_handling_stream_subscribe_voters() {
-        let (_, _, _, data_state) = create_test_data_state();
+        let (_, node_2, _, data_state) = create_test_data_state();
         let client_thread_state = Arc::new(RwLock::new(create_test_client_thread_state()));
         let data_state = Arc::new(RwLock::new(data_state));
 
@@ -2283,8 +2673,11 @@ pub mod tests {
             client_thread_state.clone(),
         );
 
-        let mut process_distribute_voters_handle =
-            ProcessDistributeVotersHandlingTask::new(client_thread_state, voters_receiver);
+        let mut process_distribute_voters_handle = ProcessDistributeVotersHandlingTask::new(
+            client_thread_state,
+            data_state,
+            voters_receiver,
+        );
 
         // Send a Connected Message to the server
         let mut internal_client_message_sender_1 = internal_client_message_sender.clone();
@@ -2302,6 +2695,7 @@ pub mod tests {
 
         let client_1_id = ClientId::from_count(2);
         let client_2_id = ClientId::from_count(3);
+        let client_3_id = ClientId::from_count(4);
 
         // Send another Connected Message to the server
         let mut internal_client_message_sender_2 = internal_client_message_sender.clone();
@@ -2351,6 +2745,20 @@ pub mod tests {
             Ok(()),
         );
 
+        // The third client is only interested in the second node.
+        assert_eq!(
+            internal_client_message_sender_1
+                .send(InternalClientMessage::Request(
+                    client_3_id,
+                    ClientMessage::SubscribeFilteredVoters(SubscriptionFilter {
+                        keys: vec![*node_2.public_key()],
+                        addresses: vec![],
+                    })
+                ))
+                .await,
+            Ok(()),
+        );
+
         // No response expected from the client messages at the moment.
 
         // send a new Node Identity
@@ -2366,6 +2774,13 @@ pub mod tests {
             server_message_receiver_2.next().await,
             Some(ServerMessage::LatestVoters(voters.clone()))
         );
+        assert_eq!(
+            server_message_receiver_3.next().await,
+            Some(ServerMessage::LatestFilteredVoters(Arc::new(vec![(
+                *node_2.public_key(),
+                false
+            )])))
+        );
 
         if let Some(process_internal_client_message_handle) =
             process_internal_client_message_handle.task_handle.take()
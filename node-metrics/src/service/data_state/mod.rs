@@ -18,7 +18,7 @@ use hotshot_types::{
     PeerConfig,
     signature_key::BLSPubKey,
     traits::{BlockPayload, EncodeBytes, block_contents::BlockHeader},
-    utils::epoch_from_block_number,
+    utils::{epoch_from_block_number, is_last_block},
 };
 use indexmap::IndexMap;
 pub use location_details::LocationDetails;
@@ -26,9 +26,12 @@ pub use node_identity::NodeIdentity;
 use time::OffsetDateTime;
 use tokio::{spawn, task::JoinHandle};
 
-use crate::api::node_validator::v0::{
-    LeafAndBlock, PublicHotShotConfig, Version01, get_node_stake_table_from_sequencer,
-    get_node_validators_from_sequencer,
+use crate::{
+    api::node_validator::v0::{
+        LeafAndBlock, PublicHotShotConfig, Version01, get_node_stake_table_from_sequencer,
+        get_node_validators_from_sequencer,
+    },
+    service::history::{BlockParticipation, HistoryStore},
 };
 
 /// MAX_HISTORY represents the last N records that are stored within the
@@ -49,6 +52,17 @@ pub struct DataState {
     // Do we need any other data at the moment?
     node_identity: Vec<NodeIdentity>,
     validators: IndexMap<Address, AuthenticatedValidator<BLSPubKey>>,
+    completed_epoch: Option<EpochLeaders>,
+    history: Option<HistoryStore>,
+}
+
+/// [EpochLeaders] records how many blocks each member of an epoch's stake
+/// table proposed over the course of that epoch.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EpochLeaders {
+    pub epoch: u64,
+    /// The number of proposals per stake table key, in stake table order.
+    pub leaders: Vec<(BLSPubKey, u16)>,
 }
 
 impl DataState {
@@ -69,6 +83,8 @@ impl DataState {
             stake_table,
             node_identity,
             validators,
+            completed_epoch: None,
+            history: None,
         }
     }
 
@@ -96,6 +112,18 @@ impl DataState {
         self.validators.values()
     }
 
+    /// The leader counts of the most recently completed epoch, if the
+    /// network has completed an epoch since this service started.
+    pub fn completed_epoch(&self) -> Option<&EpochLeaders> {
+        self.completed_epoch.as_ref()
+    }
+
+    /// The [HistoryStore] that processed blocks are persisted to, if one has
+    /// been configured.
+    pub fn history(&self) -> Option<&HistoryStore> {
+        self.history.as_ref()
+    }
+
     // [stake_table_differences] is a helper function that will check the
     // public key entry differences between the [old_stake_table] and the new
     // [stake_table].
@@ -196,6 +224,14 @@ impl DataState {
         self.latest_voters.push_back(voters);
     }
 
+    pub fn set_completed_epoch(&mut self, completed_epoch: EpochLeaders) {
+        self.completed_epoch = Some(completed_epoch);
+    }
+
+    pub fn set_history(&mut self, history: HistoryStore) {
+        self.history = Some(history);
+    }
+
     pub fn add_node_identity(&mut self, identity: NodeIdentity) {
         // We need to check to see if this identity is already in the list,
         // if it is, we will want to replace it.
@@ -503,6 +539,33 @@ where
 
     let voters_set: HashSet<BLSPubKey> = stake_table_keys_that_voted.collect();
 
+    // Capture the participation of every member of the stake table, so that
+    // it can be persisted once we no longer hold the lock.
+    let num_blocks_per_epoch = hotshot_config.epoch_height.unwrap_or(0);
+    let epoch = if num_blocks_per_epoch > 0 {
+        epoch_number_helper(
+            block_height,
+            hotshot_config.epoch_start_block.unwrap_or(0),
+            num_blocks_per_epoch,
+        )
+    } else {
+        0
+    };
+    let history = data_state_write_lock_guard.history.clone();
+    let block_participation = history.as_ref().map(|_| BlockParticipation {
+        height: block_height,
+        epoch,
+        timestamp: block_detail.time.0.unix_timestamp(),
+        proposer: block_detail.proposer_id.clone(),
+        votes: stake_table
+            .iter()
+            .map(|config| {
+                let key = config.stake_table_entry.stake_key;
+                (key, voters_set.contains(&key))
+            })
+            .collect(),
+    });
+
     let voters_bitvec = data_state_write_lock_guard.node_identity.iter().fold(
         BitVec::with_capacity(data_state_write_lock_guard.node_identity.len()),
         |mut acc, node_identity| {
@@ -518,8 +581,44 @@ where
         .latest_voters
         .push_back(voters_bitvec.clone());
 
+    // The final block of an epoch carries the number of proposals each
+    // member of that epoch's stake table made, in stake table order.
+    let mut completed_epoch = None;
+    if let Some(leader_counts) = block.header().leader_counts()
+        && num_blocks_per_epoch > 0
+        && is_last_block(block_height, num_blocks_per_epoch)
+    {
+        let leaders = zip(
+            data_state_write_lock_guard.stake_table.iter(),
+            leader_counts.iter(),
+        )
+        .map(|(config, count)| (config.stake_table_entry.stake_key, *count))
+        .collect();
+        let epoch_leaders = EpochLeaders { epoch, leaders };
+        completed_epoch = history.as_ref().map(|_| epoch_leaders.clone());
+        data_state_write_lock_guard.completed_epoch = Some(epoch_leaders);
+    }
+
     drop(data_state_write_lock_guard);
 
+    // Failing to persist the history should not stall the real-time
+    // processing of blocks, so these errors are only logged.
+    if let Some(history) = history {
+        if let Some(block_participation) = block_participation
+            && let Err(err) = history.record_block(&block_participation).await
+        {
+            tracing::error!("failed to record block {block_height} in history: {err}");
+        }
+
+        if let Some(EpochLeaders { epoch, leaders }) = completed_epoch
+            && let Err(err) = history
+                .record_epoch_leaders(epoch, block_height, &leaders)
+                .await
+        {
+            tracing::error!("failed to record leaders of epoch {epoch} in history: {err}");
+        }
+    }
+
     if let Err(err) = block_sender.send(block_detail_copy).await {
         // We have an error that prevents us from continuing
         return Err(ProcessLeafError::BlockSendError(err));
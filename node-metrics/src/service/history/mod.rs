@@ -0,0 +1,490 @@
+//! # History
+//!
+//! The [DataState] only keeps a bounded window of recent blocks and voters in
+//! memory.  The [HistoryStore] optionally persists the participation of every
+//! stake table member for each processed block, along with the proposer of
+//! the block and the per epoch leader counts, to a SQLite database.  This
+//! allows questions such as "what was the participation of validator X over
+//! the last month" to be answered over arbitrary height or epoch ranges,
+//! across restarts of the service.
+//!
+//! [DataState]: super::data_state::DataState
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::Path,
+};
+
+use bitvec::vec::BitVec;
+use espresso_types::FeeAccount;
+use hotshot_types::signature_key::BLSPubKey;
+use serde::{Deserialize, Serialize};
+use sqlx::{QueryBuilder, SqlitePool, query, query_as, sqlite::SqlitePoolOptions};
+
+/// [ParticipationRange] is the range of blocks over which a validator's
+/// participation is computed.  Both variants include `from` and exclude `to`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ParticipationRange {
+    Heights { from: u64, to: u64 },
+    Epochs { from: u64, to: u64 },
+}
+
+impl ParticipationRange {
+    // [column_and_bounds] returns the block column that this range
+    // restricts, along with the bounds of the range.
+    fn column_and_bounds(&self) -> (&'static str, i64, i64) {
+        match *self {
+            ParticipationRange::Heights { from, to } => ("height", from as i64, to as i64),
+            ParticipationRange::Epochs { from, to } => ("epoch", from as i64, to as i64),
+        }
+    }
+}
+
+/// [ParticipationRequest] asks for the participation of the given
+/// validators over the given [ParticipationRange].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParticipationRequest {
+    pub validators: Vec<BLSPubKey>,
+    pub range: ParticipationRange,
+}
+
+/// [ValidatorParticipation] summarizes the participation of a single
+/// validator over a [ParticipationRange].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidatorParticipation {
+    pub validator: BLSPubKey,
+
+    /// The number of blocks in the range for which the validator was a
+    /// member of the stake table.
+    pub eligible_blocks: u64,
+
+    /// The number of those blocks whose QC the validator signed.
+    pub votes: u64,
+
+    /// The number of blocks the validator proposed, summed over the epochs
+    /// that ended within the range.
+    pub proposals: u64,
+}
+
+/// [BlockParticipation] is the record that is persisted for every processed
+/// block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockParticipation {
+    pub height: u64,
+    pub epoch: u64,
+    /// Unix timestamp of the block, in seconds.
+    pub timestamp: i64,
+    pub proposer: Vec<FeeAccount>,
+    /// Whether each member of the stake table voted for the block.
+    pub votes: Vec<(BLSPubKey, bool)>,
+}
+
+/// [HistoryStoreError] represents the errors that can occur when reading or
+/// writing the [HistoryStore].
+#[derive(Debug)]
+pub enum HistoryStoreError {
+    Sqlx(sqlx::Error),
+    Migrate(sqlx::migrate::MigrateError),
+    Encode(serde_json::Error),
+}
+
+impl std::fmt::Display for HistoryStoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HistoryStoreError::Sqlx(err) => write!(f, "history store database error: {err}"),
+            HistoryStoreError::Migrate(err) => {
+                write!(f, "history store migration error: {err}")
+            },
+            HistoryStoreError::Encode(err) => write!(f, "history store encoding error: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for HistoryStoreError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HistoryStoreError::Sqlx(err) => Some(err),
+            HistoryStoreError::Migrate(err) => Some(err),
+            HistoryStoreError::Encode(err) => Some(err),
+        }
+    }
+}
+
+impl From<sqlx::Error> for HistoryStoreError {
+    fn from(err: sqlx::Error) -> Self {
+        HistoryStoreError::Sqlx(err)
+    }
+}
+
+impl From<sqlx::migrate::MigrateError> for HistoryStoreError {
+    fn from(err: sqlx::migrate::MigrateError) -> Self {
+        HistoryStoreError::Migrate(err)
+    }
+}
+
+impl From<serde_json::Error> for HistoryStoreError {
+    fn from(err: serde_json::Error) -> Self {
+        HistoryStoreError::Encode(err)
+    }
+}
+
+/// HISTORY_STORE_CONNECTIONS is the number of connections kept open to the
+/// history database.
+const HISTORY_STORE_CONNECTIONS: u32 = 5;
+
+/// [HistoryStore] persists per block voter participation and proposer
+/// information to a SQLite database.
+///
+/// Validators are stored by the string representation of their
+/// [BLSPubKey].
+#[derive(Clone, Debug)]
+pub struct HistoryStore {
+    pool: SqlitePool,
+}
+
+impl HistoryStore {
+    /// [connect] opens the database at [path], creating it if it does not
+    /// exist yet, and brings its schema up to date.
+    pub async fn connect(path: &Path) -> Result<Self, HistoryStoreError> {
+        let opt = hotshot_query_service::sqlite_options::sqlite_options().filename(path);
+        let pool = SqlitePoolOptions::default()
+            .max_connections(HISTORY_STORE_CONNECTIONS)
+            .connect_with(opt)
+            .await?;
+        sqlx::migrate!("./migrations").run(&pool).await?;
+
+        Ok(Self { pool })
+    }
+
+    /// [record_block] persists the participation of a single block.
+    ///
+    /// Recording a block that has already been recorded replaces it, so the
+    /// blocks that are replayed when the service restarts are not counted
+    /// twice.
+    pub async fn record_block(&self, block: &BlockParticipation) -> Result<(), HistoryStoreError> {
+        let proposer = serde_json::to_string(&block.proposer)?;
+        let height = block.height as i64;
+
+        let mut tx = self.pool.begin().await?;
+        query(
+            "INSERT INTO block (height, epoch, timestamp, proposer) VALUES ($1, $2, $3, $4)
+                ON CONFLICT (height) DO UPDATE SET
+                    epoch = excluded.epoch,
+                    timestamp = excluded.timestamp,
+                    proposer = excluded.proposer",
+        )
+        .bind(height)
+        .bind(block.epoch as i64)
+        .bind(block.timestamp)
+        .bind(proposer)
+        .execute(&mut *tx)
+        .await?;
+
+        query("DELETE FROM vote WHERE height = $1")
+            .bind(height)
+            .execute(&mut *tx)
+            .await?;
+
+        if !block.votes.is_empty() {
+            let mut builder = QueryBuilder::new("INSERT INTO vote (height, validator, voted) ");
+            builder.push_values(&block.votes, |mut row, (validator, voted)| {
+                row.push_bind(height)
+                    .push_bind(validator.to_string())
+                    .push_bind(*voted);
+            });
+            builder.build().execute(&mut *tx).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// [record_epoch_leaders] persists the number of blocks each member of
+    /// the stake table of [epoch] proposed, as reported by the final block
+    /// of the epoch at [height].
+    pub async fn record_epoch_leaders(
+        &self,
+        epoch: u64,
+        height: u64,
+        leaders: &[(BLSPubKey, u16)],
+    ) -> Result<(), HistoryStoreError> {
+        if leaders.is_empty() {
+            return Ok(());
+        }
+
+        let mut builder =
+            QueryBuilder::new("INSERT INTO epoch_leader (epoch, height, validator, proposals) ");
+        builder.push_values(leaders, |mut row, (validator, proposals)| {
+            row.push_bind(epoch as i64)
+                .push_bind(height as i64)
+                .push_bind(validator.to_string())
+                .push_bind(*proposals as i64);
+        });
+        builder.push(
+            " ON CONFLICT (validator, epoch) DO UPDATE SET
+                height = excluded.height,
+                proposals = excluded.proposals",
+        );
+        builder.build().execute(&self.pool).await?;
+        Ok(())
+    }
+
+    /// [latest_voters] reconstructs the voters of the last [limit] recorded
+    /// blocks below height [before], oldest first.  Each entry has one bit
+    /// per key in [keys], in the same order, which is set if that key voted
+    /// for the block.
+    ///
+    /// This allows the in-memory voters window to be restored when the
+    /// service restarts.
+    pub async fn latest_voters(
+        &self,
+        before: u64,
+        limit: usize,
+        keys: &[BLSPubKey],
+    ) -> Result<Vec<BitVec<u16>>, HistoryStoreError> {
+        let rows: Vec<(i64, Option<String>)> = query_as(
+            "SELECT b.height, v.validator
+                FROM (SELECT height FROM block WHERE height < $1 ORDER BY height DESC LIMIT $2)
+                    AS b
+                LEFT JOIN vote AS v ON v.height = b.height AND v.voted
+                ORDER BY b.height",
+        )
+        .bind(before as i64)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut voted: BTreeMap<i64, HashSet<String>> = BTreeMap::new();
+        for (height, validator) in rows {
+            let voters = voted.entry(height).or_default();
+            if let Some(validator) = validator {
+                voters.insert(validator);
+            }
+        }
+
+        let keys: Vec<String> = keys.iter().map(ToString::to_string).collect();
+        Ok(voted
+            .into_values()
+            .map(|voters| keys.iter().map(|key| voters.contains(key)).collect())
+            .collect())
+    }
+
+    /// [participation] computes the [ValidatorParticipation] of each of the
+    /// requested validators over the requested range.  Validators for which
+    /// nothing has been recorded are reported with all counts set to zero.
+    pub async fn participation(
+        &self,
+        request: &ParticipationRequest,
+    ) -> Result<Vec<ValidatorParticipation>, HistoryStoreError> {
+        let (column, from, to) = request.range.column_and_bounds();
+        let votes_query = format!(
+            "SELECT COUNT(*), COALESCE(SUM(v.voted), 0)
+                FROM vote AS v
+                JOIN block AS b ON b.height = v.height
+                WHERE v.validator = $1 AND b.{column} >= $2 AND b.{column} < $3"
+        );
+        let proposals_query = format!(
+            "SELECT COALESCE(SUM(proposals), 0)
+                FROM epoch_leader
+                WHERE validator = $1 AND {column} >= $2 AND {column} < $3"
+        );
+
+        let mut conn = self.pool.acquire().await?;
+        let mut participation = Vec::with_capacity(request.validators.len());
+        for validator in request.validators.iter() {
+            let key = validator.to_string();
+            let (eligible_blocks, votes): (i64, i64) = query_as(&votes_query)
+                .bind(&key)
+                .bind(from)
+                .bind(to)
+                .fetch_one(&mut *conn)
+                .await?;
+            let (proposals,): (i64,) = query_as(&proposals_query)
+                .bind(&key)
+                .bind(from)
+                .bind(to)
+                .fetch_one(&mut *conn)
+                .await?;
+
+            participation.push(ValidatorParticipation {
+                validator: *validator,
+                eligible_blocks: eligible_blocks as u64,
+                votes: votes as u64,
+                proposals: proposals as u64,
+            });
+        }
+
+        Ok(participation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn key(index: u64) -> BLSPubKey {
+        BLSPubKey::generated_from_seed_indexed([0; 32], index).0
+    }
+
+    fn block(height: u64, epoch: u64, votes: &[bool]) -> BlockParticipation {
+        BlockParticipation {
+            height,
+            epoch,
+            timestamp: height as i64,
+            proposer: vec![FeeAccount::default()],
+            votes: votes
+                .iter()
+                .enumerate()
+                .map(|(i, voted)| (key(i as u64), *voted))
+                .collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_participation_by_height_and_epoch() {
+        let dir = tempdir().unwrap();
+        let store = HistoryStore::connect(&dir.path().join("history.db"))
+            .await
+            .unwrap();
+
+        store
+            .record_block(&block(1, 1, &[true, true]))
+            .await
+            .unwrap();
+        store
+            .record_block(&block(2, 1, &[true, false]))
+            .await
+            .unwrap();
+        store
+            .record_block(&block(3, 2, &[false, true]))
+            .await
+            .unwrap();
+        store
+            .record_epoch_leaders(1, 2, &[(key(0), 2), (key(1), 0)])
+            .await
+            .unwrap();
+
+        let participation = store
+            .participation(&ParticipationRequest {
+                validators: vec![key(0), key(1), key(2)],
+                range: ParticipationRange::Heights { from: 1, to: 4 },
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            participation,
+            vec![
+                ValidatorParticipation {
+                    validator: key(0),
+                    eligible_blocks: 3,
+                    votes: 2,
+                    proposals: 2,
+                },
+                ValidatorParticipation {
+                    validator: key(1),
+                    eligible_blocks: 3,
+                    votes: 2,
+                    proposals: 0,
+                },
+                ValidatorParticipation {
+                    validator: key(2),
+                    eligible_blocks: 0,
+                    votes: 0,
+                    proposals: 0,
+                },
+            ]
+        );
+
+        let participation = store
+            .participation(&ParticipationRequest {
+                validators: vec![key(1)],
+                range: ParticipationRange::Epochs { from: 2, to: 3 },
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            participation,
+            vec![ValidatorParticipation {
+                validator: key(1),
+                eligible_blocks: 1,
+                votes: 1,
+                proposals: 0,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_latest_voters() {
+        let dir = tempdir().unwrap();
+        let store = HistoryStore::connect(&dir.path().join("history.db"))
+            .await
+            .unwrap();
+
+        store
+            .record_block(&block(1, 0, &[true, true]))
+            .await
+            .unwrap();
+        store
+            .record_block(&block(2, 0, &[false, false]))
+            .await
+            .unwrap();
+        store
+            .record_block(&block(3, 0, &[false, true]))
+            .await
+            .unwrap();
+        store
+            .record_block(&block(4, 0, &[true, false]))
+            .await
+            .unwrap();
+
+        // The keys are reported in the requested order, and keys that were
+        // not recorded never voted.
+        let voters = store
+            .latest_voters(4, 2, &[key(1), key(0), key(2)])
+            .await
+            .unwrap();
+        let expected: Vec<BitVec<u16>> = [[false, false, false], [true, false, false]]
+            .iter()
+            .map(|bits| bits.iter().copied().collect())
+            .collect();
+        assert_eq!(voters, expected);
+
+        assert_eq!(
+            store.latest_voters(10, 100, &[key(0)]).await.unwrap().len(),
+            4
+        );
+        assert!(
+            store
+                .latest_voters(1, 100, &[key(0)])
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_record_block_is_idempotent() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("history.db");
+        let store = HistoryStore::connect(&path).await.unwrap();
+
+        store.record_block(&block(1, 0, &[false])).await.unwrap();
+        // A replayed block replaces the earlier record.
+        store.record_block(&block(1, 0, &[true])).await.unwrap();
+        drop(store);
+
+        // The history survives reopening the database.
+        let store = HistoryStore::connect(&path).await.unwrap();
+        let participation = store
+            .participation(&ParticipationRequest {
+                validators: vec![key(0)],
+                range: ParticipationRange::Heights { from: 0, to: 10 },
+            })
+            .await
+            .unwrap();
+        assert_eq!(participation[0].eligible_blocks, 1);
+        assert_eq!(participation[0].votes, 1);
+    }
+}
@@ -1,6 +1,8 @@
+pub mod alerting;
 pub mod client_id;
 pub mod client_message;
 pub mod client_state;
 pub mod data_state;
+pub mod history;
 pub mod node_type;
 pub mod server_message;
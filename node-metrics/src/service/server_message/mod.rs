@@ -7,7 +7,7 @@ use hotshot_query_service::explorer::{BlockDetail, ExplorerHistograms};
 use hotshot_types::PeerConfig;
 use serde::{Deserialize, Serialize};
 
-use super::{client_id::ClientId, data_state::NodeIdentity};
+use super::{client_id::ClientId, data_state::NodeIdentity, history::ValidatorParticipation};
 
 /// [ServerMessage] represents the messages that the server can send to the
 /// client for a response.
@@ -64,6 +64,20 @@ pub enum ServerMessage {
     /// for the snapshot of the current stake table information.
     StakeTableSnapshot(Arc<Vec<PeerConfig<SeqTypes>>>),
 
+    /// ValidatorParticipation is a message that is sent in response to a
+    /// request for the participation of validators over a range of blocks.
+    ValidatorParticipation(Arc<Vec<ValidatorParticipation>>),
+
+    /// HistoryUnavailable is a message that is sent in response to a request
+    /// that needs the history database, when the database is either not
+    /// configured or failed to answer the request.
+    HistoryUnavailable(String),
+
+    /// LatestFilteredVoters is a message that is meant to show whether each
+    /// validator matched by a client's filtered voters subscription voted for
+    /// the most recent block.
+    LatestFilteredVoters(Arc<Vec<(BLSPubKey, bool)>>),
+
     // UnrecognizedRequest is a message that is sent when the server receives
     // a request that it does not recognize. This is useful for debugging and
     // for ensuring that the client is sending valid requests.
@@ -85,6 +99,9 @@ impl PartialEq for ServerMessage {
             (Self::VotersSnapshot(lhs), Self::VotersSnapshot(rhs)) => lhs == rhs,
             (Self::ValidatorsSnapshot(lhs), Self::ValidatorsSnapshot(rhs)) => lhs == rhs,
             (Self::StakeTableSnapshot(lhs), Self::StakeTableSnapshot(rhs)) => lhs == rhs,
+            (Self::ValidatorParticipation(lhs), Self::ValidatorParticipation(rhs)) => lhs == rhs,
+            (Self::HistoryUnavailable(lhs), Self::HistoryUnavailable(rhs)) => lhs == rhs,
+            (Self::LatestFilteredVoters(lhs), Self::LatestFilteredVoters(rhs)) => lhs == rhs,
             (Self::UnrecognizedRequest(lhs), Self::UnrecognizedRequest(rhs)) => lhs == rhs,
             _ => false,
         }
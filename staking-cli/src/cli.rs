@@ -29,6 +29,7 @@ use crate::deploy::deploy_contracts_for_testing;
 use crate::{
     Commands, Config, SignerConfigError, ValidSignerConfig,
     claim::fetch_claim_rewards_inputs,
+    compound::{CompoundParams, compound_rewards},
     demo::{
         ChurnParams, DemoCommands, churn_for_demo, delegate_for_demo, stake_for_demo,
         undelegate_for_demo,
@@ -38,10 +39,19 @@ use crate::{
         fetch_token_address, stake_table_info,
     },
     metadata::{MetadataUri, fetch_metadata, validate_metadata_uri},
+    offline::{
+        SignedEnvelope, UnsignedTx, broadcast, output_json, prepare_unsigned, read_json,
+        sign_unsigned,
+    },
     output::{
-        CalldataInfo, format_esp, output_calldata, output_error, output_success, output_warn,
+        CalldataInfo, format_esp, output_calldata, output_error, output_success, output_text,
+        output_warn,
+    },
+    portfolio::fetch_portfolio,
+    safe::{fetch_safe_threshold, safe_proposal},
+    signature::{
+        NodeSignatureDestination, NodeSignatureInput, NodeSignatures, SerializationFormat,
     },
-    signature::{NodeSignatureDestination, NodeSignatureInput, NodeSignatures},
     transaction::Transaction,
 };
 
@@ -218,11 +228,11 @@ fn decode_and_display_logs(logs: &[Log]) {
 
 fn resolve_node_signatures(
     signature_args: &crate::signature::NodeSignatureArgs,
-    export_calldata: bool,
+    export: bool,
     wallet: Option<&EthereumWallet>,
     sender_address: Option<Address>,
 ) -> Result<NodeSignatures> {
-    if export_calldata {
+    if export {
         let input = NodeSignatureInput::try_from((signature_args.clone(), sender_address))?;
         NodeSignatures::try_from(input)
     } else {
@@ -369,11 +379,39 @@ pub async fn run(migrated_envs: Vec<(&str, &str)>) -> Result<()> {
             output_success(serde_json::to_string_pretty(&metadata)?);
             return Ok(());
         },
+        Commands::Sign { input, output } => {
+            let wallet = ValidSignerConfig::try_from(config.signer.clone())?
+                .wallet()
+                .await?;
+            let unsigned: UnsignedTx = read_json(&input)?;
+            let signed = sign_unsigned(&wallet, unsigned).await?;
+            output_json(&signed, output.as_deref(), "Signed transaction")?;
+            return Ok(());
+        },
+        Commands::Broadcast { input, tx_log } => {
+            let envelope: SignedEnvelope = read_json(&input)?;
+            let provider = ProviderBuilder::new().connect_http(config.rpc_url.clone());
+            let receipt = broadcast(&provider, &envelope, &tx_log)
+                .await
+                .unwrap_or_else(|err| exit_err("Failed", err));
+            if !receipt.status() {
+                exit(format!(
+                    "transaction reverted: hash={}",
+                    receipt.transaction_hash
+                ));
+            }
+            output_success(format!(
+                "Success! transaction hash: {}",
+                receipt.transaction_hash
+            ));
+            decode_and_display_logs(receipt.inner.logs());
+            return Ok(());
+        },
         _ => {}, // Other commands handled after shared setup.
     }
 
     // When the staking CLI is used for our testnet, the env var names are different.
-    let config = config.apply_env_var_overrides()?;
+    let config = config.apply_env_var_overrides()?.apply_safe_address();
 
     // Commands that don't need a signer
     if let Commands::StakeTable {
@@ -427,7 +465,7 @@ pub async fn run(migrated_envs: Vec<(&str, &str)>) -> Result<()> {
 
     let stake_table_addr = config.stake_table_address;
 
-    // For export_calldata mode, we may not need a signer for most commands.
+    // For export modes, we may not need a signer for most commands.
     // We create the provider without a wallet first for token address fetching
     // and contract version detection.
     let readonly_provider = ProviderBuilder::new().connect_http(config.rpc_url.clone());
@@ -512,6 +550,60 @@ pub async fn run(migrated_envs: Vec<(&str, &str)>) -> Result<()> {
         return Ok(());
     }
 
+    if let Commands::Portfolio {
+        address,
+        from_block,
+        ref report_args,
+    } = config.commands
+    {
+        let address = address
+            .or_from_wallet(wallet.as_ref())
+            .context("Address required - provide --address or configure a signer")?;
+        let portfolio = fetch_portfolio(
+            &readonly_provider,
+            config.rpc_url.clone(),
+            stake_table_addr,
+            token_addr,
+            config.espresso_url.clone(),
+            address,
+            from_block,
+        )
+        .await?;
+        output_text(
+            &portfolio.render(report_args.format)?,
+            report_args.output.as_deref(),
+            "Report",
+        )?;
+        return Ok(());
+    }
+
+    if let Commands::CompoundRewards {
+        ref validators,
+        threshold,
+        interval,
+        ref tx_log,
+        once,
+    } = config.commands
+    {
+        if config.exports_transaction() {
+            anyhow::bail!("compound-rewards does not support exporting transactions");
+        }
+        let wallet = wallet.ok_or_else(&require_wallet)?;
+        let espresso_url = config.espresso_url.clone().ok_or_else(|| {
+            anyhow::anyhow!("espresso_url not set, use --espresso-url or ESPRESSO_URL")
+        })?;
+        let params = CompoundParams {
+            stake_table: stake_table_addr,
+            token: token_addr,
+            espresso_url,
+            split: validators.clone(),
+            threshold,
+            interval,
+            log_path: tx_log.clone(),
+        };
+        return compound_rewards(&readonly_provider, &wallet, &params, once).await;
+    }
+
     if let Commands::StakeForDemo {
         num_validators,
         num_delegators_per_validator,
@@ -678,12 +770,12 @@ pub async fn run(migrated_envs: Vec<(&str, &str)>) -> Result<()> {
                     "V3 stake table requires --x25519-key and --p2p-addr for registration"
                 );
             }
-            if !config.export_calldata {
+            if !config.exports_transaction() {
                 wallet.as_ref().ok_or_else(&require_wallet)?;
             }
             let payload = resolve_node_signatures(
                 signature_args,
-                config.export_calldata,
+                config.exports_transaction(),
                 wallet.as_ref(),
                 config.sender_address,
             )?;
@@ -716,14 +808,14 @@ pub async fn run(migrated_envs: Vec<(&str, &str)>) -> Result<()> {
                      deprecated."
                 );
             }
-            if !config.export_calldata {
+            if !config.exports_transaction() {
                 let w = wallet.as_ref().ok_or_else(&require_wallet)?;
                 let addr = NetworkWallet::<Ethereum>::default_signer_address(w);
                 tracing::info!("Updating validator {} with new keys", addr);
             }
             let payload = resolve_node_signatures(
                 signature_args,
-                config.export_calldata,
+                config.exports_transaction(),
                 wallet.as_ref(),
                 config.sender_address,
             )?;
@@ -770,7 +862,7 @@ pub async fn run(migrated_envs: Vec<(&str, &str)>) -> Result<()> {
             x25519_key,
             p2p_addr,
         } => {
-            if !config.export_calldata {
+            if !config.exports_transaction() {
                 wallet.as_ref().ok_or_else(&require_wallet)?;
             }
             Transaction::UpdateNetworkConfig {
@@ -780,7 +872,7 @@ pub async fn run(migrated_envs: Vec<(&str, &str)>) -> Result<()> {
             }
         },
         Commands::UpdateX25519Key { x25519_key } => {
-            if !config.export_calldata {
+            if !config.exports_transaction() {
                 wallet.as_ref().ok_or_else(&require_wallet)?;
             }
             Transaction::UpdateX25519Key {
@@ -789,7 +881,7 @@ pub async fn run(migrated_envs: Vec<(&str, &str)>) -> Result<()> {
             }
         },
         Commands::UpdateP2pAddr { p2p_addr } => {
-            if !config.export_calldata {
+            if !config.exports_transaction() {
                 wallet.as_ref().ok_or_else(&require_wallet)?;
             }
             Transaction::UpdateP2pAddr {
@@ -830,10 +922,11 @@ pub async fn run(migrated_envs: Vec<(&str, &str)>) -> Result<()> {
             let espresso_url = config.espresso_url.clone().ok_or_else(|| {
                 anyhow::anyhow!("espresso_url not set, use --espresso-url or ESPRESSO_URL")
             })?;
-            let claimer_address = if config.export_calldata {
+            let claimer_address = if config.exports_transaction() {
                 config.sender_address.ok_or_else(|| {
                     anyhow::anyhow!(
-                        "claim-rewards with --export-calldata requires --sender-address"
+                        "claim-rewards with --export-calldata or --export-unsigned requires \
+                         --sender-address"
                     )
                 })?
             } else {
@@ -864,6 +957,10 @@ pub async fn run(migrated_envs: Vec<(&str, &str)>) -> Result<()> {
         | Commands::UnclaimedRewards { .. }
         | Commands::TokenBalance { .. }
         | Commands::TokenAllowance { .. }
+        | Commands::Portfolio { .. }
+        | Commands::CompoundRewards { .. }
+        | Commands::Sign { .. }
+        | Commands::Broadcast { .. }
         | Commands::ExportNodeSignatures { .. }
         | Commands::PreviewMetadata { .. }
         | Commands::Demo(..)
@@ -875,8 +972,34 @@ pub async fn run(migrated_envs: Vec<(&str, &str)>) -> Result<()> {
     // Validate even for export mode to fail early if the transaction would fail on-chain.
     tx.validate_delegate_amount(&readonly_provider).await?;
 
+    if config.export_unsigned {
+        let sender = config
+            .sender_address
+            .context("--export-unsigned requires --sender-address")?;
+        if config.skip_simulation {
+            output_warn("Skipping transaction validation (--skip-simulation)");
+        }
+        let unsigned = prepare_unsigned(
+            &readonly_provider,
+            &tx,
+            sender,
+            config.nonce,
+            !config.skip_simulation,
+        )
+        .await?;
+        return output_json(
+            &unsigned,
+            config.output.output.as_deref(),
+            "Unsigned transaction",
+        );
+    }
+
     // Single code path for both export and execute modes
     if config.export_calldata {
+        if let Some(safe) = config.safe_address {
+            let threshold = fetch_safe_threshold(&readonly_provider, safe).await?;
+            tracing::info!("Exporting calldata for Safe {safe} with threshold {threshold}");
+        }
         if config.skip_simulation {
             output_warn("Skipping calldata validation (--skip-simulation)");
         } else {
@@ -891,11 +1014,20 @@ pub async fn run(migrated_envs: Vec<(&str, &str)>) -> Result<()> {
         let description = tx.description();
         let (to, data, fi) = tx.calldata()?;
         let chain_id = readonly_provider.get_chain_id().await?;
-        let info = match fi {
+        let mut info = match fi {
             Some(fi) => CalldataInfo::with_method(to, data, U256::ZERO, fi),
             None => CalldataInfo::new(to, data),
         }
         .with_description(description);
+        if let Some(safe) = config.safe_address {
+            if config.output.format == Some(SerializationFormat::SafeProposal) {
+                let proposal =
+                    safe_proposal(&readonly_provider, safe, chain_id, config.safe_nonce, &info)
+                        .await?;
+                return output_json(&proposal, config.output.output.as_deref(), "Safe proposal");
+            }
+            info = info.with_safe_address(safe);
+        }
         return output_calldata(&info, &config.output, chain_id);
     }
 
@@ -0,0 +1,366 @@
+//! Automatic compounding of staking rewards.
+//!
+//! Each round checks the unclaimed rewards of the signer and, if they exceed a threshold, claims
+//! them, approves the stake table to spend them and delegates them to the configured validators.
+//! Every transaction is written to a [`TxLog`] before it is submitted. If the process is restarted
+//! in the middle of a round it first waits for (or resubmits) the logged transactions and then
+//! continues with the remaining steps, so no step is executed twice.
+
+use std::{fmt, path::PathBuf, str::FromStr, time::Duration};
+
+use alloy::{
+    network::{Ethereum, EthereumWallet, NetworkWallet},
+    primitives::{Address, U256},
+    providers::{PendingTransactionBuilder, Provider},
+};
+use anyhow::{Context as _, Result, bail, ensure};
+use url::Url;
+
+use crate::{
+    claim::{fetch_claim_rewards_inputs, unclaimed_rewards},
+    offline::{prepare_unsigned, sign_unsigned},
+    output::format_esp,
+    transaction::Transaction,
+    tx_log::{SignedTx, TxLog, TxPhase, get_receipt_with_retry, submit_with_retry},
+};
+
+/// A validator and its share of the compounded rewards, parsed from `ADDRESS[:WEIGHT]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ValidatorWeight {
+    pub validator: Address,
+    pub weight: u64,
+}
+
+impl FromStr for ValidatorWeight {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (validator, weight) = match s.split_once(':') {
+            Some((validator, weight)) => {
+                let weight = weight
+                    .parse()
+                    .map_err(|e| format!("Invalid weight {weight:?}: {e}"))?;
+                (validator, weight)
+            },
+            None => (s, 1),
+        };
+        if weight == 0 {
+            return Err("Weight must be greater than zero".to_string());
+        }
+        let validator = validator
+            .parse()
+            .map_err(|e| format!("Invalid validator address {validator:?}: {e}"))?;
+        Ok(Self { validator, weight })
+    }
+}
+
+impl fmt::Display for ValidatorWeight {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.validator, self.weight)
+    }
+}
+
+/// Split `amount` between the validators proportionally to their weights.
+///
+/// The rounding remainder goes to the first validator so the parts always sum up to `amount`.
+/// Validators whose share rounds down to zero are omitted.
+pub fn split_rewards(amount: U256, split: &[ValidatorWeight]) -> Vec<(Address, U256)> {
+    let total_weight: U256 = split.iter().map(|v| U256::from(v.weight)).sum();
+    if total_weight.is_zero() {
+        return vec![];
+    }
+    let mut parts: Vec<_> = split
+        .iter()
+        .map(|v| (v.validator, amount * U256::from(v.weight) / total_weight))
+        .collect();
+    let distributed: U256 = parts.iter().map(|(_, part)| *part).sum();
+    parts[0].1 += amount - distributed;
+    parts.retain(|(_, part)| !part.is_zero());
+    parts
+}
+
+#[derive(Clone, Debug)]
+pub struct CompoundParams {
+    pub stake_table: Address,
+    pub token: Address,
+    pub espresso_url: Url,
+    pub split: Vec<ValidatorWeight>,
+    /// Minimum amount of unclaimed rewards to start a round.
+    pub threshold: U256,
+    pub interval: Duration,
+    pub log_path: PathBuf,
+}
+
+/// Compound rewards every `params.interval`, or only once if `once` is set.
+///
+/// Errors of a round are logged and the round is retried after the interval, unless `once` is
+/// set.
+pub async fn compound_rewards(
+    provider: &impl Provider,
+    wallet: &EthereumWallet,
+    params: &CompoundParams,
+    once: bool,
+) -> Result<()> {
+    ensure!(
+        !params.split.is_empty(),
+        "at least one validator is required"
+    );
+    let account = NetworkWallet::<Ethereum>::default_signer_address(wallet);
+    tracing::info!(
+        "compounding rewards of {account} to {} every {:?}",
+        params
+            .split
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", "),
+        params.interval
+    );
+
+    loop {
+        match compound_round(provider, wallet, params).await {
+            Ok(Some(amount)) => tracing::info!("compounded {}", format_esp(amount)),
+            Ok(None) => {},
+            Err(err) if !once => tracing::error!("compounding round failed: {err:#}"),
+            Err(err) => return Err(err),
+        }
+        if once {
+            return Ok(());
+        }
+        tokio::time::sleep(params.interval).await;
+    }
+}
+
+/// Run a single round, resuming the round recorded in the transaction log if there is one.
+///
+/// Returns the compounded amount, or `None` if the unclaimed rewards are below the threshold.
+async fn compound_round(
+    provider: &impl Provider,
+    wallet: &EthereumWallet,
+    params: &CompoundParams,
+) -> Result<Option<U256>> {
+    let account = NetworkWallet::<Ethereum>::default_signer_address(wallet);
+
+    let (mut log, amount) = match TxLog::load(&params.log_path)? {
+        Some(log) => {
+            tracing::info!(
+                "resuming compounding round from {} ({} txs)",
+                params.log_path.display(),
+                log.transactions.len()
+            );
+            for tx in &log.transactions {
+                ensure!(
+                    tx.from == account,
+                    "transaction log {} belongs to {}, not {account}",
+                    params.log_path.display(),
+                    tx.from
+                );
+                confirm(provider, tx).await?;
+            }
+            let amount = log
+                .transactions_for_phase(TxPhase::ClaimRewards)
+                .first()
+                .map(|tx| tx.amount)
+                .context("transaction log does not contain a reward claim")?;
+            (log, amount)
+        },
+        None => {
+            let unclaimed = unclaimed_rewards(
+                provider,
+                params.stake_table,
+                params.espresso_url.clone(),
+                account,
+            )
+            .await?;
+            if unclaimed.is_zero() || unclaimed < params.threshold {
+                tracing::info!(
+                    "unclaimed rewards {} below threshold {}",
+                    format_esp(unclaimed),
+                    format_esp(params.threshold)
+                );
+                return Ok(None);
+            }
+            // Fail before claiming if a delegation would be rejected by the stake table.
+            for (validator, amount) in split_rewards(unclaimed, &params.split) {
+                Transaction::Delegate {
+                    stake_table: params.stake_table,
+                    validator,
+                    amount,
+                }
+                .validate_delegate_amount(provider)
+                .await?;
+            }
+            (TxLog::new(vec![]), unclaimed)
+        },
+    };
+
+    if log.transactions_for_phase(TxPhase::ClaimRewards).is_empty() {
+        let claim =
+            fetch_claim_rewards_inputs(provider, params.stake_table, &params.espresso_url, account)
+                .await?
+                .context("no reward claim data found for address")?;
+        execute_step(
+            provider,
+            wallet,
+            params,
+            &mut log,
+            TxPhase::ClaimRewards,
+            claim,
+            amount,
+        )
+        .await?;
+    }
+
+    if log.transactions_for_phase(TxPhase::Approve).is_empty() {
+        let approve = Transaction::Approve {
+            token: params.token,
+            spender: params.stake_table,
+            amount,
+        };
+        execute_step(
+            provider,
+            wallet,
+            params,
+            &mut log,
+            TxPhase::Approve,
+            approve,
+            amount,
+        )
+        .await?;
+    }
+
+    for (validator, part) in split_rewards(amount, &params.split) {
+        let delegated = log
+            .transactions_for_phase(TxPhase::Delegate)
+            .iter()
+            .any(|tx| tx.to == validator);
+        if delegated {
+            continue;
+        }
+        let delegate = Transaction::Delegate {
+            stake_table: params.stake_table,
+            validator,
+            amount: part,
+        };
+        execute_step(
+            provider,
+            wallet,
+            params,
+            &mut log,
+            TxPhase::Delegate,
+            delegate,
+            part,
+        )
+        .await?;
+    }
+
+    log.archive(&params.log_path)?;
+    Ok(Some(amount))
+}
+
+/// Sign `tx`, record it in the log and submit it.
+///
+/// The log is saved before submission, a restart after this point resubmits the same signed
+/// transaction instead of creating a new one.
+async fn execute_step(
+    provider: &impl Provider,
+    wallet: &EthereumWallet,
+    params: &CompoundParams,
+    log: &mut TxLog,
+    phase: TxPhase,
+    tx: Transaction,
+    amount: U256,
+) -> Result<()> {
+    let account = NetworkWallet::<Ethereum>::default_signer_address(wallet);
+    let unsigned = prepare_unsigned(provider, &tx, account, None, true).await?;
+    let envelope = sign_unsigned(wallet, unsigned).await?;
+    let signed = SignedTx {
+        phase,
+        from: envelope.from,
+        // Record the validator for delegations, it identifies the step on restart.
+        to: match tx {
+            Transaction::Delegate { validator, .. } => validator,
+            _ => envelope.to,
+        },
+        amount,
+        delegator_index: None,
+        tx_hash: envelope.tx_hash,
+        signed_bytes: envelope.signed_bytes,
+        nonce: envelope.nonce,
+    };
+    log.transactions.push(signed.clone());
+    log.save(&params.log_path)?;
+
+    tracing::info!("{}: {}", phase, envelope.description);
+    confirm(provider, &signed).await
+}
+
+/// Wait until `tx` is included, submitting it if the node does not know it yet.
+async fn confirm(provider: &impl Provider, tx: &SignedTx) -> Result<()> {
+    let receipt = match get_receipt_with_retry(provider, tx.tx_hash).await? {
+        Some(receipt) => receipt,
+        None => {
+            let tx_hash = submit_with_retry(provider, &tx.signed_bytes, tx.tx_hash).await?;
+            PendingTransactionBuilder::new(provider.root().clone(), tx_hash)
+                .get_receipt()
+                .await?
+        },
+    };
+    if !receipt.status() {
+        bail!("{} tx {} reverted", tx.phase, tx.tx_hash);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_validator_weight() {
+        let validator = Address::repeat_byte(1);
+        assert_eq!(
+            format!("{validator}:3").parse::<ValidatorWeight>().unwrap(),
+            ValidatorWeight {
+                validator,
+                weight: 3
+            }
+        );
+        assert_eq!(
+            validator.to_string().parse::<ValidatorWeight>().unwrap(),
+            ValidatorWeight {
+                validator,
+                weight: 1
+            }
+        );
+        assert!(format!("{validator}:0").parse::<ValidatorWeight>().is_err());
+        assert!(format!("{validator}:x").parse::<ValidatorWeight>().is_err());
+        assert!("0x12:1".parse::<ValidatorWeight>().is_err());
+    }
+
+    #[test]
+    fn test_split_rewards() {
+        let a = Address::repeat_byte(1);
+        let b = Address::repeat_byte(2);
+        let split = [
+            ValidatorWeight {
+                validator: a,
+                weight: 2,
+            },
+            ValidatorWeight {
+                validator: b,
+                weight: 1,
+            },
+        ];
+        assert_eq!(
+            split_rewards(U256::from(10), &split),
+            vec![(a, U256::from(7)), (b, U256::from(3))]
+        );
+        // Shares that round down to zero are dropped.
+        assert_eq!(
+            split_rewards(U256::from(1), &split),
+            vec![(a, U256::from(1))]
+        );
+        assert!(split_rewards(U256::ZERO, &split).is_empty());
+    }
+}
@@ -655,7 +655,10 @@ impl<P: Provider + Clone> StakingTransactions<P> {
                                 .with_call(&call)
                         },
                         // Delegate and Undelegate are not included in funding/approval phase
-                        TxPhase::Delegate | TxPhase::Undelegate => unreachable!(),
+                        TxPhase::Delegate
+                        | TxPhase::Undelegate
+                        | TxPhase::Broadcast
+                        | TxPhase::ClaimRewards => unreachable!(),
                     }
                 },
             )
@@ -724,7 +727,9 @@ impl<P: Provider + Clone> StakingTransactions<P> {
                         TxPhase::FundEth
                         | TxPhase::FundEsp
                         | TxPhase::Approve
-                        | TxPhase::Undelegate => {
+                        | TxPhase::Undelegate
+                        | TxPhase::Broadcast
+                        | TxPhase::ClaimRewards => {
                             unreachable!()
                         },
                     }
@@ -1592,7 +1597,9 @@ pub(crate) async fn delegate_for_demo(
                             .with_to(stake_table_address)
                             .with_call(&call)
                     },
-                    TxPhase::Undelegate => unreachable!(),
+                    TxPhase::Undelegate | TxPhase::Broadcast | TxPhase::ClaimRewards => {
+                        unreachable!()
+                    },
                 },
             )
             .await?;
@@ -1935,7 +1942,12 @@ pub(crate) async fn churn_for_demo(config: &Config, params: ChurnParams) -> Resu
                     .with_to(token_address)
                     .with_call(&call)
             },
-            TxPhase::Delegate | TxPhase::Undelegate => unreachable!(),
+            TxPhase::Delegate
+            | TxPhase::Undelegate
+            | TxPhase::Broadcast
+            | TxPhase::ClaimRewards => {
+                unreachable!()
+            },
         },
     )
     .await?;
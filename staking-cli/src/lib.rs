@@ -1,3 +1,5 @@
+use std::{path::PathBuf, time::Duration};
+
 use alloy::{
     eips::BlockId,
     network::EthereumWallet,
@@ -5,9 +7,10 @@ use alloy::{
     signers::local::{MnemonicBuilder, PrivateKeySigner, coins_bip39::English},
 };
 use anyhow::Result;
-use clap::{ArgAction, Parser, Subcommand};
+use clap::{ArgAction, ArgGroup, Parser, Subcommand};
 use clap_serde_derive::ClapSerde;
 use espresso_contract_deployer::provider::connect_ledger;
+use espresso_types::parse_duration;
 use espresso_utils::logging;
 pub(crate) use hotshot_types::{
     addr::NetAddr,
@@ -18,12 +21,13 @@ pub(crate) use hotshot_types::{
 pub(crate) use jf_signature::bls_over_bn254::KeyPair as BLSKeyPair;
 use metadata::MetadataUriArgs;
 use serde::{Deserialize, Serialize};
-use signature::OutputArgs;
+use signature::{OutputArgs, ReportArgs};
 use thiserror::Error;
 use url::Url;
 
 pub(crate) mod claim;
 mod cli;
+pub(crate) mod compound;
 pub(crate) mod concurrent;
 pub(crate) mod delegation;
 /// Used by sequencer, espresso-dev-node, staking-ui-service tests.
@@ -33,12 +37,15 @@ pub(crate) mod l1;
 pub(crate) mod metadata;
 // TODO: Replace with imports from staking-ui-service once version compatibility is resolved
 pub(crate) mod metadata_types;
+pub(crate) mod offline;
 // TODO: Replace with imports from staking-ui-service once version compatibility is resolved
 pub(crate) mod openmetrics;
 pub(crate) mod output;
 pub(crate) mod parse;
+pub(crate) mod portfolio;
 pub(crate) mod receipt;
 pub(crate) mod registration;
+pub(crate) mod safe;
 pub(crate) mod signature;
 pub(crate) mod transaction;
 pub(crate) mod tx_log;
@@ -82,6 +89,11 @@ pub const DEMO_VALIDATOR_START_INDEX: u32 = 20;
 /// CLI to interact with the Espresso stake table contract.
 #[derive(ClapSerde, Clone, Debug, Deserialize, Serialize)]
 #[command(version, long_version = espresso_utils::build_info!().clap_version(), about, long_about = None)]
+#[command(group(
+    ArgGroup::new("export")
+        .args(["export_calldata", "export_unsigned", "safe_address"])
+        .multiple(true)
+))]
 pub(crate) struct Config {
     /// L1 Ethereum RPC.
     #[clap(long, env = "L1_PROVIDER")]
@@ -117,13 +129,52 @@ pub(crate) struct Config {
     #[serde(skip)]
     pub export_calldata: bool,
 
-    /// Sender address for calldata export (required for simulation).
+    /// Gnosis Safe to act as.
+    ///
+    /// Exports calldata with the Safe as sender instead of sending a transaction. Use `--format
+    /// safe-proposal` to write a Safe transaction proposal instead of a Transaction Builder batch.
+    #[clap(
+        long,
+        env = "SAFE_ADDRESS",
+        conflicts_with_all = ["mnemonic", "private_key", "ledger", "export_unsigned", "sender_address"]
+    )]
+    #[serde(skip)]
+    pub safe_address: Option<Address>,
+
+    /// Nonce of the Safe proposal.
+    ///
+    /// Defaults to the current nonce of the Safe. Set it to queue several proposals.
+    #[clap(long, env = "SAFE_NONCE", requires = "safe_address")]
+    #[serde(skip)]
+    pub safe_nonce: Option<u64>,
+
+    /// Export an unsigned transaction for offline signing with the `sign` command instead of
+    /// sending it.
+    #[clap(
+        long,
+        env = "EXPORT_UNSIGNED",
+        action = ArgAction::SetTrue,
+        conflicts_with_all = ["mnemonic", "private_key", "ledger", "export_calldata"],
+        requires = "sender_address"
+    )]
+    #[serde(skip)]
+    pub export_unsigned: bool,
+
+    /// Sender address for calldata export (required for simulation) or unsigned export.
     #[clap(long, env = "SENDER_ADDRESS")]
     #[serde(skip)]
     pub sender_address: Option<Address>,
 
-    /// Skip eth_call validation when exporting calldata.
-    #[clap(long, env = "SKIP_SIMULATION", action = ArgAction::SetTrue, requires = "export_calldata")]
+    /// Nonce of the exported unsigned transaction.
+    ///
+    /// Defaults to the current nonce of the sender. Set it when exporting several transactions
+    /// that are broadcast in sequence.
+    #[clap(long, env = "NONCE", requires = "export_unsigned")]
+    #[serde(skip)]
+    pub nonce: Option<u64>,
+
+    /// Skip eth_call validation when exporting calldata or unsigned transactions.
+    #[clap(long, env = "SKIP_SIMULATION", action = ArgAction::SetTrue, requires = "export")]
     #[serde(skip)]
     pub skip_simulation: bool,
 
@@ -273,14 +324,30 @@ impl Commands {
         matches!(
             self,
             Commands::Approve { .. }
+                | Commands::CompoundRewards { .. }
                 | Commands::Transfer { .. }
                 | Commands::TokenBalance { .. }
                 | Commands::TokenAllowance { .. }
+                | Commands::Portfolio { .. }
         )
     }
 }
 
 impl Config {
+    /// Whether the transaction is exported instead of signed and sent by this CLI.
+    pub(crate) fn exports_transaction(&self) -> bool {
+        self.export_calldata || self.export_unsigned
+    }
+
+    /// Acting as a Safe means exporting calldata with the Safe as sender.
+    pub(crate) fn apply_safe_address(mut self) -> Self {
+        if let Some(safe_address) = self.safe_address {
+            self.export_calldata = true;
+            self.sender_address = Some(safe_address);
+        }
+        self
+    }
+
     pub fn apply_env_var_overrides(self) -> Result<Self> {
         let mut config = self.clone();
         if self.stake_table_address == Address::ZERO {
@@ -447,6 +514,32 @@ pub(crate) enum Commands {
     },
     /// Claim staking rewards.
     ClaimRewards {},
+    /// Periodically claim staking rewards and delegate them.
+    ///
+    /// Runs until interrupted. Every transaction is recorded in a transaction log so that a
+    /// restarted process completes an interrupted round instead of repeating it.
+    CompoundRewards {
+        /// Validator to delegate rewards to, as `ADDRESS[:WEIGHT]`. Repeat to split rewards
+        /// between several validators proportionally to their weights (default weight 1).
+        #[clap(long = "validator", required = true)]
+        validators: Vec<compound::ValidatorWeight>,
+
+        /// Minimum amount of unclaimed rewards (in ESP) to claim and delegate.
+        #[clap(long, value_parser = parse_ether, default_value = "1")]
+        threshold: U256,
+
+        /// How often to check the unclaimed rewards.
+        #[clap(long, value_parser = parse_duration, default_value = "1h")]
+        interval: Duration,
+
+        /// Transaction log of the current round.
+        #[clap(long, default_value_os_t = tx_log::default_compound_log_path())]
+        tx_log: PathBuf,
+
+        /// Run a single round and exit.
+        #[clap(long)]
+        once: bool,
+    },
     /// Check unclaimed staking rewards.
     UnclaimedRewards {
         /// The address to check.
@@ -465,6 +558,25 @@ pub(crate) enum Commands {
         #[clap(long)]
         owner: Option<Address>,
     },
+    /// Show the full staking position of an address.
+    ///
+    /// Lists delegations, pending undelegations, pending validator exit claims, unclaimed rewards
+    /// and the ESP token balance and allowance.
+    Portfolio {
+        /// The address to report on.
+        #[clap(long)]
+        address: Option<Address>,
+
+        /// Only look for delegations made at or after this L1 block.
+        ///
+        /// Defaults to the block the stake table was initialized at. Validators the address
+        /// delegated to before this block are not included in the report.
+        #[clap(long)]
+        from_block: Option<u64>,
+
+        #[clap(flatten)]
+        report_args: ReportArgs,
+    },
     /// Transfer ESP tokens
     Transfer {
         /// The address to transfer to.
@@ -475,6 +587,28 @@ pub(crate) enum Commands {
         #[clap(long, value_parser = parse_ether)]
         amount: U256,
     },
+    /// Sign a transaction exported with `--export-unsigned`.
+    ///
+    /// Does not access the network, so it can be run on an air-gapped machine.
+    Sign {
+        /// Path to the unsigned transaction file.
+        #[clap(long)]
+        input: PathBuf,
+
+        /// Output file path. If not specified, outputs to stdout.
+        #[clap(long)]
+        output: Option<PathBuf>,
+    },
+    /// Broadcast a transaction signed with the `sign` command.
+    Broadcast {
+        /// Path to the signed transaction file.
+        #[clap(long)]
+        input: PathBuf,
+
+        /// Transaction log to record the broadcast transaction in.
+        #[clap(long, default_value_os_t = tx_log::default_broadcast_log_path())]
+        tx_log: PathBuf,
+    },
     /// Demo commands for testing and development
     Demo(demo::Demo),
     /// [DEPRECATED] Use `demo stake` instead. Register validators and create delegators for demo.
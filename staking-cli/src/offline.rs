@@ -0,0 +1,255 @@
+//! Offline signing workflow.
+//!
+//! State-changing commands run with `--export-unsigned` write an [`UnsignedTx`] with every field
+//! needed for signing (nonce, gas, fees, chain ID) filled in. The `sign` command turns it into a
+//! [`SignedEnvelope`] without any network access, and the `broadcast` command submits the envelope
+//! and records it in the transaction log.
+
+use std::path::Path;
+
+use alloy::{
+    consensus::TxEnvelope,
+    eips::eip2718::Encodable2718,
+    network::{
+        Ethereum, EthereumWallet, NetworkTransactionBuilder as _, NetworkWallet,
+        TransactionBuilder as _,
+    },
+    primitives::{Address, Bytes, TxHash, U256, keccak256},
+    providers::{PendingTransactionBuilder, Provider},
+    rpc::types::{TransactionReceipt, TransactionRequest},
+};
+use anyhow::{Context as _, Result, bail, ensure};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::{
+    output::output_text,
+    transaction::Transaction,
+    tx_log::{DEFAULT_GAS_LIMIT, SignedTx, TxLog, TxPhase, submit_with_retry},
+};
+
+/// Safety margin on top of the estimated gas, the chain state may change between export and
+/// broadcast.
+const GAS_ESTIMATE_BUFFER_PERCENT: u64 = 20;
+
+/// A transaction request ready to be signed offline.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnsignedTx {
+    pub description: String,
+    /// Amount of ESP moved or approved by the transaction, zero if none.
+    pub amount: U256,
+    pub request: TransactionRequest,
+}
+
+/// A signed transaction ready to be broadcast.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedEnvelope {
+    pub description: String,
+    pub from: Address,
+    pub to: Address,
+    pub amount: U256,
+    pub chain_id: u64,
+    pub nonce: u64,
+    pub tx_hash: TxHash,
+    pub signed_bytes: Bytes,
+}
+
+/// Build an [`UnsignedTx`] for `from`.
+///
+/// The nonce defaults to the current nonce of `from`. It has to be set explicitly when exporting
+/// several transactions that are broadcast in sequence, e.g. an approval followed by a delegation.
+/// If `simulate` is false the transaction is not validated and a fixed gas limit is used, because
+/// gas estimation would fail for transactions that depend on earlier ones that are not yet
+/// broadcast.
+pub async fn prepare_unsigned(
+    provider: &impl Provider,
+    tx: &Transaction,
+    from: Address,
+    nonce: Option<u64>,
+    simulate: bool,
+) -> Result<UnsignedTx> {
+    let request = tx.to_transaction_request()?.from(from);
+    let chain_id = provider.get_chain_id().await?;
+    let nonce = match nonce {
+        Some(nonce) => nonce,
+        None => provider.get_transaction_count(from).await?,
+    };
+    let gas_limit = if simulate {
+        tx.simulate(provider, from).await?;
+        let estimate = provider.estimate_gas(request.clone()).await?;
+        estimate + estimate * GAS_ESTIMATE_BUFFER_PERCENT / 100
+    } else {
+        DEFAULT_GAS_LIMIT
+    };
+    let fees = provider.estimate_eip1559_fees().await?;
+
+    let request = request
+        .with_nonce(nonce)
+        .with_chain_id(chain_id)
+        .with_gas_limit(gas_limit)
+        .with_max_fee_per_gas(fees.max_fee_per_gas)
+        .with_max_priority_fee_per_gas(fees.max_priority_fee_per_gas);
+
+    Ok(UnsignedTx {
+        description: tx.description(),
+        amount: tx.amount(),
+        request,
+    })
+}
+
+/// Sign an [`UnsignedTx`] with `wallet`. Does not access the network.
+pub async fn sign_unsigned(
+    wallet: &EthereumWallet,
+    unsigned: UnsignedTx,
+) -> Result<SignedEnvelope> {
+    let UnsignedTx {
+        description,
+        amount,
+        request,
+    } = unsigned;
+
+    let signer = NetworkWallet::<Ethereum>::default_signer_address(wallet);
+    let from = request.from.context("unsigned transaction has no sender")?;
+    if from != signer {
+        bail!("transaction must be signed by {from} but the configured signer is {signer}");
+    }
+    let to = request
+        .to
+        .and_then(|kind| kind.to().copied())
+        .context("unsigned transaction has no recipient")?;
+    let nonce = request.nonce.context("unsigned transaction has no nonce")?;
+    let chain_id = request
+        .chain_id
+        .context("unsigned transaction has no chain ID")?;
+
+    tracing::info!("Signing: {description}");
+    let signed: TxEnvelope = request.build(wallet).await?;
+    Ok(SignedEnvelope {
+        description,
+        from,
+        to,
+        amount,
+        chain_id,
+        nonce,
+        tx_hash: *signed.tx_hash(),
+        signed_bytes: signed.encoded_2718().into(),
+    })
+}
+
+/// Submit a [`SignedEnvelope`], record it in the transaction log at `log_path` and wait for the
+/// receipt.
+pub async fn broadcast(
+    provider: &impl Provider,
+    envelope: &SignedEnvelope,
+    log_path: &Path,
+) -> Result<TransactionReceipt> {
+    ensure!(
+        keccak256(&envelope.signed_bytes) == envelope.tx_hash,
+        "signed transaction bytes do not match transaction hash {}",
+        envelope.tx_hash
+    );
+    let chain_id = provider.get_chain_id().await?;
+    ensure!(
+        chain_id == envelope.chain_id,
+        "transaction was signed for chain {} but the RPC is connected to chain {chain_id}",
+        envelope.chain_id
+    );
+
+    tracing::info!("Broadcasting: {}", envelope.description);
+    let tx_hash = submit_with_retry(provider, &envelope.signed_bytes, envelope.tx_hash).await?;
+
+    let mut log = TxLog::load(log_path)?.unwrap_or_else(|| TxLog::new(vec![]));
+    if !log.transactions.iter().any(|tx| tx.tx_hash == tx_hash) {
+        log.transactions.push(SignedTx {
+            phase: TxPhase::Broadcast,
+            from: envelope.from,
+            to: envelope.to,
+            amount: envelope.amount,
+            delegator_index: None,
+            tx_hash,
+            signed_bytes: envelope.signed_bytes.clone(),
+            nonce: envelope.nonce,
+        });
+        log.save(log_path)?;
+    }
+
+    Ok(
+        PendingTransactionBuilder::new(provider.root().clone(), tx_hash)
+            .get_receipt()
+            .await?,
+    )
+}
+
+pub fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    serde_json::from_str(&contents).with_context(|| format!("failed to parse {}", path.display()))
+}
+
+pub fn output_json(value: &impl Serialize, path: Option<&Path>, what: &str) -> Result<()> {
+    output_text(&serde_json::to_string_pretty(value)?, path, what)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        consensus::Transaction as _, eips::eip2718::Decodable2718, signers::local::PrivateKeySigner,
+    };
+
+    use super::*;
+    use crate::DEV_PRIVATE_KEY;
+
+    fn unsigned(from: Address) -> UnsignedTx {
+        let request = Transaction::Transfer {
+            token: Address::repeat_byte(1),
+            to: Address::repeat_byte(2),
+            amount: U256::from(3),
+        }
+        .to_transaction_request()
+        .unwrap()
+        .from(from)
+        .with_nonce(7)
+        .with_chain_id(1337)
+        .with_gas_limit(100_000)
+        .with_max_fee_per_gas(2_000_000_000)
+        .with_max_priority_fee_per_gas(1_000_000_000);
+        UnsignedTx {
+            description: "transfer".to_string(),
+            amount: U256::from(3),
+            request,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sign_unsigned() {
+        let signer: PrivateKeySigner = DEV_PRIVATE_KEY.parse().unwrap();
+        let from = signer.address();
+        let wallet = EthereumWallet::from(signer);
+
+        // Round trip through JSON like the CLI does.
+        let json = serde_json::to_string(&unsigned(from)).unwrap();
+        let signed = sign_unsigned(&wallet, serde_json::from_str(&json).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(signed.from, from);
+        assert_eq!(signed.to, Address::repeat_byte(1));
+        assert_eq!(signed.nonce, 7);
+        assert_eq!(signed.chain_id, 1337);
+        assert_eq!(keccak256(&signed.signed_bytes), signed.tx_hash);
+
+        let envelope = TxEnvelope::decode_2718(&mut signed.signed_bytes.as_ref()).unwrap();
+        assert_eq!(envelope.nonce(), 7);
+        assert_eq!(envelope.gas_limit(), 100_000);
+        assert_eq!(envelope.chain_id(), Some(1337));
+    }
+
+    #[tokio::test]
+    async fn test_sign_unsigned_wrong_signer() {
+        let signer: PrivateKeySigner = DEV_PRIVATE_KEY.parse().unwrap();
+        let wallet = EthereumWallet::from(signer);
+        let err = sign_unsigned(&wallet, unsigned(Address::repeat_byte(9)))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("configured signer"));
+    }
+}
@@ -1,3 +1,5 @@
+use std::path::Path;
+
 use alloy::primitives::{U256, utils::format_ether};
 use anyhow::Result;
 pub(crate) use espresso_safe_tx_builder::CalldataInfo;
@@ -46,6 +48,9 @@ pub(crate) fn output_calldata(
         SerializationFormat::Safe => {
             output_safe_tx_builder(info, output.output.as_deref(), chain_id)?;
         },
+        SerializationFormat::SafeProposal => {
+            anyhow::bail!("--format safe-proposal requires --safe-address")
+        },
         SerializationFormat::Json | SerializationFormat::Toml => {
             // CalldataInfo derives Serialize with function_info skipped,
             // producing the legacy {to, data, value} format.
@@ -64,3 +69,18 @@ pub(crate) fn output_calldata(
 
     Ok(())
 }
+
+/// Write `text` to `path`, or to stdout if no path is given. `what` names the content in the
+/// confirmation message.
+///
+/// The text is data for other tools to consume, so it goes to stdout as is, even if logs are
+/// formatted as JSON.
+pub(crate) fn output_text(text: &str, path: Option<&Path>, what: &str) -> Result<()> {
+    if let Some(path) = path {
+        std::fs::write(path, text)?;
+        output_success(format!("{what} written to {}", path.display()));
+    } else {
+        println!("{text}");
+    }
+    Ok(())
+}
@@ -0,0 +1,389 @@
+//! Report of the full staking position of an account.
+//!
+//! Delegations, pending undelegations and pending validator exit claims are read from the stake
+//! table contract for every validator the account has delegated to. The validators are found via
+//! the `Delegated` events emitted by the stake table, so validators that have since exited are
+//! included as well. The events are scanned from the block the stake table was initialized at,
+//! unless a later start block is given.
+
+use std::{collections::BTreeSet, fmt::Write as _};
+
+use alloy::{
+    primitives::{Address, U256},
+    providers::Provider,
+};
+use anyhow::{Context as _, Result};
+use espresso_types::{
+    L1Client,
+    v0_3::{Fetcher, StakeTableEvent},
+};
+use hotshot_contract_adapter::sol_types::{EspToken, StakeTableV3};
+use serde::{Serialize, Serializer};
+use url::Url;
+
+use crate::{
+    claim::unclaimed_rewards,
+    output::{format_esp, output_warn},
+    signature::ReportFormat,
+};
+
+/// Amounts are serialized as decimal strings (in wei) to keep JSON and CSV output readable.
+fn serialize_amount<S: Serializer>(amount: &U256, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&amount.to_string())
+}
+
+fn serialize_optional_amount<S: Serializer>(
+    amount: &Option<U256>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match amount {
+        Some(amount) => serialize_amount(amount, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Delegation {
+    pub validator: Address,
+    #[serde(serialize_with = "serialize_amount")]
+    pub amount: U256,
+}
+
+/// Funds that are locked in the stake table until `unlocks_at`, either after an undelegation or
+/// after the validator exited.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct PendingWithdrawal {
+    pub validator: Address,
+    #[serde(serialize_with = "serialize_amount")]
+    pub amount: U256,
+    /// Unix timestamp (seconds) after which the funds can be claimed.
+    pub unlocks_at: u64,
+    pub claimable: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Portfolio {
+    pub address: Address,
+    /// L1 block the report was generated at.
+    pub l1_block: u64,
+    #[serde(serialize_with = "serialize_amount")]
+    pub token_balance: U256,
+    #[serde(serialize_with = "serialize_amount")]
+    pub token_allowance: U256,
+    /// `None` if no Espresso API URL was configured or the rewards could not be fetched.
+    #[serde(serialize_with = "serialize_optional_amount")]
+    pub unclaimed_rewards: Option<U256>,
+    pub delegations: Vec<Delegation>,
+    pub undelegations: Vec<PendingWithdrawal>,
+    pub validator_exits: Vec<PendingWithdrawal>,
+}
+
+/// A single row of the CSV report.
+#[derive(Serialize)]
+struct CsvRow<'a> {
+    kind: &'a str,
+    validator: Option<Address>,
+    amount: String,
+    unlocks_at: Option<u64>,
+    claimable: Option<bool>,
+}
+
+pub async fn fetch_portfolio(
+    provider: impl Provider,
+    l1_url: Url,
+    stake_table_address: Address,
+    token_address: Address,
+    espresso_url: Option<Url>,
+    address: Address,
+    from_block: Option<u64>,
+) -> Result<Portfolio> {
+    let block = provider
+        .get_block(alloy::eips::BlockId::latest())
+        .await?
+        .context("latest L1 block not found")?;
+    let l1_block = block.header.number;
+    let now = block.header.timestamp;
+
+    let l1 = L1Client::new(vec![l1_url])?;
+    let events =
+        Fetcher::fetch_events_from_contract(l1, stake_table_address, from_block, l1_block).await?;
+    let validators: BTreeSet<Address> = events
+        .into_iter()
+        .filter_map(|(_, event)| match event {
+            StakeTableEvent::Delegate(delegated) if delegated.delegator == address => {
+                Some(delegated.validator)
+            },
+            _ => None,
+        })
+        .collect();
+
+    let stake_table = StakeTableV3::new(stake_table_address, &provider);
+    let mut delegations = Vec::new();
+    let mut undelegations = Vec::new();
+    let mut validator_exits = Vec::new();
+    for validator in validators {
+        let amount = stake_table.delegations(validator, address).call().await?;
+        let exit_unlocks_at = stake_table.validatorExits(validator).call().await?;
+        if !amount.is_zero() {
+            if exit_unlocks_at.is_zero() {
+                delegations.push(Delegation { validator, amount });
+            } else {
+                // Delegations to exited validators can only be withdrawn via
+                // `claimValidatorExit`.
+                let unlocks_at = exit_unlocks_at.saturating_to();
+                validator_exits.push(PendingWithdrawal {
+                    validator,
+                    amount,
+                    unlocks_at,
+                    claimable: unlocks_at <= now,
+                });
+            }
+        }
+
+        let undelegation = stake_table.undelegations(validator, address).call().await?;
+        if !undelegation.amount.is_zero() {
+            let unlocks_at = undelegation.unlocksAt.saturating_to();
+            undelegations.push(PendingWithdrawal {
+                validator,
+                amount: undelegation.amount,
+                unlocks_at,
+                claimable: unlocks_at <= now,
+            });
+        }
+    }
+
+    let token = EspToken::new(token_address, &provider);
+    let token_balance = token.balanceOf(address).call().await?;
+    let token_allowance = token.allowance(address, stake_table_address).call().await?;
+
+    let unclaimed_rewards = match espresso_url {
+        Some(espresso_url) => {
+            match unclaimed_rewards(&provider, stake_table_address, espresso_url, address).await {
+                Ok(unclaimed) => Some(unclaimed),
+                Err(err) => {
+                    output_warn(format!("Failed to check unclaimed rewards: {err:#}"));
+                    None
+                },
+            }
+        },
+        None => {
+            output_warn("Skipping unclaimed rewards, use --espresso-url or ESPRESSO_URL");
+            None
+        },
+    };
+
+    Ok(Portfolio {
+        address,
+        l1_block,
+        token_balance,
+        token_allowance,
+        unclaimed_rewards,
+        delegations,
+        undelegations,
+        validator_exits,
+    })
+}
+
+impl Portfolio {
+    pub fn total_delegated(&self) -> U256 {
+        self.delegations.iter().map(|d| d.amount).sum()
+    }
+
+    pub fn render(&self, format: ReportFormat) -> Result<String> {
+        match format {
+            ReportFormat::Table => Ok(self.to_table()),
+            ReportFormat::Json => Ok(serde_json::to_string_pretty(self)?),
+            ReportFormat::Csv => self.to_csv(),
+        }
+    }
+
+    fn to_table(&self) -> String {
+        let withdrawal_status = |w: &PendingWithdrawal| {
+            if w.claimable {
+                "claimable".to_string()
+            } else {
+                format!("unlocks at {}", w.unlocks_at)
+            }
+        };
+
+        let mut out = String::new();
+        // Writing to a String never fails.
+        let _ = writeln!(
+            out,
+            "Portfolio for {} at L1 block {}",
+            self.address, self.l1_block
+        );
+        let _ = writeln!(out, "Token balance: {}", format_esp(self.token_balance));
+        let _ = writeln!(
+            out,
+            "Stake table token allowance: {}",
+            format_esp(self.token_allowance)
+        );
+        let unclaimed = self
+            .unclaimed_rewards
+            .map(format_esp)
+            .unwrap_or_else(|| "unknown".to_string());
+        let _ = writeln!(out, "Unclaimed rewards: {unclaimed}");
+
+        let _ = writeln!(
+            out,
+            "Delegations (total {}):",
+            format_esp(self.total_delegated())
+        );
+        if self.delegations.is_empty() {
+            let _ = writeln!(out, " - None");
+        }
+        for d in &self.delegations {
+            let _ = writeln!(
+                out,
+                " - Validator {}: {}",
+                d.validator,
+                format_esp(d.amount)
+            );
+        }
+
+        let _ = writeln!(out, "Pending undelegations:");
+        if self.undelegations.is_empty() {
+            let _ = writeln!(out, " - None");
+        }
+        for w in &self.undelegations {
+            let _ = writeln!(
+                out,
+                " - Validator {}: {} ({})",
+                w.validator,
+                format_esp(w.amount),
+                withdrawal_status(w)
+            );
+        }
+
+        let _ = writeln!(out, "Pending validator exit claims:");
+        if self.validator_exits.is_empty() {
+            let _ = writeln!(out, " - None");
+        }
+        for w in &self.validator_exits {
+            let _ = writeln!(
+                out,
+                " - Validator {}: {} ({})",
+                w.validator,
+                format_esp(w.amount),
+                withdrawal_status(w)
+            );
+        }
+        out.trim_end().to_string()
+    }
+
+    fn to_csv(&self) -> Result<String> {
+        let mut writer = csv::Writer::from_writer(vec![]);
+        let account_row = |kind, amount: U256| CsvRow {
+            kind,
+            validator: None,
+            amount: amount.to_string(),
+            unlocks_at: None,
+            claimable: None,
+        };
+        writer.serialize(account_row("token_balance", self.token_balance))?;
+        writer.serialize(account_row("token_allowance", self.token_allowance))?;
+        if let Some(unclaimed) = self.unclaimed_rewards {
+            writer.serialize(account_row("unclaimed_rewards", unclaimed))?;
+        }
+        for d in &self.delegations {
+            writer.serialize(CsvRow {
+                kind: "delegation",
+                validator: Some(d.validator),
+                amount: d.amount.to_string(),
+                unlocks_at: None,
+                claimable: None,
+            })?;
+        }
+        let withdrawals = self
+            .undelegations
+            .iter()
+            .map(|w| ("undelegation", w))
+            .chain(self.validator_exits.iter().map(|w| ("validator_exit", w)));
+        for (kind, w) in withdrawals {
+            writer.serialize(CsvRow {
+                kind,
+                validator: Some(w.validator),
+                amount: w.amount.to_string(),
+                unlocks_at: Some(w.unlocks_at),
+                claimable: Some(w.claimable),
+            })?;
+        }
+        Ok(String::from_utf8(writer.into_inner()?)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::utils::parse_ether;
+
+    use super::*;
+
+    fn portfolio() -> Portfolio {
+        Portfolio {
+            address: Address::repeat_byte(1),
+            l1_block: 42,
+            token_balance: parse_ether("10").unwrap(),
+            token_allowance: parse_ether("2.5").unwrap(),
+            unclaimed_rewards: Some(parse_ether("0.1").unwrap()),
+            delegations: vec![Delegation {
+                validator: Address::repeat_byte(2),
+                amount: parse_ether("3").unwrap(),
+            }],
+            undelegations: vec![PendingWithdrawal {
+                validator: Address::repeat_byte(2),
+                amount: parse_ether("1").unwrap(),
+                unlocks_at: 1000,
+                claimable: false,
+            }],
+            validator_exits: vec![PendingWithdrawal {
+                validator: Address::repeat_byte(3),
+                amount: parse_ether("4").unwrap(),
+                unlocks_at: 500,
+                claimable: true,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_portfolio_table() {
+        let table = portfolio().render(ReportFormat::Table).unwrap();
+        assert!(table.contains("Token balance: 10 ESP"));
+        assert!(table.contains("Unclaimed rewards: 0.1 ESP"));
+        assert!(table.contains("Delegations (total 3 ESP):"));
+        assert!(table.contains(&format!(
+            " - Validator {}: 1 ESP (unlocks at 1000)",
+            Address::repeat_byte(2)
+        )));
+        assert!(table.contains(&format!(
+            " - Validator {}: 4 ESP (claimable)",
+            Address::repeat_byte(3)
+        )));
+    }
+
+    #[test]
+    fn test_portfolio_json() {
+        let json: serde_json::Value =
+            serde_json::from_str(&portfolio().render(ReportFormat::Json).unwrap()).unwrap();
+        assert_eq!(json["token_balance"], "10000000000000000000");
+        assert_eq!(json["delegations"][0]["amount"], "3000000000000000000");
+        assert_eq!(json["undelegations"][0]["unlocks_at"], 1000);
+        assert_eq!(json["validator_exits"][0]["claimable"], true);
+    }
+
+    #[test]
+    fn test_portfolio_csv() {
+        let csv = portfolio().render(ReportFormat::Csv).unwrap();
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines[0], "kind,validator,amount,unlocks_at,claimable");
+        assert_eq!(lines.len(), 7);
+        assert_eq!(lines[1], "token_balance,,10000000000000000000,,");
+        assert_eq!(
+            lines[6],
+            format!(
+                "validator_exit,{},4000000000000000000,500,true",
+                Address::repeat_byte(3)
+            )
+        );
+    }
+}
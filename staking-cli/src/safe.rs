@@ -0,0 +1,147 @@
+//! Staking operations on behalf of a Gnosis Safe.
+//!
+//! With `--safe-address` the Safe is the sender of the exported calldata. The output is either a
+//! Safe Transaction Builder batch or a proposal file with the fields of the Safe transaction and
+//! its EIP-712 hash, ready to be signed by the Safe owners.
+
+use alloy::{
+    primitives::{Address, B256, Bytes, U256},
+    providers::Provider,
+    sol,
+};
+use anyhow::{Context as _, Result};
+use espresso_contract_deployer::proposals::safe_hash::safe_tx_hashes;
+use serde::Serialize;
+
+use crate::output::CalldataInfo;
+
+sol! {
+    #[sol(rpc)]
+    interface ISafe {
+        function nonce() external view returns (uint256);
+        function getThreshold() external view returns (uint256);
+    }
+}
+
+/// Safe transaction proposal.
+///
+/// Field names match the Safe Transaction Service API so the proposal can be submitted together
+/// with an owner signature of `contractTransactionHash`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SafeProposal {
+    pub safe: Address,
+    pub chain_id: u64,
+    pub description: String,
+    pub to: Address,
+    pub value: String,
+    pub data: Bytes,
+    /// Always a call (0), staking operations never use delegatecall.
+    pub operation: u8,
+    pub safe_tx_gas: String,
+    pub base_gas: String,
+    pub gas_price: String,
+    pub gas_token: Address,
+    pub refund_receiver: Address,
+    pub nonce: u64,
+    /// Number of owner signatures required to execute the transaction.
+    pub threshold: u64,
+    /// EIP-712 hash of the Safe transaction, the message the owners sign.
+    pub contract_transaction_hash: B256,
+}
+
+/// Check that `safe` is a deployed Safe and return its signature threshold.
+pub async fn fetch_safe_threshold(provider: &impl Provider, safe: Address) -> Result<u64> {
+    let threshold = ISafe::new(safe, provider)
+        .getThreshold()
+        .call()
+        .await
+        .with_context(|| format!("{safe} does not look like a Safe: getThreshold() failed"))?;
+    threshold.try_into().context("Safe threshold overflows u64")
+}
+
+/// Build a [`SafeProposal`] for `info`.
+///
+/// Uses the current nonce of the Safe unless `nonce` is set. Set it to queue several proposals
+/// that are executed in sequence.
+pub async fn safe_proposal(
+    provider: &impl Provider,
+    safe: Address,
+    chain_id: u64,
+    nonce: Option<u64>,
+    info: &CalldataInfo,
+) -> Result<SafeProposal> {
+    let threshold = fetch_safe_threshold(provider, safe).await?;
+    let nonce = match nonce {
+        Some(nonce) => nonce,
+        None => ISafe::new(safe, provider)
+            .nonce()
+            .call()
+            .await
+            .with_context(|| format!("failed to query nonce() on Safe {safe}"))?
+            .try_into()
+            .context("Safe nonce overflows u64")?,
+    };
+    Ok(build_proposal(safe, chain_id, nonce, threshold, info))
+}
+
+fn build_proposal(
+    safe: Address,
+    chain_id: u64,
+    nonce: u64,
+    threshold: u64,
+    info: &CalldataInfo,
+) -> SafeProposal {
+    let operation = 0;
+    let hashes = safe_tx_hashes(
+        safe, chain_id, info.to, info.value, &info.data, operation, nonce,
+    );
+    SafeProposal {
+        safe,
+        chain_id,
+        description: info.description.clone(),
+        to: info.to,
+        value: info.value.to_string(),
+        data: info.data.clone(),
+        operation,
+        safe_tx_gas: U256::ZERO.to_string(),
+        base_gas: U256::ZERO.to_string(),
+        gas_price: U256::ZERO.to_string(),
+        gas_token: Address::ZERO,
+        refund_receiver: Address::ZERO,
+        nonce,
+        threshold,
+        contract_transaction_hash: hashes.safe_tx,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_proposal() {
+        let safe = Address::repeat_byte(0xaa);
+        let info = CalldataInfo::new(Address::repeat_byte(0xbb), Bytes::from(vec![0xca, 0xfe]))
+            .with_description("Delegate".to_string());
+        let proposal = build_proposal(safe, 1, 5, 2, &info);
+
+        assert_eq!(proposal.nonce, 5);
+        assert_eq!(proposal.threshold, 2);
+        assert_eq!(
+            proposal.contract_transaction_hash,
+            safe_tx_hashes(safe, 1, info.to, U256::ZERO, &info.data, 0, 5).safe_tx
+        );
+        // The hash commits to the nonce.
+        assert_ne!(
+            proposal.contract_transaction_hash,
+            build_proposal(safe, 1, 6, 2, &info).contract_transaction_hash
+        );
+
+        let json = serde_json::to_value(&proposal).unwrap();
+        assert_eq!(json["safeTxGas"], "0");
+        assert_eq!(json["data"], "0xcafe");
+        assert_eq!(json["refundReceiver"], Address::ZERO.to_string());
+        assert!(json.get("contractTransactionHash").is_some());
+    }
+}
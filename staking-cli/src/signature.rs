@@ -54,6 +54,9 @@ pub enum NodeSignatureInput {
 pub enum SerializationFormat {
     #[value(name = "safe")]
     Safe,
+    /// Safe transaction proposal, only valid with `--safe-address`.
+    #[value(name = "safe-proposal")]
+    SafeProposal,
     #[value(name = "json")]
     Json,
     #[value(name = "toml")]
@@ -116,6 +119,30 @@ pub struct OutputArgs {
     pub format: Option<SerializationFormat>,
 }
 
+/// Output formats for read-only reports
+#[derive(Clone, Debug, Copy, Default, clap::ValueEnum, PartialEq, Eq)]
+pub enum ReportFormat {
+    #[default]
+    #[value(name = "table")]
+    Table,
+    #[value(name = "json")]
+    Json,
+    #[value(name = "csv")]
+    Csv,
+}
+
+/// Clap arguments for report output
+#[derive(Args, Clone, Debug, Default)]
+pub struct ReportArgs {
+    /// Output file path. If not specified, outputs to stdout
+    #[clap(long)]
+    pub output: Option<PathBuf>,
+
+    /// Output format
+    #[clap(long, value_enum, default_value_t = ReportFormat::default())]
+    pub format: ReportFormat,
+}
+
 impl TryFrom<&Path> for SerializationFormat {
     type Error = anyhow::Error;
 
@@ -163,7 +190,7 @@ impl NodeSignatures {
         match destination {
             NodeSignatureDestination::Stdout(format) => {
                 let output = match format {
-                    SerializationFormat::Safe => anyhow::bail!(
+                    SerializationFormat::Safe | SerializationFormat::SafeProposal => anyhow::bail!(
                         "Safe format is only valid for calldata export, not node signatures"
                     ),
                     SerializationFormat::Json => serde_json::to_string_pretty(self)?,
@@ -173,7 +200,7 @@ impl NodeSignatures {
             },
             NodeSignatureDestination::File { path, format } => {
                 let output = match format {
-                    SerializationFormat::Safe => anyhow::bail!(
+                    SerializationFormat::Safe | SerializationFormat::SafeProposal => anyhow::bail!(
                         "Safe format is only valid for calldata export, not node signatures"
                     ),
                     SerializationFormat::Json => serde_json::to_string_pretty(self)?,
@@ -243,7 +270,7 @@ impl TryFrom<NodeSignatureSource> for NodeSignatures {
                 std::io::stdin().read_to_string(&mut buffer)?;
 
                 match format {
-                    SerializationFormat::Safe => {
+                    SerializationFormat::Safe | SerializationFormat::SafeProposal => {
                         bail!("Safe format is only valid for calldata export, not node signatures")
                     },
                     SerializationFormat::Json => serde_json::from_str::<Self>(&buffer)
@@ -261,7 +288,7 @@ impl TryFrom<NodeSignatureSource> for NodeSignatures {
                 };
 
                 match format {
-                    SerializationFormat::Safe => {
+                    SerializationFormat::Safe | SerializationFormat::SafeProposal => {
                         bail!("Safe format is only valid for calldata export, not node signatures")
                     },
                     SerializationFormat::Json => serde_json::from_str::<Self>(&content)
@@ -571,7 +598,9 @@ mod tests {
         let parsed: NodeSignatures = match format {
             SerializationFormat::Json => serde_json::from_str(&content)?,
             SerializationFormat::Toml => toml::from_str(&content)?,
-            SerializationFormat::Safe => unreachable!("Safe format not used in this test"),
+            SerializationFormat::Safe | SerializationFormat::SafeProposal => {
+                unreachable!("Safe format not used in this test")
+            },
         };
         assert_eq!(parsed.address, sample_node_signatures.address);
         Ok(())
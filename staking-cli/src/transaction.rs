@@ -410,6 +410,27 @@ impl Transaction {
         })
     }
 
+    /// Amount of ESP moved or approved by this transaction, zero if none.
+    pub fn amount(&self) -> U256 {
+        match self {
+            Self::Approve { amount, .. }
+            | Self::Delegate { amount, .. }
+            | Self::Undelegate { amount, .. }
+            | Self::Transfer { amount, .. } => *amount,
+            Self::ClaimWithdrawal { .. }
+            | Self::ClaimValidatorExit { .. }
+            | Self::ClaimRewards { .. }
+            | Self::RegisterValidator { .. }
+            | Self::UpdateConsensusKeys { .. }
+            | Self::DeregisterValidator { .. }
+            | Self::UpdateCommission { .. }
+            | Self::UpdateMetadataUri { .. }
+            | Self::UpdateNetworkConfig { .. }
+            | Self::UpdateX25519Key { .. }
+            | Self::UpdateP2pAddr { .. } => U256::ZERO,
+        }
+    }
+
     pub fn description(&self) -> String {
         match self {
             Self::Approve {
@@ -469,7 +490,7 @@ impl Transaction {
         }
     }
 
-    fn to_transaction_request(&self) -> Result<TransactionRequest> {
+    pub(crate) fn to_transaction_request(&self) -> Result<TransactionRequest> {
         let (to, data, _) = self.clone().calldata()?;
         Ok(TransactionRequest::default()
             .to(to)
@@ -27,18 +27,34 @@ use tokio::{
 use crate::concurrent::map_concurrent;
 
 pub fn default_tx_log_path() -> std::path::PathBuf {
+    data_file_path("tx_log.json")
+}
+
+/// Log of transactions submitted with the `broadcast` command.
+///
+/// Kept separate from [`default_tx_log_path`] because the demo commands resume from that log.
+pub fn default_broadcast_log_path() -> std::path::PathBuf {
+    data_file_path("broadcast_log.json")
+}
+
+/// Log of the current round of the `compound-rewards` command.
+pub fn default_compound_log_path() -> std::path::PathBuf {
+    data_file_path("compound_log.json")
+}
+
+fn data_file_path(name: &str) -> std::path::PathBuf {
     let project_dir = directories::ProjectDirs::from("", "espresso", "espresso-staking-cli");
     if let Some(project_dir) = project_dir {
-        project_dir.data_dir().join("tx_log.json")
+        project_dir.data_dir().join(name)
     } else {
         tracing::warn!("Unable to find data directory, using current directory");
-        std::path::PathBuf::from("tx_log.json")
+        std::path::PathBuf::from(name)
     }
 }
 
 const MAX_RETRIES: u32 = 10;
 pub const DEFAULT_CONCURRENCY: usize = 20;
-const DEFAULT_GAS_LIMIT: u64 = 1_000_000;
+pub(crate) const DEFAULT_GAS_LIMIT: u64 = 1_000_000;
 
 /// Geth's default txpool pending limit per account.
 /// Beyond this, transactions go to the "queued" pool where they cannot
@@ -65,6 +81,10 @@ pub enum TxPhase {
     Approve,
     Delegate,
     Undelegate,
+    /// A transaction signed offline and submitted with the `broadcast` command.
+    Broadcast,
+    /// A reward claim of the `compound-rewards` command.
+    ClaimRewards,
 }
 
 impl std::fmt::Display for TxPhase {
@@ -75,6 +95,8 @@ impl std::fmt::Display for TxPhase {
             TxPhase::Approve => write!(f, "approve"),
             TxPhase::Delegate => write!(f, "delegate"),
             TxPhase::Undelegate => write!(f, "undelegate"),
+            TxPhase::Broadcast => write!(f, "broadcast"),
+            TxPhase::ClaimRewards => write!(f, "claim_rewards"),
         }
     }
 }
@@ -2,10 +2,10 @@ use std::{collections::HashMap, time::Duration};
 
 use alloy::{
     primitives::{
-        Address, U256,
+        Address, Bytes, U256,
         utils::{format_ether, parse_ether},
     },
-    providers::Provider as _,
+    providers::{Provider as _, ext::AnvilApi as _},
     signers::local::coins_bip39::{English, Mnemonic},
 };
 use anyhow::Result;
@@ -1189,6 +1189,108 @@ async fn test_cli_transfer(#[case] version: StakeTableContractVersion) -> Result
     Ok(())
 }
 
+#[test_log::test(tokio::test)]
+async fn test_cli_offline_signing() -> Result<()> {
+    let system = TestSystem::deploy().await?;
+    let tmpdir = tempfile::tempdir()?;
+    let unsigned = tmpdir.path().join("unsigned.json");
+    let signed = tmpdir.path().join("signed.json");
+    let tx_log = tmpdir.path().join("broadcast_log.json");
+    let addr = "0x1111111111111111111111111111111111111111".parse::<Address>()?;
+    let amount = parse_ether("1.5")?;
+
+    base_cmd()
+        .arg("--rpc-url")
+        .arg(system.rpc_url.to_string())
+        .arg("--stake-table-address")
+        .arg(system.stake_table.to_string())
+        .arg("--export-unsigned")
+        .arg("--sender-address")
+        .arg(system.deployer_address.to_string())
+        .arg("--output")
+        .arg(&unsigned)
+        .arg("transfer")
+        .arg("--to")
+        .arg(addr.to_string())
+        .arg("--amount")
+        .arg(format_ether(amount))
+        .assert()
+        .success();
+
+    // Signing doesn't need an RPC or a stake table address.
+    base_cmd()
+        .arg("--mnemonic")
+        .arg(DEV_MNEMONIC)
+        .arg("--account-index")
+        .arg("0")
+        .arg("sign")
+        .arg("--input")
+        .arg(&unsigned)
+        .arg("--output")
+        .arg(&signed)
+        .assert()
+        .success();
+
+    // Nothing is sent before broadcasting.
+    assert_eq!(system.balance(addr).await?, U256::ZERO);
+
+    system
+        .cmd(Signer::Mnemonic)
+        .arg("broadcast")
+        .arg("--input")
+        .arg(&signed)
+        .arg("--tx-log")
+        .arg(&tx_log)
+        .assert()
+        .success()
+        .stdout(str::contains("Transfer"));
+    assert_eq!(system.balance(addr).await?, amount);
+
+    let log = staking_cli::TxLog::load(&tx_log)?.expect("tx log should be written");
+    assert_eq!(log.transactions.len(), 1);
+    assert_eq!(log.transactions[0].from, system.deployer_address);
+    assert_eq!(log.transactions[0].amount, amount);
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn test_cli_sign_wrong_signer() -> Result<()> {
+    let system = TestSystem::deploy().await?;
+    let tmpdir = tempfile::tempdir()?;
+    let unsigned = tmpdir.path().join("unsigned.json");
+
+    base_cmd()
+        .arg("--rpc-url")
+        .arg(system.rpc_url.to_string())
+        .arg("--stake-table-address")
+        .arg(system.stake_table.to_string())
+        .arg("--export-unsigned")
+        .arg("--sender-address")
+        .arg(system.deployer_address.to_string())
+        .arg("--output")
+        .arg(&unsigned)
+        .arg("approve")
+        .arg("--amount")
+        .arg("1")
+        .assert()
+        .success();
+
+    base_cmd()
+        .arg("--mnemonic")
+        .arg(DEV_MNEMONIC)
+        .arg("--account-index")
+        .arg("1")
+        .arg("sign")
+        .arg("--input")
+        .arg(&unsigned)
+        .assert()
+        .failure()
+        .stderr(str::contains("configured signer"));
+
+    Ok(())
+}
+
 #[test_log::test(rstest::rstest)]
 #[case::no_balance(None)]
 #[case::with_balance(Some(U256::from(1)))]
@@ -1264,6 +1366,98 @@ async fn test_cli_unclaimed_rewards(
     Ok(())
 }
 
+#[test_log::test(tokio::test(flavor = "multi_thread"))]
+async fn test_cli_compound_rewards() -> Result<()> {
+    let system = TestSystem::deploy().await?;
+    system.register_validator().await?;
+    let rewards = parse_ether("5")?;
+    let espresso_url = system.setup_reward_claim_mock(rewards).await?;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let tmpdir = tempfile::tempdir()?;
+    let log_path = tmpdir.path().join("compound_log.json");
+    let compound = || {
+        system
+            .cmd(Signer::Mnemonic)
+            .arg("--espresso-url")
+            .arg(espresso_url.to_string())
+            .arg("compound-rewards")
+            .arg("--validator")
+            .arg(format!("{}:1", system.deployer_address))
+            .arg("--once")
+            .arg("--tx-log")
+            .arg(&log_path)
+    };
+
+    let stake_table = StakeTableV3::new(system.stake_table, &system.provider);
+    let delegated = || async {
+        stake_table
+            .delegations(system.deployer_address, system.deployer_address)
+            .call()
+            .await
+    };
+
+    compound().assert().success();
+    assert_eq!(delegated().await?, rewards);
+    // The completed round is archived.
+    assert!(!log_path.exists());
+
+    // All rewards are claimed, the second round does nothing.
+    compound().assert().success();
+    assert_eq!(delegated().await?, rewards);
+
+    Ok(())
+}
+
+#[test_log::test(rstest_reuse::apply(stake_table_versions))]
+async fn test_cli_portfolio(#[case] version: StakeTableContractVersion) -> Result<()> {
+    let system = TestSystem::deploy_version(version).await?;
+    system.register_validator().await?;
+    system.delegate(parse_ether("3")?).await?;
+    system.undelegate(parse_ether("1")?).await?;
+
+    let validator = system.deployer_address.to_string();
+    system
+        .cmd(Signer::Mnemonic)
+        .arg("portfolio")
+        .assert()
+        .success()
+        .stdout(str::contains("Delegations (total 2 ESP):"))
+        .stdout(str::contains(format!(" - Validator {validator}: 2 ESP")))
+        .stdout(str::contains(format!(
+            " - Validator {validator}: 1 ESP (unlocks at"
+        )));
+
+    system.deregister_validator().await?;
+
+    let output = system
+        .cmd(Signer::Mnemonic)
+        .arg("portfolio")
+        .arg("--format")
+        .arg("json")
+        .output()?;
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(json["delegations"].as_array().unwrap().len(), 0);
+    assert_eq!(json["undelegations"][0]["amount"], "1000000000000000000");
+    assert_eq!(json["validator_exits"][0]["amount"], "2000000000000000000");
+    assert_eq!(json["validator_exits"][0]["claimable"], false);
+
+    system
+        .cmd(Signer::Mnemonic)
+        .arg("portfolio")
+        .arg("--format")
+        .arg("csv")
+        .assert()
+        .success()
+        .stdout(str::contains("kind,validator,amount,unlocks_at,claimable"))
+        .stdout(str::contains(format!(
+            "validator_exit,{validator},2000000000000000000,"
+        )));
+
+    Ok(())
+}
+
 #[test_log::test(rstest_reuse::apply(stake_table_versions))]
 async fn test_cli_stake_table_full(#[case] version: StakeTableContractVersion) -> Result<()> {
     let system = TestSystem::deploy_version(version).await?;
@@ -2267,6 +2461,99 @@ async fn test_cli_export_calldata_requires_sender_address_for_simulation() -> Re
     Ok(())
 }
 
+/// Runtime code that returns `n` as a uint256 for any call, enough to stand in for the
+/// `nonce()` and `getThreshold()` getters of a Safe.
+fn safe_stub(n: u8) -> Bytes {
+    Bytes::from(vec![
+        0x60, n, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3,
+    ])
+}
+
+#[test_log::test(tokio::test)]
+async fn test_cli_safe_address_requires_safe() -> Result<()> {
+    let system = TestSystem::deploy().await?;
+
+    base_cmd()
+        .arg("--rpc-url")
+        .arg(system.rpc_url.to_string())
+        .arg("--stake-table-address")
+        .arg(system.stake_table.to_string())
+        .arg("--safe-address")
+        .arg("0x1111111111111111111111111111111111111111")
+        .arg("approve")
+        .arg("--amount")
+        .arg("1")
+        .assert()
+        .failure()
+        .stderr(str::contains("does not look like a Safe"));
+
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn test_cli_safe_address() -> Result<()> {
+    let system = TestSystem::deploy().await?;
+    let safe = "0x2222222222222222222222222222222222222222".parse::<Address>()?;
+    system.provider.anvil_set_code(safe, safe_stub(3)).await?;
+
+    let safe_cmd = || {
+        let mut cmd = base_cmd();
+        cmd.arg("--rpc-url")
+            .arg(system.rpc_url.to_string())
+            .arg("--stake-table-address")
+            .arg(system.stake_table.to_string())
+            .arg("--safe-address")
+            .arg(safe.to_string());
+        cmd
+    };
+
+    safe_cmd()
+        .arg("approve")
+        .arg("--amount")
+        .arg("1")
+        .assert()
+        .success()
+        .stdout(str::contains("contractMethod"))
+        .stdout(str::contains(format!(
+            "\"createdFromSafeAddress\": \"{safe}\""
+        )));
+
+    let output = safe_cmd()
+        .arg("--format")
+        .arg("safe-proposal")
+        .arg("approve")
+        .arg("--amount")
+        .arg("1")
+        .output()?;
+    assert!(output.status.success());
+    let proposal: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(proposal["safe"], safe.to_string());
+    assert_eq!(proposal["to"], system.token.to_string());
+    assert_eq!(proposal["nonce"], 3);
+    assert_eq!(proposal["threshold"], 3);
+    assert_eq!(proposal["operation"], 0);
+    assert!(proposal["contractTransactionHash"].is_string());
+
+    let output = safe_cmd()
+        .arg("--safe-nonce")
+        .arg("7")
+        .arg("--format")
+        .arg("safe-proposal")
+        .arg("approve")
+        .arg("--amount")
+        .arg("1")
+        .output()?;
+    assert!(output.status.success());
+    let queued: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(queued["nonce"], 7);
+    assert_ne!(
+        queued["contractTransactionHash"],
+        proposal["contractTransactionHash"]
+    );
+
+    Ok(())
+}
+
 #[test_log::test(tokio::test)]
 async fn test_cli_skip_simulation_does_not_require_sender_address() -> Result<()> {
     let system = TestSystem::deploy().await?;
@@ -2291,6 +2578,21 @@ async fn test_cli_skip_simulation_does_not_require_sender_address() -> Result<()
     Ok(())
 }
 
+#[test]
+fn test_cli_skip_simulation_requires_export() {
+    base_cmd()
+        .arg("--skip-simulation")
+        .arg("delegate")
+        .arg("--validator-address")
+        .arg(Address::ZERO.to_string())
+        .arg("--amount")
+        .arg("1")
+        .assert()
+        .failure()
+        .stderr(str::contains("--export-calldata"))
+        .stderr(str::contains("--export-unsigned"));
+}
+
 #[test_log::test(tokio::test)]
 async fn test_cli_claim_rewards_requires_sender_address_even_with_skip_simulation() -> Result<()> {
     let system = TestSystem::deploy().await?;
@@ -84,6 +84,11 @@ impl NsAvidMShare {
     pub fn ns_len(&self, ns_index: usize) -> usize {
         self.ns_lens[ns_index]
     }
+
+    /// Return the weight of this share, i.e. the number of encoded elements it holds.
+    pub fn weight(&self) -> usize {
+        self.content.first().map_or(0, |share| share.range.len())
+    }
 }
 
 impl NsAvidMScheme {
@@ -277,6 +277,33 @@ impl NsAvidMScheme {
         })
     }
 
+    /// Generate a proof of inclusion for a namespace payload from a subset of shares, without
+    /// recovering the rest of the payload.
+    /// WARN: the shares are assumed to be verified against the same namespaced commitment, so
+    /// that they all carry the same list of namespace commitments.
+    pub fn namespace_proof_from_shares(
+        param: &AvidMParam,
+        ns_index: usize,
+        shares: &[NsAvidMShare],
+    ) -> VidResult<NsProof> {
+        let ns_payload = Self::ns_recover(param, ns_index, shares)?;
+        let ns_commits = shares[0]
+            .ns_commits
+            .iter()
+            .map(|commit| commit.commit)
+            .collect::<Vec<_>>();
+        let mt = MerkleTree::from_elems(None, &ns_commits)?;
+        Ok(NsProof {
+            ns_index,
+            ns_payload,
+            ns_proof: mt
+                .lookup(ns_index as u64)
+                .expect_ok()
+                .map_err(|_| VidError::IndexOutOfBound)?
+                .1,
+        })
+    }
+
     /// Verify a namespace proof against a namespaced VID commitment.
     pub fn verify_namespace_proof(
         param: &AvidMParam,
@@ -414,6 +441,39 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_ns_proof_from_shares() {
+        let mut rng = jf_utils::test_rng();
+        let param = AvidMScheme::setup(3usize, 9usize).unwrap();
+        let mut payload = [0u8; 100];
+        rng.fill(&mut payload[..]);
+        let distribution = [1u32; 9];
+        let ns_table = vec![(0..10), (10..21), (21..33), (33..48), (48..100)];
+        let (commit, mut shares) =
+            NsAvidMScheme::ns_disperse(&param, &distribution, &payload, ns_table.clone()).unwrap();
+
+        // Only a threshold of shares is available.
+        shares.shuffle(&mut rng);
+        let shares = &shares[..3];
+
+        for (i, ns_range) in ns_table.iter().enumerate() {
+            let proof = NsAvidMScheme::namespace_proof_from_shares(&param, i, shares).unwrap();
+            assert_eq!(proof.ns_payload, payload[ns_range.clone()]);
+            assert_eq!(
+                proof,
+                NsAvidMScheme::namespace_proof(&param, &payload, i, ns_table.clone()).unwrap()
+            );
+            assert!(
+                NsAvidMScheme::verify_namespace_proof(&param, &commit, &proof)
+                    .unwrap()
+                    .is_ok()
+            );
+        }
+
+        assert!(NsAvidMScheme::namespace_proof_from_shares(&param, 5, shares).is_err());
+        assert!(NsAvidMScheme::namespace_proof_from_shares(&param, 0, &[]).is_err());
+    }
+
     #[test]
     fn test_ns_proof_of_incorrect_encoding() {
         let mut rng = jf_utils::test_rng();
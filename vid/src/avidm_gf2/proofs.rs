@@ -7,7 +7,7 @@ use crate::{
     VerificationResult, VidError, VidResult, VidScheme,
     avidm_gf2::{
         AvidmGf2Scheme, MerkleProof, MerkleTree,
-        namespaced::{NsAvidmGf2Commit, NsAvidmGf2Common, NsAvidmGf2Scheme},
+        namespaced::{NsAvidmGf2Commit, NsAvidmGf2Common, NsAvidmGf2Scheme, NsAvidmGf2Share},
     },
 };
 
@@ -60,6 +60,27 @@ impl NsAvidmGf2Scheme {
         })
     }
 
+    /// Generate a proof of inclusion for a namespace payload from a subset of shares, without
+    /// recovering the rest of the payload.
+    /// WARN: the shares are assumed to be verified against `common`.
+    pub fn namespace_proof_from_shares(
+        common: &NsAvidmGf2Common,
+        ns_index: usize,
+        shares: &[NsAvidmGf2Share],
+    ) -> VidResult<NsProof> {
+        let ns_payload = Self::ns_recover(common, ns_index, shares)?;
+        let mt = MerkleTree::from_elems(None, common.ns_commits.iter().map(|c| c.commit))?;
+        Ok(NsProof {
+            ns_index,
+            ns_payload,
+            ns_proof: mt
+                .lookup(ns_index as u64)
+                .expect_ok()
+                .map_err(|_| VidError::IndexOutOfBound)?
+                .1,
+        })
+    }
+
     /// Verify a namespace proof against a namespaced VID commitment.
     pub fn verify_namespace_proof(
         commit: &NsAvidmGf2Commit,
@@ -78,6 +99,8 @@ impl NsAvidmGf2Scheme {
 
 #[cfg(test)]
 mod tests {
+    use rand::{Rng, seq::SliceRandom};
+
     use crate::avidm_gf2::{AvidmGf2Scheme, namespaced::NsAvidmGf2Scheme};
 
     #[test]
@@ -117,4 +140,38 @@ mod tests {
                 .is_err()
         );
     }
+
+    #[test]
+    fn test_ns_proof_from_shares() {
+        let mut rng = jf_utils::test_rng();
+        let param = AvidmGf2Scheme::setup(3usize, 9usize).unwrap();
+        let mut payload = [0u8; 100];
+        rng.fill(&mut payload[..]);
+        let distribution = [1u32; 9];
+        let ns_table = vec![(0..10), (10..21), (21..33), (33..48), (48..100)];
+        let (commit, common, mut shares) =
+            NsAvidmGf2Scheme::ns_disperse(&param, &distribution, &payload, ns_table.clone())
+                .unwrap();
+
+        // Only a threshold of shares is available.
+        shares.shuffle(&mut rng);
+        let shares = &shares[..3];
+
+        for (i, ns_range) in ns_table.iter().enumerate() {
+            let proof = NsAvidmGf2Scheme::namespace_proof_from_shares(&common, i, shares).unwrap();
+            assert_eq!(proof.ns_payload, payload[ns_range.clone()]);
+            assert_eq!(
+                proof,
+                NsAvidmGf2Scheme::namespace_proof(&common, &payload, i).unwrap()
+            );
+            assert!(
+                NsAvidmGf2Scheme::verify_namespace_proof(&commit, &common, &proof)
+                    .unwrap()
+                    .is_ok()
+            );
+        }
+
+        assert!(NsAvidmGf2Scheme::namespace_proof_from_shares(&common, 5, shares).is_err());
+        assert!(NsAvidmGf2Scheme::namespace_proof_from_shares(&common, 0, &[]).is_err());
+    }
 }